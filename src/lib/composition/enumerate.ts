@@ -0,0 +1,124 @@
+// Enumerate the stoichiometries of a chemical system for high-throughput screening,
+// e.g. as input to prototype decoration, optionally keeping only charge-balanced ones
+import type { CompositionType } from '$lib/composition'
+import type { ElementSymbol } from '$lib/element'
+import { element_by_symbol } from '$lib/element/data'
+import { is_elem_symbol } from '$lib/element/helpers'
+
+export interface EnumerateCompositionsOptions {
+  // oxidation states per element used for charge balancing (default: the element's
+  // ICSD oxidation states, elements without any can't be balanced)
+  oxidation_states?: Partial<Record<ElementSymbol, readonly number[]>>
+}
+
+const gcd = (num_1: number, num_2: number): number =>
+  num_2 ? gcd(num_2, num_1 % num_2) : num_1
+
+// Net charges reachable by `count` atoms of one element, each taking any of the states
+// (mixed valence allowed, e.g. Fe²⁺Fe³⁺₂ in Fe3O4)
+function reachable_charges(states: readonly number[], count: number): Set<number> {
+  let charges = new Set([0])
+  for (let atom = 0; atom < count; atom++) {
+    const next = new Set<number>()
+    for (const charge of charges) for (const state of states) next.add(charge + state)
+    charges = next
+  }
+  return charges
+}
+
+// Whether oxidation states can be assigned so that the composition is neutral
+export function is_charge_balanced(
+  composition: CompositionType,
+  options: EnumerateCompositionsOptions = {},
+): boolean {
+  let totals = new Set([0])
+  for (const [element, count] of Object.entries(composition) as [ElementSymbol, number][]) {
+    if (!Number.isInteger(count) || count < 0) {
+      throw new Error(`Charge balancing needs integer amounts, got ${element}${count}`)
+    }
+    const states =
+      options.oxidation_states?.[element] ??
+      element_by_symbol.get(element)?.icsd_oxidation_states ??
+      []
+    const charges = reachable_charges(states, count)
+    const next = new Set<number>()
+    for (const total of totals) for (const charge of charges) next.add(total + charge)
+    totals = next
+  }
+  return totals.has(0)
+}
+
+// One oxidation state per element making the composition neutral: the first such
+// combination in order of the elements' state lists, null if there is none
+export function guess_oxidation_states(
+  composition: CompositionType,
+  options: EnumerateCompositionsOptions = {},
+): Partial<Record<ElementSymbol, number>> | null {
+  const entries = Object.entries(composition) as [ElementSymbol, number][]
+  const state_lists = entries.map(
+    ([element]) =>
+      options.oxidation_states?.[element] ??
+      element_by_symbol.get(element)?.icsd_oxidation_states ??
+      [],
+  )
+  const search = (idx: number, charge: number, chosen: number[]): number[] | null => {
+    if (idx === entries.length) return Math.abs(charge) < 1e-8 ? chosen : null
+    for (const state of state_lists[idx]) {
+      const found = search(idx + 1, charge + state * entries[idx][1], [...chosen, state])
+      if (found) return found
+    }
+    return null
+  }
+  const states = search(0, 0, [])
+  if (!states) return null
+  return Object.fromEntries(entries.map(([element], idx) => [element, states[idx]]))
+}
+
+// All reduced stoichiometries containing every element at least once with at most
+// max_atoms atoms per formula unit, ordered by size then by amounts in element order.
+// Multiples of smaller formulas (Fe2O2) are skipped.
+export function enumerate_compositions(
+  elements: readonly ElementSymbol[],
+  max_atoms: number,
+  charge_balanced_only = false,
+  options: EnumerateCompositionsOptions = {},
+): CompositionType[] {
+  if (elements.length === 0) throw new Error(`Need at least one element`)
+  const invalid = elements.find((element) => !is_elem_symbol(element))
+  if (invalid !== undefined) throw new Error(`Invalid element symbol: ${invalid}`)
+  if (new Set(elements).size !== elements.length) {
+    throw new Error(`Duplicate elements in ${elements.join(`, `)}`)
+  }
+  if (!Number.isInteger(max_atoms) || max_atoms < 1) {
+    throw new Error(`max_atoms must be a positive integer, got ${max_atoms}`)
+  }
+
+  const amounts: number[][] = []
+  const fill = (prefix: number[], budget: number) => {
+    if (prefix.length === elements.length) {
+      if (prefix.reduce(gcd) === 1) amounts.push(prefix)
+      return
+    }
+    const n_left = elements.length - prefix.length - 1 // each needs at least one atom
+    for (let count = 1; count <= budget - n_left; count++) {
+      fill([...prefix, count], budget - count)
+    }
+  }
+  fill([], max_atoms)
+
+  const size = (counts: number[]) => counts.reduce((sum, count) => sum + count, 0)
+  amounts.sort((counts_1, counts_2) => {
+    const diff = size(counts_1) - size(counts_2)
+    if (diff !== 0) return diff
+    const idx = counts_1.findIndex((count, jdx) => count !== counts_2[jdx])
+    return idx === -1 ? 0 : counts_1[idx] - counts_2[idx]
+  })
+  const compositions = amounts.map(
+    (counts) =>
+      Object.fromEntries(
+        elements.map((element, idx) => [element, counts[idx]]),
+      ) as CompositionType,
+  )
+  if (!charge_balanced_only) return compositions
+  return compositions.filter((composition) => is_charge_balanced(composition, options))
+}
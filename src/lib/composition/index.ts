@@ -4,6 +4,7 @@ import { format_num } from '$lib/labels'
 export { default as BarChart } from './BarChart.svelte'
 export { default as BubbleChart } from './BubbleChart.svelte'
 export * from './chem-sys'
+export * from './enumerate'
 export { default as Composition } from './Composition.svelte'
 export * from './format'
 export { default as Formula } from './Formula.svelte'
@@ -107,6 +107,8 @@ export const VASP_FILES_REGEX =
 export const VASP_VOLUMETRIC_REGEX =
   /(?:^|[\\/_.-])(?:chgcar|aeccar[012]?|elfcar|locpot|parchg)(?:[\\/_.-]|$)/i
 export const XDATCAR_REGEX = /xdatcar/i
+// VASP outputs holding one frame per ionic step
+export const VASP_TRAJECTORY_OUTPUTS_REGEX = /(?:^|[\\/_.-])(?:outcar|vasprun)(?:[\\/_.-]|$)/i
 export const CONFIG_DIRS_REGEX =
   /(?:^|[\\/])(?:\.vscode|\.idea|\.nyc_output|\.cache|\.tmp|\.temp|node_modules|dist|build|coverage)(?:[\\/]|$)/i
 export const MD_SIM_EXCLUDE_REGEX = /md_simulation\.(?:out|txt|yml|py|csv|html|css|md|js|ts)$/i
@@ -114,3 +116,6 @@ export const XYZ_EXTXYZ_REGEX = /\.(?:xyz|extxyz)$/i
 
 // Compression extensions regex (shared across files)
 export const COMPRESSION_EXTENSIONS_REGEX = ext_regex(COMPRESSION_EXTENSIONS)
+
+export const K_B_EV = 8.617333262e-5 // Boltzmann constant in eV/K
+export const COULOMB_EV_A = 14.399645 // e² / (4πε₀) in eV·Å
@@ -2,6 +2,7 @@
 // Enables atmosphere-controlled phase diagram analysis
 
 import { count_atoms_in_composition } from '$lib/composition'
+import { K_B_EV } from '$lib/constants'
 import type { Vec2 } from '$lib/math'
 import type {
   GasAnalysis,
@@ -14,7 +15,7 @@ import { DEFAULT_GAS_PRESSURES, GAS_SPECIES } from '$lib/convex-hull/types'
 import type { ElementSymbol } from '$lib/element'
 
 // Physical constants
-export const R_EV_PER_K = 8.617333262e-5 // Gas constant in eV/K (k_B)
+export const R_EV_PER_K = K_B_EV // Gas constant in eV/K (k_B)
 export const P_REF = 1.0 // Reference pressure in bar
 
 // Default element-to-gas mapping (which element comes from which gas)
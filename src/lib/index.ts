@@ -21,12 +21,14 @@ export {
   auto_color_config,
   auto_isosurface_settings,
   auto_volume_layer,
+  band_alignment,
   compare_volume_grids,
   compute_scalar_range,
   create_volume_sampler,
   DEFAULT_ISO_COLORMAP,
   DEFAULT_ISOSURFACE_SETTINGS,
   extract_volume_range,
+  find_vacuum_region,
   generate_layers,
   grid_data_range,
   is_signed_range,
@@ -36,11 +38,13 @@ export {
   label_file_volumes,
   lattices_match,
   LAYER_COLORS,
+  macroscopic_average,
   materialize_layers,
   merge_imported_volumes,
   parse_chgcar,
   parse_cube,
   parse_volumetric_file,
+  planar_average,
   remove_volume,
   resolve_contour_thresholds,
   resolve_slice_color_range,
@@ -50,22 +54,31 @@ export {
   sample_volume_at_positions,
   sanitize_display_range,
   scalars_to_vertex_colors,
+  slab_alignment,
   slice_to_rgba,
   tile_volumetric_data,
   trilinear_interpolate,
+  vasp_volumetric_kind,
   VolumeSlice,
 } from './isosurface'
 export type {
+  BandAlignment,
   CartesianPlane,
   DataRange,
   DisplayRange,
+  GridAxis,
   GridCompatibility,
   IsoColormap,
   IsosurfaceLayer,
   IsosurfaceSettings,
   OutOfBoundsPolicy,
+  PlanarAverage,
   PlaneSliceOptions,
+  SlabAlignment,
+  SlabAlignmentInput,
   SliceResult,
+  VacuumRegion,
+  VaspVolumetricKind,
   VolumeSliceMode,
   VolumeMergeResult,
   VolumeDisplayRangeOptions,
@@ -77,10 +90,12 @@ export * from './labels'
 export * from './layout'
 export * from './math'
 export * from './api/optimade'
+export * from './order-params'
 export * from './overlays'
 export * from './periodic-table'
 export * from './phase-diagram'
 export * from './plot'
+export * from './potentials'
 export * from './rdf'
 export * from './sanitize'
 export * from './scene'
@@ -92,6 +107,7 @@ export * from './table'
 export * from './theme'
 export * from './time'
 export * from './trajectory'
+export * from './units'
 export * from './url-params'
 export * from './utils'
 export * from './xrd'
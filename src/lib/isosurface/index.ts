@@ -6,6 +6,7 @@ export type { ScalarGrid3D, ScalarGridArray, ScalarGridLike, ScalarGridOrder } f
 
 export * from './coloring'
 export * from './parse'
+export * from './planar-average'
 export * from './sampling'
 export * from './slice'
 export * from './slice-rendering'
@@ -14,7 +14,7 @@ import { parse_leading_num } from '$lib/utils'
 import type { DataRange, VolumetricData, VolumetricFileData } from './types'
 
 // Bohr radius in Angstroms (for Gaussian .cube unit conversion)
-const BOHR_TO_ANGSTROM = 0.529177249
+export const BOHR_TO_ANGSTROM = 0.529177249
 
 // === Parse error contract ===
 // parse_chgcar/parse_cube return null and record reasons here (mirrored to console.error).
@@ -206,10 +206,34 @@ function build_grid({
 
 // === CHGCAR Parser ===
 
+// VASP volumetric file flavors: densities (CHGCAR, AECCAR, PARCHG) store ρ·V_cell,
+// LOCPOT potentials (eV) and ELFCAR values are stored as is
+export type VaspVolumetricKind = `chgcar` | `locpot` | `elfcar`
+
+// Volumetric file kind from a VASP filename (default chgcar)
+export const vasp_volumetric_kind = (filename: string): VaspVolumetricKind =>
+  /locpot/i.test(filename) ? `locpot` : /elfcar/i.test(filename) ? `elfcar` : `chgcar`
+
+// Labels of the data blocks: total + magnetization (spin-polarized) or total + m_x, m_y,
+// m_z (noncollinear) for densities, numbered blocks otherwise
+function vasp_volume_label(kind: VaspVolumetricKind, idx: number, n_blocks: number): string {
+  if (kind === `chgcar`) {
+    if (idx === 0) return `charge density`
+    return n_blocks === 4 ? `magnetization density ${`xyz`[idx - 1]}` : `magnetization density`
+  }
+  const label = kind === `locpot` ? `local potential` : `ELF`
+  return idx === 0 ? label : `${label} (block ${idx + 1})`
+}
+
 // Parse VASP CHGCAR/AECCAR/ELFCAR/LOCPOT/PARCHG file format.
 // CHGCAR/PARCHG consists of a POSCAR header followed by volumetric data on a 3D grid.
-// Spin-polarized files contain two data blocks (total charge + magnetization).
-export function parse_chgcar(content: string): VolumetricFileData | null {
+// Spin-polarized files contain two data blocks (total charge + magnetization),
+// noncollinear ones four. PAW augmentation occupancies after each block are returned
+// per atom in `augmentation`.
+export function parse_chgcar(
+  content: string,
+  kind: VaspVolumetricKind = `chgcar`,
+): VolumetricFileData | null {
   // Strip leading whitespace
   let pos = 0
   while (pos < content.length && content.charCodeAt(pos) <= 32) pos++
@@ -343,9 +367,9 @@ export function parse_chgcar(content: string): VolumetricFileData | null {
 
   // Parse volumetric data blocks
   const volumes: VolumetricData[] = []
-  const volume_labels = [`charge density`, `magnetization density`]
+  const augmentation: number[][][] = []
 
-  for (let vol_idx = 0; vol_idx < 2; vol_idx++) {
+  for (let vol_idx = 0; vol_idx < 4; vol_idx++) {
     // Skip blank lines
     while (pos < content.length) {
       cur = read_line(content, pos)
@@ -358,7 +382,8 @@ export function parse_chgcar(content: string): VolumetricFileData | null {
     // Parse grid dimensions: NGX NGY NGZ
     cur = read_line(content, pos)
     const grid_tokens = cur.line.trim().split(/\s+/).map(Number)
-    if (grid_tokens.length < 3 || grid_tokens.some(isNaN)) break
+    const is_grid_dim = (num: number) => Number.isInteger(num) && num > 0
+    if (grid_tokens.length < 3 || !grid_tokens.every(is_grid_dim)) break
 
     const [ngx, ngy, ngz] = grid_tokens
     pos = cur.next
@@ -384,7 +409,7 @@ export function parse_chgcar(content: string): VolumetricFileData | null {
     // CHGCAR stores rho * V_cell, so normalize by dividing by cell volume.
     // Use Math.abs to guard against negative determinant (left-handed lattice).
     const cell_volume = Math.abs(lattice_params.volume)
-    const divisor = cell_volume > 1e-30 ? cell_volume : 1
+    const divisor = kind === `chgcar` && cell_volume > 1e-30 ? cell_volume : 1
     const { grid, data_range } = build_grid({
       data: data.subarray(0, parsed_count),
       nx: ngx,
@@ -402,24 +427,38 @@ export function parse_chgcar(content: string): VolumetricFileData | null {
       data_range,
       data_order: `x_fastest`,
       periodic: true, // VASP grids span [0,1) with N points, wrapping at boundaries
-      label: volume_labels[vol_idx],
     })
 
-    // Skip augmentation occupancies and any remaining non-numeric lines
+    // Collect augmentation occupancies (`augmentation occupancies <ion> <count>` headers
+    // followed by the values) and skip any other lines up to the next block, starting
+    // after the rest of the last data line
+    const occupancies: number[][] = []
+    pos = read_line(content, pos).next
     while (pos < content.length) {
       cur = read_line(content, pos)
       const trimmed = cur.line.trim()
       if (trimmed === `` || /^\d+\s+\d+\s+\d+$/.test(trimmed)) break
       pos = cur.next
+      const header = /^augmentation occupancies\s+(\d+)\s+(\d+)/i.exec(trimmed)
+      if (!header) continue
+      const values = new Float64Array(Number(header[2]))
+      const { count, end_pos } = parse_float_block(content, pos, values.length, values)
+      occupancies[Number(header[1]) - 1] = Array.from(values.subarray(0, count))
+      pos = read_line(content, end_pos).next
     }
+    augmentation.push(sites.map((_, atom_idx) => occupancies[atom_idx] ?? []))
   }
 
   if (volumes.length === 0) {
     vol_error(`No volumetric data found in CHGCAR`)
     return null
   }
+  volumes.forEach((volume, idx) => {
+    volume.label = vasp_volume_label(kind, idx, volumes.length)
+  })
 
-  return { structure, volumes }
+  const has_augmentation = augmentation.some((per_atom) => per_atom.some((occ) => occ.length))
+  return { structure, volumes, ...(has_augmentation && { augmentation }) }
 }
 
 // === Gaussian .cube Parser ===
@@ -615,7 +654,8 @@ export function parse_volumetric_file(
 
   // VASP volumetric file detection by filename
   if (VASP_VOLUMETRIC_REGEX.test(lower_name)) {
-    return parse_chgcar(content) ?? fail(`VASP volumetric (CHGCAR-like)`)
+    const kind = vasp_volumetric_kind(lower_name)
+    return parse_chgcar(content, kind) ?? fail(`VASP volumetric (CHGCAR-like)`)
   }
 
   // Content-based detection (only parse first few lines, not the whole file)
@@ -0,0 +1,186 @@
+// Planar and macroscopic averages of volumetric potentials (e.g. VASP LOCPOT) and band
+// alignment of slabs against the vacuum level: ionization potentials, electron affinities
+// and band offsets between two materials
+import * as math from '$lib/math'
+import type { VolumetricData } from './types'
+
+export type GridAxis = 0 | 1 | 2
+
+export interface PlanarAverage {
+  positions: number[] // Å along the plane normal
+  values: number[] // potential averaged over each lattice plane
+  period: number // Å, interplanar extent of the cell along the normal
+}
+
+// Average the grid over the planes spanned by the two other lattice vectors. Positions
+// are heights along the plane normal, so they also hold for non-orthogonal cells.
+export function planar_average(volume: VolumetricData, axis: GridAxis = 2): PlanarAverage {
+  const { grid, grid_dims, lattice, periodic } = volume
+  const [other_1, other_2] = [0, 1, 2].filter((dim) => dim !== axis)
+  const area = Math.hypot(...math.cross_3d(lattice[other_1], lattice[other_2]))
+  const period = Math.abs(math.det_3x3(lattice)) / area
+  const n_pts = grid_dims[axis]
+  const n_plane = grid_dims[other_1] * grid_dims[other_2]
+  const values = Array<number>(n_pts).fill(0)
+  grid.forEach((plane_x, ix) =>
+    plane_x.forEach((row, iy) =>
+      row.forEach((value, iz) => {
+        values[[ix, iy, iz][axis]] += value / n_plane
+      }),
+    ),
+  )
+  const spacing = period / (periodic ? n_pts : n_pts - 1)
+  return { positions: values.map((_, idx) => idx * spacing), values, period }
+}
+
+// Running average over window Å with periodic wrap-around. Averaging over the bulk
+// interplanar period removes the atomic oscillations of the planar average; two
+// windows (applied in turn) handle interfaces between materials with different periods.
+export function macroscopic_average(
+  { positions, values }: PlanarAverage,
+  window: number | [number, number],
+): number[] {
+  const n_pts = values.length
+  const spacing = n_pts > 1 ? positions[1] - positions[0] : 0
+  const windows = typeof window === `number` ? [window] : window
+  let averaged = values
+  for (const width of windows) {
+    const n_win = Math.max(1, Math.round(width / spacing))
+    if (!(width > 0) || n_win > n_pts) {
+      throw new Error(`Averaging window must be > 0 and ≤ the cell length, got ${width} Å`)
+    }
+    const prev = averaged
+    const start = -Math.floor(n_win / 2)
+    averaged = prev.map((_, idx) => {
+      let sum = 0
+      for (let offset = start; offset < start + n_win; offset++) {
+        sum += prev[(((idx + offset) % n_pts) + n_pts) % n_pts]
+      }
+      return sum / n_win
+    })
+  }
+  return averaged
+}
+
+export interface VacuumRegion {
+  level: number // mean planar-averaged potential in the plateau, eV
+  start: number // grid index where the plateau starts (it may wrap around the cell end)
+  length: number // number of grid points in the plateau
+  center: number // Å, middle of the plateau
+}
+
+// Longest periodic run of points within tolerance (eV) of the potential maximum, i.e.
+// the flat vacuum plateau of a slab. Throws if it is shorter than min_width Å.
+export function find_vacuum_region(
+  { positions, values, period }: PlanarAverage,
+  tolerance = 0.05,
+  min_width = 2,
+): VacuumRegion {
+  const n_pts = values.length
+  const max_val = Math.max(...values)
+  const flat = values.map((value) => value >= max_val - tolerance)
+  // walk from a non-flat point so runs crossing the cell boundary stay contiguous
+  let [best_start, best_len] = [0, n_pts]
+  const offset = flat.indexOf(false)
+  if (offset >= 0) {
+    best_len = 0
+    let [run_start, run_len] = [0, 0]
+    for (let step = 1; step <= n_pts; step++) {
+      const idx = (offset + step) % n_pts
+      if (!flat[idx]) run_len = 0
+      else {
+        if (run_len === 0) run_start = idx
+        run_len++
+        if (run_len > best_len) [best_start, best_len] = [run_start, run_len]
+      }
+    }
+  }
+  const spacing = n_pts > 1 ? positions[1] - positions[0] : period
+  if (best_len * spacing < min_width) {
+    throw new Error(
+      `No flat vacuum region: widest plateau within ${tolerance} eV of the maximum ` +
+        `spans ${(best_len * spacing).toFixed(2)} Å < ${min_width} Å`,
+    )
+  }
+  const run = Array.from({ length: best_len }, (_, step) => (best_start + step) % n_pts)
+  const level = run.reduce((sum, idx) => sum + values[idx], 0) / best_len
+  const center = ((best_start + (best_len - 1) / 2) * spacing) % period
+  return { level, start: best_start, length: best_len, center }
+}
+
+export interface SlabAlignmentInput {
+  potential: VolumetricData // slab + vacuum electrostatic potential, eV
+  bulk_vbm: number // eV, VBM of the bulk calculation
+  // macroscopic average of the bulk potential (a bulk volume's cell average) in eV
+  bulk_potential: number | VolumetricData
+  window: number | [number, number] // Å, bulk interplanar period(s) for macroscopic averaging
+  band_gap?: number // eV, bulk gap for the CBM and electron affinity
+  axis?: GridAxis // surface normal grid axis (default 2)
+  vacuum_tolerance?: number // eV, plateau flatness (default 0.05)
+  // Å along the normal where the slab is bulk-like (default: opposite the vacuum center)
+  slab_center?: number
+}
+
+export interface SlabAlignment {
+  planar: PlanarAverage
+  macroscopic: number[]
+  vacuum_level: number // eV
+  slab_potential: number // macroscopic average in the bulk-like slab center, eV
+  vbm: number // eV relative to the vacuum level
+  cbm: number | null // eV relative to the vacuum level
+  ionization_potential: number // eV
+  electron_affinity: number | null // eV
+}
+
+const grid_mean = ({ grid }: VolumetricData): number => {
+  const values = grid.flat(2)
+  return values.reduce((sum, value) => sum + value, 0) / values.length
+}
+
+// Band edges of a slab relative to its vacuum level via the bulk reference:
+// E_VBM = E_VBM(bulk) − V̄(bulk) + V̄(slab center) − E_vac
+export function slab_alignment(input: SlabAlignmentInput): SlabAlignment {
+  const { potential, bulk_vbm, band_gap, axis = 2, vacuum_tolerance = 0.05 } = input
+  const planar = planar_average(potential, axis)
+  const macroscopic = macroscopic_average(planar, input.window)
+  const vacuum = find_vacuum_region(planar, vacuum_tolerance)
+  const center = input.slab_center ?? (vacuum.center + planar.period / 2) % planar.period
+  const spacing = planar.positions[1] - planar.positions[0]
+  const n_pts = planar.values.length
+  const center_idx = Math.round(center / spacing) % n_pts
+  const slab_potential = macroscopic[center_idx]
+  const bulk_potential =
+    typeof input.bulk_potential === `number`
+      ? input.bulk_potential
+      : grid_mean(input.bulk_potential)
+  const vbm = bulk_vbm - bulk_potential + slab_potential - vacuum.level
+  const cbm = band_gap === undefined ? null : vbm + band_gap
+  return {
+    planar,
+    macroscopic,
+    vacuum_level: vacuum.level,
+    slab_potential,
+    vbm,
+    cbm,
+    ionization_potential: -vbm,
+    electron_affinity: cbm === null ? null : -cbm,
+  }
+}
+
+export interface BandAlignment {
+  slabs: [SlabAlignment, SlabAlignment]
+  valence_band_offset: number // E_VBM(2) − E_VBM(1), eV
+  conduction_band_offset: number | null // E_CBM(2) − E_CBM(1), eV
+}
+
+// Natural band offsets between two materials from their vacuum-aligned slab band edges
+export function band_alignment(
+  slab_1: SlabAlignmentInput,
+  slab_2: SlabAlignmentInput,
+): BandAlignment {
+  const [first, second] = [slab_alignment(slab_1), slab_alignment(slab_2)]
+  const conduction_band_offset =
+    first.cbm === null || second.cbm === null ? null : second.cbm - first.cbm
+  const valence_band_offset = second.vbm - first.vbm
+  return { slabs: [first, second], valence_band_offset, conduction_band_offset }
+}
@@ -38,6 +38,8 @@ export interface VolumetricData {
 export interface VolumetricFileData {
   structure: ParsedStructure
   volumes: VolumetricData[] // one or more volumes (e.g. total + magnetization for spin-polarized)
+  // PAW augmentation occupancies from VASP files, [volume][atom] values (empty if absent)
+  augmentation?: number[][][]
 }
 
 // A single isosurface layer at a specific isovalue with its own appearance.
@@ -584,6 +584,68 @@ export const lerp_vec3 = (start: Vec3, end: Vec3, t: number): Vec3 => [
   start[2] + t * (end[2] - start[2]),
 ]
 
+// Abramowitz & Stegun 7.1.26, absolute error < 1.5e-7
+export function erf(x_val: number): number {
+  const abs_x = Math.abs(x_val)
+  const t_val = 1 / (1 + 0.3275911 * abs_x)
+  const poly =
+    ((((1.061405429 * t_val - 1.453152027) * t_val + 1.421413741) * t_val - 0.284496736) *
+      t_val +
+      0.254829592) *
+    t_val
+  return Math.sign(x_val) * (1 - poly * Math.exp(-abs_x * abs_x))
+}
+
+// Complementary error function with fractional error < 1.2e-7 everywhere (Numerical
+// Recipes erfcc), unlike 1 − erf(x) which loses all digits in the tail
+export function erfc(x_val: number): number {
+  const abs_x = Math.abs(x_val)
+  const t_val = 1 / (1 + 0.5 * abs_x)
+  const coeffs = [
+    -1.26551223, 1.00002368, 0.37409196, 0.09678418, -0.18628806, 0.27886807,
+    -1.13520398, 1.48851587, -0.82215223, 0.17087277,
+  ]
+  const poly = coeffs.reduceRight((acc, coeff) => acc * t_val + coeff, 0)
+  const tail = t_val * Math.exp(-abs_x * abs_x + poly)
+  return x_val >= 0 ? tail : 2 - tail
+}
+
+// Counter-based form of mulberry32: the float in [0, 1) drawn at position counter of the
+// stream for seed, computed directly without stepping through earlier draws. Values only
+// depend on (seed, counter), so they stay identical across platforms, batch orders and
+// workers that each take their own counter range.
+export function random_at(seed: number, counter: number): number {
+  let tmp = ((seed >>> 0) + Math.imul(counter + 1, 0x6d2b79f5)) >>> 0
+  tmp = Math.imul(tmp ^ (tmp >>> 15), tmp | 1)
+  tmp ^= tmp + Math.imul(tmp ^ (tmp >>> 7), tmp | 61)
+  return ((tmp ^ (tmp >>> 14)) >>> 0) / 4294967296
+}
+
+// mulberry32: small, fast 32-bit PRNG returning floats in [0, 1), i.e. random_at for
+// counters 0, 1, 2, ...
+export function mulberry32(seed: number): () => number {
+  let counter = 0
+  return () => random_at(seed, counter++)
+}
+
+// Seed of an independent substream (e.g. per site, per replica or per worker) hashed
+// from a base seed and integer keys with the murmur3 finalizer
+export function derive_seed(seed: number, ...keys: number[]): number {
+  let hash = seed >>> 0
+  for (const key of keys) {
+    hash = (hash ^ Math.imul((key >>> 0) + 1, 0x9e3779b9)) >>> 0
+    hash = Math.imul(hash ^ (hash >>> 16), 0x85ebca6b)
+    hash = Math.imul(hash ^ (hash >>> 13), 0xc2b2ae35)
+    hash = (hash ^ (hash >>> 16)) >>> 0
+  }
+  return hash
+}
+
+// Standard normal deviate by the Box-Muller transform of two uniform draws
+export function random_normal(random: () => number): number {
+  return Math.sqrt(-2 * Math.log(1 - random())) * Math.cos(2 * Math.PI * random())
+}
+
 // Centered fractional part: offset from nearest integer, returns value in [-0.5, 0.5)
 // Useful for wrapping coordinates to first Brillouin zone or similar periodic domains
 export const centered_frac = (val: number): number => {
@@ -0,0 +1,122 @@
+// Adaptive common neighbor analysis (a-CNA, Stukowski, Modelling Simul. Mater. Sci. Eng.
+// 20, 045021 (2012)): each atom is labelled fcc, hcp, bcc, icosahedral or other from the
+// bonding topology among its 12 (or 14 for bcc) nearest neighbors. Bonds use a per-atom
+// cutoff scaled from the neighbor distances, so no parameters need tuning to the lattice
+// constant or temperature.
+import type { Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import type { Neighbor } from '$lib/structure/neighbors'
+import { get_knn_neighbor_list } from '$lib/structure/neighbors'
+import type { TrajectoryType } from '$lib/trajectory'
+
+export const CNA_STRUCTURE_TYPES = [`other`, `fcc`, `hcp`, `bcc`, `ico`] as const
+export type CnaStructureType = (typeof CNA_STRUCTURE_TYPES)[number]
+
+export type CnaFrameOptions = {
+  start_frame?: number // default 0
+  stride?: number // use every stride-th frame (default 1)
+}
+
+const CNA_SCALE = (1 + Math.SQRT2) / 2
+
+// CNA signatures `common neighbors,bonds among them,longest bond chain` of the bonds
+// from an atom to each of its neighbors
+function cna_signatures(vectors: Vec3[], cutoff: number): string[] {
+  const n_vecs = vectors.length
+  const cutoff_sq = cutoff * cutoff
+  const bonded = vectors.map((vec_1) =>
+    vectors.map((vec_2) => {
+      const dist_sq =
+        (vec_1[0] - vec_2[0]) ** 2 + (vec_1[1] - vec_2[1]) ** 2 + (vec_1[2] - vec_2[2]) ** 2
+      return dist_sq > 0 && dist_sq < cutoff_sq
+    }),
+  )
+  return vectors.map((_, nb_idx) => {
+    const common = [...Array(n_vecs).keys()].filter((idx) => bonded[nb_idx][idx])
+    const bonds: [number, number][] = []
+    for (let idx = 0; idx < common.length; idx++) {
+      for (let jdx = idx + 1; jdx < common.length; jdx++) {
+        if (bonded[common[idx]][common[jdx]]) bonds.push([common[idx], common[jdx]])
+      }
+    }
+    // longest chain: most bonds in one cluster of bonds connected via shared atoms
+    const cluster_of = new Map<number, number>()
+    const cluster_sizes: number[] = []
+    for (const [atom_1, atom_2] of bonds) {
+      const [cluster_1, cluster_2] = [cluster_of.get(atom_1), cluster_of.get(atom_2)]
+      if (cluster_1 === undefined && cluster_2 === undefined) {
+        cluster_of.set(atom_1, cluster_sizes.length).set(atom_2, cluster_sizes.length)
+        cluster_sizes.push(1)
+      } else if (cluster_1 === undefined || cluster_2 === undefined) {
+        const cluster = (cluster_1 ?? cluster_2) as number
+        cluster_of.set(atom_1, cluster).set(atom_2, cluster)
+        cluster_sizes[cluster]++
+      } else {
+        if (cluster_1 !== cluster_2) {
+          for (const [atom, cluster] of cluster_of) {
+            if (cluster === cluster_2) cluster_of.set(atom, cluster_1)
+          }
+          cluster_sizes[cluster_1] += cluster_sizes[cluster_2]
+          cluster_sizes[cluster_2] = 0
+        }
+        cluster_sizes[cluster_1]++
+      }
+    }
+    return `${common.length},${bonds.length},${Math.max(0, ...cluster_sizes)}`
+  })
+}
+
+const count = (signatures: string[], signature: string): number =>
+  signatures.filter((sig) => sig === signature).length
+
+const mean_distance = (neighbors: Neighbor[]): number =>
+  neighbors.reduce((sum, { distance }) => sum + distance, 0) / neighbors.length
+
+// Index into CNA_STRUCTURE_TYPES of every atom from its (at least 14) nearest neighbors
+function classify_from_knn(knn: Neighbor[][]): Uint8Array {
+  return Uint8Array.from(knn, (neighbors) => {
+    if (neighbors.length < 12) return 0
+    const shell_12 = neighbors.slice(0, 12)
+    const signatures_12 = cna_signatures(
+      shell_12.map(({ displacement }) => displacement),
+      CNA_SCALE * mean_distance(shell_12),
+    )
+    const n_421 = count(signatures_12, `4,2,1`)
+    if (n_421 === 12) return 1
+    if (n_421 === 6 && count(signatures_12, `4,2,2`) === 6) return 2
+    if (count(signatures_12, `5,5,5`) === 12) return 4
+    if (neighbors.length < 14) return 0
+    // bcc: 8 first and 6 second shell neighbors, the first scaled to the second
+    const local_cutoff =
+      (CNA_SCALE *
+        ((2 / Math.sqrt(3)) * mean_distance(neighbors.slice(0, 8)) +
+          mean_distance(neighbors.slice(8, 14)))) /
+      2
+    const signatures_14 = cna_signatures(
+      neighbors.slice(0, 14).map(({ displacement }) => displacement),
+      local_cutoff,
+    )
+    const is_bcc = count(signatures_14, `6,6,6`) === 8 && count(signatures_14, `4,4,4`) === 6
+    return is_bcc ? 3 : 0
+  })
+}
+
+// Index into CNA_STRUCTURE_TYPES of every atom (0 = other)
+export function classify_all_atoms(structure: AnyStructure): Uint8Array {
+  return classify_from_knn(get_knn_neighbor_list(structure, 14))
+}
+
+// classify_all_atoms of every selected trajectory frame
+export function classify_all_atoms_frames(
+  trajectory: TrajectoryType,
+  options: CnaFrameOptions = {},
+): Uint8Array[] {
+  const { start_frame = 0, stride = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  return trajectory.frames
+    .slice(start_frame)
+    .filter((_, idx) => idx % stride === 0)
+    .map(({ structure }) => classify_all_atoms(structure))
+}
@@ -0,0 +1,301 @@
+// Per-atom lattice orientations by template matching against ideal neighbor bonds and
+// grain segmentation by clustering neighboring atoms with small misorientation
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, Crystal } from '$lib/structure'
+import type { Neighbor } from '$lib/structure/neighbors'
+import { get_neighbor_list } from '$lib/structure/neighbors'
+import { frame_from } from '$lib/symmetry/continuous-measures'
+import { reference_neighbor_vectors } from './nye-tensor'
+
+export type OrientationOptions = {
+  cutoff: number // neighbor cutoff in Å, should sit between the 1st and 2nd shells
+  reference: Crystal | Vec3[] // perfect crystal or its ideal neighbor vectors
+  // Max RMS angle (degrees) between bonds and the best-matching rotated ideal bonds for an
+  // atom to count as crystalline (default 15)
+  rmsd_tol?: number
+}
+
+export type GrainSegmentationOptions = OrientationOptions & {
+  // Max misorientation (degrees) between bonded atoms of the same grain (default 5)
+  threshold?: number
+  min_grain_size?: number // smaller clusters are left unassigned (default 1)
+}
+
+export type Grain = {
+  size: number // number of atoms
+  orientation: Matrix3x3 // mean lattice rotation of the grain's atoms
+}
+
+export type GrainBoundary = {
+  grains: [number, number] // grain ids, ascending
+  misorientation: number // disorientation angle between mean grain orientations in degrees
+  n_atoms: number // atoms whose neighborhood touches both grains
+}
+
+export type GrainSegmentation = {
+  grain_ids: number[] // per-atom grain id, -1 for unassigned (non-crystalline/small grains)
+  grains: Grain[] // indexed by grain id, sorted by size descending
+  boundaries: GrainBoundary[]
+  orientations: (Matrix3x3 | null)[] // per-atom lattice rotations
+}
+
+const IDENTITY: Matrix3x3 = [
+  [1, 0, 0],
+  [0, 1, 0],
+  [0, 0, 1],
+]
+
+const ideal_vectors = (reference: Crystal | Vec3[], cutoff: number): Vec3[] =>
+  Array.isArray(reference) ? reference : reference_neighbor_vectors(reference, cutoff)
+
+// Rotation R (acting on column vectors) taking the frame of (from1, from2) onto (to1, to2)
+const pair_rotation = (from1: Vec3, from2: Vec3, to1: Vec3, to2: Vec3): Matrix3x3 =>
+  math.dot(frame_from(to1, to2), math.transpose_3x3_matrix(frame_from(from1, from2)))
+
+// Rotation angle of a rotation matrix in degrees
+export function rotation_angle(rot: Matrix3x3): number {
+  const cos_angle = (rot[0][0] + rot[1][1] + rot[2][2] - 1) / 2
+  return math.to_degrees(Math.acos(Math.max(-1, Math.min(1, cos_angle))))
+}
+
+const max_abs_diff = (mat1: Matrix3x3, mat2: Matrix3x3): number =>
+  Math.max(
+    ...mat1.flatMap((row, idx) => row.map((val, jdx) => Math.abs(val - mat2[idx][jdx]))),
+  )
+
+// Rotation part R of the polar decomposition M = R U (Higham iteration), null if det ≤ 0
+export function polar_rotation(matrix: Matrix3x3, max_iter = 50): Matrix3x3 | null {
+  if (!(math.det_3x3(matrix) > 0)) return null
+  let rot = matrix
+  for (let iter = 0; iter < max_iter; iter++) {
+    const inv_t = math.transpose_3x3_matrix(math.matrix_inverse_3x3(rot))
+    const next = rot.map((row, idx) =>
+      row.map((val, jdx) => (val + inv_t[idx][jdx]) / 2),
+    ) as Matrix3x3
+    const change = max_abs_diff(next, rot)
+    rot = next
+    if (change < 1e-14) break
+  }
+  return rot
+}
+
+// Proper rotations mapping a set of ideal neighbor vectors onto itself (e.g. the 24
+// rotations of the cubic point group for fcc/bcc shells, 6 for hcp)
+export function lattice_rotations(ideal: Vec3[], tol = 1e-6): Matrix3x3[] {
+  const scale = Math.max(...ideal.map((vec) => Math.hypot(...vec)))
+  const [first] = ideal
+  const second = ideal.find((vec) => {
+    const cross = math.cross_3d(math.normalize_vec(first), math.normalize_vec(vec))
+    return Math.hypot(...cross) > 0.1
+  })
+  if (!second) throw new Error(`Ideal neighbor vectors must not all be collinear`)
+  const [len1, len2] = [Math.hypot(...first), Math.hypot(...second)]
+  const dot12 = math.dot(first, second)
+  const maps_onto_set = (rot: Matrix3x3) =>
+    ideal.every((vec) => {
+      const rotated = math.mat3x3_vec3_multiply(rot, vec)
+      return ideal.some((other) => Math.hypot(...math.subtract(rotated, other)) < tol * scale)
+    })
+
+  const rotations: Matrix3x3[] = []
+  for (const to1 of ideal) {
+    if (Math.abs(Math.hypot(...to1) - len1) > tol * scale) continue
+    for (const to2 of ideal) {
+      if (Math.abs(Math.hypot(...to2) - len2) > tol * scale) continue
+      if (Math.abs(math.dot(to1, to2) - dot12) > tol * scale * scale) continue
+      const rot = pair_rotation(first, second, to1, to2)
+      if (!maps_onto_set(rot)) continue
+      if (rotations.every((other) => max_abs_diff(other, rot) > 1e-6)) rotations.push(rot)
+    }
+  }
+  return rotations
+}
+
+// Smallest rotation angle (degrees) between two orientations over the lattice symmetry
+// rotations, i.e. the disorientation. Also returns the symmetry-equivalent of orientation
+// rot2 closest to rot1.
+export function misorientation(
+  rot1: Matrix3x3,
+  rot2: Matrix3x3,
+  symmetry: Matrix3x3[] = [IDENTITY],
+): { angle: number; closest: Matrix3x3 } {
+  const rot1_t = math.transpose_3x3_matrix(rot1)
+  let best = { angle: Infinity, closest: rot2 }
+  for (const sym of symmetry) {
+    const candidate = math.dot(rot2, sym)
+    const angle = rotation_angle(math.dot(rot1_t, candidate))
+    if (angle < best.angle) best = { angle, closest: candidate }
+  }
+  return best
+}
+
+type OrientationFit = { orientations: (Matrix3x3 | null)[]; neighbor_list: Neighbor[][] }
+
+function fit_orientations(
+  structure: AnyStructure,
+  options: OrientationOptions,
+): OrientationFit {
+  const { cutoff, rmsd_tol = 15 } = options
+  const ideal = ideal_vectors(options.reference, cutoff)
+  if (ideal.length < 3) {
+    throw new Error(`Need at least 3 ideal neighbor vectors, got ${ideal.length}`)
+  }
+  const ideal_units = ideal.map((vec) => math.normalize_vec(vec))
+  const neighbor_list = get_neighbor_list(structure, cutoff)
+
+  const orientations = neighbor_list.map((neighbors): Matrix3x3 | null => {
+    if (neighbors.length < 3) return null
+    const bond_units = neighbors.map(({ displacement }) => math.normalize_vec(displacement))
+    const [bond1] = bond_units
+    const bond2 = bond_units.find((unit) => Math.hypot(...math.cross_3d(bond1, unit)) > 0.2)
+    if (!bond2) return null
+    const cos12 = math.dot(bond1, bond2)
+
+    // Template matching: align the two reference bonds with every ideal pair at a similar
+    // angle and keep the rotation that best maps all bonds onto ideal directions
+    let best: { msd: number; matches: number[] } | null = null
+    for (const ideal1 of ideal_units) {
+      for (const ideal2 of ideal_units) {
+        const cos_ideal = math.dot(ideal1, ideal2)
+        if (Math.abs(cos_ideal) > 0.99 || Math.abs(cos_ideal - cos12) > 0.15) continue
+        const rot = pair_rotation(ideal1, ideal2, bond1, bond2)
+        const rot_ideal = ideal_units.map((unit) => math.mat3x3_vec3_multiply(rot, unit))
+        let sum_sq = 0
+        const matches = bond_units.map((unit) => {
+          let [best_idx, best_cos] = [0, -Infinity]
+          rot_ideal.forEach((rotated, ideal_idx) => {
+            const cos_angle = math.dot(unit, rotated)
+            if (cos_angle > best_cos) [best_idx, best_cos] = [ideal_idx, cos_angle]
+          })
+          sum_sq += Math.acos(Math.min(1, best_cos)) ** 2
+          return best_idx
+        })
+        const msd = sum_sq / bond_units.length
+        if (!best || msd < best.msd) best = { msd, matches }
+      }
+    }
+    if (!best || math.to_degrees(Math.sqrt(best.msd)) > rmsd_tol) return null
+    const { matches } = best
+
+    // Refine: least-squares map of bonds onto matched ideal bonds, keep its rotation part
+    const qtq: Matrix3x3 = [
+      [0, 0, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    const qtp: Matrix3x3 = [
+      [0, 0, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    neighbors.forEach(({ displacement }, bond_idx) => {
+      const target = ideal[matches[bond_idx]]
+      for (let idx = 0; idx < 3; idx++) {
+        for (let jdx = 0; jdx < 3; jdx++) {
+          qtq[idx][jdx] += displacement[idx] * displacement[jdx]
+          qtp[idx][jdx] += displacement[idx] * target[jdx]
+        }
+      }
+    })
+    const trace = qtq[0][0] + qtq[1][1] + qtq[2][2]
+    if (Math.abs(math.det_3x3(qtq)) < 1e-8 * trace ** 3) return null
+    // G maps actual onto ideal bonds (q G = p); for a rotated lattice q = R p, G = R
+    return polar_rotation(math.dot(math.matrix_inverse_3x3(qtq), qtp))
+  })
+  return { orientations, neighbor_list }
+}
+
+// Per-atom lattice orientations: rotation R taking ideal reference bonds to actual bonds
+// (q ≈ R p), null for atoms whose environment doesn't match the reference
+export const compute_orientations = (
+  structure: AnyStructure,
+  options: OrientationOptions,
+): (Matrix3x3 | null)[] => fit_orientations(structure, options).orientations
+
+// Segment a polycrystal into grains: bonded atoms join the same grain when their
+// disorientation (accounting for lattice symmetry) is below threshold. Reports grain sizes,
+// mean orientations and the misorientation across every pair of touching grains.
+export function segment_grains(
+  structure: AnyStructure,
+  options: GrainSegmentationOptions,
+): GrainSegmentation {
+  const { threshold = 5, min_grain_size = 1 } = options
+  const { orientations, neighbor_list } = fit_orientations(structure, options)
+  const symmetry = lattice_rotations(ideal_vectors(options.reference, options.cutoff))
+  const is_aligned = (site_1: number, site_2: number): boolean => {
+    const [rot1, rot2] = [orientations[site_1], orientations[site_2]]
+    return !!rot1 && !!rot2 && misorientation(rot1, rot2, symmetry).angle < threshold
+  }
+
+  // Flood fill over bonds between aligned atoms
+  const cluster_ids = Array<number>(orientations.length).fill(-1)
+  const clusters: number[][] = []
+  orientations.forEach((rot, seed) => {
+    if (!rot || cluster_ids[seed] >= 0) return
+    const members = [seed]
+    cluster_ids[seed] = clusters.length
+    for (let head = 0; head < members.length; head++) {
+      for (const { site_idx } of neighbor_list[members[head]]) {
+        if (cluster_ids[site_idx] >= 0 || !is_aligned(members[head], site_idx)) continue
+        cluster_ids[site_idx] = clusters.length
+        members.push(site_idx)
+      }
+    }
+    clusters.push(members)
+  })
+
+  // Keep clusters above min_grain_size, relabeled by size descending
+  const kept = clusters
+    .filter((members) => members.length >= min_grain_size)
+    .toSorted((members_1, members_2) => members_2.length - members_1.length)
+  const grain_ids = Array<number>(orientations.length).fill(-1)
+  const grains: Grain[] = kept.map((members, grain_id) => {
+    // Mean orientation: sum the symmetry-equivalents closest to the seed's orientation,
+    // then re-orthogonalize
+    const seed_rot = orientations[members[0]] as Matrix3x3
+    const sum: Matrix3x3 = [
+      [0, 0, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    for (const site_idx of members) {
+      grain_ids[site_idx] = grain_id
+      const rot = orientations[site_idx] as Matrix3x3
+      const { closest } = misorientation(seed_rot, rot, symmetry)
+      for (let idx = 0; idx < 3; idx++) {
+        for (let jdx = 0; jdx < 3; jdx++) sum[idx][jdx] += closest[idx][jdx]
+      }
+    }
+    return { size: members.length, orientation: polar_rotation(sum) ?? seed_rot }
+  })
+
+  // Boundaries: grain pairs meeting in the neighborhood (atom plus bonded neighbors) of
+  // some atom. Including unassigned atoms bridges the disordered layer that typically
+  // separates grains.
+  const boundary_atoms = new Map<string, number>()
+  neighbor_list.forEach((neighbors, site_idx) => {
+    const touching = new Set(
+      [site_idx, ...neighbors.map((nb) => nb.site_idx)].map((idx) => grain_ids[idx]),
+    )
+    touching.delete(-1)
+    const sorted = [...touching].toSorted((id_1, id_2) => id_1 - id_2)
+    for (const [pos, grain_1] of sorted.entries()) {
+      for (const grain_2 of sorted.slice(pos + 1)) {
+        const key = `${grain_1},${grain_2}`
+        boundary_atoms.set(key, (boundary_atoms.get(key) ?? 0) + 1)
+      }
+    }
+  })
+  const boundaries = [...boundary_atoms].map(([key, n_atoms]): GrainBoundary => {
+    const [grain_1, grain_2] = key.split(`,`).map(Number)
+    const { angle } = misorientation(
+      grains[grain_1].orientation,
+      grains[grain_2].orientation,
+      symmetry,
+    )
+    return { grains: [grain_1, grain_2], misorientation: angle, n_atoms }
+  })
+
+  return { grain_ids, grains, boundaries, orientations }
+}
@@ -0,0 +1,4 @@
+export * from './cna'
+export * from './grains'
+export * from './nye-tensor'
+export * from './steinhardt'
@@ -0,0 +1,154 @@
+// Per-atom Nye tensor from lattice correspondence against a perfect reference crystal
+// (Hartley & Mishin, Acta Mater. 53, 1313 (2005))
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, Crystal } from '$lib/structure'
+import { get_neighbor_list } from '$lib/structure/neighbors'
+
+export type NyeTensorOptions = {
+  cutoff: number // neighbor cutoff in Å, should sit between the 1st and 2nd shells
+  // Ideal neighbor vectors of the perfect lattice, or a perfect reference crystal from
+  // whose first site they're taken with the same cutoff
+  reference: Crystal | Vec3[]
+  angle_tol?: number // max angle (degrees) between matched actual/ideal bonds (default 27)
+  min_matches?: number // neighbors required for a well-conditioned fit (default 3)
+}
+
+export type NyeTensorResult = {
+  // Lattice correspondence G_i mapping actual bond (row) vectors onto ideal ones, q G ≈ p.
+  // G = F⁻ᵀ for a homogeneous deformation gradient F. null where too few bonds matched.
+  correspondence: (Matrix3x3 | null)[]
+  // Nye tensor α_jk = -ε_jmn ∂G_nk/∂x_m in Å⁻¹: Burgers vector component k per unit area
+  // normal to direction j. null where the correspondence or its gradient is undefined.
+  nye: (Matrix3x3 | null)[]
+  nye_norm: number[] // Frobenius norm of α per atom (0 where undefined)
+}
+
+const zeros = (): Matrix3x3 => [
+  [0, 0, 0],
+  [0, 0, 0],
+  [0, 0, 0],
+]
+
+// Levi-Civita symbol for indices in 0..2
+const levi_civita = (idx: number, jdx: number, kdx: number): number =>
+  ((idx - jdx) * (jdx - kdx) * (kdx - idx)) / 2
+
+// Ideal neighbor vectors of a perfect crystal: bonds from its first site within cutoff
+export function reference_neighbor_vectors(reference: Crystal, cutoff: number): Vec3[] {
+  const [first_site_neighbors = []] = get_neighbor_list(reference, cutoff)
+  return first_site_neighbors.map(({ displacement }) => displacement)
+}
+
+// Least-squares solution X of Q X ≈ B for row-stacked Q (n×3) and B (n×3):
+// X = (QᵀQ)⁻¹ QᵀB. Returns null if QᵀQ is (near-)singular.
+function least_squares_3(rows: Vec3[], targets: Vec3[]): Matrix3x3 | null {
+  const qtq = zeros()
+  const qtb = zeros()
+  rows.forEach((row, row_idx) => {
+    for (let idx = 0; idx < 3; idx++) {
+      for (let jdx = 0; jdx < 3; jdx++) {
+        qtq[idx][jdx] += row[idx] * row[jdx]
+        qtb[idx][jdx] += row[idx] * targets[row_idx][jdx]
+      }
+    }
+  })
+  const scale = qtq[0][0] + qtq[1][1] + qtq[2][2]
+  if (!(scale > 0) || Math.abs(math.det_3x3(qtq)) < 1e-8 * scale ** 3) return null
+  return math.dot(math.matrix_inverse_3x3(qtq), qtb)
+}
+
+// Compute per-atom lattice correspondence tensors and Nye tensors. Each actual bond within
+// cutoff is matched to the ideal bond with the smallest angle (rejecting matches beyond
+// angle_tol); G_i is the least-squares map of matched actual onto ideal bonds, and ∇G is
+// fitted from differences G_j - G_i over the same bonds.
+export function compute_nye_tensor(
+  structure: AnyStructure,
+  options: NyeTensorOptions,
+): NyeTensorResult {
+  const { cutoff, angle_tol = 27, min_matches = 3 } = options
+  const ideal = Array.isArray(options.reference)
+    ? options.reference
+    : reference_neighbor_vectors(options.reference, cutoff)
+  if (ideal.length < 3) {
+    throw new Error(`Need at least 3 ideal neighbor vectors, got ${ideal.length}`)
+  }
+  const ideal_units = ideal.map((vec) => math.normalize_vec(vec))
+  const cos_tol = Math.cos(math.to_radians(angle_tol))
+  const neighbor_list = get_neighbor_list(structure, cutoff)
+
+  // Match bonds to ideal neighbor vectors and fit the lattice correspondence
+  const matched = neighbor_list.map((neighbors) =>
+    neighbors.flatMap((neighbor) => {
+      const unit = math.normalize_vec(neighbor.displacement)
+      let [best_idx, best_cos] = [-1, cos_tol]
+      ideal_units.forEach((ideal_unit, ideal_idx) => {
+        const cos_angle = math.dot(unit, ideal_unit)
+        if (cos_angle > best_cos) [best_idx, best_cos] = [ideal_idx, cos_angle]
+      })
+      return best_idx < 0 ? [] : [{ neighbor, ideal: ideal[best_idx] }]
+    }),
+  )
+  const correspondence = matched.map((pairs) =>
+    pairs.length < min_matches
+      ? null
+      : least_squares_3(
+          pairs.map(({ neighbor }) => neighbor.displacement),
+          pairs.map((pair) => pair.ideal),
+        ),
+  )
+
+  const nye = matched.map((pairs, site_idx): Matrix3x3 | null => {
+    const g_center = correspondence[site_idx]
+    if (!g_center) return null
+    const usable = pairs.filter(({ neighbor }) => correspondence[neighbor.site_idx])
+    if (usable.length < min_matches) return null
+    const bonds = usable.map(({ neighbor }) => neighbor.displacement)
+    // gradient[n][k][m] = ∂G_nk / ∂x_m, fitted component-wise from ΔG over bonds
+    const gradient: Vec3[][] = []
+    for (let n_idx = 0; n_idx < 3; n_idx++) {
+      const rows: Vec3[] = []
+      for (const { neighbor } of usable) {
+        const g_neighbor = correspondence[neighbor.site_idx] as Matrix3x3
+        rows.push(math.subtract(g_neighbor[n_idx], g_center[n_idx]))
+      }
+      // Q ∇G_n· ≈ ΔG_n·  → solution columns index k, rows index m
+      const fit = least_squares_3(bonds, rows)
+      if (!fit) return null
+      gradient.push([0, 1, 2].map((k_idx) => fit.map((row) => row[k_idx]) as Vec3))
+    }
+    const alpha = zeros()
+    for (let j_idx = 0; j_idx < 3; j_idx++) {
+      for (let k_idx = 0; k_idx < 3; k_idx++) {
+        let sum = 0
+        for (let m_idx = 0; m_idx < 3; m_idx++) {
+          for (let n_idx = 0; n_idx < 3; n_idx++) {
+            const eps = levi_civita(j_idx, m_idx, n_idx)
+            if (eps !== 0) sum += eps * gradient[n_idx][k_idx][m_idx]
+          }
+        }
+        alpha[j_idx][k_idx] = -sum
+      }
+    }
+    return alpha
+  })
+
+  const nye_norm = nye.map((alpha) => (alpha ? Math.hypot(...alpha.flat()) : 0))
+  return { correspondence, nye, nye_norm }
+}
+
+// Dislocation line density (Å⁻²) from per-atom Nye tensors: ρ = Σ_i |α_i| V_i / (|b| V),
+// i.e. integrated Burgers content over the sample divided by the Burgers vector length
+// and sample volume. atomic_volume defaults to V / N for a crystal.
+export function dislocation_density(
+  nye_norm: number[],
+  burgers_length: number,
+  volume: number,
+  atomic_volume = volume / nye_norm.length,
+): number {
+  if (!(burgers_length > 0) || !(volume > 0)) {
+    throw new Error(`burgers_length and volume must be > 0`)
+  }
+  const total = nye_norm.reduce((sum, norm) => sum + norm, 0)
+  return (total * atomic_volume) / (burgers_length * volume)
+}
@@ -0,0 +1,155 @@
+// Steinhardt bond-orientational order parameters q_l (Steinhardt, Nelson & Ronchetti,
+// Phys. Rev. B 28, 784 (1983)) and their neighbor-averaged variant q̄_l (Lechner & Dellago,
+// J. Chem. Phys. 129, 114707 (2008)) per atom, for one structure or a whole trajectory.
+// Trajectories are done in one call that reuses a Verlet neighbor list across frames.
+import type { AnyStructure } from '$lib/structure'
+import type { Neighbor } from '$lib/structure/neighbors'
+import { get_knn_neighbor_list, NeighborList } from '$lib/structure/neighbors'
+import type { TrajectoryType } from '$lib/trajectory'
+
+export type SteinhardtOptions = {
+  degrees?: number[] // spherical harmonic degrees l (default [4, 6])
+  // neighbors of an atom are all atoms within cutoff (Å) or its n_neighbors nearest, e.g.
+  // 12 for close-packed metals. Exactly one of the two must be given.
+  cutoff?: number
+  n_neighbors?: number
+  // Lechner-Dellago q̄_l from q_lm averaged over each atom and its neighbors, which
+  // separates crystal structures better in thermal noise (default false)
+  averaged?: boolean
+}
+
+export type SteinhardtFrameOptions = SteinhardtOptions & {
+  start_frame?: number // default 0
+  stride?: number // use every stride-th frame (default 1)
+  skin?: number // Å, Verlet skin of the cutoff neighbor list shared by frames (default 0.3)
+}
+
+// spherical harmonic normalization sqrt((2l + 1) / 4π · (l - m)! / (l + m)!) for m = 0..l
+function harmonic_norms(degree: number): Float64Array {
+  const norms = new Float64Array(degree + 1)
+  for (let order = 0; order <= degree; order++) {
+    let ratio = 1
+    for (let factor = degree - order + 1; factor <= degree + order; factor++) ratio /= factor
+    norms[order] = Math.sqrt(((2 * degree + 1) / (4 * Math.PI)) * ratio)
+  }
+  return norms
+}
+
+// associated Legendre functions P_l^m(x) for m = 0..l written into out, without the
+// Condon-Shortley phase (a constant phase per m that q_l doesn't see)
+function associated_legendre(degree: number, x: number, out: Float64Array): void {
+  const sin_theta = Math.sqrt(Math.max(0, 1 - x * x))
+  let p_mm = 1
+  for (let order = 0; order <= degree; order++) {
+    if (order > 0) p_mm *= (2 * order - 1) * sin_theta
+    let [p_prev, p_curr] = [0, p_mm]
+    for (let ell = order + 1; ell <= degree; ell++) {
+      const p_next = (x * (2 * ell - 1) * p_curr - (ell + order - 1) * p_prev) / (ell - order)
+      p_prev = p_curr
+      p_curr = p_next
+    }
+    out[order] = p_curr
+  }
+}
+
+// q_l of every atom per degree from its neighbors. Atoms without neighbors get 0.
+function steinhardt_from_neighbors(
+  neighbor_list: Neighbor[][],
+  degrees: number[],
+  averaged: boolean,
+): Float64Array[] {
+  const n_atoms = neighbor_list.length
+  return degrees.map((degree) => {
+    const n_orders = degree + 1
+    const norms = harmonic_norms(degree)
+    const legendre = new Float64Array(n_orders)
+    // q_lm for m = 0..l as rows of n_orders per atom (q_l,-m follow by symmetry)
+    const q_re = new Float64Array(n_atoms * n_orders)
+    const q_im = new Float64Array(n_atoms * n_orders)
+    neighbor_list.forEach((neighbors, atom_idx) => {
+      for (const { distance, displacement } of neighbors) {
+        associated_legendre(degree, displacement[2] / distance, legendre)
+        const phi = Math.atan2(displacement[1], displacement[0])
+        for (let order = 0; order < n_orders; order++) {
+          const amplitude = (norms[order] * legendre[order]) / neighbors.length
+          q_re[atom_idx * n_orders + order] += amplitude * Math.cos(order * phi)
+          q_im[atom_idx * n_orders + order] += amplitude * Math.sin(order * phi)
+        }
+      }
+    })
+    let [re, im] = [q_re, q_im]
+    if (averaged) {
+      re = new Float64Array(q_re.length)
+      im = new Float64Array(q_im.length)
+      neighbor_list.forEach((neighbors, atom_idx) => {
+        const members = [atom_idx, ...neighbors.map(({ site_idx }) => site_idx)]
+        for (const member of members) {
+          for (let order = 0; order < n_orders; order++) {
+            re[atom_idx * n_orders + order] += q_re[member * n_orders + order] / members.length
+            im[atom_idx * n_orders + order] += q_im[member * n_orders + order] / members.length
+          }
+        }
+      })
+    }
+    const q_l = new Float64Array(n_atoms)
+    for (let atom_idx = 0; atom_idx < n_atoms; atom_idx++) {
+      let sum_sq = 0
+      for (let order = 0; order < n_orders; order++) {
+        const idx = atom_idx * n_orders + order
+        sum_sq += (order === 0 ? 1 : 2) * (re[idx] ** 2 + im[idx] ** 2)
+      }
+      q_l[atom_idx] = Math.sqrt(((4 * Math.PI) / (2 * degree + 1)) * sum_sq)
+    }
+    return q_l
+  })
+}
+
+// Validated degrees and a neighbor search for the options, reusing one Verlet list
+// between calls for cutoff neighbors
+function steinhardt_setup(
+  options: SteinhardtOptions,
+  skin: number,
+): { degrees: number[]; search: (structure: AnyStructure) => Neighbor[][] } {
+  const { degrees = [4, 6], cutoff, n_neighbors } = options
+  for (const degree of degrees) {
+    if (!(Number.isInteger(degree) && degree >= 0)) {
+      throw new Error(`degrees must be non-negative integers, got ${degree}`)
+    }
+  }
+  if ((cutoff === undefined) === (n_neighbors === undefined)) {
+    throw new Error(`Steinhardt parameters need exactly one of cutoff and n_neighbors`)
+  }
+  if (n_neighbors !== undefined) {
+    return {
+      degrees,
+      search: (structure) => get_knn_neighbor_list(structure, n_neighbors),
+    }
+  }
+  const neighbor_list = new NeighborList(cutoff as number, skin)
+  return { degrees, search: (structure) => neighbor_list.update(structure) }
+}
+
+// Per-atom q_l for each of options.degrees (in that order)
+export function compute_steinhardt_q(
+  structure: AnyStructure,
+  options: SteinhardtOptions,
+): Float64Array[] {
+  const { degrees, search } = steinhardt_setup(options, 0)
+  return steinhardt_from_neighbors(search(structure), degrees, options.averaged ?? false)
+}
+
+// compute_steinhardt_q of every selected trajectory frame, indexed [frame][degree][atom]
+export function compute_steinhardt_q_frames(
+  trajectory: TrajectoryType,
+  options: SteinhardtFrameOptions,
+): Float64Array[][] {
+  const { start_frame = 0, stride = 1, skin = 0.3, averaged = false } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const { degrees, search } = steinhardt_setup(options, skin)
+  return trajectory.frames
+    .slice(start_frame)
+    .filter((_, idx) => idx % stride === 0)
+    .map(({ structure }) => steinhardt_from_neighbors(search(structure), degrees, averaged))
+}
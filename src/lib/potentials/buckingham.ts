@@ -0,0 +1,141 @@
+// Rigid-ion potential of GULP-style force fields for oxides and other ionic solids: a
+// Buckingham short-range term A exp(-r/ρ) - C/r⁶ per species pair plus long-range Coulomb
+// by Ewald summation. The reciprocal sum is done directly over k-vectors (direct_reciprocal
+// of ewald_energy), which gives exact forces and stress but scales as O(N^1.5), so it suits
+// cells up to a few thousand ions.
+// Large C with small ρ makes the energy diverge to -∞ at short range (the Buckingham
+// catastrophe), so keep structures near sensible ionic distances.
+import { COULOMB_EV_A } from '$lib/constants'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { get_majority_element } from '$lib/structure/bonding'
+import type { EwaldAccuracy } from '$lib/structure/ewald'
+import {
+  direct_reciprocal,
+  EWALD_ACCURACY,
+  oxidation_state_charges,
+} from '$lib/structure/ewald'
+import { NeighborList } from '$lib/structure/neighbors'
+import { ForceAccumulator } from './helpers'
+import type { Potential, PotentialResult } from './index'
+
+export type BuckinghamPair = {
+  elements: [string, string] // either order
+  a: number // eV
+  rho: number // Å
+  c: number // eV Å⁶
+}
+
+export type BuckinghamOptions = {
+  // per-element charges in e (default: occupancy-weighted site oxidation states)
+  charges?: Record<string, number>
+  // Å, of the Buckingham terms and the real-space Ewald sum (default 10). The Ewald
+  // splitting parameter α follows from it and the accuracy like in ewald_energy.
+  cutoff?: number
+  accuracy?: EwaldAccuracy // default `medium`
+  skin?: number // Å, Verlet skin of the neighbor list reused between calls (default 0.3)
+}
+
+const pair_key = (el_1: string, el_2: string) => [el_1, el_2].sort().join(` `)
+
+// Buckingham plus Ewald Coulomb potential of 3D-periodic crystals. Species pairs without
+// Buckingham parameters interact by Coulomb only.
+export function create_buckingham_coulomb_potential(
+  pairs: BuckinghamPair[],
+  options: BuckinghamOptions = {},
+): Potential {
+  const { cutoff = 10, skin = 0.3 } = options
+  const accuracy =
+    typeof options.accuracy === `string`
+      ? EWALD_ACCURACY[options.accuracy]
+      : (options.accuracy ?? EWALD_ACCURACY.medium)
+  if (!(accuracy > 0 && accuracy < 1)) {
+    throw new Error(`accuracy must be in (0, 1), got ${options.accuracy}`)
+  }
+  if (!(cutoff > 0)) throw new Error(`cutoff must be positive, got ${cutoff}`)
+  for (const { elements, rho } of pairs) {
+    if (!(rho > 0)) throw new Error(`Buckingham rho of ${elements.join(`-`)} must be > 0`)
+  }
+  const by_key = new Map(pairs.map((pair) => [pair_key(...pair.elements), pair]))
+  const sqrt_ln = Math.sqrt(-Math.log(accuracy))
+  const alpha = sqrt_ln / cutoff
+  const k_cut = 2 * alpha * sqrt_ln
+  const neighbor_list = new NeighborList(cutoff, skin)
+
+  const compute = (structure: AnyStructure): PotentialResult => {
+    if (!(`lattice` in structure) || !structure.lattice.pbc.every(Boolean)) {
+      throw new Error(`Ewald sums need periodic boundaries along all 3 axes`)
+    }
+    const { sites, lattice } = structure
+    const elements = sites.map((site) => get_majority_element(site) ?? ``)
+    const charges = options.charges
+      ? elements.map((element, site_idx) => {
+          const charge = options.charges?.[element]
+          if (charge === undefined) {
+            throw new Error(`No charge given for site ${site_idx} (${element})`)
+          }
+          return charge
+        })
+      : oxidation_state_charges(sites)
+
+    const accumulator = new ForceAccumulator(sites.length)
+    const energies = new Array<number>(sites.length).fill(0)
+    neighbor_list.update(structure).forEach((site_neighbors, center_idx) => {
+      for (const { site_idx, distance, displacement } of site_neighbors) {
+        const q_q = COULOMB_EV_A * charges[center_idx] * charges[site_idx]
+        const screened = math.erfc(alpha * distance) / distance
+        let energy = q_q * screened
+        const gaussian =
+          ((2 * alpha) / Math.sqrt(Math.PI)) * Math.exp(-((alpha * distance) ** 2))
+        let d_energy = (-q_q * (screened + gaussian)) / distance
+        const pair = by_key.get(pair_key(elements[center_idx], elements[site_idx]))
+        if (pair) {
+          const repulsion = pair.a * Math.exp(-distance / pair.rho)
+          const dispersion = pair.c / distance ** 6
+          energy += repulsion - dispersion
+          d_energy += -repulsion / pair.rho + (6 * dispersion) / distance
+        }
+        // every pair appears once from each side, which adds half its energy and gradient
+        energies[center_idx] += energy / 2
+        const grad = math.scale(displacement, d_energy / (2 * distance))
+        accumulator.add(center_idx, site_idx, displacement, grad)
+      }
+    })
+
+    const volume = Math.abs(math.det_3x3(lattice.matrix))
+    const reciprocal = direct_reciprocal(
+      sites.map(({ abc }) => abc),
+      charges,
+      math.create_cart_to_frac_matrix(lattice.matrix),
+      lattice.matrix.map((vec) => Math.hypot(...vec)) as Vec3,
+      alpha,
+      k_cut,
+      volume,
+    )
+    const net_charge = charges.reduce((sum, charge) => sum + charge, 0)
+    // neutralizing background for charged cells, spread evenly over the sites
+    const charged = (-COULOMB_EV_A * Math.PI * net_charge ** 2) / (2 * volume * alpha ** 2)
+    charges.forEach((charge, site_idx) => {
+      const self = (-COULOMB_EV_A * alpha * charge * charge) / Math.sqrt(Math.PI)
+      energies[site_idx] +=
+        COULOMB_EV_A * reciprocal.site_energies[site_idx] + self + charged / sites.length
+    })
+
+    const result = accumulator.result(structure, energies)
+    result.forces.forEach((force, site_idx) => {
+      for (let axis = 0; axis < 3; axis++) {
+        force[axis] += COULOMB_EV_A * reciprocal.forces[site_idx][axis]
+      }
+    })
+    result.stress?.forEach((row, row_idx) => {
+      for (let col = 0; col < 3; col++) {
+        row[col] += COULOMB_EV_A * reciprocal.stress[row_idx][col]
+      }
+      row[row_idx] -= charged / volume
+    })
+    return result
+  }
+
+  return { cutoff, compute }
+}
@@ -0,0 +1,215 @@
+// Embedded-atom method (Daw & Baskes, Phys. Rev. B 29, 6443 (1984)) from LAMMPS
+// eam/alloy (setfl) files. Atom i has energy F_i(ρ_i) + ½ Σ_j φ_ij(r_ij) with host electron
+// density ρ_i = Σ_j ρ_j(r_ij). Tables are interpolated with the cubic splines of LAMMPS'
+// pair_style eam/alloy so energies, forces and stresses match it for the same file.
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { NeighborList } from '$lib/structure/neighbors'
+import { ForceAccumulator, site_types } from './helpers'
+import type { Potential, PotentialResult } from './index'
+
+export type EamAlloyElement = {
+  element: string
+  atomic_number: number
+  mass: number // amu
+  lattice_constant: number // Å
+  lattice_type: string // e.g. fcc
+  embedding: number[] // F(ρ) in eV on the density grid
+  density: number[] // ρ(r) on the distance grid
+}
+
+export type EamAlloyData = {
+  comments: string[] // the 3 header lines
+  elements: EamAlloyElement[]
+  n_rho: number
+  d_rho: number
+  n_r: number
+  d_r: number // Å
+  cutoff: number // Å
+  // r·φ(r) in eV·Å on the distance grid for element pairs i >= j in file order
+  // (1-1, 2-1, 2-2, 3-1, ...)
+  pair: number[][]
+}
+
+export type EamOptions = {
+  // Verlet skin (Å) of the neighbor list reused between calls, e.g. along MD or
+  // relaxation trajectories (default 0.3)
+  skin?: number
+}
+
+// index of pair i-j in EamAlloyData.pair
+const pair_index = (idx: number, jdx: number): number =>
+  idx >= jdx ? (idx * (idx + 1)) / 2 + jdx : (jdx * (jdx + 1)) / 2 + idx
+
+export function parse_eam_alloy(text: string): EamAlloyData {
+  const lines = text.split(/\r?\n/)
+  if (lines.length < 5) throw new Error(`setfl file needs at least 5 lines`)
+  const comments = lines.slice(0, 3)
+  const [n_elem_str, ...symbols] = lines[3].trim().split(/\s+/)
+  const n_elements = Number(n_elem_str)
+  if (!(Number.isInteger(n_elements) && n_elements > 0 && symbols.length >= n_elements)) {
+    throw new Error(`Invalid setfl element line: ${lines[3].trim()}`)
+  }
+  const tokens = lines.slice(4).join(` `).trim().split(/\s+/)
+  let pos = 0
+  const next_number = (what: string): number => {
+    if (pos >= tokens.length) throw new Error(`setfl file ended while reading ${what}`)
+    const val = Number(tokens[pos++])
+    if (!Number.isFinite(val)) throw new Error(`Invalid number ${tokens[pos - 1]} in ${what}`)
+    return val
+  }
+  const read_table = (count: number, what: string): number[] =>
+    Array.from({ length: count }, () => next_number(what))
+
+  const n_rho = next_number(`grid line`)
+  const d_rho = next_number(`grid line`)
+  const n_r = next_number(`grid line`)
+  const d_r = next_number(`grid line`)
+  const cutoff = next_number(`grid line`)
+  if (!(Number.isInteger(n_rho) && n_rho >= 2 && Number.isInteger(n_r) && n_r >= 2)) {
+    throw new Error(`setfl grids need >= 2 points, got Nrho=${n_rho} and Nr=${n_r}`)
+  }
+  if (!(d_rho > 0 && d_r > 0 && cutoff > 0)) {
+    throw new Error(`setfl drho, dr and cutoff must be > 0`)
+  }
+
+  const elements = symbols.slice(0, n_elements).map((element): EamAlloyElement => {
+    const atomic_number = next_number(`${element} header`)
+    const mass = next_number(`${element} header`)
+    const lattice_constant = next_number(`${element} header`)
+    const lattice_type = tokens[pos++] ?? ``
+    return {
+      element,
+      atomic_number,
+      mass,
+      lattice_constant,
+      lattice_type,
+      embedding: read_table(n_rho, `${element} F(rho)`),
+      density: read_table(n_r, `${element} rho(r)`),
+    }
+  })
+  const pair: number[][] = []
+  for (let idx = 0; idx < n_elements; idx++) {
+    for (let jdx = 0; jdx <= idx; jdx++) {
+      pair.push(read_table(n_r, `${symbols[idx]}-${symbols[jdx]} r*phi(r)`))
+    }
+  }
+  return { comments, elements, n_rho, d_rho, n_r, d_r, cutoff, pair }
+}
+
+// Piecewise cubic through equally spaced values with 4-point finite-difference slopes at
+// the knots, the interpolation of LAMMPS' PairEAM::interpolate. Beyond the last knot the
+// end value is held.
+class CubicTable {
+  private coeffs: Float64Array // per interval: cubic, quadratic, linear, constant term
+
+  constructor(
+    values: number[],
+    private readonly step: number,
+  ) {
+    const n_pts = values.length
+    const slopes = values.map((_, idx) => {
+      if (idx === 0) return values[1] - values[0]
+      if (idx === n_pts - 1) return values[idx] - values[idx - 1]
+      if (idx === 1 || idx === n_pts - 2) return 0.5 * (values[idx + 1] - values[idx - 1])
+      return (
+        (values[idx - 2] - values[idx + 2] + 8 * (values[idx + 1] - values[idx - 1])) / 12
+      )
+    })
+    this.coeffs = new Float64Array(4 * n_pts)
+    values.forEach((val, idx) => {
+      const rise = idx < n_pts - 1 ? values[idx + 1] - val : 0
+      const next_slope = idx < n_pts - 1 ? slopes[idx + 1] : slopes[idx]
+      this.coeffs.set(
+        idx < n_pts - 1
+          ? [
+              slopes[idx] + next_slope - 2 * rise,
+              3 * rise - 2 * slopes[idx] - next_slope,
+              slopes[idx],
+              val,
+            ]
+          : [0, 0, slopes[idx], val],
+        4 * idx,
+      )
+    })
+  }
+
+  get x_max(): number {
+    return (this.coeffs.length / 4 - 1) * this.step
+  }
+
+  // interval offset and fractional position of x
+  private locate(x: number): [number, number] {
+    const pos = Math.max(x / this.step, 0)
+    const interval = Math.min(Math.floor(pos), this.coeffs.length / 4 - 2)
+    return [4 * interval, Math.min(pos - interval, 1)]
+  }
+
+  value(x: number): number {
+    const [off, frac] = this.locate(x)
+    const [cubic, quadratic, linear, constant] = this.coeffs.subarray(off, off + 4)
+    return ((cubic * frac + quadratic) * frac + linear) * frac + constant
+  }
+
+  derivative(x: number): number {
+    const [off, frac] = this.locate(x)
+    const [cubic, quadratic, linear] = this.coeffs.subarray(off, off + 3)
+    return ((3 * cubic * frac + 2 * quadratic) * frac + linear) / this.step
+  }
+}
+
+// EAM potential for structures whose sites are all elements of the setfl data (by
+// majority species). Densities above the tabulated range continue F(ρ) linearly like
+// LAMMPS does.
+export function create_eam_potential(data: EamAlloyData, options: EamOptions = {}): Potential {
+  const { skin = 0.3 } = options
+  const elements = data.elements.map(({ element }) => element)
+  const embedding = data.elements.map(({ embedding }) => new CubicTable(embedding, data.d_rho))
+  const density = data.elements.map(({ density }) => new CubicTable(density, data.d_r))
+  const r_phi = data.pair.map((values) => new CubicTable(values, data.d_r))
+  const neighbor_list = new NeighborList(data.cutoff, skin)
+
+  const compute = (structure: AnyStructure): PotentialResult => {
+    const { sites } = structure
+    const types = site_types(structure, elements, `EAM`)
+    const neighbors = neighbor_list.update(structure)
+
+    // host densities, then embedding energies and their derivatives F'(ρ)
+    const energies = new Array<number>(sites.length).fill(0)
+    const d_embed = new Float64Array(sites.length)
+    neighbors.forEach((site_neighbors, center_idx) => {
+      let rho = 0
+      for (const { site_idx, distance } of site_neighbors) {
+        rho += density[types[site_idx]].value(distance)
+      }
+      const table = embedding[types[center_idx]]
+      const rho_max = table.x_max
+      d_embed[center_idx] = table.derivative(Math.min(rho, rho_max))
+      energies[center_idx] =
+        table.value(Math.min(rho, rho_max)) +
+        (rho > rho_max ? d_embed[center_idx] * (rho - rho_max) : 0)
+    })
+
+    // every pair appears once from each side, which adds half its energy and gradient
+    const accumulator = new ForceAccumulator(sites.length)
+    neighbors.forEach((site_neighbors, center_idx) => {
+      const center_type = types[center_idx]
+      for (const { site_idx, distance, displacement } of site_neighbors) {
+        const type = types[site_idx]
+        const table = r_phi[pair_index(center_type, type)]
+        const phi = table.value(distance) / distance
+        const d_phi = (table.derivative(distance) - phi) / distance
+        energies[center_idx] += phi / 2
+        const d_energy =
+          d_phi +
+          d_embed[center_idx] * density[type].derivative(distance) +
+          d_embed[site_idx] * density[center_type].derivative(distance)
+        const grad = math.scale(displacement, d_energy / (2 * distance))
+        accumulator.add(center_idx, site_idx, displacement, grad)
+      }
+    })
+    return accumulator.result(structure, energies)
+  }
+
+  return { cutoff: data.cutoff, compute }
+}
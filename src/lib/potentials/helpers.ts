@@ -0,0 +1,83 @@
+// Bookkeeping shared by the potentials: element types of the sites, and forces and virial
+// stress accumulated from energy gradients with respect to interatomic vectors
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { get_majority_element } from '$lib/structure/bonding'
+import type { PotentialResult } from './index'
+
+// index into elements of every site's majority element
+export function site_types(
+  structure: AnyStructure,
+  elements: readonly string[],
+  name: string,
+): number[] {
+  return structure.sites.map((site, site_idx) => {
+    const element = get_majority_element(site)
+    const type = element ? elements.indexOf(element) : -1
+    if (type < 0) {
+      throw new Error(
+        `Site ${site_idx} (${element}) is not covered by the ${name} potential for ` +
+          elements.join(`, `),
+      )
+    }
+    return type
+  })
+}
+
+// Parameter entries of three-body potentials indexed [i][j][k] by element type, for the
+// elements of all entries. Throws if some triplet has no entry.
+export function triplet_table<T extends { elements: [string, string, string] }>(
+  entries: T[],
+  name: string,
+): { elements: string[]; table: T[][][] } {
+  const elements = [...new Set(entries.flatMap(({ elements: triplet }) => triplet))]
+  const by_key = new Map(entries.map((entry) => [entry.elements.join(` `), entry]))
+  const table = elements.map((el_i) =>
+    elements.map((el_j) =>
+      elements.map((el_k) => {
+        const entry = by_key.get(`${el_i} ${el_j} ${el_k}`)
+        if (!entry) throw new Error(`Missing ${name} parameters for ${el_i} ${el_j} ${el_k}`)
+        return entry
+      }),
+    ),
+  )
+  return { elements, table }
+}
+
+// Collects gradients dE/dd of the energy with respect to vectors d = x_site - x_center
+// between a center and a neighbor image: the force on the center is +dE/dd, on the
+// neighbor -dE/dd, and each gradient adds d ⊗ dE/dd to the virial
+export class ForceAccumulator {
+  readonly forces: Vec3[]
+  readonly virial: Matrix3x3 = [
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+  ]
+
+  constructor(n_sites: number) {
+    this.forces = Array.from({ length: n_sites }, () => [0, 0, 0])
+  }
+
+  add(center_idx: number, site_idx: number, displacement: Vec3, grad: Vec3): void {
+    for (let axis = 0; axis < 3; axis++) {
+      this.forces[center_idx][axis] += grad[axis]
+      this.forces[site_idx][axis] -= grad[axis]
+      for (let col = 0; col < 3; col++) {
+        this.virial[axis][col] += displacement[axis] * grad[col]
+      }
+    }
+  }
+
+  // energy, forces and stress (1/V) dE/dε, null for molecules
+  result(structure: AnyStructure, energies: number[]): PotentialResult {
+    const energy = energies.reduce((sum, val) => sum + val, 0)
+    const volume =
+      `lattice` in structure ? Math.abs(math.det_3x3(structure.lattice.matrix)) : null
+    const stress = volume
+      ? (this.virial.map((row) => row.map((val) => val / volume)) as Matrix3x3)
+      : null
+    return { energy, energies, forces: this.forces, stress }
+  }
+}
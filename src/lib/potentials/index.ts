@@ -0,0 +1,23 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+
+export * from './buckingham'
+export * from './eam'
+export * from './stillinger-weber'
+export * from './tersoff'
+
+export type PotentialResult = {
+  energy: number // eV
+  energies: number[] // per-site contributions summing to energy (eV)
+  forces: Vec3[] // eV/Å, -dE/dxyz of each site
+  // (1/V) dE/dε in eV/Å³ with ASE's sign convention (positive = tensile), null for
+  // molecules
+  stress: Matrix3x3 | null
+}
+
+// Common interface of interatomic potentials so relaxations and MD can swap models.
+// compute() may cache neighbor lists between calls on successive configurations.
+export interface Potential {
+  cutoff: number // Å
+  compute: (structure: AnyStructure) => PotentialResult
+}
@@ -0,0 +1,170 @@
+// Stillinger-Weber potential (Phys. Rev. B 31, 5262 (1985)) for tetrahedral semiconductors:
+// pair terms plus three-body terms penalizing bond angles away from θ0 (the tetrahedral
+// angle for Si and Ge). Parameters follow LAMMPS' pair_style sw, one entry per element
+// triplet, so its .sw files can be read with parse_lammps_sw.
+import type { Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { NeighborList } from '$lib/structure/neighbors'
+import { ForceAccumulator, site_types, triplet_table } from './helpers'
+import type { Potential, PotentialResult } from './index'
+
+export type SwEntry = {
+  elements: [string, string, string] // center, neighbor j, neighbor k
+  epsilon: number // eV
+  sigma: number // Å
+  cut_ratio: number // a, the cutoff is a σ
+  lambda: number
+  gamma: number
+  cos_theta0: number
+  pair_a: number // A
+  pair_b: number // B
+  p: number
+  q: number
+}
+
+export type SwOptions = {
+  skin?: number // Å, Verlet skin of the neighbor list reused between calls (default 0.3)
+}
+
+const sw_entry = (element: string, epsilon: number, sigma: number, lambda: number) => ({
+  elements: [element, element, element] as [string, string, string],
+  epsilon,
+  sigma,
+  cut_ratio: 1.8,
+  lambda,
+  gamma: 1.2,
+  cos_theta0: -1 / 3,
+  pair_a: 7.049556277,
+  pair_b: 0.6022245584,
+  p: 4,
+  q: 0,
+})
+
+// Si of the original paper and Ge of Ding & Andersen (Phys. Rev. B 34, 6987 (1986))
+export const SW_SI: SwEntry[] = [sw_entry(`Si`, 2.1683, 2.0951, 21)]
+export const SW_GE: SwEntry[] = [sw_entry(`Ge`, 1.93, 2.181, 31)]
+
+// Entries of a LAMMPS .sw file: 3 elements then epsilon sigma a lambda gamma costheta0 A B
+// p q tol per entry (tol is unused here), # starting comments
+export function parse_lammps_sw(text: string): SwEntry[] {
+  const tokens = text
+    .split(/\r?\n/)
+    .map((line) => line.replace(/#.*/, ``))
+    .join(` `)
+    .trim()
+    .split(/\s+/)
+    .filter(Boolean)
+  if (tokens.length % 14 !== 0) {
+    throw new Error(`SW file must have 14 fields per entry, got ${tokens.length} in total`)
+  }
+  return Array.from({ length: tokens.length / 14 }, (_, entry_idx) => {
+    const fields = tokens.slice(14 * entry_idx, 14 * entry_idx + 14)
+    const values = fields.slice(3, 13).map(Number)
+    if (!values.every(Number.isFinite)) {
+      throw new Error(`Invalid number in SW entry ${fields.slice(0, 3).join(` `)}`)
+    }
+    const [epsilon, sigma, cut_ratio, lambda, gamma, cos_theta0, pair_a, pair_b, p, q] =
+      values
+    return {
+      elements: fields.slice(0, 3) as [string, string, string],
+      epsilon,
+      sigma,
+      cut_ratio,
+      lambda,
+      gamma,
+      cos_theta0,
+      pair_a,
+      pair_b,
+      p,
+      q,
+    }
+  })
+}
+
+export function create_stillinger_weber_potential(
+  entries: SwEntry[],
+  options: SwOptions = {},
+): Potential {
+  const { skin = 0.3 } = options
+  const { elements, table } = triplet_table(entries, `SW`)
+  const cutoff = Math.max(...entries.map(({ sigma, cut_ratio }) => sigma * cut_ratio))
+  const neighbor_list = new NeighborList(cutoff, skin)
+
+  const compute = (structure: AnyStructure): PotentialResult => {
+    const types = site_types(structure, elements, `Stillinger-Weber`)
+    const neighbors = neighbor_list.update(structure)
+    const energies = new Array<number>(types.length).fill(0)
+    const accumulator = new ForceAccumulator(types.length)
+
+    neighbors.forEach((site_neighbors, center_idx) => {
+      const type_i = types[center_idx]
+      // bonds within the cutoff of their pair entry with exp(γσ / (r - aσ)) and its
+      // derivative for the three-body terms
+      const bonds = site_neighbors.flatMap(({ site_idx, distance, displacement }) => {
+        const pair = table[type_i][types[site_idx]][types[site_idx]]
+        const { epsilon, sigma, cut_ratio, pair_a, pair_b, p, q, gamma } = pair
+        const r_cut = cut_ratio * sigma
+        if (distance >= r_cut) return []
+        // pair term, half of it from each side
+        const inv_gap = 1 / (distance - r_cut)
+        const cutoff_exp = Math.exp(sigma * inv_gap)
+        const [s_p, s_q] = [(sigma / distance) ** p, (sigma / distance) ** q]
+        const radial = pair_b * s_p - s_q
+        energies[center_idx] += (pair_a * epsilon * radial * cutoff_exp) / 2
+        const d_pair =
+          pair_a *
+          epsilon *
+          cutoff_exp *
+          ((q * s_q - p * pair_b * s_p) / distance - radial * sigma * inv_gap ** 2)
+        const grad = displacement.map((val) => (d_pair * val) / (2 * distance)) as Vec3
+        accumulator.add(center_idx, site_idx, displacement, grad)
+        const angular_exp = Math.exp(gamma * sigma * inv_gap)
+        const unit = displacement.map((val) => val / distance) as Vec3
+        return [
+          {
+            site_idx,
+            distance,
+            displacement,
+            unit,
+            angular_exp,
+            d_angular_exp: -angular_exp * gamma * sigma * inv_gap ** 2,
+          },
+        ]
+      })
+
+      for (let jj = 0; jj < bonds.length; jj++) {
+        const bond_j = bonds[jj]
+        for (let kk = jj + 1; kk < bonds.length; kk++) {
+          const bond_k = bonds[kk]
+          const { lambda, epsilon, cos_theta0 } =
+            table[type_i][types[bond_j.site_idx]][types[bond_k.site_idx]]
+          const cos_theta =
+            bond_j.unit[0] * bond_k.unit[0] +
+            bond_j.unit[1] * bond_k.unit[1] +
+            bond_j.unit[2] * bond_k.unit[2]
+          const delta = cos_theta - cos_theta0
+          const strength = lambda * epsilon
+          const exps = bond_j.angular_exp * bond_k.angular_exp
+          energies[center_idx] += strength * delta ** 2 * exps
+          // dE/dd_j from the angle (dcosθ/dd_j = (u_k - cosθ u_j) / r_j) and the radial
+          // exponential of bond j, and likewise for k
+          for (const [bond, other] of [
+            [bond_j, bond_k],
+            [bond_k, bond_j],
+          ]) {
+            const angle_term = (2 * strength * delta * exps) / bond.distance
+            const radial_term = strength * delta ** 2 * bond.d_angular_exp * other.angular_exp
+            const grad = bond.unit.map(
+              (val, axis) =>
+                angle_term * (other.unit[axis] - cos_theta * val) + radial_term * val,
+            ) as Vec3
+            accumulator.add(center_idx, bond.site_idx, bond.displacement, grad)
+          }
+        }
+      }
+    })
+    return accumulator.result(structure, energies)
+  }
+
+  return { cutoff, compute }
+}
@@ -0,0 +1,277 @@
+// Tersoff bond-order potential (Phys. Rev. B 37, 6991 (1988); 39, 5566 (1989)) for covalent
+// C, Si, Ge and their compounds: pair repulsion plus an attraction weakened by a bond order
+// b_ij that drops with the number and angles of the other bonds of atom i. Parameters follow
+// LAMMPS' pair_style tersoff, one entry per element triplet, so its .tersoff files can be
+// read with parse_lammps_tersoff.
+import type { Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { NeighborList } from '$lib/structure/neighbors'
+import { ForceAccumulator, site_types, triplet_table } from './helpers'
+import type { Potential, PotentialResult } from './index'
+
+export type TersoffEntry = {
+  elements: [string, string, string] // center i, bonded j, neighbor k
+  // angular and radial terms of k in the bond order of i-j
+  m: number
+  gamma: number
+  lambda3: number // Å⁻¹
+  c: number
+  d: number
+  cos_theta0: number // h
+  // bond order exponent and prefactor of i, and the i-j pair terms (used from i-j-j)
+  n: number
+  beta: number
+  lambda2: number // Å⁻¹
+  attractive_b: number // B in eV
+  cutoff_r: number // R in Å, smooth cutoff from R - D to R + D
+  cutoff_d: number // D in Å
+  lambda1: number // Å⁻¹
+  repulsive_a: number // A in eV
+}
+
+export type TersoffOptions = {
+  skin?: number // Å, Verlet skin of the neighbor list reused between calls (default 0.3)
+}
+
+type TersoffElement = {
+  a: number
+  b: number
+  lambda1: number
+  lambda2: number
+  beta: number
+  n: number
+  c: number
+  d: number
+  h: number
+  r_inner: number // R of Tersoff's papers
+  r_outer: number // S of Tersoff's papers
+}
+
+// Tersoff (1989) parameters of Si and C with the Si-C mixing χ = 0.9776
+const TERSOFF_1989: Record<string, TersoffElement> = {
+  Si: {
+    a: 1830.8,
+    b: 471.18,
+    lambda1: 2.4799,
+    lambda2: 1.7322,
+    beta: 1.1e-6,
+    n: 0.78734,
+    c: 1.0039e5,
+    d: 16.217,
+    h: -0.59825,
+    r_inner: 2.7,
+    r_outer: 3,
+  },
+  C: {
+    a: 1393.6,
+    b: 346.74,
+    lambda1: 3.4879,
+    lambda2: 2.2119,
+    beta: 1.5724e-7,
+    n: 0.72751,
+    c: 3.8049e4,
+    d: 4.3484,
+    h: -0.57058,
+    r_inner: 1.8,
+    r_outer: 2.1,
+  },
+}
+
+// LAMMPS entries of the Tersoff (1989) multicomponent mixing rules: A, B geometric means
+// (B scaled by χ), λ arithmetic means and cutoff radii geometric means
+function tersoff_1989_entries(elements: string[], chi: number): TersoffEntry[] {
+  const mix = (el_1: string, el_2: string) => {
+    const [p_1, p_2] = [TERSOFF_1989[el_1], TERSOFF_1989[el_2]]
+    const r_inner = Math.sqrt(p_1.r_inner * p_2.r_inner)
+    const r_outer = Math.sqrt(p_1.r_outer * p_2.r_outer)
+    return {
+      repulsive_a: Math.sqrt(p_1.a * p_2.a),
+      attractive_b: (el_1 === el_2 ? 1 : chi) * Math.sqrt(p_1.b * p_2.b),
+      lambda1: (p_1.lambda1 + p_2.lambda1) / 2,
+      lambda2: (p_1.lambda2 + p_2.lambda2) / 2,
+      cutoff_r: (r_inner + r_outer) / 2,
+      cutoff_d: (r_outer - r_inner) / 2,
+    }
+  }
+  return elements.flatMap((el_i) =>
+    elements.flatMap((el_j) =>
+      elements.map((el_k): TersoffEntry => {
+        const { n, beta, c, d, h } = TERSOFF_1989[el_i]
+        // the ζ cutoff is the i-k one, the pair cutoff comes from the i-j-j entry
+        const { cutoff_r, cutoff_d } = mix(el_i, el_k)
+        return {
+          ...mix(el_i, el_j),
+          elements: [el_i, el_j, el_k],
+          m: 3,
+          gamma: 1,
+          lambda3: 0,
+          c,
+          d,
+          cos_theta0: h,
+          n,
+          beta,
+          cutoff_r,
+          cutoff_d,
+        }
+      }),
+    ),
+  )
+}
+
+export const TERSOFF_SI: TersoffEntry[] = tersoff_1989_entries([`Si`], 1)
+export const TERSOFF_C: TersoffEntry[] = tersoff_1989_entries([`C`], 1)
+export const TERSOFF_SIC: TersoffEntry[] = tersoff_1989_entries([`Si`, `C`], 0.9776)
+
+const N_TERSOFF_FIELDS = 17
+
+// Entries of a LAMMPS .tersoff file: 3 elements then m gamma lambda3 c d costheta0 n beta
+// lambda2 B R D lambda1 A per entry, # starting comments
+export function parse_lammps_tersoff(text: string): TersoffEntry[] {
+  const tokens = text
+    .split(/\r?\n/)
+    .map((line) => line.replace(/#.*/, ``))
+    .join(` `)
+    .trim()
+    .split(/\s+/)
+    .filter(Boolean)
+  if (tokens.length % N_TERSOFF_FIELDS !== 0) {
+    throw new Error(
+      `Tersoff file must have ${N_TERSOFF_FIELDS} fields per entry, got ${tokens.length} ` +
+        `in total`,
+    )
+  }
+  return Array.from({ length: tokens.length / N_TERSOFF_FIELDS }, (_, entry_idx) => {
+    const start = N_TERSOFF_FIELDS * entry_idx
+    const fields = tokens.slice(start, start + N_TERSOFF_FIELDS)
+    const values = fields.slice(3).map(Number)
+    if (!values.every(Number.isFinite)) {
+      throw new Error(`Invalid number in Tersoff entry ${fields.slice(0, 3).join(` `)}`)
+    }
+    const [m, gamma, lambda3, c, d, cos_theta0, n, beta, lambda2, attractive_b] = values
+    const [cutoff_r, cutoff_d, lambda1, repulsive_a] = values.slice(10)
+    return {
+      elements: fields.slice(0, 3) as [string, string, string],
+      m,
+      gamma,
+      lambda3,
+      c,
+      d,
+      cos_theta0,
+      n,
+      beta,
+      lambda2,
+      attractive_b,
+      cutoff_r,
+      cutoff_d,
+      lambda1,
+      repulsive_a,
+    }
+  })
+}
+
+// smooth cutoff f_C(r) and its derivative
+function cutoff_fn(distance: number, { cutoff_r, cutoff_d }: TersoffEntry): [number, number] {
+  if (distance < cutoff_r - cutoff_d) return [1, 0]
+  if (distance > cutoff_r + cutoff_d) return [0, 0]
+  const arg = (Math.PI / 2) * ((distance - cutoff_r) / cutoff_d)
+  return [0.5 - 0.5 * Math.sin(arg), (-0.25 * Math.PI * Math.cos(arg)) / cutoff_d]
+}
+
+export function create_tersoff_potential(
+  entries: TersoffEntry[],
+  options: TersoffOptions = {},
+): Potential {
+  const { skin = 0.3 } = options
+  const { elements, table } = triplet_table(entries, `Tersoff`)
+  const cutoff = Math.max(...entries.map(({ cutoff_r, cutoff_d }) => cutoff_r + cutoff_d))
+  const neighbor_list = new NeighborList(cutoff, skin)
+
+  const compute = (structure: AnyStructure): PotentialResult => {
+    const types = site_types(structure, elements, `Tersoff`)
+    const neighbors = neighbor_list.update(structure)
+    const energies = new Array<number>(types.length).fill(0)
+    const accumulator = new ForceAccumulator(types.length)
+
+    neighbors.forEach((site_neighbors, center_idx) => {
+      const type_i = types[center_idx]
+      const bonds = site_neighbors.map(({ site_idx, distance, displacement }) => ({
+        site_idx,
+        distance,
+        displacement,
+        unit: displacement.map((val) => val / distance) as Vec3,
+      }))
+      for (const bond_j of bonds) {
+        const pair = table[type_i][types[bond_j.site_idx]][types[bond_j.site_idx]]
+        const [f_c, d_f_c] = cutoff_fn(bond_j.distance, pair)
+        if (f_c === 0) continue
+        const { repulsive_a, attractive_b, lambda1, lambda2, n, beta } = pair
+        const f_r = repulsive_a * Math.exp(-lambda1 * bond_j.distance)
+        const f_a = -attractive_b * Math.exp(-lambda2 * bond_j.distance)
+
+        // ζ_ij = Σ_k f_C(r_ik) g(θ_ijk) exp(λ3^m (r_ij - r_ik)^m) with its gradients
+        // w.r.t. d_ij (summed) and each d_ik
+        let zeta = 0
+        const d_zeta_j: Vec3 = [0, 0, 0]
+        const d_zeta_k: [typeof bond_j, Vec3][] = []
+        for (const bond_k of bonds) {
+          if (bond_k === bond_j) continue
+          const triplet = table[type_i][types[bond_j.site_idx]][types[bond_k.site_idx]]
+          const [f_c_k, d_f_c_k] = cutoff_fn(bond_k.distance, triplet)
+          if (f_c_k === 0) continue
+          const { m, gamma, lambda3, c, d, cos_theta0 } = triplet
+          const cos_theta =
+            bond_j.unit[0] * bond_k.unit[0] +
+            bond_j.unit[1] * bond_k.unit[1] +
+            bond_j.unit[2] * bond_k.unit[2]
+          const h_cos = cos_theta0 - cos_theta
+          const denom = d * d + h_cos * h_cos
+          const g_theta = gamma * (1 + (c * c) / (d * d) - (c * c) / denom)
+          const d_g_theta = (-2 * gamma * c * c * h_cos) / (denom * denom)
+          const r_diff = bond_j.distance - bond_k.distance
+          const expo = Math.exp(lambda3 ** m * r_diff ** m)
+          const d_expo = m * lambda3 ** m * r_diff ** (m - 1) * expo
+          zeta += f_c_k * g_theta * expo
+          for (let axis = 0; axis < 3; axis++) {
+            const [u_j, u_k] = [bond_j.unit[axis], bond_k.unit[axis]]
+            d_zeta_j[axis] +=
+              f_c_k *
+              (d_g_theta * expo * ((u_k - cos_theta * u_j) / bond_j.distance) +
+                g_theta * d_expo * u_j)
+          }
+          const grad_k = bond_k.unit.map((u_k, axis) => {
+            const d_cos = (bond_j.unit[axis] - cos_theta * u_k) / bond_k.distance
+            return (
+              d_f_c_k * g_theta * expo * u_k +
+              f_c_k * (d_g_theta * expo * d_cos - g_theta * d_expo * u_k)
+            )
+          }) as Vec3
+          d_zeta_k.push([bond_k, grad_k])
+        }
+
+        const beta_zeta_n = (beta * zeta) ** n
+        const bond_order = (1 + beta_zeta_n) ** (-1 / (2 * n))
+        // db/dζ, zero without other bonds
+        const d_bond_order =
+          zeta > 0 ? (-0.5 * bond_order * beta_zeta_n) / ((1 + beta_zeta_n) * zeta) : 0
+        const d_pair =
+          (d_f_c * (f_r + bond_order * f_a) -
+            f_c * (lambda1 * f_r + lambda2 * bond_order * f_a)) /
+          2
+        energies[center_idx] += (f_c * (f_r + bond_order * f_a)) / 2
+        // half of V_ij per directed bond, so half the gradients too
+        const zeta_scale = (f_c * f_a * d_bond_order) / 2
+        const grad_j = bond_j.unit.map(
+          (u_j, axis) => d_pair * u_j + zeta_scale * d_zeta_j[axis],
+        ) as Vec3
+        accumulator.add(center_idx, bond_j.site_idx, bond_j.displacement, grad_j)
+        for (const [bond_k, grad_k] of d_zeta_k) {
+          const grad = grad_k.map((val) => zeta_scale * val) as Vec3
+          accumulator.add(center_idx, bond_k.site_idx, bond_k.displacement, grad)
+        }
+      }
+    })
+    return accumulator.result(structure, energies)
+  }
+
+  return { cutoff, compute }
+}
@@ -6,6 +6,7 @@ import {
   pbc_dist,
 } from '$lib/math'
 import type { Crystal, Site } from '$lib/structure'
+import { selection_mask } from '$lib/structure/select'
 import type { Pbc } from '$lib/structure/pbc'
 import type { RdfOptions, RdfPattern } from './index'
 
@@ -102,11 +103,21 @@ export function calculate_rdf(structure: Crystal, options: RdfOptions = {}): Rdf
   const r = Array.from({ length: n_bins }, (_, idx) => (idx + 0.5) * bin_size)
   const g_r = Array(n_bins).fill(0)
 
-  // Centers stay in the original cell; neighbor_sites may include periodic images
-  const centers = structure.sites.filter((site) => has_species(site, center_species))
-  const neighbors = neighbor_sites.filter((site) => has_species(site, neighbor_species))
+  // Centers stay in the original cell; neighbor_sites may include periodic images, laid
+  // out image by image over all sites so idx % n_sites is the parent site
+  const n_sites = structure.sites.length
+  const center_mask = selection_mask(structure, options.center_selection)
+  const neighbor_mask = selection_mask(structure, options.neighbor_selection)
+  const centers = structure.sites.filter(
+    (site, idx) => center_mask[idx] && has_species(site, center_species),
+  )
+  const neighbors = neighbor_sites.filter(
+    (site, idx) => neighbor_mask[idx % n_sites] && has_species(site, neighbor_species),
+  )
   // Normalization density uses the original cell (not the image cloud)
-  const norm_neighbors = structure.sites.filter((site) => has_species(site, neighbor_species))
+  const norm_neighbors = structure.sites.filter(
+    (site, idx) => neighbor_mask[idx] && has_species(site, neighbor_species),
+  )
 
   const element_pair =
     center_species && neighbor_species
@@ -1,6 +1,7 @@
-import type { Pbc } from '$lib/structure'
+import type { AtomSelection, Pbc } from '$lib/structure'
 
 export * from './calc-rdf'
+export * from './structure-factor'
 export { default as RdfPlot } from './RdfPlot.svelte'
 
 export type RdfPattern = {
@@ -23,4 +24,21 @@ export interface RdfOptions {
   n_bins?: number
   pbc?: Pbc
   auto_expand?: boolean
+  // restrict centers/neighbors to a subset of sites (combined with the species filters),
+  // evaluated on the original cell so periodic images follow their parent site
+  center_selection?: AtomSelection
+  neighbor_selection?: AtomSelection
+}
+
+export type StructureFactor = {
+  q: number[] // Å⁻¹
+  s_q: number[]
+}
+
+export interface StructureFactorOptions {
+  q_max?: number // Å⁻¹ (default 10)
+  n_bins?: number // |q| bins to average reciprocal lattice vectors over (default 100)
+  // per-element scattering lengths, e.g. coherent neutron lengths (fm) or X-ray form
+  // factors at low q. Every atom scatters with weight 1 if omitted.
+  scattering_lengths?: Record<string, number>
 }
@@ -0,0 +1,127 @@
+// Static structure factor S(q) for comparison with neutron/X-ray diffraction of liquids and
+// amorphous solids, either by Fourier transform of g(r) or directly from positions as
+// |ρ(q)|² over the reciprocal lattice vectors the periodic cell supports
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import type { RdfPattern, StructureFactor, StructureFactorOptions } from './index'
+
+export type ReciprocalVector = {
+  hkl: Vec3
+  q_vec: Vec3 // Cartesian q = 2π (h b₁ + k b₂ + l b₃) in Å⁻¹
+  q_norm: number
+}
+
+// Reciprocal lattice vectors (with the 2π factor) with q_min < |q| <= q_max sorted by |q|,
+// always excluding q = 0
+export function reciprocal_lattice_vectors(
+  lattice: Matrix3x3,
+  q_max: number,
+  q_min = 0,
+): ReciprocalVector[] {
+  if (!(q_max > 0)) throw new Error(`q_max must be > 0, got ${q_max}`)
+  // rows of the cart-to-frac matrix are the reciprocal basis without 2π
+  const recip = math
+    .create_cart_to_frac_matrix(lattice)
+    .map((row) => math.scale(row, 2 * Math.PI)) as Matrix3x3
+  // h = q · aᵢ / 2π, so |h| <= q_max |aᵢ| / 2π
+  const [h_max, k_max, l_max] = lattice.map((vec) =>
+    Math.floor((q_max * Math.hypot(...vec)) / (2 * Math.PI) + 1e-9),
+  )
+  const vectors: ReciprocalVector[] = []
+  for (let hh = -h_max; hh <= h_max; hh++) {
+    for (let kk = -k_max; kk <= k_max; kk++) {
+      for (let ll = -l_max; ll <= l_max; ll++) {
+        const q_vec = [0, 1, 2].map(
+          (dim) => hh * recip[0][dim] + kk * recip[1][dim] + ll * recip[2][dim],
+        ) as Vec3
+        const q_norm = Math.hypot(...q_vec)
+        if (q_norm > Math.max(q_min, 0) && q_norm <= q_max) {
+          vectors.push({ hkl: [hh, kk, ll], q_vec, q_norm })
+        }
+      }
+    }
+  }
+  return vectors.sort((vec_1, vec_2) => vec_1.q_norm - vec_2.q_norm)
+}
+
+// Occupancy-weighted scattering length of each site (1 per atom by default)
+export const site_scattering_lengths = (
+  sites: Crystal[`sites`],
+  scattering_lengths?: Record<string, number>,
+): number[] =>
+  sites.map(({ species }) =>
+    species.reduce(
+      (sum, { element, occu }) =>
+        sum + occu * (scattering_lengths ? (scattering_lengths[element] ?? 0) : 1),
+      0,
+    ),
+  )
+
+// S(q) = |Σⱼ bⱼ exp(i q·rⱼ)|² / Σⱼ bⱼ² averaged over the reciprocal lattice vectors in
+// each |q| bin. Only q commensurate with the cell exist, so bins narrower than 2π / L
+// (L = cell length) come back empty and are dropped.
+export function calculate_structure_factor(
+  structure: Crystal,
+  options: StructureFactorOptions = {},
+): StructureFactor {
+  const { q_max = 10, n_bins = 100, scattering_lengths } = options
+  if (!Number.isInteger(n_bins) || n_bins < 1) {
+    throw new Error(`n_bins must be a positive integer, got ${n_bins}`)
+  }
+  const { sites, lattice } = structure
+  const weights = site_scattering_lengths(sites, scattering_lengths)
+  const norm = weights.reduce((sum, weight) => sum + weight ** 2, 0)
+  if (!(norm > 0)) throw new Error(`Structure has no scattering sites`)
+
+  const bin_size = q_max / n_bins
+  const [q_sums, s_sums, counts] = [0, 1, 2].map(() => Array<number>(n_bins).fill(0))
+  for (const { hkl, q_norm } of reciprocal_lattice_vectors(lattice.matrix, q_max)) {
+    // q · r = 2π hkl · abc
+    let [re, im] = [0, 0]
+    sites.forEach(({ abc }, site_idx) => {
+      const phase = 2 * Math.PI * (hkl[0] * abc[0] + hkl[1] * abc[1] + hkl[2] * abc[2])
+      re += weights[site_idx] * Math.cos(phase)
+      im += weights[site_idx] * Math.sin(phase)
+    })
+    const bin = Math.min(Math.floor(q_norm / bin_size), n_bins - 1)
+    q_sums[bin] += q_norm
+    s_sums[bin] += (re ** 2 + im ** 2) / norm
+    counts[bin]++
+  }
+  const bins = counts.flatMap((count, bin) => (count > 0 ? [bin] : []))
+  return {
+    q: bins.map((bin) => q_sums[bin] / counts[bin]),
+    s_q: bins.map((bin) => s_sums[bin] / counts[bin]),
+  }
+}
+
+// S(q) = 1 + 4πρ ∫ r² (g(r) − 1) sin(qr) / (qr) dr from an RDF with uniform bins, where
+// density is the number density (Å⁻³) g(r) was normalized with (e.g. n_sites / volume for
+// calculate_rdf of all sites). The Lorch window sin(πr/r_c) / (πr/r_c) damps the
+// ripples from truncating g(r) at the cutoff r_c (default on).
+export function structure_factor_from_rdf(
+  pattern: RdfPattern,
+  density: number,
+  options: { q_max?: number; n_q?: number; lorch?: boolean } = {},
+): StructureFactor {
+  const { q_max = 10, n_q = 200, lorch = true } = options
+  const { r, g_r } = pattern
+  if (r.length < 2) throw new Error(`Need at least 2 RDF bins, got ${r.length}`)
+  if (!(density > 0)) throw new Error(`density must be > 0, got ${density}`)
+  const bin_size = r[1] - r[0]
+  const r_cut = r[r.length - 1] + bin_size / 2
+  const integrand = r.map((rad, idx) => {
+    const arg = (Math.PI * rad) / r_cut
+    const window = lorch ? Math.sin(arg) / arg : 1
+    return rad * (g_r[idx] - 1) * window * bin_size
+  })
+  const q = Array.from({ length: n_q }, (_, idx) => ((idx + 1) * q_max) / n_q)
+  const s_q = q.map(
+    (q_val) =>
+      1 +
+      ((4 * Math.PI * density) / q_val) *
+        r.reduce((sum, rad, idx) => sum + integrand[idx] * Math.sin(q_val * rad), 0),
+  )
+  return { q, s_q }
+}
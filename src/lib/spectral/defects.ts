@@ -0,0 +1,174 @@
+// Self-consistent Fermi level and equilibrium defect/carrier concentrations from charge
+// neutrality, given defect formation energies and either an electronic DOS or effective masses
+import { K_B_EV } from '$lib/constants'
+import type { ElectronicDos } from './types'
+
+const K_B_SI = 1.380649e-23 // J/K
+const PLANCK = 6.62607015e-34 // J·s
+const ELECTRON_MASS = 9.1093837015e-31 // kg
+
+export interface DefectChargeState {
+  charge: number // in units of e, e.g. +1 for a singly ionized donor
+  formation_energy: number // eV at the Fermi level = VBM (fixed chemical potentials)
+  degeneracy?: number // spin/configurational degeneracy (default 1)
+}
+
+export interface Defect {
+  name: string
+  charge_states: DefectChargeState[]
+  sites_per_cell?: number // lattice sites the defect can occupy per cell (default 1)
+}
+
+export interface DefectEquilibriumOptions {
+  band_gap: number // eV
+  volume: number // Å³ of the cell that DOS and sites_per_cell refer to
+  // total DOS in states/eV per cell (spin channels summed) with the VBM at energy vbm
+  dos?: ElectronicDos
+  vbm?: number // eV on the DOS energy axis (default dos.efermi ?? 0)
+  // parabolic bands with Boltzmann statistics, used when no DOS is given
+  effective_masses?: { electron: number; hole: number } // in units of m_e
+  tolerance?: number // eV convergence of the Fermi level (default 1e-10)
+}
+
+export interface DefectConcentration {
+  name: string
+  charge: number
+  concentration: number // cm⁻³
+}
+
+export interface DefectEquilibrium {
+  temperature: number // K
+  fermi_level: number // eV above the VBM
+  electrons: number // cm⁻³ in the conduction band
+  holes: number // cm⁻³ in the valence band
+  defects: DefectConcentration[]
+}
+
+// Fermi-Dirac occupation, overflow-safe for large |x| = |E − E_F| / k_B·T
+const fermi_dirac = (x: number): number =>
+  x > 0 ? Math.exp(-x) / (1 + Math.exp(-x)) : 1 / (1 + Math.exp(x))
+
+// Trapezoidal integral of densities·weight over the points where include(energy) holds
+function integrate_dos(
+  energies: number[],
+  densities: number[],
+  include: (energy: number) => boolean,
+  weight: (energy: number) => number,
+): number {
+  let total = 0
+  for (let idx = 1; idx < energies.length; idx++) {
+    const [e_1, e_2] = [energies[idx - 1], energies[idx]]
+    if (!include(e_1) || !include(e_2)) continue
+    const [f_1, f_2] = [densities[idx - 1] * weight(e_1), densities[idx] * weight(e_2)]
+    total += 0.5 * (e_2 - e_1) * (f_1 + f_2)
+  }
+  return total
+}
+
+// Carrier densities (cm⁻³) as a function of the Fermi level (eV above VBM)
+function make_carrier_densities(
+  temperature: number,
+  options: DefectEquilibriumOptions,
+): (fermi_level: number) => { electrons: number; holes: number } {
+  const { band_gap, volume, dos, effective_masses } = options
+  const k_t = K_B_EV * temperature
+  if (dos) {
+    const vbm = options.vbm ?? dos.efermi ?? 0
+    const energies = dos.energies.map((energy) => energy - vbm)
+    const { densities, spin_down_densities: down } = dos
+    const total = down ? densities.map((dens, idx) => dens + (down[idx] ?? 0)) : densities
+    const per_cm3 = 1e24 / volume // per cell → cm⁻³
+    const eps = 1e-9
+    return (fermi_level) => ({
+      electrons:
+        per_cm3 *
+        integrate_dos(
+          energies,
+          total,
+          (energy) => energy >= band_gap - eps,
+          (energy) => fermi_dirac((energy - fermi_level) / k_t),
+        ),
+      holes:
+        per_cm3 *
+        integrate_dos(
+          energies,
+          total,
+          (energy) => energy <= eps,
+          (energy) => fermi_dirac((fermi_level - energy) / k_t),
+        ),
+    })
+  }
+  if (!effective_masses) {
+    throw new Error(`Either dos or effective_masses is needed for carrier densities`)
+  }
+  // effective density of states N = 2·(2π·m*·k_B·T / h²)^{3/2}, m⁻³ → cm⁻³
+  const eff_dos = (mass: number) => {
+    const thermal = (2 * Math.PI * mass * ELECTRON_MASS * K_B_SI * temperature) / PLANCK ** 2
+    return 2 * thermal ** 1.5 * 1e-6
+  }
+  const [n_c, n_v] = [eff_dos(effective_masses.electron), eff_dos(effective_masses.hole)]
+  return (fermi_level) => ({
+    electrons: n_c * Math.exp(-(band_gap - fermi_level) / k_t),
+    holes: n_v * Math.exp(-fermi_level / k_t),
+  })
+}
+
+// Equilibrium Fermi level at one temperature by bisection on the net charge
+// p − n + Σ q·c_q (monotonically decreasing in E_F), with defect concentrations
+// c_q = g_q · N_sites · exp(−E_f(q, E_F) / k_B·T) and E_f(q, E_F) = E_f(q, 0) + q·E_F
+export function solve_defect_equilibrium(
+  defects: Defect[],
+  temperature: number,
+  options: DefectEquilibriumOptions,
+): DefectEquilibrium {
+  const { band_gap, volume, tolerance = 1e-10 } = options
+  if (!(temperature > 0)) throw new Error(`Temperature must be > 0 K, got ${temperature}`)
+  if (!(volume > 0)) throw new Error(`Cell volume must be > 0 Å³, got ${volume}`)
+  const k_t = K_B_EV * temperature
+  const carriers = make_carrier_densities(temperature, options)
+  const site_density = 1e24 / volume // cm⁻³ per site per cell
+
+  const concentrations = (fermi_level: number): DefectConcentration[] =>
+    defects.flatMap(({ name, charge_states, sites_per_cell = 1 }) =>
+      charge_states.map(({ charge, formation_energy, degeneracy = 1 }) => ({
+        name,
+        charge,
+        concentration:
+          degeneracy *
+          sites_per_cell *
+          site_density *
+          Math.exp(-(formation_energy + charge * fermi_level) / k_t),
+      })),
+    )
+  const net_charge = (fermi_level: number): number => {
+    const { electrons, holes } = carriers(fermi_level)
+    return concentrations(fermi_level).reduce(
+      (sum, { charge, concentration }) => sum + charge * concentration,
+      holes - electrons,
+    )
+  }
+
+  // widen the bracket until the net charge changes sign
+  let [lower, upper] = [-1, band_gap + 1]
+  for (let step = 0; net_charge(lower) < 0 || net_charge(upper) > 0; step++) {
+    if (step > 50) throw new Error(`Could not bracket the equilibrium Fermi level`)
+    lower -= 1
+    upper += 1
+  }
+  while (upper - lower > tolerance) {
+    const mid = 0.5 * (lower + upper)
+    if (net_charge(mid) > 0) lower = mid
+    else upper = mid
+  }
+  const fermi_level = 0.5 * (lower + upper)
+  const defect_concentrations = concentrations(fermi_level)
+  return { temperature, fermi_level, ...carriers(fermi_level), defects: defect_concentrations }
+}
+
+// solve_defect_equilibrium at each temperature, e.g. for concentration vs T plots
+export const defect_equilibrium_vs_temperature = (
+  defects: Defect[],
+  temperatures: number[],
+  options: DefectEquilibriumOptions,
+): DefectEquilibrium[] =>
+  temperatures.map((temperature) => solve_defect_equilibrium(defects, temperature, options))
@@ -0,0 +1,262 @@
+// DOS parsing (VASP DOSCAR, Quantum ESPRESSO dos.x output), re-smearing and descriptors:
+// band gap from the DOS and band moments such as the d-band center used for surface reactivity
+import { apply_gaussian_smearing } from './helpers'
+import type { ElectronicDos } from './types'
+
+// VASP orbital order for LORBIT = 10 (l-decomposed) and 11 (lm-decomposed) projections
+export const L_ORBITALS = [`s`, `p`, `d`, `f`] as const
+export const LM_ORBITALS = [
+  `s`,
+  `py`,
+  `pz`,
+  `px`,
+  `dxy`,
+  `dyz`,
+  `dz2`,
+  `dxz`,
+  `dx2`,
+  `f_3`,
+  `f_2`,
+  `f_1`,
+  `f0`,
+  `f1`,
+  `f2`,
+  `f3`,
+] as const
+
+export interface DoscarData {
+  efermi: number
+  total: ElectronicDos
+  integrated: number[] // integrated total DOS (spin up, or total without spin polarization)
+  integrated_down?: number[]
+  pdos: Record<string, ElectronicDos>[] // per site, keyed by orbital (empty without LORBIT)
+}
+
+const parse_numbers = (line: string | undefined): number[] =>
+  (line ?? ``).trim().split(/\s+/).filter(Boolean).map(Number)
+
+const orbital_names = (n_orbitals: number): string[] => {
+  if (n_orbitals === 3 || n_orbitals === 4) return L_ORBITALS.slice(0, n_orbitals)
+  if (n_orbitals === 9 || n_orbitals === 16) return LM_ORBITALS.slice(0, n_orbitals)
+  return Array.from({ length: n_orbitals }, (_, idx) => `orbital_${idx}`)
+}
+
+const electronic_dos = (
+  energies: number[],
+  densities: number[],
+  efermi: number,
+  spin_down_densities?: number[],
+): ElectronicDos => ({
+  type: `electronic`,
+  energies,
+  densities,
+  ...(spin_down_densities ? { spin_down_densities } : {}),
+  spin_polarized: spin_down_densities !== undefined,
+  efermi,
+})
+
+// Parse a VASP DOSCAR: total DOS plus site- and orbital-projected DOS when written
+// (LORBIT ≥ 10). Non-collinear projections keep only the total (not the m_x,y,z) channel.
+export function parse_doscar(content: string): DoscarData {
+  const lines = content.split(/\r?\n/)
+  const [n_ions, , , ncdij] = parse_numbers(lines[0])
+  const [, , nedos, efermi] = parse_numbers(lines[5])
+  if (!Number.isInteger(nedos) || nedos < 1 || !Number.isFinite(efermi)) {
+    throw new Error(`Invalid DOSCAR header: expected EMAX EMIN NEDOS EFERMI on line 6`)
+  }
+  const read_block = (start: number): number[][] => {
+    const rows = lines.slice(start, start + nedos).map(parse_numbers)
+    if (rows.length < nedos || rows.some((row) => row.length < 2)) {
+      throw new Error(`Truncated DOSCAR: expected ${nedos} rows from line ${start + 1}`)
+    }
+    return rows
+  }
+
+  const total_rows = read_block(6)
+  const energies = total_rows.map((row) => row[0])
+  const is_spin = total_rows[0].length >= 5
+  const column = (rows: number[][], col: number) => rows.map((row) => row[col])
+  const total = is_spin
+    ? electronic_dos(energies, column(total_rows, 1), efermi, column(total_rows, 2))
+    : electronic_dos(energies, column(total_rows, 1), efermi)
+  const integrated = column(total_rows, is_spin ? 3 : 2)
+  const integrated_down = is_spin ? column(total_rows, 4) : undefined
+
+  const pdos: Record<string, ElectronicDos>[] = []
+  const per_orbital = is_spin ? 2 : ncdij === 4 ? 4 : 1
+  for (let ion = 0; ion < n_ions; ion++) {
+    const start = 6 + (ion + 1) * (nedos + 1)
+    if (!lines[start]?.trim()) break // no projections written
+    const rows = read_block(start)
+    const names = orbital_names((rows[0].length - 1) / per_orbital)
+    const site: Record<string, ElectronicDos> = {}
+    names.forEach((name, orb_idx) => {
+      const col = 1 + orb_idx * per_orbital
+      site[name] = is_spin
+        ? electronic_dos(energies, column(rows, col), efermi, column(rows, col + 1))
+        : electronic_dos(energies, column(rows, col), efermi)
+    })
+    pdos.push(site)
+  }
+  return { efermi, total, integrated, ...(integrated_down && { integrated_down }), pdos }
+}
+
+// Parse Quantum ESPRESSO dos.x output (E, dos or dos_up dos_dw, integrated DOS). The
+// Fermi energy is read from the header comment when present.
+export function parse_qe_dos(content: string): ElectronicDos {
+  const lines = content.split(/\r?\n/)
+  const header = lines.find((line) => line.trim().startsWith(`#`)) ?? ``
+  const efermi_match = /EFermi\s*=\s*(-?[\d.]+(?:[eE][-+]?\d+)?)/.exec(header)
+  const rows = lines
+    .filter((line) => line.trim() && !line.trim().startsWith(`#`))
+    .map(parse_numbers)
+  if (rows.length === 0 || rows.some((row) => row.length < 3 || row.some(Number.isNaN))) {
+    throw new Error(`Invalid dos.x file: expected rows of E, DOS and integrated DOS`)
+  }
+  const efermi = efermi_match ? Number(efermi_match[1]) : 0
+  const energies = rows.map((row) => row[0])
+  const densities = rows.map((row) => row[1])
+  const is_spin = rows[0].length >= 4
+  return is_spin
+    ? electronic_dos(energies, densities, efermi, rows.map((row) => row[2]))
+    : electronic_dos(energies, densities, efermi)
+}
+
+export type SmearingMethod = `gaussian` | `methfessel_paxton`
+
+// Methfessel-Paxton delta approximation of order N:
+// δ_N(x) = exp(−x²) Σ_{n=0..N} A_n H_2n(x), A_n = (−1)ⁿ / (n! 4ⁿ √π)
+function methfessel_paxton_delta(x_val: number, order: number): number {
+  let [h_prev, h_curr] = [1, 2 * x_val] // Hermite H_0, H_1
+  let [sum, coeff] = [1 / Math.sqrt(Math.PI), 1 / Math.sqrt(Math.PI)]
+  for (let n_val = 1; n_val <= order; n_val++) {
+    // advance to H_2n via two recursion steps H_{k+1} = 2x H_k − 2k H_{k−1}
+    for (const k_val of [2 * n_val - 1, 2 * n_val]) {
+      ;[h_prev, h_curr] = [h_curr, 2 * x_val * h_curr - 2 * k_val * h_prev]
+    }
+    coeff *= -1 / (4 * n_val)
+    sum += coeff * h_prev
+  }
+  return Math.exp(-x_val * x_val) * sum
+}
+
+// Re-smear DOS densities. gaussian: sigma is the standard deviation and the sum of
+// densities is preserved (same as the plot smearing). methfessel_paxton: sigma is the
+// width parameter (VASP SIGMA with ISMEAR = N) and the kernel may go negative.
+export function smear_dos(
+  energies: number[],
+  densities: number[],
+  sigma: number,
+  method: SmearingMethod = `gaussian`,
+  order = 1,
+): number[] {
+  if (!(sigma > 0)) return densities
+  if (method === `gaussian`) return apply_gaussian_smearing(energies, densities, sigma)
+  const n_pts = energies.length
+  const widths = energies.map((_, idx) => {
+    const lower = energies[Math.max(idx - 1, 0)]
+    const upper = energies[Math.min(idx + 1, n_pts - 1)]
+    return (upper - lower) / (idx === 0 || idx === n_pts - 1 ? 1 : 2)
+  })
+  const cutoff = 6 * sigma
+  return energies.map((energy) => {
+    let total = 0
+    for (let idx = 0; idx < n_pts; idx++) {
+      const delta = energy - energies[idx]
+      if (Math.abs(delta) > cutoff) continue
+      const kernel = methfessel_paxton_delta(delta / sigma, order) / sigma
+      total += densities[idx] * widths[idx] * kernel
+    }
+    return total
+  })
+}
+
+export interface DosBandGap {
+  band_gap: number // eV, 0 for metals
+  vbm: number // eV
+  cbm: number // eV
+  is_metal: boolean
+}
+
+// Band edges from the total DOS (spin channels summed): VBM is the highest energy ≤ E_F
+// and CBM the lowest energy ≥ E_F with DOS above tolerance (states/eV). Without any
+// gridpoint of vanishing DOS between them the system is metallic.
+export function dos_band_gap(
+  dos: ElectronicDos,
+  options: { tolerance?: number; efermi?: number } = {},
+): DosBandGap {
+  const { tolerance = 1e-3 } = options
+  const efermi = options.efermi ?? dos.efermi ?? 0
+  const { energies, densities, spin_down_densities: down } = dos
+  const total = densities.map((dens, idx) => dens + (down?.[idx] ?? 0))
+  const filled = total.map((dens) => dens > tolerance)
+  const vbm_idx = energies.findLastIndex((energy, idx) => energy <= efermi && filled[idx])
+  const cbm_idx = energies.findIndex((energy, idx) => energy >= efermi && filled[idx])
+  if (vbm_idx < 0 || cbm_idx < 0) {
+    throw new Error(`DOS has no states above ${tolerance} on both sides of E_F = ${efermi}`)
+  }
+  const [vbm, cbm] = [energies[vbm_idx], energies[cbm_idx]]
+  const is_metal = cbm_idx - vbm_idx <= 1
+  return { band_gap: is_metal ? 0 : cbm - vbm, vbm, cbm, is_metal }
+}
+
+export interface BandMoments {
+  center: number // eV, first moment ∫Eρ / ∫ρ
+  width: number // eV, square root of the second central moment
+  filling: number | null // fraction of states below E_F (null without E_F)
+}
+
+export interface BandMomentOptions {
+  e_min?: number // eV, integration window (default: whole energy range)
+  e_max?: number
+  efermi?: number // for the filling
+}
+
+// Moments of a (projected) DOS over an energy window by trapezoidal integration
+export function band_moments(
+  energies: number[],
+  densities: number[],
+  options: BandMomentOptions = {},
+): BandMoments {
+  const { e_min = -Infinity, e_max = Infinity, efermi } = options
+  const integrate = (weight: (energy: number) => number, upper = e_max): number => {
+    let total = 0
+    for (let idx = 1; idx < energies.length; idx++) {
+      const [e_1, e_2] = [energies[idx - 1], energies[idx]]
+      if (e_1 < e_min || e_2 > upper) continue
+      const [f_1, f_2] = [densities[idx - 1] * weight(e_1), densities[idx] * weight(e_2)]
+      total += 0.5 * (e_2 - e_1) * (f_1 + f_2)
+    }
+    return total
+  }
+  const norm = integrate(() => 1)
+  if (!(norm > 0)) throw new Error(`No states in the energy window [${e_min}, ${e_max}]`)
+  const center = integrate((energy) => energy) / norm
+  const variance = integrate((energy) => (energy - center) ** 2) / norm
+  const filling =
+    efermi === undefined ? null : integrate(() => 1, Math.min(efermi, e_max)) / norm
+  return { center, width: Math.sqrt(Math.max(variance, 0)), filling }
+}
+
+// Band moments of the l-projected DOS (e.g. d-band center) summed over sites and spins,
+// with energies relative to E_F. Sums the lm-resolved channels when LORBIT = 11.
+export function projected_band_moments(
+  data: DoscarData,
+  site_indices: number[],
+  angular: (typeof L_ORBITALS)[number] = `d`,
+  options: Omit<BandMomentOptions, `efermi`> = {},
+): BandMoments {
+  const energies = data.total.energies.map((energy) => energy - data.efermi)
+  const summed = energies.map(() => 0)
+  for (const site_idx of site_indices) {
+    const site = data.pdos[site_idx]
+    if (!site) throw new Error(`No projected DOS for site ${site_idx}`)
+    for (const [name, dos] of Object.entries(site)) {
+      if (!name.startsWith(angular)) continue
+      dos.densities.forEach((dens, idx) => {
+        summed[idx] += dens + (dos.spin_down_densities?.[idx] ?? 0)
+      })
+    }
+  }
+  return band_moments(energies, summed, { ...options, efermi: 0 })
+}
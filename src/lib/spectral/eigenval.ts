@@ -0,0 +1,200 @@
+// VASP EIGENVAL parsing and basic electronic descriptors for screening: band edges,
+// band gap (direct/indirect) and parabolic effective masses around the band extrema
+import { reciprocal_lattice } from '$lib/brillouin/compute'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+
+// ħ²/(2·m_e) in eV·Å², so that m*/m_e = HBAR2_OVER_2ME / c for E = c·k² (k in 1/Å)
+export const HBAR2_OVER_2ME = 3.80998212
+
+export interface EigenvalData {
+  n_electrons: number
+  n_kpoints: number
+  n_bands: number
+  n_spins: 1 | 2
+  kpoints: Vec3[] // fractional reciprocal coordinates
+  weights: number[]
+  eigenvalues: number[][][] // [spin][kpoint][band], eV
+  occupations: number[][][] | null // [spin][kpoint][band], null for pre-5.4 files without them
+}
+
+// Parse the text of a VASP EIGENVAL file (ISPIN = 1 or 2, with or without occupations)
+export function parse_eigenval(content: string): EigenvalData {
+  const lines = content.split(/\r?\n/)
+  const numbers = (line: string | undefined) =>
+    (line ?? ``).trim().split(/\s+/).filter(Boolean).map(Number)
+  const n_spins = numbers(lines[0])[3] === 2 ? 2 : 1
+  const [n_electrons, n_kpoints, n_bands] = numbers(lines[5])
+  if (![n_electrons, n_kpoints, n_bands].every(Number.isFinite) || !(n_kpoints > 0)) {
+    throw new Error(`Invalid EIGENVAL header: expected NELECT NKPTS NBANDS on line 6`)
+  }
+
+  // after the 6 header lines, each k-point block is a k-point line + n_bands band lines
+  const data_lines = lines.slice(6).filter((line) => line.trim())
+  if (data_lines.length < n_kpoints * (n_bands + 1)) {
+    const [expected, found] = [n_kpoints * (n_bands + 1), data_lines.length]
+    throw new Error(`Truncated EIGENVAL: expected ${expected} data lines, got ${found}`)
+  }
+  const kpoints: Vec3[] = []
+  const weights: number[] = []
+  const eigenvalues: number[][][] = Array.from({ length: n_spins }, () => [])
+  const occupations: number[][][] = Array.from({ length: n_spins }, () => [])
+  let has_occupations = true
+  for (let kpt_idx = 0; kpt_idx < n_kpoints; kpt_idx++) {
+    const block = kpt_idx * (n_bands + 1)
+    const [k_x, k_y, k_z, weight] = numbers(data_lines[block])
+    kpoints.push([k_x, k_y, k_z])
+    weights.push(weight)
+    for (let spin = 0; spin < n_spins; spin++) {
+      eigenvalues[spin].push([])
+      occupations[spin].push([])
+    }
+    for (let band = 0; band < n_bands; band++) {
+      // columns: index, energy per spin, then occupation per spin (VASP ≥ 5.4)
+      const values = numbers(data_lines[block + 1 + band])
+      if (values.length < 1 + 2 * n_spins) has_occupations = false
+      for (let spin = 0; spin < n_spins; spin++) {
+        eigenvalues[spin][kpt_idx].push(values[1 + spin])
+        occupations[spin][kpt_idx].push(values[1 + n_spins + spin])
+      }
+    }
+  }
+  return {
+    n_electrons,
+    n_kpoints,
+    n_bands,
+    n_spins,
+    kpoints,
+    weights,
+    eigenvalues,
+    occupations: has_occupations ? occupations : null,
+  }
+}
+
+export interface BandState {
+  energy: number // eV
+  spin: number
+  kpoint_idx: number
+  band_idx: number
+}
+
+export interface BandEdges {
+  vbm: BandState
+  cbm: BandState
+  band_gap: number // eV, 0 for metals
+  is_direct: boolean // VBM and CBM at the same k-point
+  is_metal: boolean // some band is partially occupied across k-points (or bands overlap)
+}
+
+// VBM = highest occupied, CBM = lowest unoccupied state. States more than half
+// occupied count as occupied; without occupations the lowest n_electrons / 2 bands
+// (per spin channel) are filled at every k-point.
+export function find_band_edges(data: EigenvalData): BandEdges {
+  const { eigenvalues, occupations, n_electrons } = data
+  // VASP writes per-state occupations in [0, 1], other writers may use [0, 2]
+  const occ_scale = occupations?.flat(2).some((occ) => occ > 1.001) ? 2 : 1
+  const is_occupied = (spin: number, kpt_idx: number, band_idx: number) =>
+    occupations
+      ? occupations[spin][kpt_idx][band_idx] / occ_scale > 0.5
+      : band_idx < n_electrons / 2
+
+  let vbm: BandState | null = null
+  let cbm: BandState | null = null
+  let partial_band = false
+  for (const [spin, spin_eigs] of eigenvalues.entries()) {
+    const n_bands = spin_eigs[0]?.length ?? 0
+    for (let band_idx = 0; band_idx < n_bands; band_idx++) {
+      let [n_occ, n_empty] = [0, 0]
+      for (const [kpt_idx, kpt_eigs] of spin_eigs.entries()) {
+        const state = { energy: kpt_eigs[band_idx], spin, kpoint_idx: kpt_idx, band_idx }
+        if (is_occupied(spin, kpt_idx, band_idx)) {
+          n_occ++
+          if (!vbm || state.energy > vbm.energy) vbm = state
+        } else {
+          n_empty++
+          if (!cbm || state.energy < cbm.energy) cbm = state
+        }
+      }
+      if (n_occ > 0 && n_empty > 0) partial_band = true
+    }
+  }
+  if (!vbm || !cbm) throw new Error(`Need both occupied and empty states for band edges`)
+  const is_metal = partial_band || cbm.energy <= vbm.energy
+  return {
+    vbm,
+    cbm,
+    band_gap: is_metal ? 0 : cbm.energy - vbm.energy,
+    is_direct: vbm.kpoint_idx === cbm.kpoint_idx,
+    is_metal,
+  }
+}
+
+export interface EffectiveMass {
+  direction: Vec3 // unit Cartesian direction in reciprocal space
+  mass: number // m*/m_e, negative for hole-like (downward curved) bands
+  n_points: number // k-points used in the fit, including the extremum
+}
+
+export interface EffectiveMassOptions {
+  // fractional reciprocal directions to probe (default: every direction in which at
+  // least two k-points lie within max_distance of the extremum, e.g. band path lines)
+  directions?: Vec3[]
+  max_distance?: number // 1/Å (incl. 2π) from the extremum (default 0.1)
+}
+
+// Effective masses at a band extremum from least-squares parabolas E = a + b·s + c·s²
+// through the k-points on lines through the extremum: m* = ħ² / (m_e · d²E/dk²)
+export function calc_effective_masses(
+  data: EigenvalData,
+  extremum: BandState,
+  lattice: Matrix3x3,
+  options: EffectiveMassOptions = {},
+): EffectiveMass[] {
+  const { max_distance = 0.1 } = options
+  const frac_to_cart = math.create_frac_to_cart(reciprocal_lattice(lattice))
+  const { spin, band_idx, kpoint_idx } = extremum
+  const origin = frac_to_cart(data.kpoints[kpoint_idx])
+  const energies = data.eigenvalues[spin].map((kpt_eigs) => kpt_eigs[band_idx])
+  const nearby = data.kpoints.flatMap((kpt, kpt_idx) => {
+    const delta = math.subtract(frac_to_cart(kpt), origin)
+    const dist = Math.hypot(...delta)
+    return dist > 1e-8 && dist <= max_distance ? [{ delta, dist, kpt_idx }] : []
+  })
+  const parallel = (vec_1: Vec3, vec_2: Vec3) => Math.abs(math.dot(vec_1, vec_2)) > 1 - 1e-6
+
+  let directions: Vec3[]
+  if (options.directions) {
+    directions = options.directions.map((dir) => math.normalize_vec(frac_to_cart(dir)))
+  } else {
+    directions = []
+    for (const { delta, dist } of nearby) {
+      const unit = math.scale(delta, 1 / dist)
+      const canonical = unit.find((val) => Math.abs(val) > 1e-8) ?? 1
+      const dir = math.scale(unit, Math.sign(canonical))
+      if (!directions.some((known) => parallel(known, dir))) directions.push(dir)
+    }
+  }
+
+  return directions.flatMap((direction) => {
+    const points = nearby
+      .filter(({ delta, dist }) => parallel(math.scale(delta, 1 / dist), direction))
+      .map(({ delta, kpt_idx }) => [math.dot(delta, direction), energies[kpt_idx]])
+    points.push([0, energies[kpoint_idx]])
+    if (points.length < 3) return []
+    // normal equations for the quadratic fit in (1, s, s²)
+    const moments = [0, 1, 2, 3, 4].map((power) =>
+      points.reduce((sum, [dist]) => sum + dist ** power, 0),
+    )
+    const rhs = [0, 1, 2].map((power) =>
+      points.reduce((sum, [dist, energy]) => sum + dist ** power * energy, 0),
+    ) as Vec3
+    const normal: Matrix3x3 = [
+      [moments[0], moments[1], moments[2]],
+      [moments[1], moments[2], moments[3]],
+      [moments[2], moments[3], moments[4]],
+    ]
+    if (Math.abs(math.det_3x3(normal)) < 1e-12) return []
+    const [, , curvature] = math.mat3x3_vec3_multiply(math.matrix_inverse_3x3(normal), rhs)
+    return [{ direction, mass: HBAR2_OVER_2ME / curvature, n_points: points.length }]
+  })
+}
@@ -4,5 +4,9 @@ export { default as Bands } from './Bands.svelte'
 export { default as BandsAndDos } from './BandsAndDos.svelte'
 export { default as BrillouinBandsDos } from './BrillouinBandsDos.svelte'
 export { default as Dos } from './Dos.svelte'
+export * from './defects'
+export * from './dos-analysis'
+export * from './eigenval'
 export * from './helpers'
+export * from './transport'
 export type * from './types'
@@ -0,0 +1,494 @@
+// Boltzmann transport from band energies on a k-grid in the spirit of BoltzTraP (Madsen
+// & Singh, Comput. Phys. Commun. 175, 67, 2006): bands are interpolated by smoothed
+// Fourier (star function) expansions, evaluated with group velocities on a dense grid
+// and integrated into Onsager coefficients (conductivity over relaxation time, Seebeck)
+// in the constant relaxation-time approximation. Heavyweight, import it only if needed.
+import { K_B_EV } from '$lib/constants'
+import type { Matrix3x3, Vec2, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { EigenvalData } from './eigenval'
+
+const HBAR_EV_S = 6.582119569e-16 // eV·s
+const E_CHARGE = 1.602176634e-19 // C
+// roughness ρ(R) = (1 − C1 (R/R_min)²)² + C2 (R/R_min)⁶ of Pickett, Krakauer & Allen
+const [ROUGH_C1, ROUGH_C2] = [0.75, 0.75]
+const IDENTITY: Matrix3x3 = [
+  [1, 0, 0],
+  [0, 1, 0],
+  [0, 0, 1],
+]
+
+export interface BandInterpolationOptions {
+  // integer rotations acting on fractional direct-lattice coordinates (e.g. the rotation
+  // parts of the space-group operations), time reversal is always added. Without them
+  // only k and −k are equivalent, so the k-points must cover the full Brillouin zone.
+  rotations?: Matrix3x3[]
+  star_factor?: number // star functions per inequivalent k-point (default 5)
+  energy_range?: Vec2 // eV, only bands overlapping it are interpolated (default all)
+}
+
+export interface BandInterpolation {
+  lattice: Matrix3x3
+  stars: Vec3[][] // orbits of integer lattice vectors R, shortest first
+  coefficients: number[][][] // [spin][band][star], E(k) = Σ_m c_m S_m(k)
+  band_indices: number[][] // [spin][band] index into the original bands
+  n_spins: 1 | 2
+  n_electrons: number // valence electrons in the interpolated bands
+}
+
+// Rotations plus their products with inversion (time reversal)
+function symmetry_operations(rotations?: Matrix3x3[]): Matrix3x3[] {
+  const ops = (rotations?.length ? rotations : [IDENTITY]).map(
+    (rot) => rot.map((row) => row.map(Math.round)) as Matrix3x3,
+  )
+  return [...ops, ...ops.map((rot) => rot.map((row) => row.map((val) => -val)) as Matrix3x3)]
+}
+
+// Indices of symmetry-inequivalent k-points, k' ≡ ±Wᵀk (mod 1) being equivalent since
+// star functions satisfy S(Wᵀk) = S(k)
+function inequivalent_kpoints(kpoints: readonly Vec3[], ops: Matrix3x3[]): number[] {
+  const wrap = (val: number) => (((Math.round(val * 1e6) % 1e6) + 1e6) % 1e6) / 1e6
+  const seen = new Set<string>()
+  const kept: number[] = []
+  kpoints.forEach((kpt, idx) => {
+    const images = ops.map((op) =>
+      math.mat3x3_vec3_multiply(math.transpose_3x3_matrix(op), kpt).map(wrap).join(),
+    )
+    if (images.some((key) => seen.has(key))) return
+    images.forEach((key) => seen.add(key))
+    kept.push(idx)
+  })
+  return kept
+}
+
+// Shortest n_stars orbits of lattice vectors under the operations, R = 0 first
+function lattice_stars(lattice: Matrix3x3, ops: Matrix3x3[], n_stars: number): Vec3[][] {
+  const to_cart = math.create_frac_to_cart(lattice)
+  const length = (vec: Vec3) => Math.hypot(...to_cart(vec))
+  const volume = Math.abs(math.det_3x3(lattice))
+  // sphere holding about n_stars full orbits, grown until enough stars fit
+  let radius = Math.cbrt((3 * volume * n_stars * ops.length) / (4 * Math.PI))
+  for (;;) {
+    const [b_1, b_2, b_3] = math.frac_cutoff_per_axis(lattice, radius).map(Math.ceil)
+    const points: { vec: Vec3; len: number }[] = []
+    for (let idx = -b_1; idx <= b_1; idx++) {
+      for (let jdx = -b_2; jdx <= b_2; jdx++) {
+        for (let kdx = -b_3; kdx <= b_3; kdx++) {
+          const vec: Vec3 = [idx, jdx, kdx]
+          const len = length(vec)
+          if (len <= radius) points.push({ vec, len })
+        }
+      }
+    }
+    points.sort((pt_1, pt_2) => pt_1.len - pt_2.len)
+    const seen = new Set<string>()
+    const stars: Vec3[][] = []
+    for (const { vec, len } of points) {
+      if (seen.has(vec.join())) continue
+      const orbit = new Map<string, Vec3>()
+      for (const op of ops) {
+        const image = math.mat3x3_vec3_multiply(op, vec)
+        if (Math.abs(length(image) - len) > 1e-6 * Math.max(1, len)) {
+          throw new Error(`Rotations do not preserve the lattice metric`)
+        }
+        orbit.set(image.join(), image)
+      }
+      for (const key of orbit.keys()) seen.add(key)
+      stars.push([...orbit.values()])
+      if (stars.length === n_stars) return stars
+    }
+    radius *= 1.3
+  }
+}
+
+// S_m(k) = 1/n_m Σ_{R ∈ m} cos(2π k·R)
+const star_functions = (stars: Vec3[][], kpt: Vec3): number[] =>
+  stars.map(
+    (star) =>
+      star.reduce((sum, vec) => sum + Math.cos(2 * Math.PI * math.dot(kpt, vec)), 0) /
+      star.length,
+  )
+
+// Lower-triangular L with L Lᵀ = matrix for a symmetric positive-definite matrix
+function cholesky(matrix: number[][]): number[][] {
+  const size = matrix.length
+  const lower = matrix.map(() => Array<number>(size).fill(0))
+  for (let row = 0; row < size; row++) {
+    for (let col = 0; col <= row; col++) {
+      let sum = matrix[row][col]
+      for (let kdx = 0; kdx < col; kdx++) sum -= lower[row][kdx] * lower[col][kdx]
+      if (row !== col) lower[row][col] = sum / lower[col][col]
+      else if (sum > 0) lower[row][row] = Math.sqrt(sum)
+      else {
+        throw new Error(
+          `Singular interpolation system, increase star_factor or remove duplicate k-points`,
+        )
+      }
+    }
+  }
+  return lower
+}
+
+function cholesky_solve(lower: number[][], rhs: number[]): number[] {
+  const size = rhs.length
+  const fwd = [...rhs]
+  for (let row = 0; row < size; row++) {
+    for (let col = 0; col < row; col++) fwd[row] -= lower[row][col] * fwd[col]
+    fwd[row] /= lower[row][row]
+  }
+  for (let row = size - 1; row >= 0; row--) {
+    for (let col = row + 1; col < size; col++) fwd[row] -= lower[col][row] * fwd[col]
+    fwd[row] /= lower[row][row]
+  }
+  return fwd
+}
+
+// Smoothed Fourier interpolation through the band energies at the symmetry-inequivalent
+// k-points: among all star expansions passing exactly through the data, the one with the
+// smallest roughness Σ ρ_m |c_m|² (BoltzTraP with lpfac = star_factor)
+export function interpolate_bands(
+  data: EigenvalData,
+  lattice: Matrix3x3,
+  options: BandInterpolationOptions = {},
+): BandInterpolation {
+  const { star_factor = 5, energy_range = [-Infinity, Infinity] } = options
+  const ops = symmetry_operations(options.rotations)
+  const kept = inequivalent_kpoints(data.kpoints, ops)
+  const n_kpts = kept.length
+  if (n_kpts < 2) throw new Error(`Need at least 2 symmetry-inequivalent k-points`)
+  const n_stars = Math.max(n_kpts + 1, Math.round(star_factor * n_kpts))
+  const stars = lattice_stars(lattice, ops, n_stars)
+  const to_cart = math.create_frac_to_cart(lattice)
+  const lengths = stars.map(([vec]) => Math.hypot(...to_cart(vec)))
+  const roughness = lengths.map((len) => {
+    const ratio = len / lengths[1]
+    return (1 - ROUGH_C1 * ratio ** 2) ** 2 + ROUGH_C2 * ratio ** 6
+  })
+
+  // constraints relative to the last k-point remove the constant star
+  const values = kept.map((idx) => star_functions(stars, data.kpoints[idx]))
+  const last = values[n_kpts - 1]
+  const diffs = values.slice(0, -1).map((row) => row.map((val, star) => val - last[star]))
+  const system = diffs.map(() => Array<number>(n_kpts - 1).fill(0))
+  diffs.forEach((row_i, idx) => {
+    for (let jdx = 0; jdx <= idx; jdx++) {
+      let sum = 0
+      for (let star = 1; star < n_stars; star++) {
+        sum += (row_i[star] * diffs[jdx][star]) / roughness[star]
+      }
+      system[idx][jdx] = system[jdx][idx] = sum
+    }
+  })
+  const lower = cholesky(system)
+
+  const [lo, hi] = energy_range
+  let n_filled = 0 // electrons in bands entirely below energy_range
+  const coefficients: number[][][] = []
+  const band_indices: number[][] = []
+  for (const spin_eigs of data.eigenvalues) {
+    const [spin_coeffs, spin_bands]: [number[][], number[]] = [[], []]
+    for (let band = 0; band < data.n_bands; band++) {
+      const energies = kept.map((idx) => spin_eigs[idx][band])
+      if (energies.every((energy) => energy < lo)) {
+        n_filled += 2 / data.n_spins
+        continue
+      }
+      if (energies.every((energy) => energy > hi)) continue
+      const e_last = energies[n_kpts - 1]
+      const lambda = cholesky_solve(
+        lower,
+        energies.slice(0, -1).map((energy) => energy - e_last),
+      )
+      const coeffs = roughness.map((rho, star) =>
+        star === 0
+          ? 0
+          : diffs.reduce((sum, row, idx) => sum + lambda[idx] * row[star], 0) / rho,
+      )
+      coeffs[0] = e_last - coeffs.reduce((sum, val, star) => sum + val * last[star], 0)
+      spin_coeffs.push(coeffs)
+      spin_bands.push(band)
+    }
+    coefficients.push(spin_coeffs)
+    band_indices.push(spin_bands)
+  }
+  return {
+    lattice,
+    stars,
+    coefficients,
+    band_indices,
+    n_spins: data.n_spins,
+    n_electrons: data.n_electrons - n_filled,
+  }
+}
+
+export interface InterpolatedBands {
+  energies: number[][][] // [spin][kpoint][band], eV
+  velocities: Vec3[][][] // [spin][kpoint][band], Cartesian group velocities in m/s
+}
+
+// Interpolated energies and group velocities v = ∇_k E / ħ at fractional k-points
+export function evaluate_bands(
+  interp: BandInterpolation,
+  kpoints: readonly Vec3[],
+): InterpolatedBands {
+  const to_cart = math.create_frac_to_cart(interp.lattice)
+  const cart_stars = interp.stars.map((star) => star.map(to_cart))
+  const energies: number[][][] = []
+  const velocities: Vec3[][][] = []
+  for (const spin_coeffs of interp.coefficients) {
+    const [spin_energies, spin_velocities]: [number[][], Vec3[][]] = [[], []]
+    for (const kpt of kpoints) {
+      const phases = interp.stars.map((star, star_idx) =>
+        star.map((vec, vec_idx) => {
+          const angle = 2 * Math.PI * math.dot(kpt, vec)
+          const cart = cart_stars[star_idx][vec_idx]
+          return { cos: Math.cos(angle), sin: Math.sin(angle), cart }
+        }),
+      )
+      const band_energies: number[] = []
+      const band_velocities: Vec3[] = []
+      for (const coeffs of spin_coeffs) {
+        let energy = 0
+        const grad: Vec3 = [0, 0, 0] // eV·Å
+        phases.forEach((star, star_idx) => {
+          const weight = coeffs[star_idx] / star.length
+          for (const { cos, sin, cart } of star) {
+            energy += weight * cos
+            for (let axis = 0; axis < 3; axis++) grad[axis] -= weight * sin * cart[axis]
+          }
+        })
+        band_energies.push(energy)
+        band_velocities.push(math.scale(grad, 1e-10 / HBAR_EV_S))
+      }
+      spin_energies.push(band_energies)
+      spin_velocities.push(band_velocities)
+    }
+    energies.push(spin_energies)
+    velocities.push(spin_velocities)
+  }
+  return { energies, velocities }
+}
+
+// In-place separable transform f(k) = Σ_R F(R) e^(2πi k·R) on a Γ-centered grid, with
+// F(R) stored at R mod dims (exact at the grid points even if several R share a slot)
+function grid_transform(re: Float64Array, im: Float64Array, dims: Vec3) {
+  const strides = [dims[1] * dims[2], dims[2], 1]
+  for (let axis = 0; axis < 3; axis++) {
+    const [size, stride] = [dims[axis], strides[axis]]
+    const cos_table = Float64Array.from({ length: size }, (_, idx) =>
+      Math.cos((2 * Math.PI * idx) / size),
+    )
+    const sin_table = Float64Array.from({ length: size }, (_, idx) =>
+      Math.sin((2 * Math.PI * idx) / size),
+    )
+    const [line_re, line_im] = [new Float64Array(size), new Float64Array(size)]
+    for (let start = 0; start < re.length; start++) {
+      if (Math.floor(start / stride) % size !== 0) continue // not the start of a line
+      for (let idx = 0; idx < size; idx++) {
+        line_re[idx] = re[start + idx * stride]
+        line_im[idx] = im[start + idx * stride]
+      }
+      for (let kdx = 0; kdx < size; kdx++) {
+        let [sum_re, sum_im] = [0, 0]
+        for (let idx = 0; idx < size; idx++) {
+          const twiddle = (kdx * idx) % size
+          sum_re += line_re[idx] * cos_table[twiddle] - line_im[idx] * sin_table[twiddle]
+          sum_im += line_re[idx] * sin_table[twiddle] + line_im[idx] * cos_table[twiddle]
+        }
+        re[start + kdx * stride] = sum_re
+        im[start + kdx * stride] = sum_im
+      }
+    }
+  }
+}
+
+export interface TransportDistributionOptions {
+  n_grid?: Vec3 // dense Γ-centered k-grid (default 2·max|R| + 1 per axis)
+  energy_step?: number // eV, histogram bin width (default 0.005)
+}
+
+export interface TransportDistribution {
+  energies: number[] // eV, bin centers
+  dos: number[] // states / eV / cell, spin included
+  // Σ_nk v_α v_β δ(ε − ε_nk) / N_k per cell in (m/s)² / eV, spin included
+  sigma: Matrix3x3[]
+  energy_step: number
+  volume: number // Å³
+  n_electrons: number // valence electrons in the interpolated bands
+}
+
+// Histogram the interpolated states and velocity products of a dense k-grid into the
+// density of states and transport distribution σ(ε)
+export function transport_distribution(
+  interp: BandInterpolation,
+  options: TransportDistributionOptions = {},
+): TransportDistribution {
+  const { energy_step = 0.005 } = options
+  if (!(energy_step > 0)) throw new Error(`energy_step must be > 0, got ${energy_step}`)
+  const extent = [0, 1, 2].map((axis) =>
+    interp.stars.flat().reduce((max, vec) => Math.max(max, Math.abs(vec[axis])), 0),
+  )
+  const dims = options.n_grid ?? (extent.map((val) => 2 * val + 1) as Vec3)
+  if (!dims.every((dim) => Number.isInteger(dim) && dim > 0)) {
+    throw new Error(`n_grid must be 3 positive integers, got ${dims}`)
+  }
+  const n_points = dims[0] * dims[1] * dims[2]
+  const to_cart = math.create_frac_to_cart(interp.lattice)
+  const slot = (vec: Vec3) =>
+    vec.reduce((acc, val, axis) => {
+      const dim = dims[axis]
+      return acc * dim + (((val % dim) + dim) % dim)
+    }, 0)
+
+  // energies and velocities of every interpolated band on the dense grid
+  const states: Float64Array[][] = [] // per band: [energy, v_x, v_y, v_z]
+  for (const spin_coeffs of interp.coefficients) {
+    for (const coeffs of spin_coeffs) {
+      const fields = [0, 1, 2, 3].map(() => {
+        const [re, im] = [new Float64Array(n_points), new Float64Array(n_points)]
+        return { re, im }
+      })
+      interp.stars.forEach((star, star_idx) => {
+        const weight = coeffs[star_idx] / star.length
+        for (const vec of star) {
+          const idx = slot(vec)
+          fields[0].re[idx] += weight
+          // ∇_k cos(k·R) = −R sin(k·R) = Re(i R e^(ik·R)) in eV·Å
+          to_cart(vec).forEach((val, axis) => (fields[axis + 1].im[idx] += weight * val))
+        }
+      })
+      for (const { re, im } of fields) grid_transform(re, im, dims)
+      const to_velocity = 1e-10 / HBAR_EV_S // eV·Å → m/s
+      states.push(
+        fields.map(({ re }, field) => (field ? re.map((val) => val * to_velocity) : re)),
+      )
+    }
+  }
+  if (states.length === 0) throw new Error(`No interpolated bands in the energy range`)
+
+  let [e_min, e_max] = [Infinity, -Infinity]
+  for (const [energies] of states) {
+    for (const energy of energies) {
+      if (energy < e_min) e_min = energy
+      if (energy > e_max) e_max = energy
+    }
+  }
+  const n_bins = Math.max(1, Math.ceil((e_max - e_min) / energy_step))
+  const dos = Array<number>(n_bins).fill(0)
+  const sigma = dos.map(() => [0, 1, 2].map(() => [0, 0, 0]) as Matrix3x3)
+  const weight = 2 / interp.n_spins / n_points / energy_step
+  for (const [energies, v_x, v_y, v_z] of states) {
+    energies.forEach((energy, idx) => {
+      const bin = Math.min(Math.floor((energy - e_min) / energy_step), n_bins - 1)
+      const vel = [v_x[idx], v_y[idx], v_z[idx]]
+      dos[bin] += weight
+      for (let row = 0; row < 3; row++) {
+        for (let col = 0; col < 3; col++) sigma[bin][row][col] += weight * vel[row] * vel[col]
+      }
+    })
+  }
+  return {
+    energies: dos.map((_, bin) => e_min + (bin + 0.5) * energy_step),
+    dos,
+    sigma,
+    energy_step,
+    volume: Math.abs(math.det_3x3(interp.lattice)),
+    n_electrons: interp.n_electrons,
+  }
+}
+
+// 0 K Fermi level: middle of the energy interval where the integrated DOS reaches the
+// electron count (mid-gap for insulators)
+export function transport_fermi_level(dist: TransportDistribution): number {
+  const { energies, dos, energy_step, n_electrons } = dist
+  const tol = 1e-6 * Math.max(1, n_electrons)
+  let [count, first, last] = [0, -1, -1]
+  dos.forEach((val, bin) => {
+    count += val * energy_step
+    if (first === -1 && count >= n_electrons - tol) first = bin
+    if (count <= n_electrons + tol) last = bin
+  })
+  if (first === -1) {
+    throw new Error(`Interpolated bands hold fewer than ${n_electrons} electrons`)
+  }
+  const edge = (bin: number) => energies[0] + (bin - 0.5) * energy_step
+  return last >= first ? (edge(first + 1) + edge(last + 1)) / 2 : energies[first]
+}
+
+export interface TransportPoint {
+  temperature: number // K
+  mu: number // eV, chemical potential
+  n_carriers: number // electrons per cell relative to the neutral count (< 0 for holes)
+  carrier_density: number // cm⁻³, same sign convention as n_carriers
+  conductivity_over_tau: Matrix3x3 // σ/τ in 1/(Ω·m·s)
+  seebeck: Matrix3x3 // μV/K, NaN where σ is singular (e.g. deep in a gap)
+}
+
+// Onsager coefficients L_n = ∫ σ(ε) (ε − μ)ⁿ (−∂f/∂ε) dε at one chemical potential and
+// temperature: σ/τ = e² L_0 / V and S = −L_0⁻¹ L_1 / (eT)
+export function onsager_coefficients(
+  dist: TransportDistribution,
+  mu: number,
+  temperature: number,
+): TransportPoint {
+  if (!(temperature > 0)) throw new Error(`temperature must be > 0, got ${temperature}`)
+  const { energies, dos, sigma, energy_step, volume } = dist
+  const k_t = K_B_EV * temperature
+  const l_0 = [0, 1, 2].map(() => [0, 0, 0]) as Matrix3x3
+  const l_1 = [0, 1, 2].map(() => [0, 0, 0]) as Matrix3x3
+  let n_occupied = 0
+  energies.forEach((energy, bin) => {
+    const reduced = (energy - mu) / k_t
+    const occupation = 1 / (1 + Math.exp(reduced))
+    n_occupied += dos[bin] * occupation * energy_step
+    const window = (occupation * (1 - occupation) * energy_step) / k_t // −∂f/∂ε dε
+    if (!(window > 0)) return
+    for (let row = 0; row < 3; row++) {
+      for (let col = 0; col < 3; col++) {
+        l_0[row][col] += sigma[bin][row][col] * window
+        l_1[row][col] += sigma[bin][row][col] * (energy - mu) * window
+      }
+    }
+  })
+  const n_carriers = n_occupied - dist.n_electrons
+  const trace = l_0[0][0] + l_0[1][1] + l_0[2][2]
+  const singular = !(Math.abs(math.det_3x3(l_0)) > 1e-12 * trace ** 3)
+  const seebeck = singular
+    ? ([0, 1, 2].map(() => [NaN, NaN, NaN]) as Matrix3x3)
+    : (math
+        .dot(math.matrix_inverse_3x3(l_0), l_1)
+        .map((row) => row.map((val) => (-val / temperature) * 1e6)) as Matrix3x3)
+  return {
+    temperature,
+    mu,
+    n_carriers,
+    carrier_density: (n_carriers / volume) * 1e24,
+    // e² Σ v v (−∂f/∂ε) / V with ∂f/∂ε per eV: one factor e converts eV to J
+    conductivity_over_tau: l_0.map((row) =>
+      row.map((val) => (E_CHARGE * val) / (volume * 1e-30)),
+    ) as Matrix3x3,
+    seebeck,
+  }
+}
+
+export interface TransportOptions {
+  temperatures?: number[] // K (default [300])
+  mu_values?: number[] // eV (default: 0 K Fermi level ± 1 eV in 25 meV steps)
+}
+
+// Transport coefficients on a grid of temperatures and chemical potentials (doping
+// levels), ordered by temperature, then chemical potential
+export function calc_transport(
+  dist: TransportDistribution,
+  options: TransportOptions = {},
+): TransportPoint[] {
+  const { temperatures = [300] } = options
+  let { mu_values } = options
+  if (!mu_values) {
+    const fermi_level = transport_fermi_level(dist)
+    mu_values = Array.from({ length: 81 }, (_, idx) => fermi_level + (idx - 40) * 0.025)
+  }
+  return temperatures.flatMap((temperature) =>
+    mu_values.map((mu) => onsager_coefficients(dist, mu, temperature)),
+  )
+}
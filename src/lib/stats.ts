@@ -0,0 +1,172 @@
+// Small line-fitting utilities for analysis functions (growth velocities, rate fits):
+// weighted least squares with standard errors, the outlier-robust Theil-Sen estimator and
+// Student-t quantiles for confidence intervals from few points
+
+export type LinearFit = {
+  slope: number
+  intercept: number
+  slope_err: number // standard error, NaN with fewer than 3 points
+  intercept_err: number
+  r_squared: number // weighted coefficient of determination
+  n_points: number
+}
+
+export type TheilSenFit = {
+  slope: number // median of all pairwise slopes
+  intercept: number // median of y − slope · x
+  slope_range: [number, number] // interquartile range of the pairwise slopes
+  n_points: number
+}
+
+const median_sorted = (sorted: readonly number[]): number => {
+  const mid = sorted.length >> 1
+  return sorted.length % 2 ? sorted[mid] : (sorted[mid - 1] + sorted[mid]) / 2
+}
+
+const check_points = (xs: readonly number[], ys: readonly number[]) => {
+  if (xs.length !== ys.length) {
+    throw new Error(`xs and ys must have equal length, got ${xs.length} and ${ys.length}`)
+  }
+  if (xs.length < 2) throw new Error(`Need at least 2 points to fit a line`)
+}
+
+// Least-squares line y = slope · x + intercept with optional weights (e.g. 1/σ²).
+// Standard errors come from the covariance matrix scaled by the reduced χ², so only
+// relative weights matter. NaN slope and errors if all x are equal.
+export function linear_fit(
+  xs: readonly number[],
+  ys: readonly number[],
+  weights?: readonly number[],
+): LinearFit {
+  check_points(xs, ys)
+  if (weights && weights.length !== xs.length) {
+    throw new Error(`weights must match the ${xs.length} points, got ${weights.length}`)
+  }
+  if (weights?.some((weight) => !(weight >= 0))) {
+    throw new Error(`weights must be non-negative`)
+  }
+  const weight_at = (idx: number) => weights?.[idx] ?? 1
+  let [sum_w, sum_x, sum_y] = [0, 0, 0]
+  xs.forEach((x_val, idx) => {
+    sum_w += weight_at(idx)
+    sum_x += weight_at(idx) * x_val
+    sum_y += weight_at(idx) * ys[idx]
+  })
+  const n_points = xs.length
+  if (!(sum_w > 0)) throw new Error(`weights must not all be zero`)
+  const [mean_x, mean_y] = [sum_x / sum_w, sum_y / sum_w]
+  let [s_xx, s_xy, s_yy] = [0, 0, 0]
+  xs.forEach((x_val, idx) => {
+    const [dx, dy] = [x_val - mean_x, ys[idx] - mean_y]
+    s_xx += weight_at(idx) * dx * dx
+    s_xy += weight_at(idx) * dx * dy
+    s_yy += weight_at(idx) * dy * dy
+  })
+  const slope = s_xx > 0 ? s_xy / s_xx : NaN
+  const intercept = mean_y - slope * mean_x
+  const chi_sq = Math.max(0, s_yy - slope * s_xy) // weighted residual sum of squares
+  const red_chi_sq = n_points > 2 ? chi_sq / (n_points - 2) : NaN
+  const slope_err = Math.sqrt(red_chi_sq / s_xx)
+  const intercept_err = Math.sqrt(red_chi_sq * (1 / sum_w + (mean_x * mean_x) / s_xx))
+  const r_squared = s_yy > 0 ? 1 - chi_sq / s_yy : 1
+  return { slope, intercept, slope_err, intercept_err, r_squared, n_points }
+}
+
+// Theil-Sen line: median slope over all point pairs with distinct x, robust to up to
+// ~29% outliers (e.g. misdetected frames). O(n²) pairs, fine for a few thousand points.
+export function theil_sen(xs: readonly number[], ys: readonly number[]): TheilSenFit {
+  check_points(xs, ys)
+  const slopes: number[] = []
+  for (let idx = 0; idx < xs.length; idx++) {
+    for (let jdx = idx + 1; jdx < xs.length; jdx++) {
+      const dx = xs[jdx] - xs[idx]
+      if (dx !== 0) slopes.push((ys[jdx] - ys[idx]) / dx)
+    }
+  }
+  if (slopes.length === 0) throw new Error(`Theil-Sen needs at least 2 distinct x values`)
+  slopes.sort((val_1, val_2) => val_1 - val_2)
+  const slope = median_sorted(slopes)
+  const offsets = ys.map((y_val, idx) => y_val - slope * xs[idx])
+  const intercept = median_sorted(offsets.sort((val_1, val_2) => val_1 - val_2))
+  const quartile = (frac: number) => slopes[Math.round(frac * (slopes.length - 1))]
+  const slope_range: [number, number] = [quartile(0.25), quartile(0.75)]
+  return { slope, intercept, slope_range, n_points: xs.length }
+}
+
+// Lanczos coefficients (g = 7, n = 9) for ln Γ to ~15 significant digits
+const LANCZOS = [
+  0.99999999999980993, 676.5203681218851, -1259.1392167224028, 771.32342877765313,
+  -176.61502916214059, 12.507343278686905, -0.13857109526572012, 9.9843695780195716e-6,
+  1.5056327351493116e-7,
+]
+
+// ln Γ(x), using the reflection formula below 1/2
+export function log_gamma(x_val: number): number {
+  if (x_val < 0.5) {
+    return Math.log(Math.PI / Math.abs(Math.sin(Math.PI * x_val))) - log_gamma(1 - x_val)
+  }
+  const shifted = x_val - 1
+  const base = shifted + 7.5
+  let series = LANCZOS[0]
+  for (let idx = 1; idx < LANCZOS.length; idx++) series += LANCZOS[idx] / (shifted + idx)
+  return (
+    0.5 * Math.log(2 * Math.PI) + (shifted + 0.5) * Math.log(base) - base + Math.log(series)
+  )
+}
+
+// Regularized incomplete beta function I_x(a, b) by Lentz's continued fraction, using
+// I_x(a, b) = 1 − I_(1−x)(b, a) where the fraction converges slowly
+export function incomplete_beta(x_val: number, a_val: number, b_val: number): number {
+  if (x_val <= 0) return 0
+  if (x_val >= 1) return 1
+  if (x_val > (a_val + 1) / (a_val + b_val + 2)) {
+    return 1 - incomplete_beta(1 - x_val, b_val, a_val)
+  }
+  const ln_front =
+    log_gamma(a_val + b_val) -
+    log_gamma(a_val) -
+    log_gamma(b_val) +
+    a_val * Math.log(x_val) +
+    b_val * Math.log(1 - x_val)
+  const tiny = 1e-300
+  const clamp = (val: number) => (Math.abs(val) < tiny ? tiny : val)
+  let num = 1
+  let den = 1 / clamp(1 - ((a_val + b_val) * x_val) / (a_val + 1))
+  let frac = den
+  for (let step = 1; step <= 500; step++) {
+    const two_step = 2 * step
+    for (const coeff of [
+      (step * (b_val - step) * x_val) / ((a_val + two_step - 1) * (a_val + two_step)),
+      (-(a_val + step) * (a_val + b_val + step) * x_val) /
+        ((a_val + two_step) * (a_val + two_step + 1)),
+    ]) {
+      den = 1 / clamp(1 + coeff * den)
+      num = clamp(1 + coeff / num)
+      frac *= den * num
+    }
+    if (Math.abs(den * num - 1) < 1e-15) break
+  }
+  return (Math.exp(ln_front) * frac) / a_val
+}
+
+// Cumulative distribution of Student's t with dof degrees of freedom
+export function student_t_cdf(t_val: number, dof: number): number {
+  if (!(dof > 0)) throw new Error(`dof must be > 0, got ${dof}`)
+  const tail = 0.5 * incomplete_beta(dof / (dof + t_val * t_val), dof / 2, 0.5)
+  return t_val >= 0 ? 1 - tail : tail
+}
+
+// Quantile t_p of Student's t by bisection of the CDF, e.g. student_t_quantile(0.975, 3)
+// ≈ 3.182 for the half-width of a two-sided 95% interval from 5 points of a line fit
+export function student_t_quantile(prob: number, dof: number): number {
+  if (!(prob > 0 && prob < 1)) throw new Error(`prob must be in (0, 1), got ${prob}`)
+  if (prob < 0.5) return -student_t_quantile(1 - prob, dof)
+  let [lower, upper] = [0, 1]
+  while (student_t_cdf(upper, dof) < prob) [lower, upper] = [upper, 2 * upper]
+  for (let iter = 0; iter < 200 && upper - lower > 1e-14 * upper; iter++) {
+    const mid = (lower + upper) / 2
+    if (student_t_cdf(mid, dof) < prob) lower = mid
+    else upper = mid
+  }
+  return (lower + upper) / 2
+}
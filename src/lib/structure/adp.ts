@@ -0,0 +1,53 @@
+// Atomic displacement parameters (ADPs): mean-square displacement tensors U_ij per site.
+// Stored on sites as a Cartesian 3x3 tensor in Å² under properties.u_cart.
+import type { Matrix3x3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Site } from './index'
+
+// CIF aniso loop order: U_11, U_22, U_33, U_12, U_13, U_23
+export type AdpCif = [number, number, number, number, number, number]
+
+export const ADP_PROPERTY_KEY = `u_cart`
+
+// Lengths of the reciprocal lattice vectors |a*|, |b*|, |c*| (without 2π)
+function recip_lengths(lattice_matrix: Matrix3x3): [Matrix3x3, math.Vec3] {
+  const inv = math.matrix_inverse_3x3(lattice_matrix)
+  // columns of inv(M) are the reciprocal lattice vectors for row-wise lattice matrices
+  const recip_rows = math.transpose_3x3_matrix(inv)
+  return [inv, recip_rows.map((row) => Math.hypot(...row)) as math.Vec3]
+}
+
+// Cartesian U tensor -> CIF U_ij (Trueblood et al. 1996: U_cart = A N U_cif N Aᵀ with A
+// holding lattice vectors as columns and N = diag(|a*|, |b*|, |c*|))
+export function u_cart_to_cif(u_cart: Matrix3x3, lattice_matrix: Matrix3x3): AdpCif {
+  const [inv, lengths] = recip_lengths(lattice_matrix)
+  // U* = M⁻ᵀ U_cart M⁻¹ for row-wise lattice matrix M = Aᵀ
+  const u_star = math.dot(math.dot(math.transpose_3x3_matrix(inv), u_cart), inv)
+  const u_ij = (idx: number, jdx: number) => u_star[idx][jdx] / (lengths[idx] * lengths[jdx])
+  return [u_ij(0, 0), u_ij(1, 1), u_ij(2, 2), u_ij(0, 1), u_ij(0, 2), u_ij(1, 2)]
+}
+
+// CIF U_ij -> Cartesian U tensor (inverse of u_cart_to_cif)
+export function u_cif_to_cart(u_cif: AdpCif, lattice_matrix: Matrix3x3): Matrix3x3 {
+  const [, lengths] = recip_lengths(lattice_matrix)
+  const [u11, u22, u33, u12, u13, u23] = u_cif
+  const u_mat: Matrix3x3 = [
+    [u11, u12, u13],
+    [u12, u22, u23],
+    [u13, u23, u33],
+  ]
+  const u_star = u_mat.map((row, idx) =>
+    row.map((val, jdx) => val * lengths[idx] * lengths[jdx]),
+  ) as Matrix3x3
+  return math.dot(math.dot(math.transpose_3x3_matrix(lattice_matrix), u_star), lattice_matrix)
+}
+
+// Equivalent isotropic displacement U_eq = tr(U_cart) / 3
+export const u_equiv = (u_cart: Matrix3x3): number =>
+  (u_cart[0][0] + u_cart[1][1] + u_cart[2][2]) / 3
+
+// Read a site's Cartesian U tensor from properties.u_cart, null if absent or malformed
+export function get_site_u_cart(site: Site): Matrix3x3 | null {
+  const u_cart = site.properties?.[ADP_PROPERTY_KEY]
+  return math.is_square_matrix(u_cart, 3) ? (u_cart as Matrix3x3) : null
+}
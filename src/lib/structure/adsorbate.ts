@@ -0,0 +1,215 @@
+// Adsorbate identification in slab + adsorbate structures (e.g. relaxed surface calculations):
+// separates the substrate from adsorbed molecules/atoms via bond connectivity and height
+// along the surface normal, then classifies each adsorbate's binding site
+import type { CompositionType } from '$lib/composition'
+import { get_hill_formula } from '$lib/composition'
+import type { ElementSymbol } from '$lib/element'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { get_majority_element } from './bonding'
+import { displacement_pbc } from './measure'
+import { perceive_topology } from './topology'
+
+export type AdsorptionSite = `top` | `bridge` | `fcc` | `hcp` | `hollow`
+
+export interface Adsorbate {
+  site_indices: number[]
+  composition: CompositionType
+  formula: string // Hill formula, e.g. CO or H2O
+  binding_indices: number[] // adsorbate atoms bonded to the substrate
+  surface_indices: number[] // substrate atoms bonded to the lowest binding atom
+  site_type: AdsorptionSite | null // null if not bonded to the substrate
+  height: number // Å of the lowest binding (or lowest) atom above the top substrate layer
+}
+
+export interface SlabAdsorbateAnalysis {
+  normal: Vec3 // unit surface normal (a × b, oriented along c)
+  substrate_indices: number[]
+  surface_indices: number[] // top substrate layer
+  surface_height: number // mean height of the top substrate layer along the normal
+  adsorbates: Adsorbate[]
+}
+
+export interface AdsorbateOptions {
+  substrate_elements?: ElementSymbol[] // default: elements in the lower half of the cell
+  tolerance?: number // Å added to the sum of covalent radii for bonds (default 0.45)
+  layer_tolerance?: number // max height gap (Å) within one layer (default 0.7)
+  // top substrate layers with fewer than this fraction of the atoms in the layer below are
+  // adatoms (e.g. metal dimers) and count as adsorbates, 0 disables (default 0.5)
+  adatom_fraction?: number
+  hollow_tolerance?: number // max lateral offset (Å) of a subsurface atom below hcp sites
+}
+
+// Split site indices into layers along the normal wherever consecutive heights differ
+// by more than layer_tolerance, ordered bottom to top
+function group_layers(
+  indices: number[],
+  heights: number[],
+  layer_tolerance: number,
+): number[][] {
+  const sorted = indices.toSorted((idx_1, idx_2) => heights[idx_1] - heights[idx_2])
+  const layers: number[][] = []
+  sorted.forEach((site_idx, pos) => {
+    const prev = sorted[pos - 1]
+    if (pos === 0 || heights[site_idx] - heights[prev] > layer_tolerance) layers.push([])
+    layers[layers.length - 1].push(site_idx)
+  })
+  return layers
+}
+
+// Connected components of the bond graph restricted to the given sites
+function connected_components(members: Set<number>, bonded: number[][]): number[][] {
+  const seen = new Set<number>()
+  const components: number[][] = []
+  for (const start of [...members].sort((idx_1, idx_2) => idx_1 - idx_2)) {
+    if (seen.has(start)) continue
+    const component: number[] = []
+    const stack = [start]
+    seen.add(start)
+    while (stack.length > 0) {
+      const site_idx = stack.pop() as number
+      component.push(site_idx)
+      for (const nb_idx of bonded[site_idx]) {
+        if (!members.has(nb_idx) || seen.has(nb_idx)) continue
+        seen.add(nb_idx)
+        stack.push(nb_idx)
+      }
+    }
+    components.push(component.sort((idx_1, idx_2) => idx_1 - idx_2))
+  }
+  return components
+}
+
+// Inverse of placing adsorbates on a slab. The substrate is the network of substrate-element
+// atoms bonded to the bottom layer, minus sparse top layers of adatoms. Every other bonded
+// group is one adsorbate whose site type follows from the number of surface atoms bonded to
+// its lowest binding atom (3-fold hollows are fcc/hcp depending on whether a subsurface atom
+// lies below). Assumes the slab isn't split across the cell boundary along the normal.
+export function identify_adsorbates(
+  structure: AnyStructure,
+  options: AdsorbateOptions = {},
+): SlabAdsorbateAnalysis {
+  const {
+    tolerance = 0.45,
+    layer_tolerance = 0.7,
+    adatom_fraction = 0.5,
+    hollow_tolerance = 0.8,
+  } = options
+  const { sites } = structure
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const elements = sites.map((site) => get_majority_element(site))
+
+  let normal: Vec3 = [0, 0, 1]
+  if (lattice) {
+    const [vec_a, vec_b, vec_c] = lattice.matrix
+    normal = math.normalize_vec(math.cross_3d(vec_a, vec_b), normal)
+    if (math.dot(normal, vec_c) < 0) normal = math.scale(normal, -1)
+  }
+  const heights = sites.map((site) => math.dot(site.xyz, normal))
+  const empty = { normal, substrate_indices: [], surface_indices: [], adsorbates: [] }
+  if (sites.length === 0) return { ...empty, surface_height: 0 }
+
+  const [min_height, max_height] = [Math.min(...heights), Math.max(...heights)]
+  const substrate_elements = new Set<string>(
+    options.substrate_elements ??
+      sites.flatMap((_, idx) =>
+        heights[idx] <= (min_height + max_height) / 2 && elements[idx] ? [elements[idx]] : [],
+      ),
+  )
+
+  // covalent bonds (d ≤ r_i + r_j + tolerance) as adjacency lists of site indices
+  const bonded: number[][] = sites.map(() => [])
+  const { bonds } = perceive_topology(structure, { tolerance, perceive_orders: false })
+  for (const { site_idx_1, site_idx_2 } of bonds) {
+    if (site_idx_1 === site_idx_2) continue
+    if (!bonded[site_idx_1].includes(site_idx_2)) bonded[site_idx_1].push(site_idx_2)
+    if (!bonded[site_idx_2].includes(site_idx_1)) bonded[site_idx_2].push(site_idx_1)
+  }
+
+  // grow the substrate from its bottom layer through substrate-element bonds
+  const is_substrate_element = (idx: number): boolean =>
+    substrate_elements.has(elements[idx] ?? ``)
+  const seeds = sites.flatMap((_, idx) =>
+    is_substrate_element(idx) && heights[idx] - min_height <= layer_tolerance ? [idx] : [],
+  )
+  const substrate = new Set(seeds)
+  const stack = [...seeds]
+  while (stack.length > 0) {
+    for (const nb_idx of bonded[stack.pop() as number]) {
+      if (substrate.has(nb_idx) || !is_substrate_element(nb_idx)) continue
+      substrate.add(nb_idx)
+      stack.push(nb_idx)
+    }
+  }
+  const layers = group_layers([...substrate], heights, layer_tolerance)
+  while (
+    layers.length > 1 &&
+    layers[layers.length - 1].length < adatom_fraction * layers[layers.length - 2].length
+  ) {
+    for (const site_idx of layers.pop() ?? []) substrate.delete(site_idx)
+  }
+  if (layers.length === 0) return { ...empty, surface_height: min_height }
+
+  const surface_indices = layers[layers.length - 1].toSorted((idx_1, idx_2) => idx_1 - idx_2)
+  const subsurface = layers.length > 1 ? layers[layers.length - 2] : []
+  const surface_height =
+    surface_indices.reduce((sum, idx) => sum + heights[idx], 0) / surface_indices.length
+
+  // lateral (in-plane) minimum-image distance between two sites
+  const lateral_distance = (idx_1: number, idx_2: number): number => {
+    const disp = displacement_pbc(
+      sites[idx_1].xyz,
+      sites[idx_2].xyz,
+      lattice?.matrix,
+      undefined,
+      lattice?.pbc,
+    )
+    return Math.hypot(...math.subtract(disp, math.scale(normal, math.dot(disp, normal))))
+  }
+
+  const adsorbate_atoms = new Set(sites.flatMap((_, idx) => (substrate.has(idx) ? [] : [idx])))
+  const adsorbates = connected_components(adsorbate_atoms, bonded).map((site_indices) => {
+    const composition: CompositionType = {}
+    for (const idx of site_indices) {
+      const element = elements[idx]
+      if (element) composition[element] = (composition[element] ?? 0) + 1
+    }
+    const by_height = site_indices.toSorted((idx_1, idx_2) => heights[idx_1] - heights[idx_2])
+    const binding_indices = site_indices.filter((idx) =>
+      bonded[idx].some((nb_idx) => substrate.has(nb_idx)),
+    )
+    const anchor = by_height.find((idx) => binding_indices.includes(idx)) ?? by_height[0]
+    const anchor_surface = bonded[anchor]
+      .filter((nb_idx) => substrate.has(nb_idx))
+      .sort((idx_1, idx_2) => idx_1 - idx_2)
+
+    let site_type: AdsorptionSite | null = null
+    if (anchor_surface.length === 1) site_type = `top`
+    else if (anchor_surface.length === 2) site_type = `bridge`
+    else if (anchor_surface.length > 3 || (anchor_surface.length && !subsurface.length)) {
+      site_type = `hollow`
+    } else if (anchor_surface.length === 3) {
+      const below = subsurface.some((idx) => lateral_distance(anchor, idx) <= hollow_tolerance)
+      site_type = below ? `hcp` : `fcc`
+    }
+
+    return {
+      site_indices,
+      composition,
+      formula: get_hill_formula(composition, true, ``),
+      binding_indices,
+      surface_indices: anchor_surface,
+      site_type,
+      height: heights[anchor] - surface_height,
+    }
+  })
+
+  return {
+    normal,
+    substrate_indices: [...substrate].sort((idx_1, idx_2) => idx_1 - idx_2),
+    surface_indices,
+    surface_height,
+    adsorbates,
+  }
+}
@@ -0,0 +1,107 @@
+// AiiDA StructureData interop via its attributes dict (cell, pbc1-3, kinds and sites, as
+// in StructureData.base.attributes.all and archive/REST exports). Kinds carry the species,
+// so alloy kinds (several symbols with weights) map to partially occupied sites.
+import { ATOMIC_WEIGHTS } from '$lib/composition/parse'
+import { is_elem_symbol } from '$lib/element/helpers'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { is_plain_object } from '$lib/utils'
+import type { AnyStructure, Site, Species } from './index'
+import type { Pbc } from './pbc'
+
+export type AiidaKind = { name: string; symbols: string[]; weights: number[]; mass?: number }
+
+export type AiidaStructureData = {
+  cell: number[][] // lattice vectors as rows, Å
+  pbc1: boolean
+  pbc2: boolean
+  pbc3: boolean
+  kinds: AiidaKind[]
+  sites: { kind_name: string; position: number[] }[] // Cartesian, Å
+}
+
+// vacuum added around molecules, which AiiDA still stores in a cell
+export const AIIDA_MOLECULE_PADDING = 10
+
+export function is_aiida_structure(obj: unknown): obj is AiidaStructureData {
+  if (!is_plain_object(obj) || !Array.isArray(obj.cell)) return false
+  const { kinds, sites } = obj
+  if (!Array.isArray(kinds) || !Array.isArray(sites) || sites.length === 0) return false
+  return is_plain_object(sites[0]) && `kind_name` in sites[0] && `position` in sites[0]
+}
+
+// Structure from StructureData attributes. Kind names become site labels, and kind masses
+// that differ from the standard atomic weight (e.g. deuterium) a `mass` site property.
+export function from_aiida_structure(data: AiidaStructureData): AnyStructure {
+  const kinds = new Map(data.kinds.map((kind) => [kind.name, kind]))
+  const matrix = data.cell.map((row) => row.slice(0, 3)) as Matrix3x3
+  const pbc: Pbc = [data.pbc1, data.pbc2, data.pbc3].map(Boolean) as Pbc
+  const periodic = pbc.some(Boolean)
+  const cart_to_frac = periodic ? math.create_cart_to_frac(matrix) : null
+
+  const sites: Site[] = data.sites.map(({ kind_name, position }, idx) => {
+    const kind = kinds.get(kind_name)
+    if (!kind) throw new Error(`AiiDA site ${idx} refers to unknown kind ${kind_name}`)
+    if (kind.symbols.length !== kind.weights.length) {
+      throw new Error(`AiiDA kind ${kind_name} needs one weight per symbol`)
+    }
+    const species = kind.symbols.map((symbol, sym_idx): Species => {
+      if (!is_elem_symbol(symbol)) {
+        throw new Error(`Unknown element ${symbol} in AiiDA kind ${kind_name}`)
+      }
+      return { element: symbol, occu: kind.weights[sym_idx], oxidation_state: 0 }
+    })
+    const properties: Record<string, unknown> = {}
+    const standard_mass = kind.symbols.reduce(
+      (sum, symbol, sym_idx) =>
+        sum + kind.weights[sym_idx] * (ATOMIC_WEIGHTS.get(symbol as Species[`element`]) ?? 0),
+      0,
+    )
+    const total_weight = kind.weights.reduce((sum, weight) => sum + weight, 0)
+    if (kind.mass !== undefined && Math.abs(kind.mass - standard_mass / total_weight) > 1e-3) {
+      properties.mass = kind.mass
+    }
+    const xyz = position.slice(0, 3) as Vec3
+    const abc = cart_to_frac ? cart_to_frac(xyz) : ([0, 0, 0] as Vec3)
+    return { species, abc, xyz, label: kind_name, properties }
+  })
+
+  if (!periodic) return { sites }
+  return { sites, lattice: { matrix, pbc, ...math.calc_lattice_params(matrix) } }
+}
+
+// StructureData attributes of a structure. Sites with the same occupation share a kind
+// named after their elements, numbered when the same elements occur with other weights.
+// Molecules get a box around their atoms with AIIDA_MOLECULE_PADDING of vacuum.
+export function to_aiida_structure(structure: AnyStructure): AiidaStructureData {
+  const kinds: AiidaKind[] = []
+  const kind_by_occupation = new Map<string, string>()
+  const sites = structure.sites.map(({ species, xyz, properties }) => {
+    const symbols: string[] = species.map(({ element }) => element)
+    const weights = species.map(({ occu }) => occu)
+    const mass = typeof properties?.mass === `number` ? properties.mass : undefined
+    const key = JSON.stringify([symbols, weights, mass])
+    let kind_name = kind_by_occupation.get(key)
+    if (!kind_name) {
+      const base = symbols.join(``)
+      kind_name = base
+      for (let count = 2; kinds.some(({ name }) => name === kind_name); count++) {
+        kind_name = `${base}${count}`
+      }
+      kind_by_occupation.set(key, kind_name)
+      kinds.push({ name: kind_name, symbols, weights, ...(mass !== undefined && { mass }) })
+    }
+    return { kind_name, position: [...xyz] }
+  })
+
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const extent = [0, 1, 2].map((dim) => {
+    const coords = structure.sites.map(({ xyz }) => xyz[dim])
+    return Math.max(...coords) - Math.min(...coords) + AIIDA_MOLECULE_PADDING
+  })
+  const cell = lattice
+    ? lattice.matrix.map((row) => [...row])
+    : [0, 1, 2].map((row) => [0, 1, 2].map((col) => (row === col ? extent[row] : 0)))
+  const [pbc1, pbc2, pbc3] = lattice?.pbc ?? [false, false, false]
+  return { cell, pbc1, pbc2, pbc3, kinds, sites }
+}
@@ -0,0 +1,137 @@
+// ASE interop via the plain-dict form of ase.Atoms (Atoms.todict() plus calculator forces,
+// as in ase.io.jsonio / ase db JSON rows), round-tripping per-atom arrays and constraints
+// through site properties so hand-offs to and from ASE workflows don't drop them
+import { ATOMIC_NUMBER_TO_SYMBOL, SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import type { AnyStructure, Site } from './index'
+import type { Pbc } from './pbc'
+import { make_site } from './site'
+
+// FixAtoms { indices } or FixCartesian { a, mask } with mask true for fixed directions
+export type AseConstraint = { name: string; kwargs: Record<string, unknown> }
+
+export type AseAtomsDict = {
+  numbers: number[]
+  positions: number[][] // Å
+  cell?: number[][]
+  pbc?: boolean[]
+  forces?: number[][] // eV/Å, from the calculator
+  initial_charges?: number[]
+  initial_magmoms?: number[] | number[][]
+  tags?: number[]
+  momenta?: number[][] // ASE units (amu·Å/ASE time), kept as-is
+  constraints?: AseConstraint[]
+  info?: Record<string, unknown>
+}
+
+// ASE array name → site property key
+export const ASE_ARRAY_PROPERTIES = {
+  forces: `force`,
+  initial_charges: `charge`,
+  initial_magmoms: `magmom`,
+  tags: `tag`,
+  momenta: `momentum`,
+} as const satisfies Record<string, string>
+
+type AseArrayName = keyof typeof ASE_ARRAY_PROPERTIES
+
+// Per-site selective dynamics (true = free to move, as in POSCAR) from ASE constraints.
+// Other constraint types have no site-level equivalent and are skipped with a warning.
+function constraints_to_selective_dynamics(
+  constraints: AseConstraint[],
+  n_sites: number,
+): ([boolean, boolean, boolean] | undefined)[] {
+  const dynamics: ([boolean, boolean, boolean] | undefined)[] = Array(n_sites).fill(undefined)
+  const as_indices = (val: unknown): number[] => (Array.isArray(val) ? val : [val]).map(Number)
+  for (const { name, kwargs } of constraints) {
+    if (name === `FixAtoms`) {
+      for (const idx of as_indices(kwargs.indices)) dynamics[idx] = [false, false, false]
+    } else if (name === `FixCartesian`) {
+      const mask = (kwargs.mask ?? [true, true, true]) as boolean[]
+      for (const idx of as_indices(kwargs.a)) {
+        dynamics[idx] = [!mask[0], !mask[1], !mask[2]]
+      }
+    } else console.warn(`Skipping unsupported ASE constraint ${name}`)
+  }
+  return dynamics
+}
+
+// Structure from an ASE Atoms dict. Crystals need a non-zero cell; an all-zero or missing
+// cell gives a molecule.
+export function from_ase_atoms(atoms: AseAtomsDict): AnyStructure {
+  const { numbers, positions } = atoms
+  if (numbers.length !== positions.length) {
+    throw new Error(
+      `ASE numbers and positions differ in length: ${numbers.length} vs ${positions.length}`,
+    )
+  }
+  const has_cell = atoms.cell?.length === 3 && atoms.cell.flat().some((val) => val !== 0)
+  const cell = has_cell ? (atoms.cell as Matrix3x3) : null
+  const cart_to_frac = cell ? math.create_cart_to_frac(cell) : null
+  const dynamics = constraints_to_selective_dynamics(atoms.constraints ?? [], numbers.length)
+
+  const sites: Site[] = numbers.map((number, idx) => {
+    const element = ATOMIC_NUMBER_TO_SYMBOL[number]
+    if (!element) throw new Error(`Unknown atomic number ${number} at ASE atom ${idx}`)
+    const xyz = positions[idx].slice(0, 3) as Vec3
+    const properties: Record<string, unknown> = {}
+    for (const [name, key] of Object.entries(ASE_ARRAY_PROPERTIES)) {
+      const value = atoms[name as AseArrayName]?.[idx]
+      if (value !== undefined) properties[key] = value
+    }
+    if (dynamics[idx]) properties.selective_dynamics = dynamics[idx]
+    const abc = cart_to_frac ? cart_to_frac(xyz) : ([0, 0, 0] as Vec3)
+    return make_site(element, abc, xyz, `${element}${idx + 1}`, properties)
+  })
+
+  const info = atoms.info && { properties: atoms.info }
+  if (!cell) return { sites, ...info }
+  const pbc = (atoms.pbc?.length === 3 ? atoms.pbc : [true, true, true]) as Pbc
+  return { sites, lattice: { matrix: cell, pbc, ...math.calc_lattice_params(cell) }, ...info }
+}
+
+// ASE Atoms dict of a structure. Arrays are included when any site has the matching
+// property (filling zeros for the rest, as ASE arrays cover all atoms), and fully fixed
+// or partially fixed sites become FixAtoms and FixCartesian constraints.
+export function to_ase_atoms(structure: AnyStructure): AseAtomsDict {
+  const { sites } = structure
+  const numbers = sites.map((site, idx) => {
+    const element = get_majority_element(site)
+    const number = element && SYMBOL_TO_ATOMIC_NUMBER[element]
+    if (!number) throw new Error(`No atomic number for site ${idx} (${element})`)
+    return number
+  })
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const atoms: AseAtomsDict = {
+    numbers,
+    positions: sites.map((site) => [...site.xyz]),
+    cell: (lattice?.matrix ?? [[0, 0, 0], [0, 0, 0], [0, 0, 0]]).map((row) => [...row]),
+    pbc: lattice ? [...lattice.pbc] : [false, false, false],
+  }
+
+  for (const [name, key] of Object.entries(ASE_ARRAY_PROPERTIES)) {
+    const values = sites.map((site) => site.properties?.[key])
+    const first = values.find((val) => val !== undefined)
+    if (first === undefined) continue
+    const fill = Array.isArray(first) ? first.map(() => 0) : 0
+    Object.assign(atoms, { [name]: values.map((val) => val ?? fill) })
+  }
+
+  const fixed: number[] = []
+  const constraints: AseConstraint[] = []
+  sites.forEach((site, idx) => {
+    const dynamics = site.properties?.selective_dynamics as boolean[] | undefined
+    if (!dynamics || dynamics.every(Boolean)) return
+    if (!dynamics.some(Boolean)) fixed.push(idx)
+    else {
+      const mask = dynamics.map((free) => !free)
+      constraints.push({ name: `FixCartesian`, kwargs: { a: [idx], mask } })
+    }
+  })
+  if (fixed.length) constraints.unshift({ name: `FixAtoms`, kwargs: { indices: fixed } })
+  if (constraints.length) atoms.constraints = constraints
+  if (structure.properties) atoms.info = { ...structure.properties }
+  return atoms
+}
@@ -0,0 +1,162 @@
+// Struct-of-arrays atoms for very large models (millions of atoms, e.g. dislocation or
+// grain boundary cells for MD) where Site objects cost hundreds of bytes per atom. An
+// AtomArrays holds 25 bytes per atom: supercells are replicated straight into typed
+// arrays, neighbor pairs are streamed with for_each_neighbor_pair and files are written
+// as chunks of lines instead of one string.
+import {
+  ATOMIC_NUMBER_TO_SYMBOL,
+  ATOMIC_WEIGHTS,
+  SYMBOL_TO_ATOMIC_NUMBER,
+} from '$lib/composition/parse'
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import { lammps_box_matrix } from './export'
+import type { AnyStructure } from './index'
+import type { Pbc } from './pbc'
+
+export type AtomArrays = {
+  numbers: Uint8Array // atomic number of each atom's majority species, 0 if unknown
+  positions: Float64Array // [n_atoms, 3] Cartesian coordinates in Å
+  lattice: { matrix: Matrix3x3; pbc: Pbc } | null // null for molecules
+}
+
+export type AtomArraysWriteOptions = {
+  chunk_size?: number // atom lines per yielded chunk (default 100 000)
+  title?: string // extXYZ comment prefix or LAMMPS title line (default Structure)
+}
+
+export function structure_to_atom_arrays(structure: AnyStructure): AtomArrays {
+  const numbers = Uint8Array.from(structure.sites, (site) => {
+    const element = get_majority_element(site)
+    return element ? (SYMBOL_TO_ATOMIC_NUMBER[element] ?? 0) : 0
+  })
+  const positions = new Float64Array(3 * structure.sites.length)
+  structure.sites.forEach(({ xyz }, idx) => positions.set(xyz, 3 * idx))
+  const lattice =
+    `lattice` in structure
+      ? { matrix: structure.lattice.matrix, pbc: structure.lattice.pbc }
+      : null
+  return { numbers, positions, lattice }
+}
+
+// n_a × n_b × n_c supercell of a crystal in the atom order of make_supercell (cells with
+// a varying fastest, then the sites of each cell), without creating Site objects
+export function replicate_atom_arrays(
+  structure: AnyStructure | AtomArrays,
+  scaling: Vec3,
+): AtomArrays {
+  const unit = `sites` in structure ? structure_to_atom_arrays(structure) : structure
+  if (!unit.lattice) throw new Error(`Cannot replicate atoms without a lattice`)
+  if (!scaling.every((count) => Number.isInteger(count) && count > 0)) {
+    throw new Error(`scaling must be 3 positive integers, got ${scaling.join(`, `)}`)
+  }
+  const [n_a, n_b, n_c] = scaling
+  const n_unit = unit.numbers.length
+  const numbers = new Uint8Array(n_unit * n_a * n_b * n_c)
+  const positions = new Float64Array(3 * numbers.length)
+  const [[ax, ay, az], [bx, by, bz], [cx, cy, cz]] = unit.lattice.matrix
+  let offset = 0
+  for (let kk = 0; kk < n_c; kk++) {
+    for (let jj = 0; jj < n_b; jj++) {
+      for (let ii = 0; ii < n_a; ii++) {
+        const shift = [
+          ii * ax + jj * bx + kk * cx,
+          ii * ay + jj * by + kk * cy,
+          ii * az + jj * bz + kk * cz,
+        ]
+        numbers.set(unit.numbers, offset)
+        for (let idx = 0; idx < 3 * n_unit; idx++) {
+          positions[3 * offset + idx] = unit.positions[idx] + shift[idx % 3]
+        }
+        offset += n_unit
+      }
+    }
+  }
+  const matrix = math.scale_lattice_matrix(unit.lattice.matrix, scaling)
+  return { numbers, positions, lattice: { matrix, pbc: unit.lattice.pbc } }
+}
+
+const element_symbol = (number: number): string => ATOMIC_NUMBER_TO_SYMBOL[number] ?? `X`
+
+// Extended XYZ file (same layout as structure_to_xyz_str) as chunks of at most
+// chunk_size atom lines, e.g. to pipe into a file stream or Blob
+export function* atom_arrays_to_extxyz(
+  atoms: AtomArrays,
+  options: AtomArraysWriteOptions = {},
+): Generator<string> {
+  const { chunk_size = 100_000, title = `Structure` } = options
+  const { numbers, positions, lattice } = atoms
+  const lattice_str = lattice
+    ? ` Lattice="${lattice.matrix.flat().map((val) => val.toFixed(8)).join(` `)}"`
+    : ``
+  yield `${numbers.length}\n${title}${lattice_str} Properties=species:S:1:pos:R:3\n`
+  for (let start = 0; start < numbers.length; start += chunk_size) {
+    const lines: string[] = []
+    for (let idx = start; idx < Math.min(start + chunk_size, numbers.length); idx++) {
+      const [x, y, z] = [0, 1, 2].map((axis) => positions[3 * idx + axis].toFixed(6))
+      lines.push(`${element_symbol(numbers[idx])} ${x} ${y} ${z}\n`)
+    }
+    yield lines.join(``)
+  }
+}
+
+// LAMMPS data file (metal units, atomic style) like structure_to_lammps_data_str, as
+// chunks of at most chunk_size atom lines. Atom types are numbered by first appearance of
+// each element and positions are rotated into LAMMPS' restricted triclinic box.
+export function* atom_arrays_to_lammps_data(
+  atoms: AtomArrays,
+  options: AtomArraysWriteOptions = {},
+): Generator<string> {
+  const { chunk_size = 100_000, title = `Structure` } = options
+  const { numbers, positions, lattice } = atoms
+  if (!lattice) throw new Error(`No lattice information for LAMMPS data export`)
+  if (!(math.det_3x3(lattice.matrix) > 0)) {
+    throw new Error(`LAMMPS data export requires a right-handed lattice`)
+  }
+  const box = lammps_box_matrix(lattice.matrix)
+  // Cartesian → fractional → Cartesian in the rotated box, as one matrix
+  const rotation = math.dot(
+    math.transpose_3x3_matrix(box),
+    math.create_cart_to_frac_matrix(lattice.matrix),
+  )
+  const fmt = (val: number): string => String(Number(val.toPrecision(12)))
+
+  const type_ids = new Map<number, number>()
+  for (const number of numbers) {
+    if (!type_ids.has(number)) type_ids.set(number, type_ids.size + 1)
+  }
+  const mass_lines = [...type_ids].map(([number, type_id]) => {
+    const element = element_symbol(number)
+    const weight = ATOMIC_WEIGHTS.get(element as ElementSymbol)
+    if (weight === undefined) throw new Error(`Unknown element for LAMMPS export: ${element}`)
+    return `${type_id} ${fmt(weight)} # ${element}`
+  })
+  const [[lx], [xy, ly], [xz, yz, lz]] = box
+  const header = [
+    `${title} (written by MatterViz, units metal)`,
+    ``,
+    `${numbers.length} atoms`,
+    `${type_ids.size} atom types`,
+    ``,
+    `0 ${fmt(lx)} xlo xhi`,
+    `0 ${fmt(ly)} ylo yhi`,
+    `0 ${fmt(lz)} zlo zhi`,
+  ]
+  if ([xy, xz, yz].some((tilt) => Math.abs(tilt) > 1e-10)) {
+    header.push(`${fmt(xy)} ${fmt(xz)} ${fmt(yz)} xy xz yz`)
+  }
+  header.push(``, `Masses`, ``, ...mass_lines, ``, `Atoms # atomic`, ``)
+  yield `${header.join(`\n`)}\n`
+
+  for (let start = 0; start < numbers.length; start += chunk_size) {
+    const lines: string[] = []
+    for (let idx = start; idx < Math.min(start + chunk_size, numbers.length); idx++) {
+      const [x, y, z] = [positions[3 * idx], positions[3 * idx + 1], positions[3 * idx + 2]]
+      const coords = rotation.map((row) => fmt(row[0] * x + row[1] * y + row[2] * z))
+      lines.push(`${idx + 1} ${type_ids.get(numbers[idx])} ${coords.join(` `)}\n`)
+    }
+    yield lines.join(``)
+  }
+}
@@ -0,0 +1,40 @@
+// Bond valence sums (Brown & Altermatt, Acta Cryst. B 41, 244, 1985): V_i = Σ_j
+// exp((R0_ij − r_ij) / b) over the neighbors of each site, a quick estimate of the
+// oxidation state of cations (and the magnitude for anions)
+import type { AnyStructure } from './index'
+import { get_majority_element } from './bonding'
+import { get_neighbor_list } from './neighbors'
+
+export type BondValenceOptions = {
+  // bond valence parameters in Å per element pair, e.g. { 'Fe-O': 1.759 } (any order),
+  // only listed pairs contribute
+  r0: Record<string, number>
+  b?: number // Å, bond softness (default 0.37)
+  cutoff?: number // Å, neighbor search radius (default 3.5)
+}
+
+// Bond valence sum of every site over all neighbor images within cutoff (0 for sites
+// without a listed pair in range)
+export function compute_bv_sums(
+  structure: AnyStructure,
+  options: BondValenceOptions,
+): number[] {
+  const { b = 0.37, cutoff = 3.5 } = options
+  if (!(b > 0)) throw new Error(`b must be positive, got ${b}`)
+  const r0 = new Map<string, number>()
+  for (const [pair, value] of Object.entries(options.r0)) {
+    const [el_1, el_2] = pair.split(`-`)
+    if (!el_2) throw new Error(`Bond valence pairs must look like 'Fe-O', got '${pair}'`)
+    r0.set(`${el_1}-${el_2}`, value).set(`${el_2}-${el_1}`, value)
+  }
+  const { sites } = structure
+  if (sites.length === 0) return []
+  const elements = sites.map((site) => get_majority_element(site) ?? ``)
+
+  return get_neighbor_list(structure, cutoff).map((neighbors, idx) =>
+    neighbors.reduce((total, { site_idx: nb_idx, distance }) => {
+      const pair_r0 = r0.get(`${elements[idx]}-${elements[nb_idx]}`)
+      return pair_r0 === undefined ? total : total + Math.exp((pair_r0 - distance) / b)
+    }, 0),
+  )
+}
@@ -8,7 +8,8 @@ import type { AnyStructure, BondOrder, BondPair, Site, StructureBond } from '$li
 
 type SpatialGrid = Map<number, number[]>
 
-const covalent_radii = new Map<string, number>(
+// Covalent radii (Å) by element symbol, for elements that have one
+export const covalent_radii = new Map<string, number>(
   element_data.flatMap((el) =>
     el.covalent_radius === null ? [] : [[el.symbol, el.covalent_radius]],
   ),
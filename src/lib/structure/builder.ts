@@ -0,0 +1,163 @@
+// Fluent construction of crystals and molecules for library consumers, e.g.
+// new StructureBuilder().with_lattice(cell).add_site(`Na`, [0, 0, 0]).build(). Inputs are
+// only collected until build(), which checks them all at once and reports every problem
+// in one error instead of failing on the first bad site.
+import type { ElementSymbol } from '$lib/element'
+import { is_elem_symbol } from '$lib/element/helpers'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, LatticeParams, Pbc, Site, Species } from './index'
+import { get_neighbor_list } from './neighbors'
+
+export type BuilderSiteOptions = {
+  cartesian?: boolean // coords are Cartesian (Å) instead of fractional (default false)
+  label?: string // default element symbol + 1-based site number, e.g. Na1
+  oxidation_state?: number // of every species on the site (default 0)
+  properties?: Record<string, unknown>
+}
+
+export type BuildOptions = {
+  // reject sites closer than this (Å), including periodic images (default no check)
+  min_distance?: number
+}
+
+type SiteInput = {
+  // element → occupancy, several entries for disordered sites
+  occupancies: Record<string, number>
+  coords: Vec3
+  options: BuilderSiteOptions
+}
+
+export class StructureBuilder {
+  private lattice: { matrix: Matrix3x3; pbc: Pbc } | null = null
+  private site_inputs: SiteInput[] = []
+  private charge: number | undefined = undefined
+  private properties: Record<string, unknown> = {}
+
+  // lattice vectors as matrix rows or cell parameters (Å and degrees). Without a
+  // lattice, build() returns a molecule.
+  with_lattice(lattice: Matrix3x3 | LatticeParams, pbc: Pbc = [true, true, true]): this {
+    const matrix = Array.isArray(lattice)
+      ? lattice
+      : math.cell_to_lattice_matrix(
+          lattice.a,
+          lattice.b,
+          lattice.c,
+          lattice.alpha,
+          lattice.beta,
+          lattice.gamma,
+        )
+    this.lattice = { matrix, pbc }
+    return this
+  }
+
+  // species is an element symbol or element → occupancy for partially occupied sites
+  add_site(
+    species: string | Record<string, number>,
+    coords: Vec3,
+    options: BuilderSiteOptions = {},
+  ): this {
+    const occupancies = typeof species === `string` ? { [species]: 1 } : species
+    this.site_inputs.push({ occupancies, coords, options })
+    return this
+  }
+
+  with_charge(charge: number): this {
+    this.charge = charge
+    return this
+  }
+
+  with_property(key: string, value: unknown): this {
+    this.properties[key] = value
+    return this
+  }
+
+  // All problems that would make build() throw, empty if the inputs are valid
+  validate(options: BuildOptions = {}): string[] {
+    const errors: string[] = []
+    if (this.lattice) {
+      const { matrix, pbc } = this.lattice
+      if (!matrix.flat().every(Number.isFinite)) {
+        errors.push(`Lattice matrix must be finite, got ${JSON.stringify(matrix)}`)
+      } else if (!(Math.abs(math.det_3x3(matrix)) > 1e-8)) {
+        errors.push(`Lattice vectors are linearly dependent (zero cell volume)`)
+      }
+      if (pbc.length !== 3) errors.push(`pbc needs 3 entries, got ${pbc.length}`)
+    }
+    if (this.site_inputs.length === 0) errors.push(`Structure needs at least one site`)
+    this.site_inputs.forEach(({ occupancies, coords, options: site_options }, idx) => {
+      const entries = Object.entries(occupancies)
+      if (entries.length === 0) errors.push(`Site ${idx} has no species`)
+      for (const [element, occu] of entries) {
+        if (!is_elem_symbol(element)) errors.push(`Site ${idx} has unknown element ${element}`)
+        if (!(occu > 0 && occu <= 1)) {
+          errors.push(`Site ${idx} occupancy of ${element} must be in (0, 1], got ${occu}`)
+        }
+      }
+      const total_occu = entries.reduce((sum, [, occu]) => sum + occu, 0)
+      if (total_occu > 1 + 1e-8) {
+        errors.push(`Site ${idx} occupancies sum to ${total_occu} > 1`)
+      }
+      if (coords.length !== 3 || !coords.every(Number.isFinite)) {
+        errors.push(`Site ${idx} needs 3 finite coordinates, got ${JSON.stringify(coords)}`)
+      }
+      if (!this.lattice && !site_options.cartesian) {
+        errors.push(`Site ${idx} has fractional coordinates but no lattice was set`)
+      }
+    })
+    if (this.charge !== undefined && !Number.isFinite(this.charge)) {
+      errors.push(`charge must be finite, got ${this.charge}`)
+    }
+    const { min_distance } = options
+    if (errors.length === 0 && min_distance !== undefined && min_distance > 0) {
+      const neighbor_list = get_neighbor_list(this.assemble(), min_distance)
+      neighbor_list.forEach((neighbors, idx) => {
+        const close = neighbors.find(({ site_idx }) => site_idx >= idx)
+        if (close) {
+          errors.push(
+            `Sites ${idx} and ${close.site_idx} are ${close.distance.toFixed(4)} Å apart, ` +
+              `closer than min_distance=${min_distance}`,
+          )
+        }
+      })
+    }
+    return errors
+  }
+
+  // Crystal if a lattice was set, else molecule. Throws listing all validation errors.
+  build(options: BuildOptions = {}): AnyStructure {
+    const errors = this.validate(options)
+    if (errors.length > 0) throw new Error(`Invalid structure: ${errors.join(`; `)}`)
+    return this.assemble()
+  }
+
+  private assemble(): AnyStructure {
+    const converters = this.lattice && math.create_lattice_converters(this.lattice.matrix)
+    const sites = this.site_inputs.map(({ occupancies, coords, options }, idx): Site => {
+      const { cartesian = false, oxidation_state = 0, properties = {} } = options
+      const species = Object.entries(occupancies).map(
+        ([element, occu]): Species => ({
+          element: element as ElementSymbol,
+          occu,
+          oxidation_state,
+        }),
+      )
+      const xyz = cartesian || !converters ? coords : converters.frac_to_cart(coords)
+      const frac = cartesian && converters ? converters.cart_to_frac(coords) : coords
+      const abc: Vec3 = converters ? frac : [0, 0, 0]
+      const label = options.label ?? `${species[0].element}${idx + 1}`
+      return { species, abc, xyz, label, properties: { ...properties } }
+    })
+    const extras = {
+      ...(this.charge !== undefined && { charge: this.charge }),
+      ...(Object.keys(this.properties).length > 0 && { properties: { ...this.properties } }),
+    }
+    if (!this.lattice) return { sites, ...extras }
+    const { matrix, pbc } = this.lattice
+    return {
+      sites,
+      lattice: { matrix, pbc, ...math.calc_lattice_params(matrix) },
+      ...extras,
+    }
+  }
+}
@@ -0,0 +1,350 @@
+// Bond-valence site energy (BVSE) maps for screening ionic conductors (Chen & Adams,
+// Acta Cryst. B 73, 1011, 2017): the energy of a probe mobile ion on a grid over the cell
+// from Morse-type bonds to anions plus screened Coulomb repulsion from framework cations.
+// The lowest energy at which accessible regions connect to their periodic images
+// estimates the migration barrier for 1D, 2D and 3D transport.
+import { COULOMB_EV_A } from '$lib/constants'
+import type { ElementSymbol } from '$lib/element'
+import type { VolumetricData } from '$lib/isosurface/types'
+import { grid_data_range } from '$lib/isosurface/types'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import type { Crystal } from './index'
+
+export type BvseBondParams = {
+  b: number // Å, bond softness (softBV b, Morse α = 1 / b)
+  r_min: number // Å, equilibrium distance of the mobile ion-anion bond
+  d0: number // eV, bond dissociation energy
+}
+
+export type BvseOptions = {
+  mobile_ion: ElementSymbol // sites of this element are removed from the framework
+  // Morse parameters of the mobile ion's bonds per anion element, e.g. from the softBV
+  // table. Sites of other elements repel the probe if their charge is positive.
+  bonds: Partial<Record<ElementSymbol, BvseBondParams>>
+  mobile_charge?: number // oxidation state of the mobile ion (default 1)
+  // cation oxidation states (default: the sites' species oxidation states)
+  charges?: Partial<Record<ElementSymbol, number>>
+  screening?: number // Å, erfc screening length of the cation repulsion (default 1)
+  resolution?: number // Å, target grid spacing (default 0.2)
+  cutoff?: number // Å, interaction range (default 8)
+  max_energy?: number // eV above the minimum at which the map is capped (default 5)
+}
+
+// energies in eV above the minimum for 1D, 2D and 3D percolation, null if the capped
+// map doesn't percolate in that many dimensions
+export type PercolationThresholds = [number | null, number | null, number | null]
+
+export type BvseMap = {
+  volume: VolumetricData // site energies in eV relative to the global minimum
+  minimum: Vec3 // fractional grid point of the global minimum
+  thresholds: PercolationThresholds
+}
+
+export type MigrationPath = {
+  barrier: number // eV, highest energy along the path
+  abc: Vec3[] // unwrapped fractional grid points from start to its periodic image
+  energies: number[] // eV per point
+}
+
+type Grid = { values: Float64Array; dims: Vec3 }
+
+const flat_grid = ({ grid, grid_dims }: VolumetricData): Grid => ({
+  values: Float64Array.from(grid.flat(2)),
+  dims: grid_dims,
+})
+
+// Each grid point's 6 face neighbors with the cell shift crossed when stepping there
+function grid_neighbors(dims: Vec3, idx: number): { idx: number; shift: Vec3 }[] {
+  const [, ny, nz] = dims
+  const coords = [Math.floor(idx / (ny * nz)), Math.floor(idx / nz) % ny, idx % nz]
+  const neighbors: { idx: number; shift: Vec3 }[] = []
+  for (let axis = 0; axis < 3; axis++) {
+    for (const step of [-1, 1]) {
+      const next = [...coords]
+      const shift: Vec3 = [0, 0, 0]
+      next[axis] += step
+      if (next[axis] < 0 || next[axis] >= dims[axis]) {
+        shift[axis] = step
+        next[axis] -= step * dims[axis]
+      }
+      neighbors.push({ idx: (next[0] * ny + next[1]) * nz + next[2], shift })
+    }
+  }
+  return neighbors
+}
+
+// Rank of a set of integer lattice vectors (at most 3)
+function vec_rank(vecs: Vec3[]): number {
+  if (vecs.length === 3) return math.det_3x3([vecs[0], vecs[1], vecs[2]]) !== 0 ? 3 : 2
+  if (vecs.length === 2) return math.cross_3d(vecs[0], vecs[1]).some(Boolean) ? 2 : 1
+  return vecs.length
+}
+
+// Lowest energies at which a connected region of {E ≤ threshold} wraps onto its own
+// periodic image along 1, 2 and 3 independent lattice directions. Flood fills grid points
+// in order of increasing energy with a union-find tracking each point's cell image
+// relative to its cluster root; a bond closing a loop with a nonzero image offset adds a
+// wrapping vector to the cluster. Values are relative to the grid minimum.
+export function percolation_thresholds(
+  volume: VolumetricData,
+  max_energy = Infinity,
+): PercolationThresholds {
+  const { values, dims } = flat_grid(volume)
+  const n_points = values.length
+  const parent = Int32Array.from({ length: n_points }, (_, idx) => idx)
+  const offset = new Int32Array(3 * n_points) // image of a point relative to its parent
+  const wraps = new Map<number, Vec3[]>() // independent wrapping vectors per root
+  const added = new Uint8Array(n_points)
+  const find = (idx: number): { root: number; image: Vec3 } => {
+    const path: number[] = []
+    let root = idx
+    while (parent[root] !== root) {
+      path.push(root)
+      root = parent[root]
+    }
+    // compress from the node next to the root outwards, accumulating images
+    const image: Vec3 = [0, 0, 0]
+    for (let pos = path.length - 1; pos >= 0; pos--) {
+      const node = path[pos]
+      for (let axis = 0; axis < 3; axis++) {
+        image[axis] += offset[3 * node + axis]
+        offset[3 * node + axis] = image[axis]
+      }
+      parent[node] = root
+    }
+    if (idx === root) return { root, image: [0, 0, 0] }
+    return { root, image: [0, 1, 2].map((axis) => offset[3 * idx + axis]) as Vec3 }
+  }
+  const add_wrap = (root: number, vec: Vec3) => {
+    const vecs = wraps.get(root) ?? []
+    if (vecs.length < 3 && vec_rank([...vecs, vec]) > vecs.length) vecs.push(vec)
+    wraps.set(root, vecs)
+    return vecs.length
+  }
+
+  const order = [...values.keys()].sort((idx_1, idx_2) => values[idx_1] - values[idx_2])
+  const min_val = values[order[0]] ?? 0
+  const thresholds: PercolationThresholds = [null, null, null]
+  let max_rank = 0
+  for (const idx of order) {
+    const energy = values[idx] - min_val
+    if (energy >= max_energy) break
+    added[idx] = 1
+    for (const { idx: nb_idx, shift } of grid_neighbors(dims, idx)) {
+      if (!added[nb_idx]) continue
+      const { root: root_1, image: image_1 } = find(idx)
+      const { root: root_2, image: image_2 } = find(nb_idx)
+      // image of root_2 in root_1's frame when the neighbor copy sits next to idx's copy
+      const rel = [0, 1, 2].map((axis) => image_1[axis] + shift[axis] - image_2[axis]) as Vec3
+      let rank = 0
+      if (root_1 === root_2) {
+        if (rel.some(Boolean)) rank = add_wrap(root_1, rel)
+      } else {
+        parent[root_2] = root_1
+        rel.forEach((val, axis) => (offset[3 * root_2 + axis] = val))
+        for (const vec of wraps.get(root_2) ?? []) rank = add_wrap(root_1, vec)
+        wraps.delete(root_2)
+      }
+      for (let dim = max_rank; dim < rank; dim++) thresholds[dim] = energy
+      max_rank = Math.max(max_rank, rank)
+    }
+    if (max_rank === 3) break
+  }
+  return thresholds
+}
+
+// Minimum-energy (minimax) migration path from a grid point to its periodic image one
+// lattice translation `direction` away, e.g. [0, 0, 1] for transport along c: the lowest
+// threshold at which both copies connect is found by bisection over the sorted energies,
+// then the shortest grid path below it. Starts at the global minimum by default. Null if
+// the copies don't connect at all.
+export function migration_path(
+  volume: VolumetricData,
+  direction: Vec3,
+  start?: Vec3,
+): MigrationPath | null {
+  const { values, dims } = flat_grid(volume)
+  const [, ny, nz] = dims
+  const n_points = values.length
+  if (!direction.every(Number.isInteger) || !direction.some(Boolean)) {
+    throw new Error(`direction must be a nonzero integer lattice vector, got ${direction}`)
+  }
+  let start_idx = 0
+  if (start) {
+    const [ix, iy, iz] = start.map((val, axis) => {
+      const steps = Math.round(val * dims[axis])
+      return ((steps % dims[axis]) + dims[axis]) % dims[axis]
+    })
+    start_idx = (ix * ny + iy) * nz + iz
+  } else {
+    for (let idx = 1; idx < n_points; idx++) {
+      if (values[idx] < values[start_idx]) start_idx = idx
+    }
+  }
+
+  // cell images reachable by the search: a box spanning the target plus one cell margin
+  const lo = direction.map((val) => Math.min(0, val) - 1)
+  const span = direction.map((val, axis) => Math.max(0, val) + 1 - lo[axis] + 1)
+  const n_images = span[0] * span[1] * span[2]
+  const encode = (idx: number, [ia, ib, ic]: Vec3) =>
+    idx + n_points * (((ia - lo[0]) * span[1] + ib - lo[1]) * span[2] + ic - lo[2])
+  const decode = (state: number): { idx: number; image: Vec3 } => {
+    const [idx, code] = [state % n_points, Math.floor(state / n_points)]
+    const image: Vec3 = [
+      Math.floor(code / (span[1] * span[2])) + lo[0],
+      (Math.floor(code / span[2]) % span[1]) + lo[1],
+      (code % span[2]) + lo[2],
+    ]
+    return { idx, image }
+  }
+  const target = encode(start_idx, direction)
+
+  // breadth-first search below a threshold, returning predecessors if the target is hit
+  const search = (threshold: number): Int32Array | null => {
+    if (values[start_idx] > threshold) return null
+    const prev = new Int32Array(n_points * n_images).fill(-1)
+    const source = encode(start_idx, [0, 0, 0])
+    prev[source] = source
+    const queue = [source]
+    for (let head = 0; head < queue.length; head++) {
+      const state = queue[head]
+      if (state === target) return prev
+      const { idx, image } = decode(state)
+      for (const { idx: nb_idx, shift } of grid_neighbors(dims, idx)) {
+        if (values[nb_idx] > threshold) continue
+        const nb_image = math.add(image, shift)
+        if (nb_image.some((val, axis) => val < lo[axis] || val >= lo[axis] + span[axis])) {
+          continue
+        }
+        const next = encode(nb_idx, nb_image)
+        if (prev[next] !== -1) continue
+        prev[next] = state
+        queue.push(next)
+      }
+    }
+    return null
+  }
+
+  const levels = [...new Set(values)].sort((val_1, val_2) => val_1 - val_2)
+  if (!search(levels[levels.length - 1])) return null
+  let [low, high] = [0, levels.length - 1]
+  while (low < high) {
+    const mid = Math.floor((low + high) / 2)
+    if (search(levels[mid])) high = mid
+    else low = mid + 1
+  }
+  const prev = search(levels[low])
+  if (!prev) return null
+
+  const states = [target]
+  while (prev[states[states.length - 1]] !== states[states.length - 1]) {
+    states.push(prev[states[states.length - 1]])
+  }
+  states.reverse()
+  const min_val = levels[0]
+  const abc: Vec3[] = []
+  const energies: number[] = []
+  for (const state of states) {
+    const { idx, image } = decode(state)
+    const coords = [Math.floor(idx / (ny * nz)), Math.floor(idx / nz) % ny, idx % nz]
+    abc.push(coords.map((val, axis) => val / dims[axis] + image[axis]) as Vec3)
+    energies.push(values[idx] - min_val)
+  }
+  return { barrier: levels[low] - min_val, abc, energies }
+}
+
+// BVSE landscape of a probe mobile ion over a periodic grid with spacing ≈ resolution,
+// with its percolation thresholds. Morse bonds E = D0 [(exp(α (Rmin − r)) − 1)² − 1] to
+// anions listed in `bonds`; cations repel as q_A q_B e² / r · erfc(r / screening).
+export function bvse_map(structure: Crystal, options: BvseOptions): BvseMap {
+  const {
+    mobile_ion,
+    bonds,
+    mobile_charge = 1,
+    charges = {},
+    screening = 1,
+    resolution = 0.2,
+    cutoff = 8,
+    max_energy = 5,
+  } = options
+  if (!(resolution > 0)) throw new Error(`resolution must be > 0, got ${resolution}`)
+  if (Object.keys(bonds).length === 0) throw new Error(`Need bond parameters for an anion`)
+  const { matrix } = structure.lattice
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+
+  type Center = { abc: Vec3; bond: BvseBondParams | null; charge: number }
+  const centers: Center[] = structure.sites.flatMap((site): Center[] => {
+    const element = get_majority_element(site)
+    if (!element || element === mobile_ion) return []
+    const bond = bonds[element] ?? null
+    const site_charge = site.species.reduce(
+      (sum, { occu, oxidation_state }) => sum + occu * oxidation_state,
+      0,
+    )
+    const charge = bond ? 0 : (charges[element] ?? site_charge)
+    return bond || charge > 0 ? [{ abc: site.abc, bond, charge }] : []
+  })
+  if (!centers.some(({ bond }) => bond)) {
+    throw new Error(`No framework sites match the anions in bonds`)
+  }
+
+  // lattice translations within reach of the cutoff from the minimum image
+  const reach = math.frac_cutoff_per_axis(matrix, cutoff).map((val) => Math.ceil(val))
+  const translations: Vec3[] = []
+  for (let na = -reach[0]; na <= reach[0]; na++) {
+    for (let nb = -reach[1]; nb <= reach[1]; nb++) {
+      for (let nc = -reach[2]; nc <= reach[2]; nc++) {
+        translations.push(frac_to_cart([na, nb, nc]))
+      }
+    }
+  }
+
+  const lengths = [0, 1, 2].map((axis) => Math.hypot(...matrix[axis]))
+  const grid_dims = lengths.map((len) => Math.max(4, Math.ceil(len / resolution))) as Vec3
+  const site_energy = (abc: Vec3): number => {
+    let energy = 0
+    for (const { abc: center, bond, charge } of centers) {
+      const delta = math.subtract(abc, center).map((val) => val - Math.round(val)) as Vec3
+      const base = frac_to_cart(delta)
+      for (const shift of translations) {
+        const dist = Math.hypot(base[0] + shift[0], base[1] + shift[1], base[2] + shift[2])
+        if (dist > cutoff) continue
+        if (bond) {
+          const morse = Math.exp((bond.r_min - dist) / bond.b) - 1
+          energy += bond.d0 * (morse * morse - 1)
+        } else {
+          const screened = math.erfc(dist / screening)
+          energy += (COULOMB_EV_A * mobile_charge * charge * screened) / dist
+        }
+      }
+    }
+    return energy
+  }
+  const [nx, ny, nz] = grid_dims
+  let [min_val, minimum]: [number, Vec3] = [Infinity, [0, 0, 0]]
+  const raw = Array.from({ length: nx }, (_, ix) =>
+    Array.from({ length: ny }, (_, iy) =>
+      Array.from({ length: nz }, (_, iz) => {
+        const abc: Vec3 = [ix / nx, iy / ny, iz / nz]
+        const energy = site_energy(abc)
+        if (energy < min_val) [min_val, minimum] = [energy, abc]
+        return energy
+      }),
+    ),
+  )
+  const grid = raw.map((plane) =>
+    plane.map((row) => row.map((val) => Math.min(val - min_val, max_energy))),
+  )
+  const volume: VolumetricData = {
+    grid,
+    grid_dims,
+    lattice: matrix,
+    origin: [0, 0, 0],
+    data_range: grid_data_range(grid),
+    periodic: true,
+    label: `BVSE ${mobile_ion}`,
+  }
+  return { volume, minimum, thresholds: percolation_thresholds(volume, max_energy) }
+}
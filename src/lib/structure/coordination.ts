@@ -0,0 +1,393 @@
+// Coordination sequences (successive neighbor-shell counts on the periodic bond graph, a
+// standard zeolite/COF framework fingerprint), distance-resolved neighbor shells (e.g.
+// for cluster-expansion cutoffs), smooth fractional coordination numbers and Voronoi
+// coordination from the cells of all sites
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, Crystal } from '$lib/structure'
+import { covalent_radii, get_majority_element } from './bonding'
+import type { Neighbor } from './neighbors'
+import { get_neighbor_list } from './neighbors'
+import type { AtomSelection } from './select'
+import { selection_mask } from './select'
+import { perceive_topology } from './topology'
+
+export interface CoordinationSequenceOptions {
+  tolerance?: number // Å added to covalent radii sums for bonds (see perceive_topology)
+  // elements that only bridge two nodes (e.g. O in zeolites, so T-O-T counts as one step
+  // between T atoms). Bridging sites are not counted themselves.
+  bridging_elements?: string[]
+}
+
+type GraphEdge = { site_idx: number; image: Vec3 }
+
+// Number of sites (periodic images counted separately) at bond-graph distance 1..n_shells
+// from the given site, via breadth-first search over the periodic bond graph
+export function coordination_sequence(
+  structure: AnyStructure,
+  site_idx: number,
+  n_shells: number,
+  options: CoordinationSequenceOptions = {},
+): number[] {
+  const { sites } = structure
+  if (site_idx < 0 || site_idx >= sites.length) {
+    throw new Error(`Site index ${site_idx} out of range for ${sites.length} sites`)
+  }
+  const bridging = new Set(options.bridging_elements ?? [])
+  const is_bridging = (idx: number) => bridging.has(get_majority_element(sites[idx]) ?? ``)
+  if (is_bridging(site_idx)) {
+    throw new Error(`Site ${site_idx} is a bridging element, pick a node site`)
+  }
+
+  const topology = perceive_topology(structure, {
+    tolerance: options.tolerance,
+    perceive_orders: false,
+  })
+  const bonded: GraphEdge[][] = sites.map(() => [])
+  for (const { site_idx_1, site_idx_2, cell_shift = [0, 0, 0] } of topology.bonds) {
+    bonded[site_idx_1].push({ site_idx: site_idx_2, image: cell_shift })
+    bonded[site_idx_2].push({ site_idx: site_idx_1, image: math.scale(cell_shift, -1) })
+  }
+  // contract node-bridge-node paths into single node-node edges
+  const edges: GraphEdge[][] = sites.map((_, idx) => {
+    if (is_bridging(idx)) return []
+    return bonded[idx].flatMap((edge) => {
+      if (!is_bridging(edge.site_idx)) return [edge]
+      return bonded[edge.site_idx].flatMap(({ site_idx: far_idx, image }) => {
+        const total = math.add(edge.image, image)
+        const is_return = far_idx === idx && total.every((shift) => shift === 0)
+        return is_return || is_bridging(far_idx) ? [] : [{ site_idx: far_idx, image: total }]
+      })
+    })
+  })
+
+  const key = ({ site_idx: idx, image }: GraphEdge) => `${idx}|${image.join(`,`)}`
+  const start: GraphEdge = { site_idx, image: [0, 0, 0] }
+  let [previous, current] = [new Set<string>(), new Map([[key(start), start]])]
+  const counts: number[] = []
+  for (let shell = 0; shell < n_shells; shell++) {
+    const next = new Map<string, GraphEdge>()
+    for (const node of current.values()) {
+      for (const edge of edges[node.site_idx]) {
+        const neighbor = { site_idx: edge.site_idx, image: math.add(node.image, edge.image) }
+        const nb_key = key(neighbor)
+        if (!previous.has(nb_key) && !current.has(nb_key)) next.set(nb_key, neighbor)
+      }
+    }
+    counts.push(next.size)
+    previous = new Set(current.keys())
+    current = next
+  }
+  return counts
+}
+
+export interface NeighborShell {
+  distance: number // mean distance of the shell, Å
+  neighbors: Neighbor[]
+}
+
+// Neighbors of a site grouped into distance shells (distances within tolerance share a
+// shell). The search radius doubles from 4 Å until n_shells complete shells are found
+// or max_cutoff is reached, so the last returned shell is never truncated.
+export function neighbor_shells(
+  structure: AnyStructure,
+  site_idx: number,
+  n_shells: number,
+  tolerance = 1e-3,
+  max_cutoff = 20,
+): NeighborShell[] {
+  if (site_idx < 0 || site_idx >= structure.sites.length) {
+    throw new Error(`Site index ${site_idx} out of range for ${structure.sites.length} sites`)
+  }
+  let cutoff = 4
+  while (true) {
+    const neighbors = get_neighbor_list(structure, cutoff)[site_idx]
+    const shells: NeighborShell[] = []
+    for (const neighbor of neighbors) {
+      const last = shells.at(-1)
+      if (last && neighbor.distance - last.neighbors[0].distance <= tolerance) {
+        last.neighbors.push(neighbor)
+      } else shells.push({ distance: neighbor.distance, neighbors: [neighbor] })
+    }
+    // only shells fully inside the cutoff are complete
+    const complete = shells.filter(
+      ({ neighbors: shell }) => shell[shell.length - 1].distance < cutoff - tolerance,
+    )
+    if (complete.length >= n_shells || cutoff >= max_cutoff) {
+      return complete.slice(0, n_shells).map(({ neighbors: shell }) => ({
+        distance: shell.reduce((sum, { distance }) => sum + distance, 0) / shell.length,
+        neighbors: shell,
+      }))
+    }
+    cutoff = Math.min(2 * cutoff, max_cutoff)
+  }
+}
+
+export type CutoffFunction = `cosine` | `polynomial`
+
+// Smooth cutoff f(r): 1 for r ≤ r_on, 0 for r ≥ r_cut and in between either the Behler
+// cosine 0.5·(cos(πx) + 1) or the polynomial 1 − 10x³ + 15x⁴ − 6x⁵ (zero first and second
+// derivatives at both ends) with x = (r − r_on) / (r_cut − r_on)
+export function smooth_cutoff(
+  distance: number,
+  r_cut: number,
+  cutoff_fn: CutoffFunction = `cosine`,
+  r_on = 0,
+): number {
+  if (distance <= r_on) return 1
+  if (distance >= r_cut) return 0
+  const x = (distance - r_on) / (r_cut - r_on)
+  if (cutoff_fn === `cosine`) return 0.5 * (Math.cos(Math.PI * x) + 1)
+  return 1 - x ** 3 * (10 - 15 * x + 6 * x * x)
+}
+
+// df/dr of smooth_cutoff, e.g. for forces from coordination-based collective variables
+export function smooth_cutoff_derivative(
+  distance: number,
+  r_cut: number,
+  cutoff_fn: CutoffFunction = `cosine`,
+  r_on = 0,
+): number {
+  if (distance <= r_on || distance >= r_cut) return 0
+  const width = r_cut - r_on
+  const x = (distance - r_on) / width
+  if (cutoff_fn === `cosine`) return (-0.5 * Math.PI * Math.sin(Math.PI * x)) / width
+  return (-30 * x * x * (1 - x) ** 2) / width
+}
+
+export interface SmoothCoordinationOptions {
+  cutoff_fn?: CutoffFunction // default cosine
+  // default cutoff (Å) for pairs missing from pair_cutoffs, else covalent radii sum + 0.45
+  r_cut?: number
+  pair_cutoffs?: Record<string, number> // per element pair, e.g. { 'Si-O': 2.2 } (any order)
+  r_on_fraction?: number // switching starts at r_on = r_on_fraction · r_cut (default 0)
+  neighbor_elements?: string[] // only count neighbors of these elements
+  neighbor_selection?: AtomSelection // only count neighbors among these sites
+}
+
+// Fractional coordination number of every site, CN_i = Σ_j f(r_ij) over all neighbors
+// (incl. periodic images). Continuous in the atomic positions, unlike integer counts
+// within a hard cutoff, so usable as collective variable in biased MD.
+export function smooth_coordination_numbers(
+  structure: AnyStructure,
+  options: SmoothCoordinationOptions = {},
+): number[] {
+  const { cutoff_fn = `cosine`, pair_cutoffs = {}, r_on_fraction = 0 } = options
+  const { sites } = structure
+  if (sites.length === 0) return []
+  const elements = sites.map((site) => get_majority_element(site) ?? ``)
+  const counted = options.neighbor_elements && new Set(options.neighbor_elements)
+  const selected = selection_mask(structure, options.neighbor_selection)
+
+  const pair_cutoff = new Map<string, number>()
+  for (const [pair, r_cut] of Object.entries(pair_cutoffs)) {
+    const [el_1, el_2] = pair.split(`-`)
+    pair_cutoff.set(`${el_1}-${el_2}`, r_cut).set(`${el_2}-${el_1}`, r_cut)
+  }
+  const cutoff_for = (el_1: string, el_2: string): number =>
+    pair_cutoff.get(`${el_1}-${el_2}`) ??
+    options.r_cut ??
+    (covalent_radii.get(el_1) ?? 0) + (covalent_radii.get(el_2) ?? 0) + 0.45
+  const unique = [...new Set(elements)]
+  const max_cutoff = Math.max(
+    ...unique.flatMap((el_1) => unique.map((el_2) => cutoff_for(el_1, el_2))),
+  )
+  if (!(max_cutoff > 0)) return sites.map(() => 0)
+
+  return get_neighbor_list(structure, max_cutoff).map((neighbors, idx) =>
+    neighbors.reduce((total, { site_idx: nb_idx, distance }) => {
+      if (!selected[nb_idx] || (counted && !counted.has(elements[nb_idx]))) return total
+      const r_cut = cutoff_for(elements[idx], elements[nb_idx])
+      return total + smooth_cutoff(distance, r_cut, cutoff_fn, r_on_fraction * r_cut)
+    }, 0),
+  )
+}
+
+export interface VoronoiFace {
+  site_idx: number // neighbor whose bisecting plane forms this face
+  image: Vec3
+  distance: number // Å to the neighbor image
+  area: number // Å²
+  solid_angle: number // sr subtended at the center site
+  weight: number // solid angle relative to the largest face of the cell
+}
+
+export interface VoronoiCell {
+  cn: number // faces with weight above min_weight
+  weighted_cn: number // sum of those weights
+  volume: number // Å³, cells of all sites fill the unit cell
+  faces: VoronoiFace[] // sorted by neighbor distance
+}
+
+export interface VoronoiOptions {
+  // faces with relative solid angle up to this don't count as bonds (default 0)
+  min_weight?: number
+  max_cutoff?: number // Å, largest neighbor search radius (default 20)
+}
+
+type CellFace = { neighbor_idx: number; vertices: Vec3[] } // neighbor_idx -1 for box faces
+
+// Points closer than this (Å) to a clipping plane count as lying on it
+const VORONOI_EPS = 1e-9
+
+// corners of a square in cyclic order
+const SQUARE_CORNERS = [
+  [-1, -1],
+  [1, -1],
+  [1, 1],
+  [-1, 1],
+]
+
+// Clip a convex polyhedron (faces with vertices in cyclic order) to n·x <= offset for a
+// unit normal n and close it with a new face from the cut points. Returns the input if
+// nothing is cut off.
+function clip_cell(
+  faces: CellFace[],
+  normal: Vec3,
+  offset: number,
+  neighbor_idx: number,
+): CellFace[] {
+  const side = (vertex: Vec3) => {
+    const val = math.dot(normal, vertex) - offset
+    return Math.abs(val) < VORONOI_EPS ? 0 : val
+  }
+  if (!faces.some(({ vertices }) => vertices.some((vertex) => side(vertex) > 0))) return faces
+  const cut_points: Vec3[] = []
+  const clipped: CellFace[] = []
+  for (const face of faces) {
+    const kept: Vec3[] = []
+    face.vertices.forEach((vertex, idx) => {
+      const next = face.vertices[(idx + 1) % face.vertices.length]
+      const [side_1, side_2] = [side(vertex), side(next)]
+      if (side_1 <= 0) kept.push(vertex)
+      if (side_1 === 0) cut_points.push(vertex)
+      if (side_1 * side_2 < 0) {
+        const fraction = side_1 / (side_1 - side_2)
+        const crossing = math.add(vertex, math.scale(math.subtract(next, vertex), fraction))
+        kept.push(crossing)
+        cut_points.push(crossing)
+      }
+    })
+    if (kept.length >= 3) clipped.push({ ...face, vertices: kept })
+  }
+  // order the cut points around their centroid to form the new face
+  const unique = cut_points.filter(
+    (point, idx) =>
+      cut_points.findIndex((other) => math.euclidean_dist(point, other) < 1e-7) === idx,
+  )
+  if (unique.length >= 3) {
+    const centroid = math.scale(math.add(...unique), 1 / unique.length)
+    const axis_u = math.normalize_vec(math.subtract(unique[0], centroid))
+    const axis_v = math.cross_3d(normal, axis_u)
+    const angle = (point: Vec3) => {
+      const rel = math.subtract(point, centroid)
+      return Math.atan2(math.dot(rel, axis_v), math.dot(rel, axis_u))
+    }
+    const vertices = unique.toSorted((point_1, point_2) => angle(point_1) - angle(point_2))
+    clipped.push({ neighbor_idx, vertices })
+  }
+  return clipped
+}
+
+// Area and solid angle (seen from the origin) of a planar convex polygon, the latter
+// from Van Oosterom-Strackee triangles fanning out from the first vertex
+function polygon_area_and_solid_angle(vertices: Vec3[]): [number, number] {
+  let vector_area: Vec3 = [0, 0, 0]
+  let solid_angle = 0
+  const [first] = vertices
+  const len_first = Math.hypot(...first)
+  for (let idx = 1; idx + 1 < vertices.length; idx++) {
+    const [vert_b, vert_c] = [vertices[idx], vertices[idx + 1]]
+    vector_area = math.add(
+      vector_area,
+      math.cross_3d(math.subtract(vert_b, first), math.subtract(vert_c, first)),
+    )
+    const [len_b, len_c] = [Math.hypot(...vert_b), Math.hypot(...vert_c)]
+    const triple = Math.abs(math.dot(first, math.cross_3d(vert_b, vert_c)))
+    const denom =
+      len_first * len_b * len_c +
+      math.dot(first, vert_b) * len_c +
+      math.dot(first, vert_c) * len_b +
+      math.dot(vert_b, vert_c) * len_first
+    solid_angle += 2 * Math.atan2(triple, denom)
+  }
+  return [0.5 * Math.hypot(...vector_area), solid_angle]
+}
+
+// Voronoi cells of all sites of a 3D-periodic crystal from one shared neighbor list: each
+// cell is a box clipped by the bisecting planes of neighbors in order of distance, until
+// the cell is closed and no farther neighbor can cut it. The search radius doubles from
+// 4 Å for sites whose cells aren't closed yet. Coordination numbers count faces whose
+// solid angle, relative to the largest face (pymatgen's VoronoiNN weights), exceeds
+// min_weight.
+export function voronoi_coordination(
+  structure: Crystal,
+  options: VoronoiOptions = {},
+): VoronoiCell[] {
+  const { min_weight = 0, max_cutoff = 20 } = options
+  if (!(`lattice` in structure) || !structure.lattice.pbc.every(Boolean)) {
+    throw new Error(`Voronoi coordination needs a structure periodic along all 3 axes`)
+  }
+  const n_sites = structure.sites.length
+  const cells: (VoronoiCell | null)[] = Array(n_sites).fill(null)
+  let cutoff = Math.min(4, max_cutoff)
+  while (true) {
+    const neighbor_list = get_neighbor_list(structure, cutoff)
+    neighbor_list.forEach((neighbors, site_idx) => {
+      if (cells[site_idx]) return
+      // start from a cube of half-width cutoff around the center
+      let faces: CellFace[] = [0, 1, 2].flatMap((axis) =>
+        [-1, 1].map((sign) => ({
+          neighbor_idx: -1,
+          vertices: SQUARE_CORNERS.map(([coord_u, coord_v]) => {
+            const vertex: Vec3 = [0, 0, 0]
+            vertex[axis] = sign * cutoff
+            vertex[(axis + 1) % 3] = coord_u * cutoff
+            vertex[(axis + 2) % 3] = coord_v * cutoff
+            return vertex
+          }),
+        })),
+      )
+      let max_radius = cutoff * Math.sqrt(3)
+      neighbors.forEach(({ displacement, distance }, neighbor_idx) => {
+        // the bisecting plane at distance / 2 misses the cell beyond its farthest vertex
+        if (distance / 2 > max_radius + VORONOI_EPS) return
+        const normal = math.scale(displacement, 1 / distance)
+        faces = clip_cell(faces, normal, distance / 2, neighbor_idx)
+        max_radius = Math.max(
+          ...faces.flatMap(({ vertices }) => vertices.map((vertex) => Math.hypot(...vertex))),
+        )
+      })
+      const closed = faces.every(({ neighbor_idx }) => neighbor_idx >= 0)
+      if (!closed || 2 * max_radius > cutoff) return
+
+      const measured = faces.map(({ neighbor_idx, vertices }) => {
+        const { site_idx: nb_idx, image, distance } = neighbors[neighbor_idx]
+        const [area, solid_angle] = polygon_area_and_solid_angle(vertices)
+        return { site_idx: nb_idx, image, distance, area, solid_angle, weight: 0 }
+      })
+      const voronoi_faces = measured
+        .filter(({ area }) => area > VORONOI_EPS)
+        .toSorted((face_1, face_2) => face_1.distance - face_2.distance)
+      const max_solid_angle = Math.max(...voronoi_faces.map(({ solid_angle }) => solid_angle))
+      for (const face of voronoi_faces) face.weight = face.solid_angle / max_solid_angle
+      const bonded = voronoi_faces.filter(({ weight }) => weight > min_weight)
+      cells[site_idx] = {
+        cn: bonded.length,
+        weighted_cn: bonded.reduce((sum, { weight }) => sum + weight, 0),
+        // pyramids from the center with the face as base and height distance / 2
+        volume: voronoi_faces.reduce(
+          (sum, { area, distance }) => sum + (area * distance) / 6,
+          0,
+        ),
+        faces: voronoi_faces,
+      }
+    })
+    const open_idx = cells.findIndex((cell) => cell === null)
+    if (open_idx === -1) return cells as VoronoiCell[]
+    if (cutoff >= max_cutoff) {
+      throw new Error(`Voronoi cell of site ${open_idx} not closed within ${max_cutoff} Å`)
+    }
+    cutoff = Math.min(2 * cutoff, max_cutoff)
+  }
+}
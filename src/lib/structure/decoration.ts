@@ -0,0 +1,192 @@
+// Prototype decoration for high-throughput screening: substitute compositions into a
+// library of prototype structures (one candidate per stoichiometry-compatible element
+// mapping), rescale the cells with ionic radii and drop duplicate candidates
+import type { CompositionType } from '$lib/composition'
+import { guess_oxidation_states } from '$lib/composition/enumerate'
+import type { ElementSymbol } from '$lib/element'
+import { element_by_symbol } from '$lib/element/data'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import type { FingerprintOptions } from './fingerprint'
+import { structure_fingerprint } from './fingerprint'
+import type { Crystal } from './index'
+import { get_neighbor_list } from './neighbors'
+
+export type DecorationOptions = {
+  // scale the cell so nearest-neighbor distances follow the change in ionic radii
+  // (default true)
+  rescale?: boolean
+  // oxidation states per element for picking ionic radii (default: a charge-neutral
+  // guess from ICSD oxidation states, see guess_oxidation_states)
+  oxidation_states?: Partial<Record<ElementSymbol, readonly number[]>>
+}
+
+export type DecorationCandidate = {
+  structure: Crystal
+  prototype: string // key of the prototype in the library
+  composition: CompositionType // target composition as passed in
+  substitution: Partial<Record<ElementSymbol, ElementSymbol>> // prototype → new element
+}
+
+export type PrototypeDecorationOptions = DecorationOptions & {
+  deduplicate?: boolean // drop candidates with equal structure fingerprints (default true)
+  fingerprint?: FingerprintOptions // options for the duplicate check (default {})
+}
+
+// Ionic radius (Å) of an element in an oxidation state, falling back to the mean over
+// its tabulated states, then its atomic radius, then 1 Å
+export function ionic_radius(element: ElementSymbol, oxidation_state?: number): number {
+  const data = element_by_symbol.get(element)
+  const radii = data?.ionic_radii ?? {}
+  const tabulated = oxidation_state === undefined ? undefined : radii[`${oxidation_state}`]
+  if (tabulated !== undefined) return tabulated
+  const values = Object.values(radii)
+  if (values.length > 0) return values.reduce((sum, val) => sum + val, 0) / values.length
+  return data?.atomic_radius ?? 1
+}
+
+const site_elements = (structure: Crystal): ElementSymbol[] =>
+  structure.sites.map((site, idx) => {
+    const element = get_majority_element(site)
+    if (site.species.length !== 1 || !element) {
+      throw new Error(`Prototype site ${idx} must be ordered (one species)`)
+    }
+    return element
+  })
+
+const count_elements = (elements: readonly ElementSymbol[]): CompositionType => {
+  const counts: CompositionType = {}
+  for (const element of elements) counts[element] = (counts[element] ?? 0) + 1
+  return counts
+}
+
+type RadiusLookup = (element: ElementSymbol, site_idx: number) => number
+
+// Element → ionic radius, from the sites' oxidation states if all are set, otherwise
+// from a charge-neutral guess
+function radius_lookup(
+  structure: Crystal,
+  elements: readonly ElementSymbol[],
+  oxidation_states?: DecorationOptions[`oxidation_states`],
+): RadiusLookup {
+  const site_states = structure.sites.map((site) => site.species[0].oxidation_state)
+  if (site_states.every((state) => state !== 0)) {
+    return (element, site_idx) => ionic_radius(element, site_states[site_idx])
+  }
+  const guess = guess_oxidation_states(count_elements(elements), { oxidation_states })
+  return (element) => ionic_radius(element, guess?.[element])
+}
+
+// Mean ratio of summed ionic radii of every site and its nearest neighbor after vs
+// before substitution
+function radius_scale(
+  prototype: Crystal,
+  old_radius: RadiusLookup,
+  new_radius: RadiusLookup,
+  old_elements: readonly ElementSymbol[],
+  new_elements: readonly ElementSymbol[],
+): number {
+  const atomic_length = Math.cbrt(prototype.lattice.volume / prototype.sites.length)
+  let cutoff = 1.5 * atomic_length
+  let neighbor_list = get_neighbor_list(prototype, cutoff)
+  for (let iter = 0; iter < 8 && neighbor_list.some((nbs) => nbs.length === 0); iter++) {
+    cutoff *= 1.5
+    neighbor_list = get_neighbor_list(prototype, cutoff)
+  }
+  let [sum, count] = [0, 0]
+  neighbor_list.forEach(([nearest], idx) => {
+    if (!nearest) return
+    const nb_idx = nearest.site_idx
+    const pair_sum = (radius: RadiusLookup, elements: readonly ElementSymbol[]) =>
+      radius(elements[idx], idx) + radius(elements[nb_idx], nb_idx)
+    sum += pair_sum(new_radius, new_elements) / pair_sum(old_radius, old_elements)
+    count++
+  })
+  return count > 0 ? sum / count : 1
+}
+
+function permutations<T>(items: readonly T[]): T[][] {
+  if (items.length <= 1) return [[...items]]
+  return items.flatMap((item, idx) =>
+    permutations(items.filter((_, jdx) => jdx !== idx)).map((rest) => [item, ...rest]),
+  )
+}
+
+// Substitute a composition into an ordered prototype: one structure per mapping of
+// prototype elements onto the composition's elements with matching stoichiometry,
+// e.g. NaCl → MgO gives Na→Mg, Cl→O and Na→O, Cl→Mg. Empty if the ratios don't fit.
+export function decorate_prototype(
+  prototype: Crystal,
+  composition: CompositionType,
+  options: DecorationOptions = {},
+): { structure: Crystal; substitution: Partial<Record<ElementSymbol, ElementSymbol>> }[] {
+  const { rescale = true, oxidation_states } = options
+  const old_elements = site_elements(prototype)
+  const old_counts = Object.entries(count_elements(old_elements)) as [ElementSymbol, number][]
+  const targets = (Object.entries(composition) as [ElementSymbol, number][]).filter(
+    ([, amount]) => amount > 0,
+  )
+  if (targets.length !== old_counts.length) return []
+  const [old_total, new_total] = [old_counts, targets].map((entries) =>
+    entries.reduce((sum, [, amount]) => sum + amount, 0),
+  )
+  const old_radius = radius_lookup(prototype, old_elements, oxidation_states)
+
+  return permutations(targets).flatMap((assigned) => {
+    const fits = old_counts.every(
+      ([, count], idx) => Math.abs(count / old_total - assigned[idx][1] / new_total) < 1e-8,
+    )
+    if (!fits) return []
+    const substitution = Object.fromEntries(
+      old_counts.map(([element], idx) => [element, assigned[idx][0]]),
+    ) as Partial<Record<ElementSymbol, ElementSymbol>>
+    const new_elements = old_elements.map((element) => substitution[element] ?? element)
+    const guess = guess_oxidation_states(count_elements(new_elements), { oxidation_states })
+    const new_radius = (element: ElementSymbol) => ionic_radius(element, guess?.[element])
+    const factor = rescale
+      ? radius_scale(prototype, old_radius, new_radius, old_elements, new_elements)
+      : 1
+    const { matrix: old_matrix } = prototype.lattice
+    const matrix = old_matrix.map((vec) => math.scale(vec, factor)) as math.Matrix3x3
+    const frac_to_cart = math.create_frac_to_cart(matrix)
+    const sites = prototype.sites.map((site, idx) => {
+      const element = new_elements[idx]
+      const oxidation_state = guess?.[element] ?? 0
+      return {
+        ...site,
+        species: [{ ...site.species[0], element, oxidation_state }],
+        xyz: frac_to_cart(site.abc),
+        label: element,
+        properties: { ...site.properties },
+      }
+    })
+    const lattice = { ...prototype.lattice, matrix, ...math.calc_lattice_params(matrix) }
+    return [{ structure: { ...prototype, lattice, sites }, substitution }]
+  })
+}
+
+// Batch decoration of every composition into every prototype of a library, keeping the
+// first of each set of candidates with equal structure fingerprints (e.g. the two
+// equivalent MgO decorations of rock salt) unless deduplicate is false
+export function decorate_prototypes(
+  prototypes: Record<string, Crystal>,
+  compositions: readonly CompositionType[],
+  options: PrototypeDecorationOptions = {},
+): DecorationCandidate[] {
+  const { deduplicate = true, fingerprint = {} } = options
+  const seen = new Set<string>()
+  const candidates: DecorationCandidate[] = []
+  for (const composition of compositions) {
+    for (const [prototype, structure] of Object.entries(prototypes)) {
+      for (const decorated of decorate_prototype(structure, composition, options)) {
+        if (deduplicate) {
+          const key = structure_fingerprint(decorated.structure, fingerprint)
+          if (seen.has(key)) continue
+          seen.add(key)
+        }
+        candidates.push({ ...decorated, prototype, composition })
+      }
+    }
+  }
+  return candidates
+}
@@ -0,0 +1,184 @@
+// Displacement and atomic strain fields around point defects: sites of a relaxed defect
+// supercell are mapped onto the pristine supercell, and each atom's local deformation
+// gradient is fitted to its mapped neighbors (Falk & Langer, Phys. Rev. E 57, 7192, 1998).
+// Results are attached as site fields so they export as extXYZ columns.
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import { structure_to_xyz_str } from './export'
+import type { Crystal } from './index'
+import { get_neighbor_list } from './neighbors'
+import { set_site_field } from './site-fields'
+
+export type DefectSiteMapping = {
+  mapping: number[] // pristine site index per defect site, -1 for interstitials
+  vacancies: number[] // pristine sites left without a defect site
+  substitutions: number[] // defect sites whose element differs from their pristine site
+}
+
+export type DefectMappingOptions = {
+  max_displacement?: number // Å, max shift of a site from its pristine site (default 1)
+}
+
+// Map every defect site onto the closest unclaimed pristine site (minimum image in the
+// pristine cell). Both structures must share the supercell, i.e. a fixed-cell relaxation.
+export function map_defect_sites(
+  pristine: Crystal,
+  defect: Crystal,
+  options: DefectMappingOptions = {},
+): DefectSiteMapping {
+  const { max_displacement = 1 } = options
+  const { matrix, pbc } = pristine.lattice
+  const converters = math.create_lattice_converters(matrix)
+  const candidates = defect.sites.flatMap((site, defect_idx) =>
+    pristine.sites.flatMap((ref_site, pristine_idx) => {
+      const disp = math.min_image_displacement(ref_site.xyz, site.xyz, matrix, converters, pbc)
+      const distance = Math.hypot(...disp)
+      return distance <= max_displacement ? [{ defect_idx, pristine_idx, distance }] : []
+    }),
+  )
+  candidates.sort((cand_1, cand_2) => cand_1.distance - cand_2.distance)
+  const mapping = defect.sites.map(() => -1)
+  const claimed = new Set<number>()
+  for (const { defect_idx, pristine_idx } of candidates) {
+    if (mapping[defect_idx] !== -1 || claimed.has(pristine_idx)) continue
+    mapping[defect_idx] = pristine_idx
+    claimed.add(pristine_idx)
+  }
+  const vacancies = [...pristine.sites.keys()].filter((idx) => !claimed.has(idx))
+  const substitutions = [...defect.sites.keys()].filter((idx) => {
+    const pristine_idx = mapping[idx]
+    if (pristine_idx === -1) return false
+    const element = get_majority_element(defect.sites[idx])
+    return element !== get_majority_element(pristine.sites[pristine_idx])
+  })
+  return { mapping, vacancies, substitutions }
+}
+
+export type DefectStrainOptions = DefectMappingOptions & {
+  cutoff: number // Å, pristine neighbors entering each atom's deformation gradient
+  // Cartesian defect center for the defect_distance field (default: the first vacancy,
+  // interstitial or substitution found)
+  defect_position?: Vec3
+}
+
+export type DefectStrainField = {
+  mapping: DefectSiteMapping
+  displacements: Vec3[] // Å per defect site, zero for interstitials
+  volumetric_strain: number[] // tr(E) / 3 of the Green-Lagrange strain E
+  shear_strain: number[] // von Mises shear invariant of E
+  distances: number[] | null // Å from the defect center, null without a defect
+  // defect structure with fields displacement, volumetric_strain, shear_strain and
+  // defect_distance (if known)
+  structure: Crystal
+}
+
+const zeros = (): Matrix3x3 => [
+  [0, 0, 0],
+  [0, 0, 0],
+  [0, 0, 0],
+]
+
+// Green-Lagrange strain of the least-squares deformation gradient F = Y X⁻¹ mapping
+// reference bond vectors d0 onto current ones d, with X = Σ d0 d0ᵀ and Y = Σ d d0ᵀ.
+// Null with fewer than 3 non-coplanar neighbors.
+function atomic_strain(pairs: { ref: Vec3; cur: Vec3 }[]): Matrix3x3 | null {
+  const [x_mat, y_mat] = [zeros(), zeros()]
+  for (const { ref, cur } of pairs) {
+    for (let idx = 0; idx < 3; idx++) {
+      for (let jdx = 0; jdx < 3; jdx++) {
+        x_mat[idx][jdx] += ref[idx] * ref[jdx]
+        y_mat[idx][jdx] += cur[idx] * ref[jdx]
+      }
+    }
+  }
+  const scale = Math.max(...x_mat.map((row, idx) => row[idx]))
+  if (!(scale > 0) || Math.abs(math.det_3x3(x_mat)) < 1e-8 * scale ** 3) return null
+  const x_inv = math.matrix_inverse_3x3(x_mat)
+  const grad = y_mat.map((row) =>
+    [0, 1, 2].map((jdx) => row.reduce((sum, val, kdx) => sum + val * x_inv[kdx][jdx], 0)),
+  )
+  return zeros().map((row, idx) =>
+    row.map((_, jdx) => {
+      const ftf = grad.reduce((sum, grad_row) => sum + grad_row[idx] * grad_row[jdx], 0)
+      return (ftf - (idx === jdx ? 1 : 0)) / 2
+    }),
+  ) as Matrix3x3
+}
+
+// Per-atom displacements and atomic strains of a relaxed defect supercell relative to the
+// pristine one. Interstitials and atoms with too few mapped neighbors get zero strain.
+export function defect_strain_field(
+  pristine: Crystal,
+  defect: Crystal,
+  options: DefectStrainOptions,
+): DefectStrainField {
+  const mapping = map_defect_sites(pristine, defect, options)
+  const { matrix, pbc } = pristine.lattice
+  const converters = math.create_lattice_converters(matrix)
+  const defect_of = pristine.sites.map(() => -1)
+  mapping.mapping.forEach((pristine_idx, defect_idx) => {
+    if (pristine_idx !== -1) defect_of[pristine_idx] = defect_idx
+  })
+  const displacements = defect.sites.map((site, idx): Vec3 => {
+    const pristine_idx = mapping.mapping[idx]
+    if (pristine_idx === -1) return [0, 0, 0]
+    const { xyz } = pristine.sites[pristine_idx]
+    return math.min_image_displacement(xyz, site.xyz, matrix, converters, pbc)
+  })
+
+  const neighbor_list = get_neighbor_list(pristine, options.cutoff)
+  const volumetric_strain: number[] = []
+  const shear_strain: number[] = []
+  mapping.mapping.forEach((pristine_idx, defect_idx) => {
+    const pairs = (pristine_idx === -1 ? [] : neighbor_list[pristine_idx]).flatMap(
+      ({ site_idx, displacement }) => {
+        const nb_idx = defect_of[site_idx]
+        if (nb_idx === -1 || nb_idx === defect_idx) return []
+        const rel = math.subtract(displacements[nb_idx], displacements[defect_idx])
+        return [{ ref: displacement, cur: math.add(displacement, rel) }]
+      },
+    )
+    const strain = atomic_strain(pairs)
+    if (!strain) {
+      volumetric_strain.push(0)
+      shear_strain.push(0)
+      return
+    }
+    const [[e_xx, e_xy, e_xz], [, e_yy, e_yz], [, , e_zz]] = strain
+    volumetric_strain.push((e_xx + e_yy + e_zz) / 3)
+    const normal = ((e_xx - e_yy) ** 2 + (e_yy - e_zz) ** 2 + (e_zz - e_xx) ** 2) / 6
+    shear_strain.push(Math.sqrt(e_xy ** 2 + e_xz ** 2 + e_yz ** 2 + normal))
+  })
+
+  const { vacancies, substitutions } = mapping
+  const interstitial = mapping.mapping.indexOf(-1)
+  let defect_position = options.defect_position ?? null
+  if (!defect_position && vacancies.length > 0) {
+    defect_position = pristine.sites[vacancies[0]].xyz
+  } else if (!defect_position && interstitial !== -1) {
+    defect_position = defect.sites[interstitial].xyz
+  } else if (!defect_position && substitutions.length > 0) {
+    defect_position = defect.sites[substitutions[0]].xyz
+  }
+  const center = defect_position
+  const distances = center
+    ? defect.sites.map(({ xyz }) =>
+        Math.hypot(...math.min_image_displacement(center, xyz, matrix, converters, pbc)),
+      )
+    : null
+
+  let structure = set_site_field(defect, `displacement`, displacements, `Å`)
+  structure = set_site_field(structure, `volumetric_strain`, volumetric_strain)
+  structure = set_site_field(structure, `shear_strain`, shear_strain)
+  if (distances) structure = set_site_field(structure, `defect_distance`, distances, `Å`)
+  return { mapping, displacements, volumetric_strain, shear_strain, distances, structure }
+}
+
+// Extended XYZ of the defect supercell with the strain field as per-atom columns, e.g. for
+// coloring atoms by shear strain in OVITO or the structure viewer
+export const defect_strain_xyz = (
+  pristine: Crystal,
+  defect: Crystal,
+  options: DefectStrainOptions,
+): string => structure_to_xyz_str(defect_strain_field(pristine, defect, options).structure)
@@ -0,0 +1,235 @@
+// Fixed-length vectors of cheap global structure descriptors as baseline features for ML
+// property models. Coordination, electronegativity contrasts, site orbits and the RDF all
+// come from a single neighbor list out to rdf_cutoff. Coulomb (Rupp et al. 2012) and sine
+// (Faber et al. 2015) matrices give per-structure pairwise descriptors.
+import { ATOMIC_WEIGHTS, SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import { element_by_symbol } from '$lib/element'
+import * as math from '$lib/math'
+import { jacobi_eigen } from '$lib/symmetry/continuous-measures'
+import { get_majority_element } from './bonding'
+import type { AnyStructure, Crystal } from './index'
+import { get_neighbor_list } from './neighbors'
+
+const AMU_PER_A3_TO_G_PER_CM3 = 1.66053907
+
+// feature order of featurize vectors
+export const DESCRIPTOR_NAMES = [
+  `density`, // g/cm³
+  `volume_per_atom`, // Å³
+  `packing_fraction`, // atomic sphere volume over cell volume
+  `mean_coordination`,
+  `std_coordination`,
+  `mean_en_diff`, // mean |Δχ| (Pauling) over coordinating pairs
+  `max_en_diff`,
+  `wyckoff_entropy`, // Shannon entropy (nats) of the site fractions per orbit
+  `rdf_mean`, // Å, moments of r weighted by g(r)
+  `rdf_std`,
+  `rdf_skewness`,
+  `rdf_kurtosis`, // excess kurtosis
+] as const
+export type DescriptorName = (typeof DESCRIPTOR_NAMES)[number]
+
+export type FeaturizeOptions = {
+  // neighbors within (1 + cn_tolerance) times a site's nearest-neighbor distance count as
+  // coordinating (default 0.15)
+  cn_tolerance?: number
+  rdf_cutoff?: number // Å (default 8)
+  rdf_bins?: number // (default 80)
+  // symmetry orbit index per site (e.g. moyo's dataset.orbits). Without it, sites are
+  // grouped by element and neighbor distances, which merges symmetry-equivalent sites.
+  orbits?: readonly number[]
+}
+
+// Mean, standard deviation, skewness and excess kurtosis of values under weights
+function weighted_moments(
+  values: number[],
+  weights: number[],
+): [number, number, number, number] {
+  const total = weights.reduce((sum, weight) => sum + weight, 0)
+  if (!(total > 0)) return [0, 0, 0, 0]
+  const central = (power: number, center: number) =>
+    values.reduce((sum, val, idx) => sum + weights[idx] * (val - center) ** power, 0) / total
+  const mean = central(1, 0)
+  const variance = central(2, mean)
+  if (!(variance > 0)) return [mean, 0, 0, 0]
+  const std = Math.sqrt(variance)
+  return [mean, std, central(3, mean) / std ** 3, central(4, mean) / variance ** 2 - 3]
+}
+
+// Descriptor vector of a crystal in DESCRIPTOR_NAMES order
+export function featurize(structure: Crystal, options: FeaturizeOptions = {}): Float64Array {
+  const { cn_tolerance = 0.15, rdf_cutoff = 8, rdf_bins = 80, orbits } = options
+  const { sites, lattice } = structure
+  const n_sites = sites.length
+  if (n_sites === 0) throw new Error(`Cannot featurize a structure without sites`)
+  if (orbits && orbits.length !== n_sites) {
+    throw new Error(`orbits has ${orbits.length} entries for ${n_sites} sites`)
+  }
+  const { volume } = lattice
+  let [n_atoms, mass, sphere_volume] = [0, 0, 0]
+  for (const { species } of sites) {
+    for (const { element, occu } of species) {
+      const radius = element_by_symbol.get(element)?.atomic_radius ?? 1
+      n_atoms += occu
+      mass += occu * (ATOMIC_WEIGHTS.get(element) ?? 0)
+      sphere_volume += (occu * 4 * Math.PI * radius ** 3) / 3
+    }
+  }
+
+  const neighbor_list = get_neighbor_list(structure, rdf_cutoff)
+  const elements = sites.map(get_majority_element)
+  const electroneg = elements.map((element) =>
+    element ? (element_by_symbol.get(element)?.electronegativity ?? null) : null,
+  )
+  const coordination: number[] = []
+  const en_diffs: number[] = []
+  const signatures: string[] = []
+  const bin_size = rdf_cutoff / rdf_bins
+  const pair_counts = Array(rdf_bins).fill(0)
+  neighbor_list.forEach((neighbors, site_idx) => {
+    const shell_radius = (neighbors[0]?.distance ?? 0) * (1 + cn_tolerance)
+    const shell = neighbors.filter(({ distance }) => distance <= shell_radius)
+    coordination.push(shell.length)
+    for (const { site_idx: neighbor_idx } of shell) {
+      const [en_1, en_2] = [electroneg[site_idx], electroneg[neighbor_idx]]
+      if (en_1 !== null && en_2 !== null) en_diffs.push(Math.abs(en_1 - en_2))
+    }
+    const shells = neighbors.slice(0, 12).map(({ distance }) => Math.round(distance * 100))
+    signatures.push(`${elements[site_idx] ?? `X`}|${shells.join(`,`)}`)
+    for (const { distance } of neighbors) {
+      if (distance > 0) pair_counts[Math.min(Math.floor(distance / bin_size), rdf_bins - 1)]++
+    }
+  })
+
+  const orbit_counts = new Map<string | number, number>()
+  for (const [site_idx, signature] of signatures.entries()) {
+    const orbit = orbits ? orbits[site_idx] : signature
+    orbit_counts.set(orbit, (orbit_counts.get(orbit) ?? 0) + 1)
+  }
+  const wyckoff_entropy = [...orbit_counts.values()].reduce(
+    (sum, count) => sum - (count / n_sites) * Math.log(count / n_sites),
+    0,
+  )
+
+  // g(r) with ideal-gas normalization by the site number density
+  const radii = pair_counts.map((_, idx) => (idx + 0.5) * bin_size)
+  const pair_density = n_sites ** 2 / volume
+  const g_r = pair_counts.map(
+    (count, idx) => count / (pair_density * 4 * Math.PI * radii[idx] ** 2 * bin_size),
+  )
+  const mean = (vals: number[]) =>
+    vals.length ? vals.reduce((sum, val) => sum + val, 0) / vals.length : 0
+  const mean_cn = mean(coordination)
+  const std_cn = Math.sqrt(mean(coordination.map((cn) => (cn - mean_cn) ** 2)))
+
+  return Float64Array.from([
+    (AMU_PER_A3_TO_G_PER_CM3 * mass) / volume,
+    volume / n_atoms,
+    sphere_volume / volume,
+    mean_cn,
+    std_cn,
+    mean(en_diffs),
+    Math.max(0, ...en_diffs),
+    wyckoff_entropy,
+    ...weighted_moments(radii, g_r),
+  ])
+}
+
+// featurize as a name → value record
+export const featurize_named = (
+  structure: Crystal,
+  options: FeaturizeOptions = {},
+): Record<DescriptorName, number> => {
+  const features = featurize(structure, options)
+  return Object.fromEntries(
+    DESCRIPTOR_NAMES.map((name, idx) => [name, features[idx]]),
+  ) as Record<DescriptorName, number>
+}
+
+export type PairMatrixOptions = {
+  // `row_norm` orders sites by descending row norm (Rupp et al.'s permutation-invariant
+  // ordering), `none` keeps the site order (default `row_norm`)
+  sort?: `none` | `row_norm`
+  n_max?: number // zero-pad to n_max × n_max (e.g. the largest structure in a dataset)
+}
+
+// Occupancy-weighted atomic number of each site
+const site_charges = ({ sites }: AnyStructure): number[] =>
+  sites.map(({ species }) =>
+    species.reduce(
+      (sum, { element, occu }) => sum + occu * (SYMBOL_TO_ATOMIC_NUMBER[element] ?? 0),
+      0,
+    ),
+  )
+
+// Z_i^2.4 / 2 on the diagonal and Z_i Z_j / d(i, j) off it, sorted and padded
+function pair_matrix(
+  charges: number[],
+  distance: (idx_1: number, idx_2: number) => number,
+  options: PairMatrixOptions,
+): number[][] {
+  const { sort = `row_norm`, n_max = charges.length } = options
+  if (n_max < charges.length) {
+    throw new Error(`n_max=${n_max} is smaller than the number of sites ${charges.length}`)
+  }
+  const matrix = charges.map((z_1, idx_1) =>
+    charges.map((z_2, idx_2) =>
+      idx_1 === idx_2 ? 0.5 * z_1 ** 2.4 : (z_1 * z_2) / distance(idx_1, idx_2),
+    ),
+  )
+  const order = charges.map((_, idx) => idx)
+  if (sort === `row_norm`) {
+    const norms = matrix.map((row) => Math.hypot(...row))
+    order.sort((idx_1, idx_2) => norms[idx_2] - norms[idx_1])
+  }
+  return Array.from({ length: n_max }, (_, row) =>
+    Array.from({ length: n_max }, (_, col) =>
+      row < order.length && col < order.length ? matrix[order[row]][order[col]] : 0,
+    ),
+  )
+}
+
+// Coulomb matrix from Cartesian distances (periodic images are ignored, so use
+// sine_matrix for crystals)
+export const coulomb_matrix = (
+  structure: AnyStructure,
+  options: PairMatrixOptions = {},
+): number[][] => {
+  const { sites } = structure
+  const distance = (idx_1: number, idx_2: number) =>
+    math.euclidean_dist(sites[idx_1].xyz, sites[idx_2].xyz)
+  return pair_matrix(site_charges(structure), distance, options)
+}
+
+// Sine matrix: Coulomb matrix with d(i, j) = |Σₖ aₖ sin²(π Δfₖ)| for fractional offsets Δf,
+// which is periodic in the lattice and invariant to wrapping sites into the cell
+export const sine_matrix = (
+  structure: Crystal,
+  options: PairMatrixOptions = {},
+): number[][] => {
+  const { sites, lattice } = structure
+  const distance = (idx_1: number, idx_2: number) => {
+    const weights = math.subtract(sites[idx_1].abc, sites[idx_2].abc).map(
+      (delta) => Math.sin(Math.PI * delta) ** 2,
+    )
+    return Math.hypot(
+      ...[0, 1, 2].map((dim) =>
+        lattice.matrix.reduce((sum, vec, axis) => sum + weights[axis] * vec[dim], 0),
+      ),
+    )
+  }
+  return pair_matrix(site_charges(structure), distance, options)
+}
+
+// Eigenvalues of a symmetric matrix sorted by descending magnitude, zero-padded to n_max.
+// The permutation-invariant spectrum of a Coulomb or sine matrix.
+export function sorted_eigenvalues(
+  matrix: readonly (readonly number[])[],
+  n_max = matrix.length,
+): number[] {
+  const size = matrix.length
+  if (n_max < size) throw new Error(`n_max=${n_max} is smaller than the matrix size ${size}`)
+  const eigenvalues = jacobi_eigen(matrix).values
+  eigenvalues.sort((val_1, val_2) => Math.abs(val_2) - Math.abs(val_1))
+  return [...eigenvalues, ...Array(n_max - size).fill(0)]
+}
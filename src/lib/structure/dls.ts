@@ -0,0 +1,201 @@
+// Distance least squares (DLS, Meier & Villiger, Z. Kristallogr. 129, 411, 1969) cell
+// prediction: refine the lattice (and optionally fractional coordinates) of a decorated
+// prototype so cation–anion bond lengths match sums of tabulated ionic radii, a better
+// starting point for DFT relaxations than isotropic volume scaling
+import { guess_oxidation_states } from '$lib/composition/enumerate'
+import type { CompositionType } from '$lib/composition'
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import { ionic_radius } from './decoration'
+import type { Crystal } from './index'
+import { get_neighbor_list } from './neighbors'
+
+export type DlsOptions = {
+  relax_positions?: boolean // also refine fractional coordinates (default false)
+  radii?: Partial<Record<ElementSymbol, number>> // Å, overrides the tabulated ionic radii
+  // anions within (1 + bond_tolerance) × the nearest anion distance of a cation count as
+  // its bonds, fixed during the refinement (default 0.2)
+  bond_tolerance?: number
+  max_iter?: number // Levenberg-Marquardt iterations (default 200)
+  tolerance?: number // Å, stop once the RMS bond error changes less (default 1e-10)
+}
+
+export type DlsResult = {
+  structure: Crystal
+  n_bonds: number
+  rms_before: number // Å, RMS deviation of bond lengths from the radius sums
+  rms_after: number // Å
+  n_iter: number
+  converged: boolean
+}
+
+type Bond = { site_1: number; site_2: number; image: Vec3; target: number }
+
+// Cation–anion bonds with target lengths from ionic radii, using the sites' oxidation
+// states or a charge-neutral guess when they are unset
+function find_bonds(structure: Crystal, options: DlsOptions): Bond[] {
+  const { radii = {}, bond_tolerance = 0.2 } = options
+  const elements = structure.sites.map((site, idx) => {
+    const element = get_majority_element(site)
+    if (!element) throw new Error(`Site ${idx} has no species`)
+    return element
+  })
+  let states = structure.sites.map((site) =>
+    site.species.reduce((sum, { occu, oxidation_state }) => sum + occu * oxidation_state, 0),
+  )
+  if (states.every((state) => state === 0)) {
+    const composition: CompositionType = {}
+    for (const element of elements) composition[element] = (composition[element] ?? 0) + 1
+    const guess = guess_oxidation_states(composition)
+    if (!guess) throw new Error(`DLS needs oxidation states to tell cations from anions`)
+    states = elements.map((element) => guess[element] ?? 0)
+  }
+  const radius = (idx: number) =>
+    radii[elements[idx]] ?? ionic_radius(elements[idx], states[idx])
+
+  const atomic_length = Math.cbrt(structure.lattice.volume / structure.sites.length)
+  const neighbor_list = get_neighbor_list(structure, 3 * atomic_length)
+  return neighbor_list.flatMap((neighbors, idx) => {
+    if (!(states[idx] > 0)) return []
+    const anions = neighbors.filter(({ site_idx }) => states[site_idx] < 0)
+    if (anions.length === 0) return []
+    const max_dist = (1 + bond_tolerance) * anions[0].distance
+    return anions
+      .filter(({ distance }) => distance <= max_dist)
+      .map(({ site_idx, image }) => ({
+        site_1: idx,
+        site_2: site_idx,
+        image,
+        target: radius(idx) + radius(site_idx),
+      }))
+  })
+}
+
+// Refine the cell by Levenberg-Marquardt on the residuals |v_b| − (r_1 + r_2) of every
+// bond vector v_b = (s_2 + n − s_1) A F, varying the symmetric deformation F = I + ε
+// of the lattice A and, with relax_positions, the fractional coordinates s. Symmetric
+// starting structures stay symmetric since the gradient shares their symmetry.
+export function dls_refine(structure: Crystal, options: DlsOptions = {}): DlsResult {
+  const { relax_positions = false, max_iter = 200, tolerance = 1e-10 } = options
+  const bonds = find_bonds(structure, options)
+  if (bonds.length === 0) throw new Error(`No cation–anion bonds found`)
+  const n_sites = structure.sites.length
+  const n_params = 6 + (relax_positions ? 3 * n_sites : 0)
+  const ref_matrix = structure.lattice.matrix
+  const voigt: [number, number][] = [
+    [0, 0],
+    [1, 1],
+    [2, 2],
+    [1, 2],
+    [0, 2],
+    [0, 1],
+  ]
+
+  const unpack = (params: number[]) => {
+    const deform = [0, 1, 2].map((row) => [0, 1, 2].map((col) => (row === col ? 1 : 0)))
+    voigt.forEach(([row, col], idx) => {
+      deform[row][col] += params[idx]
+      if (row !== col) deform[col][row] += params[idx]
+    })
+    const matrix = math.dot(ref_matrix, deform as Matrix3x3)
+    const abcs = structure.sites.map(({ abc }, idx): Vec3 => {
+      if (!relax_positions) return abc
+      return [0, 1, 2].map((axis) => abc[axis] + params[6 + 3 * idx + axis]) as Vec3
+    })
+    return { matrix, abcs }
+  }
+
+  // residuals and Jacobian rows at a parameter vector
+  const evaluate = (params: number[], with_jacobian: boolean) => {
+    const { matrix, abcs } = unpack(params)
+    const residuals: number[] = []
+    const jacobian: number[][] = []
+    for (const { site_1, site_2, image, target } of bonds) {
+      const frac = math.add(math.subtract(abcs[site_2], abcs[site_1]), image)
+      const vec = math.mat3x3_vec3_multiply(math.transpose_3x3_matrix(matrix), frac)
+      const length = Math.hypot(...vec)
+      residuals.push(length - target)
+      if (!with_jacobian) continue
+      // w = Δs A is the bond vector before deformation, v = w F
+      const ref_vec = math.mat3x3_vec3_multiply(math.transpose_3x3_matrix(ref_matrix), frac)
+      const row = Array<number>(n_params).fill(0)
+      voigt.forEach(([jdx, kdx], param) => {
+        const grad = jdx === kdx
+          ? ref_vec[jdx] * vec[jdx]
+          : ref_vec[jdx] * vec[kdx] + ref_vec[kdx] * vec[jdx]
+        row[param] = grad / length
+      })
+      if (relax_positions) {
+        const unit_grad = math.mat3x3_vec3_multiply(matrix, vec).map((val) => val / length)
+        for (let axis = 0; axis < 3; axis++) {
+          row[6 + 3 * site_2 + axis] += unit_grad[axis]
+          row[6 + 3 * site_1 + axis] -= unit_grad[axis]
+        }
+      }
+      jacobian.push(row)
+    }
+    return { residuals, jacobian }
+  }
+  const cost = (residuals: number[]) => residuals.reduce((sum, val) => sum + val * val, 0)
+  const rms = (residuals: number[]) => Math.sqrt(cost(residuals) / residuals.length)
+
+  let params = Array<number>(n_params).fill(0)
+  let current = evaluate(params, true)
+  const rms_before = rms(current.residuals)
+  let damping = 1e-3
+  let [n_iter, converged] = [0, false]
+  while (n_iter < max_iter && !converged) {
+    n_iter++
+    const { residuals, jacobian } = current
+    const normal = Array.from({ length: n_params }, (_, row) =>
+      Array.from({ length: n_params }, (_, col) =>
+        jacobian.reduce((sum, jac_row) => sum + jac_row[row] * jac_row[col], 0),
+      ),
+    )
+    const gradient = Array.from({ length: n_params }, (_, row) =>
+      jacobian.reduce((sum, jac_row, idx) => sum - jac_row[row] * residuals[idx], 0),
+    )
+    // retry with more damping until the cost drops (the small shift on the diagonal
+    // pins null space directions like rigid translations or unconstrained shears)
+    let accepted = false
+    for (let attempt = 0; attempt < 30 && !accepted; attempt++) {
+      const damped = normal.map((row, idx) =>
+        row.map((val, jdx) => (idx === jdx ? val * (1 + damping) + 1e-8 : val)),
+      )
+      const step = math.solve_linear_system(damped, gradient)
+      if (!step) {
+        damping *= 10
+        continue
+      }
+      const trial_params = params.map((val, idx) => val + step[idx])
+      const trial = evaluate(trial_params, false)
+      if (cost(trial.residuals) < cost(residuals)) {
+        converged = Math.abs(rms(residuals) - rms(trial.residuals)) < tolerance
+        params = trial_params
+        current = evaluate(params, true)
+        damping = Math.max(damping / 3, 1e-12)
+        accepted = true
+      } else damping *= 4
+    }
+    if (!accepted) converged = true // no descent step left: at a (local) minimum
+  }
+
+  const { matrix, abcs } = unpack(params)
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  const sites = structure.sites.map((site, idx) => ({
+    ...site,
+    abc: abcs[idx],
+    xyz: frac_to_cart(abcs[idx]),
+  }))
+  const lattice = { ...structure.lattice, matrix, ...math.calc_lattice_params(matrix) }
+  return {
+    structure: { ...structure, lattice, sites },
+    n_bonds: bonds.length,
+    rms_before,
+    rms_after: rms(current.residuals),
+    n_iter,
+    converged,
+  }
+}
@@ -0,0 +1,117 @@
+// Elastic dipole tensors and relaxation volumes of point defects (Varvenne & Clouet,
+// Phys. Rev. B 96, 224103, 2017) from the residual stress of a fixed-cell defect
+// supercell or from Kanzaki forces, for defect-strain interactions E = −P_ij ε_ij
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from './index'
+
+export const EV_PER_A3_TO_GPA = 160.21766208
+
+export type ElasticTensor = number[][] // 6×6 Voigt stiffness C_ij in GPa
+
+export type RelaxationVolume = {
+  tensor: Matrix3x3 // Ω_ij = S_ijkl P_kl in Å³
+  volume: number // ΔV = tr Ω in Å³
+}
+
+export type ElasticDipole = {
+  dipole: Matrix3x3 // P_ij in eV, symmetric
+  relaxation: RelaxationVolume | null // null without an elastic tensor
+}
+
+const symmetrize = (tensor: Matrix3x3): Matrix3x3 =>
+  tensor.map((row, idx) => row.map((val, jdx) => (val + tensor[jdx][idx]) / 2)) as Matrix3x3
+
+// Relaxation volume tensor Ω = S : P with S = C⁻¹. Voigt strains from C⁻¹ are
+// engineering shears, halved back to tensor components.
+export function relaxation_volume(
+  dipole: Matrix3x3,
+  elastic_tensor: ElasticTensor,
+): RelaxationVolume {
+  if (!math.is_square_matrix(elastic_tensor, 6)) {
+    throw new Error(`Elastic tensor must be a 6x6 Voigt matrix in GPa`)
+  }
+  const dipole_voigt = math.to_voigt(dipole).map((val) => val * EV_PER_A3_TO_GPA)
+  const strain = math.solve_linear_system(elastic_tensor, dipole_voigt)
+  if (!strain) throw new Error(`Elastic tensor is singular`)
+  const [e_xx, e_yy, e_zz, e_yz, e_xz, e_xy] = strain
+  const tensor: Matrix3x3 = [
+    [e_xx, e_xy / 2, e_xz / 2],
+    [e_xy / 2, e_yy, e_yz / 2],
+    [e_xz / 2, e_yz / 2, e_zz],
+  ]
+  return { tensor, volume: e_xx + e_yy + e_zz }
+}
+
+const with_relaxation = (dipole: Matrix3x3, elastic_tensor?: ElasticTensor) => ({
+  dipole,
+  relaxation: elastic_tensor ? relaxation_volume(dipole, elastic_tensor) : null,
+})
+
+export type DipoleFromStressOptions = {
+  reference_stress?: Matrix3x3 // GPa, perfect supercell with the same cell (default 0)
+  elastic_tensor?: ElasticTensor
+}
+
+// P_ij = −V (σ_ij − σ⁰_ij) for a defect supercell of volume V (Å³) relaxed at fixed
+// cell. Stresses in GPa, tensile positive (ASE convention; VASP's kB output is −10× this).
+export function elastic_dipole_from_stress(
+  stress: Matrix3x3,
+  volume: number,
+  options: DipoleFromStressOptions = {},
+): ElasticDipole {
+  const { reference_stress, elastic_tensor } = options
+  if (!(volume > 0)) throw new Error(`Supercell volume must be > 0, got ${volume}`)
+  const dipole = symmetrize(
+    stress.map((row, idx) =>
+      row.map((val, jdx) => {
+        const delta = val - (reference_stress?.[idx][jdx] ?? 0)
+        return (-volume * delta) / EV_PER_A3_TO_GPA
+      }),
+    ) as Matrix3x3,
+  )
+  return with_relaxation(dipole, elastic_tensor)
+}
+
+export type DipoleFromForcesOptions = {
+  forces?: Vec3[] // eV/Å per site (default: site.properties.force)
+  cutoff?: number // Å, only atoms this close to the defect contribute (default: all)
+  elastic_tensor?: ElasticTensor
+}
+
+// Kanzaki forces: forces on the atoms of a defect supercell held at perfect-lattice
+// positions give P_ij = Σ_n F_i^n r_j^n with r^n the minimum-image position of atom n
+// relative to the defect (Cartesian Å)
+export function elastic_dipole_from_forces(
+  structure: Crystal,
+  defect_position: Vec3,
+  options: DipoleFromForcesOptions = {},
+): ElasticDipole {
+  const { cutoff = Infinity, elastic_tensor } = options
+  const { matrix, pbc } = structure.lattice
+  const converters = math.create_lattice_converters(matrix)
+  const dipole: Matrix3x3 = [
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+  ]
+  structure.sites.forEach((site, site_idx) => {
+    const force = options.forces?.[site_idx] ?? site.properties?.force
+    if (!math.is_finite_vec3_like(force)) {
+      throw new Error(`No force for site ${site_idx}, pass forces or set properties.force`)
+    }
+    const rel = math.min_image_displacement(defect_position, site.xyz, matrix, converters, pbc)
+    if (Math.hypot(...rel) > cutoff) return
+    for (let idx = 0; idx < 3; idx++) {
+      for (let jdx = 0; jdx < 3; jdx++) dipole[idx][jdx] += force[idx] * rel[jdx]
+    }
+  })
+  return with_relaxation(symmetrize(dipole), elastic_tensor)
+}
+
+// Interaction energy (eV) of a defect with a homogeneous strain: E = −P_ij ε_ij
+export const dipole_strain_energy = (dipole: Matrix3x3, strain: Matrix3x3): number =>
+  -dipole.reduce(
+    (sum, row, idx) => sum + row.reduce((acc, val, jdx) => acc + val * strain[idx][jdx], 0),
+    0,
+  )
@@ -0,0 +1,367 @@
+// Ewald summation of the electrostatic energy of periodic point charges, either as the
+// direct real + reciprocal lattice sum or with smooth particle-mesh Ewald (PME, Essmann
+// et al., J. Chem. Phys. 103, 8577, 1995) which spreads charges onto a mesh with cardinal
+// B-splines and does the reciprocal sum by FFT in O(N log N) instead of O(N^1.5)
+import { COULOMB_EV_A } from '$lib/constants'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal, Site } from './index'
+import { get_neighbor_list } from './neighbors'
+
+// target relative size of the truncated terms erfc(α r_c) and exp(−k_c² / 4α²)
+export const EWALD_ACCURACY = { low: 1e-4, medium: 1e-5, high: 1e-7 } as const
+export type EwaldAccuracy = keyof typeof EWALD_ACCURACY | number
+export type EwaldMethod = `auto` | `direct` | `pme`
+
+// method `auto` switches to PME from this many charged sites on
+export const PME_MIN_SITES = 5000
+
+export type EwaldOptions = {
+  // per-site charges in e (default: occupancy-weighted species oxidation states)
+  charges?: readonly number[]
+  method?: EwaldMethod // default `auto`
+  accuracy?: EwaldAccuracy // preset name or number (default `medium`)
+  // Gaussian splitting parameter in 1/Å (default: balances real and reciprocal cost for
+  // direct sums, and real_cutoff for PME)
+  alpha?: number
+  real_cutoff?: number // Å (default: from alpha and accuracy, 9 Å for PME)
+  mesh?: Vec3 // PME grid points per axis, powers of 2 (default: from accuracy)
+  order?: number // PME B-spline order (default 4, cubic)
+}
+
+export type EwaldResult = {
+  energy: number // eV, sum of the terms below
+  real: number // eV
+  reciprocal: number // eV
+  self: number // eV
+  charged: number // eV, neutralizing background correction for non-zero net charge
+  method: `direct` | `pme`
+  alpha: number // 1/Å
+  real_cutoff: number // Å
+  reciprocal_cutoff: number // 1/Å, |k| including the 2π
+  mesh: Vec3 | null // null for direct sums
+  order: number | null
+}
+
+// Iterative radix-2 Cooley-Tukey FFT in place (sign is irrelevant as only |F|² is used)
+function fft_in_place(re: Float64Array, im: Float64Array): void {
+  const size = re.length
+  for (let idx = 1, rev = 0; idx < size; idx++) {
+    let bit = size >> 1
+    for (; rev & bit; bit >>= 1) rev ^= bit
+    rev ^= bit
+    if (idx < rev) {
+      ;[re[idx], re[rev]] = [re[rev], re[idx]]
+      ;[im[idx], im[rev]] = [im[rev], im[idx]]
+    }
+  }
+  for (let len = 2; len <= size; len <<= 1) {
+    const angle = (-2 * Math.PI) / len
+    for (let start = 0; start < size; start += len) {
+      for (let k = 0; k < len / 2; k++) {
+        const [w_re, w_im] = [Math.cos(angle * k), Math.sin(angle * k)]
+        const [even, odd] = [start + k, start + k + len / 2]
+        const t_re = w_re * re[odd] - w_im * im[odd]
+        const t_im = w_re * im[odd] + w_im * re[odd]
+        re[odd] = re[even] - t_re
+        im[odd] = im[even] - t_im
+        re[even] += t_re
+        im[even] += t_im
+      }
+    }
+  }
+}
+
+// 3D FFT of a row-major grid as 1D transforms along each axis
+function fft_3d(re: Float64Array, im: Float64Array, dims: Vec3): void {
+  const strides = [dims[1] * dims[2], dims[2], 1]
+  for (let axis = 0; axis < 3; axis++) {
+    const [size, stride] = [dims[axis], strides[axis]]
+    const line_re = new Float64Array(size)
+    const line_im = new Float64Array(size)
+    for (let start = 0; start < re.length; start++) {
+      // lines start where the index along this axis is 0
+      if (Math.floor(start / stride) % size !== 0) continue
+      for (let idx = 0; idx < size; idx++) {
+        line_re[idx] = re[start + idx * stride]
+        line_im[idx] = im[start + idx * stride]
+      }
+      fft_in_place(line_re, line_im)
+      for (let idx = 0; idx < size; idx++) {
+        re[start + idx * stride] = line_re[idx]
+        im[start + idx * stride] = line_im[idx]
+      }
+    }
+  }
+}
+
+// Cardinal B-spline values M_n(w + k) for k = 0..order − 1 and w in [0, 1)
+function bspline_weights(frac: number, order: number): number[] {
+  let weights = [frac, 1 - frac]
+  for (let deg = 3; deg <= order; deg++) {
+    weights = Array.from({ length: deg }, (_, k) => {
+      const x_val = frac + k
+      const here = (x_val * (weights[k] ?? 0)) / (deg - 1)
+      return here + ((deg - x_val) * (weights[k - 1] ?? 0)) / (deg - 1)
+    })
+  }
+  return weights
+}
+
+// Squared Euler exponential spline moduli |b(m)|² along one mesh axis, 0 where the
+// interpolation is undefined (at the Nyquist frequency for odd orders)
+function bspline_moduli(size: number, order: number): Float64Array {
+  const at_integers = bspline_weights(0, order) // M_n(k)
+  return Float64Array.from({ length: size }, (_, freq) => {
+    let [den_re, den_im] = [0, 0]
+    for (let k = 0; k < order - 1; k++) {
+      const angle = (2 * Math.PI * freq * k) / size
+      den_re += at_integers[k + 1] * Math.cos(angle)
+      den_im += at_integers[k + 1] * Math.sin(angle)
+    }
+    const den_sq = den_re * den_re + den_im * den_im
+    return den_sq > 1e-20 ? 1 / den_sq : 0
+  })
+}
+
+// Occupancy-weighted oxidation states of the sites, the default charges of Ewald sums
+export const oxidation_state_charges = (sites: readonly Site[]): number[] =>
+  sites.map(({ species }) =>
+    species.reduce((sum, { occu, oxidation_state }) => sum + occu * oxidation_state, 0),
+  )
+
+const next_pow2 = (val: number): number => 2 ** Math.ceil(Math.log2(Math.max(1, val)))
+
+export type ReciprocalSum = {
+  energy: number // e²/Å
+  site_energies: number[] // q_i Re(e^{-ik·x_i} S(k)) summed over k, adding up to energy
+  forces: Vec3[] // e²/Å², -dE/dx_i
+  stress: math.Matrix3x3 // e²/Å⁴, (1/V) dE/dε
+}
+
+// Reciprocal energy, per-site energies, forces and stress (all in e² and Å) by direct sum
+// over k-vectors with |k| <= k_cut. Exact gradients, so also used by force fields.
+export function direct_reciprocal(
+  fracs: Vec3[],
+  charges: readonly number[],
+  recip: math.Matrix3x3,
+  lattice_norms: Vec3,
+  alpha: number,
+  k_cut: number,
+  volume: number,
+): ReciprocalSum {
+  const m_max = lattice_norms.map((norm) => Math.ceil((k_cut * norm) / (2 * Math.PI)))
+  const prefactor = (2 * Math.PI) / volume
+  const site_energies = new Array<number>(charges.length).fill(0)
+  const forces: Vec3[] = charges.map(() => [0, 0, 0])
+  const stress: math.Matrix3x3 = [
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+  ]
+  const [cos_kx, sin_kx] = [new Float64Array(charges.length), new Float64Array(charges.length)]
+  let total = 0
+  for (let m_a = -m_max[0]; m_a <= m_max[0]; m_a++) {
+    for (let m_b = -m_max[1]; m_b <= m_max[1]; m_b++) {
+      for (let m_c = -m_max[2]; m_c <= m_max[2]; m_c++) {
+        if (m_a === 0 && m_b === 0 && m_c === 0) continue
+        const k_vec = [0, 1, 2].map(
+          (dim) =>
+            2 * Math.PI * (m_a * recip[0][dim] + m_b * recip[1][dim] + m_c * recip[2][dim]),
+        )
+        const k_sq = k_vec[0] ** 2 + k_vec[1] ** 2 + k_vec[2] ** 2
+        if (k_sq > k_cut * k_cut) continue
+        let [s_re, s_im] = [0, 0]
+        fracs.forEach(([fa, fb, fc], idx) => {
+          const phase = 2 * Math.PI * (m_a * fa + m_b * fb + m_c * fc)
+          cos_kx[idx] = Math.cos(phase)
+          sin_kx[idx] = Math.sin(phase)
+          s_re += charges[idx] * cos_kx[idx]
+          s_im += charges[idx] * sin_kx[idx]
+        })
+        const weight = (prefactor * Math.exp(-k_sq / (4 * alpha * alpha))) / k_sq
+        const energy = weight * (s_re ** 2 + s_im ** 2)
+        total += energy
+        charges.forEach((charge, idx) => {
+          if (charge === 0) return
+          site_energies[idx] += weight * charge * (cos_kx[idx] * s_re + sin_kx[idx] * s_im)
+          // -dE/dx_i = 2 w q_i k Im(S* e^{ik·x_i})
+          const im_part = s_re * sin_kx[idx] - s_im * cos_kx[idx]
+          for (let axis = 0; axis < 3; axis++) {
+            forces[idx][axis] += 2 * weight * charge * k_vec[axis] * im_part
+          }
+        })
+        // strain scales 1/V and contracts k: dk²/dε_ab = -2 k_a k_b
+        const k_factor = 2 * (1 / (4 * alpha * alpha) + 1 / k_sq)
+        for (let row = 0; row < 3; row++) {
+          stress[row][row] -= energy / volume
+          for (let col = 0; col < 3; col++) {
+            stress[row][col] += (energy * k_factor * k_vec[row] * k_vec[col]) / volume
+          }
+        }
+      }
+    }
+  }
+  return { energy: total, site_energies, forces, stress }
+}
+
+// Reciprocal energy (in e²/Å) by smooth PME on a mesh of the given dims
+function pme_reciprocal(
+  fracs: Vec3[],
+  charges: readonly number[],
+  recip: math.Matrix3x3,
+  alpha: number,
+  mesh: Vec3,
+  order: number,
+  volume: number,
+): number {
+  const [n_a, n_b, n_c] = mesh
+  const re = new Float64Array(n_a * n_b * n_c)
+  const im = new Float64Array(re.length)
+  fracs.forEach((frac, idx) => {
+    if (charges[idx] === 0) return
+    const [idx_a, idx_b, idx_c] = frac.map((val, axis) => {
+      const scaled = (val - Math.floor(val)) * mesh[axis]
+      const base = Math.floor(scaled)
+      const weights = bspline_weights(scaled - base, order)
+      // grid point base − k gets weight M_n(w + k)
+      return weights.map((weight, k) => ({
+        point: (((base - k) % mesh[axis]) + mesh[axis]) % mesh[axis],
+        weight,
+      }))
+    })
+    for (const { point: pa, weight: wa } of idx_a) {
+      for (const { point: pb, weight: wb } of idx_b) {
+        const row = (pa * n_b + pb) * n_c
+        for (const { point: pc, weight: wc } of idx_c) {
+          re[row + pc] += charges[idx] * wa * wb * wc
+        }
+      }
+    }
+  })
+  fft_3d(re, im, mesh)
+
+  const moduli = mesh.map((size) => bspline_moduli(size, order))
+  const signed = (freq: number, size: number) => (freq < size / 2 ? freq : freq - size)
+  let total = 0
+  for (let fa = 0; fa < n_a; fa++) {
+    const m_a = signed(fa, n_a)
+    for (let fb = 0; fb < n_b; fb++) {
+      const m_b = signed(fb, n_b)
+      for (let fc = 0; fc < n_c; fc++) {
+        if (fa === 0 && fb === 0 && fc === 0) continue
+        const m_c = signed(fc, n_c)
+        const m_vec = [0, 1, 2].map(
+          (dim) => m_a * recip[0][dim] + m_b * recip[1][dim] + m_c * recip[2][dim],
+        )
+        const m_sq = m_vec[0] ** 2 + m_vec[1] ** 2 + m_vec[2] ** 2
+        const b_mod = moduli[0][fa] * moduli[1][fb] * moduli[2][fc]
+        const flat = (fa * n_b + fb) * n_c + fc
+        const structure_sq = re[flat] ** 2 + im[flat] ** 2
+        const damping = Math.exp(-((Math.PI / alpha) ** 2) * m_sq)
+        total += (damping / m_sq) * b_mod * structure_sq
+      }
+    }
+  }
+  return total / (2 * Math.PI * volume)
+}
+
+// Electrostatic energy of a 3D-periodic crystal's point charges. Parameters not given
+// are chosen so every truncated term is below the target accuracy: direct sums balance
+// real and reciprocal work, PME fixes the real-space cutoff and picks the coarsest mesh
+// whose B-spline interpolation error matches the accuracy.
+export function ewald_energy(structure: Crystal, options: EwaldOptions = {}): EwaldResult {
+  const { sites, lattice } = structure
+  if (!lattice.pbc.every(Boolean)) {
+    throw new Error(`Ewald sums need periodic boundaries along all 3 axes`)
+  }
+  const charges = options.charges ?? oxidation_state_charges(sites)
+  if (charges.length !== sites.length) {
+    throw new Error(`charges must match the ${sites.length} sites, got ${charges.length}`)
+  }
+  const accuracy =
+    typeof options.accuracy === `string`
+      ? EWALD_ACCURACY[options.accuracy]
+      : (options.accuracy ?? EWALD_ACCURACY.medium)
+  if (!(accuracy > 0 && accuracy < 1)) {
+    throw new Error(`accuracy must be in (0, 1), got ${options.accuracy}`)
+  }
+  const n_charged = charges.filter((charge) => charge !== 0).length
+  const { method: requested = `auto` } = options
+  const method =
+    requested === `auto` ? (n_charged >= PME_MIN_SITES ? `pme` : `direct`) : requested
+  const order = options.order ?? 4
+  if (method === `pme` && !(Number.isInteger(order) && order >= 3)) {
+    throw new Error(`PME order must be an integer >= 3, got ${order}`)
+  }
+
+  const volume = Math.abs(math.det_3x3(lattice.matrix))
+  const sqrt_ln = Math.sqrt(-Math.log(accuracy))
+  const alpha =
+    options.alpha ??
+    (options.real_cutoff
+      ? sqrt_ln / options.real_cutoff
+      : method === `pme`
+        ? sqrt_ln / 9
+        : Math.sqrt(Math.PI) * (Math.max(1, n_charged) / volume ** 2) ** (1 / 6))
+  if (!(alpha > 0)) throw new Error(`alpha must be positive, got ${alpha}`)
+  const real_cutoff = options.real_cutoff ?? sqrt_ln / alpha
+  if (!(real_cutoff > 0)) throw new Error(`real_cutoff must be positive, got ${real_cutoff}`)
+  const k_cut = 2 * alpha * sqrt_ln
+
+  const recip = math.create_cart_to_frac_matrix(lattice.matrix)
+  const lattice_norms = lattice.matrix.map((vec) => Math.hypot(...vec)) as Vec3
+  let mesh: Vec3 | null = null
+  if (method === `pme`) {
+    // enough points to resolve |k| up to the cutoff and a spacing h with
+    // (α h / 3)^order ≈ accuracy, an empirical bound on the interpolation error
+    const spacing = (3 * accuracy ** (1 / order)) / alpha
+    mesh =
+      options.mesh ??
+      (lattice_norms.map((norm) =>
+        next_pow2(
+          Math.max(order, 2 * Math.ceil((k_cut * norm) / (2 * Math.PI)) + 1, norm / spacing),
+        ),
+      ) as Vec3)
+    if (!mesh.every((size) => Number.isInteger(Math.log2(size)) && size >= order)) {
+      throw new Error(`PME mesh sizes must be powers of 2 >= order, got [${mesh}]`)
+    }
+  }
+
+  const fracs = sites.map((site) => site.abc)
+  const reciprocal =
+    COULOMB_EV_A *
+    (mesh
+      ? pme_reciprocal(fracs, charges, recip, alpha, mesh, order, volume)
+      : direct_reciprocal(fracs, charges, recip, lattice_norms, alpha, k_cut, volume).energy)
+
+  let real = 0
+  if (n_charged > 0) {
+    get_neighbor_list(structure, real_cutoff).forEach((neighbors, idx) => {
+      if (charges[idx] === 0) return
+      for (const { site_idx, distance } of neighbors) {
+        real += (charges[idx] * charges[site_idx] * math.erfc(alpha * distance)) / distance
+      }
+    })
+  }
+  real *= COULOMB_EV_A / 2
+
+  const sum_sq = charges.reduce((sum, charge) => sum + charge * charge, 0)
+  const net_charge = charges.reduce((sum, charge) => sum + charge, 0)
+  const self = (-COULOMB_EV_A * alpha * sum_sq) / Math.sqrt(Math.PI)
+  const charged = (-COULOMB_EV_A * Math.PI * net_charge ** 2) / (2 * volume * alpha ** 2)
+
+  return {
+    energy: real + reciprocal + self + charged,
+    real,
+    reciprocal,
+    self,
+    charged,
+    method,
+    alpha,
+    real_cutoff,
+    reciprocal_cutoff: k_cut,
+    mesh,
+    order: mesh ? order : null,
+  }
+}
@@ -1,8 +1,20 @@
+import type { OptimadeStructure } from '$lib/api/optimade'
 import { get_electro_neg_formula } from '$lib/composition'
+import { ATOMIC_WEIGHTS, SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import type { ElementSymbol } from '$lib/element'
 import { download } from '$lib/io/fetch'
+import { BOHR_TO_ANGSTROM } from '$lib/isosurface/parse'
+import type { VolumetricData } from '$lib/isosurface/types'
 import type { Vec3 } from '$lib/math'
 import * as math from '$lib/math'
-import type { AnyStructure, Site } from '$lib/structure'
+import type { AnyStructure, BondOrder, Site } from '$lib/structure'
+import type { AdpCif } from './adp'
+import { get_site_u_cart, u_cart_to_cif } from './adp'
+import { get_site_fields, site_field_columns } from './site-fields'
+import type { MolecularTopology } from './topology'
+import { perceive_topology } from './topology'
+import type { UnitSystem } from '$lib/units'
+import { unit_factor } from '$lib/units'
 import { is_plain_object } from '$lib/utils'
 import type { BufferGeometry, InstancedMesh, Material, Object3D, Scene } from 'three'
 import { Color, Group, Matrix4, Mesh, MeshStandardMaterial, ShaderMaterial } from 'three'
@@ -451,6 +463,16 @@ export function structure_to_xyz_str(structure?: AnyStructure): string {
     comment_parts.push(`Lattice="${lattice_values}"`)
   }
 
+  // Site fields become extended XYZ Properties columns after species and positions
+  const fields = Object.entries(get_site_fields(structure))
+  if (fields.length > 0) {
+    const columns = fields.map(
+      ([name, field]) => `${name}:R:${field.kind === `scalar` ? 1 : 3}`,
+    )
+    comment_parts.push(`Properties=species:S:1:pos:R:3:${columns.join(`:`)}`)
+  }
+  const field_rows = site_field_columns(structure).rows
+
   const comment =
     comment_parts.length > 0 ? comment_parts.join(` `) : `Generated from structure`
   lines.push(comment)
@@ -459,7 +481,7 @@ export function structure_to_xyz_str(structure?: AnyStructure): string {
   const frac_to_cart = lattice_matrix ? math.create_frac_to_cart(lattice_matrix) : null
 
   // Atom lines: element symbol followed by x, y, z coordinates
-  for (const site of structure.sites) {
+  for (const [site_idx, site] of structure.sites.entries()) {
     const element_symbol = site_element(site)
 
     // Get coordinates - prefer xyz; fallback to abc (converted to cartesian if lattice available)
@@ -472,7 +494,8 @@ export function structure_to_xyz_str(structure?: AnyStructure): string {
 
     // Format coordinates to reasonable precision
     const [x, y, z] = coords.map((coord) => coord.toFixed(6))
-    lines.push(`${element_symbol} ${x} ${y} ${z}`)
+    const extra = field_rows[site_idx].map((val) => ` ${val.toFixed(8)}`).join(``)
+    lines.push(`${element_symbol} ${x} ${y} ${z}${extra}`)
   }
 
   return lines.join(`\n`)
@@ -584,6 +607,10 @@ export function structure_to_cif_str(structure?: AnyStructure): string {
   const cart_to_frac =
     lattice.matrix?.length === 3 ? math.create_cart_to_frac(lattice.matrix) : null
 
+  const aniso_rows: [string, AdpCif][] = []
+  const field_columns = site_field_columns(structure)
+  const field_rows: [string, number[]][] = []
+
   // Atom sites: one row per species entry so disordered (multi-species) sites
   // keep every component with its own occupancy instead of only species[0]
   for (let idx = 0; idx < structure.sites.length; idx++) {
@@ -603,6 +630,35 @@ export function structure_to_cif_str(structure?: AnyStructure): string {
           ? `${elem}${idx + 1}_${spec_idx}`
           : site.label || `${elem}${idx + 1}`
       lines.push(`${label} ${elem} ${coords_str} ${(species?.occu ?? 1).toFixed(8)}`)
+      const u_cart = get_site_u_cart(site)
+      if (u_cart) aniso_rows.push([label, u_cart_to_cif(u_cart, lattice.matrix)])
+      if (field_columns.names.length > 0) field_rows.push([label, field_columns.rows[idx]])
+    }
+  }
+
+  // Anisotropic displacement parameters for sites carrying a Cartesian U tensor
+  if (aniso_rows.length > 0) {
+    lines.push(
+      ``,
+      `loop_`,
+      `_atom_site_aniso_label`,
+      ...[`11`, `22`, `33`, `12`, `13`, `23`].map((ij) => `_atom_site_aniso_U_${ij}`),
+    )
+    for (const [label, u_cif] of aniso_rows) {
+      lines.push(`${label} ${u_cif.map((val) => val.toFixed(6)).join(` `)}`)
+    }
+  }
+
+  // Site fields as a separate loop keyed by atom label, vectors split into _x, _y, _z
+  if (field_rows.length > 0) {
+    lines.push(
+      ``,
+      `loop_`,
+      `_atom_site_field_label`,
+      ...field_columns.names.map((name) => `_atom_site_field_${name}`),
+    )
+    for (const [label, values] of field_rows) {
+      lines.push(`${label} ${values.map((val) => val.toFixed(8)).join(` `)}`)
     }
   }
 
@@ -695,6 +751,292 @@ export function structure_to_poscar_str(structure?: AnyStructure): string {
   return lines.join(`\n`)
 }
 
+export interface LammpsDataExportOptions {
+  units?: UnitSystem // LAMMPS `units` to write in (default: metal)
+  // `charge` writes q from site.properties.charge, else the species' oxidation state.
+  // `full` also writes molecule IDs and Bonds/Angles/Dihedrals/Impropers sections.
+  atom_style?: `atomic` | `charge` | `full`
+  topology?: MolecularTopology // used by atom_style full (default: perceive_topology)
+}
+
+const BOND_ORDER_SYMBOLS: Partial<Record<BondOrder, string>> = { 1: `-`, 2: `=`, 3: `#` }
+
+// Force-field style type keys from element symbols, invariant under reversing a term
+// (bonds, angles, dihedrals) or permuting the outer atoms of an improper (center first)
+const bond_type_key = (elements: string[], order: BondOrder): string =>
+  elements.toSorted().join(BOND_ORDER_SYMBOLS[order] ?? `:`)
+const angle_type_key = ([end_1, center, end_2]: string[]): string => {
+  const [first, last] = [end_1, end_2].sort()
+  return `${first}-${center}-${last}`
+}
+const dihedral_type_key = (elements: string[]): string => {
+  const [forward, reverse] = [elements.join(`-`), elements.toReversed().join(`-`)]
+  return forward < reverse ? forward : reverse
+}
+const improper_type_key = ([center, ...others]: string[]): string =>
+  [center, ...others.sort()].join(`-`)
+
+type LammpsTopologySection = { name: string; type_keys: string[]; rows: number[][] }
+
+// Number topology terms (site index tuples) by type key. Types are 1-based in order of
+// first appearance; rows hold the type followed by 1-based atom IDs.
+function lammps_topology_section(
+  name: string,
+  terms: readonly (readonly number[])[],
+  type_key: (term_idx: number) => string,
+): LammpsTopologySection {
+  const type_ids = new Map<string, number>()
+  const rows = terms.map((term, term_idx) => {
+    const key = type_key(term_idx)
+    if (!type_ids.has(key)) type_ids.set(key, type_ids.size + 1)
+    return [type_ids.get(key) as number, ...term.map((site_idx) => site_idx + 1)]
+  })
+  return { name, type_keys: [...type_ids.keys()], rows }
+}
+
+// Rotate a right-handed lattice into LAMMPS' restricted triclinic form: a along x, b in the
+// xy plane (https://docs.lammps.org/Howto_triclinic.html). Fractional coordinates are
+// unchanged by the rotation.
+export function lammps_box_matrix(matrix: math.Matrix3x3): math.Matrix3x3 {
+  const [vec_a, vec_b, vec_c] = matrix
+  const len_a = Math.hypot(...vec_a)
+  const xy = math.dot(vec_b, vec_a) / len_a
+  const ly = Math.sqrt(Math.max(0, math.dot(vec_b, vec_b) - xy ** 2))
+  const xz = math.dot(vec_c, vec_a) / len_a
+  const yz = (math.dot(vec_b, vec_c) - xy * xz) / ly
+  const lz = Math.sqrt(Math.max(0, math.dot(vec_c, vec_c) - xz ** 2 - yz ** 2))
+  return [
+    [len_a, 0, 0],
+    [xy, ly, 0],
+    [xz, yz, lz],
+  ]
+}
+
+// Generate a LAMMPS data file (read_data format) in the given unit system. Atom types are
+// numbered by first appearance of each element; velocities (Å/fs in site.properties.velocity)
+// are written when every site has one. Positions and velocities are rotated with the box.
+export function structure_to_lammps_data_str(
+  structure?: AnyStructure,
+  options: LammpsDataExportOptions = {},
+): string {
+  if (!structure?.sites) throw new Error(`No structure or sites to export`)
+  if (!(`lattice` in structure) || !structure.lattice) {
+    throw new Error(`No lattice information for LAMMPS data export`)
+  }
+  const { units = `metal`, atom_style = `atomic` } = options
+  const { matrix } = structure.lattice
+  if (!(math.det_3x3(matrix) > 0)) {
+    throw new Error(`LAMMPS data export requires a right-handed lattice`)
+  }
+  const length_scale = unit_factor(`length`, `ase`, units)
+  const lammps_lattice = lammps_box_matrix(matrix)
+  const cart_to_frac = math.create_cart_to_frac(matrix)
+  const frac_to_cart = math.create_frac_to_cart(lammps_lattice)
+  // 12 significant digits, compact for any unit scale (-0 prints as 0)
+  const fmt = (val: number): string => String(Number(val.toPrecision(12)))
+
+  const elements = [...new Set(structure.sites.map(site_element))]
+  const mass_scale = unit_factor(`mass`, `ase`, units)
+  const mass_lines = elements.map((element, idx) => {
+    const weight = ATOMIC_WEIGHTS.get(element as ElementSymbol)
+    if (weight === undefined) throw new Error(`Unknown element for LAMMPS export: ${element}`)
+    return `${idx + 1} ${fmt(weight * mass_scale)} # ${element}`
+  })
+
+  const topology =
+    atom_style === `full` ? (options.topology ?? perceive_topology(structure)) : null
+  const sections: LammpsTopologySection[] = []
+  if (topology) {
+    const { bonds, angles, dihedrals, impropers } = topology
+    const elems = (site_indices: readonly number[]): string[] =>
+      site_indices.map((site_idx) => site_element(structure.sites[site_idx]))
+    const bond_sites = bonds.map(({ site_idx_1, site_idx_2 }) => [site_idx_1, site_idx_2])
+    sections.push(
+      lammps_topology_section(`bond`, bond_sites, (idx) =>
+        bond_type_key(elems(bond_sites[idx]), bonds[idx].order),
+      ),
+      lammps_topology_section(`angle`, angles, (idx) => angle_type_key(elems(angles[idx]))),
+      lammps_topology_section(`dihedral`, dihedrals, (idx) =>
+        dihedral_type_key(elems(dihedrals[idx])),
+      ),
+      lammps_topology_section(`improper`, impropers, (idx) =>
+        improper_type_key(elems(impropers[idx])),
+      ),
+    )
+  }
+
+  const formula = get_electro_neg_formula(structure, true)
+  const title = structure.id ?? (formula && formula !== `Unknown` ? formula : `Structure`)
+  const [[lx], [xy, ly], [xz, yz, lz]] = lammps_lattice.map((row) =>
+    row.map((val) => val * length_scale),
+  )
+  const lines = [
+    `${title} (written by MatterViz, units ${units})`,
+    ``,
+    `${structure.sites.length} atoms`,
+    ...sections.map(({ name, rows }) => `${rows.length} ${name}s`),
+    `${elements.length} atom types`,
+    // type keys as trailing comment, e.g. `2 bond types # C-H C=C`
+    ...sections.map(({ name, type_keys }) => {
+      const count_line = `${type_keys.length} ${name} types`
+      return type_keys.length ? `${count_line} # ${type_keys.join(` `)}` : count_line
+    }),
+    ``,
+    `0 ${fmt(lx)} xlo xhi`,
+    `0 ${fmt(ly)} ylo yhi`,
+    `0 ${fmt(lz)} zlo zhi`,
+  ]
+  if ([xy, xz, yz].some((tilt) => Math.abs(tilt) > 1e-10)) {
+    lines.push(`${fmt(xy)} ${fmt(xz)} ${fmt(yz)} xy xz yz`)
+  }
+  lines.push(``, `Masses`, ``, ...mass_lines, ``, `Atoms # ${atom_style}`, ``)
+
+  structure.sites.forEach((site, idx) => {
+    const frac = get_frac_coords(site, cart_to_frac, idx) as Vec3
+    const xyz = frac_to_cart(frac).map((coord) => coord * length_scale)
+    const type = elements.indexOf(site_element(site)) + 1
+    const charge = site.properties?.charge ?? site.species?.[0]?.oxidation_state ?? 0
+    const charge_col = atom_style === `atomic` ? `` : ` ${fmt(Number(charge))}`
+    const mol_col = topology ? ` ${topology.molecule_ids[idx] + 1}` : ``
+    lines.push(`${idx + 1}${mol_col} ${type}${charge_col} ${xyz.map(fmt).join(` `)}`)
+  })
+
+  const velocities = structure.sites.map((site) => site.properties?.velocity)
+  const has_velocities =
+    velocities.length > 0 &&
+    velocities.every(
+      (vel) => Array.isArray(vel) && vel.length === 3 && vel.every(Number.isFinite),
+    )
+  if (has_velocities) {
+    const velocity_scale = unit_factor(`velocity`, `ase`, units)
+    lines.push(``, `Velocities`, ``)
+    velocities.forEach((vel, idx) => {
+      const rotated = frac_to_cart(cart_to_frac(vel as Vec3))
+      const scaled = rotated.map((val) => fmt(val * velocity_scale))
+      lines.push(`${idx + 1} ${scaled.join(` `)}`)
+    })
+  }
+
+  for (const { name, rows } of sections) {
+    if (rows.length === 0) continue // LAMMPS rejects sections for zero counts
+    lines.push(``, `${name[0].toUpperCase()}${name.slice(1)}s`, ``)
+    rows.forEach((row, idx) => lines.push(`${idx + 1} ${row.join(` `)}`))
+  }
+
+  return `${lines.join(`\n`)}\n`
+}
+
+// Fixed-width numbers in the Gaussian .cube layout: %12.6f for coordinates, %13.5E for data
+const cube_fixed = (val: number): string => val.toFixed(6).padStart(12)
+const cube_sci = (val: number): string => {
+  const [mantissa, exponent] = val.toExponential(5).split(`e`)
+  const exp_digits = String(Math.abs(Number(exponent))).padStart(2, `0`)
+  return ` ${mantissa}E${Number(exponent) < 0 ? `-` : `+`}${exp_digits}`.padStart(13)
+}
+
+// Generate a Gaussian .cube file (lengths in Bohr) from a structure and one volume on its
+// grid. Voxel vectors are the volume's lattice vectors over the grid dims, values are
+// written z-fastest with 6 per line and a line break after every z row.
+export function structure_to_cube_str(
+  structure: AnyStructure | undefined,
+  volume: VolumetricData,
+  comment = `Generated by matterviz`,
+): string {
+  if (!structure?.sites) throw new Error(`No structure or sites to export`)
+  const { grid, grid_dims, lattice, origin } = volume
+  const [n_x, n_y, n_z] = grid_dims
+  if (grid.length !== n_x || grid.some((plane) => plane.length !== n_y)) {
+    throw new Error(`Volume grid does not match grid_dims [${grid_dims}]`)
+  }
+  const to_bohr = (vec: number[]) => vec.map((val) => cube_fixed(val / BOHR_TO_ANGSTROM))
+
+  const lines = [comment.split(`\n`)[0], volume.label ?? `volumetric data`]
+  lines.push(`${String(structure.sites.length).padStart(5)}${to_bohr(origin).join(``)}`)
+  lattice.forEach((vec, axis) => {
+    const voxel = vec.map((val) => val / grid_dims[axis])
+    lines.push(`${String(grid_dims[axis]).padStart(5)}${to_bohr(voxel).join(``)}`)
+  })
+  for (const site of structure.sites) {
+    const atomic_number = SYMBOL_TO_ATOMIC_NUMBER[site_element(site) as ElementSymbol] ?? 0
+    // parse_cube stores positions relative to the origin
+    const xyz = math.add(site.xyz, origin)
+    lines.push(`${String(atomic_number).padStart(5)}${cube_fixed(0)}${to_bohr(xyz).join(``)}`)
+  }
+  for (const plane of grid) {
+    for (const row of plane) {
+      if (row.length !== n_z) throw new Error(`Volume grid does not match grid_dims`)
+      for (let start = 0; start < n_z; start += 6) {
+        lines.push(row.slice(start, start + 6).map(cube_sci).join(``))
+      }
+    }
+  }
+  return `${lines.join(`\n`)}\n`
+}
+
+// OPTIMADE structures resource with the v1 structure attributes. Every distinct site
+// occupation becomes a species entry; partial occupancies keep their concentrations plus
+// a vacancy for the remainder and mark the structure with the `disorder` feature.
+export function structure_to_optimade(
+  structure: AnyStructure | undefined,
+  id = structure?.id ?? `matterviz`,
+): OptimadeStructure {
+  if (!structure?.sites) throw new Error(`No structure or sites to export`)
+  type SpeciesEntry = NonNullable<OptimadeStructure[`attributes`][`species`]>[number]
+  const species: SpeciesEntry[] = []
+  const name_by_occupation = new Map<string, string>()
+  const species_at_sites = structure.sites.map((site) => {
+    const chemical_symbols: string[] = site.species.map(({ element }) => element)
+    const concentration = site.species.map(({ occu }) => occu)
+    const total = concentration.reduce((sum, occu) => sum + occu, 0)
+    if (total < 1 - 1e-6) {
+      chemical_symbols.push(`vacancy`)
+      concentration.push(1 - total)
+    }
+    const key = JSON.stringify([chemical_symbols, concentration])
+    let name = name_by_occupation.get(key)
+    if (!name) {
+      // names are element symbols, numbered when the same elements occur with other
+      // concentrations (e.g. Fe and Fe2 for full and half occupied iron)
+      const base = site.species.map(({ element }) => element).join(``)
+      name = base
+      for (let count = 2; species.some((entry) => entry.name === name); count++) {
+        name = `${base}${count}`
+      }
+      name_by_occupation.set(key, name)
+      species.push({ name, chemical_symbols, concentration })
+    }
+    return name
+  })
+
+  const lattice = `lattice` in structure ? structure.lattice : undefined
+  const pbc = lattice?.pbc ?? [false, false, false]
+  const elements = [
+    ...new Set(structure.sites.flatMap((site) => site.species.map(({ element }) => element))),
+  ].sort()
+  const disordered = species.some(({ concentration }) => concentration?.[0] !== 1)
+  return {
+    id,
+    type: `structures`,
+    attributes: {
+      ...(lattice && { lattice_vectors: lattice.matrix.map((vec) => [...vec]) }),
+      dimension_types: pbc.map(Number),
+      nperiodic_dimensions: pbc.filter(Boolean).length,
+      cartesian_site_positions: structure.sites.map(({ xyz }) => [...xyz]),
+      species_at_sites,
+      species,
+      nsites: structure.sites.length,
+      elements,
+      nelements: elements.length,
+      structure_features: disordered ? [`disorder`] : [],
+    },
+  }
+}
+
+// OPTIMADE JSON with the structure resource under `data`, as served by providers
+export function structure_to_optimade_str(structure?: AnyStructure): string {
+  return JSON.stringify({ data: structure_to_optimade(structure) }, null, 2)
+}
+
 // Generate JSON content string without saving
 export function structure_to_json_str(structure?: AnyStructure): string {
   if (!structure) throw new Error(`No structure to export`)
@@ -0,0 +1,98 @@
+// Canonical structure fingerprints for fast pre-filtering of duplicate candidates: a hash
+// of the reduced composition and quantized per-site neighbor distance shells that doesn't
+// depend on site order, cell setting or supercell size. Equal structures hash equally
+// (up to distances straddling a quantization bin edge), so bucket by fingerprint first
+// and run the expensive structure comparison only within buckets.
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import type { Crystal } from './index'
+import { get_neighbor_list } from './neighbors'
+
+export type FingerprintOptions = {
+  anonymous?: boolean // ignore element identities, e.g. NaCl and KBr match (default false)
+  // normalize lengths by (V/N)^(1/3) so uniformly scaled cells match (default true)
+  scale?: boolean
+  n_neighbors?: number // nearest-neighbor distances per site entering the hash (default 6)
+  precision?: number // length quantum in units of the length scale (default 0.05)
+}
+
+const gcd = (val_1: number, val_2: number): number =>
+  val_2 === 0 ? val_1 : gcd(val_2, val_1 % val_2)
+
+// 64-bit hex digest from two FNV-1a style 32-bit passes with different constants
+function fnv_hash(text: string): string {
+  let [hash_1, hash_2] = [0x811c9dc5, 0x01000193]
+  for (let idx = 0; idx < text.length; idx++) {
+    const code = text.charCodeAt(idx)
+    hash_1 = Math.imul(hash_1 ^ code, 0x01000193)
+    hash_2 = Math.imul(hash_2 ^ code, 0x811c9dc5 | 1)
+  }
+  return [hash_1, hash_2].map((val) => (val >>> 0).toString(16).padStart(8, `0`)).join(``)
+}
+
+// Canonical (unhashed) fingerprint string: reduced composition plus the reduced counts
+// of distinct per-site signatures (center element + quantized neighbor distances)
+export function structure_fingerprint_key(
+  structure: Crystal,
+  options: FingerprintOptions = {},
+): string {
+  const { anonymous = false, scale = true, n_neighbors = 6, precision = 0.05 } = options
+  const { sites, lattice } = structure
+  if (sites.length === 0) throw new Error(`Cannot fingerprint a structure without sites`)
+  if (!(precision > 0)) throw new Error(`precision must be > 0, got ${precision}`)
+  const atomic_length = Math.cbrt(Math.abs(math.det_3x3(lattice.matrix)) / sites.length)
+  const length_unit = scale ? atomic_length : 1
+
+  // grow the cutoff until every site has enough neighbors (bounded for non-periodic axes)
+  let cutoff = 1.5 * atomic_length
+  let neighbor_list = get_neighbor_list(structure, cutoff)
+  for (let iter = 0; iter < 8; iter++) {
+    if (neighbor_list.every((neighbors) => neighbors.length >= n_neighbors)) break
+    cutoff *= 1.5
+    neighbor_list = get_neighbor_list(structure, cutoff)
+  }
+
+  const elements = sites.map((site) => get_majority_element(site) ?? `X`)
+  const signature_counts = new Map<string, number>()
+  neighbor_list.forEach((neighbors, site_idx) => {
+    const shells = neighbors
+      .slice(0, n_neighbors)
+      .map(({ distance }) => Math.round(distance / length_unit / precision))
+    const signature = `${anonymous ? `` : elements[site_idx]}|${shells.join(`,`)}`
+    signature_counts.set(signature, (signature_counts.get(signature) ?? 0) + 1)
+  })
+
+  const element_counts = new Map<string, number>()
+  for (const element of elements) {
+    element_counts.set(element, (element_counts.get(element) ?? 0) + 1)
+  }
+  // divide by the common factor of all counts so supercells reduce to the same key
+  const divisor = [...signature_counts.values(), ...element_counts.values()].reduce(gcd)
+  const composition = [...element_counts.entries()].map(([element, count]) =>
+    anonymous ? `${count / divisor}` : `${element}${count / divisor}`,
+  )
+  const signatures = [...signature_counts.entries()].map(
+    ([signature, count]) => `${count / divisor}*${signature}`,
+  )
+  return `${composition.toSorted().join(` `)};${signatures.toSorted().join(`;`)}`
+}
+
+// Fixed-length hex hash of structure_fingerprint_key
+export const structure_fingerprint = (
+  structure: Crystal,
+  options: FingerprintOptions = {},
+): string => fnv_hash(structure_fingerprint_key(structure, options))
+
+// Bucket structure indices by fingerprint (insertion-ordered), so duplicates only need
+// to be searched for within each bucket
+export function group_by_fingerprint(
+  structures: readonly Crystal[],
+  options: FingerprintOptions = {},
+): Map<string, number[]> {
+  const buckets = new Map<string, number[]>()
+  structures.forEach((structure, idx) => {
+    const key = structure_fingerprint(structure, options)
+    buckets.set(key, [...(buckets.get(key) ?? []), idx])
+  })
+  return buckets
+}
@@ -0,0 +1,148 @@
+// Atom graphs for graph neural networks (PyTorch Geometric, DGL, jraph): node features
+// and directed edges to every neighbor image in flat typed arrays, so they can be shipped
+// to workers or Python without per-edge objects. Edges come from a radius cutoff or the k
+// nearest neighbors of each site.
+import { SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import { get_majority_element } from './bonding'
+import type { AnyStructure } from './index'
+import type { KnnOptions } from './neighbors'
+import { get_knn_neighbor_list, get_neighbor_list } from './neighbors'
+
+// tie_tolerance and max_cutoff only apply with knn
+export type GraphOptions = KnnOptions & {
+  cutoff?: number // Å, edges to all neighbors within (default 5)
+  // k nearest neighbors per site instead of a fixed cutoff, see get_knn_neighbor_list
+  knn?: number
+  moment_key?: string // site property holding magnetic moments (default magmom)
+}
+
+export type StructureGraph = {
+  n_nodes: number
+  n_edges: number
+  atomic_numbers: Int32Array // of the majority species per site
+  charges: Float64Array // occupancy-weighted oxidation states
+  magmoms: Float64Array // scalar moments (length of vector moments), 0 if missing
+  // [2, n_edges] row-major: center site indices, then neighbor site indices
+  edge_index: Int32Array
+  edge_images: Int32Array // [n_edges, 3] lattice translation of each neighbor image
+  edge_distances: Float64Array // Å
+  edge_vectors: Float64Array // [n_edges, 3] unit vectors from center to neighbor image
+}
+
+// Graph of a crystal or molecule with one directed edge per (center, neighbor image) pair,
+// so every bond appears once in each direction
+export function structure_to_graph(
+  structure: AnyStructure,
+  options: GraphOptions = {},
+): StructureGraph {
+  const { cutoff = 5, knn, tie_tolerance, max_cutoff, moment_key = `magmom` } = options
+  const { sites } = structure
+  const neighbor_list =
+    knn === undefined
+      ? get_neighbor_list(structure, cutoff)
+      : get_knn_neighbor_list(structure, knn, { tie_tolerance, max_cutoff })
+
+  const n_nodes = sites.length
+  const atomic_numbers = Int32Array.from(sites, (site) => {
+    const element = get_majority_element(site)
+    return element ? (SYMBOL_TO_ATOMIC_NUMBER[element] ?? 0) : 0
+  })
+  const charges = Float64Array.from(sites, ({ species }) =>
+    species.reduce((sum, { occu, oxidation_state }) => sum + occu * oxidation_state, 0),
+  )
+  const magmoms = Float64Array.from(sites, ({ properties }) => {
+    const moment = properties?.[moment_key]
+    if (typeof moment === `number`) return moment
+    const is_vec = Array.isArray(moment) && moment.every((val) => typeof val === `number`)
+    return is_vec ? Math.hypot(...(moment as number[])) : 0
+  })
+
+  const n_edges = neighbor_list.reduce((sum, neighbors) => sum + neighbors.length, 0)
+  const edge_index = new Int32Array(2 * n_edges)
+  const edge_images = new Int32Array(3 * n_edges)
+  const edge_distances = new Float64Array(n_edges)
+  const edge_vectors = new Float64Array(3 * n_edges)
+  let edge = 0
+  neighbor_list.forEach((neighbors, center_idx) => {
+    for (const { site_idx, distance, displacement, image } of neighbors) {
+      edge_index[edge] = center_idx
+      edge_index[n_edges + edge] = site_idx
+      edge_images.set(image, 3 * edge)
+      edge_distances[edge] = distance
+      edge_vectors.set(displacement.map((val) => val / distance), 3 * edge)
+      edge++
+    }
+  })
+  return {
+    n_nodes,
+    n_edges,
+    atomic_numbers,
+    charges,
+    magmoms,
+    edge_index,
+    edge_images,
+    edge_distances,
+    edge_vectors,
+  }
+}
+
+export type GraphBatch = StructureGraph & {
+  batch: Int32Array // graph index of each node
+  node_ptr: Int32Array // first node of each graph, plus n_nodes at the end
+  edge_ptr: Int32Array // first edge of each graph, plus n_edges at the end
+}
+
+// Concatenate graphs into one disconnected batch graph like torch_geometric's Batch, with
+// edge indices offset by the nodes of preceding graphs
+export function batch_graphs(graphs: readonly StructureGraph[]): GraphBatch {
+  const node_ptr = new Int32Array(graphs.length + 1)
+  const edge_ptr = new Int32Array(graphs.length + 1)
+  graphs.forEach((graph, idx) => {
+    node_ptr[idx + 1] = node_ptr[idx] + graph.n_nodes
+    edge_ptr[idx + 1] = edge_ptr[idx] + graph.n_edges
+  })
+  const [n_nodes, n_edges] = [node_ptr[graphs.length], edge_ptr[graphs.length]]
+  const batch = new Int32Array(n_nodes)
+  const atomic_numbers = new Int32Array(n_nodes)
+  const [charges, magmoms] = [new Float64Array(n_nodes), new Float64Array(n_nodes)]
+  const edge_index = new Int32Array(2 * n_edges)
+  const edge_images = new Int32Array(3 * n_edges)
+  const edge_distances = new Float64Array(n_edges)
+  const edge_vectors = new Float64Array(3 * n_edges)
+  graphs.forEach((graph, idx) => {
+    const [node_start, edge_start] = [node_ptr[idx], edge_ptr[idx]]
+    batch.fill(idx, node_start, node_ptr[idx + 1])
+    atomic_numbers.set(graph.atomic_numbers, node_start)
+    charges.set(graph.charges, node_start)
+    magmoms.set(graph.magmoms, node_start)
+    for (let edge = 0; edge < graph.n_edges; edge++) {
+      edge_index[edge_start + edge] = graph.edge_index[edge] + node_start
+      edge_index[n_edges + edge_start + edge] =
+        graph.edge_index[graph.n_edges + edge] + node_start
+    }
+    edge_images.set(graph.edge_images, 3 * edge_start)
+    edge_distances.set(graph.edge_distances, edge_start)
+    edge_vectors.set(graph.edge_vectors, 3 * edge_start)
+  })
+  return {
+    n_nodes,
+    n_edges,
+    atomic_numbers,
+    charges,
+    magmoms,
+    edge_index,
+    edge_images,
+    edge_distances,
+    edge_vectors,
+    batch,
+    node_ptr,
+    edge_ptr,
+  }
+}
+
+// Graphs of many structures as one batch
+export const structures_to_graph_batch = (
+  structures: Iterable<AnyStructure>,
+  options: GraphOptions = {},
+): GraphBatch =>
+  batch_graphs(Array.from(structures, (structure) => structure_to_graph(structure, options)))
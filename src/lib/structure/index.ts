@@ -9,10 +9,31 @@ import type { CameraProjection } from '$lib/settings'
 import type { ComponentProps } from 'svelte'
 import type LatticeComponent from './Lattice.svelte'
 import type { Pbc } from './pbc'
+import type { SiteFields } from './site-fields'
 import type StructureSceneComponent from './StructureScene.svelte'
 
 export { default as Arrow } from './Arrow.svelte'
+export * from './adp'
+export * from './adsorbate'
+export * from './aiida'
+export * from './ase'
+export * from './atom-arrays'
 export * from './atom-properties'
+export * from './bond-valence'
+export * from './builder'
+export * from './bvse'
+export * from './coordination'
+export * from './decoration'
+export * from './defect-strain'
+export * from './descriptors'
+export * from './dls'
+export * from './elastic-dipole'
+export * from './ewald'
+export * from './fingerprint'
+export * from './graph'
+export * from './jsonl'
+export * from './kmc'
+export * from './lattice-detection'
 export { default as AtomLegend } from './AtomLegend.svelte'
 export { default as Bond } from './Bond.svelte'
 export * as bonding_strategies from './bonding'
@@ -20,10 +41,20 @@ export { default as CanvasTooltip } from './CanvasTooltip.svelte'
 export { default as Cylinder } from './Cylinder.svelte'
 export { default as Lattice } from './Lattice.svelte'
 export * from './measure'
+export * from './molecule-matcher'
+export * from './mp-docs'
+export * from './neighbors'
 export * from './pbc'
+export * from './perturb'
+export * from './polarization'
 export * from './polyhedra'
+export * from './select'
 export * from './serialize'
 export * from './site'
+export * from './site-fields'
+export * from './smiles'
+export * from './species-style'
+export * from './spin-monte-carlo'
 export { default as Structure } from './Structure.svelte'
 export { default as StructureCarousel } from './StructureCarousel.svelte'
 
@@ -42,6 +73,7 @@ export { default as StructureInfoPane } from './StructureInfoPane.svelte'
 export { default as StructureScene } from './StructureScene.svelte'
 export { default as StructureViewport } from './StructureViewport.svelte'
 export * from './supercell'
+export * from './topology'
 export * from './validation'
 
 export type MeasureMode = `distance` | `angle` | `edit-bonds` | `edit-atoms`
@@ -105,7 +137,10 @@ export type StructureBond = {
   cell_shift?: Vec3
 }
 
-export type StructureProperties = Record<string, unknown> & { bonds?: StructureBond[] }
+export type StructureProperties = Record<string, unknown> & {
+  bonds?: StructureBond[]
+  site_fields?: SiteFields
+}
 
 // Bond pair with position vectors, site indices, bond length, strength score,
 // optional explicit bond order, and transformation matrix.
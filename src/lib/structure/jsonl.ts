@@ -0,0 +1,151 @@
+// JSON Lines structure datasets with one pymatgen Structure/Molecule dict per line (as
+// written by monty's dumpfn to .jsonl or dataset dumps of MP documents). Reading and
+// writing go line by line, so multi-million-structure files stream through batch APIs
+// like group_by_fingerprint in constant memory. Streams can be gzip/deflate compressed via
+// the browser Compression Streams API (zstd is not available there).
+import type { BrowserCompressionFormat } from '$lib/io/decompress'
+import { is_plain_object } from '$lib/utils'
+import type { AnyStructure } from './index'
+import { from_mp_document, from_pymatgen_dict, is_mp_document } from './mp-docs'
+import { is_parsed_structure } from './parse'
+
+// Structure of one JSON line: a pymatgen dict or an MP document wrapping one. A string id
+// on the dict is kept.
+function parse_jsonl_line(line: string, line_number: number): AnyStructure {
+  let obj: unknown
+  try {
+    obj = JSON.parse(line)
+  } catch (error) {
+    throw new Error(`Invalid JSON on line ${line_number}: ${error}`, { cause: error })
+  }
+  if (is_mp_document(obj)) return from_mp_document(obj)
+  if (!is_plain_object(obj) || !is_parsed_structure(obj)) {
+    throw new Error(`Line ${line_number} is not a pymatgen structure`)
+  }
+  const structure = from_pymatgen_dict(obj)
+  return typeof obj.id === `string` ? { ...structure, id: obj.id } : structure
+}
+
+// Complete lines across text chunks, numbered from 1
+class LineSplitter {
+  private partial = ``
+  private count = 0
+
+  push(chunk: string): [string, number][] {
+    const lines = (this.partial + chunk).split(`\n`)
+    this.partial = lines.pop() ?? ``
+    return lines.map((line) => [line, ++this.count])
+  }
+
+  flush(): [string, number][] {
+    const rest: [string, number][] = this.partial ? [[this.partial, ++this.count]] : []
+    this.partial = ``
+    return rest
+  }
+}
+
+function* parse_lines(lines: [string, number][]): Generator<AnyStructure> {
+  for (const [line, line_number] of lines) {
+    if (line.trim()) yield parse_jsonl_line(line, line_number)
+  }
+}
+
+// Lazily parse JSON Lines structures from the whole text or any iterable of text chunks,
+// skipping blank lines. Throws with the line number on invalid lines.
+export function* iter_jsonl_structures(
+  chunks: string | Iterable<string>,
+): Generator<AnyStructure> {
+  const splitter = new LineSplitter()
+  for (const chunk of typeof chunks === `string` ? [chunks] : chunks) {
+    yield* parse_lines(splitter.push(chunk))
+  }
+  yield* parse_lines(splitter.flush())
+}
+
+// iter_jsonl_structures for async text streams
+export async function* iter_jsonl_structures_async(
+  chunks: AsyncIterable<string>,
+): AsyncGenerator<AnyStructure> {
+  const splitter = new LineSplitter()
+  for await (const chunk of chunks) yield* parse_lines(splitter.push(chunk))
+  yield* parse_lines(splitter.flush())
+}
+
+// Stream structures from JSON Lines bytes, e.g. file.stream() of a browser File or a
+// fetch response body, decompressing on the fly if compression is given
+export async function* read_jsonl_structures(
+  stream: ReadableStream<Uint8Array>,
+  compression?: BrowserCompressionFormat,
+): AsyncGenerator<AnyStructure> {
+  const bytes = compression ? stream.pipeThrough(new DecompressionStream(compression)) : stream
+  const reader = bytes.getReader()
+  const decoder = new TextDecoder()
+  async function* text_chunks(): AsyncGenerator<string> {
+    try {
+      for (let result = await reader.read(); !result.done; result = await reader.read()) {
+        yield decoder.decode(result.value, { stream: true })
+      }
+      yield decoder.decode()
+    } finally {
+      reader.releaseLock()
+    }
+  }
+  yield* iter_jsonl_structures_async(text_chunks())
+}
+
+// pymatgen as_dict() of a structure (Structure with a lattice, else Molecule) plus its id
+export function structure_to_pymatgen_dict(structure: AnyStructure): Record<string, unknown> {
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const sites = structure.sites.map(({ species, abc, xyz, label, properties }) => ({
+    species: species.map(({ element, occu, oxidation_state }) => ({
+      element,
+      occu,
+      ...(oxidation_state !== 0 && { oxidation_state }),
+    })),
+    ...(lattice && { abc }),
+    xyz,
+    label,
+    properties,
+  }))
+  return {
+    '@module': `pymatgen.core.structure`,
+    '@class': lattice ? `Structure` : `Molecule`,
+    ...(structure.id !== undefined && { id: structure.id }),
+    charge: structure.charge ?? 0,
+    ...(lattice && { lattice: { matrix: lattice.matrix, pbc: lattice.pbc } }),
+    properties: structure.properties ?? {},
+    sites,
+  }
+}
+
+const to_jsonl_line = (structure: AnyStructure): string =>
+  `${JSON.stringify(structure_to_pymatgen_dict(structure))}\n`
+
+// JSON Lines text of structures, one newline-terminated line at a time
+export function* structures_to_jsonl(structures: Iterable<AnyStructure>): Generator<string> {
+  for (const structure of structures) yield to_jsonl_line(structure)
+}
+
+// Byte stream of JSON Lines structures, serialized as the stream is read (so structures
+// can be produced lazily) and compressed if compression is given
+export function write_jsonl_structures(
+  structures: Iterable<AnyStructure> | AsyncIterable<AnyStructure>,
+  compression?: BrowserCompressionFormat,
+): ReadableStream<Uint8Array> {
+  const iterator =
+    Symbol.asyncIterator in structures
+      ? structures[Symbol.asyncIterator]()
+      : structures[Symbol.iterator]()
+  const encoder = new TextEncoder()
+  const bytes = new ReadableStream<Uint8Array>({
+    async pull(controller) {
+      const { done, value } = await iterator.next()
+      if (done) controller.close()
+      else controller.enqueue(encoder.encode(to_jsonl_line(value)))
+    },
+    async cancel() {
+      await iterator.return?.()
+    },
+  })
+  return compression ? bytes.pipeThrough(new CompressionStream(compression)) : bytes
+}
@@ -0,0 +1,237 @@
+// Lattice kinetic Monte Carlo of mobile ions on a precomputed hop network (sites plus
+// hop rates or barriers, e.g. from BVSE paths, NEB or site_occupancy of MD runs) for
+// tracer and collective diffusion in regimes too slow for MD
+import { K_B_EV } from '$lib/constants'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { linear_fit } from '$lib/stats'
+
+export type HopSite = {
+  abc: Vec3 // fractional coordinates
+  energy?: number // eV, site energy for detailed balance of reverse hops (default 0)
+}
+
+export type Hop = {
+  from: number // site indices
+  to: number
+  image?: Vec3 // cell of the target site relative to the source's (default [0, 0, 0])
+  rate?: number // per time unit, takes precedence over barrier
+  barrier?: number // eV, rate = attempt_frequency · exp(−barrier / k_B T)
+  attempt_frequency?: number // per time unit (default options.attempt_frequency)
+}
+
+export type HopNetwork = {
+  lattice: Matrix3x3 // row lattice vectors in Å
+  sites: HopSite[]
+  hops: Hop[] // directed, see KmcOptions.add_reverse
+}
+
+export type KmcOptions = {
+  n_carriers?: number // mobile ions placed on random distinct sites (default 1)
+  initial_sites?: number[] // explicit start sites instead of random placement
+  temperature?: number // K, for barriers and reverse rates (default 300)
+  attempt_frequency?: number // per time unit, default 1e13 so times are in s
+  // add the reverse of every hop not listed explicitly, with the rate that satisfies
+  // detailed balance for the site energies (default true)
+  add_reverse?: boolean
+  exclusion?: boolean // at most one carrier per site (default true)
+  n_equilibration?: number // hops discarded before sampling (default 0)
+  n_steps?: number // sampled hops (default 100 000)
+  n_samples?: number // snapshots for the MSD fits (default 100)
+  seed?: number // default 0, runs are reproducible for a given seed
+}
+
+export type KmcResult = {
+  time: number // simulated time of the sampled hops
+  n_hops: number
+  // diffusion coefficients in Å² per time unit from multi-origin MSD fits
+  tracer_diffusion: number // D* = <|Δr_i|²> / 6t
+  collective_diffusion: number // D_σ = <|Σ Δr_i|²> / 6Nt (jump diffusion)
+  // f = Σ|R_i|² / Σ(jump lengths²) over the whole run: 1 for uncorrelated walks, 0.653
+  // for vacancy diffusion on a simple cubic lattice
+  correlation_factor: number
+  haven_ratio: number // D* / D_σ
+  site_occupancy: number[] // time-averaged carriers per site
+  lag_times: number[] // mean time per snapshot lag
+  tracer_msd: number[] // Å² per lag
+  collective_msd: number[] // Å² per lag, divided by the number of carriers
+}
+
+type KmcHop = { to: number; rate: number; jump: Vec3; jump_sq: number }
+
+// Directed hops with rates and Cartesian jump vectors, reverse hops added if requested
+function build_hops(network: HopNetwork, options: KmcOptions): KmcHop[][] {
+  const { temperature = 300, attempt_frequency = 1e13, add_reverse = true } = options
+  const { sites, lattice } = network
+  const k_t = K_B_EV * temperature
+  if (!(k_t > 0)) throw new Error(`temperature must be positive, got ${temperature}`)
+  const frac_to_cart = math.create_frac_to_cart(lattice)
+  const energy = (idx: number) => sites[idx].energy ?? 0
+
+  const rated = network.hops.map((hop) => {
+    const { from, to, image = [0, 0, 0] } = hop
+    if (!sites[from] || !sites[to]) {
+      throw new Error(`Hop ${from} → ${to} references a missing site (${sites.length} sites)`)
+    }
+    const prefactor = hop.attempt_frequency ?? attempt_frequency
+    const rate = hop.rate ?? prefactor * Math.exp(-(hop.barrier ?? NaN) / k_t)
+    if (!(rate >= 0)) {
+      throw new Error(`Hop ${from} → ${to} needs a non-negative rate or a barrier`)
+    }
+    return { from, to, image, rate }
+  })
+  const key = (from: number, to: number, image: Vec3) => `${from},${to},${image.join(`,`)}`
+  const listed = new Set(rated.map(({ from, to, image }) => key(from, to, image)))
+  if (add_reverse) {
+    for (const { from, to, image, rate } of [...rated]) {
+      const back = math.scale(image, -1).map((shift) => shift + 0) as Vec3
+      if (listed.has(key(to, from, back))) continue
+      listed.add(key(to, from, back))
+      const reverse_rate = rate * Math.exp((energy(to) - energy(from)) / k_t)
+      rated.push({ from: to, to: from, image: back, rate: reverse_rate })
+    }
+  }
+
+  const hops: KmcHop[][] = sites.map(() => [])
+  for (const { from, to, image, rate } of rated) {
+    const frac = math.add(math.subtract(sites[to].abc, sites[from].abc), image)
+    const jump = frac_to_cart(frac)
+    hops[from].push({ to, rate, jump, jump_sq: math.dot(jump, jump) })
+  }
+  return hops
+}
+
+// Rejection-free (BKL / n-fold way) kMC: every step picks one of all allowed carrier hops
+// with probability proportional to its rate and advances the clock by an exponential
+// waiting time. Carriers block each other's target sites with exclusion, which gives
+// correlation factors and Haven ratios below 1 at high site filling.
+export function kinetic_monte_carlo(network: HopNetwork, options: KmcOptions = {}): KmcResult {
+  const {
+    n_carriers = options.initial_sites?.length ?? 1,
+    exclusion = true,
+    n_equilibration = 0,
+    n_steps = 100_000,
+    n_samples = 100,
+    seed = 0,
+  } = options
+  const n_sites = network.sites.length
+  if (!Number.isInteger(n_carriers) || n_carriers < 1) {
+    throw new Error(`n_carriers must be a positive integer, got ${n_carriers}`)
+  }
+  if (exclusion && n_carriers > n_sites) {
+    throw new Error(`${n_carriers} carriers don't fit on ${n_sites} sites with exclusion`)
+  }
+  if (!Number.isInteger(n_samples) || n_samples < 2 || n_samples > n_steps) {
+    throw new Error(`n_samples must be an integer in [2, n_steps], got ${n_samples}`)
+  }
+  const hops = build_hops(network, options)
+  const random = math.mulberry32(seed)
+
+  let positions: number[]
+  if (options.initial_sites) {
+    positions = [...options.initial_sites]
+    if (positions.length !== n_carriers || positions.some((site) => !network.sites[site])) {
+      throw new Error(`initial_sites must list ${n_carriers} valid site indices`)
+    }
+    if (exclusion && new Set(positions).size !== positions.length) {
+      throw new Error(`initial_sites must be distinct with exclusion`)
+    }
+  } else if (exclusion) {
+    // partial Fisher-Yates shuffle of the site indices
+    const order = network.sites.map((_, idx) => idx)
+    for (let idx = 0; idx < n_carriers; idx++) {
+      const swap = idx + Math.floor(random() * (n_sites - idx))
+      ;[order[idx], order[swap]] = [order[swap], order[idx]]
+    }
+    positions = order.slice(0, n_carriers)
+  } else positions = Array.from({ length: n_carriers }, () => Math.floor(random() * n_sites))
+  const occupied = network.sites.map(() => 0)
+  for (const site of positions) occupied[site]++
+
+  const displacements: Vec3[] = positions.map(() => [0, 0, 0])
+  const squared_jumps = positions.map(() => 0)
+  const occupancy_time = network.sites.map(() => 0)
+  const snapshots: { time: number; disp: Vec3[] }[] = []
+  const sample_every = Math.floor(n_steps / n_samples)
+  let time = 0
+  const candidates: { carrier: number; hop: KmcHop; cumulative: number }[] = []
+  for (let step = -n_equilibration; step < n_steps; step++) {
+    if (step === 0) {
+      time = 0
+      displacements.forEach((disp) => disp.fill(0))
+      squared_jumps.fill(0)
+      occupancy_time.fill(0)
+    }
+    if (step >= 0 && step % sample_every === 0) {
+      snapshots.push({ time, disp: displacements.map((disp) => [...disp] as Vec3) })
+    }
+    candidates.length = 0
+    let total_rate = 0
+    positions.forEach((site, carrier) => {
+      for (const hop of hops[site]) {
+        if (exclusion && occupied[hop.to] > 0) continue
+        total_rate += hop.rate
+        candidates.push({ carrier, hop, cumulative: total_rate })
+      }
+    })
+    if (!(total_rate > 0)) throw new Error(`No allowed hops left after ${step} steps`)
+    const target = random() * total_rate
+    const chosen =
+      candidates.find(({ cumulative }) => cumulative > target) ??
+      candidates[candidates.length - 1]
+    const waiting = -Math.log(1 - random()) / total_rate
+    if (step >= 0) for (const site of positions) occupancy_time[site] += waiting
+    time += waiting
+
+    const { carrier, hop } = chosen
+    occupied[positions[carrier]]--
+    occupied[hop.to]++
+    positions[carrier] = hop.to
+    displacements[carrier] = math.add(displacements[carrier], hop.jump)
+    squared_jumps[carrier] += hop.jump_sq
+  }
+  snapshots.push({ time, disp: displacements.map((disp) => [...disp] as Vec3) })
+
+  // multi-origin MSDs over snapshot lags up to half the run
+  const lag_times: number[] = []
+  const tracer_msd: number[] = []
+  const collective_msd: number[] = []
+  const n_snapshots = snapshots.length
+  for (let lag = 1; lag <= Math.floor((n_snapshots - 1) / 2); lag++) {
+    let [sum_dt, sum_tracer, sum_collective, n_origins] = [0, 0, 0, 0]
+    for (let origin = 0; origin + lag < n_snapshots; origin++) {
+      const [start, end] = [snapshots[origin], snapshots[origin + lag]]
+      const total: Vec3 = [0, 0, 0]
+      end.disp.forEach((disp, carrier) => {
+        const delta = math.subtract(disp, start.disp[carrier])
+        sum_tracer += math.dot(delta, delta) / n_carriers
+        for (let dim = 0; dim < 3; dim++) total[dim] += delta[dim]
+      })
+      sum_collective += math.dot(total, total) / n_carriers
+      sum_dt += end.time - start.time
+      n_origins++
+    }
+    lag_times.push(sum_dt / n_origins)
+    tracer_msd.push(sum_tracer / n_origins)
+    collective_msd.push(sum_collective / n_origins)
+  }
+  const fit_slope = (msd: number[]) =>
+    lag_times.length >= 2 ? linear_fit(lag_times, msd).slope : msd[0] / lag_times[0]
+  const tracer_diffusion = fit_slope(tracer_msd) / 6
+  const collective_diffusion = fit_slope(collective_msd) / 6
+
+  const sum_sq_disp = displacements.reduce((sum, disp) => sum + math.dot(disp, disp), 0)
+  const sum_sq_jumps = squared_jumps.reduce((sum, val) => sum + val, 0)
+  return {
+    time,
+    n_hops: n_steps,
+    tracer_diffusion,
+    collective_diffusion,
+    correlation_factor: sum_sq_disp / sum_sq_jumps,
+    haven_ratio: tracer_diffusion / collective_diffusion,
+    site_occupancy: occupancy_time.map((occ_time) => occ_time / time),
+    lag_times,
+    tracer_msd,
+    collective_msd,
+  }
+}
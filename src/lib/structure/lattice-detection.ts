@@ -0,0 +1,198 @@
+// Periodic lattice detection in extended Cartesian point clouds (atom lists from STM/TEM
+// images, crystalline clusters cut from MD, ...): recurring same-element difference
+// vectors are scored by how many points they translate onto other points, the three
+// shortest independent translations span the cell, and points are folded into it to
+// find the basis
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from './bonding'
+import type { AnyStructure, Crystal } from './index'
+import { make_site } from './site'
+
+export type LatticeDetectionOptions = {
+  tolerance?: number // Å, max deviation of a point from its ideal position (default 0.2)
+  n_neighbors?: number // nearest neighbors per point giving candidate vectors (default 12)
+  // translations must map at least this fraction of the best candidate's points onto
+  // other points (surfaces lower every score alike, intra-basis vectors score lower)
+  min_score?: number // default 0.75
+  // basis sites holding fewer points than this fraction of the most populated site are
+  // treated as defects and reported as outliers (default 0.5)
+  min_occupancy?: number
+}
+
+export type LatticeDetection = {
+  structure: Crystal // best-fit cell with the averaged basis
+  residuals: number[] // Å per input point from its ideal position, NaN for outliers
+  rms_residual: number // Å over assigned points
+  outliers: number[] // input point indices not assigned to a basis site
+  n_cells: number // assigned points per basis atom
+}
+
+type Point = { element: ElementSymbol | null; xyz: Vec3 }
+type BasisSite = {
+  element: ElementSymbol | null
+  ref: Vec3 // fractional position of the first point assigned to the site
+  offsets: Vec3[] // minimum-image fractional offsets of all members from ref
+  members: number[] // point indices
+}
+
+// Uniform grid hash for fixed-radius lookups of nearby points
+function make_point_lookup(points: Point[], radius: number) {
+  const key = (xyz: Vec3) => xyz.map((coord) => Math.floor(coord / radius)).join(`,`)
+  const buckets = new Map<string, number[]>()
+  points.forEach(({ xyz }, idx) => {
+    const bucket_key = key(xyz)
+    buckets.set(bucket_key, [...(buckets.get(bucket_key) ?? []), idx])
+  })
+  // index of a point with this element within radius of xyz, or -1
+  return (xyz: Vec3, element: ElementSymbol | null): number => {
+    const base = xyz.map((coord) => Math.floor(coord / radius))
+    for (let dx = -1; dx <= 1; dx++) {
+      for (let dy = -1; dy <= 1; dy++) {
+        for (let dz = -1; dz <= 1; dz++) {
+          const bucket = buckets.get(`${base[0] + dx},${base[1] + dy},${base[2] + dz}`)
+          for (const idx of bucket ?? []) {
+            const point = points[idx]
+            if (point.element !== element) continue
+            if (Math.hypot(...math.subtract(point.xyz, xyz)) <= radius) return idx
+          }
+        }
+      }
+    }
+    return -1
+  }
+}
+
+// Detect the lattice and basis of a point cloud (a Molecule or the sites of any
+// structure, Cartesian positions only). Throws if fewer than 3 independent translations
+// recur, e.g. for 2D sheets, amorphous clusters or too small clouds.
+export function detect_lattice(
+  structure: AnyStructure,
+  options: LatticeDetectionOptions = {},
+): LatticeDetection {
+  const { tolerance = 0.2, n_neighbors = 12, min_score = 0.75, min_occupancy = 0.5 } = options
+  const points: Point[] = structure.sites.map((site) => ({
+    element: get_majority_element(site),
+    xyz: site.xyz,
+  }))
+  const n_points = points.length
+  if (n_points < 4) throw new Error(`Need at least 4 points to detect a lattice`)
+
+  // candidate translations: same-element difference vectors to nearest neighbors, with
+  // the sign fixed so v and −v merge, clustered within tolerance
+  const clusters: { sum: Vec3; count: number }[] = []
+  const canonical = (vec: Vec3): Vec3 =>
+    vec[0] * 1e6 + vec[1] * 1e3 + vec[2] < 0 ? math.scale(vec, -1) : vec
+  points.forEach((point, idx) => {
+    const nearest = points
+      .map((other, other_idx) => ({
+        other_idx,
+        dist: Math.hypot(...math.subtract(other.xyz, point.xyz)),
+      }))
+      .filter(
+        ({ other_idx }) => other_idx !== idx && points[other_idx].element === point.element,
+      )
+      .sort((nb_1, nb_2) => nb_1.dist - nb_2.dist)
+      .slice(0, n_neighbors)
+    for (const { other_idx } of nearest) {
+      const diff = canonical(math.subtract(points[other_idx].xyz, point.xyz))
+      const cluster = clusters.find(({ sum, count }) => {
+        const mean = math.scale(sum, 1 / count)
+        return Math.hypot(...math.subtract(mean, diff)) <= tolerance
+      })
+      if (cluster) {
+        cluster.sum = math.add(cluster.sum, diff)
+        cluster.count++
+      } else clusters.push({ sum: diff, count: 1 })
+    }
+  })
+
+  // score = fraction of points p with a same-element point at p + v or p − v
+  const find_point = make_point_lookup(points, tolerance)
+  const candidates = clusters.map(({ sum, count }) => {
+    const vec = math.scale(sum, 1 / count)
+    const hits = points.filter(
+      ({ element, xyz }) =>
+        find_point(math.add(xyz, vec), element) !== -1 ||
+        find_point(math.subtract(xyz, vec), element) !== -1,
+    ).length
+    return { vec, score: hits / n_points, length: Math.hypot(...vec) }
+  })
+  const max_score = Math.max(0, ...candidates.map(({ score }) => score))
+  const translations = candidates
+    .filter(({ score, length }) => score >= min_score * max_score && length > tolerance)
+    .sort((cand_1, cand_2) => cand_1.length - cand_2.length)
+
+  // successive minima: shortest vector, shortest non-parallel, shortest non-coplanar
+  const basis: Vec3[] = []
+  for (const { vec, length } of translations) {
+    if (basis.length === 1) {
+      const cross = Math.hypot(...math.cross_3d(basis[0], vec))
+      if (cross < 0.1 * Math.hypot(...basis[0]) * length) continue
+    } else if (basis.length === 2) {
+      const lengths = Math.hypot(...basis[0]) * Math.hypot(...basis[1]) * length
+      if (Math.abs(math.det_3x3([basis[0], basis[1], vec])) < 0.1 * lengths) continue
+    }
+    basis.push(vec)
+    if (basis.length === 3) break
+  }
+  if (basis.length < 3) {
+    throw new Error(`Found only ${basis.length} independent lattice translations`)
+  }
+  if (math.det_3x3(basis as Matrix3x3) < 0) basis[2] = math.scale(basis[2], -1)
+  const matrix = basis as Matrix3x3
+
+  // fold points into the cell (origin at the first point) and cluster them into basis
+  // sites by element and minimum-image distance
+  const cart_to_frac = math.create_cart_to_frac(matrix)
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  const origin = points[0].xyz
+  const wrap = (frac: Vec3) => frac.map((val) => val - Math.floor(val)) as Vec3
+  const min_image = (frac: Vec3) => frac.map((val) => val - Math.round(val)) as Vec3
+  const sites: BasisSite[] = []
+  const fracs = points.map(({ xyz }) => wrap(cart_to_frac(math.subtract(xyz, origin))))
+  fracs.forEach((frac, idx) => {
+    const { element } = points[idx]
+    const site = sites.find(
+      (cand) =>
+        cand.element === element &&
+        Math.hypot(...frac_to_cart(min_image(math.subtract(frac, cand.ref)))) <= tolerance,
+    )
+    if (site) {
+      site.offsets.push(min_image(math.subtract(frac, site.ref)))
+      site.members.push(idx)
+    } else sites.push({ element, ref: frac, offsets: [[0, 0, 0]], members: [idx] })
+  })
+  const max_members = Math.max(...sites.map(({ members }) => members.length))
+  const kept = sites.filter(({ members }) => members.length >= min_occupancy * max_members)
+
+  const residuals = points.map(() => NaN)
+  const crystal_sites = kept.map(({ element, ref, offsets, members }, site_idx) => {
+    const mean_offset = math.scale(math.add(...offsets), 1 / offsets.length)
+    const abc = wrap(math.add(ref, mean_offset))
+    offsets.forEach((offset, member_idx) => {
+      const deviation = frac_to_cart(math.subtract(offset, mean_offset))
+      residuals[members[member_idx]] = Math.hypot(...deviation)
+    })
+    const symbol = element ?? (`X` as ElementSymbol)
+    return make_site(symbol, abc, frac_to_cart(abc), `${symbol}${site_idx + 1}`)
+  })
+  const assigned = residuals.filter((val) => !Number.isNaN(val))
+  const outliers = [...residuals.keys()].filter((idx) => Number.isNaN(residuals[idx]))
+  const rms_residual = Math.sqrt(
+    assigned.reduce((sum, val) => sum + val * val, 0) / Math.max(assigned.length, 1),
+  )
+  const lattice = {
+    matrix,
+    pbc: [true, true, true] as const,
+    ...math.calc_lattice_params(matrix),
+  }
+  return {
+    structure: { lattice, sites: crystal_sites },
+    residuals,
+    rms_residual,
+    outliers,
+    n_cells: assigned.length / Math.max(crystal_sites.length, 1),
+  }
+}
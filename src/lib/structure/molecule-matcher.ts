@@ -0,0 +1,245 @@
+// Matching of finite systems (molecules, clusters, adsorbates cut out of slabs) by RMSD
+// after optimal superposition: centroids are aligned, same-element atoms are paired by
+// Hungarian assignment and the rotation comes from the Kabsch fit of the paired atoms.
+// Lattices are ignored, so compare adsorbates via their identify_adsorbates site_indices
+// rather than whole slabs.
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import {
+  centered,
+  frame_from,
+  min_cost_assignment,
+  optimal_rotation,
+} from '$lib/symmetry/continuous-measures'
+import { get_majority_element } from './bonding'
+import type { AnyStructure } from './index'
+
+export type MoleculeMatcherOptions = {
+  tolerance?: number // max RMSD in Å for two structures to match (default 0.1)
+  // also superimpose the mirror image of the second structure, so enantiomers match
+  // (default false)
+  allow_inversion?: boolean
+  // reorder same-element atoms to find the best pairing, otherwise atoms are paired by
+  // index and must have the same elements in the same order (default true)
+  permute?: boolean
+}
+
+export type MoleculeGroupOptions = MoleculeMatcherOptions & {
+  // member listed first in each group as its representative: the first occurrence or the
+  // medoid, i.e. the member with the smallest summed RMSD to all others, which is a better
+  // canonical geometry for noisy relaxations (default `first`)
+  representative?: `first` | `medoid`
+}
+
+export type MoleculeFit = {
+  rmsd: number // Å, over all atoms after superposition
+  mapping: number[] // index in the second structure paired with each site of the first
+  rotation: Matrix3x3 // proper rotation taking the centered second onto the first
+  inverted: boolean // whether the second structure was mirrored through its centroid
+}
+
+type Fit = { sum_sq: number; mapping: number[]; rotation: Matrix3x3 }
+
+// Sum of squared deviations after the Kabsch rotation of sources onto targets
+function kabsch_fit(targets: Vec3[], sources: Vec3[], mapping: number[]): Fit {
+  const paired = mapping.map((src_idx) => sources[src_idx])
+  const { rotation } = optimal_rotation(paired, targets)
+  // summed directly rather than from the overlap to avoid cancellation for close matches
+  const sum_sq = paired.reduce(
+    (sum, src, idx) =>
+      sum + math.euclidean_dist(targets[idx], math.mat3x3_vec3_multiply(rotation, src)) ** 2,
+    0,
+  )
+  return { sum_sq, mapping, rotation }
+}
+
+// Pairing of same-element atoms minimizing the squared distances between targets and
+// rotated sources, solved per element
+function assign_by_element(targets: Vec3[], rotated: Vec3[], groups: number[][][]): number[] {
+  const mapping = Array(targets.length).fill(-1)
+  for (const [target_idx, source_idx] of groups) {
+    const cost = target_idx.map((tgt) =>
+      source_idx.map((src) => math.euclidean_dist(targets[tgt], rotated[src]) ** 2),
+    )
+    min_cost_assignment(cost).forEach((col, row) => {
+      mapping[target_idx[row]] = source_idx[col]
+    })
+  }
+  return mapping
+}
+
+// Best permuted fit, seeding rotations by aligning an anchor atom pair of the targets
+// (farthest atom from the centroid plus the atom spanning the largest angle with it) with
+// every same-element source pair at similar radii and separation, then alternating
+// assignment and Kabsch rotation until the pairing is stable
+function permuted_fit(
+  targets: Vec3[],
+  sources: Vec3[],
+  elements: [string[], string[]],
+  seed_tol: number,
+): Fit {
+  const by_element = new Map<string, number[][]>()
+  elements.forEach((list, which) =>
+    list.forEach((element, idx) => {
+      const group = by_element.get(element) ?? [[], []]
+      group[which].push(idx)
+      by_element.set(element, group)
+    }),
+  )
+  const groups = [...by_element.values()]
+  const radii = [targets, sources].map((points) => points.map((vec) => Math.hypot(...vec)))
+  const anchor = radii[0].indexOf(Math.max(...radii[0]))
+  let best: Fit = kabsch_fit(targets, sources, assign_by_element(targets, sources, groups))
+  if (radii[0][anchor] < 1e-8) return best
+
+  const cross_norms = targets.map((vec) => Math.hypot(...math.cross_3d(targets[anchor], vec)))
+  const partner = cross_norms.indexOf(Math.max(...cross_norms))
+  const has_partner = cross_norms[partner] > 1e-6 * radii[0][anchor] ** 2
+  const target_frame = frame_from(targets[anchor], has_partner ? targets[partner] : null)
+  const anchor_dist = math.euclidean_dist(targets[anchor], targets[partner])
+
+  const candidates = (tgt_idx: number) =>
+    sources.flatMap((_, src_idx) =>
+      elements[1][src_idx] === elements[0][tgt_idx] &&
+      Math.abs(radii[1][src_idx] - radii[0][tgt_idx]) <= seed_tol
+        ? [src_idx]
+        : [],
+    )
+  const seeds: [number, number | null][] = candidates(anchor).flatMap((src_anchor) => {
+    if (!has_partner) return [[src_anchor, null] as [number, null]]
+    return candidates(partner)
+      .filter(
+        (src_partner) =>
+          src_partner !== src_anchor &&
+          Math.abs(
+            math.euclidean_dist(sources[src_anchor], sources[src_partner]) - anchor_dist,
+          ) <= seed_tol,
+      )
+      .map((src_partner): [number, number] => [src_anchor, src_partner])
+  })
+
+  const tried = new Set<string>()
+  for (const [src_anchor, src_partner] of seeds) {
+    const source_frame = frame_from(
+      sources[src_anchor],
+      src_partner === null ? null : sources[src_partner],
+    )
+    let rotation = math.dot(target_frame, math.transpose_3x3_matrix(source_frame))
+    for (let iter = 0; iter < 10; iter++) {
+      const rotated = sources.map((vec) => math.mat3x3_vec3_multiply(rotation, vec))
+      const mapping = assign_by_element(targets, rotated, groups)
+      const key = mapping.join(`,`)
+      if (tried.has(key)) break
+      tried.add(key)
+      const fit = kabsch_fit(targets, sources, mapping)
+      if (fit.sum_sq < best.sum_sq) best = fit
+      rotation = fit.rotation
+    }
+  }
+  return best
+}
+
+const element_list = (structure: AnyStructure): string[] =>
+  structure.sites.map((site) => get_majority_element(site) ?? `X`)
+
+// Composition key of sorted element counts
+const composition_key = (elements: string[]): string => {
+  const counts = new Map<string, number>()
+  for (const element of elements) counts.set(element, (counts.get(element) ?? 0) + 1)
+  return [...counts.entries()]
+    .map(([element, count]) => `${element}${count}`)
+    .toSorted()
+    .join(` `)
+}
+
+// Minimum-RMSD superposition of struct_2 onto struct_1, null if their compositions differ
+// (or, with permute: false, their element sequences)
+export function molecule_rmsd(
+  struct_1: AnyStructure,
+  struct_2: AnyStructure,
+  options: MoleculeMatcherOptions = {},
+): MoleculeFit | null {
+  const { allow_inversion = false, permute = true, tolerance = 0.1 } = options
+  const elements: [string[], string[]] = [element_list(struct_1), element_list(struct_2)]
+  const n_sites = elements[0].length
+  if (n_sites === 0) throw new Error(`Cannot match structures without sites`)
+  if (permute) {
+    if (composition_key(elements[0]) !== composition_key(elements[1])) return null
+  } else if (elements[0].join() !== elements[1].join()) return null
+
+  const targets = centered(struct_1.sites.map(({ xyz }) => xyz))
+  const sources = centered(struct_2.sites.map(({ xyz }) => xyz))
+  // seeds only need to land in the right basin, so allow generous geometric slack
+  const seed_tol = Math.max(0.5, 4 * tolerance)
+  const identity = [...Array(n_sites).keys()]
+  const fit_to = (points: Vec3[]) =>
+    permute
+      ? permuted_fit(targets, points, elements, seed_tol)
+      : kabsch_fit(targets, points, identity)
+
+  let best = { ...fit_to(sources), inverted: false }
+  if (allow_inversion) {
+    const mirrored = fit_to(sources.map((vec) => math.scale(vec, -1)))
+    if (mirrored.sum_sq < best.sum_sq - 1e-12) best = { ...mirrored, inverted: true }
+  }
+  const { sum_sq, mapping, rotation, inverted } = best
+  return { rmsd: Math.sqrt(sum_sq / n_sites), mapping, rotation, inverted }
+}
+
+// Whether two finite structures superimpose within options.tolerance RMSD
+export function molecules_match(
+  struct_1: AnyStructure,
+  struct_2: AnyStructure,
+  options: MoleculeMatcherOptions = {},
+): boolean {
+  const fit = molecule_rmsd(struct_1, struct_2, options)
+  return fit !== null && fit.rmsd <= (options.tolerance ?? 0.1)
+}
+
+// Member of a group of matching structures with the smallest summed RMSD to the other
+// members (the first one on ties)
+export function group_medoid(
+  structures: readonly AnyStructure[],
+  members: readonly number[],
+  options: MoleculeMatcherOptions = {},
+): number {
+  if (members.length === 0) throw new Error(`Cannot pick the medoid of an empty group`)
+  const dists = members.map(() => Array(members.length).fill(0))
+  members.forEach((idx_1, pos_1) => {
+    for (let pos_2 = pos_1 + 1; pos_2 < members.length; pos_2++) {
+      const fit = molecule_rmsd(structures[idx_1], structures[members[pos_2]], options)
+      dists[pos_1][pos_2] = dists[pos_2][pos_1] = fit?.rmsd ?? Infinity
+    }
+  })
+  const totals = dists.map((row) => row.reduce((sum, dist) => sum + dist, 0))
+  return members[totals.indexOf(Math.min(...totals))]
+}
+
+// Group structure indices into sets of matching molecules/clusters (insertion-ordered),
+// comparing each structure with the first occurrence of every group of equal composition.
+// With representative: `medoid`, each group's medoid is then moved to the front.
+export function group_molecules(
+  structures: readonly AnyStructure[],
+  options: MoleculeGroupOptions = {},
+): number[][] {
+  const groups: number[][] = []
+  const keys: string[] = []
+  structures.forEach((structure, idx) => {
+    const key = composition_key(element_list(structure))
+    const group = groups.find(
+      (members, group_idx) =>
+        keys[group_idx] === key && molecules_match(structures[members[0]], structure, options),
+    )
+    if (group) group.push(idx)
+    else {
+      groups.push([idx])
+      keys.push(key)
+    }
+  })
+  if (options.representative !== `medoid`) return groups
+  return groups.map((members) => {
+    if (members.length < 3) return members // either member of a pair is a medoid
+    const medoid = group_medoid(structures, members, options)
+    return [medoid, ...members.filter((idx) => idx !== medoid)]
+  })
+}
@@ -0,0 +1,90 @@
+// Materials Project API documents (SummaryDoc, MaterialsDoc, TaskDoc and API responses
+// wrapping them under `data`) to structures. The pymatgen structure dict is taken from the
+// document's preferred location, i.e. the relaxed output for task documents, and headline
+// properties of the document are copied onto the structure.
+import { is_plain_object } from '$lib/utils'
+import type { AnyStructure } from './index'
+import { parse_pymatgen_json } from './parse'
+
+// where each document type keeps its structure, in order of preference
+export const MP_STRUCTURE_PATHS: readonly (readonly (string | number)[])[] = [
+  [`structure`],
+  [`output`, `structure`],
+  [`calcs_reversed`, 0, `output`, `structure`],
+  [`input`, `structure`],
+]
+
+// scalar document fields copied to structure.properties (task docs keep energies and band
+// gaps under `output`, which is searched too)
+export const MP_PROPERTY_KEYS = [
+  `formula_pretty`,
+  `energy_per_atom`,
+  `uncorrected_energy_per_atom`,
+  `formation_energy_per_atom`,
+  `energy_above_hull`,
+  `is_stable`,
+  `band_gap`,
+  `bandgap`,
+  `is_metal`,
+  `is_magnetic`,
+  `total_magnetization`,
+  `density`,
+  `task_type`,
+] as const
+
+type MpDocument = Record<string, unknown>
+
+const get_path = (obj: unknown, path: readonly (string | number)[]): unknown =>
+  path.reduce<unknown>(
+    (value, key) =>
+      Array.isArray(value) || is_plain_object(value)
+        ? (value as Record<string | number, unknown>)[key]
+        : undefined,
+    obj,
+  )
+
+// API responses wrap documents as { data: [doc, ...], meta }, use the first one
+const unwrap_response = (obj: unknown): unknown =>
+  is_plain_object(obj) && Array.isArray(obj.data) && is_plain_object(obj.meta)
+    ? obj.data[0]
+    : obj
+
+const has_pymatgen_sites = (value: unknown): boolean =>
+  is_plain_object(value) && Array.isArray(value.sites) && value.sites.length > 0
+
+export function is_mp_document(obj: unknown): obj is MpDocument {
+  const doc = unwrap_response(obj)
+  if (!is_plain_object(doc)) return false
+  if (typeof doc.material_id !== `string` && typeof doc.task_id !== `string`) return false
+  return MP_STRUCTURE_PATHS.some((path) => has_pymatgen_sites(get_path(doc, path)))
+}
+
+// Structure of a pymatgen Structure or Molecule dict as found in MP documents, read by
+// the same parser as pymatgen JSON files
+export function from_pymatgen_dict(dict: Record<string, unknown>): AnyStructure {
+  const structure = parse_pymatgen_json(dict)
+  if (!structure) throw new Error(`Not a pymatgen structure dict`)
+  const { lattice } = structure
+  if (!lattice) return structure
+  return { ...structure, lattice: { ...lattice, pbc: lattice.pbc ?? [true, true, true] } }
+}
+
+// Structure of an MP document with its material_id (or task_id) as id and the
+// MP_PROPERTY_KEYS fields it has as properties
+export function from_mp_document(obj: unknown): AnyStructure {
+  const doc = unwrap_response(obj)
+  if (!is_mp_document(doc)) throw new Error(`Not a Materials Project document`)
+  const dict = MP_STRUCTURE_PATHS.map((path) => get_path(doc, path)).find(has_pymatgen_sites)
+  const structure = from_pymatgen_dict(dict as Record<string, unknown>)
+
+  const properties: Record<string, unknown> = {}
+  for (const source of [doc.output, doc]) {
+    if (!is_plain_object(source)) continue
+    for (const key of MP_PROPERTY_KEYS) {
+      const value = source[key]
+      if ([`string`, `number`, `boolean`].includes(typeof value)) properties[key] = value
+    }
+  }
+  const id = doc.material_id ?? doc.task_id
+  return { ...structure, id: String(id), properties }
+}
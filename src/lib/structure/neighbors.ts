@@ -0,0 +1,457 @@
+// Periodic neighbor lists: all neighbors within a cutoff, including periodic images
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AtomArrays } from './atom-arrays'
+import type { AnyStructure } from './index'
+
+export type Neighbor = {
+  site_idx: number
+  distance: number
+  displacement: Vec3 // Cartesian vector from the center site to this neighbor image
+  image: Vec3 // integer lattice translation of the neighbor image
+}
+
+// Distances closer than this count as degenerate when ordering neighbors
+const DEGENERATE_DIST = 1e-8
+
+// Order by distance with degenerate distances broken by site index, then image, so
+// neighbor lists (and kNN cuts through a shell) don't depend on floating-point noise
+const compare_neighbors = (nb1: Neighbor, nb2: Neighbor): number => {
+  if (Math.abs(nb1.distance - nb2.distance) > DEGENERATE_DIST) {
+    return nb1.distance - nb2.distance
+  }
+  if (nb1.site_idx !== nb2.site_idx) return nb1.site_idx - nb2.site_idx
+  return (
+    nb1.image[0] - nb2.image[0] || nb1.image[1] - nb2.image[1] || nb1.image[2] - nb2.image[2]
+  )
+}
+
+// Structures with at least this many sites are binned into linked cells
+const CELL_LIST_MIN_SITES = 100
+
+export type NeighborListOptions = {
+  // all pairs (O(N²)), linked cells of width >= cutoff (O(N)), or cells from
+  // CELL_LIST_MIN_SITES sites on (default auto). Both give identical lists.
+  method?: `auto` | `brute_force` | `cell_list`
+}
+
+// Neighbors of every site within cutoff (Å), sorted by distance. Crystals search all
+// periodic images along pbc axes (so small cells can list the same site several times,
+// including images of the center itself); molecules use plain Cartesian distances.
+export function get_neighbor_list(
+  structure: AnyStructure,
+  cutoff: number,
+  options: NeighborListOptions = {},
+): Neighbor[][] {
+  if (!(cutoff > 0)) throw new Error(`cutoff must be > 0, got ${cutoff}`)
+  const { method = `auto` } = options
+  const use_cells =
+    method === `cell_list` ||
+    (method === `auto` && structure.sites.length >= CELL_LIST_MIN_SITES)
+  return (
+    (use_cells && cell_list_neighbors(structure, cutoff)) ||
+    brute_force_neighbors(structure, cutoff)
+  )
+}
+
+// All-pairs search, for the given centers only if center_indices is set
+function brute_force_neighbors(
+  structure: AnyStructure,
+  cutoff: number,
+  center_indices?: number[],
+): Neighbor[][] {
+  const { sites } = structure
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const cutoff_sq = cutoff * cutoff
+  const centers = center_indices ?? sites.map((_, idx) => idx)
+
+  if (!lattice) {
+    return centers.map((center_idx) => {
+      const center = sites[center_idx]
+      const neighbors: Neighbor[] = []
+      sites.forEach((site, site_idx) => {
+        if (site_idx === center_idx) return
+        const displacement = math.subtract(site.xyz, center.xyz)
+        const dist_sq = math.dot(displacement, displacement)
+        if (dist_sq > cutoff_sq) return
+        const distance = Math.sqrt(dist_sq)
+        neighbors.push({ site_idx, distance, displacement, image: [0, 0, 0] })
+      })
+      return neighbors.sort(compare_neighbors)
+    })
+  }
+
+  const { cart_to_frac, frac_to_cart, reciprocal_axis_norms } =
+    math.create_lattice_converters(lattice.matrix)
+  const pbc = lattice.pbc
+  // Fractional differences are wrapped to [-0.5, 0.5] first, so images up to
+  // cutoff·|a*| + 0.5 cells away can fall inside the cutoff sphere
+  const extents = reciprocal_axis_norms.map((norm, axis) =>
+    pbc[axis] ? Math.ceil(cutoff * norm + 0.5) : 0,
+  )
+  const fracs = sites.map((site) => cart_to_frac(site.xyz))
+  const translations: { image: Vec3; cart: Vec3 }[] = []
+  for (let ia = -extents[0]; ia <= extents[0]; ia++) {
+    for (let ib = -extents[1]; ib <= extents[1]; ib++) {
+      for (let ic = -extents[2]; ic <= extents[2]; ic++) {
+        translations.push({ image: [ia, ib, ic], cart: frac_to_cart([ia, ib, ic]) })
+      }
+    }
+  }
+
+  return centers.map((center_idx) => {
+    const center_frac = fracs[center_idx]
+    const neighbors: Neighbor[] = []
+    fracs.forEach((frac, site_idx) => {
+      const wrap_shift = frac.map((val, axis) =>
+        pbc[axis] ? -Math.round(val - center_frac[axis]) : 0,
+      ) as Vec3
+      const base = frac_to_cart(math.add(math.subtract(frac, center_frac), wrap_shift))
+      for (const { image, cart } of translations) {
+        const total_image = math.add(image, wrap_shift)
+        if (site_idx === center_idx && total_image.every((shift) => shift === 0)) continue
+        const displacement = math.add(base, cart)
+        const dist_sq = math.dot(displacement, displacement)
+        if (dist_sq > cutoff_sq) continue
+        const distance = Math.sqrt(dist_sq)
+        neighbors.push({ site_idx, distance, displacement, image: total_image })
+      }
+    })
+    return neighbors.sort(compare_neighbors)
+  })
+}
+
+// One directed pair from for_each_neighbor_pair. The same object is refilled for every
+// pair, so copy fields that need to outlive the visitor call.
+export type NeighborPair = {
+  center_idx: number
+  site_idx: number
+  distance: number
+  displacement: Float64Array // Cartesian vector from the center to the neighbor image
+  image: Int32Array // integer lattice translation of the neighbor image
+}
+
+// Linked-cell search: atoms are binned into cells at least cutoff wide (perpendicular to
+// their faces) and visited one cell (tile) at a time, each center only looking at the 27
+// cells around its own. Bins live in fractional space for crystals and Cartesian space
+// for molecules, wrap along pbc axes and span the occupied range along the others. All
+// buffers are typed arrays of O(N) size. Returns false without visiting anything if a
+// periodic axis fits fewer than 3 cells, where the search over images is needed.
+function visit_cell_pairs(
+  atoms: Pick<AtomArrays, `positions` | `lattice`>,
+  cutoff: number,
+  visit: (pair: NeighborPair) => void,
+): boolean {
+  const { positions, lattice } = atoms
+  const n_atoms = positions.length / 3
+  const periodic = lattice ? lattice.pbc : [false, false, false]
+  const to_frac = lattice ? math.create_cart_to_frac_matrix(lattice.matrix) : null
+  const to_cart = lattice ? math.transpose_3x3_matrix(lattice.matrix) : null
+  const axis_norms = to_frac ? to_frac.map((row) => Math.hypot(...row)) : [1, 1, 1]
+
+  // wrapped coordinates plus the whole cells removed from each atom along pbc axes
+  const wrapped = new Float64Array(3 * n_atoms)
+  const shifts = new Int32Array(3 * n_atoms)
+  const lows = [Infinity, Infinity, Infinity]
+  const highs = [-Infinity, -Infinity, -Infinity]
+  for (let atom = 0; atom < n_atoms; atom++) {
+    const x = positions[3 * atom]
+    const y = positions[3 * atom + 1]
+    const z = positions[3 * atom + 2]
+    for (let axis = 0; axis < 3; axis++) {
+      let val = to_frac
+        ? to_frac[axis][0] * x + to_frac[axis][1] * y + to_frac[axis][2] * z
+        : positions[3 * atom + axis]
+      if (periodic[axis]) {
+        shifts[3 * atom + axis] = Math.floor(val)
+        val -= Math.floor(val)
+      }
+      wrapped[3 * atom + axis] = val
+      lows[axis] = Math.min(lows[axis], val)
+      highs[axis] = Math.max(highs[axis], val)
+    }
+  }
+  const starts = lows.map((low, axis) => (periodic[axis] ? 0 : low))
+  const spans = highs.map((high, axis) => (periodic[axis] ? 1 : high - starts[axis]))
+  // perpendicular width of a span is span / |a*|, so this many bins are >= cutoff wide
+  const n_bins = spans.map((span, axis) =>
+    Math.max(1, Math.floor(span / (cutoff * axis_norms[axis]))),
+  )
+  if (n_bins.some((count, axis) => periodic[axis] && count < 3)) return false
+
+  // linked list of atoms per bin: head[bin] → next[atom] → ... → -1
+  const head = new Int32Array(n_bins[0] * n_bins[1] * n_bins[2]).fill(-1)
+  const next = new Int32Array(n_atoms).fill(-1)
+  for (let atom = n_atoms - 1; atom >= 0; atom--) {
+    let bin = 0
+    for (let axis = 0; axis < 3; axis++) {
+      const rel = spans[axis] > 0 ? (wrapped[3 * atom + axis] - starts[axis]) / spans[axis] : 0
+      bin = bin * n_bins[axis] + Math.min(n_bins[axis] - 1, Math.floor(rel * n_bins[axis]))
+    }
+    next[atom] = head[bin]
+    head[bin] = atom
+  }
+
+  const cutoff_sq = cutoff * cutoff
+  const pair: NeighborPair = {
+    center_idx: 0,
+    site_idx: 0,
+    distance: 0,
+    displacement: new Float64Array(3),
+    image: new Int32Array(3),
+  }
+  const frac = new Float64Array(3)
+  // flat index and periodic image of the up to 27 bins around the current tile
+  const near_bins = new Int32Array(27)
+  const near_images = new Int32Array(81)
+  for (let tile_a = 0; tile_a < n_bins[0]; tile_a++) {
+    for (let tile_b = 0; tile_b < n_bins[1]; tile_b++) {
+      for (let tile_c = 0; tile_c < n_bins[2]; tile_c++) {
+        const tile = (tile_a * n_bins[1] + tile_b) * n_bins[2] + tile_c
+        if (head[tile] < 0) continue
+        let n_near = 0
+        for (let offset = 0; offset < 27; offset++) {
+          const raw = [
+            tile_a + Math.floor(offset / 9) - 1,
+            tile_b + (Math.floor(offset / 3) % 3) - 1,
+            tile_c + (offset % 3) - 1,
+          ]
+          let bin = 0
+          let in_range = true
+          for (let axis = 0; axis < 3; axis++) {
+            const count = n_bins[axis]
+            const bin_image = periodic[axis] ? Math.floor(raw[axis] / count) : 0
+            const wrapped_idx = raw[axis] - bin_image * count
+            if (wrapped_idx < 0 || wrapped_idx >= count) in_range = false
+            near_images[3 * n_near + axis] = bin_image
+            bin = bin * count + wrapped_idx
+          }
+          if (in_range) near_bins[n_near++] = bin
+        }
+
+        for (let center = head[tile]; center >= 0; center = next[center]) {
+          pair.center_idx = center
+          for (let near = 0; near < n_near; near++) {
+            const img_a = near_images[3 * near]
+            const img_b = near_images[3 * near + 1]
+            const img_c = near_images[3 * near + 2]
+            for (let atom = head[near_bins[near]]; atom >= 0; atom = next[atom]) {
+              if (atom === center && img_a === 0 && img_b === 0 && img_c === 0) continue
+              frac[0] = wrapped[3 * atom] + img_a - wrapped[3 * center]
+              frac[1] = wrapped[3 * atom + 1] + img_b - wrapped[3 * center + 1]
+              frac[2] = wrapped[3 * atom + 2] + img_c - wrapped[3 * center + 2]
+              const disp = pair.displacement
+              for (let axis = 0; axis < 3; axis++) {
+                disp[axis] = to_cart
+                  ? to_cart[axis][0] * frac[0] +
+                    to_cart[axis][1] * frac[1] +
+                    to_cart[axis][2] * frac[2]
+                  : frac[axis]
+              }
+              const dist_sq = disp[0] * disp[0] + disp[1] * disp[1] + disp[2] * disp[2]
+              if (dist_sq > cutoff_sq) continue
+              pair.site_idx = atom
+              pair.distance = Math.sqrt(dist_sq)
+              // displacement = frac[atom] − frac[center] + image in unwrapped coordinates
+              pair.image[0] = img_a - shifts[3 * atom] + shifts[3 * center]
+              pair.image[1] = img_b - shifts[3 * atom + 1] + shifts[3 * center + 1]
+              pair.image[2] = img_c - shifts[3 * atom + 2] + shifts[3 * center + 2]
+              visit(pair)
+            }
+          }
+        }
+      }
+    }
+  }
+  return true
+}
+
+// Linked-cell version of get_neighbor_list, null if the cell is too small for it
+function cell_list_neighbors(structure: AnyStructure, cutoff: number): Neighbor[][] | null {
+  const positions = new Float64Array(structure.sites.flatMap(({ xyz }) => xyz))
+  const lattice = `lattice` in structure ? structure.lattice : null
+  const neighbor_list: Neighbor[][] = structure.sites.map(() => [])
+  const found = visit_cell_pairs({ positions, lattice }, cutoff, (pair) => {
+    neighbor_list[pair.center_idx].push({
+      site_idx: pair.site_idx,
+      distance: pair.distance,
+      displacement: [...pair.displacement] as Vec3,
+      image: [...pair.image] as Vec3,
+    })
+  })
+  return found ? neighbor_list.map((neighbors) => neighbors.sort(compare_neighbors)) : null
+}
+
+// Streams every directed neighbor pair within cutoff (Å) to visit without building
+// per-site lists, e.g. to accumulate coordination numbers or pair energies of
+// multi-million-atom cells. Pairs arrive tile by tile in no particular order, with the
+// same pairs and images as get_neighbor_list. Throws for cells narrower than 3 cutoffs
+// along a periodic axis (use get_neighbor_list there).
+export function for_each_neighbor_pair(
+  atoms: AtomArrays,
+  cutoff: number,
+  visit: (pair: NeighborPair) => void,
+): void {
+  if (!(cutoff > 0)) throw new Error(`cutoff must be > 0, got ${cutoff}`)
+  if (!visit_cell_pairs(atoms, cutoff, visit)) {
+    throw new Error(
+      `Streamed neighbor pairs need a cell at least 3 cutoffs (${3 * cutoff} Å) wide ` +
+        `along periodic axes`,
+    )
+  }
+}
+
+export type KnnOptions = {
+  // also keep neighbors within tie_tolerance (Å) of the k-th one, e.g. k = 12 with a
+  // small tolerance gives complete first shells of fcc and hcp metals (default 0, i.e.
+  // exactly k neighbors with degenerate ones picked by site index and image)
+  tie_tolerance?: number
+  max_cutoff?: number // Å, largest search radius (default 20)
+}
+
+// k nearest neighbors (including periodic images) of every site, sorted like
+// get_neighbor_list. The search radius doubles from 4 Å until every site's k-th neighbor
+// plus tie_tolerance lies inside it, or max_cutoff is reached (then lists may be short).
+// Molecules with fewer than k other atoms get all of them.
+export function get_knn_neighbor_list(
+  structure: AnyStructure,
+  k: number,
+  options: KnnOptions = {},
+): Neighbor[][] {
+  return knn_search(structure, k, options, (cutoff) => get_neighbor_list(structure, cutoff))
+}
+
+// k nearest neighbors (including periodic images) of a single site with their distances,
+// displacements and images, like one entry of get_knn_neighbor_list but only searching
+// around that site
+export function get_k_nearest(
+  structure: AnyStructure,
+  site_idx: number,
+  k: number,
+  options: KnnOptions = {},
+): Neighbor[] {
+  const n_sites = structure.sites.length
+  if (!(Number.isInteger(site_idx) && site_idx >= 0 && site_idx < n_sites)) {
+    throw new Error(`Site index ${site_idx} out of range for ${n_sites} sites`)
+  }
+  const [neighbors] = knn_search(structure, k, options, (cutoff) =>
+    brute_force_neighbors(structure, cutoff, [site_idx]),
+  )
+  return neighbors
+}
+
+// Doubling radius search shared by the kNN queries, search(cutoff) lists the neighbors
+// of the queried centers within cutoff
+function knn_search(
+  structure: AnyStructure,
+  k: number,
+  options: KnnOptions,
+  search: (cutoff: number) => Neighbor[][],
+): Neighbor[][] {
+  const { tie_tolerance = 0, max_cutoff = 20 } = options
+  if (!(Number.isInteger(k) && k > 0)) {
+    throw new Error(`k must be a positive integer, got ${k}`)
+  }
+  if (!(tie_tolerance >= 0)) {
+    throw new Error(`tie_tolerance must be >= 0, got ${tie_tolerance}`)
+  }
+  const n_others = `lattice` in structure ? Infinity : structure.sites.length - 1
+  const keep = (neighbors: Neighbor[]) => {
+    const kth = neighbors[k - 1]
+    if (!kth || tie_tolerance === 0) return neighbors.slice(0, k)
+    return neighbors.filter(({ distance }) => distance <= kth.distance + tie_tolerance)
+  }
+  let cutoff = Math.min(4, max_cutoff)
+  while (true) {
+    const neighbor_list = search(cutoff)
+    const complete = neighbor_list.every(
+      (neighbors) =>
+        neighbors.length >= n_others ||
+        (neighbors.length >= k &&
+          neighbors[k - 1].distance + tie_tolerance + DEGENERATE_DIST < cutoff),
+    )
+    if (complete || cutoff >= max_cutoff) return neighbor_list.map(keep)
+    cutoff = Math.min(2 * cutoff, max_cutoff)
+  }
+}
+
+// Verlet neighbor list reused across MD steps: pairs are searched once out to
+// cutoff + skin, and later updates only recompute distances of those candidate pairs.
+// That stays exact until some atom has moved more than skin / 2 since the last build,
+// at which point (or when the cell or number of sites changes) the list is rebuilt.
+// Atoms wrapped back into the cell between steps keep their candidate pairs.
+export class NeighborList {
+  n_builds = 0
+  private candidates: Neighbor[][] = []
+  private ref_xyz: Vec3[] = []
+  private ref_frac: Vec3[] = []
+  private ref_matrix: number[] | null = null
+
+  constructor(
+    readonly cutoff: number,
+    readonly skin = 0.3,
+  ) {
+    if (!(cutoff > 0)) throw new Error(`cutoff must be > 0, got ${cutoff}`)
+    if (!(skin >= 0)) throw new Error(`skin must be >= 0, got ${skin}`)
+  }
+
+  // Neighbors of every site within cutoff for the current positions, sorted and with
+  // images like get_neighbor_list
+  update(structure: AnyStructure): Neighbor[][] {
+    const { sites } = structure
+    const lattice = `lattice` in structure ? structure.lattice : null
+    const matrix = lattice ? lattice.matrix.flat() : null
+    const same_cell =
+      this.n_builds > 0 &&
+      sites.length === this.ref_xyz.length &&
+      (matrix === null
+        ? this.ref_matrix === null
+        : matrix.every((val, idx) => val === this.ref_matrix?.[idx]))
+    const converters = lattice ? math.create_lattice_converters(lattice.matrix) : null
+    const fracs = converters ? sites.map((site) => converters.cart_to_frac(site.xyz)) : []
+
+    // whole-cell wraps of each site since the last build, so its candidate images can
+    // be shifted to follow it
+    const wraps: Vec3[] = sites.map((_, site_idx) =>
+      lattice && same_cell
+        ? (fracs[site_idx].map((val, axis) =>
+            lattice.pbc[axis] ? Math.round(val - this.ref_frac[site_idx][axis]) : 0,
+          ) as Vec3)
+        : [0, 0, 0],
+    )
+    const max_move_sq = !same_cell
+      ? Infinity
+      : sites.reduce((max_sq, site, site_idx) => {
+          const wrap_cart = converters ? converters.frac_to_cart(wraps[site_idx]) : null
+          let move = math.subtract(site.xyz, this.ref_xyz[site_idx])
+          if (wrap_cart) move = math.subtract(move, wrap_cart)
+          return Math.max(max_sq, math.dot(move, move))
+        }, 0)
+
+    if (max_move_sq > (this.skin / 2) ** 2) {
+      this.candidates = get_neighbor_list(structure, this.cutoff + this.skin)
+      this.ref_xyz = sites.map((site) => site.xyz)
+      this.ref_frac = fracs
+      this.ref_matrix = matrix
+      this.n_builds++
+      return this.candidates.map((neighbors) =>
+        neighbors.filter(({ distance }) => distance <= this.cutoff),
+      )
+    }
+
+    const cutoff_sq = this.cutoff * this.cutoff
+    return this.candidates.map((candidates, center_idx) => {
+      const neighbors: Neighbor[] = []
+      for (const { site_idx, image } of candidates) {
+        const shifted = math.add(image, math.subtract(wraps[center_idx], wraps[site_idx]))
+        let displacement = math.subtract(sites[site_idx].xyz, sites[center_idx].xyz)
+        if (converters) displacement = math.add(displacement, converters.frac_to_cart(shifted))
+        const dist_sq = math.dot(displacement, displacement)
+        if (dist_sq > cutoff_sq) continue
+        const distance = Math.sqrt(dist_sq)
+        neighbors.push({ site_idx, distance, displacement, image: shifted })
+      }
+      return neighbors.sort(compare_neighbors)
+    })
+  }
+}
@@ -1,15 +1,33 @@
 import type { OptimadeStructure } from '$lib/api/optimade'
+import { ATOMIC_WEIGHTS } from '$lib/composition/parse'
 import { XYZ_EXTXYZ_REGEX } from '$lib/constants'
 import type { ElementSymbol } from '$lib/element'
 import { FALLBACK_ELEMENTS, is_elem_symbol } from '$lib/element/helpers'
 import { strip_compression_extensions } from '$lib/io/decompress'
 import type { Vec3 } from '$lib/math'
 import * as math from '$lib/math'
-import type { AnyStructure, Crystal, Site, StructureProperties } from '$lib/structure'
+import type {
+  AnyStructure,
+  Crystal,
+  Site,
+  Species,
+  StructureProperties,
+} from '$lib/structure'
 import type { Pbc } from '$lib/structure/pbc'
 import { wrap_to_unit_cell } from '$lib/structure/pbc'
+import { from_aiida_structure, is_aiida_structure } from '$lib/structure/aiida'
+import { from_mp_document, is_mp_document } from '$lib/structure/mp-docs'
 import { make_site } from '$lib/structure/site'
+import {
+  hm_symbol_to_spacegroup_num,
+  normalize_spacegroup,
+  SPACEGROUP_NUM_TO_SYMBOL,
+} from '$lib/symmetry/spacegroups'
+import { ELEM_SYMBOLS } from '$lib/labels'
 import { is_xyz_atom_line, iter_xyz_frames } from '$lib/trajectory/helpers'
+import type { AtomTypeMapping } from '$lib/trajectory/types'
+import type { UnitSystem } from '$lib/units'
+import { unit_factor } from '$lib/units'
 import {
   normalize_scientific_notation,
   parse_leading_num,
@@ -27,11 +45,14 @@ export { is_structure_file } from '$lib/structure/format-detect'
 // parse_structure_file and parse_any_structure reset the collector on entry and THROW a
 // descriptive Error aggregating the recorded reasons when nothing parses, so failure
 // causes can reach the UI (callers surface error.message). Warnings (element-symbol
-// fallbacks, skipped atoms, ...) never fail a parse and only go to the console.
+// fallbacks, skipped atoms, lenient CIF recoveries, ...) never fail a parse; they go to the
+// console and are kept for get_parse_warnings.
 let parse_errors: string[] = []
+let parse_warnings: string[] = []
 
 const reset_parse_diagnostics = (): void => {
   parse_errors = []
+  parse_warnings = []
 }
 // Record a failure reason; with `error` present, logs in `console.error('msg:', error)` form
 const diag_error = (message: string, error?: unknown): void => {
@@ -40,7 +61,13 @@ const diag_error = (message: string, error?: unknown): void => {
   if (error === undefined) console.error(message)
   else console.error(`${message}:`, error)
 }
-const diag_warn = (message: string): void => console.warn(message)
+const diag_warn = (message: string): void => {
+  parse_warnings.push(message)
+  console.warn(message)
+}
+
+// Warnings recorded by the last parse_structure_file or parse_any_structure call
+export const get_parse_warnings = (): string[] => [...new Set(parse_warnings)]
 // Aggregate recorded failure reasons into the Error thrown by top-level entry points
 const aggregate_parse_error = (filename?: string): Error => {
   const reasons = [...new Set(parse_errors)]
@@ -212,6 +239,47 @@ function resolve_optimade_element(
   return { symbol: validate_element_symbol(species_name, index), sym_idx: -1 }
 }
 
+type OptimadeSpecies = NonNullable<OptimadeStructure[`attributes`][`species`]>[number]
+export type OptimadeAssembly = { sites_in_groups: number[][]; group_probabilities: number[] }
+
+// Partial occupancies of a disordered OPTIMADE species, dominant element first. Needs
+// concentrations in (0, 1] summing to at most 1 (the remainder being vacancies), else
+// (and for fully occupied single-element species) returns null.
+function optimade_partial_species(spec: OptimadeSpecies | undefined): Species[] | null {
+  const { chemical_symbols, concentration } = spec ?? {}
+  if (!chemical_symbols || concentration?.length !== chemical_symbols.length) return null
+  const total = concentration.reduce((sum, conc) => sum + conc, 0)
+  if (!concentration.every((conc) => conc > 0 && conc <= 1) || total > 1 + 1e-6) return null
+  const species = chemical_symbols
+    .map((symbol, idx) => ({ element: symbol, occu: concentration[idx], oxidation_state: 0 }))
+    .filter((entry): entry is Species => is_elem_symbol(entry.element))
+    .sort((spec_1, spec_2) => spec_2.occu - spec_1.occu)
+  return species.length === 0 || (species.length === 1 && species[0].occu === 1)
+    ? null
+    : species
+}
+
+// Scale site occupancies by the probability of the assembly group each site belongs to,
+// so alternative groups (e.g. two orientations of a molecule) become partial occupancies
+function apply_optimade_assemblies(
+  sites: (Site | undefined)[],
+  assemblies: OptimadeAssembly[],
+): void {
+  for (const { sites_in_groups, group_probabilities } of assemblies) {
+    if (sites_in_groups?.length !== group_probabilities?.length) {
+      throw new Error(`OPTIMADE assembly needs one probability per group`)
+    }
+    sites_in_groups.forEach((group, group_idx) => {
+      for (const site_idx of group) {
+        const site = sites[site_idx]
+        if (!site) continue
+        const prob = group_probabilities[group_idx]
+        site.species = site.species.map((spec) => ({ ...spec, occu: spec.occu * prob }))
+      }
+    })
+  }
+}
+
 const approximate_cart_to_frac = (xyz: Vec3, axis_lengths: Vec3): Vec3 => [
   Math.abs(axis_lengths[0]) > math.EPS ? xyz[0] / axis_lengths[0] : 0,
   Math.abs(axis_lengths[1]) > math.EPS ? xyz[1] / axis_lengths[1] : 0,
@@ -629,25 +697,55 @@ const parse_cif_uncertain_number = (token: string): number | null => {
   return isNaN(value) ? null : value
 }
 
-const extract_cif_cell_parameters = (text: string, type: string, strict = true): number[] =>
-  text
-    .split(`\n`)
-    .filter((line) => line.startsWith(`_${type}`))
-    .map((line) => {
-      // Strip trailing comment (# after whitespace) and take the value right after the tag
-      const sans_comment = line.replace(/\s#.*$/, ``)
-      const tokens = sans_comment.split(/\s+/).filter(Boolean)
-      if (tokens.length < 2) {
-        if (strict) throw new Error(`Invalid CIF cell parameter line format: ${line}`)
-        return null
-      }
-      const value = parse_cif_uncertain_number(tokens[1])
-      if (value === null && strict) {
-        throw new Error(`Invalid CIF cell parameter in line: ${line}`)
-      }
-      return value
-    })
-    .filter((val): val is number => val !== null)
+const CIF_CELL_TAGS = [
+  `_cell_length_a`,
+  `_cell_length_b`,
+  `_cell_length_c`,
+  `_cell_angle_alpha`,
+  `_cell_angle_beta`,
+  `_cell_angle_gamma`,
+] as const
+
+// Read the six cell parameters by (case-insensitive) tag, null where missing. Malformed
+// values throw unless tolerant, in which case they're reported via `issue` and treated as
+// missing. Tolerant mode also accepts a value on the line after its tag.
+const read_cif_cell_parameters = (
+  lines: string[],
+  tolerant: boolean,
+  issue: (message: string) => void,
+): (number | null)[] =>
+  CIF_CELL_TAGS.map((tag) => {
+    const line_idx = lines.findIndex(
+      (line) => line.trim().split(/\s+/)[0].toLowerCase() === tag,
+    )
+    if (line_idx < 0) return null
+    const line = lines[line_idx].trim()
+    // Strip trailing comment (# after whitespace) and take the value right after the tag
+    let value_token = line.replace(/\s#.*$/, ``).split(/\s+/)[1]
+    if (value_token === undefined) {
+      if (!tolerant) throw new Error(`Invalid CIF cell parameter line format: ${line}`)
+      value_token = lines[line_idx + 1]?.trim().split(/\s+/)[0] ?? ``
+    }
+    const value = parse_cif_uncertain_number(value_token)
+    if (value === null) {
+      const message = `Invalid CIF cell parameter in line: ${line}`
+      if (!tolerant) throw new Error(message)
+      issue(message)
+    }
+    return value
+  })
+
+// Value of the first item among `tags` (case-insensitive, quotes stripped), or null
+const read_cif_item = (lines: string[], tags: string[]): string | null => {
+  for (const line of lines) {
+    const match = /^(?<tag>_\S+)\s+(?<value>.+)$/.exec(line.trim())
+    if (match?.groups && tags.includes(match.groups.tag.toLowerCase())) {
+      const value = match.groups.value.replaceAll(/['"]/g, ``).trim()
+      if (value && value !== `?` && value !== `.`) return value
+    }
+  }
+  return null
+}
 
 // build header index mapping for atom site data (supports fract and Cartn coordinates)
 const build_cif_atom_site_header_indices = (headers: string[]): Record<string, number> => {
@@ -706,19 +804,45 @@ type CifAtom = {
   occupancy: number
 }
 
-// Walk CIF loop_ blocks: yields each loop's header tags plus the index of its first data line
+// A loop's data ends at the next tag, loop_ or data block (keywords are case-insensitive)
+const is_cif_loop_end = (line: string): boolean =>
+  line.startsWith(`_`) || /^(?:loop_|data_)/i.test(line)
+const is_cif_tag_only = (line: string): boolean => /^_\S+$/.test(line)
+// Categories whose loops are recovered when the `loop_` keyword is missing
+const IMPLICIT_LOOP_TAGS = /^_(?:atom_site_|symmetry_equiv_pos_|space_group_symop_)/i
+
+// Walk CIF loop_ blocks: yields each loop's header tags plus the index of its first data
+// line. With implicit_loops, a run of ≥2 value-less atom-site/symmetry tags directly
+// followed by data (i.e. a forgotten `loop_`) is yielded too, flagged as implicit.
 function* iter_cif_loops(
   lines: string[],
-): Generator<{ headers: string[]; data_start: number }> {
+  implicit_loops = false,
+): Generator<{ headers: string[]; data_start: number; implicit: boolean }> {
   for (let idx = 0; idx < lines.length; idx++) {
-    if (lines[idx].trim() !== `loop_`) continue
-    const headers: string[] = []
-    let jj = idx + 1
-    while (jj < lines.length && lines[jj].trim().startsWith(`_`)) {
-      headers.push(lines[jj].trim())
-      jj++
-    }
-    yield { headers, data_start: jj }
+    const line = lines[idx].trim()
+    if (line.toLowerCase() === `loop_`) {
+      const headers: string[] = []
+      let jj = idx + 1
+      while (jj < lines.length && lines[jj].trim().startsWith(`_`)) {
+        headers.push(lines[jj].trim())
+        jj++
+      }
+      yield { headers, data_start: jj, implicit: false }
+      continue
+    }
+    if (!implicit_loops || !is_cif_tag_only(line) || !IMPLICIT_LOOP_TAGS.test(line)) continue
+    const prev = lines[idx - 1]?.trim() ?? ``
+    if (is_cif_tag_only(prev) || prev.toLowerCase() === `loop_`) continue
+    let jj = idx
+    while (jj < lines.length && is_cif_tag_only(lines[jj].trim())) jj++
+    const next = lines[jj]?.trim()
+    if (jj - idx < 2 || !next || is_cif_loop_end(next)) continue
+    yield {
+      headers: lines.slice(idx, jj).map((header) => header.trim()),
+      data_start: jj,
+      implicit: true,
+    }
+    idx = jj - 1
   }
 }
 
@@ -773,40 +897,87 @@ const parse_cif_atom_data = (
   }
 }
 
-// @internal parser exported for tests; public entry points: parse_structure_file/parse_any_structure. Parse CIF (Crystallographic Information File).
-export function parse_cif(
+// CIF parse modes:
+// - default: current behavior; malformed cell parameters abort the parse, invalid atom
+//   rows are skipped with a warning
+// - lenient: recover from common ICSD/COD quirks (`?`/`.` or malformed values, missing
+//   cell angles, forgotten `loop_` keywords, non-standard space-group symbols), recording
+//   each recovery as a warning
+// - strict: validation; everything lenient mode would recover from, plus a missing data_
+//   header, duplicate labels, unknown elements and occupancies outside (0, 1], is an
+//   error and no structure is returned
+export type CifParseMode = `default` | `lenient` | `strict`
+
+export interface CifParseOptions {
+  mode?: CifParseMode
+  wrap_fractional_coords?: boolean
+}
+
+export interface CifParseReport {
+  structure: ParsedStructure | null
+  warnings: string[]
+  errors: string[]
+  // From _space_group_IT_number / _symmetry_Int_Tables_number, else the H-M symbol
+  spacegroup_number: number | null
+}
+
+// Parse a CIF and report every warning and error encountered (also mirrored to the
+// console and the parse diagnostics like other format parsers)
+export function parse_cif_with_report(
   content: string,
-  wrap_fractional_coords: boolean = true,
-  strict: boolean = true,
-): ParsedStructure | null {
+  options: CifParseOptions = {},
+): CifParseReport {
+  const { mode = `default`, wrap_fractional_coords = true } = options
+  const tolerant = mode !== `default`
+  const report: CifParseReport = {
+    structure: null,
+    warnings: [],
+    errors: [],
+    spacegroup_number: null,
+  }
+  const fail = (message: string, error?: unknown): CifParseReport => {
+    const detail = error === undefined ? `` : `: ${to_error(error).message}`
+    report.errors.push(`${message}${detail}`)
+    diag_error(message, error)
+    return report
+  }
+  // Recoverable problem: a warning, except in strict mode where it invalidates the file
+  const issue = (message: string): void => {
+    if (mode === `strict`) {
+      report.errors.push(message)
+      diag_error(message)
+    } else {
+      report.warnings.push(message)
+      diag_warn(message)
+    }
+  }
+
   try {
     const text = content.trim()
-    if (!text) {
-      diag_error(`CIF file is empty`)
-      return null
+    if (!text) return fail(`CIF file is empty`)
+
+    const lines = text.split(`\n`)
+    if (mode === `strict` && !lines.some((line) => /^data_\S/i.test(line.trim()))) {
+      issue(`CIF file has no data_ block header`)
     }
 
     // Find atom site loop that actually contains coordinates (fract or Cartn)
-    const lines = text.split(`\n`)
     let atom_headers: string[] = []
     const atom_data_lines: string[] = []
     const symmetry_ops: string[] = []
+    const is_symop_tag = (tag: string): boolean =>
+      /_symmetry_equiv_pos_as_xyz|_space_group_symop_operation_xyz/i.test(tag)
 
-    for (const { headers, data_start } of iter_cif_loops(lines)) {
+    for (const { headers, data_start, implicit } of iter_cif_loops(lines, tolerant)) {
       let jj = data_start
 
       // Check if this is a symmetry operations loop
-      if (
-        headers.some(
-          (header) =>
-            header.includes(`_symmetry_equiv_pos_as_xyz`) ||
-            header.includes(`_space_group_symop_operation_xyz`),
-        )
-      ) {
+      if (headers.some(is_symop_tag)) {
+        if (implicit) issue(`Symmetry operation loop is missing its loop_ keyword`)
         // Collect symmetry operations
         while (jj < lines.length) {
           const line = lines[jj].trim()
-          if (line === `loop_` || line.startsWith(`data_`)) break
+          if (is_cif_loop_end(line)) break
           if (line && !line.startsWith(`#`) && !line.startsWith(`;`)) {
             symmetry_ops.push(line)
           }
@@ -816,17 +987,18 @@ export function parse_cif(
       }
 
       // Not an atom-site loop → continue search
-      if (!headers.some((header) => header.includes(`_atom_site_`))) continue
+      if (!headers.some((header) => header.toLowerCase().includes(`_atom_site_`))) continue
 
       // Check if this loop contains coordinate headers
       const indices_preview = build_cif_atom_site_header_indices(headers)
       if (cif_coords_type(indices_preview) === null) continue
 
       // This is the desired atom-site loop with coordinates: collect data lines
+      if (implicit) issue(`Atom site loop is missing its loop_ keyword`)
       atom_headers = headers
       while (jj < lines.length) {
         const line = lines[jj].trim()
-        if (line === `loop_` || line.startsWith(`data_`)) break
+        if (is_cif_loop_end(line)) break
         if (line && !line.startsWith(`#`)) {
           if (line.startsWith(`;`)) {
             let multi_line_data = ``
@@ -845,9 +1017,27 @@ export function parse_cif(
       if (atom_data_lines.length > 0) break
     }
 
+    // A single atom site (or symmetry operation) may be given as plain unlooped items
+    const unlooped_items = (matches: (tag: string) => boolean) =>
+      lines
+        .map((line) => /^(?<tag>_\S+)\s+(?<value>.+)$/.exec(line.trim())?.groups)
+        .filter((groups) => groups !== undefined && matches(groups.tag))
+        .map((groups) => groups as { tag: string; value: string })
+    if (atom_data_lines.length === 0) {
+      const items = unlooped_items(
+        (tag) => /^_atom_site_/i.test(tag) && !/^_atom_site_aniso_/i.test(tag),
+      )
+      if (cif_coords_type(build_cif_atom_site_header_indices(items.map(({ tag }) => tag)))) {
+        atom_headers = items.map(({ tag }) => tag)
+        atom_data_lines.push(items.map(({ value }) => value.trim()).join(` `))
+      }
+    }
+    if (symmetry_ops.length === 0) {
+      symmetry_ops.push(...unlooped_items(is_symop_tag).map(({ value }) => value.trim()))
+    }
+
     if (atom_headers.length === 0 || atom_data_lines.length === 0) {
-      diag_error(`No valid atom site loop found in CIF file`)
-      return null
+      return fail(`No valid atom site loop found in CIF file`)
     }
 
     // Parse atom data with error handling
@@ -857,55 +1047,93 @@ export function parse_cif(
     const coords_type = cif_coords_type(header_indices)
 
     if (!coords_type) {
-      diag_error(`CIF atom site loop missing coordinates (fract or Cartn)`)
-      return null
+      return fail(`CIF atom site loop missing coordinates (fract or Cartn)`)
     }
 
     // Collect required coordinate indices
     const required_indices = cif_coord_indices(header_indices, coords_type)
+    const max_required_idx = Math.max(...required_indices)
 
     const atoms = atom_data_lines
       .map(split_cif_tokens)
-      .filter((tokens) => {
+      .filter((tokens, row_idx) => {
+        const row = atom_data_lines[row_idx]
         const { disorder } = header_indices
-        const max_required_idx = Math.max(...required_indices)
-        return (
-          !(disorder !== undefined && tokens[disorder] === `2`) &&
-          tokens.length > max_required_idx
-        )
+        if (disorder !== undefined && tokens[disorder] === `2`) return false
+        if (tokens.length > max_required_idx) return true
+        if (tolerant) issue(`Skipping atom row with too few values: ${row}`)
+        return false
       })
       .map((tokens) => {
         try {
           return parse_cif_atom_data(tokens, header_indices, coords_type)
         } catch (error) {
-          diag_warn(`Skipping invalid atom data: ${error}`)
+          issue(`Skipping invalid atom data: ${error}`)
           return null
         }
       })
       .filter((atom): atom is NonNullable<typeof atom> => atom !== null)
 
-    if (atoms.length === 0) {
-      diag_error(`No valid atoms found in CIF file`)
-      return null
+    if (atoms.length === 0) return fail(`No valid atoms found in CIF file`)
+
+    if (tolerant) {
+      const seen_labels = new Set<string>()
+      for (const atom of atoms) {
+        if (seen_labels.has(atom.id)) issue(`Duplicate atom site label: ${atom.id}`)
+        seen_labels.add(atom.id)
+        if (!is_elem_symbol(atom.element)) {
+          issue(`Unknown element symbol '${atom.element}' for atom ${atom.id}`)
+        }
+        if (!(atom.occupancy > 0 && atom.occupancy <= 1)) {
+          issue(`Occupancy ${atom.occupancy} of atom ${atom.id} is outside (0, 1]`)
+        }
+      }
     }
 
     // Extract cell parameters and build lattice
-    const lengths = extract_cif_cell_parameters(text, `cell_length`, strict)
-    const angles = extract_cif_cell_parameters(text, `cell_angle`, strict)
-
-    if (lengths.length < 3 || angles.length < 3) {
-      diag_error(`Insufficient cell parameters in CIF file`)
-      return null
+    const cell = read_cif_cell_parameters(lines, tolerant, issue)
+    if (tolerant) {
+      cell.forEach((value, idx) => {
+        if (idx < 3 || value !== null) return
+        issue(`Missing ${CIF_CELL_TAGS[idx]}, assuming 90°`)
+        cell[idx] = 90
+      })
+    }
+    if (cell.some((value) => value === null)) {
+      return fail(`Insufficient cell parameters in CIF file`)
     }
 
     // Build lattice and create sites
-    const [a, b, c] = lengths
-    const [alpha, beta, gamma] = angles
+    const [a, b, c, alpha, beta, gamma] = cell as number[]
     const lattice_matrix = math.cell_to_lattice_matrix(a, b, c, alpha, beta, gamma)
     const lattice_params = math.calc_lattice_params(lattice_matrix)
     const frac_to_cart = math.create_frac_to_cart(lattice_matrix)
     const cart_to_frac = cart_to_frac_with_fallback(lattice_matrix, [a, b, c]).convert
 
+    // Space group from its number, else the (possibly non-standard) H-M symbol
+    const hm_symbol = read_cif_item(lines, [
+      `_symmetry_space_group_name_h-m`,
+      `_space_group_name_h-m_alt`,
+    ])
+    const it_number = read_cif_item(lines, [
+      `_space_group_it_number`,
+      `_symmetry_int_tables_number`,
+    ])
+    const number_from_symbol = hm_symbol ? hm_symbol_to_spacegroup_num(hm_symbol) : null
+    const number_from_tag = it_number ? normalize_spacegroup(it_number) : null
+    report.spacegroup_number = number_from_tag ?? number_from_symbol
+    if (tolerant) {
+      if (hm_symbol && number_from_symbol === null) {
+        issue(`Unrecognized space group symbol '${hm_symbol}'`)
+      } else if (hm_symbol && number_from_tag && number_from_symbol !== number_from_tag) {
+        issue(`Space group symbol '${hm_symbol}' does not match number ${number_from_tag}`)
+      }
+      if (symmetry_ops.length === 0 && (report.spacegroup_number ?? 1) > 1) {
+        const sg_num = report.spacegroup_number
+        issue(`No symmetry operations for space group ${sg_num}, using atom sites as listed`)
+      }
+    }
+
     // Create sites with coordinate conversion and symmetry operations
     const wrap_vec3 = (vec: Vec3): Vec3 =>
       wrap_fractional_coords ? wrap_to_unit_cell(vec) : vec
@@ -925,7 +1153,7 @@ export function parse_cif(
       if (sym_idx === -1 || num_idx === -1) continue
       for (let lj = data_start; lj < lines.length; lj++) {
         const line = lines[lj].trim()
-        if (!line || line === `loop_` || line.startsWith(`data_`)) break
+        if (!line || is_cif_loop_end(line)) break
         if (line.startsWith(`#`)) continue
         const toks = split_cif_tokens(line)
         if (toks.length > Math.max(sym_idx, num_idx)) {
@@ -959,7 +1187,13 @@ export function parse_cif(
     // Candidate lattice-centering translations from the space-group symbol (R
     // only valid in the hexagonal setting, α≈β≈90°, γ≈120°). Whether to actually
     // apply them is decided below by reconciling against _atom_type_number_in_cell.
-    const centering_letter = extract_cif_centering(text)
+    // Tolerant modes fall back to the centering of the space group number when the
+    // H-M symbol is missing or unrecognized
+    const spacegroup_symbol = report.spacegroup_number
+      ? SPACEGROUP_NUM_TO_SYMBOL[report.spacegroup_number]
+      : undefined
+    const centering_letter =
+      extract_cif_centering(text) ?? (tolerant ? (spacegroup_symbol?.[0] ?? null) : null)
     const is_hexagonal_setting =
       Math.abs(alpha - 90) <= 1 && Math.abs(beta - 90) <= 1 && Math.abs(gamma - 120) <= 1
     const centering =
@@ -1031,9 +1265,285 @@ export function parse_cif(
       if (reconciles) sites = centered_sites
     }
 
+    // strict mode is all-or-nothing: any recorded issue invalidates the file
+    if (report.errors.length === 0) {
+      report.structure = { sites, lattice: { matrix: lattice_matrix, ...lattice_params } }
+    }
+    return report
+  } catch (error) {
+    return fail(`Error parsing CIF file`, error)
+  }
+}
+
+// @internal parser exported for tests; public entry points: parse_structure_file/parse_any_structure. Parse CIF (Crystallographic Information File).
+// `mode` also accepts the legacy strict flag: true = default, false = lenient.
+export function parse_cif(
+  content: string,
+  wrap_fractional_coords: boolean = true,
+  mode: CifParseMode | boolean = `default`,
+): ParsedStructure | null {
+  const parse_mode = mode === true ? `default` : mode === false ? `lenient` : mode
+  return parse_cif_with_report(content, { mode: parse_mode, wrap_fractional_coords })
+    .structure
+}
+
+// What to do with a data block that yields no structure: skip it (default), throw, or
+// yield it with a null structure so callers can log its errors
+export type CifBlockErrorPolicy = `skip` | `throw` | `yield`
+
+export interface CifReadOptions extends CifParseOptions {
+  on_error?: CifBlockErrorPolicy
+}
+
+export interface CifBlock extends CifParseReport {
+  name: string // block code after data_
+  index: number // position of the block in the file, failed blocks included
+}
+
+// Incremental splitter of CIF text into data blocks. Lines starting with data_ inside
+// semicolon text fields don't start a block, lines before the first data_ (comments,
+// the #\#CIF_2.0 magic) are dropped.
+class CifBlockSplitter {
+  private partial = ``
+  private block: string[] | null = null
+  private in_text_field = false
+
+  // complete blocks ended by lines of this chunk
+  push(chunk: string): string[] {
+    const lines = (this.partial + chunk).split(`\n`)
+    this.partial = lines.pop() ?? ``
+    return lines.flatMap((line) => this.add_line(line))
+  }
+
+  // the last block once the input is exhausted
+  flush(): string[] {
+    const done = this.partial ? this.add_line(this.partial) : []
+    this.partial = ``
+    if (this.block) done.push(this.block.join(`\n`))
+    this.block = null
+    return done
+  }
+
+  private add_line(line: string): string[] {
+    if (line.startsWith(`;`)) this.in_text_field = !this.in_text_field
+    const starts_block = !this.in_text_field && /^data_\S/i.test(line.trim())
+    if (!starts_block) {
+      this.block?.push(line)
+      return []
+    }
+    const done = this.block ? [this.block.join(`\n`)] : []
+    this.block = [line]
+    return done
+  }
+}
+
+function* parse_cif_blocks(
+  texts: string[],
+  counter: { index: number },
+  options: CifReadOptions,
+): Generator<CifBlock> {
+  const { on_error = `skip`, ...parse_options } = options
+  for (const text of texts) {
+    const name = /^\s*data_(\S+)/i.exec(text)?.[1] ?? ``
+    const index = counter.index++
+    const report = parse_cif_with_report(text, parse_options)
+    if (!report.structure) {
+      if (on_error === `skip`) continue
+      if (on_error === `throw`) {
+        throw new Error(`CIF block data_${name} (#${index}): ${report.errors.join(`; `)}`)
+      }
+    }
+    yield { ...report, name, index }
+  }
+}
+
+// Lazily parse a multi-block CIF (e.g. ICSD/COD dumps) one data_ block at a time. Takes
+// the whole text or any iterable of text chunks, so only the current block is held in
+// memory. Every block is parsed independently with parse_cif_with_report.
+export function* iter_cif_blocks(
+  chunks: string | Iterable<string>,
+  options: CifReadOptions = {},
+): Generator<CifBlock> {
+  const splitter = new CifBlockSplitter()
+  const counter = { index: 0 }
+  for (const chunk of typeof chunks === `string` ? [chunks] : chunks) {
+    yield* parse_cif_blocks(splitter.push(chunk), counter, options)
+  }
+  yield* parse_cif_blocks(splitter.flush(), counter, options)
+}
+
+// iter_cif_blocks for async text streams, e.g.
+// file.stream().pipeThrough(new TextDecoderStream()) of a browser File
+export async function* iter_cif_blocks_async(
+  chunks: AsyncIterable<string>,
+  options: CifReadOptions = {},
+): AsyncGenerator<CifBlock> {
+  const splitter = new CifBlockSplitter()
+  const counter = { index: 0 }
+  for await (const chunk of chunks) {
+    yield* parse_cif_blocks(splitter.push(chunk), counter, options)
+  }
+  yield* parse_cif_blocks(splitter.flush(), counter, options)
+}
+
+export interface LammpsDataOptions {
+  units?: UnitSystem // LAMMPS `units` the file was written in (default: metal)
+  atom_type_mapping?: AtomTypeMapping // explicit type → element map, overrides Masses
+}
+
+// Column indices per supported Atoms section style (column 0 is always the atom ID)
+const LAMMPS_ATOM_STYLES: Record<
+  string,
+  { type: number; pos: number; charge?: number; molecule?: number }
+> = {
+  atomic: { type: 1, pos: 2 },
+  charge: { type: 1, charge: 2, pos: 3 },
+  molecular: { molecule: 1, type: 2, pos: 3 },
+  full: { molecule: 1, type: 2, charge: 3, pos: 4 },
+}
+// Atom style by column count when the Atoms header has no style comment (±3 image flags)
+const LAMMPS_STYLE_BY_N_COLS: Record<number, string> = {
+  5: `atomic`,
+  6: `charge`,
+  7: `full`,
+  8: `atomic`,
+  9: `charge`,
+  10: `full`,
+}
+
+// Element whose standard atomic weight is closest to mass (amu), if within 0.5 amu
+function element_from_mass(mass: number): ElementSymbol | undefined {
+  let [best, best_diff]: [ElementSymbol | undefined, number] = [undefined, 0.5]
+  for (const [element, weight] of ATOMIC_WEIGHTS) {
+    const diff = Math.abs(weight - mass)
+    if (diff < best_diff) [best, best_diff] = [element, diff]
+  }
+  return best
+}
+
+// Parse a LAMMPS data file (read_data format) with atomic, charge, molecular or full atom
+// style. Atom types map to elements via atom_type_mapping, else the element whose atomic
+// weight is closest to the type's Masses entry, else 1→H, 2→He, etc. Box and positions
+// are converted to Å and velocities to Å/fs; charges and molecule IDs become site
+// properties.
+export function parse_lammps_data(
+  content: string,
+  options: LammpsDataOptions = {},
+): ParsedStructure | null {
+  const { units = `metal`, atom_type_mapping } = options
+  try {
+    // First line is a title; header keywords follow until the first section name
+    const sections = new Map<string, { style: string; rows: string[][] }>()
+    const [lo, hi]: [Vec3, Vec3] = [
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    let tilts: Vec3 = [0, 0, 0]
+    let n_atoms = 0
+    let rows: string[][] | null = null
+    for (const line of content.split(/\r?\n/).slice(1)) {
+      const hash_idx = line.indexOf(`#`)
+      const text = (hash_idx < 0 ? line : line.slice(0, hash_idx)).trim()
+      if (!text) continue
+      if (/^[A-Za-z]/.test(text)) {
+        rows = []
+        const style = hash_idx < 0 ? `` : line.slice(hash_idx + 1).trim().split(/\s+/)[0]
+        sections.set(text, { style, rows })
+        continue
+      }
+      const tokens = text.split(/\s+/)
+      if (rows) {
+        rows.push(tokens)
+        continue
+      }
+      const keyword = tokens.filter((token) => /^[a-z]/i.test(token)).join(` `)
+      const values = tokens.map(Number)
+      const axis = [`xlo xhi`, `ylo yhi`, `zlo zhi`].indexOf(keyword)
+      if (keyword === `atoms`) n_atoms = values[0]
+      else if (axis >= 0) [lo[axis], hi[axis]] = [values[0], values[1]]
+      else if (keyword === `xy xz yz`) tilts = [values[0], values[1], values[2]]
+    }
+
+    const atoms = sections.get(`Atoms`)
+    if (!atoms?.rows.length) {
+      diag_error(`LAMMPS data file has no Atoms section`)
+      return null
+    }
+    const style = atoms.style || LAMMPS_STYLE_BY_N_COLS[atoms.rows[0].length]
+    const columns = LAMMPS_ATOM_STYLES[style]
+    if (!columns) {
+      diag_error(`Unsupported LAMMPS atom style: ${style || `unknown`}`)
+      return null
+    }
+    if (n_atoms && n_atoms !== atoms.rows.length) {
+      diag_warn(
+        `LAMMPS data header declares ${n_atoms} atoms, Atoms section has ${atoms.rows.length}`,
+      )
+    }
+
+    const length_scale = unit_factor(`length`, units, `ase`)
+    const [xy, xz, yz] = tilts
+    const lattice_matrix = (
+      [
+        [hi[0] - lo[0], 0, 0],
+        [xy, hi[1] - lo[1], 0],
+        [xz, yz, hi[2] - lo[2]],
+      ] as math.Matrix3x3
+    ).map((row) => row.map((val) => val * length_scale)) as math.Matrix3x3
+    if (Math.abs(math.det_3x3(lattice_matrix)) < 1e-10) {
+      diag_error(`LAMMPS data file has a degenerate or missing simulation box`)
+      return null
+    }
+    const cart_to_frac = math.create_cart_to_frac(lattice_matrix)
+
+    const mass_scale = unit_factor(`mass`, units, `ase`)
+    const masses = new Map(
+      (sections.get(`Masses`)?.rows ?? []).map(([type, mass]) => [
+        Number(type),
+        Number(mass) * mass_scale,
+      ]),
+    )
+    const velocity_scale = unit_factor(`velocity`, units, `ase`)
+    const velocities = new Map(
+      (sections.get(`Velocities`)?.rows ?? []).map(([id, ...vel]) => [
+        Number(id),
+        vel.slice(0, 3).map((val) => Number(val) * velocity_scale) as Vec3,
+      ]),
+    )
+    const get_element = (atom_type: number): ElementSymbol => {
+      const mass = masses.get(atom_type)
+      const from_mass = mass === undefined ? undefined : element_from_mass(mass)
+      const element = atom_type_mapping?.[atom_type] ?? from_mass
+      if (element) return element
+      diag_warn(`No element for LAMMPS atom type ${atom_type}, using default mapping`)
+      return ELEM_SYMBOLS[Math.max(0, atom_type - 1) % ELEM_SYMBOLS.length]
+    }
+    const elements = new Map<number, ElementSymbol>()
+
+    const sorted_rows = atoms.rows.toSorted((row1, row2) => Number(row1[0]) - Number(row2[0]))
+    const sites = sorted_rows.map((row, idx) => {
+      const atom_type = Math.trunc(Number(row[columns.type]))
+      if (!elements.has(atom_type)) elements.set(atom_type, get_element(atom_type))
+      const element = elements.get(atom_type) as ElementSymbol
+      const xyz = vec3_from_values(
+        row.slice(columns.pos, columns.pos + 3).map(Number),
+        `LAMMPS atom ${row[0]} position`,
+      ).map((coord, axis) => (coord - lo[axis]) * length_scale) as Vec3
+
+      const properties: Record<string, unknown> = {}
+      if (columns.charge !== undefined) properties.charge = Number(row[columns.charge])
+      if (columns.molecule !== undefined) {
+        properties.molecule_id = Number(row[columns.molecule])
+      }
+      const velocity = velocities.get(Number(row[0]))
+      if (velocity) properties.velocity = velocity
+      return make_site(element, cart_to_frac(xyz), xyz, `${element}${idx + 1}`, properties)
+    })
+
+    const lattice_params = math.calc_lattice_params(lattice_matrix)
     return { sites, lattice: { matrix: lattice_matrix, ...lattice_params } }
   } catch (error) {
-    diag_error(`Error parsing CIF file`, error)
+    diag_error(`Error parsing LAMMPS data file`, error)
     return null
   }
 }
@@ -1258,7 +1768,8 @@ export function normalize_fractional_coords(structure: ParsedStructure): ParsedS
   return { ...structure, sites: normalized_sites }
 }
 
-// Detect a structure inside already-stringified JSON (OPTIMADE or pymatgen/nested).
+// Detect a structure inside already-stringified JSON (OPTIMADE, AiiDA StructureData,
+// Materials Project documents or pymatgen/nested).
 // Throws if `content` isn't valid JSON; returns null if it holds no known structure.
 const detect_json_structure = (content: string): ParsedStructure | null => {
   const parsed = JSON.parse(content)
@@ -1266,11 +1777,27 @@ const detect_json_structure = (content: string): ParsedStructure | null => {
     const result = parse_optimade_from_raw(parsed)
     if (result) return result
   }
+  // AiiDA exports nest the StructureData attributes under `attributes`
+  const aiida = is_aiida_structure(parsed?.attributes) ? parsed.attributes : parsed
+  if (is_aiida_structure(aiida)) return from_aiida_structure(aiida)
+  // task documents hold input and output structures, pick the relaxed one
+  if (is_mp_document(parsed)) return from_mp_document(parsed)
   // Otherwise try parsing as pymatgen/nested structure JSON
-  const structure = find_structure_in_json(parsed)
+  return parse_pymatgen_json(parsed)
+}
+
+// pymatgen Structure/Molecule dict (possibly nested in other JSON) with fractional
+// coordinates wrapped into the cell and lattice params derived from the matrix
+export function parse_pymatgen_json(obj: unknown): ParsedStructure | null {
+  const structure = find_structure_in_json(obj)
   return structure ? ensure_lattice_params(normalize_fractional_coords(structure)) : null
 }
 
+// Default-mode CIF parse, retried in lenient mode only if that fails, so well-formed files
+// never pick up lenient recoveries (whose warnings reach get_parse_warnings)
+const parse_cif_with_fallback = (content: string): ParsedStructure | null =>
+  parse_cif(content) ?? parse_cif(content, true, `lenient`)
+
 // Internal: auto-detect file format, returns null on failure after recording reasons (see parse error contract at top)
 function parse_structure_file_impl(
   content: string,
@@ -1286,8 +1813,8 @@ function parse_structure_file_impl(
     // Try to detect format by file extension
     if (ext === `xyz` || ext === `extxyz`) return parse_xyz(content)
 
-    // CIF files
-    if (ext === `cif`) return parse_cif(content)
+    // CIF files (files from ICSD/COD exports often need the lenient fallback)
+    if (ext === `cif`) return parse_cif_with_fallback(content)
 
     // JSON files - extension is authoritative, so failures return null
     if (ext === `json`) {
@@ -1304,6 +1831,11 @@ function parse_structure_file_impl(
     // YAML files (phonopy)
     if (ext === `yaml` || ext === `yml`) return parse_phonopy_yaml(content)
 
+    // LAMMPS data files (.data is generic, so require an `N atoms` header line)
+    if (ext === `lmp` || (ext === `data` && /^\s*\d+\s+atoms\s*$/m.test(content))) {
+      return parse_lammps_data(content)
+    }
+
     // POSCAR files may not have extensions or have various names
     if (ext === `poscar` || base_filename.includes(`poscar`)) {
       return parse_poscar(content)
@@ -1359,7 +1891,7 @@ function parse_structure_file_impl(
       line.includes(`_atom_site_`) ||
       line.trim() === `loop_`,
   )
-  if (has_cif_keywords) return parse_cif(content)
+  if (has_cif_keywords) return parse_cif_with_fallback(content)
 
   // YAML format detection: look for phonopy-specific keywords
   const has_phonopy_keywords = lines.some(
@@ -1430,7 +1962,8 @@ export function parse_optimade_json(content: string): ParsedStructure | null {
 
 // Build sites + lattice shared by parse_optimade_from_raw and optimade_to_crystal.
 // on_invalid controls whether invalid positions are skipped with a warning or throw;
-// site_props extracts per-site mass/concentration from the species list.
+// site_props extracts per-site mass/concentration from the species list. Disordered
+// species and assemblies become partial occupancies.
 function build_optimade_sites(
   attrs: OptimadeStructure[`attributes`],
   opts: { on_invalid: `skip` | `throw`; site_props?: boolean },
@@ -1463,6 +1996,8 @@ function build_optimade_sites(
   }
 
   const sites: Site[] = []
+  // sites by their OPTIMADE index, which assemblies refer to (skipped sites are undefined)
+  const sites_by_idx: (Site | undefined)[] = []
   for (let idx = 0; idx < positions.length; idx++) {
     const species_name = species_at_sites[idx]
     if (!species_name) {
@@ -1489,12 +2024,12 @@ function build_optimade_sites(
     // Calculate fractional coordinates if lattice is available
     const abc: Vec3 = cart_to_frac ? cart_to_frac(xyz) : [0, 0, 0]
 
+    const spec = species_list?.find((entry) => entry.name === species_name)
     const site_props: Record<string, unknown> = {}
     if (opts.site_props) {
       // Extract mass/concentration for the chosen element. sym_idx indexes the (parallel)
       // chemical_symbols/mass/concentration arrays; -1 (name resolved directly, no
       // chemical_symbols) falls back to index 0 — the single-element entry.
-      const spec = species_list?.find((entry) => entry.name === species_name)
       const spec_idx = Math.max(sym_idx, 0)
       if (spec?.mass?.[spec_idx] !== undefined) site_props.mass = spec.mass[spec_idx]
       if (
@@ -1505,7 +2040,13 @@ function build_optimade_sites(
       }
     }
 
-    sites.push(make_site(element, abc, xyz, `${element}${idx + 1}`, site_props))
+    const site = make_site(element, abc, xyz, `${element}${idx + 1}`, site_props)
+    site.species = optimade_partial_species(spec) ?? site.species
+    sites.push(site)
+    sites_by_idx[idx] = site
+  }
+  if (Array.isArray(attrs.assemblies)) {
+    apply_optimade_assemblies(sites_by_idx, attrs.assemblies as OptimadeAssembly[])
   }
 
   return { sites, lattice_matrix, lattice_params }
@@ -1596,6 +2137,7 @@ export function optimade_to_crystal(optimade_structure: OptimadeStructure): Crys
     cartesian_site_positions,
     species_at_sites,
     species: _species, // excluded from the properties rest
+    assemblies: _assemblies, // applied to site occupancies
     ...properties
   } = optimade_structure.attributes
 
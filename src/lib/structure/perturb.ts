@@ -0,0 +1,74 @@
+// Seeded random displacements of sites, e.g. to break symmetry before relaxations, start
+// MD from a rattled lattice or generate training data for ML potentials. Each site draws
+// from its own substream derive_seed(seed, site_idx), so its displacement depends only on
+// the seed and its index, not on the other sites or how structures are batched.
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from './index'
+import type { AtomSelection } from './select'
+import { selection_mask } from './select'
+
+export type DisplacementOptions = {
+  seed?: number // default 0, results are reproducible for a given seed
+  selection?: AtomSelection // only displace these sites (default all)
+}
+
+// Copy of a structure with each selected site moved by displace(random stream of the
+// site), keeping fractional coordinates in sync for crystals
+function displace_sites<T extends AnyStructure>(
+  structure: T,
+  options: DisplacementOptions,
+  displace: (random: () => number) => Vec3,
+): T {
+  const { seed = 0, selection } = options
+  const mask = selection_mask(structure, selection)
+  const cart_to_frac =
+    `lattice` in structure
+      ? math.create_lattice_converters(structure.lattice.matrix).cart_to_frac
+      : null
+  const sites = structure.sites.map((site, site_idx) => {
+    if (!mask[site_idx]) return site
+    const xyz = math.add(site.xyz, displace(math.mulberry32(math.derive_seed(seed, site_idx))))
+    return { ...site, xyz, abc: cart_to_frac ? cart_to_frac(xyz) : site.abc }
+  })
+  return { ...structure, sites }
+}
+
+// ASE-style rattle: independent normal displacements with standard deviation stdev (Å)
+// along each Cartesian axis
+export function rattle_structure<T extends AnyStructure>(
+  structure: T,
+  stdev: number,
+  options: DisplacementOptions = {},
+): T {
+  if (!(stdev >= 0)) throw new Error(`stdev must be >= 0, got ${stdev}`)
+  return displace_sites(structure, options, (random) => [
+    stdev * math.random_normal(random),
+    stdev * math.random_normal(random),
+    stdev * math.random_normal(random),
+  ])
+}
+
+// pymatgen-style perturb: move each site in a uniformly random direction by a distance
+// drawn uniformly from [min_distance, distance] (exactly distance by default)
+export function perturb_structure<T extends AnyStructure>(
+  structure: T,
+  distance: number,
+  options: DisplacementOptions & { min_distance?: number } = {},
+): T {
+  const { min_distance = distance } = options
+  if (!(min_distance >= 0 && min_distance <= distance)) {
+    throw new Error(`Need 0 <= min_distance <= distance, got ${min_distance} and ${distance}`)
+  }
+  return displace_sites(structure, options, (random) => {
+    const cos_theta = 2 * random() - 1
+    const phi = 2 * Math.PI * random()
+    const sin_theta = Math.sqrt(1 - cos_theta ** 2)
+    const length = min_distance + (distance - min_distance) * random()
+    return [
+      length * sin_theta * Math.cos(phi),
+      length * sin_theta * Math.sin(phi),
+      length * cos_theta,
+    ]
+  })
+}
@@ -0,0 +1,100 @@
+// Formal ionic polarization of a polar structure relative to a (centrosymmetric) reference
+// from point charges (nominal oxidation states) or Born effective charges, for quick
+// screening of ferroelectric candidates
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+
+// 1 e/Å² in μC/cm²
+export const E_PER_A2_TO_UC_PER_CM2 = 1602.176634
+
+export interface PolarizationOptions {
+  // per-site Born effective charges (scalar or 3x3 tensor in units of e), defaults to
+  // the occupancy-weighted oxidation states of each site
+  born_charges?: (number | Matrix3x3)[]
+}
+
+export interface Polarization {
+  polarization: Vec3 // Cartesian, μC/cm²
+  magnitude: number // μC/cm²
+  dipole: Vec3 // Cartesian dipole change per cell, e·Å
+  quantum: Vec3 // polarization quantum e·|a_i|/V along each lattice vector, μC/cm²
+  quanta: Vec3 // polarization along each lattice vector in units of its quantum
+  displacements: Vec3[] // Cartesian shift of each site from its reference position, Å
+}
+
+// ΔP = (1/V) Σ_i Z_i · Δr_i with sites matched by index. Polarization of a periodic
+// crystal is only defined modulo the quantum e·R/V (R any lattice vector), so each site
+// takes its shortest periodic displacement from the reference, which puts ΔP on the
+// branch continuously connected to the reference. The quanta show how close ΔP is to the
+// branch ambiguity (|quanta| near 0.5 means a different branch may be physical).
+export function calc_polarization(
+  structure: AnyStructure,
+  reference: AnyStructure,
+  options: PolarizationOptions = {},
+): Polarization {
+  if (!(`lattice` in structure) || !(`lattice` in reference)) {
+    throw new Error(`Cannot compute polarization: structure and reference need a lattice`)
+  }
+  const { sites, lattice } = structure
+  const [n_sites, n_ref] = [sites.length, reference.sites.length]
+  if (n_sites !== n_ref) {
+    throw new Error(`Site count mismatch: structure has ${n_sites}, reference has ${n_ref}`)
+  }
+  const { born_charges } = options
+  if (born_charges && born_charges.length !== sites.length) {
+    throw new Error(
+      `Expected ${sites.length} Born charges (one per site), got ${born_charges.length}`,
+    )
+  }
+
+  let charges: (number | Matrix3x3)[]
+  if (born_charges) charges = born_charges
+  else {
+    const oxi_states = sites.map((site) =>
+      site.species.reduce((sum, { occu, oxidation_state }) => sum + occu * oxidation_state, 0),
+    )
+    if (oxi_states.every((charge) => charge === 0)) {
+      throw new Error(`Structure has no oxidation states, pass born_charges instead`)
+    }
+    const total_charge = oxi_states.reduce((sum, charge) => sum + charge, 0)
+    // a net charge makes the dipole depend on the choice of origin
+    if (Math.abs(total_charge) > 1e-6) {
+      throw new Error(`Structure is not charge neutral (total charge ${total_charge})`)
+    }
+    charges = oxi_states
+  }
+
+  const frac_to_cart = math.create_frac_to_cart(lattice.matrix)
+  const volume = Math.abs(math.det_3x3(lattice.matrix))
+  const dipole: Vec3 = [0, 0, 0]
+  const displacements = sites.map((site, site_idx) => {
+    const frac_shift = site.abc.map((coord, axis) => {
+      const diff = coord - reference.sites[site_idx].abc[axis]
+      return lattice.pbc[axis] ? diff - Math.round(diff) : diff
+    }) as Vec3
+    const displacement = frac_to_cart(frac_shift)
+    const charge = charges[site_idx]
+    const site_dipole =
+      typeof charge === `number`
+        ? math.scale(displacement, charge)
+        : math.mat3x3_vec3_multiply(charge, displacement)
+    for (const axis of [0, 1, 2]) dipole[axis] += site_dipole[axis]
+    return displacement
+  })
+
+  const polarization = math.scale(dipole, E_PER_A2_TO_UC_PER_CM2 / volume)
+  const quantum = lattice.matrix.map(
+    (vec) => (E_PER_A2_TO_UC_PER_CM2 * Math.hypot(...vec)) / volume,
+  ) as Vec3
+  // dipole in fractional coordinates = polarization in units of the quantum per axis
+  const quanta = math.create_cart_to_frac(lattice.matrix)(dipole)
+  return {
+    polarization,
+    magnitude: Math.hypot(...polarization),
+    dipole,
+    quantum,
+    quanta,
+    displacements,
+  }
+}
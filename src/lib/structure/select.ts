@@ -0,0 +1,126 @@
+// Composable atom selections for analysis functions (RDF, profiles, coordination,
+// trajectory transcoding), e.g. Select.element(`Li`).and(Select.z_above(10)) instead
+// of hand-built index lists. Selections are evaluated per structure, so geometric ones
+// follow the atoms through a trajectory.
+import type { ElementSymbol } from '$lib/element'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, Site } from './index'
+
+export type SiteFilter = (site: Site, site_idx: number, structure: AnyStructure) => boolean
+
+export class Select {
+  private constructor(private readonly filter: SiteFilter) {}
+
+  static all(): Select {
+    return new Select(() => true)
+  }
+
+  static none(): Select {
+    return new Select(() => false)
+  }
+
+  // custom per-site predicate
+  static where(filter: SiteFilter): Select {
+    return new Select(filter)
+  }
+
+  // sites with any species of these elements (also partially occupied ones)
+  static element(...elements: ElementSymbol[]): Select {
+    const wanted = new Set<string>(elements)
+    return new Select(({ species }) => species.some(({ element }) => wanted.has(element)))
+  }
+
+  static index(...indices: number[]): Select {
+    const wanted = new Set(indices)
+    return new Select((_, site_idx) => wanted.has(site_idx))
+  }
+
+  static label(...labels: string[]): Select {
+    const wanted = new Set(labels)
+    return new Select(({ label }) => wanted.has(label))
+  }
+
+  // Cartesian coordinate along axis (0 = x, 1 = y, 2 = z) in [min, max)
+  static coord_between(axis: 0 | 1 | 2, min: number, max: number): Select {
+    return new Select(({ xyz }) => xyz[axis] >= min && xyz[axis] < max)
+  }
+
+  static z_above(height: number): Select {
+    return Select.coord_between(2, height, Infinity)
+  }
+
+  static z_below(height: number): Select {
+    return Select.coord_between(2, -Infinity, height)
+  }
+
+  // fractional coordinate along a lattice vector in [min, max) after wrapping into
+  // [0, 1), e.g. a slab of a periodic cell (never matches sites of molecules)
+  static frac_between(axis: 0 | 1 | 2, min: number, max: number): Select {
+    return new Select((site, _, structure) => {
+      if (!(`lattice` in structure)) return false
+      const frac = site.abc[axis] - Math.floor(site.abc[axis])
+      return frac >= min && frac < max
+    })
+  }
+
+  // sites within radius (Å) of a point, by minimum-image distance in periodic cells
+  static within(center: Vec3, radius: number): Select {
+    return new Select(({ xyz }, _, structure) => {
+      if (!(`lattice` in structure)) return math.euclidean_dist(center, xyz) <= radius
+      const { matrix, pbc } = structure.lattice
+      return math.pbc_dist(center, xyz, matrix, undefined, pbc) <= radius
+    })
+  }
+
+  and(other: Select): Select {
+    return new Select((...args) => this.filter(...args) && other.filter(...args))
+  }
+
+  or(other: Select): Select {
+    return new Select((...args) => this.filter(...args) || other.filter(...args))
+  }
+
+  not(): Select {
+    return new Select((...args) => !this.filter(...args))
+  }
+
+  mask(structure: AnyStructure): boolean[] {
+    return structure.sites.map((site, idx) => this.filter(site, idx, structure))
+  }
+
+  indices(structure: AnyStructure): number[] {
+    return structure.sites.flatMap((site, idx) =>
+      this.filter(site, idx, structure) ? [idx] : [],
+    )
+  }
+}
+
+// what analysis functions accept: a Select or explicit site indices
+export type AtomSelection = Select | readonly number[]
+
+// Selected site indices in the selection's order (ascending for a Select), all sites if
+// the selection is undefined. Throws for out-of-range indices.
+export function selection_indices(
+  structure: AnyStructure,
+  selection?: AtomSelection,
+): number[] {
+  if (!selection) return structure.sites.map((_, idx) => idx)
+  if (selection instanceof Select) return selection.indices(structure)
+  const bad_idx = selection.find(
+    (idx) => !Number.isInteger(idx) || idx < 0 || idx >= structure.sites.length,
+  )
+  if (bad_idx !== undefined) {
+    throw new Error(`Atom index ${bad_idx} out of range for ${structure.sites.length} sites`)
+  }
+  return [...selection]
+}
+
+// Per-site membership of a selection, all true if it is undefined
+export function selection_mask(structure: AnyStructure, selection?: AtomSelection): boolean[] {
+  if (selection instanceof Select) return selection.mask(structure)
+  if (!selection) return structure.sites.map(() => true)
+  const mask = structure.sites.map(() => false)
+  for (const idx of selection_indices(structure, selection)) mask[idx] = true
+  return mask
+}
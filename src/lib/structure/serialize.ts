@@ -4,8 +4,12 @@ export {
   export_structure_as,
   STRUCT_TEXT_FORMATS,
   structure_to_cif_str,
+  structure_to_cube_str,
   structure_to_json_str,
+  structure_to_lammps_data_str,
+  structure_to_optimade,
+  structure_to_optimade_str,
   structure_to_poscar_str,
   structure_to_xyz_str,
 } from './export'
-export type { StructTextFormat } from './export'
+export type { LammpsDataExportOptions, StructTextFormat } from './export'
@@ -0,0 +1,207 @@
+// Per-site scalar and vector fields (charges, forces, order parameters, strains, ...)
+// stored in structure.properties.site_fields, so analysis results can be colored in
+// the viewer and written by the extXYZ/CIF/JSON exporters without ad-hoc property keys
+import type { Vec3 } from '$lib/math'
+import type { AnyStructure, Site } from '$lib/structure'
+
+export type SiteField =
+  | { kind: `scalar`; values: number[]; unit?: string }
+  | { kind: `vector`; values: Vec3[]; unit?: string }
+export type SiteFields = Record<string, SiteField>
+
+// field names become extXYZ columns and CIF data names, so keep them identifier-like
+const FIELD_NAME_RE = /^[A-Za-z_][A-Za-z0-9_]*$/
+
+const is_vec3 = (val: unknown): val is Vec3 =>
+  Array.isArray(val) && val.length === 3 && val.every(Number.isFinite)
+
+// Attach (or replace) a field with one number or Cartesian 3-vector per site.
+// Returns a new structure; the input is not modified.
+export function set_site_field<T extends AnyStructure>(
+  structure: T,
+  name: string,
+  values: number[] | Vec3[],
+  unit?: string,
+): T {
+  if (!FIELD_NAME_RE.test(name)) {
+    throw new Error(`Invalid site field name '${name}', use letters, digits and _`)
+  }
+  const n_sites = structure.sites.length
+  if (values.length !== n_sites) {
+    throw new Error(`Site field ${name} has ${values.length} values for ${n_sites} sites`)
+  }
+  const entries: (number | Vec3)[] = values
+  let field: SiteField
+  if (entries.every((val): val is number => typeof val === `number`)) {
+    field = { kind: `scalar`, values: [...entries] }
+  } else if (entries.every(is_vec3)) {
+    field = { kind: `vector`, values: entries.map((vec): Vec3 => [vec[0], vec[1], vec[2]]) }
+  } else {
+    throw new Error(`Site field ${name} must hold only numbers or only 3-vectors`)
+  }
+  if (unit) field.unit = unit
+  const site_fields = { ...structure.properties?.site_fields, [name]: field }
+  return { ...structure, properties: { ...structure.properties, site_fields } }
+}
+
+export function remove_site_field<T extends AnyStructure>(structure: T, name: string): T {
+  const { [name]: _removed, ...site_fields } = structure.properties?.site_fields ?? {}
+  return { ...structure, properties: { ...structure.properties, site_fields } }
+}
+
+// All fields of a structure, checked against its current number of sites
+export function get_site_fields(structure: AnyStructure): SiteFields {
+  const fields = structure.properties?.site_fields ?? {}
+  const n_sites = structure.sites.length
+  for (const [name, { values }] of Object.entries(fields)) {
+    if (values.length !== n_sites) {
+      throw new Error(`Site field ${name} has ${values.length} values for ${n_sites} sites`)
+    }
+  }
+  return fields
+}
+
+// Lift numeric or 3-vector entries of site.properties (e.g. from a parsed extXYZ or
+// pymatgen JSON) into site fields. Keys missing or malformed on any site are skipped.
+export function site_fields_from_properties<T extends AnyStructure>(
+  structure: T,
+  keys: string[],
+): T {
+  let result = structure
+  for (const key of keys) {
+    const values = structure.sites.map((site) => site.properties?.[key])
+    const numeric = values.every((val) => typeof val === `number` && Number.isFinite(val))
+    if (numeric || values.every(is_vec3)) {
+      result = set_site_field(result, key, values as number[] | Vec3[])
+    }
+  }
+  return result
+}
+
+// pymatgen's SiteCollection.site_properties: one list per key over all sites, with null
+// (Python None) for sites that lack the key
+export function get_site_properties(structure: AnyStructure): Record<string, unknown[]> {
+  const keys = new Set(structure.sites.flatMap((site) => Object.keys(site.properties ?? {})))
+  const column = (key: string) => structure.sites.map((site) => site.properties?.[key] ?? null)
+  return Object.fromEntries([...keys].map((key) => [key, column(key)]))
+}
+
+// Merge a pymatgen-style site_properties dict-of-lists into the per-site property maps.
+// Every list must have one entry per site; null entries remove the key from that site.
+// Returns a new structure; the input is not modified.
+export function set_site_properties<T extends AnyStructure>(
+  structure: T,
+  site_properties: Record<string, readonly unknown[]>,
+): T {
+  const n_sites = structure.sites.length
+  for (const [key, values] of Object.entries(site_properties)) {
+    if (!Array.isArray(values) || values.length !== n_sites) {
+      const got = Array.isArray(values) ? `${values.length} values` : typeof values
+      throw new Error(`Site property ${key} needs one value per site (${n_sites}), got ${got}`)
+    }
+  }
+  const sites = structure.sites.map((site, idx) => {
+    const properties = { ...site.properties }
+    for (const [key, values] of Object.entries(site_properties)) {
+      if (values[idx] === null || values[idx] === undefined) delete properties[key]
+      else properties[key] = values[idx]
+    }
+    return { ...site, properties }
+  })
+  return { ...structure, sites }
+}
+
+// Scalar values of a field for coloring and plotting (vector fields give their norms)
+export function site_field_values(structure: AnyStructure, name: string): number[] {
+  const field = get_site_fields(structure)[name]
+  if (!field) throw new Error(`No site field '${name}'`)
+  if (field.kind === `scalar`) return field.values
+  return field.values.map((vec) => Math.hypot(...vec))
+}
+
+// color_fn for AtomColorConfig (mode `custom`) that colors atoms by a site field
+export function site_field_color_fn(
+  structure: AnyStructure,
+  name: string,
+): (site: Site, idx: number) => number {
+  const values = site_field_values(structure, name)
+  return (_site, idx) => values[idx]
+}
+
+export interface SiteFieldArray {
+  data: Float64Array // row-major, n_sites × n_components
+  n_components: 1 | 3
+  unit?: string
+}
+
+// Flat typed arrays per field, the layout WASM consumers expect
+export function site_fields_to_typed_arrays(
+  structure: AnyStructure,
+): Record<string, SiteFieldArray> {
+  return Object.fromEntries(
+    Object.entries(get_site_fields(structure)).map(([name, field]) => {
+      const n_components: 1 | 3 = field.kind === `scalar` ? 1 : 3
+      const data = Float64Array.from(
+        field.kind === `scalar` ? field.values : field.values.flat(),
+      )
+      return [name, { data, n_components, ...(field.unit && { unit: field.unit }) }]
+    }),
+  )
+}
+
+// Column names and per-site rows of all fields, vector fields split into _x, _y, _z
+export function site_field_columns(structure: AnyStructure): {
+  names: string[]
+  rows: number[][]
+} {
+  const fields = Object.entries(get_site_fields(structure))
+  const names = fields.flatMap(([name, field]) =>
+    field.kind === `scalar` ? [name] : [`${name}_x`, `${name}_y`, `${name}_z`],
+  )
+  const rows = structure.sites.map((_, idx) =>
+    fields.flatMap(([, field]) =>
+      field.kind === `scalar` ? [field.values[idx]] : field.values[idx],
+    ),
+  )
+  return { names, rows }
+}
+
+// Tile fields for a supercell whose sites repeat the original ones cell by cell
+export function replicate_site_fields(fields: SiteFields, n_cells: number): SiteFields {
+  const tile = <V>(values: V[]): V[] =>
+    Array.from({ length: n_cells * values.length }, (_, idx) => values[idx % values.length])
+  return Object.fromEntries(
+    Object.entries(fields).map(([name, field]): [string, SiteField] =>
+      field.kind === `scalar`
+        ? [name, { ...field, values: tile(field.values) }]
+        : [name, { ...field, values: tile(field.values) }],
+    ),
+  )
+}
+
+// Restrict fields to a subset of sites, e.g. after filtering or dropping sites
+export function select_site_fields(fields: SiteFields, indices: readonly number[]): SiteFields {
+  return Object.fromEntries(
+    Object.entries(fields).map(([name, field]): [string, SiteField] =>
+      field.kind === `scalar`
+        ? [name, { ...field, values: indices.map((idx) => field.values[idx]) }]
+        : [name, { ...field, values: indices.map((idx) => field.values[idx]) }],
+    ),
+  )
+}
+
+// Structure restricted to the sites at indices (in that order) with its fields cut down
+// alongside, so the result still exports
+export function select_sites<T extends AnyStructure>(
+  structure: T,
+  indices: readonly number[],
+): T {
+  const sites = indices.map((idx) => structure.sites[idx])
+  const site_fields = structure.properties?.site_fields
+  if (!site_fields) return { ...structure, sites }
+  return {
+    ...structure,
+    sites,
+    properties: { ...structure.properties, site_fields: select_site_fields(site_fields, indices) },
+  }
+}
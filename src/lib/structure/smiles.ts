@@ -0,0 +1,255 @@
+// Canonical SMILES for molecules and bonded fragments (e.g. adsorbates or solvent molecules
+// in hybrid systems) from the perceived molecular topology. Atoms are ranked by iterative
+// Morgan-style refinement of atom invariants so the string doesn't depend on site order.
+// Charges and stereochemistry aren't perceived, so neither is written.
+// InChI is deliberately not generated: database lookups need the exact standard InChI,
+// whose canonical numbering is defined by the IUPAC reference implementation, and a
+// reimplementation that diverges on edge cases would silently fail to match.
+import { SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import type { AnyStructure, BondOrder } from '$lib/structure'
+import { get_majority_element } from './bonding'
+import type { MolecularTopology } from './topology'
+import { perceive_topology } from './topology'
+
+export interface FragmentSmiles {
+  site_indices: number[] // sites of the fragment (incl. suppressed hydrogens)
+  smiles: string | null // null for fragments extended through periodic images
+}
+
+// Standard valences of the SMILES organic subset, lowest first
+const ORGANIC_VALENCES: Record<string, number[]> = {
+  B: [3],
+  C: [4],
+  N: [3, 5],
+  O: [2],
+  P: [3, 5],
+  S: [2, 4, 6],
+  F: [1],
+  Cl: [1],
+  Br: [1],
+  I: [1],
+}
+const AROMATIC_SYMBOLS = new Set([`B`, `C`, `N`, `O`, `P`, `S`])
+
+type SmilesBond = { neighbor: number; order: BondOrder }
+
+const is_aromatic_order = (order: BondOrder): boolean => order === `aromatic` || order === 1.5
+const bond_valence = (order: BondOrder): number => (is_aromatic_order(order) ? 1 : order)
+
+// True if a fragment's bonds chain a site to one of its own periodic images, i.e. the
+// fragment is an infinite chain/layer/framework rather than a molecule
+function is_extended_fragment(
+  fragment: number[],
+  adjacency: { neighbor: number; image: number[] }[][],
+): boolean {
+  const offsets = new Map<number, number[]>([[fragment[0], [0, 0, 0]]])
+  const stack = [fragment[0]]
+  while (stack.length > 0) {
+    const site_idx = stack.pop() as number
+    const offset = offsets.get(site_idx) as number[]
+    for (const { neighbor, image } of adjacency[site_idx]) {
+      const nb_offset = offset.map((val, axis) => val + image[axis])
+      const seen = offsets.get(neighbor)
+      if (!seen) {
+        offsets.set(neighbor, nb_offset)
+        stack.push(neighbor)
+      } else if (seen.some((val, axis) => val !== nb_offset[axis])) return true
+    }
+  }
+  return false
+}
+
+// Canonical atom ranks: refine invariant classes by sorted neighbor classes until stable,
+// then break remaining ties (symmetry-equivalent atoms) one at a time and refine again
+function canonical_ranks(invariants: number[][], bonds: SmilesBond[][]): number[] {
+  const rank_by_key = (keys: string[]): number[] => {
+    const rank_of = new Map([...new Set(keys)].sort().map((key, rank) => [key, rank]))
+    return keys.map((key) => rank_of.get(key) as number)
+  }
+  const pad = (val: number): string => String(val).padStart(8, `0`)
+  const n_classes = (ranks: number[]): number => new Set(ranks).size
+  const refine = (ranks: number[]): number[] => {
+    let current = ranks
+    while (true) {
+      const keys = current.map((rank, idx) => {
+        const nb_keys = bonds[idx]
+          .map(({ neighbor, order }) => `${pad(current[neighbor])}${order}`)
+          .sort()
+        return `${pad(rank)}|${nb_keys.join(`,`)}`
+      })
+      const next = rank_by_key(keys)
+      if (n_classes(next) === n_classes(current)) return current
+      current = next
+    }
+  }
+
+  let ranks = refine(rank_by_key(invariants.map((vals) => vals.map(pad).join(`.`))))
+  while (n_classes(ranks) < ranks.length) {
+    const tied_rank = Math.min(
+      ...ranks.filter((rank) => ranks.indexOf(rank) !== ranks.lastIndexOf(rank)),
+    )
+    const chosen = ranks.indexOf(tied_rank)
+    ranks = refine(
+      rank_by_key(ranks.map((rank, idx) => pad(rank * 2 + (idx === chosen ? 0 : 1)))),
+    )
+  }
+  return ranks
+}
+
+// SMILES of one finite fragment. Hydrogens bonded to exactly one heavy atom are folded
+// into their neighbor's implicit/bracket H count, all other atoms are written.
+function fragment_to_smiles(
+  elements: string[],
+  fragment: number[],
+  bonds_by_site: SmilesBond[][],
+): string {
+  const is_suppressed_h = (site_idx: number): boolean =>
+    elements[site_idx] === `H` &&
+    bonds_by_site[site_idx].length === 1 &&
+    elements[bonds_by_site[site_idx][0].neighbor] !== `H`
+  const atoms = fragment.filter((site_idx) => !is_suppressed_h(site_idx))
+  const local_idx = new Map(atoms.map((site_idx, idx) => [site_idx, idx]))
+  const h_counts = atoms.map(
+    (site_idx) =>
+      bonds_by_site[site_idx].filter(({ neighbor }) => is_suppressed_h(neighbor)).length,
+  )
+  const bonds: SmilesBond[][] = atoms.map((site_idx) =>
+    bonds_by_site[site_idx].flatMap(({ neighbor, order }) => {
+      const nb_idx = local_idx.get(neighbor)
+      return nb_idx === undefined ? [] : [{ neighbor: nb_idx, order }]
+    }),
+  )
+  const aromatic = bonds.map((atom_bonds, idx) =>
+    atom_bonds.some(({ order }) => is_aromatic_order(order)) &&
+    AROMATIC_SYMBOLS.has(elements[atoms[idx]]),
+  )
+
+  const invariants = atoms.map((site_idx, idx) => [
+    SYMBOL_TO_ATOMIC_NUMBER[elements[site_idx] as keyof typeof SYMBOL_TO_ATOMIC_NUMBER] ?? 0,
+    bonds[idx].length,
+    h_counts[idx],
+    Number(aromatic[idx]),
+    bonds[idx].reduce((sum, { order }) => sum + (is_aromatic_order(order) ? 3 : order * 2), 0),
+  ])
+  const ranks = canonical_ranks(invariants, bonds)
+  for (const atom_bonds of bonds) {
+    atom_bonds.sort((bond_1, bond_2) => ranks[bond_1.neighbor] - ranks[bond_2.neighbor])
+  }
+
+  const atom_symbol = (idx: number): string => {
+    const element = elements[atoms[idx]]
+    const n_h = h_counts[idx]
+    const valences = ORGANIC_VALENCES[element]
+    if (valences) {
+      // implicit H count a SMILES reader would assign (aromatic atoms donate one
+      // bond to the π system and only use their lowest valence)
+      const used =
+        bonds[idx].reduce((sum, { order }) => sum + bond_valence(order), 0) +
+        Number(aromatic[idx])
+      const target = aromatic[idx]
+        ? valences[0]
+        : (valences.find((valence) => valence >= used) ?? used)
+      if (Math.max(0, target - used) === n_h) {
+        return aromatic[idx] ? element.toLowerCase() : element
+      }
+    }
+    const symbol = aromatic[idx] ? element.toLowerCase() : element
+    return `[${symbol}${n_h > 0 ? `H${n_h > 1 ? n_h : ``}` : ``}]`
+  }
+  const bond_symbol = (idx_1: number, idx_2: number, order: BondOrder): string => {
+    if (is_aromatic_order(order)) return aromatic[idx_1] && aromatic[idx_2] ? `` : `:`
+    if (order === 1) return aromatic[idx_1] && aromatic[idx_2] ? `-` : ``
+    return order === 2 ? `=` : order === 3 ? `#` : ``
+  }
+
+  // Pass 1: DFS from the lowest-ranked least-connected atom (so chains start at a
+  // terminal atom) visiting neighbors in rank order, recording tree children and
+  // ring-closure bonds (opened at the earlier-visited atom)
+  const start = ranks.reduce((best, rank, idx) => {
+    const [degree, best_degree] = [bonds[idx].length, bonds[best].length]
+    return degree < best_degree || (degree === best_degree && rank < ranks[best]) ? idx : best
+  }, 0)
+  const children: SmilesBond[][] = atoms.map(() => [])
+  const ring_bonds: { atom: number; partner: number; order: BondOrder; opens: boolean }[][] =
+    atoms.map(() => [])
+  const visited = new Set<number>()
+  const closed = new Set<string>()
+  const visit = (idx: number, parent: number): void => {
+    visited.add(idx)
+    for (const { neighbor, order } of bonds[idx]) {
+      if (neighbor === parent) continue
+      const key = `${Math.min(idx, neighbor)}-${Math.max(idx, neighbor)}`
+      if (visited.has(neighbor)) {
+        if (closed.has(key)) continue
+        closed.add(key)
+        ring_bonds[neighbor].push({ atom: neighbor, partner: idx, order, opens: true })
+        ring_bonds[idx].push({ atom: idx, partner: neighbor, order, opens: false })
+        continue
+      }
+      closed.add(key)
+      children[idx].push({ neighbor, order })
+      visit(neighbor, idx)
+    }
+  }
+  visit(start, -1)
+
+  // Pass 2: write atoms with ring-closure digits (lowest free digit) and branches
+  const digits = new Map<string, number>()
+  const in_use = new Set<number>()
+  const write = (idx: number): string => {
+    let out = atom_symbol(idx)
+    for (const { atom, partner, order, opens } of ring_bonds[idx]) {
+      const key = `${Math.min(atom, partner)}-${Math.max(atom, partner)}`
+      let digit: number
+      if (opens) {
+        digit = 1
+        while (in_use.has(digit)) digit++
+        in_use.add(digit)
+        digits.set(key, digit)
+        out += bond_symbol(atom, partner, order)
+      } else {
+        digit = digits.get(key) as number
+        in_use.delete(digit)
+      }
+      out += digit > 9 ? `%${digit}` : String(digit)
+    }
+    children[idx].forEach(({ neighbor, order }, child_idx) => {
+      const branch = `${bond_symbol(idx, neighbor, order)}${write(neighbor)}`
+      out += child_idx < children[idx].length - 1 ? `(${branch})` : branch
+    })
+    return out
+  }
+  return write(start)
+}
+
+// SMILES of every bonded fragment in a structure (see perceive_topology for bonding)
+export function fragment_smiles(
+  structure: AnyStructure,
+  topology: MolecularTopology = perceive_topology(structure),
+): FragmentSmiles[] {
+  const elements = structure.sites.map((site) => get_majority_element(site) ?? `X`)
+  const bonds_by_site: SmilesBond[][] = structure.sites.map(() => [])
+  const adjacency: { neighbor: number; image: number[] }[][] = structure.sites.map(() => [])
+  for (const { site_idx_1, site_idx_2, order, cell_shift = [0, 0, 0] } of topology.bonds) {
+    bonds_by_site[site_idx_1].push({ neighbor: site_idx_2, order })
+    bonds_by_site[site_idx_2].push({ neighbor: site_idx_1, order })
+    adjacency[site_idx_1].push({ neighbor: site_idx_2, image: cell_shift })
+    adjacency[site_idx_2].push({ neighbor: site_idx_1, image: cell_shift.map((val) => -val) })
+  }
+  return topology.fragments.map((site_indices) => ({
+    site_indices,
+    smiles: is_extended_fragment(site_indices, adjacency)
+      ? null
+      : fragment_to_smiles(elements, site_indices, bonds_by_site),
+  }))
+}
+
+// Canonical SMILES of all finite fragments, sorted and joined with `.`
+export const structure_to_smiles = (
+  structure: AnyStructure,
+  topology?: MolecularTopology,
+): string =>
+  fragment_smiles(structure, topology)
+    .flatMap(({ smiles }) => (smiles === null ? [] : [smiles]))
+    .sort()
+    .join(`.`)
@@ -0,0 +1,93 @@
+// Per-species rendering metadata (colors and radii) to ship alongside a structure, so other
+// viewers draw it like MatterViz without duplicating its element tables. Radii are atomic
+// (what the structure viewer uses), covalent or ionic for the species' oxidation state.
+import type { ColorSchemeName } from '$lib/colors'
+import { ELEMENT_COLOR_SCHEMES } from '$lib/colors'
+import { format_oxi_state } from '$lib/composition/format'
+import type { ElementSymbol } from '$lib/element'
+import { element_by_symbol } from '$lib/element/data'
+import { ionic_radius } from './decoration'
+import type { AnyStructure } from './index'
+
+export type RadiusType = `atomic` | `covalent` | `ionic`
+
+export type SpeciesStyleOptions = {
+  // one of ELEMENT_COLOR_SCHEMES or CPK from the element data (default Vesta)
+  color_scheme?: ColorSchemeName | `CPK`
+  radius_type?: RadiusType // default atomic
+  colors?: Partial<Record<ElementSymbol, string>> // per-element overrides
+  radii?: Partial<Record<ElementSymbol, number>> // per-element overrides in Å
+}
+
+export type SpeciesStyle = {
+  element: ElementSymbol
+  oxidation_state: number // 0 if unknown
+  label: string // element with oxidation state, e.g. Fe+3
+  color: string // hex
+  radius: number // Å
+}
+
+export type SpeciesStyles = {
+  color_scheme: ColorSchemeName | `CPK`
+  radius_type: RadiusType
+  species: SpeciesStyle[] // distinct (element, oxidation state) in order of appearance
+}
+
+const FALLBACK_COLOR = `#808080`
+
+function element_color(element: ElementSymbol, scheme: ColorSchemeName | `CPK`): string {
+  if (scheme !== `CPK`) {
+    const colors: Record<string, string> = ELEMENT_COLOR_SCHEMES[scheme]
+    return colors[element] ?? FALLBACK_COLOR
+  }
+  const cpk = element_by_symbol.get(element)?.[`cpk-hex`]
+  return cpk ? `#${cpk.replace(/^#/, ``)}` : FALLBACK_COLOR
+}
+
+// radius of one species, falling back to the atomic radius and then 1 Å like the viewer.
+// Ionic radii of species without oxidation state are averaged over the tabulated ones.
+function species_radius(
+  element: ElementSymbol,
+  oxidation_state: number,
+  radius_type: RadiusType,
+): number {
+  const data = element_by_symbol.get(element)
+  if (radius_type === `ionic`) return ionic_radius(element, oxidation_state || undefined)
+  if (radius_type === `covalent`) return data?.covalent_radius ?? data?.atomic_radius ?? 1
+  return data?.atomic_radius ?? 1
+}
+
+// Colors and radii of every species in the structure
+export function get_species_styles(
+  structure: AnyStructure,
+  options: SpeciesStyleOptions = {},
+): SpeciesStyles {
+  const { color_scheme = `Vesta`, radius_type = `atomic`, colors = {}, radii = {} } = options
+  if (color_scheme !== `CPK` && !(color_scheme in ELEMENT_COLOR_SCHEMES)) {
+    throw new Error(`Unknown color scheme ${color_scheme}`)
+  }
+  const styles = new Map<string, SpeciesStyle>()
+  for (const site of structure.sites) {
+    for (const { element, oxidation_state = 0 } of site.species) {
+      const label = `${element}${format_oxi_state(oxidation_state)}`
+      if (styles.has(label)) continue
+      styles.set(label, {
+        element,
+        oxidation_state,
+        label,
+        color: colors[element] ?? element_color(element, color_scheme),
+        radius: radii[element] ?? species_radius(element, oxidation_state, radius_type),
+      })
+    }
+  }
+  return { color_scheme, radius_type, species: [...styles.values()] }
+}
+
+// JSON of the structure with its species styles, e.g. for a viewer in another language
+export function structure_to_styled_json_str(
+  structure: AnyStructure,
+  options: SpeciesStyleOptions = {},
+): string {
+  const species_styles = get_species_styles(structure, options)
+  return JSON.stringify({ structure, species_styles }, null, 2)
+}
@@ -0,0 +1,181 @@
+// Classical Heisenberg/Ising Monte Carlo on the magnetic sites of a structure with
+// per-neighbor-shell exchange couplings, for magnetization vs temperature curves and
+// rough Curie/Néel temperature estimates
+import { K_B_EV } from '$lib/constants'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { neighbor_shells } from './coordination'
+
+export type SpinModel = `ising` | `heisenberg`
+export type MagneticOrdering = `ferromagnetic` | `ferrimagnetic` | `antiferromagnetic`
+
+export interface SpinMonteCarloOptions {
+  // exchange J (meV) for the 1st, 2nd, ... neighbor shell of magnetic sites in
+  // H = −Σ_{i<j} J_ij s_i·s_j with unit spins. J > 0 ferromagnetic, J < 0 antiferromagnetic.
+  couplings: number[]
+  temperatures: number[] // K, all > 0
+  model?: SpinModel // default heisenberg
+  n_equilibration?: number // sweeps discarded per temperature (default 500)
+  n_sweeps?: number // sweeps averaged per temperature (default 2000)
+  seed?: number // default 0, runs are reproducible for a given seed
+  moment_key?: string // site property with scalar or vector moments (default magmom)
+  min_moment?: number // sites with smaller |moment| (μB) are non-magnetic (default 0.1)
+  shell_tolerance?: number // Å, distances within it share a shell (default 0.01)
+}
+
+export interface SpinMonteCarloPoint {
+  temperature: number // K
+  magnetization: number // ⟨|Σ m_i s_i|⟩ / N, μB per magnetic site
+  order_parameter: number // ⟨|Σ σ_i s_i|⟩ / N ∈ [0, 1], σ_i = sign of the input moment
+  susceptibility: number // N·var(order_parameter) / k_B·T, 1/meV
+  energy: number // ⟨H⟩ / N, meV per magnetic site
+  heat_capacity: number // var(H) / N·(k_B·T)², k_B per magnetic site
+  acceptance: number // fraction of accepted spin moves
+}
+
+export interface SpinMonteCarloResult {
+  magnetic_sites: number[] // indices into structure.sites
+  ordering: MagneticOrdering // of the collinear input moments used as ground state
+  points: SpinMonteCarloPoint[] // sorted by temperature
+  // temperature of the susceptibility peak (Curie for ferro-, Néel for antiferromagnets),
+  // null when the peak lies at either end of the temperature grid
+  critical_temperature: number | null
+}
+
+// Metropolis Monte Carlo with single-spin moves (uniform on the sphere for Heisenberg,
+// flips for Ising). Magnetic sites and their signs σ_i come from the moments projected on
+// the first non-zero moment, and each temperature (ascending) starts from the previous
+// configuration, the first from the collinear input state. Couplings act within the
+// given cell, so pass a supercell large enough that no site sees its own image.
+export function spin_monte_carlo(
+  structure: AnyStructure,
+  options: SpinMonteCarloOptions,
+): SpinMonteCarloResult {
+  const {
+    couplings,
+    model = `heisenberg`,
+    n_equilibration = 500,
+    n_sweeps = 2000,
+    seed = 0,
+    moment_key = `magmom`,
+    min_moment = 0.1,
+    shell_tolerance = 0.01,
+  } = options
+  const temperatures = [...options.temperatures].sort((t1, t2) => t1 - t2)
+  if (temperatures.length === 0 || !(temperatures[0] > 0)) {
+    throw new Error(`Temperatures must be a non-empty list of positive values`)
+  }
+  if (!(n_sweeps > 0)) throw new Error(`n_sweeps must be > 0, got ${n_sweeps}`)
+
+  const moments = structure.sites.map((site): Vec3 | null => {
+    const value = site.properties?.[moment_key]
+    if (typeof value === `number` && isFinite(value)) return [0, 0, value]
+    const is_vec = Array.isArray(value) && value.length === 3
+    return is_vec && value.every((elem) => typeof elem === `number`) ? (value as Vec3) : null
+  })
+  const magnetic_sites = moments.flatMap((moment, idx) =>
+    moment && Math.hypot(...moment) >= min_moment ? [idx] : [],
+  )
+  if (magnetic_sites.length === 0) {
+    throw new Error(`No sites with |${moment_key}| ≥ ${min_moment} μB`)
+  }
+  const site_moments = magnetic_sites.map((idx) => moments[idx] ?? [0, 0, 0])
+  const axis = site_moments[0]
+  const signs = site_moments.map((moment) => (math.dot(moment, axis) < 0 ? -1 : 1))
+  const sizes = site_moments.map((moment) => Math.hypot(...moment))
+
+  // per magnetic site: summed J to every other magnetic site (all images within a shell)
+  const magnetic: AnyStructure = {
+    ...structure,
+    sites: magnetic_sites.map((idx) => structure.sites[idx]),
+  }
+  const n_spins = magnetic_sites.length
+  const exchange = magnetic_sites.map((_, idx) => {
+    const shells = neighbor_shells(magnetic, idx, couplings.length, shell_tolerance)
+    const summed = new Map<number, number>()
+    shells.forEach(({ neighbors }, shell_idx) => {
+      for (const { site_idx } of neighbors) {
+        if (site_idx === idx) {
+          throw new Error(
+            `Site ${magnetic_sites[idx]} couples to its own periodic image in shell ` +
+              `${shell_idx + 1}, use a larger supercell`,
+          )
+        }
+        summed.set(site_idx, (summed.get(site_idx) ?? 0) + couplings[shell_idx])
+      }
+    })
+    return [...summed].map(([site_idx, coupling]) => ({ site_idx, coupling }))
+  })
+
+  const random = math.mulberry32(seed)
+  const random_unit = (): Vec3 => {
+    const z_val = 2 * random() - 1
+    const phi = 2 * Math.PI * random()
+    const radius = Math.sqrt(1 - z_val * z_val)
+    return [radius * Math.cos(phi), radius * Math.sin(phi), z_val]
+  }
+  const spins: Vec3[] = signs.map((sign) => [0, 0, sign])
+  const local_field = (idx: number): Vec3 => {
+    const field: Vec3 = [0, 0, 0]
+    for (const { site_idx, coupling } of exchange[idx]) {
+      for (let dim = 0; dim < 3; dim++) field[dim] += coupling * spins[site_idx][dim]
+    }
+    return field
+  }
+
+  const points = temperatures.map((temperature): SpinMonteCarloPoint => {
+    const k_t = 1000 * K_B_EV * temperature // meV, like the couplings
+    let [accepted, sum_m, sum_q, sum_q2, sum_e, sum_e2] = [0, 0, 0, 0, 0, 0]
+    for (let sweep = 0; sweep < n_equilibration + n_sweeps; sweep++) {
+      for (let idx = 0; idx < n_spins; idx++) {
+        const spin = spins[idx]
+        const proposal = model === `ising` ? math.scale(spin, -1) : random_unit()
+        const delta_e = -math.dot(math.subtract(proposal, spin), local_field(idx))
+        if (delta_e <= 0 || random() < Math.exp(-delta_e / k_t)) {
+          spins[idx] = proposal
+          if (sweep >= n_equilibration) accepted++
+        }
+      }
+      if (sweep < n_equilibration) continue
+      const moment = math.add(...spins.map((spin, idx) => math.scale(spin, sizes[idx])))
+      const staggered = math.add(...spins.map((spin, idx) => math.scale(spin, signs[idx])))
+      const order = Math.hypot(...staggered) / n_spins
+      const energy =
+        -0.5 * spins.reduce((sum, spin, idx) => sum + math.dot(spin, local_field(idx)), 0)
+      sum_m += Math.hypot(...moment) / n_spins
+      sum_q += order
+      sum_q2 += order * order
+      sum_e += energy
+      sum_e2 += energy * energy
+    }
+    const order_parameter = sum_q / n_sweeps
+    const mean_e = sum_e / n_sweeps
+    return {
+      temperature,
+      magnetization: sum_m / n_sweeps,
+      order_parameter,
+      susceptibility: (n_spins * (sum_q2 / n_sweeps - order_parameter ** 2)) / k_t,
+      energy: mean_e / n_spins,
+      heat_capacity: (sum_e2 / n_sweeps - mean_e ** 2) / (n_spins * k_t * k_t),
+      acceptance: accepted / (n_sweeps * n_spins),
+    }
+  })
+
+  const net = Math.abs(signs.reduce((sum, sign, idx) => sum + sign * sizes[idx], 0))
+  const total = sizes.reduce((sum, size) => sum + size, 0)
+  const ordering: MagneticOrdering = signs.every((sign) => sign === 1)
+    ? `ferromagnetic`
+    : net < 1e-6 * total
+      ? `antiferromagnetic`
+      : `ferrimagnetic`
+
+  const peak_idx = points.reduce(
+    (best, point, idx) => (point.susceptibility > points[best].susceptibility ? idx : best),
+    0,
+  )
+  const is_interior = peak_idx > 0 && peak_idx < points.length - 1
+  const critical_temperature = is_interior ? points[peak_idx].temperature : null
+
+  return { magnetic_sites, ordering, points, critical_temperature }
+}
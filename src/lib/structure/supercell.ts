@@ -1,9 +1,10 @@
 // Supercell generation utilities for Crystal
-import type { Vec3 } from '$lib/math'
+import type { Matrix3x3, Vec3 } from '$lib/math'
 import * as math from '$lib/math'
 import type { Crystal, Site, StructureBond } from './index'
 import { wrap_frac_coord } from './pbc'
-import { normalize_structure_bond } from './bonding'
+import { replicate_site_fields, select_site_fields } from './site-fields'
+import { get_majority_element, normalize_structure_bond } from './bonding'
 
 type SupercellType = Crystal & {
   supercell_scaling?: Vec3
@@ -187,16 +188,16 @@ export function make_supercell(
     }
   }
 
+  const { bonds, site_fields } = structure.properties ?? {}
   const properties =
-    structure.properties?.bonds === undefined
+    bonds === undefined && site_fields === undefined
       ? structure.properties
       : {
           ...structure.properties,
-          bonds: replicate_bonds_for_supercell(
-            structure.properties.bonds,
-            n_sites,
-            supercell_scaling,
-          ),
+          ...(bonds && {
+            bonds: replicate_bonds_for_supercell(bonds, n_sites, supercell_scaling),
+          }),
+          ...(site_fields && { site_fields: replicate_site_fields(site_fields, total_cells) }),
         }
 
   return {
@@ -219,3 +220,280 @@ export function is_valid_supercell_input(input: string): boolean {
     return false
   }
 }
+
+export interface DiscretizeOccupanciesOptions {
+  tolerance?: number // max allowed |achieved - target| occupancy per species (default 0.01)
+  max_atoms?: number // atom budget: unit cell sites × number of cells (default 200)
+  max_scaling?: number // max supercell factor along each axis (default 4)
+}
+
+export interface DiscretizedSiteOccupancy {
+  site_idx: number // index into the unit cell's sites
+  species: { element: string; target: number; achieved: number; count: number }[]
+  error: number // largest |achieved - target| over the site's species
+}
+
+export interface OccupancyDiscretization {
+  scaling: Vec3
+  n_cells: number
+  structure: Crystal // supercell with occupancies rounded to multiples of 1 / n_cells
+  sites: DiscretizedSiteOccupancy[] // partially occupied unit cell sites only
+  max_error: number
+  within_tolerance: boolean
+}
+
+const OCCUPANCY_EPS = 1e-8
+
+// Round the species occupancies of one site to counts out of n_cells, never exceeding
+// n_cells in total (round-half-up can overshoot, e.g. 0.5/0.5 on 3 cells).
+const discretize_site_occupancy = (
+  site: Site,
+  site_idx: number,
+  n_cells: number,
+): DiscretizedSiteOccupancy => {
+  const counts = site.species.map(({ occu }) => Math.round(occu * n_cells))
+  const overshoot = (idx: number): number => counts[idx] - site.species[idx].occu * n_cells
+  let excess = counts.reduce((sum, count) => sum + count, 0) - n_cells
+  while (excess-- > 0) {
+    // undo the rounding that overshot the most
+    let worst_idx = 0
+    for (let idx = 1; idx < counts.length; idx++) {
+      if (overshoot(idx) > overshoot(worst_idx)) worst_idx = idx
+    }
+    counts[worst_idx]--
+  }
+  const species = site.species.map(({ element, occu }, idx) => ({
+    element,
+    target: occu,
+    achieved: counts[idx] / n_cells,
+    count: counts[idx],
+  }))
+  const error = Math.max(...species.map(({ target, achieved }) => Math.abs(achieved - target)))
+  return { site_idx, species, error }
+}
+
+// Find the smallest supercell (within the atom budget) in which every partial occupancy
+// becomes a whole number of atoms to within `tolerance`, e.g. 0.437 → 7/16 in a 4×2×2
+// cell for tolerance 1e-3. Among supercells with the same number of cells, the one whose
+// edge lengths are most similar wins. If none meets the tolerance, the candidate with the
+// smallest max error is returned with within_tolerance = false. Rounded occupancies are
+// applied to the returned supercell (species that round to 0 are dropped, as are sites
+// left empty) so it can be handed to an ordering/enumeration step.
+export function discretize_occupancies(
+  structure: Crystal,
+  options: DiscretizeOccupanciesOptions = {},
+): OccupancyDiscretization {
+  const { tolerance = 0.01, max_atoms = 200, max_scaling = 4 } = options
+  if (!structure.lattice) {
+    throw new Error(`Cannot discretize occupancies: structure has no lattice`)
+  }
+  if (!(tolerance >= 0)) throw new Error(`tolerance must be non-negative, got ${tolerance}`)
+  if (!Number.isInteger(max_scaling) || max_scaling < 1) {
+    throw new Error(`max_scaling must be a positive integer, got ${max_scaling}`)
+  }
+
+  const partial_indices = structure.sites.flatMap((site, idx) =>
+    site.species.some(({ occu }) => occu < 1 - OCCUPANCY_EPS) ? [idx] : [],
+  )
+  const n_sites = structure.sites.length
+  const { a, b, c } = structure.lattice
+  // ratio of longest to shortest supercell edge (1 = equal edge lengths)
+  const anisotropy = ([sx, sy, sz]: Vec3): number => {
+    const edges = [sx * a, sy * b, sz * c]
+    return Math.max(...edges) / Math.min(...edges)
+  }
+
+  // Most isotropic scaling per cell count. Axes are enumerated from large to small
+  // factors so ties favor stretching a before b before c.
+  const scaling_by_n_cells = new Map<number, Vec3>()
+  for (let sx = max_scaling; sx >= 1; sx--) {
+    for (let sy = max_scaling; sy >= 1; sy--) {
+      for (let sz = max_scaling; sz >= 1; sz--) {
+        const n_cells = sx * sy * sz
+        if (n_cells > 1 && n_cells * n_sites > max_atoms) continue
+        const prev = scaling_by_n_cells.get(n_cells)
+        if (!prev || anisotropy([sx, sy, sz]) < anisotropy(prev) - 1e-9) {
+          scaling_by_n_cells.set(n_cells, [sx, sy, sz])
+        }
+      }
+    }
+  }
+
+  let best: { n_cells: number; sites: DiscretizedSiteOccupancy[]; max_error: number } | null =
+    null
+  for (const n_cells of [...scaling_by_n_cells.keys()].sort((n1, n2) => n1 - n2)) {
+    const sites = partial_indices.map((idx) =>
+      discretize_site_occupancy(structure.sites[idx], idx, n_cells),
+    )
+    const max_error = Math.max(0, ...sites.map(({ error }) => error))
+    if (!best || max_error < best.max_error - OCCUPANCY_EPS) {
+      best = { n_cells, sites, max_error }
+    }
+    if (max_error <= tolerance + OCCUPANCY_EPS) break
+  }
+  // 1x1x1 is always a candidate, so best is set
+  const { n_cells, sites, max_error } = best as NonNullable<typeof best>
+  const scaling = scaling_by_n_cells.get(n_cells) as Vec3
+
+  const supercell = make_supercell(structure, scaling)
+  const achieved_by_site = new Map(sites.map((site) => [site.site_idx, site]))
+  const kept_indices: number[] = []
+  const discretized_sites = supercell.sites.flatMap((site, idx) => {
+    const discretized = achieved_by_site.get(idx % n_sites)
+    if (!discretized) {
+      kept_indices.push(idx)
+      return [site]
+    }
+    const species = site.species
+      .map((specie, spec_idx) => ({ ...specie, occu: discretized.species[spec_idx].achieved }))
+      .filter(({ occu }) => occu > 0)
+    if (species.length === 0) return []
+    kept_indices.push(idx)
+    return [{ ...site, species }]
+  })
+
+  // bond site indices no longer line up once vacant sites were dropped, site fields
+  // are cut down to the remaining sites
+  const { bonds, site_fields } = supercell.properties ?? {}
+  const dropped = discretized_sites.length < supercell.sites.length
+  const properties =
+    dropped && (bonds || site_fields)
+      ? {
+          ...supercell.properties,
+          ...(bonds && { bonds: undefined }),
+          ...(site_fields && { site_fields: select_site_fields(site_fields, kept_indices) }),
+        }
+      : supercell.properties
+
+  return {
+    scaling,
+    n_cells,
+    structure: { ...supercell, sites: discretized_sites, properties },
+    sites,
+    max_error,
+    within_tolerance: max_error <= tolerance + OCCUPANCY_EPS,
+  }
+}
+
+export interface SupercellFitOptions {
+  // allow the supercell to be strained relative to the unit cell, e.g. by thermal expansion
+  // in MD, fitting an anisotropic deformation (default false)
+  scale?: boolean
+  // max relative volume mismatch and Green-Lagrange strain without scale (default 0.02)
+  ltol?: number
+  max_strain?: number // max Green-Lagrange strain component with scale (default 0.2)
+  // Å, max distance of a supercell site to its unit-cell image after undoing the strain
+  // (default 0.3)
+  stol?: number
+}
+
+export interface SupercellFit {
+  supercell_matrix: Matrix3x3 // integer M, supercell lattice rows ≈ M · unit cell rows
+  n_cells: number // det(M)
+  volume_ratio: number // supercell over unit cell volume, non-integer for strained cells
+  // Cartesian deformation gradient F taking the unstrained supercell M · unit cell onto the
+  // given one
+  deformation: Matrix3x3
+  axis_scaling: Vec3 // stretch of the unit cell vectors a, b, c under the deformation
+  rms_displacement: number // Å, over supercell sites after undoing the deformation
+  max_displacement: number // Å
+}
+
+// Fit a supercell to a unit cell: infer the integer supercell matrix from the volume ratio
+// and the lattices, then check every supercell site lies on a unit-cell site of the same
+// element (up to a global translation). With scale, the volume ratio is rounded to the
+// nearest number of cells and the residual strain is fitted as a general deformation, so
+// thermally expanded or anisotropically relaxed supercells still match. Lattices need the
+// same Cartesian orientation. Returns null if no fit is found within tolerance.
+export function fit_supercell(
+  unit_cell: Crystal,
+  supercell: Crystal,
+  options: SupercellFitOptions = {},
+): SupercellFit | null {
+  const { scale = false, ltol = 0.02, max_strain = 0.2, stol = 0.3 } = options
+  const unit_matrix = unit_cell.lattice.matrix
+  const super_matrix = supercell.lattice.matrix
+  const volume_ratio = Math.abs(math.det_3x3(super_matrix) / math.det_3x3(unit_matrix))
+  const n_cells = Math.round(volume_ratio)
+  if (n_cells < 1 || (!scale && Math.abs(volume_ratio / n_cells - 1) > ltol)) return null
+  if (supercell.sites.length !== n_cells * unit_cell.sites.length) return null
+
+  // remove isotropic expansion before rounding so large supercells round correctly
+  const iso_scale = scale ? Math.cbrt(volume_ratio / n_cells) : 1
+  const supercell_matrix = math
+    .dot(super_matrix, math.matrix_inverse_3x3(unit_matrix))
+    .map((row) => row.map((val) => Math.round(val / iso_scale) || 0)) as Matrix3x3
+  if (Math.round(math.det_3x3(supercell_matrix)) !== n_cells) return null
+
+  // rows: super_matrix = M · unit_matrix · Fᵀ
+  const ideal_matrix = math.dot(supercell_matrix, unit_matrix)
+  const deformation = math.transpose_3x3_matrix(
+    math.dot(math.matrix_inverse_3x3(ideal_matrix), super_matrix),
+  )
+  const strain = math
+    .dot(math.transpose_3x3_matrix(deformation), deformation)
+    .map((row, idx) => row.map((val, jdx) => (val - (idx === jdx ? 1 : 0)) / 2))
+  const max_strain_comp = Math.max(...strain.flat().map(Math.abs))
+  if (max_strain_comp > (scale ? max_strain : ltol)) return null
+  const axis_scaling = unit_matrix.map(
+    (vec) => Math.hypot(...math.mat3x3_vec3_multiply(deformation, vec)) / Math.hypot(...vec),
+  ) as Vec3
+
+  // supercell sites mapped back into the unstrained frame, paired with unit-cell sites
+  const inv_deformation = math.matrix_inverse_3x3(deformation)
+  const positions = supercell.sites.map(({ xyz }) =>
+    math.mat3x3_vec3_multiply(inv_deformation, xyz),
+  )
+  const unit_elements = unit_cell.sites.map(get_majority_element)
+  const super_elements = supercell.sites.map(get_majority_element)
+  const converters = math.create_lattice_converters(unit_matrix)
+  const anchor = super_elements.indexOf(unit_elements[0])
+  if (anchor === -1) return null
+
+  // squared distances of shifted sites to their nearest same-element unit-cell site, null
+  // if one is beyond stol or some unit-cell site doesn't get one image per cell
+  const score_shift = (shift: Vec3): { sum_sq: number; max_dist: number } | null => {
+    const counts = unit_cell.sites.map(() => 0)
+    let [sum_sq, max_dist] = [0, 0]
+    for (const [site_idx, pos] of positions.entries()) {
+      const shifted = math.add(pos, shift)
+      let [nearest, nearest_dist] = [-1, Infinity]
+      for (const [unit_idx, unit_site] of unit_cell.sites.entries()) {
+        if (unit_elements[unit_idx] !== super_elements[site_idx]) continue
+        const disp = math.min_image_displacement(
+          unit_site.xyz,
+          shifted,
+          unit_matrix,
+          converters,
+          unit_cell.lattice.pbc,
+        )
+        const dist = Math.hypot(...disp)
+        if (dist < nearest_dist) [nearest, nearest_dist] = [unit_idx, dist]
+      }
+      if (nearest === -1 || nearest_dist > stol) return null
+      counts[nearest]++
+      sum_sq += nearest_dist ** 2
+      max_dist = Math.max(max_dist, nearest_dist)
+    }
+    return counts.every((count) => count === n_cells) ? { sum_sq, max_dist } : null
+  }
+
+  // try every translation putting the anchor site onto a unit-cell site of its element
+  let best: { sum_sq: number; max_dist: number } | null = null
+  for (const [ref_idx, ref_site] of unit_cell.sites.entries()) {
+    if (unit_elements[ref_idx] !== unit_elements[0]) continue
+    const score = score_shift(math.subtract(ref_site.xyz, positions[anchor]))
+    if (score && (!best || score.sum_sq < best.sum_sq)) best = score
+  }
+  if (!best) return null
+  const { sum_sq, max_dist } = best
+  return {
+    supercell_matrix,
+    n_cells,
+    volume_ratio,
+    deformation,
+    axis_scaling,
+    rms_displacement: Math.sqrt(sum_sq / positions.length),
+    max_displacement: max_dist,
+  }
+}
@@ -0,0 +1,153 @@
+// Molecular topology perception: covalent bonds (with perceived bond orders), angles,
+// proper/improper dihedrals and connected fragments, e.g. for bonded force fields or
+// LAMMPS `full` data files
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure, BondOrder, BondPair, StructureBond } from '$lib/structure'
+import { perceive_bond_orders } from './bond-order-perception'
+import { compute_bond_transform, covalent_radii, get_majority_element } from './bonding'
+import { get_neighbor_list } from './neighbors'
+
+export type TopologyBond = StructureBond & { length: number }
+export type Dihedral = [number, number, number, number]
+
+export interface MolecularTopology {
+  bonds: TopologyBond[] // cell_shift set for bonds to periodic images
+  angles: Vec3[] // [end, center, end] site indices
+  dihedrals: Dihedral[] // [i, j, k, l] for the torsion around bond j-k
+  impropers: Dihedral[] // [center, n1, n2, n3] for every 3-coordinate atom
+  fragments: number[][] // site indices of each bonded fragment (molecule)
+  molecule_ids: number[] // per-site index into fragments
+}
+
+export interface TopologyOptions {
+  tolerance?: number // Å added to the sum of covalent radii (default 0.45, as Open Babel)
+  min_distance?: number // shorter contacts are overlaps, not bonds (default 0.4 Å)
+  perceive_orders?: boolean // run bond-order perception, else all bonds are single
+  total_charge?: number // forwarded to bond-order perception
+}
+
+// Bonded neighbor of an atom: site index plus lattice image relative to the atom's cell
+type BondedNeighbor = { site_idx: number; image: Vec3 }
+
+const same_image = (img_1: Vec3, img_2: Vec3): boolean =>
+  img_1[0] === img_2[0] && img_1[1] === img_2[1] && img_1[2] === img_2[2]
+
+// Keep one direction of each neighbor pair (the other is its mirror with negated image)
+const is_canonical_pair = (idx_1: number, idx_2: number, image: Vec3): boolean => {
+  if (idx_1 !== idx_2) return idx_1 < idx_2
+  const first_nonzero = image.find((shift) => shift !== 0) ?? 0
+  return first_nonzero > 0
+}
+
+// Bonds from covalent radii (d_ij ≤ r_i + r_j + tolerance) including bonds across periodic
+// boundaries, then angles, dihedrals and impropers enumerated over the bond graph. Sites
+// of elements without a covalent radius stay unbonded.
+export function perceive_topology(
+  structure: AnyStructure,
+  options: TopologyOptions = {},
+): MolecularTopology {
+  const { tolerance = 0.45, min_distance = 0.4, perceive_orders = true } = options
+  const { sites } = structure
+  const radii = sites.map((site) => covalent_radii.get(get_majority_element(site) ?? ``) ?? 0)
+  const max_radius = Math.max(0, ...radii)
+
+  const bond_pairs: BondPair[] = []
+  if (max_radius > 0) {
+    const neighbor_list = get_neighbor_list(structure, 2 * max_radius + tolerance)
+    neighbor_list.forEach((neighbors, idx_1) => {
+      if (radii[idx_1] === 0) return
+      for (const { site_idx: idx_2, distance, displacement, image } of neighbors) {
+        if (radii[idx_2] === 0 || !is_canonical_pair(idx_1, idx_2, image)) continue
+        if (distance < min_distance || distance > radii[idx_1] + radii[idx_2] + tolerance) {
+          continue
+        }
+        const pos_1 = sites[idx_1].xyz
+        const pos_2 = math.add(pos_1, displacement)
+        bond_pairs.push({
+          pos_1,
+          pos_2,
+          site_idx_1: idx_1,
+          site_idx_2: idx_2,
+          bond_length: distance,
+          strength: 1,
+          cell_shift: image,
+          transform_matrix: compute_bond_transform(pos_1, pos_2),
+        })
+      }
+    })
+  }
+
+  const orders: BondOrder[] = perceive_orders
+    ? perceive_bond_orders(sites, bond_pairs, { total_charge: options.total_charge }).map(
+        ({ bond_order }) => bond_order,
+      )
+    : bond_pairs.map(() => 1)
+  const bonds: TopologyBond[] = bond_pairs.map((pair, idx) => ({
+    site_idx_1: pair.site_idx_1,
+    site_idx_2: pair.site_idx_2,
+    order: orders[idx],
+    length: pair.bond_length,
+    // + 0 turns the -0 shifts of mirrored images into plain zeros
+    ...(pair.cell_shift?.some(Boolean) && {
+      cell_shift: pair.cell_shift.map((shift) => shift + 0) as Vec3,
+    }),
+  }))
+
+  const adjacency: BondedNeighbor[][] = sites.map(() => [])
+  for (const { site_idx_1, site_idx_2, cell_shift = [0, 0, 0] } of bonds) {
+    adjacency[site_idx_1].push({ site_idx: site_idx_2, image: cell_shift })
+    adjacency[site_idx_2].push({ site_idx: site_idx_1, image: math.scale(cell_shift, -1) })
+  }
+
+  const angles: Vec3[] = []
+  const impropers: Dihedral[] = []
+  adjacency.forEach((neighbors, center) => {
+    for (let n1 = 0; n1 < neighbors.length; n1++) {
+      for (let n2 = n1 + 1; n2 < neighbors.length; n2++) {
+        angles.push([neighbors[n1].site_idx, center, neighbors[n2].site_idx])
+      }
+    }
+    if (neighbors.length === 3) {
+      const [nb1, nb2, nb3] = neighbors.map(({ site_idx }) => site_idx)
+      impropers.push([center, nb1, nb2, nb3])
+    }
+  })
+
+  // One torsion per (i, l) pair around each bond j-k. Images are tracked relative to j's
+  // cell so 3-membered rings (i = l) and small periodic cells are handled correctly.
+  const dihedrals: Dihedral[] = []
+  for (const { site_idx_1: jj, site_idx_2: kk, cell_shift = [0, 0, 0] } of bonds) {
+    for (const nb_i of adjacency[jj]) {
+      if (nb_i.site_idx === kk && same_image(nb_i.image, cell_shift)) continue
+      for (const nb_l of adjacency[kk]) {
+        const l_image = math.add(nb_l.image, cell_shift)
+        if (nb_l.site_idx === jj && same_image(l_image, [0, 0, 0])) continue
+        if (nb_l.site_idx === nb_i.site_idx && same_image(l_image, nb_i.image)) continue
+        dihedrals.push([nb_i.site_idx, jj, kk, nb_l.site_idx])
+      }
+    }
+  }
+
+  // Connected components of the bond graph (periodic images join the same fragment)
+  const molecule_ids = sites.map(() => -1)
+  const fragments: number[][] = []
+  for (let start = 0; start < sites.length; start++) {
+    if (molecule_ids[start] !== -1) continue
+    const fragment: number[] = []
+    const stack = [start]
+    molecule_ids[start] = fragments.length
+    while (stack.length > 0) {
+      const site_idx = stack.pop() as number
+      fragment.push(site_idx)
+      for (const { site_idx: nb_idx } of adjacency[site_idx]) {
+        if (molecule_ids[nb_idx] !== -1) continue
+        molecule_ids[nb_idx] = fragments.length
+        stack.push(nb_idx)
+      }
+    }
+    fragments.push(fragment.sort((idx_1, idx_2) => idx_1 - idx_2))
+  }
+
+  return { bonds, angles, dihedrals, impropers, fragments, molecule_ids }
+}
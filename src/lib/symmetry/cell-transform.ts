@@ -10,8 +10,10 @@ export type CellType = `original` | `conventional` | `primitive`
 
 const transferable_properties = (properties: Crystal[`properties`]): Crystal[`properties`] => {
   if (properties === undefined) return undefined
+  // bonds and site fields are indexed by site, which don't map onto the transformed cell
   const rest = { ...properties }
   delete rest.bonds
+  delete rest.site_fields
   return Object.keys(rest).length > 0 ? rest : undefined
 }
 
@@ -0,0 +1,325 @@
+// Continuous symmetry measures: how far a structure is from a target space group (e.g. how
+// "almost cubic" a relaxed perovskite is), continuous shape measures of local environments
+// against ideal polyhedra (Pinsky & Avnir, 0 = ideal, 100 = maximally distorted) and
+// chirality measures/flags
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import { get_majority_element } from '$lib/structure/bonding'
+import { get_neighbor_list } from '$lib/structure/neighbors'
+import type { SymmetryOperation } from './distortion'
+import { mat3_from_flat_col_major } from './symmetry-elements'
+
+const sqrt3 = Math.sqrt(3)
+// trigonal prism on the unit sphere with all edges equal
+const [prism_rho, prism_h] = [2 / Math.sqrt(7), Math.sqrt(3 / 7)]
+const triangle: Vec3[] = [[1, 0, 0], [-0.5, sqrt3 / 2, 0], [-0.5, -sqrt3 / 2, 0]]
+
+// Ideal reference polyhedra (vertex positions around the center) by coordination number
+export const IDEAL_POLYHEDRA: Record<string, Vec3[]> = {
+  linear: [[0, 0, 1], [0, 0, -1]],
+  trigonal_planar: [...triangle],
+  tetrahedron: [[1, 1, 1], [1, -1, -1], [-1, 1, -1], [-1, -1, 1]],
+  square_planar: [[1, 0, 0], [0, 1, 0], [-1, 0, 0], [0, -1, 0]],
+  trigonal_bipyramid: [...triangle, [0, 0, 1], [0, 0, -1]],
+  square_pyramid: [[1, 0, 0], [0, 1, 0], [-1, 0, 0], [0, -1, 0], [0, 0, 1]],
+  octahedron: [[1, 0, 0], [-1, 0, 0], [0, 1, 0], [0, -1, 0], [0, 0, 1], [0, 0, -1]],
+  trigonal_prism: [1, -1].flatMap((z_sign) =>
+    [0, 1, 2].map((idx): Vec3 => {
+      const angle = (2 * Math.PI * idx) / 3
+      return [prism_rho * Math.cos(angle), prism_rho * Math.sin(angle), z_sign * prism_h]
+    }),
+  ),
+  cube: [1, -1].flatMap((x) => [1, -1].flatMap((y) => [1, -1].map((z): Vec3 => [x, y, z]))),
+  cuboctahedron: [1, -1].flatMap((s1) =>
+    [1, -1].flatMap((s2): Vec3[] => [[s1, s2, 0], [s1, 0, s2], [0, s1, s2]]),
+  ),
+}
+
+// Eigen-decomposition of a small symmetric matrix by cyclic Jacobi rotations. Returns
+// eigenvalues and eigenvectors (columns of vectors[i][k] = component i of vector k).
+export function jacobi_eigen(matrix: readonly (readonly number[])[]): {
+  values: number[]
+  vectors: number[][]
+} {
+  const size = matrix.length
+  const mat = matrix.map((row) => [...row])
+  const vectors = mat.map((_, row) => mat.map((_, col) => Number(row === col)))
+  for (let sweep = 0; sweep < 50; sweep++) {
+    let off_diag = 0
+    for (let pp = 0; pp < size; pp++) {
+      for (let qq = pp + 1; qq < size; qq++) off_diag += mat[pp][qq] ** 2
+    }
+    if (off_diag < 1e-22) break
+    for (let pp = 0; pp < size; pp++) {
+      for (let qq = pp + 1; qq < size; qq++) {
+        if (Math.abs(mat[pp][qq]) < 1e-300) continue
+        const theta = (mat[qq][qq] - mat[pp][pp]) / (2 * mat[pp][qq])
+        const tan = Math.sign(theta || 1) / (Math.abs(theta) + Math.sqrt(theta * theta + 1))
+        const cos = 1 / Math.sqrt(tan * tan + 1)
+        const sin = tan * cos
+        for (let kk = 0; kk < size; kk++) {
+          const [m_kp, m_kq] = [mat[kk][pp], mat[kk][qq]]
+          mat[kk][pp] = cos * m_kp - sin * m_kq
+          mat[kk][qq] = sin * m_kp + cos * m_kq
+        }
+        for (let kk = 0; kk < size; kk++) {
+          const [m_pk, m_qk] = [mat[pp][kk], mat[qq][kk]]
+          mat[pp][kk] = cos * m_pk - sin * m_qk
+          mat[qq][kk] = sin * m_pk + cos * m_qk
+        }
+        for (let kk = 0; kk < size; kk++) {
+          const [v_kp, v_kq] = [vectors[kk][pp], vectors[kk][qq]]
+          vectors[kk][pp] = cos * v_kp - sin * v_kq
+          vectors[kk][qq] = sin * v_kp + cos * v_kq
+        }
+      }
+    }
+  }
+  return { values: mat.map((row, idx) => row[idx]), vectors }
+}
+
+// Proper rotation R maximizing Σ targets_i · R sources_i (Horn's quaternion method).
+// Returns R and the maximum.
+export function optimal_rotation(
+  sources: Vec3[],
+  targets: Vec3[],
+): { rotation: Matrix3x3; overlap: number } {
+  const corr = [0, 1, 2].map((row) =>
+    [0, 1, 2].map((col) =>
+      sources.reduce((sum, src, idx) => sum + src[row] * targets[idx][col], 0),
+    ),
+  )
+  const [[sxx, sxy, sxz], [syx, syy, syz], [szx, szy, szz]] = corr
+  const { values, vectors } = jacobi_eigen([
+    [sxx + syy + szz, syz - szy, szx - sxz, sxy - syx],
+    [syz - szy, sxx - syy - szz, sxy + syx, szx + sxz],
+    [szx - sxz, sxy + syx, -sxx + syy - szz, syz + szy],
+    [sxy - syx, szx + sxz, syz + szy, -sxx - syy + szz],
+  ])
+  const best = values.indexOf(Math.max(...values))
+  const [qw, qx, qy, qz] = vectors.map((row) => row[best])
+  const rotation: Matrix3x3 = [
+    [qw * qw + qx * qx - qy * qy - qz * qz, 2 * (qx * qy - qw * qz), 2 * (qx * qz + qw * qy)],
+    [2 * (qx * qy + qw * qz), qw * qw - qx * qx + qy * qy - qz * qz, 2 * (qy * qz - qw * qx)],
+    [2 * (qx * qz - qw * qy), 2 * (qy * qz + qw * qx), qw * qw - qx * qx - qy * qy + qz * qz],
+  ]
+  return { rotation, overlap: values[best] }
+}
+
+// Minimum-cost perfect matching of a square cost matrix (Hungarian algorithm, O(n³)).
+// Returns the column assigned to each row.
+export function min_cost_assignment(cost: number[][]): number[] {
+  const size = cost.length
+  const [row_pot, col_pot] = [Array(size + 1).fill(0), Array(size + 1).fill(0)]
+  const col_row = Array(size + 1).fill(0) // 1-based row matched to each column
+  const way = Array(size + 1).fill(0)
+  for (let row = 1; row <= size; row++) {
+    col_row[0] = row
+    let col_0 = 0
+    const min_val = Array(size + 1).fill(Infinity)
+    const used = Array(size + 1).fill(false)
+    do {
+      used[col_0] = true
+      const row_0 = col_row[col_0]
+      let [delta, col_1] = [Infinity, 0]
+      for (let col = 1; col <= size; col++) {
+        if (used[col]) continue
+        const reduced = cost[row_0 - 1][col - 1] - row_pot[row_0] - col_pot[col]
+        if (reduced < min_val[col]) [min_val[col], way[col]] = [reduced, col_0]
+        if (min_val[col] < delta) [delta, col_1] = [min_val[col], col]
+      }
+      for (let col = 0; col <= size; col++) {
+        if (used[col]) {
+          row_pot[col_row[col]] += delta
+          col_pot[col] -= delta
+        } else min_val[col] -= delta
+      }
+      col_0 = col_1
+    } while (col_row[col_0] !== 0)
+    do {
+      const col_1 = way[col_0]
+      col_row[col_0] = col_row[col_1]
+      col_0 = col_1
+    } while (col_0 !== 0)
+  }
+  const assignment = Array(size).fill(-1)
+  for (let col = 1; col <= size; col++) assignment[col_row[col] - 1] = col - 1
+  return assignment
+}
+
+// Points shifted so their centroid is at the origin
+export const centered = (points: readonly Vec3[]): Vec3[] => {
+  const centroid = math.scale(math.add(...points), 1 / points.length)
+  return points.map((point) => math.subtract(point, centroid))
+}
+
+// Orthonormal frame (as matrix columns) with first axis along vec_1 and vec_2 in the
+// plane of the first two axes (any perpendicular axes if vec_2 is null or collinear)
+export function frame_from(vec_1: Vec3, vec_2: Vec3 | null): Matrix3x3 {
+  const e1 = math.normalize_vec(vec_1)
+  const normal = vec_2 ? math.cross_3d(e1, vec_2) : [0, 0, 0]
+  const e3 =
+    Math.hypot(...normal) > 1e-8
+      ? math.normalize_vec(normal as Vec3)
+      : math.compute_in_plane_basis(e1)[0]
+  const e2 = math.cross_3d(e3, e1)
+  return [0, 1, 2].map((row) => [e1[row], e2[row], e3[row]]) as Matrix3x3
+}
+
+// Continuous shape measure S = 100·(1 − max (Σ q_i·R r_π(i))² / (Σ|q|² Σ|r|²)) of
+// points q relative to a reference shape r, minimized over rotation, uniform scaling and
+// vertex permutations (both centered on their centroids). Permutations come from the
+// Hungarian assignment after seeding the rotation with every pair of reference vertices,
+// then alternating assignment and optimal rotation until the matching is stable.
+export function shape_measure(points: readonly Vec3[], reference: readonly Vec3[]): number {
+  if (points.length !== reference.length) {
+    const counts = `${points.length} vs ${reference.length}`
+    throw new Error(`Shape measure needs equal point counts, got ${counts}`)
+  }
+  if (points.length < 2) return 0
+  const [qs, rs] = [centered(points), centered(reference)]
+  const norm_sq = (vecs: Vec3[]) => vecs.reduce((sum, vec) => sum + math.dot(vec, vec), 0)
+  const [q_norm, r_norm] = [norm_sq(qs), norm_sq(rs)]
+  if (q_norm < 1e-12 || r_norm < 1e-12) return 0
+
+  const lengths = qs.map((vec) => Math.hypot(...vec))
+  const anchor = lengths.indexOf(Math.max(...lengths))
+  const cross_norms = qs.map((vec) => Math.hypot(...math.cross_3d(qs[anchor], vec)))
+  const partner = cross_norms.indexOf(Math.max(...cross_norms))
+  const has_partner = cross_norms[partner] > 1e-8 * lengths[anchor] ** 2
+  const q_frame = frame_from(qs[anchor], has_partner ? qs[partner] : null)
+
+  let best_overlap = -Infinity
+  const tried = new Set<string>()
+  for (let ref_1 = 0; ref_1 < rs.length; ref_1++) {
+    if (Math.hypot(...rs[ref_1]) < 1e-8) continue
+    for (let ref_2 = 0; ref_2 < rs.length; ref_2++) {
+      if (ref_2 === ref_1 && has_partner) continue
+      const r_frame = frame_from(rs[ref_1], has_partner ? rs[ref_2] : null)
+      let rotation = math.dot(q_frame, math.transpose_3x3_matrix(r_frame))
+      for (let iter = 0; iter < 10; iter++) {
+        const rotated = rs.map((vec) => math.mat3x3_vec3_multiply(rotation, vec))
+        const perm = min_cost_assignment(qs.map((q) => rotated.map((r) => -math.dot(q, r))))
+        const key = perm.join(`,`)
+        if (tried.has(key)) break
+        tried.add(key)
+        const fit = optimal_rotation(perm.map((ref_idx) => rs[ref_idx]), qs)
+        best_overlap = Math.max(best_overlap, fit.overlap)
+        rotation = fit.rotation
+      }
+      if (!has_partner) break
+    }
+  }
+  const overlap = Math.max(0, best_overlap)
+  return Math.max(0, 100 * (1 - (overlap * overlap) / (q_norm * r_norm)))
+}
+
+// Shape measure between a point set and its mirror image: 0 for achiral arrangements,
+// growing with how far no rotation can superimpose the two
+export const chirality_measure = (points: readonly Vec3[]): number =>
+  shape_measure(points, points.map((point) => math.scale(point, -1)))
+
+export const is_chiral_environment = (points: readonly Vec3[], tolerance = 0.01): boolean =>
+  chirality_measure(points) > tolerance
+
+// A crystal structure is chiral iff its space group has no improper operations (Sohncke
+// group), i.e. every rotation part has determinant +1
+export const is_chiral_space_group = (operations: readonly SymmetryOperation[]): boolean =>
+  operations.every(({ rotation }) => math.det_3x3(mat3_from_flat_col_major(rotation)) > 0)
+
+export interface LocalEnvironment {
+  neighbor_indices: number[]
+  vectors: Vec3[] // Cartesian vectors from the center site to each neighbor image
+}
+
+// The n nearest neighbors (incl. periodic images) of a site within cutoff (Å)
+export function local_environment(
+  structure: Crystal,
+  site_idx: number,
+  n_neighbors: number,
+  cutoff = 5,
+): LocalEnvironment {
+  const neighbors = get_neighbor_list(structure, cutoff)[site_idx] ?? []
+  if (neighbors.length < n_neighbors) {
+    const found = `${neighbors.length} neighbors within ${cutoff} Å`
+    throw new Error(`Site ${site_idx} has ${found}, need ${n_neighbors}`)
+  }
+  const nearest = neighbors.slice(0, n_neighbors)
+  return {
+    neighbor_indices: nearest.map(({ site_idx: nb_idx }) => nb_idx),
+    vectors: nearest.map(({ displacement }) => displacement),
+  }
+}
+
+// Shape measures of a site's n-neighbor environment against every ideal polyhedron with
+// n vertices, sorted from best to worst match
+export function local_shape_measures(
+  structure: Crystal,
+  site_idx: number,
+  n_neighbors: number,
+  cutoff = 5,
+): { shape: string; measure: number }[] {
+  const { vectors } = local_environment(structure, site_idx, n_neighbors, cutoff)
+  return Object.entries(IDEAL_POLYHEDRA)
+    .filter(([, vertices]) => vertices.length === n_neighbors)
+    .map(([shape, vertices]) => ({ shape, measure: shape_measure(vectors, vertices) }))
+    .sort((shape_1, shape_2) => shape_1.measure - shape_2.measure)
+}
+
+export interface SpaceGroupDeviation {
+  deviations: Vec3[] // Cartesian shift of each site to its symmetrized position, Å
+  symmetrized_abc: Vec3[] // fractional site positions averaged over the group
+  rms: number // root-mean-square deviation, Å
+  max: number // largest site deviation, Å
+}
+
+// Deviation of a structure from a target space group given by its operations in the
+// structure's cell (e.g. moyo's operations at a loose symprec). Folding-unfolding: every
+// operation maps each site onto its nearest same-element image, and the group average
+// of the mapped positions is the closest symmetric structure for that site matching.
+export function space_group_deviation(
+  structure: Crystal,
+  operations: readonly SymmetryOperation[],
+  max_distance = 1,
+): SpaceGroupDeviation {
+  const { sites, lattice } = structure
+  const frac_to_cart = math.create_frac_to_cart(lattice.matrix)
+  const elements = sites.map((site) => get_majority_element(site))
+  const wrap = (diff: Vec3): Vec3 =>
+    diff.map((val, axis) => (lattice.pbc[axis] ? val - Math.round(val) : val)) as Vec3
+  const shifts = sites.map((): Vec3 => [0, 0, 0])
+
+  for (const [op_idx, { rotation, translation }] of operations.entries()) {
+    const rot = mat3_from_flat_col_major(rotation)
+    const rot_inv = math.matrix_inverse_3x3(rot)
+    sites.forEach((site, idx) => {
+      const image = math.add(math.mat3x3_vec3_multiply(rot, site.abc), translation as Vec3)
+      let [best_dist, best_delta]: [number, Vec3 | null] = [Infinity, null]
+      sites.forEach((other, other_idx) => {
+        if (elements[other_idx] !== elements[idx]) return
+        const delta = wrap(math.subtract(other.abc, image))
+        const dist = Math.hypot(...frac_to_cart(delta))
+        if (dist < best_dist) [best_dist, best_delta] = [dist, delta]
+      })
+      if (!best_delta || best_dist > max_distance) {
+        const where = `more than ${max_distance} Å from any site`
+        throw new Error(`Operation ${op_idx} maps site ${idx} ${where}`)
+      }
+      // g⁻¹ of the matched image lies at abc + W⁻¹·delta
+      shifts[idx] = math.add(shifts[idx], math.mat3x3_vec3_multiply(rot_inv, best_delta))
+    })
+  }
+
+  const n_ops = Math.max(operations.length, 1)
+  const frac_devs = shifts.map((shift) => math.scale(shift, 1 / n_ops))
+  const deviations = frac_devs.map(frac_to_cart)
+  const lengths = deviations.map((dev) => Math.hypot(...dev))
+  const sum_sq = lengths.reduce((sum, len) => sum + len * len, 0)
+  return {
+    deviations,
+    symmetrized_abc: sites.map((site, idx) => math.add(site.abc, frac_devs[idx])),
+    rms: Math.sqrt(sum_sq / Math.max(sites.length, 1)),
+    max: Math.max(0, ...lengths),
+  }
+}
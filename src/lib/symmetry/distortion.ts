@@ -0,0 +1,345 @@
+// Symmetry-mode decomposition of the distortion between a high-symmetry parent and a
+// distorted child structure (in the spirit of AMPLIMODES): the displacement field is split
+// into amplitudes per irreducible representation of the parent operations, per parent
+// orbit, and into the isotropy subgroup (parent operations the distortion preserves)
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import { get_majority_element } from '$lib/structure/bonding'
+import { jacobi_eigen } from './continuous-measures'
+import { mat3_from_flat_col_major } from './symmetry-elements'
+
+// Subset of moyo's operations: rotation is the column-major flattened 3x3 matrix W acting
+// on fractional coordinates, so moyo datasets' `operations` can be passed directly
+export type SymmetryOperation = { rotation: readonly number[]; translation: readonly number[] }
+
+export interface DistortionMode {
+  amplitude: number // Å, sqrt of summed squared site displacements
+  displacements: Vec3[] // Cartesian per parent site, Å
+  orbit_amplitudes: number[] // per parent orbit (see DistortionDecomposition.orbits)
+}
+
+// Component of the distortion transforming as one physically irreducible representation
+// (complex-conjugate pairs of irreps combined into one real representation)
+export interface IrrepMode extends DistortionMode {
+  characters: number[] // per parent operation, trace of the representation
+  dim: number // dimension of the representation (character of the identity)
+  isotropy_ops: number[] // indices of parent operations that leave this component invariant
+}
+
+export interface DistortionDecomposition {
+  site_mapping: number[] // child site index for each parent site
+  orbits: number[][] // parent site indices of each symmetry orbit
+  total: DistortionMode
+  symmetric: DistortionMode // totally symmetric (identity irrep) component
+  breaking: DistortionMode // everything lowering the parent symmetry
+  // non-zero irrep components, totally symmetric first, then by descending amplitude
+  irreps: IrrepMode[]
+  isotropy_ops: number[] // indices of parent operations that leave the distortion invariant
+}
+
+export interface DistortionOptions {
+  symprec?: number // Å tolerance for parent self-mapping and invariance (default 0.01)
+  max_displacement?: number // Å, max shift of a child site from its parent site (default 1)
+  remove_translation?: boolean // subtract the mean displacement (origin shift, default true)
+}
+
+const wrap_frac = (diff: Vec3): Vec3 => diff.map((val) => val - Math.round(val)) as Vec3
+
+const mode_from = (displacements: Vec3[], orbits: number[][]): DistortionMode => {
+  const norm_sq = (indices: number[]) =>
+    indices.reduce((sum, idx) => sum + math.dot(displacements[idx], displacements[idx]), 0)
+  return {
+    amplitude: Math.sqrt(norm_sq([...displacements.keys()])),
+    displacements,
+    orbit_amplitudes: orbits.map((orbit) => Math.sqrt(norm_sq(orbit))),
+  }
+}
+
+// Decompose child - parent displacements using the parent's symmetry operations (e.g.
+// moyo's `operations` for the parent cell). The child must be expressed in the parent's
+// cell with the same sites; lattice strain between the two isn't part of the decomposition.
+export function decompose_distortion(
+  parent: Crystal,
+  child: Crystal,
+  operations: readonly SymmetryOperation[],
+  options: DistortionOptions = {},
+): DistortionDecomposition {
+  const { symprec = 0.01, max_displacement = 1, remove_translation = true } = options
+  const { sites } = parent
+  const [n_parent, n_child] = [sites.length, child.sites.length]
+  if (n_child !== n_parent) {
+    throw new Error(`Parent and child site counts differ: ${n_parent} vs ${n_child}`)
+  }
+  const frac_to_cart = math.create_frac_to_cart(parent.lattice.matrix)
+  const cart_norm = (diff: Vec3): number => Math.hypot(...frac_to_cart(diff))
+  const frac_dist = (diff: Vec3): number => cart_norm(wrap_frac(diff))
+  const elements = sites.map((site) => get_majority_element(site))
+  const child_elements = child.sites.map((site) => get_majority_element(site))
+
+  // map each parent site onto the closest unclaimed child site of the same element
+  const candidates = sites.flatMap((site, parent_idx) =>
+    child.sites.flatMap((child_site, child_idx) => {
+      if (child_elements[child_idx] !== elements[parent_idx]) return []
+      const distance = frac_dist(math.subtract(child_site.abc, site.abc))
+      return distance <= max_displacement ? [{ parent_idx, child_idx, distance }] : []
+    }),
+  )
+  const site_mapping = sites.map(() => -1)
+  const claimed = new Set<number>()
+  candidates.sort((cand_1, cand_2) => cand_1.distance - cand_2.distance)
+  for (const { parent_idx, child_idx } of candidates) {
+    if (site_mapping[parent_idx] !== -1 || claimed.has(child_idx)) continue
+    site_mapping[parent_idx] = child_idx
+    claimed.add(child_idx)
+  }
+  const unmapped = site_mapping.indexOf(-1)
+  if (unmapped !== -1) {
+    const site_desc = `parent site ${unmapped} (${elements[unmapped]})`
+    throw new Error(`No child site within ${max_displacement} Å matches ${site_desc}`)
+  }
+
+  // fractional displacements, optionally without the rigid shift of the whole structure
+  let frac_disps = sites.map((site, idx) =>
+    wrap_frac(math.subtract(child.sites[site_mapping[idx]].abc, site.abc)),
+  )
+  if (remove_translation && sites.length > 0) {
+    const mean = math.scale(math.add(...frac_disps), 1 / sites.length)
+    frac_disps = frac_disps.map((disp) => math.subtract(disp, mean))
+  }
+
+  // site permutation of every operation: g maps parent site i onto site perms[g][i]
+  const rotations: Matrix3x3[] = operations.map(({ rotation }) =>
+    mat3_from_flat_col_major(rotation),
+  )
+  const perms = operations.map(({ translation }, op_idx) =>
+    sites.map((site, idx) => {
+      const image = math.add(
+        math.mat3x3_vec3_multiply(rotations[op_idx], site.abc),
+        translation as Vec3,
+      )
+      const target = sites.findIndex(
+        (other, other_idx) =>
+          elements[other_idx] === elements[idx] &&
+          frac_dist(math.subtract(image, other.abc)) <= symprec,
+      )
+      if (target === -1) {
+        throw new Error(`Operation ${op_idx} does not map parent site ${idx} onto the parent`)
+      }
+      return target
+    }),
+  )
+  // (g·u)_{π(i)} = W u_i
+  const apply_op = (op_idx: number, field: Vec3[]): Vec3[] => {
+    const out = field.map((): Vec3 => [0, 0, 0])
+    field.forEach((disp, idx) => {
+      out[perms[op_idx][idx]] = math.mat3x3_vec3_multiply(rotations[op_idx], disp)
+    })
+    return out
+  }
+
+  const n_ops = Math.max(operations.length, 1)
+  const symmetric_frac = sites.map((): Vec3 => [0, 0, 0])
+  const isotropy_ops: number[] = []
+  operations.forEach((_, op_idx) => {
+    const transformed = apply_op(op_idx, frac_disps)
+    transformed.forEach((disp, idx) => {
+      symmetric_frac[idx] = math.add(symmetric_frac[idx], math.scale(disp, 1 / n_ops))
+    })
+    const invariant = transformed.every(
+      (disp, idx) => cart_norm(math.subtract(disp, frac_disps[idx])) <= symprec,
+    )
+    if (invariant) isotropy_ops.push(op_idx)
+  })
+
+  // orbit of a site = its images under all operations (they form a group)
+  const orbit_of = sites.map(() => -1)
+  const orbits: number[][] = []
+  sites.forEach((_, start) => {
+    if (orbit_of[start] !== -1) return
+    const orbit = [...new Set([start, ...perms.map((perm) => perm[start])])]
+    for (const idx of orbit) orbit_of[idx] = orbits.length
+    orbits.push(orbit.sort((idx_1, idx_2) => idx_1 - idx_2))
+  })
+
+  const total = frac_disps.map(frac_to_cart)
+  const symmetric = symmetric_frac.map(frac_to_cart)
+  const breaking = total.map((disp, idx) => math.subtract(disp, symmetric[idx]))
+  const lattice_t = math.transpose_3x3_matrix(parent.lattice.matrix)
+  const cart_rotations = rotations.map((rot) =>
+    math.dot(math.dot(lattice_t, rot), math.matrix_inverse_3x3(lattice_t)),
+  )
+  const irreps = project_irreps(total, operations, rotations, cart_rotations, perms, symprec)
+  return {
+    site_mapping,
+    orbits,
+    total: mode_from(total, orbits),
+    symmetric: mode_from(symmetric, orbits),
+    breaking: mode_from(breaking, orbits),
+    irreps: irreps.map((irrep) => ({ ...irrep, ...mode_from(irrep.displacements, orbits) })),
+    isotropy_ops,
+  }
+}
+
+// Split a Cartesian displacement field into irrep components. The projectors need no
+// character tables: a random class function Σ_g w(g) D(g) (with equal weights on a class
+// and its inverse, so the operator is symmetric) commutes with the representation D and
+// has one eigenvalue per physically irreducible representation. Its eigenspaces within
+// the span of the images g·u are the components of u, and their traces under D give
+// m × the characters, from which the multiplicity m follows by Σ_g |χ(g)|² = |G| (or 2|G|
+// for a pair of complex-conjugate irreps).
+function project_irreps(
+  field: Vec3[],
+  operations: readonly SymmetryOperation[],
+  rotations: Matrix3x3[],
+  cart_rotations: Matrix3x3[],
+  perms: number[][],
+  symprec: number,
+): Omit<IrrepMode, `amplitude` | `orbit_amplitudes`>[] {
+  const n_ops = operations.length
+  const n_dims = 3 * field.length
+  const norm = Math.hypot(...field.flat())
+  if (n_ops === 0 || norm === 0) return []
+
+  // group multiplication table from rotations and translations modulo lattice vectors
+  const op_key = (rot: Matrix3x3, trans: Vec3) =>
+    [
+      ...rot.flat().map(Math.round),
+      ...trans.map((val) => Math.round((val - Math.floor(val)) * 1e4) % 1e4),
+    ].join()
+  const op_index = new Map(
+    operations.map(({ translation }, op_idx) => [
+      op_key(rotations[op_idx], translation as Vec3),
+      op_idx,
+    ]),
+  )
+  const product = (op_1: number, op_2: number): number => {
+    const rot = math.dot(rotations[op_1], rotations[op_2])
+    const trans = math.add(
+      math.mat3x3_vec3_multiply(rotations[op_1], operations[op_2].translation as Vec3),
+      operations[op_1].translation as Vec3,
+    )
+    const idx = op_index.get(op_key(rot, trans))
+    if (idx === undefined) throw new Error(`Operations do not form a group`)
+    return idx
+  }
+  const table = operations.map((_, op_1) => operations.map((_, op_2) => product(op_1, op_2)))
+  const identity = table.findIndex((row) => row.every((val, idx) => val === idx))
+  if (identity === -1) throw new Error(`Operations do not form a group`)
+  const inverse = table.map((row) => row.indexOf(identity))
+
+  // conjugacy classes, weighted by an irrational-like value shared with the inverse class
+  const class_of = operations.map(() => -1)
+  let n_classes = 0
+  for (let op_idx = 0; op_idx < n_ops; op_idx++) {
+    if (class_of[op_idx] !== -1) continue
+    for (let conj = 0; conj < n_ops; conj++) {
+      class_of[table[table[conj][op_idx]][inverse[conj]]] = n_classes
+    }
+    n_classes++
+  }
+  const weights = class_of.map((cls, op_idx) => {
+    const pair = Math.min(cls, class_of[inverse[op_idx]])
+    return 1 + (((pair + 1) * 0.6180339887498949) % 1)
+  })
+
+  // (g·u)_{π(i)} = R u_i on flattened Cartesian fields
+  const apply = (op_idx: number, vec: number[]): number[] => {
+    const out = new Array<number>(n_dims).fill(0)
+    for (let idx = 0; idx < field.length; idx++) {
+      const disp = vec.slice(3 * idx, 3 * idx + 3) as Vec3
+      const image = math.mat3x3_vec3_multiply(cart_rotations[op_idx], disp)
+      out.splice(3 * perms[op_idx][idx], 3, ...image)
+    }
+    return out
+  }
+  const dot = (vec_1: number[], vec_2: number[]) =>
+    vec_1.reduce((sum, val, idx) => sum + val * vec_2[idx], 0)
+
+  // orthonormal basis of the span of all images g·u (Gram-Schmidt)
+  const basis: number[][] = []
+  const flat = field.flat()
+  for (let op_idx = 0; op_idx < n_ops; op_idx++) {
+    const vec = apply(op_idx, flat)
+    for (const base of basis) {
+      const overlap = dot(vec, base)
+      base.forEach((val, idx) => (vec[idx] -= overlap * val))
+    }
+    const vec_norm = Math.hypot(...vec)
+    if (vec_norm > 1e-8 * norm) basis.push(vec.map((val) => val / vec_norm))
+  }
+
+  // class function operator restricted to that span, symmetrized against round-off
+  const class_sum = basis.map((base) => {
+    const out = new Array<number>(n_dims).fill(0)
+    for (let op_idx = 0; op_idx < n_ops; op_idx++) {
+      apply(op_idx, base).forEach((val, idx) => (out[idx] += weights[op_idx] * val))
+    }
+    return out
+  })
+  const reduced = basis.map((base_1, row) =>
+    basis.map((_, col) => (dot(base_1, class_sum[col]) + dot(basis[col], class_sum[row])) / 2),
+  )
+  const { values, vectors } = jacobi_eigen(reduced)
+
+  // cluster equal eigenvalues into the isotypic components
+  const tol = 1e-6 * Math.max(1, ...values.map(Math.abs))
+  const clusters: number[][] = []
+  for (const idx of [...values.keys()].sort((idx_1, idx_2) => values[idx_1] - values[idx_2])) {
+    const last = clusters.at(-1)
+    if (last && Math.abs(values[idx] - values[last[0]]) <= tol) last.push(idx)
+    else clusters.push([idx])
+  }
+
+  const to_field = (vec: number[]): Vec3[] =>
+    field.map((_, idx) => vec.slice(3 * idx, 3 * idx + 3) as Vec3)
+  const modes = clusters.flatMap((cluster) => {
+    // eigenvectors of the component in the full displacement space
+    const eig_vecs = cluster.map((col) =>
+      basis.reduce(
+        (acc, base, row) => acc.map((val, idx) => val + vectors[row][col] * base[idx]),
+        new Array<number>(n_dims).fill(0),
+      ),
+    )
+    const component = eig_vecs.reduce(
+      (acc, vec) => {
+        const overlap = dot(vec, flat)
+        return acc.map((val, idx) => val + overlap * vec[idx])
+      },
+      new Array<number>(n_dims).fill(0),
+    )
+    if (Math.hypot(...component) <= 1e-8 * norm) return []
+    const traces = operations.map((_, op_idx) =>
+      eig_vecs.reduce((sum, vec) => sum + dot(vec, apply(op_idx, vec)), 0),
+    )
+    const norm_sq = traces.reduce((sum, val) => sum + val ** 2, 0) / n_ops
+    const real_mult = Math.round(Math.sqrt(norm_sq))
+    const multiplicity =
+      Math.abs(real_mult ** 2 - norm_sq) < 1e-6 * norm_sq
+        ? real_mult
+        : Math.round(Math.sqrt(norm_sq / 2))
+    // characters of crystallographic (physically irreducible) representations are integers
+    const characters = traces.map((val) => Math.round(val / multiplicity) || 0)
+    const isotropy_ops = operations.flatMap((_, op_idx) => {
+      const moved = apply(op_idx, component).map((val, idx) => val - component[idx])
+      return Math.hypot(...moved) <= symprec ? [op_idx] : []
+    })
+    return [
+      {
+        characters,
+        dim: Math.round(characters[identity]),
+        isotropy_ops,
+        displacements: to_field(component),
+      },
+    ]
+  })
+  const is_symmetric = ({ characters }: { characters: number[] }) =>
+    characters.every((val) => Math.abs(val - 1) < 1e-6)
+  const amplitude = ({ displacements }: { displacements: Vec3[] }) =>
+    Math.hypot(...displacements.flat())
+  return modes.sort(
+    (mode_1, mode_2) =>
+      Number(is_symmetric(mode_2)) - Number(is_symmetric(mode_1)) ||
+      amplitude(mode_2) - amplitude(mode_1),
+  )
+}
@@ -4,14 +4,24 @@ import * as math from '$lib/math'
 import { DEFAULTS } from '$lib/settings'
 import type { AnyStructure, Crystal } from '$lib/structure'
 import { merge_split_partial_sites } from '$lib/structure/partial-occupancy'
+import { make_site } from '$lib/structure/site'
 import type { MoyoCell, MoyoDataset } from '@spglib/moyo-wasm'
 import init, { analyze_cell } from '@spglib/moyo-wasm'
 import moyo_wasm_url from '@spglib/moyo-wasm/moyo_wasm_bg.wasm?url'
+import { get_conventional_cell, get_primitive_cell } from './cell-transform'
+import type { IrreducibleKpoints, KpointGridOptions } from './kpoints'
+import { irreducible_kpoints_from_operations } from './kpoints'
+import { symmetrize_structure } from './symmetrize'
 import { mat3_from_flat_col_major } from './symmetry-elements'
 import { wyckoff_letter } from './wyckoff-db'
 
 export * from './cell-transform'
+export * from './continuous-measures'
+export * from './distortion'
+export * from './kpoints'
+export * from './property-tensors'
 export * from './spacegroups'
+export * from './symmetrize'
 export * from './symmetry-elements'
 export * from './wyckoff-db'
 export { default as SymmetryElementControls } from './SymmetryElementControls.svelte'
@@ -366,6 +376,215 @@ export function wyckoff_positions_from_moyo(sym_data: SymmetryDataset | null): W
   })
 }
 
+export type DistinctSite = {
+  site_idx: number // orbit representative (lowest site index)
+  site_indices: number[] // all sites of the orbit in the analyzed structure
+  multiplicity: number // orbit size in the analyzed structure
+  wyckoff: string // e.g. `4a`, multiplicity counted in the conventional cell
+  elem: string
+  site_symmetry?: string
+}
+
+// One representative site per crystallographic orbit (sorted by site index), e.g. to
+// run substitution or vacancy scans only over symmetry-inequivalent sites. Restrict to
+// some elements with elements (default: all).
+export function distinct_sites_from_moyo(
+  sym_data: SymmetryDataset | null,
+  elements?: readonly string[],
+): DistinctSite[] {
+  return wyckoff_positions_from_moyo(sym_data)
+    .filter(({ elem }) => !elements || elements.includes(elem))
+    .map(({ wyckoff, elem, site_indices = [], site_symmetry }) => ({
+      site_idx: site_indices[0] ?? -1,
+      site_indices,
+      multiplicity: site_indices.length,
+      wyckoff,
+      elem,
+      ...(site_symmetry ? { site_symmetry } : {}),
+    }))
+    .toSorted((site_1, site_2) => site_1.site_idx - site_2.site_idx)
+}
+
+// Analyze a structure's symmetry and return its inequivalent sites
+export async function symmetry_distinct_sites(
+  structure: Crystal,
+  options: Partial<SymmetrySettings> & { elements?: readonly string[] } = {},
+): Promise<DistinctSite[]> {
+  const { elements, ...settings } = options
+  const sym_data = await analyze_structure_symmetry(structure, settings)
+  return distinct_sites_from_moyo(sym_data, elements)
+}
+
+// Detect the space group within symprec and symmetrize lattice and positions onto it
+// (see symmetrize_structure). Images are matched to sites within 3 symprec since moyo's
+// tolerance checks aren't plain Cartesian distances.
+export async function snap_to_symmetry(
+  structure: Crystal,
+  settings: Partial<SymmetrySettings> = {},
+): Promise<Crystal> {
+  const sym_data = await analyze_structure_symmetry(structure, settings)
+  const { symprec } = { ...default_sym_settings, ...settings }
+  return symmetrize_structure(structure, sym_data.operations, { symprec: 3 * symprec })
+}
+
+// Irreducible k-points of a mesh in the structure's reciprocal lattice under its detected
+// space group, see irreducible_kpoints_from_operations
+export async function irreducible_kpoints(
+  structure: Crystal,
+  mesh: Vec3,
+  options: KpointGridOptions & Partial<SymmetrySettings> = {},
+): Promise<IrreducibleKpoints> {
+  const { shift, time_reversal, ...settings } = options
+  const sym_data = await analyze_structure_symmetry(structure, settings)
+  return irreducible_kpoints_from_operations(mesh, sym_data.operations, {
+    shift,
+    time_reversal,
+  })
+}
+
+export type PrimitiveSearch = {
+  structure: Crystal // primitive standardized cell
+  symprec: number // smallest tolerance giving this cell
+  spacegroup: number
+  // every tolerance tried: primitive cell size, or null if moyo failed or the cell's
+  // composition didn't match the input
+  scan: { symprec: number; n_sites: number | null; spacegroup: number | null }[]
+}
+
+// Noise-robust primitive cell: scan symmetry tolerances (ascending) and keep the
+// primitive cell with the fewest sites whose composition matches the input's (ties keep
+// the smaller tolerance). Relaxed structures often only reduce above the default symprec.
+export async function find_primitive_cell(
+  structure: Crystal,
+  options: { symprecs?: readonly number[]; algo?: SymmetrySettings[`algo`] } = {},
+): Promise<PrimitiveSearch> {
+  const { symprecs = [1e-5, 1e-4, 1e-3, 0.01, 0.03, 0.1], algo } = options
+  const counts = (crystal: Crystal) => {
+    const count_map = new Map<string, number>()
+    for (const site of crystal.sites) {
+      const elem = site.species[0]?.element ?? ``
+      count_map.set(elem, (count_map.get(elem) ?? 0) + 1)
+    }
+    return count_map
+  }
+  const input_counts = counts(structure)
+  const n_input = structure.sites.length
+  let best: Omit<PrimitiveSearch, `scan`> | null = null
+  const scan: PrimitiveSearch[`scan`] = []
+  for (const symprec of [...symprecs].toSorted((tol_1, tol_2) => tol_1 - tol_2)) {
+    let primitive: Crystal
+    let spacegroup: number
+    try {
+      const sym_data = await analyze_structure_symmetry(structure, { symprec, algo })
+      primitive = get_primitive_cell(structure, sym_data)
+      spacegroup = sym_data.number
+    } catch {
+      scan.push({ symprec, n_sites: null, spacegroup: null })
+      continue
+    }
+    // every element count must shrink by the same factor
+    const n_prim = primitive.sites.length
+    const prim_counts = counts(primitive)
+    const consistent =
+      n_prim > 0 &&
+      prim_counts.size === input_counts.size &&
+      [...input_counts].every(
+        ([elem, count]) => (prim_counts.get(elem) ?? 0) * n_input === count * n_prim,
+      )
+    scan.push({ symprec, n_sites: consistent ? n_prim : null, spacegroup })
+    if (consistent && (!best || n_prim < best.structure.sites.length)) {
+      best = { structure: primitive, symprec, spacegroup }
+    }
+  }
+  if (!best) throw new Error(`No tolerance in [${symprecs.join(`, `)}] gave a primitive cell`)
+  return { ...best, scan }
+}
+
+export type CanonicalizeOptions = Partial<SymmetrySettings> & {
+  cell?: `conventional` | `primitive` // standardized cell to return (default conventional)
+  decimals?: number // rounding of lattice parameters and coordinates (default 6)
+}
+
+const compare_strings = (str_1: string, str_2: string) =>
+  str_1 < str_2 ? -1 : str_1 > str_2 ? 1 : 0
+
+// Deterministic representation of a crystal for caching and reproducible pipelines:
+// moyo's standardized cell in crystallographic orientation (a along x, b in the xy
+// plane) with sites sorted by atomic number, Wyckoff letter, orbit and fractional
+// coordinates, all rounded to decimals. Equal inputs (also with reordered sites or a
+// rotated Cartesian frame) give byte-identical JSON.stringify output. Sites come from
+// moyo's atomic numbers, so like get_conventional_cell this drops oxidation states and
+// partial occupancies.
+export async function canonicalize_structure(
+  structure: Crystal,
+  options: CanonicalizeOptions = {},
+): Promise<Crystal> {
+  const { cell = `conventional`, decimals = 6, ...settings } = options
+  if (!Number.isInteger(decimals) || decimals < 0 || decimals > 15) {
+    throw new Error(`decimals must be an integer in [0, 15], got ${decimals}`)
+  }
+  const sym_data = await analyze_structure_symmetry(structure, settings)
+  const std =
+    cell === `primitive`
+      ? get_primitive_cell(structure, sym_data)
+      : get_conventional_cell(structure, sym_data)
+  // round and turn -0 into 0 so equal values serialize identically
+  const round = (value: number) => Number(value.toFixed(decimals)) || 0
+  const abcs = std.sites.map(
+    ({ abc }) => abc.map((coord) => round(to_unit(coord)) % 1) as Vec3,
+  )
+  const coord_keys = abcs.map((abc) => abc.map((coord) => coord.toFixed(decimals)).join(`,`))
+
+  // Wyckoff letter and orbit of every site, from analyzing the standardized cell itself
+  const std_data = await analyze_structure_symmetry(std, settings)
+  const letters = std.sites.map(() => ``)
+  const orbit_reps = std.sites.map((_, idx) => idx)
+  std_data.orig_site_indices_by_input_idx?.forEach((site_indices, input_idx) => {
+    for (const idx of site_indices) {
+      letters[idx] = wyckoff_letter(std_data.wyckoffs?.[input_idx] ?? ``)
+      orbit_reps[idx] = std_data.orbits?.[input_idx] ?? input_idx
+    }
+  })
+  // distinct orbits sharing element and letter are ordered by their smallest coordinates
+  const orbit_keys = new Map<number, string>()
+  orbit_reps.forEach((rep, idx) => {
+    const key = orbit_keys.get(rep)
+    if (key === undefined || coord_keys[idx] < key) orbit_keys.set(rep, coord_keys[idx])
+  })
+
+  const atomic_numbers = std.sites.map(
+    ({ species }) => SYMBOL_TO_ATOMIC_NUMBER[species[0].element] ?? 0,
+  )
+  const order = std.sites
+    .map((_, idx) => idx)
+    .toSorted(
+      (idx_1, idx_2) =>
+        atomic_numbers[idx_1] - atomic_numbers[idx_2] ||
+        compare_strings(letters[idx_1], letters[idx_2]) ||
+        compare_strings(
+          orbit_keys.get(orbit_reps[idx_1]) ?? ``,
+          orbit_keys.get(orbit_reps[idx_2]) ?? ``,
+        ) ||
+        compare_strings(coord_keys[idx_1], coord_keys[idx_2]),
+    )
+
+  const [a, b, c, alpha, beta, gamma] = (
+    [`a`, `b`, `c`, `alpha`, `beta`, `gamma`] as const
+  ).map((key) => round(std.lattice[key]))
+  const matrix = math
+    .cell_to_lattice_matrix(a, b, c, alpha, beta, gamma)
+    .map((vec) => vec.map(round)) as Matrix3x3
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  const sites = order.map((idx) => {
+    const { element } = std.sites[idx].species[0]
+    const xyz = frac_to_cart(abcs[idx]).map(round) as Vec3
+    return make_site(element, abcs[idx], xyz, element)
+  })
+  const volume = round(math.det_3x3(matrix))
+  const lattice = { matrix, pbc: std.lattice.pbc, a, b, c, alpha, beta, gamma, volume }
+  return { ...std, lattice, sites }
+}
+
 // Apply symmetry operations to find all equivalent positions for a given fractional coordinate
 export function apply_symmetry_operations(
   position: Vec3,
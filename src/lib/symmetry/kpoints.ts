@@ -0,0 +1,122 @@
+// Symmetry-reduced Monkhorst-Pack k-point grids: the irreducible k-points of a regular
+// mesh with their weights and the map from every grid point to its irreducible point, for
+// KPOINTS files and for weighting Brillouin-zone sums (transport, phonon DOS) by only
+// evaluating irreducible points.
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { SymmetryOperation } from './distortion'
+import { mat3_from_flat_col_major } from './symmetry-elements'
+
+export type KpointGridOptions = {
+  // offset of the grid in units of the grid spacing, 0 (Γ-centered) or 0.5 per axis, e.g.
+  // [0.5, 0.5, 0.5] for VASP's Monkhorst-Pack grids with even meshes (default [0, 0, 0])
+  shift?: Vec3
+  time_reversal?: boolean // k and -k are equivalent (default true)
+}
+
+export type IrreducibleKpoints = {
+  mesh: Vec3
+  shift: Vec3
+  // fractional reciprocal coordinates in [-0.5, 0.5) of the full grid, index
+  // i + n_a (j + n_b k) for grid point (i, j, k) like spglib and VASP
+  grid: Vec3[]
+  kpoints: Vec3[] // irreducible points, the first grid point of each orbit
+  weights: number[] // grid points per irreducible point, summing to the grid size
+  mapping: number[] // index into kpoints of every grid point
+  // distinct rotations used incl. time reversal, without those not keeping the grid
+  n_operations: number
+}
+
+// Irreducible k-points of a mesh under the rotations of symmetry operations acting on
+// fractional real-space coordinates (e.g. moyo's `operations`). k-points transform as
+// k' = Wᵀ k, which over a group is the same orbit as W⁻ᵀ k. Rotations that move some grid
+// point off the grid (e.g. 3-fold axes on unequal meshes) are skipped.
+export function irreducible_kpoints_from_operations(
+  mesh: Vec3,
+  operations: readonly Pick<SymmetryOperation, `rotation`>[],
+  options: KpointGridOptions = {},
+): IrreducibleKpoints {
+  const { shift = [0, 0, 0], time_reversal = true } = options
+  if (!mesh.every((size) => Number.isInteger(size) && size > 0)) {
+    throw new Error(`mesh must be 3 positive integers, got [${mesh}]`)
+  }
+  if (!shift.every((val) => val === 0 || val === 0.5)) {
+    throw new Error(`shift must be 0 or 0.5 along each axis, got [${shift}]`)
+  }
+  const [n_a, n_b, n_c] = mesh
+  const n_points = n_a * n_b * n_c
+  const grid: Vec3[] = Array.from({ length: n_points }, (_, idx) => {
+    const address = [idx % n_a, Math.floor(idx / n_a) % n_b, Math.floor(idx / (n_a * n_b))]
+    return address.map((val, axis) => {
+      const frac = (val + shift[axis]) / mesh[axis]
+      return frac - Math.floor(frac + 0.5)
+    }) as Vec3
+  })
+  // grid index of a fractional k-point, -1 if it's off the grid
+  const grid_index = (kpt: Vec3): number => {
+    let idx = 0
+    for (let axis = 2; axis >= 0; axis--) {
+      const scaled = kpt[axis] * mesh[axis] - shift[axis]
+      const address = Math.round(scaled)
+      if (Math.abs(scaled - address) > 1e-6) return -1
+      idx = idx * mesh[axis] + (((address % mesh[axis]) + mesh[axis]) % mesh[axis])
+    }
+    return idx
+  }
+
+  // distinct transposed rotations, with their negatives for time reversal
+  const rotations = new Map<string, Matrix3x3>()
+  const identity = [1, 0, 0, 0, 1, 0, 0, 0, 1]
+  for (const { rotation } of [{ rotation: identity }, ...operations]) {
+    const transposed = math.transpose_3x3_matrix(mat3_from_flat_col_major(rotation))
+    const rounded = transposed.map((row) => row.map(Math.round)) as Matrix3x3
+    rotations.set(rounded.flat().join(), rounded)
+    if (time_reversal) {
+      const negated = rounded.map((row) => row.map((val) => -val || 0)) as Matrix3x3
+      rotations.set(negated.flat().join(), negated)
+    }
+  }
+  // grid images under each rotation that keeps the grid, whose images form a group so
+  // each orbit is the set of images of any of its points
+  const image_maps: Int32Array[] = []
+  for (const rotation of rotations.values()) {
+    const images = new Int32Array(n_points)
+    const keeps_grid = grid.every((kpt, idx) => {
+      images[idx] = grid_index(math.mat3x3_vec3_multiply(rotation, kpt))
+      return images[idx] >= 0
+    })
+    if (keeps_grid) image_maps.push(images)
+  }
+
+  const kpoints: Vec3[] = []
+  const weights: number[] = []
+  const mapping = new Array<number>(n_points).fill(-1)
+  for (let idx = 0; idx < n_points; idx++) {
+    if (mapping[idx] >= 0) continue
+    const orbit = new Set(image_maps.map((images) => images[idx]))
+    for (const member of orbit) mapping[member] = kpoints.length
+    kpoints.push(grid[idx])
+    weights.push(orbit.size)
+  }
+  return {
+    mesh: [...mesh] as Vec3,
+    shift: [...shift] as Vec3,
+    grid,
+    kpoints,
+    weights,
+    mapping,
+    n_operations: image_maps.length,
+  }
+}
+
+// VASP KPOINTS file listing the irreducible k-points explicitly with integer weights
+export function irreducible_kpoints_to_vasp(
+  { kpoints, weights, mesh }: IrreducibleKpoints,
+  comment = `Irreducible k-points of a ${mesh.join(`x`)} grid`,
+): string {
+  const lines = kpoints.map((kpt, idx) => {
+    const coords = kpt.map((val) => val.toFixed(10).padStart(14)).join(``)
+    return `${coords}  ${weights[idx]}`
+  })
+  return [comment, `${kpoints.length}`, `Reciprocal`, ...lines].join(`\n`) + `\n`
+}
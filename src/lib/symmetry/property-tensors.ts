@@ -0,0 +1,264 @@
+// Piezoelectric (3×6 Voigt) and dielectric (3×3) property tensors: rotation, symmetry
+// projection and validation against a crystal's point group (Cartesian rotations, see
+// cartesian_rotations), IEEE 176 axis alignment and the direction of maximum
+// longitudinal response
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { jacobi_eigen } from './continuous-measures'
+import type { CrystalSystem } from './spacegroups'
+
+export type DielectricTensor = Matrix3x3
+// rows i = polarization direction, columns J = Voigt strain/stress component
+// (xx, yy, zz, yz, xz, xy)
+export type PiezoTensor = number[][]
+// `e`: stress coefficients e_iJ (C/m²), shear columns equal the tensor components
+// `d`: strain coefficients d_iJ (pC/N), shear columns are twice the tensor components
+export type PiezoKind = `e` | `d`
+export type Tensor3 = number[][][]
+
+export type TensorSymmetryCheck<T> = {
+  symmetrized: T // projection onto the invariant subspace of the point group
+  // largest component change from symmetrization relative to the largest component
+  max_violation: number
+  valid: boolean // max_violation ≤ tolerance
+}
+
+export type MaxResponse = {
+  direction: Vec3 // unit vector (sign is arbitrary for even responses)
+  value: number
+}
+
+const VOIGT_PAIRS: [number, number][] = [
+  [0, 0],
+  [1, 1],
+  [2, 2],
+  [1, 2],
+  [0, 2],
+  [0, 1],
+]
+const voigt_index = (jdx: number, kdx: number) => (jdx === kdx ? jdx : 6 - jdx - kdx)
+const AXES = [0, 1, 2]
+
+function check_piezo_shape(tensor: PiezoTensor) {
+  if (tensor.length !== 3 || tensor.some((row) => row.length !== 6)) {
+    throw new Error(`Piezoelectric tensor must be a 3x6 Voigt matrix`)
+  }
+}
+
+// Full third-rank tensor t_ijk (symmetric in j, k) from Voigt notation
+export function piezo_to_full(tensor: PiezoTensor, kind: PiezoKind = `e`): Tensor3 {
+  check_piezo_shape(tensor)
+  return AXES.map((idx) =>
+    AXES.map((jdx) =>
+      AXES.map((kdx) => {
+        const factor = kind === `d` && jdx !== kdx ? 0.5 : 1
+        return factor * tensor[idx][voigt_index(jdx, kdx)]
+      }),
+    ),
+  )
+}
+
+// Voigt form of a full third-rank tensor, averaging t_ijk and t_ikj
+export const piezo_from_full = (full: Tensor3, kind: PiezoKind = `e`): PiezoTensor =>
+  AXES.map((idx) =>
+    VOIGT_PAIRS.map(([jdx, kdx]) => {
+      const factor = kind === `d` && jdx !== kdx ? 2 : 1
+      return (factor * (full[idx][jdx][kdx] + full[idx][kdx][jdx])) / 2
+    }),
+  )
+
+// ε' = R ε Rᵀ
+export const rotate_dielectric = (
+  tensor: DielectricTensor,
+  rotation: Matrix3x3,
+): DielectricTensor =>
+  AXES.map((idx) =>
+    AXES.map((jdx) => {
+      let sum = 0
+      for (const kdx of AXES) {
+        for (const ldx of AXES) {
+          sum += rotation[idx][kdx] * rotation[jdx][ldx] * tensor[kdx][ldx]
+        }
+      }
+      return sum
+    }),
+  ) as DielectricTensor
+
+function rotate_full(full: Tensor3, rotation: Matrix3x3): Tensor3 {
+  return AXES.map((idx) =>
+    AXES.map((jdx) =>
+      AXES.map((kdx) => {
+        let sum = 0
+        for (const ldx of AXES) {
+          for (const mdx of AXES) {
+            for (const ndx of AXES) {
+              const rot = rotation[idx][ldx] * rotation[jdx][mdx] * rotation[kdx][ndx]
+              sum += rot * full[ldx][mdx][ndx]
+            }
+          }
+        }
+        return sum
+      }),
+    ),
+  )
+}
+
+// t'_ijk = R_il R_jm R_kn t_lmn
+export const rotate_piezo = (
+  tensor: PiezoTensor,
+  rotation: Matrix3x3,
+  kind: PiezoKind = `e`,
+): PiezoTensor => piezo_from_full(rotate_full(piezo_to_full(tensor, kind), rotation), kind)
+
+// Group average ⟨R ε Rᵀ⟩ of the symmetric part of ε
+export function symmetrize_dielectric(
+  tensor: DielectricTensor,
+  rotations: readonly Matrix3x3[],
+): DielectricTensor {
+  if (!math.is_square_matrix(tensor, 3)) throw new Error(`Dielectric tensor must be 3x3`)
+  const sym = AXES.map((idx) =>
+    AXES.map((jdx) => (tensor[idx][jdx] + tensor[jdx][idx]) / 2),
+  ) as DielectricTensor
+  if (rotations.length === 0) return sym
+  const out = AXES.map(() => [0, 0, 0]) as DielectricTensor
+  for (const rotation of rotations) {
+    const rotated = rotate_dielectric(sym, rotation)
+    AXES.forEach((idx) => AXES.forEach((jdx) => (out[idx][jdx] += rotated[idx][jdx])))
+  }
+  return out.map((row) => row.map((val) => val / rotations.length)) as DielectricTensor
+}
+
+// Group average ⟨R·t⟩, zero for centrosymmetric point groups
+export function symmetrize_piezo(
+  tensor: PiezoTensor,
+  rotations: readonly Matrix3x3[],
+  kind: PiezoKind = `e`,
+): PiezoTensor {
+  const full = piezo_to_full(tensor, kind)
+  if (rotations.length === 0) return piezo_from_full(full, kind)
+  const out: Tensor3 = AXES.map(() => AXES.map(() => [0, 0, 0]))
+  for (const rotation of rotations) {
+    const rotated = rotate_full(full, rotation)
+    for (const idx of AXES) {
+      for (const jdx of AXES) {
+        for (const kdx of AXES) out[idx][jdx][kdx] += rotated[idx][jdx][kdx] / rotations.length
+      }
+    }
+  }
+  return piezo_from_full(out, kind)
+}
+
+function symmetry_check<T extends number[][]>(
+  tensor: T,
+  symmetrized: T,
+  tolerance: number,
+): TensorSymmetryCheck<T> {
+  const scale = Math.max(...tensor.flat().map(Math.abs))
+  const diff = Math.max(
+    ...tensor.flatMap((row, idx) =>
+      row.map((val, jdx) => Math.abs(val - symmetrized[idx][jdx])),
+    ),
+  )
+  const max_violation = scale > 0 ? diff / scale : 0
+  return { symmetrized, max_violation, valid: max_violation <= tolerance }
+}
+
+// Check that a dielectric tensor is symmetric and invariant under the point group
+// (tolerance relative to the largest component, default 1e-3)
+export const check_dielectric_symmetry = (
+  tensor: DielectricTensor,
+  rotations: readonly Matrix3x3[],
+  tolerance = 1e-3,
+): TensorSymmetryCheck<DielectricTensor> =>
+  symmetry_check(tensor, symmetrize_dielectric(tensor, rotations), tolerance)
+
+// Check that a piezoelectric tensor only has components allowed by the point group
+// (tolerance relative to the largest component, default 1e-3). Any nonzero tensor
+// fails for centrosymmetric groups.
+export const check_piezo_symmetry = (
+  tensor: PiezoTensor,
+  rotations: readonly Matrix3x3[],
+  kind: PiezoKind = `e`,
+  tolerance = 1e-3,
+): TensorSymmetryCheck<PiezoTensor> =>
+  symmetry_check(tensor, symmetrize_piezo(tensor, rotations, kind), tolerance)
+
+const normalize = (vec: Vec3): Vec3 => math.scale(vec, 1 / Math.hypot(...vec))
+
+// Rotation to the IEEE 176 Cartesian frame of a conventional standard cell: rows are
+// the new x, y, z axes, so R v expresses v in that frame (pass R to rotate_dielectric
+// or rotate_piezo). Monoclinic cells (unique axis b) get y ∥ b and z ∥ c; all others
+// z ∥ c and x ∥ a (its part normal to c). Orthorhombic axes are not relabeled to the
+// IEEE c < a < b ordering.
+export function ieee_rotation(
+  lattice_matrix: Matrix3x3,
+  crystal_system: CrystalSystem,
+): Matrix3x3 {
+  const [vec_a, vec_b, vec_c] = lattice_matrix
+  const orthogonal_part = (vec: Vec3, axis: Vec3) =>
+    normalize(math.subtract(vec, math.scale(axis, math.dot(vec, axis))))
+  if (crystal_system === `monoclinic`) {
+    const y_axis = normalize(vec_b)
+    const z_axis = orthogonal_part(vec_c, y_axis)
+    return [math.cross_3d(y_axis, z_axis), y_axis, z_axis] as Matrix3x3
+  }
+  const z_axis = normalize(vec_c)
+  const x_axis = orthogonal_part(vec_a, z_axis)
+  return [x_axis, math.cross_3d(z_axis, x_axis), z_axis] as Matrix3x3
+}
+
+// Maximize a function on the unit sphere: best of a Fibonacci grid, then a shrinking
+// pattern search in the tangent plane
+function maximize_on_sphere(func: (dir: Vec3) => number, n_grid = 2000): MaxResponse {
+  let [direction, value]: [Vec3, number] = [[0, 0, 1], -Infinity]
+  const golden = Math.PI * (3 - Math.sqrt(5))
+  for (let idx = 0; idx < n_grid; idx++) {
+    const z_val = 1 - (2 * (idx + 0.5)) / n_grid
+    const radius = Math.sqrt(1 - z_val * z_val)
+    const dir: Vec3 = [radius * Math.cos(golden * idx), radius * Math.sin(golden * idx), z_val]
+    const val = func(dir)
+    if (val > value) [direction, value] = [dir, val]
+  }
+  let step = 0.05
+  while (step > 1e-9) {
+    const helper: Vec3 = Math.abs(direction[0]) < 0.9 ? [1, 0, 0] : [0, 1, 0]
+    const tangent_1 = normalize(math.cross_3d(direction, helper))
+    const tangent_2 = math.cross_3d(direction, tangent_1)
+    let improved = false
+    for (const tangent of [tangent_1, tangent_2]) {
+      for (const sign of [-1, 1]) {
+        const trial = normalize(math.add(direction, math.scale(tangent, sign * step)))
+        const val = func(trial)
+        if (val <= value) continue
+        ;[direction, value, improved] = [trial, val, true]
+      }
+    }
+    if (!improved) step /= 2
+  }
+  return { direction, value }
+}
+
+// Direction of the largest longitudinal permittivity nᵀ ε n, i.e. the eigenvector of the
+// largest eigenvalue of the symmetric part of ε
+export function max_dielectric_response(tensor: DielectricTensor): MaxResponse {
+  const sym_part = tensor.map((row, idx) => row.map((val, jdx) => (val + tensor[jdx][idx]) / 2))
+  const { values, vectors } = jacobi_eigen(sym_part)
+  const best = values.indexOf(Math.max(...values))
+  const direction = normalize(vectors.map((row) => row[best]) as Vec3)
+  return { direction, value: values[best] }
+}
+
+// Direction of the largest longitudinal piezoelectric coefficient Σ n_i n_j n_k t_ijk,
+// e.g. the optimal poling/cut direction for d33-type devices
+export function max_piezo_response(tensor: PiezoTensor, kind: PiezoKind = `e`): MaxResponse {
+  const full = piezo_to_full(tensor, kind)
+  return maximize_on_sphere((dir) => {
+    let sum = 0
+    for (const idx of AXES) {
+      for (const jdx of AXES) {
+        for (const kdx of AXES) sum += dir[idx] * dir[jdx] * dir[kdx] * full[idx][jdx][kdx]
+      }
+    }
+    return sum
+  })
+}
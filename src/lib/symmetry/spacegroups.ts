@@ -72,8 +72,9 @@ export function spacegroup_num_to_lattice_system(spacegroup: number): LatticeSys
   return RHOMBOHEDRAL_SPACEGROUPS.includes(spacegroup) ? `rhombohedral` : `hexagonal`
 }
 
-// Normalize space group input (number, Hermann-Mauguin symbol, or numeric string
-// like "225") to a space group number in [1, 230], or null if invalid
+// Normalize space group input (number, Hermann-Mauguin symbol incl. variants like
+// `P 21/c`, or numeric string like "225") to a space group number in [1, 230], or null
+// if invalid
 export function normalize_spacegroup(spacegroup: number | string): number | null {
   if (typeof spacegroup === `number`) {
     return spacegroup >= 1 && spacegroup <= 230 ? spacegroup : null
@@ -82,7 +83,8 @@ export function normalize_spacegroup(spacegroup: number | string): number | null
   if (from_symbol !== undefined) return from_symbol
   // Accept trailing setting qualifiers like `146:R` by parsing the leading integer
   const int_match = /^\s*(?<num>\d+)/.exec(spacegroup)
-  return int_match ? normalize_spacegroup(Number(int_match[1])) : null
+  if (int_match) return normalize_spacegroup(Number(int_match[1]))
+  return hm_symbol_to_spacegroup_num(spacegroup)
 }
 
 export const SPACEGROUP_SYMBOL_TO_NUM: Record<string, number> = {
@@ -421,6 +423,35 @@ export const SPACEGROUP_NUM_TO_SYMBOL = Object.entries(SPACEGROUP_SYMBOL_TO_NUM)
   return acc
 }, {})
 
+// Pre-2002 symbols of the five groups whose glide planes were renamed to `e`
+const LEGACY_HM_SYMBOLS: Record<string, number> = {
+  Abm2: 39,
+  Aba2: 41,
+  Cmca: 64,
+  Cmma: 67,
+  Ccca: 68,
+}
+// Lookup keys without underscores so `P21/c` matches `P2_1/c` (no two groups collide)
+const COMPACT_SYMBOL_TO_NUM = new Map(
+  Object.entries({ ...SPACEGROUP_SYMBOL_TO_NUM, ...LEGACY_HM_SYMBOLS }).map(
+    ([symbol, num]) => [symbol.replaceAll(`_`, ``), num],
+  ),
+)
+
+// Space group number from a Hermann-Mauguin symbol as written in CIFs and other files:
+// tolerates spaces, quotes and underscores (`P 21/c`, `'P 1 21/c 1'`), a lowercase lattice
+// letter, origin/setting suffixes (`Fd-3m:2`, `R -3 m H`, `Pnma S`) and pre-2002 glide
+// notation (`Cmca`). Returns null if unrecognized.
+export function hm_symbol_to_spacegroup_num(symbol: string): number | null {
+  const compact = symbol
+    .replaceAll(/['"]/g, ``)
+    .trim()
+    .replace(/(?::\s*\w+|\s+[HRSZ])$/i, ``)
+    .replaceAll(/[\s_]/g, ``)
+  if (!compact) return null
+  return COMPACT_SYMBOL_TO_NUM.get(compact[0].toUpperCase() + compact.slice(1)) ?? null
+}
+
 export interface SpacegroupSunburstMetadata {
   spacegroup: number
   crystal_system: CrystalSystem
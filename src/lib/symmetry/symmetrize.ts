@@ -0,0 +1,209 @@
+// Snap a structure onto its detected space group: the metric tensor is averaged over
+// the point group and the positions over each orbit, removing numerical noise left by
+// relaxations so Wyckoff assignment and enumeration behave deterministically. The same
+// group averages project forces and stresses for symmetry-constrained relaxations.
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import { get_majority_element } from '$lib/structure/bonding'
+import { wrap_to_unit_cell } from '$lib/structure/pbc'
+import type { SymmetryOperation } from './distortion'
+import { mat3_from_flat_col_major } from './symmetry-elements'
+
+export type SymmetrizeOptions = {
+  // Å, max distance between a site's image under an operation and its partner site
+  // (default 0.01, i.e. the symprec of the symmetry search)
+  symprec?: number
+}
+
+const identity = (): Matrix3x3 => [
+  [1, 0, 0],
+  [0, 1, 0],
+  [0, 0, 1],
+]
+
+const mat_mul = (mat_1: Matrix3x3, mat_2: Matrix3x3): Matrix3x3 =>
+  mat_1.map((row) =>
+    [0, 1, 2].map((jdx) => row.reduce((sum, val, kdx) => sum + val * mat_2[kdx][jdx], 0)),
+  ) as Matrix3x3
+
+const mat_mean = (mat_1: Matrix3x3, mat_2: Matrix3x3): Matrix3x3 =>
+  mat_1.map((row, idx) => row.map((val, jdx) => (val + mat_2[idx][jdx]) / 2)) as Matrix3x3
+
+// Square root of a symmetric positive-definite matrix (Denman-Beavers iteration)
+function sqrt_spd(matrix: Matrix3x3): Matrix3x3 {
+  let [y_mat, z_mat]: Matrix3x3[] = [matrix, identity()]
+  for (let iter = 0; iter < 50; iter++) {
+    const next_y = mat_mean(y_mat, math.matrix_inverse_3x3(z_mat))
+    z_mat = mat_mean(z_mat, math.matrix_inverse_3x3(y_mat))
+    const prev = y_mat.flat()
+    const change = Math.max(...next_y.flat().map((val, idx) => Math.abs(val - prev[idx])))
+    y_mat = next_y
+    if (change < 1e-14) break
+  }
+  return y_mat
+}
+
+type SiteImage = {
+  target: number // site the image lands on
+  delta: Vec3 // fractional offset of the image from that site (minimum image)
+}
+
+// Partner site of every site's image under every operation (same element, nearest in
+// the minimum-image sense), throwing if an image lands more than symprec Å off-site
+function map_site_images(
+  structure: Crystal,
+  operations: readonly SymmetryOperation[],
+  symprec: number,
+): SiteImage[][] {
+  const frac_to_cart = math.create_frac_to_cart(structure.lattice.matrix)
+  const elements = structure.sites.map((site) => get_majority_element(site))
+  return operations.map(({ rotation, translation }, op_idx) => {
+    const rot = mat3_from_flat_col_major(rotation)
+    return structure.sites.map((site, site_idx) => {
+      const image = math.add(math.mat3x3_vec3_multiply(rot, site.abc), translation as Vec3)
+      let [best_idx, best_dist] = [-1, Infinity]
+      let best_delta: Vec3 = [0, 0, 0]
+      structure.sites.forEach((other, other_idx) => {
+        if (elements[other_idx] !== elements[site_idx]) return
+        const diff = math.subtract(image, other.abc)
+        const delta = diff.map((val) => val - Math.round(val)) as Vec3
+        const dist = Math.hypot(...frac_to_cart(delta))
+        if (dist >= best_dist) return
+        ;[best_idx, best_dist, best_delta] = [other_idx, dist, delta]
+      })
+      if (best_dist > symprec) {
+        const desc = `site ${site_idx} (${elements[site_idx]})`
+        throw new Error(`Operation ${op_idx} maps ${desc} ${best_dist.toFixed(4)} Å off-site`)
+      }
+      return { target: best_idx, delta: best_delta }
+    })
+  })
+}
+
+// Symmetrize lattice and positions with the given operations (e.g. moyo's `operations`
+// for this cell, which act on fractional coordinates). The cell is idealized by the
+// symmetric stretch that brings its metric onto the point-group average, so the
+// orientation is kept. Sites keep their order, species and properties.
+export function symmetrize_structure(
+  structure: Crystal,
+  operations: readonly SymmetryOperation[],
+  options: SymmetrizeOptions = {},
+): Crystal {
+  const { symprec = 0.01 } = options
+  if (operations.length === 0) return structure
+  const { matrix } = structure.lattice
+  const rotations = operations.map(({ rotation }) => mat3_from_flat_col_major(rotation))
+
+  // metric G = A Aᵀ (rows of A are lattice vectors) averaged as Wᵀ G W over the group
+  const metric = mat_mul(matrix, math.transpose_3x3_matrix(matrix))
+  const avg_metric: Matrix3x3 = [
+    [0, 0, 0],
+    [0, 0, 0],
+    [0, 0, 0],
+  ]
+  for (const rot of rotations) {
+    const term = mat_mul(mat_mul(math.transpose_3x3_matrix(rot), metric), rot)
+    for (let idx = 0; idx < 3; idx++) {
+      for (let jdx = 0; jdx < 3; jdx++) avg_metric[idx][jdx] += term[idx][jdx]
+    }
+  }
+  avg_metric.forEach((row) => row.forEach((_, jdx) => (row[jdx] /= rotations.length)))
+  // A' = A D with D symmetric and D² = A⁻¹ G' A⁻ᵀ
+  const inv = math.matrix_inverse_3x3(matrix)
+  const stretch_sq = mat_mul(mat_mul(inv, avg_metric), math.transpose_3x3_matrix(inv))
+  const new_matrix = mat_mul(matrix, sqrt_spd(stretch_sq))
+
+  // average each site over the images landing on it: x_j' = x_j + ⟨W x_i + t − x_j⟩
+  const shifts = structure.sites.map((): Vec3 => [0, 0, 0])
+  const counts = structure.sites.map(() => 0)
+  for (const images of map_site_images(structure, operations, symprec)) {
+    for (const { target, delta } of images) {
+      shifts[target] = math.add(shifts[target], delta)
+      counts[target]++
+    }
+  }
+
+  const new_frac_to_cart = math.create_frac_to_cart(new_matrix)
+  const sites = structure.sites.map((site, idx) => {
+    const mean_shift = math.scale(shifts[idx], 1 / counts[idx])
+    const abc = wrap_to_unit_cell(math.add(site.abc, mean_shift))
+    return { ...site, abc, xyz: new_frac_to_cart(abc) }
+  })
+  const lattice = {
+    ...structure.lattice,
+    matrix: new_matrix,
+    ...math.calc_lattice_params(new_matrix),
+  }
+  return { ...structure, lattice, sites }
+}
+
+// Cartesian rotations R = Aᵀ W A⁻ᵀ of operations acting on fractional coordinates of a
+// cell with lattice vectors as rows of A, e.g. to symmetrize property tensors
+export function cartesian_rotations(
+  lattice_matrix: Matrix3x3,
+  operations: readonly Pick<SymmetryOperation, `rotation`>[],
+): Matrix3x3[] {
+  const to_cart = math.transpose_3x3_matrix(lattice_matrix)
+  const from_cart = math.matrix_inverse_3x3(to_cart)
+  return operations.map(({ rotation }) =>
+    mat_mul(mat_mul(to_cart, mat3_from_flat_col_major(rotation)), from_cart),
+  )
+}
+
+export type SymmetryFilter = {
+  forces: (forces: readonly Vec3[]) => Vec3[] // Cartesian, one per site
+  stress: (stress: Matrix3x3) => Matrix3x3 // Cartesian stress or cell gradient
+}
+
+// Projector onto the symmetry-invariant subspace for constrained relaxations: apply to
+// forces and stress (cell gradient) every optimizer step so FIRE/L-BFGS updates can't
+// break the space group. Operations act on fractional coordinates of this cell; their
+// Cartesian form is R = Aᵀ W A⁻ᵀ. Set up once per relaxation, assuming the structure
+// starts symmetric (see symmetrize_structure).
+export function create_symmetry_filter(
+  structure: Crystal,
+  operations: readonly SymmetryOperation[],
+  options: SymmetrizeOptions = {},
+): SymmetryFilter {
+  const { symprec = 0.01 } = options
+  const images = map_site_images(structure, operations, symprec)
+  const cart_rotations = cartesian_rotations(structure.lattice.matrix, operations)
+  const n_ops = Math.max(operations.length, 1)
+
+  // F'_π(i) = ⟨R F_i⟩: each site collects the rotated forces of its preimages
+  const forces = (site_forces: readonly Vec3[]): Vec3[] => {
+    if (site_forces.length !== structure.sites.length) {
+      const [n_forces, n_sites] = [site_forces.length, structure.sites.length]
+      throw new Error(`Got ${n_forces} forces for ${n_sites} sites`)
+    }
+    if (operations.length === 0) return site_forces.map((force): Vec3 => [...force])
+    const out = site_forces.map((): Vec3 => [0, 0, 0])
+    cart_rotations.forEach((rot, op_idx) => {
+      site_forces.forEach((force, site_idx) => {
+        const { target } = images[op_idx][site_idx]
+        const rotated = math.scale(math.mat3x3_vec3_multiply(rot, force), 1 / n_ops)
+        out[target] = math.add(out[target], rotated)
+      })
+    })
+    return out
+  }
+
+  // σ' = ⟨R σ Rᵀ⟩
+  const stress = (tensor: Matrix3x3): Matrix3x3 => {
+    if (operations.length === 0) return tensor.map((row) => [...row]) as Matrix3x3
+    const out: Matrix3x3 = [
+      [0, 0, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    for (const rot of cart_rotations) {
+      const term = mat_mul(mat_mul(rot, tensor), math.transpose_3x3_matrix(rot))
+      for (let idx = 0; idx < 3; idx++) {
+        for (let jdx = 0; jdx < 3; jdx++) out[idx][jdx] += term[idx][jdx] / n_ops
+      }
+    }
+    return out
+  }
+  return { forces, stress }
+}
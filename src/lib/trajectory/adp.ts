@@ -0,0 +1,127 @@
+// Anisotropic displacement parameters (thermal ellipsoids) from MD trajectories
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure/index'
+import { ADP_PROPERTY_KEY, u_equiv } from '$lib/structure/adp'
+import { wrap_to_unit_cell } from '$lib/structure/pbc'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+
+export type AdpOptions = {
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  remove_drift?: boolean // subtract each frame's mean displacement of all atoms (default true)
+}
+
+export type AdpResult = {
+  // Time-averaged structure (mean lattice, mean wrapped positions) with each site's
+  // Cartesian U tensor stored in properties.u_cart
+  structure: Crystal
+  u_cart: Matrix3x3[] // per-site mean-square displacement tensors in Å²
+  u_equiv: number[] // per-site U_eq = tr(U) / 3 in Å² (B = 8π² U_eq)
+  n_frames: number
+}
+
+// Per-atom mean-square displacement tensors U_ij = <Δr_i Δr_j> about the time-averaged
+// positions. Fractional coordinates are unwrapped across periodic boundaries between
+// consecutive frames, so sampled frames should be closer than half a cell per atom.
+export function compute_adp_tensors(
+  trajectory: TrajectoryType,
+  options: AdpOptions = {},
+): AdpResult {
+  const { start_frame = 0, stride = 1, remove_drift = true } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames
+    .slice(start_frame)
+    .filter((_, idx) => idx % stride === 0)
+    .map((frame) => frame.structure)
+  if (frames.length < 2) {
+    throw new Error(`Need at least 2 frames to compute ADPs, got ${frames.length}`)
+  }
+  const crystals = frames.filter(is_crystal)
+  if (crystals.length !== frames.length) {
+    throw new Error(`ADPs require periodic structures with a lattice in every frame`)
+  }
+  const n_sites = crystals[0].sites.length
+  if (crystals.some((crystal) => crystal.sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+  const pbc = crystals[0].lattice.pbc
+
+  // Unwrap fractional trajectories: accumulate minimum-image steps between frames
+  const unwrapped: Vec3[][] = [crystals[0].sites.map((site) => [...site.abc] as Vec3)]
+  for (let frame_idx = 1; frame_idx < crystals.length; frame_idx++) {
+    const prev_sites = crystals[frame_idx - 1].sites
+    const prev_unwrapped = unwrapped[frame_idx - 1]
+    unwrapped.push(
+      crystals[frame_idx].sites.map((site, site_idx) => {
+        const step = math.subtract(site.abc, prev_sites[site_idx].abc)
+        return step.map((delta, dim) => {
+          const image_shift = pbc[dim] ? Math.round(delta) : 0
+          return prev_unwrapped[site_idx][dim] + delta - image_shift
+        }) as Vec3
+      }),
+    )
+  }
+
+  if (remove_drift) {
+    const origin = unwrapped[0]
+    for (const positions of unwrapped) {
+      const drift = math.scale(
+        math.add(...positions.map((pos, site_idx) => math.subtract(pos, origin[site_idx]))),
+        1 / n_sites,
+      )
+      positions.forEach((pos, site_idx) => {
+        positions[site_idx] = math.subtract(pos, drift)
+      })
+    }
+  }
+
+  const n_frames = crystals.length
+  const mean_frac = Array.from({ length: n_sites }, (_, site_idx) =>
+    math.scale(math.add(...unwrapped.map((positions) => positions[site_idx])), 1 / n_frames),
+  )
+
+  // Accumulate Cartesian displacement outer products, converting with each frame's cell
+  // so NPT box fluctuations don't masquerade as atomic vibrations
+  const u_cart = Array.from(
+    { length: n_sites },
+    (): Matrix3x3 => [
+      [0, 0, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ],
+  )
+  unwrapped.forEach((positions, frame_idx) => {
+    const frac_to_cart = math.create_frac_to_cart(crystals[frame_idx].lattice.matrix)
+    positions.forEach((pos, site_idx) => {
+      const disp = frac_to_cart(math.subtract(pos, mean_frac[site_idx]))
+      for (let row = 0; row < 3; row++) {
+        for (let col = 0; col < 3; col++) {
+          u_cart[site_idx][row][col] += (disp[row] * disp[col]) / n_frames
+        }
+      }
+    })
+  })
+
+  const mean_matrix = math.scale(
+    math.add(...crystals.map((crystal) => crystal.lattice.matrix.flat())),
+    1 / n_frames,
+  )
+  const matrix = math.vec9_to_mat3x3(mean_matrix)
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  const reference = crystals[0]
+  const structure: Crystal = {
+    ...reference,
+    lattice: { ...reference.lattice, ...math.calc_lattice_params(matrix), matrix },
+    sites: reference.sites.map((site, site_idx) => {
+      const abc = wrap_to_unit_cell(mean_frac[site_idx])
+      const properties = { ...site.properties, [ADP_PROPERTY_KEY]: u_cart[site_idx] }
+      return { ...site, abc, xyz: frac_to_cart(abc), properties }
+    }),
+  }
+
+  return { structure, u_cart, u_equiv: u_cart.map(u_equiv), n_frames }
+}
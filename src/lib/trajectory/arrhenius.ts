@@ -0,0 +1,132 @@
+// Arrhenius fits of thermally activated transport data, Y = A exp(−E_a / k_B T) for
+// diffusion coefficients or σT = A exp(−E_a / k_B T) for ionic conductivities, with
+// extrapolation to other temperatures (e.g. room temperature from high-T MD)
+import { K_B_EV } from '$lib/constants'
+import { linear_fit, student_t_quantile } from '$lib/stats'
+
+export type ArrheniusOptions = {
+  // standard uncertainties of the values, weighting ln Y by (Y / error)². Only relative
+  // weights matter as parameter errors are scaled by the reduced χ².
+  errors?: readonly number[]
+  times_temperature?: boolean // fit Y·T instead of Y (default false)
+  // two-sided level of the Student-t confidence intervals (default 0.95)
+  confidence?: number
+}
+
+export type ArrheniusFit = {
+  activation_energy: number // eV
+  activation_energy_err: number // standard error, NaN with fewer than 3 points
+  prefactor: number // A in units of the values (times K with times_temperature)
+  ln_prefactor_err: number // standard error of ln A since A is log-normal
+  covariance: number // cov(ln A, E_a) in eV, needed for extrapolation errors
+  // Student-t quantile with n − 2 degrees of freedom for the confidence level, which
+  // widens intervals from the few temperatures of typical MD runs. Pass it as n_sigma to
+  // arrhenius_predict for bands at the same level. NaN with fewer than 3 points.
+  t_value: number
+  activation_energy_ci: [number, number] // eV
+  prefactor_ci: [number, number] // log-normal, so asymmetric around the prefactor
+  r_squared: number // of ln Y against 1/T
+  n_points: number
+  times_temperature: boolean
+}
+
+export type ArrheniusPrediction = {
+  temperature: number
+  value: number // Y at temperature (Y, not Y·T, with times_temperature)
+  // value · exp(∓n_sigma · σ_ln Y), asymmetric like the log-normal uncertainty of Y
+  lower: number
+  upper: number
+}
+
+// Weighted least squares of ln Y against 1/T with slope −E_a / k_B
+export function arrhenius_fit(
+  temperatures: readonly number[],
+  values: readonly number[],
+  options: ArrheniusOptions = {},
+): ArrheniusFit {
+  const { errors, times_temperature = false, confidence = 0.95 } = options
+  if (temperatures.length !== values.length) {
+    const [n_temps, n_values] = [temperatures.length, values.length]
+    throw new Error(`Need one value per temperature, got ${n_temps} and ${n_values}`)
+  }
+  if (!(confidence > 0 && confidence < 1)) {
+    throw new Error(`confidence must be in (0, 1), got ${confidence}`)
+  }
+  if (errors && errors.length !== values.length) {
+    throw new Error(`errors must match the ${values.length} values, got ${errors.length}`)
+  }
+  temperatures.forEach((temp, idx) => {
+    if (!(temp > 0)) throw new Error(`Temperatures must be positive, got ${temp} K`)
+    if (!(values[idx] > 0)) {
+      throw new Error(`Arrhenius fits need positive values, got ${values[idx]} at ${temp} K`)
+    }
+    if (errors && !(errors[idx] > 0)) {
+      throw new Error(`errors must be positive, got ${errors[idx]} at ${temp} K`)
+    }
+  })
+  const inv_temps = temperatures.map((temp) => 1 / temp)
+  const ln_values = values.map((val, idx) =>
+    Math.log(times_temperature ? val * temperatures[idx] : val),
+  )
+  // σ_ln Y = σ_Y / Y (the factor T cancels)
+  const weights = errors?.map((err, idx) => (values[idx] / err) ** 2)
+  const fit = linear_fit(inv_temps, ln_values, weights)
+
+  // cov(intercept, slope) = −x̄_w σ²_slope for a weighted line fit
+  const sum_w = weights?.reduce((sum, weight) => sum + weight, 0) ?? inv_temps.length
+  const mean_inv_temp =
+    inv_temps.reduce((sum, inv_t, idx) => sum + (weights?.[idx] ?? 1) * inv_t, 0) / sum_w
+  const t_value =
+    fit.n_points > 2 ? student_t_quantile((1 + confidence) / 2, fit.n_points - 2) : NaN
+  const activation_energy = -fit.slope * K_B_EV
+  const activation_energy_err = fit.slope_err * K_B_EV
+  const e_a_half_width = t_value * activation_energy_err
+  const ln_a_half_width = t_value * fit.intercept_err
+  return {
+    activation_energy,
+    activation_energy_err,
+    prefactor: Math.exp(fit.intercept),
+    ln_prefactor_err: fit.intercept_err,
+    covariance: K_B_EV * mean_inv_temp * fit.slope_err ** 2,
+    t_value,
+    activation_energy_ci: [
+      activation_energy - e_a_half_width,
+      activation_energy + e_a_half_width,
+    ],
+    prefactor_ci: [
+      Math.exp(fit.intercept - ln_a_half_width),
+      Math.exp(fit.intercept + ln_a_half_width),
+    ],
+    r_squared: fit.r_squared,
+    n_points: fit.n_points,
+    times_temperature,
+  }
+}
+
+// Evaluate a fit at other temperatures with ±n_sigma confidence bands (default 2, ≈ 95%
+// for many points) propagated from the parameter errors and their covariance. Bands are
+// NaN if the fit had no degrees of freedom left for errors.
+export function arrhenius_predict(
+  fit: ArrheniusFit,
+  temperatures: readonly number[],
+  n_sigma = 2,
+): ArrheniusPrediction[] {
+  const { activation_energy, activation_energy_err, ln_prefactor_err, covariance } = fit
+  return temperatures.map((temperature) => {
+    if (!(temperature > 0)) {
+      throw new Error(`Temperatures must be positive, got ${temperature} K`)
+    }
+    const inv_kt = 1 / (K_B_EV * temperature)
+    let ln_value = Math.log(fit.prefactor) - activation_energy * inv_kt
+    if (fit.times_temperature) ln_value -= Math.log(temperature)
+    const ln_var =
+      ln_prefactor_err ** 2 + (inv_kt * activation_energy_err) ** 2 - 2 * inv_kt * covariance
+    const half_width = Number.isNaN(ln_var) ? NaN : n_sigma * Math.sqrt(Math.max(0, ln_var))
+    return {
+      temperature,
+      value: Math.exp(ln_value),
+      lower: Math.exp(ln_value - half_width),
+      upper: Math.exp(ln_value + half_width),
+    }
+  })
+}
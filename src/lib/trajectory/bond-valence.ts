@@ -0,0 +1,110 @@
+// Bond valence sums along trajectories: per-atom BVS time series as instantaneous
+// oxidation states, e.g. to spot redox events and polaron hopping in AIMD runs
+import type { BondValenceOptions } from '$lib/structure/bond-valence'
+import { compute_bv_sums } from '$lib/structure/bond-valence'
+import { get_majority_element } from '$lib/structure/bonding'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_indices } from '$lib/structure/select'
+import type { TrajectoryType } from './index'
+
+export type BvsTrajectoryOptions = BondValenceOptions & {
+  selection?: AtomSelection // atoms to track, evaluated on the first sampled frame
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  time_step?: number // time per MD step (default 1)
+  smoothing?: number // centered running mean over this many frames (odd, default 1)
+  // candidate oxidation states per element, e.g. { Fe: [2, 3] }: every frame gets the
+  // state closest to the smoothed BVS and changes between frames are redox events
+  oxidation_states?: Record<string, number[]>
+}
+
+export type RedoxEvent = {
+  site_idx: number
+  frame: number // index into the sampled frames where the new state starts
+  time: number
+  from: number
+  to: number
+}
+
+export type BvsTrajectory = {
+  times: number[]
+  site_indices: number[] // tracked atoms
+  bv_sums: number[][] // [atom][frame] raw BVS
+  smoothed: number[][] // [atom][frame] after the running mean
+  mean: number[] // per atom over all sampled frames
+  std: number[]
+  states: (number | null)[][] // [atom][frame], null for elements without candidates
+  redox_events: RedoxEvent[] // sorted by frame
+  n_frames: number
+}
+
+// Centered running mean with the window shrunk at the ends
+const running_mean = (values: number[], window: number): number[] => {
+  const half = (window - 1) / 2
+  return values.map((_, idx) => {
+    const chunk = values.slice(Math.max(0, idx - half), idx + half + 1)
+    return chunk.reduce((sum, val) => sum + val, 0) / chunk.length
+  })
+}
+
+// Evaluate compute_bv_sums on every sampled frame and track the selected atoms
+export function bv_sum_trajectory(
+  trajectory: TrajectoryType,
+  options: BvsTrajectoryOptions,
+): BvsTrajectory {
+  const { start_frame = 0, stride = 1, time_step = 1, smoothing = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  if (!Number.isInteger(smoothing) || smoothing < 1 || smoothing % 2 === 0) {
+    throw new Error(`smoothing must be an odd positive integer, got ${smoothing}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length === 0) throw new Error(`No frames left after start_frame/stride`)
+  const n_sites = frames[0].structure.sites.length
+  if (frames.some(({ structure }) => structure.sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+  const site_indices = selection_indices(frames[0].structure, options.selection)
+
+  const per_frame = frames.map(({ structure }) => compute_bv_sums(structure, options))
+  const bv_sums = site_indices.map((site_idx) => per_frame.map((sums) => sums[site_idx]))
+  const smoothed = bv_sums.map((series) => running_mean(series, smoothing))
+  const n_frames = frames.length
+  const mean = bv_sums.map((series) => series.reduce((sum, val) => sum + val, 0) / n_frames)
+  const std = bv_sums.map((series, atom) =>
+    Math.sqrt(series.reduce((sum, val) => sum + (val - mean[atom]) ** 2, 0) / n_frames),
+  )
+
+  const times = frames.map(({ step }) => step * time_step)
+  const redox_events: RedoxEvent[] = []
+  const states = site_indices.map((site_idx, atom) => {
+    const element = get_majority_element(frames[0].structure.sites[site_idx]) ?? ``
+    const candidates = options.oxidation_states?.[element]
+    if (!candidates?.length) return smoothed[atom].map(() => null)
+    const nearest = (bvs: number) =>
+      candidates.reduce((best, state) =>
+        Math.abs(state - bvs) < Math.abs(best - bvs) ? state : best,
+      )
+    const series = smoothed[atom].map(nearest)
+    series.forEach((state, frame) => {
+      const from = series[frame - 1]
+      if (frame === 0 || state === from) return
+      redox_events.push({ site_idx, frame, time: times[frame], from, to: state })
+    })
+    return series
+  })
+  redox_events.sort((ev_1, ev_2) => ev_1.frame - ev_2.frame || ev_1.site_idx - ev_2.site_idx)
+
+  return {
+    times,
+    site_indices,
+    bv_sums,
+    smoothed,
+    mean,
+    std,
+    states,
+    redox_events,
+    n_frames,
+  }
+}
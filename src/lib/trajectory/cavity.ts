@@ -0,0 +1,219 @@
+// Cavity (free volume) detection: grid points where a probe sphere fits between atoms
+// are flood-filled into connected voids per frame, and voids are followed through a
+// trajectory to get their volume histories and lifetimes, e.g. for vacancy-like hopping
+// and free-volume diffusion mechanisms in glasses
+import type { ElementSymbol } from '$lib/element'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { atomic_radii } from '$lib/structure/index'
+import type { Crystal } from '$lib/structure/index'
+import { get_majority_element } from '$lib/structure/bonding'
+import { make_site } from '$lib/structure/site'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+
+export type CavityOptions = {
+  probe_radius?: number // Å, radius of the probe sphere that must fit (default 1)
+  // Å per element, atoms without an entry use atomic_radii (default 1 Å if unknown)
+  radii?: Partial<Record<ElementSymbol, number>>
+  resolution?: number // Å, target grid spacing (default 0.25)
+  min_volume?: number // Å³, smaller voids are dropped (default 0)
+}
+
+export type Cavity = {
+  center: Vec3 // Cartesian centroid of the accessible region, wrapped into the cell
+  volume: number // Å³ accessible to the probe center
+  n_points: number // grid points in the cavity
+  // connects to its own periodic image, i.e. a channel rather than a closed void
+  percolating: boolean
+}
+
+// Connected probe-accessible regions of a periodic structure, largest first
+export function find_cavities(structure: Crystal, options: CavityOptions = {}): Cavity[] {
+  const { probe_radius = 1, radii = {}, resolution = 0.25, min_volume = 0 } = options
+  if (!(resolution > 0)) throw new Error(`resolution must be > 0, got ${resolution}`)
+  const { matrix } = structure.lattice
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  const dims = matrix.map((vec) => Math.max(2, Math.ceil(Math.hypot(...vec) / resolution)))
+  const [nx, ny, nz] = dims
+  const blocked = new Uint8Array(nx * ny * nz)
+
+  // stamp the exclusion sphere (atom radius + probe radius) of every atom onto the grid,
+  // looping over unwrapped indices so periodic images are covered
+  for (const site of structure.sites) {
+    const element = get_majority_element(site)
+    const atom_radius = element ? (radii[element] ?? atomic_radii[element] ?? 1) : 1
+    const reach = atom_radius + probe_radius
+    const pad = math.frac_cutoff_per_axis(matrix, reach)
+    const [lo, hi] = [-1, 1].map((sign) =>
+      site.abc.map((val, axis) => {
+        const bound = (val + sign * pad[axis]) * dims[axis]
+        return sign < 0 ? Math.floor(bound) : Math.ceil(bound)
+      }),
+    )
+    for (let ix = lo[0]; ix <= hi[0]; ix++) {
+      for (let iy = lo[1]; iy <= hi[1]; iy++) {
+        for (let iz = lo[2]; iz <= hi[2]; iz++) {
+          const delta = math.subtract([ix / nx, iy / ny, iz / nz], site.abc)
+          if (Math.hypot(...frac_to_cart(delta)) >= reach) continue
+          const [wx, wy, wz] = [ix, iy, iz].map((idx, axis) => {
+            const dim = dims[axis]
+            return ((idx % dim) + dim) % dim
+          })
+          blocked[(wx * ny + wy) * nz + wz] = 1
+        }
+      }
+    }
+  }
+
+  // flood fill open points with unwrapped grid coordinates, so centroids of voids
+  // crossing the cell boundary stay contiguous and wrapping clusters get flagged
+  const voxel_volume = Math.abs(math.det_3x3(matrix)) / blocked.length
+  const unwrapped = new Int32Array(3 * blocked.length)
+  const visited = new Uint8Array(blocked.length)
+  const cavities: Cavity[] = []
+  for (let seed = 0; seed < blocked.length; seed++) {
+    if (blocked[seed] || visited[seed]) continue
+    visited[seed] = 1
+    const seed_coords = [Math.floor(seed / (ny * nz)), Math.floor(seed / nz) % ny, seed % nz]
+    seed_coords.forEach((val, axis) => (unwrapped[3 * seed + axis] = val))
+    const queue = [seed]
+    const sum: Vec3 = [0, 0, 0]
+    let percolating = false
+    for (let head = 0; head < queue.length; head++) {
+      const idx = queue[head]
+      const coords = [0, 1, 2].map((axis) => unwrapped[3 * idx + axis])
+      coords.forEach((val, axis) => (sum[axis] += val / dims[axis]))
+      for (let axis = 0; axis < 3; axis++) {
+        for (const step of [-1, 1]) {
+          const next = [...coords]
+          next[axis] += step
+          const [wx, wy, wz] = next.map((val, dim_idx) => {
+            const dim = dims[dim_idx]
+            return ((val % dim) + dim) % dim
+          })
+          const nb_idx = (wx * ny + wy) * nz + wz
+          if (blocked[nb_idx]) continue
+          if (visited[nb_idx]) {
+            const seen = [0, 1, 2].map((dim_idx) => unwrapped[3 * nb_idx + dim_idx])
+            if (seen.some((val, dim_idx) => val !== next[dim_idx])) percolating = true
+            continue
+          }
+          visited[nb_idx] = 1
+          next.forEach((val, dim_idx) => (unwrapped[3 * nb_idx + dim_idx] = val))
+          queue.push(nb_idx)
+        }
+      }
+    }
+    const volume = queue.length * voxel_volume
+    if (volume < min_volume) continue
+    const centroid = sum.map((val) => val / queue.length) as Vec3
+    const center = frac_to_cart(centroid.map((val) => val - Math.floor(val)) as Vec3)
+    cavities.push({ center, volume, n_points: queue.length, percolating })
+  }
+  return cavities.sort((cav_1, cav_2) => cav_2.volume - cav_1.volume)
+}
+
+// Copy of the structure with a placeholder site at every cavity center for viewing
+// voids next to the atoms, carrying the cavity volume as a site property
+export function with_cavity_sites(
+  structure: Crystal,
+  cavities: readonly Cavity[],
+  element: ElementSymbol = `He`, // placeholder species (default He)
+): Crystal {
+  const cart_to_frac = math.create_cart_to_frac(structure.lattice.matrix)
+  const cavity_sites = cavities.map(({ center, volume }, idx) => ({
+    ...make_site(element, cart_to_frac(center), center, `cavity${idx + 1}`),
+    properties: { cavity_volume: volume },
+  }))
+  return { ...structure, sites: [...structure.sites, ...cavity_sites] }
+}
+
+export type CavityTrackingOptions = CavityOptions & {
+  start_frame?: number // default 0
+  stride?: number // default 1
+  time_step?: number // time per MD step, lifetimes are in this unit (default 1)
+  // Å, max minimum-image shift of a cavity center between sampled frames (default 1)
+  max_shift?: number
+}
+
+export type CavityTrack = {
+  frames: number[] // indices into the sampled frames
+  times: number[]
+  centers: Vec3[]
+  volumes: number[] // Å³
+  lifetime: number // time between first and last sighting
+  closed: boolean // vanished before the last sampled frame
+}
+
+export type CavityTracking = {
+  times: number[]
+  cavities: Cavity[][] // per sampled frame
+  tracks: CavityTrack[] // ordered by first appearance
+}
+
+// Detect cavities in every sampled frame and link them into tracks: each cavity
+// continues the unclaimed track whose last center (seen in the previous frame) is
+// nearest within max_shift, otherwise it starts a new track
+export function track_cavities(
+  trajectory: TrajectoryType,
+  options: CavityTrackingOptions = {},
+): CavityTracking {
+  const { start_frame = 0, stride = 1, time_step = 1, max_shift = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length === 0) throw new Error(`No frames left after start_frame/stride`)
+
+  const times: number[] = []
+  const cavities: Cavity[][] = []
+  const tracks: CavityTrack[] = []
+  let active: number[] = [] // track indices seen in the previous frame
+  frames.forEach(({ structure, step }, frame_idx) => {
+    if (!is_crystal(structure)) {
+      throw new Error(`Cavity tracking requires periodic structures in every frame`)
+    }
+    const time = step * time_step
+    const found = find_cavities(structure, options)
+    times.push(time)
+    cavities.push(found)
+
+    const { matrix, pbc } = structure.lattice
+    const converters = math.create_lattice_converters(matrix)
+    const pairs = found.flatMap(({ center }, cav_idx) =>
+      active.flatMap((track_idx) => {
+        const last = tracks[track_idx].centers.at(-1) ?? center
+        const shift = math.min_image_displacement(last, center, matrix, converters, pbc)
+        const dist = Math.hypot(...shift)
+        return dist <= max_shift ? [{ cav_idx, track_idx, dist }] : []
+      }),
+    )
+    pairs.sort((pair_1, pair_2) => pair_1.dist - pair_2.dist)
+    const track_of = found.map(() => -1)
+    const claimed = new Set<number>()
+    for (const { cav_idx, track_idx } of pairs) {
+      if (track_of[cav_idx] !== -1 || claimed.has(track_idx)) continue
+      track_of[cav_idx] = track_idx
+      claimed.add(track_idx)
+    }
+    active = found.map(({ center, volume }, cav_idx) => {
+      let track_idx = track_of[cav_idx]
+      if (track_idx === -1) {
+        track_idx = tracks.length
+        const empty = { frames: [], times: [], centers: [], volumes: [] }
+        tracks.push({ ...empty, lifetime: 0, closed: false })
+      }
+      const track = tracks[track_idx]
+      track.frames.push(frame_idx)
+      track.times.push(time)
+      track.centers.push(center)
+      track.volumes.push(volume)
+      track.lifetime = time - track.times[0]
+      return track_idx
+    })
+  })
+  const last_frame = frames.length - 1
+  for (const track of tracks) track.closed = track.frames.at(-1) !== last_frame
+  return { times, cavities, tracks }
+}
@@ -0,0 +1,136 @@
+// Collective variables (CVs) for reaction-coordinate analysis: project trajectory frames
+// onto distances, smooth coordination numbers or per-atom order parameters, and turn the
+// sampled CV values into free-energy profiles by Boltzmann inversion of their histogram
+import { K_B_EV } from '$lib/constants'
+import type { Vec2 } from '$lib/math'
+import * as math from '$lib/math'
+import type { SmoothCoordinationOptions } from '$lib/structure/coordination'
+import { smooth_coordination_numbers } from '$lib/structure/coordination'
+import type { AnyStructure } from '$lib/structure/index'
+import { site_field_values } from '$lib/structure/site-fields'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+
+// scalar function of one frame
+export type CollectiveVariable = (structure: AnyStructure) => number
+
+const check_sites = (structure: AnyStructure, sites: readonly number[]) => {
+  const n_sites = structure.sites.length
+  const bad = sites.find((idx) => !Number.isInteger(idx) || idx < 0 || idx >= n_sites)
+  if (bad !== undefined) throw new Error(`Site index ${bad} out of range for ${n_sites} sites`)
+}
+
+const mean_over = (values: number[], sites?: readonly number[]): number => {
+  const selected = sites ? sites.map((idx) => values[idx]) : values
+  return selected.reduce((sum, val) => sum + val, 0) / selected.length
+}
+
+// Distance between two sites (minimum image for periodic structures)
+export const distance_cv =
+  (site_1: number, site_2: number): CollectiveVariable =>
+  (structure) => {
+    check_sites(structure, [site_1, site_2])
+    const [xyz_1, xyz_2] = [structure.sites[site_1].xyz, structure.sites[site_2].xyz]
+    if (!is_crystal(structure)) return Math.hypot(...math.subtract(xyz_2, xyz_1))
+    const { matrix, pbc } = structure.lattice
+    return Math.hypot(...math.min_image_displacement(xyz_1, xyz_2, matrix, undefined, pbc))
+  }
+
+// Mean smooth coordination number of the given sites (all sites if omitted), e.g. the
+// number of water oxygens around a solvated ion (see smooth_coordination_numbers)
+export const coordination_cv =
+  (sites?: readonly number[], options: SmoothCoordinationOptions = {}): CollectiveVariable =>
+  (structure) => {
+    if (sites) check_sites(structure, sites)
+    return mean_over(smooth_coordination_numbers(structure, options), sites)
+  }
+
+// Mean of a per-atom site field over the given sites (all sites if omitted), e.g. a local
+// order parameter stored with set_site_field
+export const site_field_cv =
+  (field: string, sites?: readonly number[]): CollectiveVariable =>
+  (structure) => {
+    if (sites) check_sites(structure, sites)
+    return mean_over(site_field_values(structure, field), sites)
+  }
+
+export type ProjectionOptions = {
+  start_frame?: number // default 0
+  stride?: number // default 1
+  time_step?: number // time per MD step (default 1)
+}
+
+export type CvProjection = {
+  times: number[]
+  values: Record<string, number[]> // per CV name, one value per sampled frame
+}
+
+// Evaluate named CVs on every sampled frame, e.g. { d_OH: distance_cv(0, 5) }
+export function project_trajectory(
+  trajectory: TrajectoryType,
+  cvs: Record<string, CollectiveVariable>,
+  options: ProjectionOptions = {},
+): CvProjection {
+  const { start_frame = 0, stride = 1, time_step = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  const values = Object.fromEntries(
+    Object.entries(cvs).map(([name, cv]) => [
+      name,
+      frames.map(({ structure }) => cv(structure)),
+    ]),
+  )
+  return { times: frames.map(({ step }) => step * time_step), values }
+}
+
+export type FreeEnergyOptions = {
+  n_bins?: number // default 50
+  range?: Vec2 // histogram range (default: min and max of the samples)
+  temperature?: number // K (default 300)
+  weights?: readonly number[] // per-sample weights, e.g. reweighting factors (default 1)
+}
+
+export type FreeEnergyProfile = {
+  centers: number[] // bin centers in CV units
+  probability: number[] // normalized probability density per CV unit
+  free_energy: number[] // eV, −kT ln p shifted to a minimum of 0, Infinity for empty bins
+  counts: number[] // samples per bin (out-of-range samples are dropped)
+}
+
+// Free-energy profile F(s) = −kT ln p(s) from a histogram of CV samples of an unbiased
+// (or reweighted) run
+export function free_energy_profile(
+  samples: readonly number[],
+  options: FreeEnergyOptions = {},
+): FreeEnergyProfile {
+  const { n_bins = 50, temperature = 300, weights } = options
+  if (!Number.isInteger(n_bins) || n_bins < 1) {
+    throw new Error(`n_bins must be a positive integer, got ${n_bins}`)
+  }
+  if (weights && weights.length !== samples.length) {
+    throw new Error(`Got ${weights.length} weights for ${samples.length} samples`)
+  }
+  const finite = samples.filter(Number.isFinite)
+  if (finite.length === 0) throw new Error(`Need at least one finite sample`)
+  const [lo, hi] = options.range ?? [Math.min(...finite), Math.max(...finite)]
+  const width = (hi - lo) / n_bins || 1 // all samples equal: unit-width bins
+  const counts = Array<number>(n_bins).fill(0)
+  const hist = Array<number>(n_bins).fill(0)
+  samples.forEach((val, idx) => {
+    if (!Number.isFinite(val) || val < lo || val > hi) return
+    const bin = Math.min(Math.floor((val - lo) / width), n_bins - 1)
+    counts[bin]++
+    hist[bin] += weights?.[idx] ?? 1
+  })
+  const total = hist.reduce((sum, val) => sum + val, 0)
+  if (!(total > 0)) throw new Error(`No weighted samples within [${lo}, ${hi}]`)
+  const probability = hist.map((val) => val / (total * width))
+  const k_t = K_B_EV * temperature
+  const raw = probability.map((prob) => (prob > 0 ? -k_t * Math.log(prob) : Infinity))
+  const min_f = Math.min(...raw)
+  const free_energy = raw.map((val) => val - min_f)
+  const centers = counts.map((_, bin) => lo + (bin + 0.5) * width)
+  return { centers, probability, free_energy, counts }
+}
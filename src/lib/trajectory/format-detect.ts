@@ -5,6 +5,7 @@ import {
   TRAJ_EXTENSIONS_REGEX,
   TRAJ_FALLBACK_EXTENSIONS_REGEX,
   TRAJ_KEYWORDS_REGEX,
+  VASP_TRAJECTORY_OUTPUTS_REGEX,
   XDATCAR_REGEX,
 } from '$lib/constants'
 import { strip_compression_extensions } from '$lib/io/decompress'
@@ -69,6 +70,20 @@ export const FORMAT_PATTERNS = {
     )
   },
 
+  outcar: (data: string, filename?: string) => {
+    const basename = filename?.toLowerCase().split(`/`).pop() ?? ``
+    if (basename.startsWith(`outcar`)) return true
+    if (ext_hint(filename, /outcar$/i) === false) return false
+    return data.includes(`TOTAL-FORCE (eV/Angst)`) && data.includes(`ions per type =`)
+  },
+
+  vasprun: (data: string, filename?: string) => {
+    const basename = filename?.toLowerCase().split(`/`).pop() ?? ``
+    if (basename.startsWith(`vasprun`)) return true
+    if (ext_hint(filename, /\.xml$/) === false) return false
+    return data.includes(`<modeling>`) && data.includes(`<calculation>`)
+  },
+
   xyz_multi: (data: string, filename?: string) => {
     if (ext_hint(filename, /\.(?:xyz|extxyz)$/) === false) return false
     return count_xyz_frames(data) >= 2
@@ -93,6 +108,7 @@ export function is_trajectory_file(filename: string, content?: string): boolean
 
   // Always detect these specific trajectory formats
   if (TRAJ_EXTENSIONS_REGEX.test(base_name) || XDATCAR_REGEX.test(base_name)) return true
+  if (VASP_TRAJECTORY_OUTPUTS_REGEX.test(base_name)) return true
 
   // Special exclusion for generic md_simulation pattern with certain extensions
   if (MD_SIM_EXCLUDE_REGEX.test(base_name)) return false
@@ -0,0 +1,166 @@
+// Whole-trajectory operations on loaded frames: slicing, concatenation, species filtering,
+// per-frame property series (energies, stresses, temperatures, ...) and unwrapping of
+// PBC-wrapped coordinates. Results are plain in-memory trajectories, so streaming fields
+// describing the source file (frame_loader, indexed_frames, ...) are not carried over.
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { get_majority_element } from '$lib/structure/bonding'
+import type { AnyStructure } from '$lib/structure/index'
+import { select_sites } from '$lib/structure/site-fields'
+import type { TrajectoryFrame, TrajectoryType } from './index'
+
+// frame metadata keys holding one entry per site, filtered alongside the sites
+export const PER_SITE_FRAME_KEYS = [`forces`, `velocities`, `magmoms`, `charges`, `momenta`]
+
+// Cartesian positions unwrapped across periodic boundaries by accumulating each frame's
+// Cartesian step from the previous wrapped position, reduced to its minimum image in the
+// current frame's cell. Unlike scaling fractional steps, this keeps the affine rescaling
+// of NPT/variable-cell runs out of the displacements (Kulke & Vermaas, JCTC 18, 6161,
+// 2022). Frames should be closer than half a cell per atom.
+export function unwrap_positions(structures: readonly AnyStructure[]): Vec3[][] {
+  if (structures.length === 0) return []
+  const unwrapped: Vec3[][] = [structures[0].sites.map((site) => [...site.xyz] as Vec3)]
+  for (let frame_idx = 1; frame_idx < structures.length; frame_idx++) {
+    const structure = structures[frame_idx]
+    const prev_sites = structures[frame_idx - 1].sites
+    const prev_unwrapped = unwrapped[frame_idx - 1]
+    const lattice = `lattice` in structure ? structure.lattice : null
+    const converters = lattice && math.create_lattice_converters(lattice.matrix)
+    unwrapped.push(
+      structure.sites.map((site, site_idx) => {
+        const step = math.subtract(site.xyz, prev_sites[site_idx].xyz)
+        if (!lattice || !converters) return math.add(prev_unwrapped[site_idx], step)
+        const frac_step = converters.cart_to_frac(step).map(
+          (delta, dim) => delta - (lattice.pbc[dim] ? Math.round(delta) : 0),
+        ) as Vec3
+        return math.add(prev_unwrapped[site_idx], converters.frac_to_cart(frac_step))
+      }),
+    )
+  }
+  return unwrapped
+}
+
+// Frames start to end (exclusive, negative values count from the end as in Array.slice)
+// keeping every stride-th one
+export function slice_trajectory(
+  trajectory: TrajectoryType,
+  start = 0,
+  end?: number,
+  stride = 1,
+): TrajectoryType {
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start, end).filter((_, idx) => idx % stride === 0)
+  return { frames, ...(trajectory.metadata && { metadata: { ...trajectory.metadata } }) }
+}
+
+// Frames of several trajectories of the same system (e.g. restarts of an MD run) in order.
+// Steps are kept as is and metadata is taken from the first trajectory.
+export function concat_trajectories(trajectories: readonly TrajectoryType[]): TrajectoryType {
+  const frames = trajectories.flatMap(({ frames }) => frames)
+  if (frames.length === 0) throw new Error(`Cannot concatenate trajectories without frames`)
+  const elements = frames[0].structure.sites.map(get_majority_element).join()
+  trajectories.forEach(({ frames: traj_frames }, traj_idx) => {
+    const structure = traj_frames[0]?.structure
+    if (structure && structure.sites.map(get_majority_element).join() !== elements) {
+      throw new Error(`Trajectory ${traj_idx} has different sites than trajectory 0`)
+    }
+  })
+  const metadata = trajectories[0].metadata
+  return { frames, ...(metadata && { metadata: { ...metadata } }) }
+}
+
+// Trajectory restricted to sites of the given elements (judged by majority element on the
+// first frame). Site fields and per-site frame metadata (PER_SITE_FRAME_KEYS) are filtered
+// alongside, system-wide values like energies and stresses are kept unchanged.
+export function filter_trajectory_species(
+  trajectory: TrajectoryType,
+  elements: readonly string[],
+): TrajectoryType {
+  const first = trajectory.frames[0]?.structure
+  if (!first) return { frames: [] }
+  const n_sites = first.sites.length
+  const keep = first.sites.flatMap((site, idx) =>
+    elements.includes(get_majority_element(site) ?? ``) ? [idx] : [],
+  )
+  const frames = trajectory.frames.map(({ structure, step, metadata }, frame_idx) => {
+    if (structure.sites.length !== n_sites) {
+      throw new Error(
+        `Frame ${frame_idx} has ${structure.sites.length} sites, expected ${n_sites}`,
+      )
+    }
+    const frame: TrajectoryFrame = {
+      structure: select_sites(structure, keep),
+      step,
+    }
+    if (metadata) {
+      frame.metadata = Object.fromEntries(
+        Object.entries(metadata).map(([key, value]) => [
+          key,
+          PER_SITE_FRAME_KEYS.includes(key) && Array.isArray(value) && value.length === n_sites
+            ? keep.map((idx) => value[idx])
+            : value,
+        ]),
+      )
+    }
+    return frame
+  })
+  return { frames, ...(trajectory.metadata && { metadata: { ...trajectory.metadata } }) }
+}
+
+// Numeric scalar frame metadata keys (energy, pressure, temperature, ...) in order of first
+// appearance across frames
+export function frame_property_keys(trajectory: TrajectoryType): string[] {
+  const keys = new Set<string>()
+  for (const { metadata } of trajectory.frames) {
+    for (const [key, value] of Object.entries(metadata ?? {})) {
+      if (typeof value === `number` && Number.isFinite(value)) keys.add(key)
+    }
+  }
+  return [...keys]
+}
+
+// Per-frame series of a numeric metadata property, null for frames without it
+export const frame_property = (trajectory: TrajectoryType, key: string): (number | null)[] =>
+  trajectory.frames.map(({ metadata }) => {
+    const value = metadata?.[key]
+    return typeof value === `number` && Number.isFinite(value) ? value : null
+  })
+
+// Per-frame 3x3 stress tensors (units as parsed), null for frames without one
+export const frame_stresses = (trajectory: TrajectoryType): (Matrix3x3 | null)[] =>
+  trajectory.frames.map(({ metadata }) =>
+    math.is_square_matrix(metadata?.stress, 3) ? (metadata.stress as Matrix3x3) : null,
+  )
+
+// Trajectory with continuous coordinates instead of ones wrapped into the cell, so atoms
+// crossing a boundary keep moving instead of jumping to the opposite face. Fractional
+// coordinates are recomputed from the unwrapped Cartesian ones and may leave [0, 1).
+export function unwrap_trajectory(trajectory: TrajectoryType): TrajectoryType {
+  const { frames } = trajectory
+  if (frames.length === 0) return { frames: [] }
+  const n_sites = frames[0].structure.sites.length
+  frames.forEach(({ structure }, frame_idx) => {
+    if (structure.sites.length !== n_sites) {
+      throw new Error(
+        `Frame ${frame_idx} has ${structure.sites.length} sites, expected ${n_sites}`,
+      )
+    }
+  })
+  const positions = unwrap_positions(frames.map(({ structure }) => structure))
+  const unwrapped = frames.map((frame, frame_idx) => {
+    const { structure } = frame
+    const lattice = `lattice` in structure ? structure.lattice : null
+    const cart_to_frac = lattice && math.create_cart_to_frac(lattice.matrix)
+    const sites = structure.sites.map((site, site_idx) => {
+      const xyz = positions[frame_idx][site_idx]
+      return { ...site, xyz, abc: cart_to_frac ? cart_to_frac(xyz) : site.abc }
+    })
+    return { ...frame, structure: { ...structure, sites } as AnyStructure }
+  })
+  return {
+    frames: unwrapped,
+    ...(trajectory.metadata && { metadata: { ...trajectory.metadata } }),
+  }
+}
@@ -0,0 +1,227 @@
+// Unbiased free-energy profiles from biased sampling (umbrella windows, static bias
+// potentials): the weighted histogram analysis method (WHAM, Kumar et al., J. Comput.
+// Chem. 13, 1011, 1992) and the bin-less multistate Bennett acceptance ratio (MBAR,
+// Shirts & Chodera, J. Chem. Phys. 129, 124105, 2008), with bootstrap uncertainties
+import { K_B_EV } from '$lib/constants'
+import type { Vec2 } from '$lib/math'
+import * as math from '$lib/math'
+import type { FreeEnergyProfile } from './collective-variables'
+import { free_energy_profile } from './collective-variables'
+
+// bias energy in eV added to the potential at a CV value
+export type BiasPotential = (cv: number) => number
+
+// Umbrella restraint U(s) = k / 2 (s − center)² with k in eV per CV unit²
+export const harmonic_bias =
+  (center: number, spring_constant: number): BiasPotential =>
+  (cv) =>
+    0.5 * spring_constant * (cv - center) ** 2
+
+export type BiasedWindow = {
+  samples: readonly number[] // CV values sampled under this bias
+  bias: BiasPotential
+}
+
+export type ReweightingOptions = {
+  n_bins?: number // default 50
+  range?: Vec2 // histogram range (default: min and max over all samples)
+  temperature?: number // K (default 300)
+  tolerance?: number // max change of the window free energies in kT to stop (default 1e-7)
+  max_iter?: number // self-consistent iterations (default 10000)
+  n_bootstrap?: number // resamples for uncertainties, 0 to skip (default 0)
+  seed?: number // bootstrap PRNG seed (default 0)
+}
+
+export type ReweightedProfile = FreeEnergyProfile & {
+  window_free_energies: number[] // eV per window relative to the first one
+  // eV, bootstrap standard deviation of free_energy per bin (NaN where undetermined),
+  // null without bootstrapping
+  uncertainty: number[] | null
+  n_iter: number
+  converged: boolean
+}
+
+type Estimate = {
+  log_density: number[] // ln of the normalized unbiased density per bin
+  counts: number[]
+  f_k: number[] // window free energies in kT
+  n_iter: number
+  converged: boolean
+}
+type Estimator = (windows: readonly BiasedWindow[]) => Estimate
+
+// reduce instead of spreading, pooled MBAR samples can exceed the argument limit
+const max_of = (values: readonly number[]): number =>
+  values.reduce((max, val) => (val > max ? val : max), -Infinity)
+
+const log_sum_exp = (values: readonly number[]): number => {
+  const max = max_of(values)
+  if (max === -Infinity) return -Infinity
+  return max + Math.log(values.reduce((sum, val) => sum + Math.exp(val - max), 0))
+}
+
+// Iterate f ↦ update(f) (shifted so f_0 = 0) until the largest change drops below tol
+function self_consistent(
+  n_windows: number,
+  update: (f_k: number[]) => number[],
+  tolerance: number,
+  max_iter: number,
+) {
+  let f_k = Array<number>(n_windows).fill(0)
+  for (let iter = 1; iter <= max_iter; iter++) {
+    const next = update(f_k)
+    const shifted = next.map((val) => val - next[0])
+    const change = Math.max(...shifted.map((val, idx) => Math.abs(val - f_k[idx])))
+    f_k = shifted
+    if (change < tolerance) return { f_k, n_iter: iter, converged: true }
+  }
+  return { f_k, n_iter: max_iter, converged: false }
+}
+
+function validate_windows(windows: readonly BiasedWindow[]): number[] {
+  if (windows.length === 0) throw new Error(`Need at least one biased window`)
+  const empty = windows.findIndex(({ samples }) => samples.length === 0)
+  if (empty !== -1) throw new Error(`Window ${empty} has no samples`)
+  const finite = windows.flatMap(({ samples }) => samples.filter(Number.isFinite))
+  if (finite.length === 0) throw new Error(`Need at least one finite sample`)
+  return finite
+}
+
+// Shared driver: binning, bootstrap resampling and conversion to eV
+function reweight(
+  windows: readonly BiasedWindow[],
+  options: ReweightingOptions,
+  make_estimator: (lo: number, width: number, beta: number) => Estimator,
+): ReweightedProfile {
+  const { n_bins = 50, temperature = 300, n_bootstrap = 0, seed = 0 } = options
+  if (!Number.isInteger(n_bins) || n_bins < 1) {
+    throw new Error(`n_bins must be a positive integer, got ${n_bins}`)
+  }
+  const finite = validate_windows(windows)
+  const [lo, hi] = options.range ?? [-max_of(finite.map((val) => -val)), max_of(finite)]
+  const width = (hi - lo) / n_bins || 1
+  const k_t = K_B_EV * temperature
+  const estimate = make_estimator(lo, width, 1 / k_t)
+
+  const to_free_energy = (log_density: number[]) => {
+    const max = max_of(log_density)
+    return log_density.map((val) => k_t * (max - val))
+  }
+  const { log_density, counts, f_k, n_iter, converged } = estimate(windows)
+  const free_energy = to_free_energy(log_density)
+
+  let uncertainty: number[] | null = null
+  if (n_bootstrap > 0) {
+    const random = math.mulberry32(seed)
+    const sums = free_energy.map(() => [0, 0, 0]) // n, Σx, Σx² of finite resamples
+    for (let rep = 0; rep < n_bootstrap; rep++) {
+      const resampled = windows.map(({ samples, bias }) => ({
+        samples: samples.map(() => samples[Math.floor(random() * samples.length)]),
+        bias,
+      }))
+      to_free_energy(estimate(resampled).log_density).forEach((val, bin) => {
+        if (!Number.isFinite(val)) return
+        sums[bin][0]++
+        sums[bin][1] += val
+        sums[bin][2] += val * val
+      })
+    }
+    uncertainty = sums.map(([count, sum, sum_sq]) => {
+      if (count < 2) return NaN
+      return Math.sqrt(Math.max(0, (sum_sq - (sum * sum) / count) / (count - 1)))
+    })
+  }
+  return {
+    centers: counts.map((_, bin) => lo + (bin + 0.5) * width),
+    probability: log_density.map(Math.exp),
+    free_energy,
+    counts,
+    window_free_energies: f_k.map((val) => val * k_t),
+    uncertainty,
+    n_iter,
+    converged,
+  }
+}
+
+// WHAM on a common histogram: p_i = Σ_k n_ki / Σ_k N_k e^(f_k − βU_k(s_i)) and
+// e^(−f_k) = Σ_i p_i e^(−βU_k(s_i)), iterated to self-consistency (in log space)
+export function wham(
+  windows: readonly BiasedWindow[],
+  options: ReweightingOptions = {},
+): ReweightedProfile {
+  const { n_bins = 50, tolerance = 1e-7, max_iter = 10_000 } = options
+  return reweight(windows, options, (lo, width, beta) => (wins) => {
+    const centers = Array.from({ length: n_bins }, (_, bin) => lo + (bin + 0.5) * width)
+    const reduced_bias = wins.map(({ bias }) => centers.map((cv) => beta * bias(cv)))
+    const log_n = wins.map(({ samples }) => Math.log(samples.length))
+    const counts = Array<number>(n_bins).fill(0)
+    for (const { samples } of wins) {
+      for (const val of samples) {
+        if (!(val >= lo && val <= lo + n_bins * width)) continue // out of range or NaN
+        counts[Math.min(Math.floor((val - lo) / width), n_bins - 1)]++
+      }
+    }
+    const log_p = (f_k: number[]) =>
+      counts.map((count, bin) => {
+        if (count === 0) return -Infinity
+        const terms = f_k.map((f_val, win) => log_n[win] + f_val - reduced_bias[win][bin])
+        return Math.log(count) - log_sum_exp(terms)
+      })
+    const result = self_consistent(
+      wins.length,
+      (f_k) => {
+        const log_probs = log_p(f_k)
+        return reduced_bias.map(
+          (row) => -log_sum_exp(log_probs.map((val, bin) => val - row[bin])),
+        )
+      },
+      tolerance,
+      max_iter,
+    )
+    const log_probs = log_p(result.f_k)
+    const norm = log_sum_exp(log_probs) + Math.log(width)
+    return { ...result, counts, log_density: log_probs.map((val) => val - norm) }
+  })
+}
+
+// MBAR: window free energies f_k = −ln Σ_n e^(−βU_k(x_n)) / Σ_l N_l e^(f_l − βU_l(x_n))
+// over all pooled samples without binning, then each sample's unbiased weight
+// 1 / Σ_l N_l e^(f_l − βU_l(x_n)) enters the histogram. Less sensitive to the bin width
+// than WHAM, at the cost of evaluating every bias on every sample.
+export function mbar(
+  windows: readonly BiasedWindow[],
+  options: ReweightingOptions = {},
+): ReweightedProfile {
+  const { n_bins = 50, tolerance = 1e-7, max_iter = 10_000, temperature = 300 } = options
+  return reweight(windows, options, (lo, width, beta) => (wins) => {
+    const finite = wins.map(({ samples }) => samples.filter(Number.isFinite))
+    const pooled = finite.flat()
+    const reduced_bias = wins.map(({ bias }) => pooled.map((cv) => beta * bias(cv)))
+    const log_n = finite.map((samples) => Math.log(samples.length))
+    const log_denominators = (f_k: number[]) =>
+      pooled.map((_, idx) =>
+        log_sum_exp(f_k.map((f_val, win) => log_n[win] + f_val - reduced_bias[win][idx])),
+      )
+    const result = self_consistent(
+      wins.length,
+      (f_k) => {
+        const log_denom = log_denominators(f_k)
+        return reduced_bias.map(
+          (row) => -log_sum_exp(row.map((u_val, idx) => -u_val - log_denom[idx])),
+        )
+      },
+      tolerance,
+      max_iter,
+    )
+    const log_weights = log_denominators(result.f_k).map((val) => -val)
+    const max_log = max_of(log_weights)
+    const profile = free_energy_profile(pooled, {
+      n_bins,
+      range: [lo, lo + n_bins * width],
+      temperature,
+      weights: log_weights.map((val) => Math.exp(val - max_log)),
+    })
+    const log_density = profile.probability.map(Math.log)
+    return { ...result, counts: profile.counts, log_density }
+  })
+}
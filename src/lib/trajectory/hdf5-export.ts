@@ -0,0 +1,113 @@
+// HDF5 trajectory export in the torch-sim style layout parse_hdf5_trajectory reads back:
+// positions (frames × atoms × 3, Å), atomic_numbers, cell (frames × 3 × 3 with lattice
+// vectors as columns, as torch-sim stores them), steps, and numeric frame metadata as one
+// dataset per key under frame_properties/. Per-frame datasets hold one frame per chunk so
+// read_hdf5_frame only decompresses the frame it asks for.
+import { SYMBOL_TO_ATOMIC_NUMBER } from '$lib/composition/parse'
+import * as math from '$lib/math'
+import * as h5wasm from 'h5wasm'
+import type { TrajectoryType } from './index'
+
+export type Hdf5ExportOptions = {
+  compression_level?: number // gzip level 0-9 of chunked datasets (default 4, 0 = off)
+}
+
+export async function trajectory_to_hdf5(
+  trajectory: TrajectoryType,
+  options: Hdf5ExportOptions = {},
+): Promise<ArrayBuffer> {
+  const { frames } = trajectory
+  if (frames.length === 0) throw new Error(`Cannot write an empty trajectory to HDF5`)
+  const { compression_level = 4 } = options
+  if (!Number.isInteger(compression_level) || compression_level < 0 || compression_level > 9) {
+    throw new Error(`compression_level must be an integer in 0-9, got ${compression_level}`)
+  }
+  const n_frames = frames.length
+  const n_atoms = frames[0].structure.sites.length
+  const numbers = frames.map(({ structure }, frame_idx) => {
+    if (structure.sites.length !== n_atoms) {
+      throw new Error(
+        `HDF5 export needs ${n_atoms} sites in every frame, frame ${frame_idx} has ${structure.sites.length}`,
+      )
+    }
+    return structure.sites.map(({ species }) => {
+      const number = SYMBOL_TO_ATOMIC_NUMBER[species[0].element]
+      if (!number) throw new Error(`No atomic number for ${species[0].element}`)
+      return number
+    })
+  })
+  // species are stored once unless they change between frames (e.g. transmutation MC)
+  const fixed_species = numbers.every((row) =>
+    row.every((num, idx) => num === numbers[0][idx]),
+  )
+
+  const positions = new Float64Array(n_frames * n_atoms * 3)
+  frames.forEach(({ structure }, frame_idx) => {
+    const offset = frame_idx * n_atoms * 3
+    structure.sites.forEach(({ xyz }, idx) => positions.set(xyz, offset + idx * 3))
+  })
+  const lattices = frames.map(({ structure }) =>
+    `lattice` in structure ? structure.lattice.matrix : null,
+  )
+  const cells = lattices.every((matrix): matrix is math.Matrix3x3 => matrix !== null)
+    ? Float64Array.from(lattices.flatMap((matrix) => math.transpose_3x3_matrix(matrix).flat()))
+    : null
+  const scalar_keys = Object.keys(frames[0].metadata ?? {}).filter(
+    (key) =>
+      key !== `step` &&
+      frames.every(({ metadata }) => Number.isFinite(metadata?.[key] as number)),
+  )
+
+  const chunked = (shape: number[]) => ({
+    shape,
+    chunks: [1, ...shape.slice(1)],
+    ...(compression_level > 0 && {
+      compression: `gzip` as const,
+      compression_opts: [compression_level],
+    }),
+  })
+  const { FS } = await h5wasm.ready
+  const temp_filename = `trajectory-${Date.now()}-${Math.random().toString(36).slice(2)}.h5`
+  const h5_file = new h5wasm.File(temp_filename, `w`)
+  try {
+    h5_file.create_dataset({
+      name: `positions`,
+      data: positions,
+      ...chunked([n_frames, n_atoms, 3]),
+    })
+    h5_file.create_dataset({
+      name: `atomic_numbers`,
+      data: Int32Array.from(fixed_species ? numbers[0] : numbers.flat()),
+      shape: fixed_species ? [n_atoms] : [n_frames, n_atoms],
+    })
+    if (cells) {
+      h5_file.create_dataset({ name: `cell`, data: cells, ...chunked([n_frames, 3, 3]) })
+    }
+    h5_file.create_dataset({
+      name: `steps`,
+      data: Int32Array.from(frames, ({ step }) => step),
+      shape: [n_frames],
+    })
+    if (scalar_keys.length > 0) {
+      const group = h5_file.create_group(`frame_properties`)
+      for (const key of scalar_keys) {
+        group.create_dataset({
+          name: key,
+          data: Float64Array.from(frames, ({ metadata }) => metadata?.[key] as number),
+          shape: [n_frames],
+        })
+      }
+    }
+  } finally {
+    h5_file.close()
+  }
+  try {
+    const bytes = FS.readFile(temp_filename)
+    // copy into a plain ArrayBuffer (bytes.buffer may be a SharedArrayBuffer view)
+    const buffer = new ArrayBuffer(bytes.byteLength)
+    new Uint8Array(buffer).set(bytes)
+    return buffer
+  } finally {
+    FS.unlink(temp_filename)
+  }
+}
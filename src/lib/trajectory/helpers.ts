@@ -96,8 +96,9 @@ export const create_trajectory_frame = (
   pbc: Pbc | undefined,
   step: number,
   metadata: Record<string, unknown> = {},
+  force_data?: number[][],
 ): TrajectoryFrame => ({
-  structure: create_structure(positions, elements, lattice_matrix, pbc),
+  structure: create_structure(positions, elements, lattice_matrix, pbc, force_data),
   step,
   metadata,
 })
@@ -8,12 +8,83 @@ export { default as Trajectory } from './Trajectory.svelte'
 export { default as TrajectoryError } from './TrajectoryError.svelte'
 export { default as TrajectoryExportPane } from './TrajectoryExportPane.svelte'
 export { default as TrajectoryInfoPane } from './TrajectoryInfoPane.svelte'
+export { compute_adp_tensors } from './adp'
+export type { AdpOptions, AdpResult } from './adp'
+export { arrhenius_fit, arrhenius_predict } from './arrhenius'
+export type { ArrheniusFit, ArrheniusOptions, ArrheniusPrediction } from './arrhenius'
+export { bv_sum_trajectory } from './bond-valence'
+export type { BvsTrajectory, BvsTrajectoryOptions, RedoxEvent } from './bond-valence'
+export { find_cavities, track_cavities, with_cavity_sites } from './cavity'
+export type {
+  Cavity,
+  CavityOptions,
+  CavityTrack,
+  CavityTracking,
+  CavityTrackingOptions,
+} from './cavity'
+export {
+  coordination_cv,
+  distance_cv,
+  free_energy_profile,
+  project_trajectory,
+  site_field_cv,
+} from './collective-variables'
+export type {
+  CollectiveVariable,
+  CvProjection,
+  FreeEnergyOptions,
+  FreeEnergyProfile,
+  ProjectionOptions,
+} from './collective-variables'
 export {
   energy_data_extractor,
   force_stress_data_extractor,
   full_data_extractor,
   structural_data_extractor,
 } from './extract'
+export {
+  concat_trajectories,
+  filter_trajectory_species,
+  frame_property,
+  frame_property_keys,
+  frame_stresses,
+  PER_SITE_FRAME_KEYS,
+  slice_trajectory,
+  unwrap_positions,
+  unwrap_trajectory,
+} from './frames'
+export { harmonic_bias, mbar, wham } from './free-energy'
+export type {
+  BiasedWindow,
+  BiasPotential,
+  ReweightedProfile,
+  ReweightingOptions,
+} from './free-energy'
+export { find_interfaces, track_interfaces } from './interface'
+export type {
+  InterfaceOptions,
+  InterfaceProfile,
+  InterfaceTrack,
+  InterfaceTracking,
+  InterfaceTrackingOptions,
+  SolidClassifier,
+  SolidLiquidInterface,
+} from './interface'
+export { compute_msd, MSD_COMPONENTS } from './msd'
+export type { MsdComponent, MsdGroup, MsdOptions, MsdResult } from './msd'
+export {
+  largest_cluster_series,
+  largest_solid_cluster,
+  nucleation_mfpt,
+} from './nucleation'
+export type {
+  ClusterSeries,
+  ClusterSeriesOptions,
+  MfptOptions,
+  MfptResult,
+  SolidCluster,
+  SolidClusterOptions,
+} from './nucleation'
 export {
   generate_axis_labels,
   generate_axis_scale_types,
@@ -22,6 +93,35 @@ export {
   should_hide_plot,
 } from './plotting'
 export type { PlotSeriesOptions } from './plotting'
+export { calc_profile, calc_trajectory_profile } from './profile'
+export type {
+  Profile,
+  ProfileOptions,
+  ProfileValue,
+  TrajectoryProfileOptions,
+} from './profile'
+export { rotational_dynamics } from './rotation'
+export { dynamic_structure_factor, intermediate_scattering } from './scattering'
+export type {
+  DynamicStructureFactor,
+  ScatteringFunction,
+  ScatteringOptions,
+  ScatteringResult,
+} from './scattering'
+export type {
+  MolecularAxis,
+  RotationalDynamics,
+  RotationalSpecies,
+  RotationOptions,
+} from './rotation'
+export { reference_sites_from_wyckoff, site_occupancy } from './site-occupancy'
+export type { ReferenceSite, SiteOccupancy, SiteOccupancyOptions } from './site-occupancy'
+export {
+  TRANSCODE_FORMATS,
+  transcode_trajectory,
+  transcode_trajectory_to_string,
+} from './transcode'
+export type { TranscodeFormat, TranscodeOptions } from './transcode'
 
 export type TrajectoryFormat = `hdf5` | `json` | `xyz` | `xdatcar` | `traj` | `unknown`
 export type { AtomTypeMapping } from './types'
@@ -0,0 +1,174 @@
+// Solid-liquid interface detection in two-phase MD cells from per-atom structure
+// classification, and interface tracking over time for crystal growth (or melting)
+// velocities
+import type { OrientationOptions } from '$lib/order-params'
+import { compute_orientations } from '$lib/order-params'
+import { linear_fit, theil_sen } from '$lib/stats'
+import type { Crystal } from '$lib/structure/index'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+import { calc_profile } from './profile'
+
+// per-atom solid (true) / liquid (false) labels of a frame
+export type SolidClassifier = (structure: Crystal) => boolean[]
+
+export type InterfaceOptions = {
+  // custom classifier, or template matching against a reference crystal where atoms with
+  // a matching lattice orientation count as solid (see compute_orientations)
+  classify: SolidClassifier | OrientationOptions
+  axis?: 0 | 1 | 2 // lattice vector normal to the interfaces (default 2)
+  n_bins?: number // profile bins along the axis (default 50)
+  smoothing?: number // bins in the periodic running mean of the solid fraction (default 3)
+  threshold?: number // solid fraction at the interface (default 0.5)
+}
+
+export type SolidLiquidInterface = {
+  position: number // Å along the plane normal, in [0, period)
+  // +1 if the liquid lies at larger heights (solid fraction drops across the interface),
+  // -1 otherwise; crystal growth moves the interface along direction
+  direction: 1 | -1
+}
+
+export type InterfaceProfile = {
+  positions: number[] // Å, bin centers
+  solid_fraction: number[] // smoothed per-bin fraction of solid atoms
+  period: number // Å, cell height along the normal
+  n_solid: number
+  interfaces: SolidLiquidInterface[] // sorted by position
+}
+
+export const as_classifier = (classify: InterfaceOptions[`classify`]): SolidClassifier =>
+  typeof classify === `function`
+    ? classify
+    : (structure) => compute_orientations(structure, classify).map((rot) => rot !== null)
+
+// Locate interfaces as the threshold crossings of the smoothed solid fraction profile
+export function find_interfaces(
+  structure: Crystal,
+  options: InterfaceOptions,
+): InterfaceProfile {
+  const { axis = 2, n_bins = 50, smoothing = 3, threshold = 0.5 } = options
+  const is_solid = as_classifier(options.classify)(structure)
+  if (is_solid.length !== structure.sites.length) {
+    const [n_labels, n_sites] = [is_solid.length, structure.sites.length]
+    throw new Error(`Classifier returned ${n_labels} labels for ${n_sites} sites`)
+  }
+  const profile = calc_profile(structure, {
+    axis,
+    n_bins,
+    value: (_site, site_idx) => (is_solid[site_idx] ? 1 : 0),
+  })
+  const raw = profile.values ?? []
+  const window = Math.max(1, Math.round(smoothing))
+  const start = -Math.floor(window / 2)
+  const solid_fraction = raw.map((_, bin) => {
+    let [sum, count] = [0, 0]
+    for (let offset = start; offset < start + window; offset++) {
+      const val = raw[(((bin + offset) % n_bins) + n_bins) % n_bins]
+      if (Number.isNaN(val)) continue // empty bin
+      sum += val
+      count++
+    }
+    return count > 0 ? sum / count : NaN
+  })
+
+  const spacing = profile.positions[0] * 2 // first bin center sits half a bin above 0
+  const period = spacing * n_bins
+  const interfaces: SolidLiquidInterface[] = []
+  solid_fraction.forEach((frac_1, bin) => {
+    const frac_2 = solid_fraction[(bin + 1) % n_bins]
+    const crosses_down = frac_1 >= threshold && frac_2 < threshold
+    const crosses_up = frac_1 < threshold && frac_2 >= threshold
+    if (!crosses_down && !crosses_up) return
+    const shift = ((threshold - frac_1) / (frac_2 - frac_1)) * spacing
+    const height = profile.positions[bin] + shift
+    interfaces.push({ position: height % period, direction: crosses_down ? 1 : -1 })
+  })
+  interfaces.sort((if_1, if_2) => if_1.position - if_2.position)
+  const n_solid = is_solid.filter(Boolean).length
+  return { positions: profile.positions, solid_fraction, period, n_solid, interfaces }
+}
+
+export type InterfaceTrackingOptions = InterfaceOptions & {
+  start_frame?: number // default 0
+  stride?: number // default 1
+  time_step?: number // time per MD step, velocities are in Å per this unit (default 1)
+  // Theil-Sen instead of least-squares velocities, robust to frames with misdetected
+  // interfaces (default false)
+  robust?: boolean
+}
+
+export type InterfaceTrack = {
+  direction: 1 | -1
+  times: number[]
+  positions: number[] // Å, unwrapped across the periodic boundary
+  velocity: number // slope of position vs time (NaN if seen at a single time)
+  // standard error of the least-squares slope, or with robust half the interquartile
+  // range of the pairwise slopes
+  velocity_err: number
+  growth_velocity: number // velocity × direction, > 0 when the crystal grows
+}
+
+export type InterfaceTracking = {
+  times: number[]
+  profiles: InterfaceProfile[]
+  tracks: InterfaceTrack[] // one per interface of the first frame
+  growth_velocity: number // mean over tracks (NaN without interfaces)
+}
+
+const fit_velocity = (times: number[], positions: number[], robust: boolean) => {
+  if (new Set(times).size < 2) return { velocity: NaN, velocity_err: NaN }
+  if (!robust) {
+    const { slope, slope_err } = linear_fit(times, positions)
+    return { velocity: slope, velocity_err: slope_err }
+  }
+  const { slope, slope_range } = theil_sen(times, positions)
+  return { velocity: slope, velocity_err: (slope_range[1] - slope_range[0]) / 2 }
+}
+
+// Track the interfaces of the first sampled frame through the trajectory: each one
+// follows the nearest (minimum-image) interface of the same direction in later frames.
+// Frames should be close enough that interfaces move less than half their spacing.
+export function track_interfaces(
+  trajectory: TrajectoryType,
+  options: InterfaceTrackingOptions,
+): InterfaceTracking {
+  const { start_frame = 0, stride = 1, time_step = 1, robust = false } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length === 0) throw new Error(`No frames left after start_frame/stride`)
+  const times: number[] = []
+  const profiles = frames.map(({ structure, step }) => {
+    if (!is_crystal(structure)) {
+      throw new Error(`Interface tracking requires periodic structures in every frame`)
+    }
+    times.push(step * time_step)
+    return find_interfaces(structure, options)
+  })
+
+  const tracks = profiles[0].interfaces.map(({ position, direction }) => {
+    const track = { direction, times: [times[0]], positions: [position] }
+    profiles.slice(1).forEach(({ interfaces, period }, idx) => {
+      const last = track.positions[track.positions.length - 1]
+      let best_delta: number | null = null
+      for (const candidate of interfaces) {
+        if (candidate.direction !== direction) continue
+        const delta = candidate.position - last
+        const wrapped = delta - period * Math.round(delta / period)
+        if (best_delta === null || Math.abs(wrapped) < Math.abs(best_delta)) {
+          best_delta = wrapped
+        }
+      }
+      if (best_delta === null) return // interface vanished in this frame
+      track.times.push(times[idx + 1])
+      track.positions.push(last + best_delta)
+    })
+    const { velocity, velocity_err } = fit_velocity(track.times, track.positions, robust)
+    return { ...track, velocity, velocity_err, growth_velocity: velocity * direction }
+  })
+  const growth_velocity =
+    tracks.reduce((sum, { growth_velocity: vel }) => sum + vel, 0) / tracks.length
+  return { times, profiles, tracks, growth_velocity }
+}
@@ -0,0 +1,186 @@
+// Mean-square displacements and tracer diffusion coefficients from MD trajectories, for
+// all atoms, every element and custom atom selections in a single pass, split into
+// Cartesian and ab-plane vs c-axis components to resolve anisotropic diffusion
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { linear_fit } from '$lib/stats'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_mask } from '$lib/structure/select'
+import { unwrap_positions } from './frames'
+import type { TrajectoryType } from './index'
+
+// total, Cartesian axes, in-plane (ab) and along the normal of the ab plane (c)
+export const MSD_COMPONENTS = [`total`, `x`, `y`, `z`, `ab`, `c`] as const
+export type MsdComponent = (typeof MSD_COMPONENTS)[number]
+// dimensionality d of each component in MSD = 2 d D t
+const MSD_DIMS: Record<MsdComponent, number> = { total: 3, x: 1, y: 1, z: 1, ab: 2, c: 1 }
+
+export type MsdOptions = {
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  time_step?: number // time per MD step, diffusion in Å² per this unit (default 1)
+  max_lag?: number // longest lag in sampled frames (default half the sampled frames)
+  // named atom groups in addition to `all` and the elements, evaluated on the first
+  // sampled frame (e.g. { surface: Select.z_above(12) })
+  selections?: Record<string, AtomSelection>
+  remove_drift?: boolean // subtract each frame's mean displacement of all atoms (default true)
+  // unwrap positions across periodic boundaries, disable for already unwrapped coordinates
+  // like LAMMPS xu/yu/zu dumps or unwrap_trajectory output (default true)
+  unwrap?: boolean
+  // fraction of the lag range used for the linear diffusion fit, skipping the ballistic
+  // start and the noisy tail with few time origins (default [0.2, 0.8])
+  fit_range?: [number, number]
+}
+
+export type MsdGroup = {
+  n_atoms: number
+  msd: Record<MsdComponent, number[]> // Å² per lag, averaged over atoms and time origins
+  diffusion: Record<MsdComponent, number> // slope / 2d in Å² per time unit
+  diffusion_err: Record<MsdComponent, number> // standard error of the fit
+}
+
+export type MsdResult = {
+  lag_times: number[] // time of each lag, starting at 0
+  groups: Record<string, MsdGroup> // `all`, element symbols and named selections
+  n_frames: number
+}
+
+const per_component = <T>(make: (component: MsdComponent) => T) =>
+  Object.fromEntries(MSD_COMPONENTS.map((comp) => [comp, make(comp)])) as Record<
+    MsdComponent,
+    T
+  >
+
+// MSD(τ) = <|r_i(t + τ) − r_i(t)|²> averaged over all time origins t and atoms i of each
+// group, plus diffusion coefficients D = slope / 2d from a linear fit over fit_range.
+// Lag times assume equally spaced sampled frames. Sampled frames should be closer than
+// half a cell per atom for unwrapping.
+export function compute_msd(trajectory: TrajectoryType, options: MsdOptions = {}): MsdResult {
+  const {
+    start_frame = 0,
+    stride = 1,
+    time_step = 1,
+    selections = {},
+    remove_drift = true,
+    unwrap = true,
+    fit_range = [0.2, 0.8],
+  } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length < 2) {
+    throw new Error(`Need at least 2 frames to compute MSDs, got ${frames.length}`)
+  }
+  const structures = frames.map(({ structure }) => structure)
+  const n_sites = structures[0].sites.length
+  if (structures.some(({ sites }) => sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+  const max_lag = options.max_lag ?? Math.floor(frames.length / 2)
+  if (!Number.isInteger(max_lag) || max_lag < 1 || max_lag >= frames.length) {
+    throw new Error(`max_lag must be an integer in [1, ${frames.length - 1}], got ${max_lag}`)
+  }
+  const [fit_lo, fit_hi] = fit_range
+  if (!(fit_lo >= 0 && fit_lo < fit_hi && fit_hi <= 1)) {
+    throw new Error(`fit_range must satisfy 0 <= start < end <= 1, got [${fit_lo}, ${fit_hi}]`)
+  }
+
+  const positions = unwrap
+    ? unwrap_positions(structures)
+    : structures.map(({ sites }) => sites.map((site) => [...site.xyz] as Vec3))
+  if (remove_drift && n_sites > 0) {
+    const origin = positions[0]
+    for (const frame_pos of positions) {
+      const drift = math.scale(
+        math.add(...frame_pos.map((pos, site_idx) => math.subtract(pos, origin[site_idx]))),
+        1 / n_sites,
+      )
+      frame_pos.forEach((pos, site_idx) => {
+        frame_pos[site_idx] = math.subtract(pos, drift)
+      })
+    }
+  }
+
+  // groups each atom belongs to, so every displacement is computed once
+  const first = structures[0]
+  const group_names = [`all`]
+  const site_groups: number[][] = first.sites.map(() => [0])
+  first.sites.forEach((site, site_idx) => {
+    const element = site.species[0]?.element
+    if (!element) return
+    if (!group_names.includes(element)) group_names.push(element)
+    site_groups[site_idx].push(group_names.indexOf(element))
+  })
+  for (const [name, selection] of Object.entries(selections)) {
+    if (group_names.includes(name)) {
+      throw new Error(`Selection name ${name} clashes with an element or 'all' group`)
+    }
+    group_names.push(name)
+    selection_mask(first, selection).forEach((selected, site_idx) => {
+      if (selected) site_groups[site_idx].push(group_names.length - 1)
+    })
+  }
+  const n_atoms = group_names.map(
+    (_, group_idx) => site_groups.filter((groups) => groups.includes(group_idx)).length,
+  )
+
+  // unit normal of the first frame's ab plane splits in-plane from c-axis motion
+  const normal: Vec3 =
+    `lattice` in first
+      ? math.normalize_vec(math.cross_3d(first.lattice.matrix[0], first.lattice.matrix[1]))
+      : [0, 0, 1]
+  const sums = group_names.map(() => per_component(() => Array<number>(max_lag + 1).fill(0)))
+  for (let lag = 1; lag <= max_lag; lag++) {
+    for (let origin = 0; origin + lag < positions.length; origin++) {
+      positions[origin + lag].forEach((pos, site_idx) => {
+        const disp = math.subtract(pos, positions[origin][site_idx])
+        const [dx, dy, dz] = disp
+        const total = dx * dx + dy * dy + dz * dz
+        const along_c = math.dot(disp, normal) ** 2
+        const parts: Record<MsdComponent, number> = {
+          total,
+          x: dx * dx,
+          y: dy * dy,
+          z: dz * dz,
+          ab: total - along_c,
+          c: along_c,
+        }
+        for (const group_idx of site_groups[site_idx]) {
+          for (const comp of MSD_COMPONENTS) sums[group_idx][comp][lag] += parts[comp]
+        }
+      })
+    }
+  }
+
+  const frame_time = (frames[1].step - frames[0].step) * time_step
+  const lag_times = Array.from({ length: max_lag + 1 }, (_, lag) => lag * frame_time)
+  const fit_lags = lag_times
+    .map((_, lag) => lag)
+    .filter((lag) => lag / max_lag >= fit_lo && lag / max_lag <= fit_hi)
+  const groups: Record<string, MsdGroup> = {}
+  group_names.forEach((name, group_idx) => {
+    const msd = per_component((comp) =>
+      sums[group_idx][comp].map((sum, lag) =>
+        lag === 0 ? 0 : sum / (n_atoms[group_idx] * (positions.length - lag)),
+      ),
+    )
+    const fits = per_component((comp) =>
+      fit_lags.length >= 2 && n_atoms[group_idx] > 0
+        ? linear_fit(
+            fit_lags.map((lag) => lag_times[lag]),
+            fit_lags.map((lag) => msd[comp][lag]),
+          )
+        : null,
+    )
+    groups[name] = {
+      n_atoms: n_atoms[group_idx],
+      msd,
+      diffusion: per_component((comp) => (fits[comp]?.slope ?? NaN) / (2 * MSD_DIMS[comp])),
+      diffusion_err: per_component(
+        (comp) => (fits[comp]?.slope_err ?? NaN) / (2 * MSD_DIMS[comp]),
+      ),
+    }
+  })
+  return { lag_times, groups, n_frames: frames.length }
+}
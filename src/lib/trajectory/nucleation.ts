@@ -0,0 +1,164 @@
+// Nucleation analysis from MD: largest solid cluster per frame and the mean first
+// passage time (MFPT) method of Wedekind et al. (J. Chem. Phys. 126, 134103, 2007) for
+// nucleation rates, critical cluster sizes and Zeldovich factors
+import { erf } from '$lib/math'
+import type { Crystal } from '$lib/structure/index'
+import { get_neighbor_list } from '$lib/structure/neighbors'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+import type { InterfaceOptions } from './interface'
+import { as_classifier } from './interface'
+
+export type SolidClusterOptions = {
+  classify: InterfaceOptions[`classify`] // per-atom solid/liquid labels
+  cutoff: number // Å, solid atoms closer than this belong to the same cluster
+}
+
+export type SolidCluster = {
+  size: number
+  site_indices: number[] // sorted
+}
+
+// Largest connected cluster of solid atoms (periodic images included in the bonding)
+export function largest_solid_cluster(
+  structure: Crystal,
+  options: SolidClusterOptions,
+): SolidCluster {
+  const is_solid = as_classifier(options.classify)(structure)
+  const neighbor_list = get_neighbor_list(structure, options.cutoff)
+  const visited = new Set<number>()
+  let largest: number[] = []
+  for (let seed = 0; seed < structure.sites.length; seed++) {
+    if (!is_solid[seed] || visited.has(seed)) continue
+    visited.add(seed)
+    const cluster = [seed]
+    for (let head = 0; head < cluster.length; head++) {
+      for (const { site_idx } of neighbor_list[cluster[head]]) {
+        if (!is_solid[site_idx] || visited.has(site_idx)) continue
+        visited.add(site_idx)
+        cluster.push(site_idx)
+      }
+    }
+    if (cluster.length > largest.length) largest = cluster
+  }
+  const site_indices = largest.toSorted((idx_1, idx_2) => idx_1 - idx_2)
+  return { size: largest.length, site_indices }
+}
+
+export type ClusterSeries = {
+  times: number[]
+  sizes: number[] // largest solid cluster size per frame
+}
+
+export type ClusterSeriesOptions = SolidClusterOptions & {
+  start_frame?: number // default 0
+  stride?: number // default 1
+  time_step?: number // time per MD step (default 1)
+}
+
+// Largest solid cluster size vs time along one trajectory
+export function largest_cluster_series(
+  trajectory: TrajectoryType,
+  options: ClusterSeriesOptions,
+): ClusterSeries {
+  const { start_frame = 0, stride = 1, time_step = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  const series: ClusterSeries = { times: [], sizes: [] }
+  for (const { structure, step } of frames) {
+    if (!is_crystal(structure)) {
+      throw new Error(`Cluster analysis requires periodic structures in every frame`)
+    }
+    series.times.push(step * time_step)
+    series.sizes.push(largest_solid_cluster(structure, options).size)
+  }
+  return series
+}
+
+export type MfptOptions = {
+  // cluster sizes at which to evaluate first passage times (default 1 up to the largest
+  // size reached by every trajectory)
+  sizes?: number[]
+  volume?: number // Å³ of the simulation cell, for the rate per volume
+}
+
+export type MfptResult = {
+  sizes: number[]
+  mfpt: number[] // mean first passage time to reach each size
+  fitted: number[] // τ(n) = τ_J / 2 · (1 + erf(c (n − n*))) at each size
+  tau_j: number // mean nucleation time τ_J
+  critical_size: number // n*
+  zeldovich: number // Z = c / √π
+  rate: number | null // J = 1 / (τ_J V) per Å³ and time unit, null without volume
+  n_trajectories: number
+}
+
+// Mean first passage times of the largest cluster over independent trajectories (each
+// timed from its first frame) fitted to the Wedekind form by a refined grid search over
+// n* and log c with the least-squares τ_J in closed form
+export function nucleation_mfpt(
+  series: ClusterSeries[],
+  options: MfptOptions = {},
+): MfptResult {
+  if (series.length === 0) throw new Error(`Need at least one cluster size series`)
+  const n_reached = Math.min(...series.map(({ sizes }) => Math.max(...sizes)))
+  const sizes =
+    options.sizes ?? Array.from({ length: Math.max(0, n_reached) }, (_, idx) => idx + 1)
+  if (sizes.length < 3) {
+    throw new Error(`Need at least 3 cluster sizes reached by every trajectory`)
+  }
+  const mfpt = sizes.map((size) => {
+    let sum = 0
+    for (const { times, sizes: cluster_sizes } of series) {
+      const first = cluster_sizes.findIndex((val) => val >= size)
+      if (first < 0) throw new Error(`Not every trajectory reaches cluster size ${size}`)
+      sum += times[first] - times[0]
+    }
+    return sum / series.length
+  })
+
+  const shape = (critical: number, c_val: number) =>
+    sizes.map((size) => 0.5 * (1 + erf(c_val * (size - critical))))
+  const fit = (critical: number, log_c: number) => {
+    const shape_vals = shape(critical, Math.exp(log_c))
+    const norm = shape_vals.reduce((sum, val) => sum + val * val, 0)
+    const tau_j = mfpt.reduce((sum, val, idx) => sum + val * shape_vals[idx], 0) / norm
+    const residual = mfpt.reduce(
+      (sum, val, idx) => sum + (val - tau_j * shape_vals[idx]) ** 2,
+      0,
+    )
+    return { critical, log_c, tau_j, residual }
+  }
+  const n_grid = 40
+  let [n_lo, n_hi] = [Math.min(...sizes), Math.max(...sizes)]
+  let [c_lo, c_hi] = [Math.log(1e-3), Math.log(10)]
+  let best = fit((n_lo + n_hi) / 2, (c_lo + c_hi) / 2)
+  for (let round = 0; round < 4; round++) {
+    for (let idx = 0; idx <= n_grid; idx++) {
+      for (let jdx = 0; jdx <= n_grid; jdx++) {
+        const critical = n_lo + ((n_hi - n_lo) * idx) / n_grid
+        const trial = fit(critical, c_lo + ((c_hi - c_lo) * jdx) / n_grid)
+        if (trial.residual < best.residual) best = trial
+      }
+    }
+    // zoom in on the best grid point for the next round
+    const [n_step, c_step] = [(n_hi - n_lo) / n_grid, (c_hi - c_lo) / n_grid]
+    ;[n_lo, n_hi] = [best.critical - 2 * n_step, best.critical + 2 * n_step]
+    ;[c_lo, c_hi] = [best.log_c - 2 * c_step, best.log_c + 2 * c_step]
+  }
+
+  const c_val = Math.exp(best.log_c)
+  const { volume } = options
+  return {
+    sizes,
+    mfpt,
+    fitted: shape(best.critical, c_val).map((val) => best.tau_j * val),
+    tau_j: best.tau_j,
+    critical_size: best.critical,
+    zeldovich: c_val / Math.sqrt(Math.PI),
+    rate: volume ? 1 / (best.tau_j * volume) : null,
+    n_trajectories: series.length,
+  }
+}
@@ -1,12 +1,14 @@
 // ASE trajectory (.traj) parsing - binary format
 import * as math from '$lib/math'
 import {
+  calc_force_stats,
   convert_atomic_numbers,
   create_trajectory_frame,
   read_ndarray_from_view,
   validate_3x3_matrix,
 } from '$lib/trajectory/helpers'
 import type { TrajectoryFrame, TrajectoryType } from '$lib/trajectory/index'
+import { is_plain_object } from '$lib/utils'
 
 const MAX_SAFE_STRING_LENGTH = 0x1fffffe8 * 0.5 // 50% of JS max string length as safety
 
@@ -16,6 +18,54 @@ export const read_ase_header = (view: DataView): { n_items: number; offsets_pos:
   offsets_pos: Number(view.getBigInt64(40, true)),
 })
 
+// ULM stores numpy arrays as { ndarray: [shape, dtype, offset] } refs, 1D ones flattened
+const resolve_ulm_value = (view: DataView, value: unknown): unknown => {
+  if (!is_plain_object(value) || !Array.isArray(value.ndarray)) return value
+  const data = read_ndarray_from_view(view, value as { ndarray: unknown[] })
+  return (value.ndarray[0] as number[]).length === 1 ? data[0] : data
+}
+
+// ASE stress in eV/Å³, either a 3x3 matrix or Voigt-ordered [xx, yy, zz, yz, xz, xy]
+const to_stress_matrix = (stress: number[] | number[][]): number[][] => {
+  if (stress.length !== 6) return stress as number[][]
+  const [xx, yy, zz, yz, xz, xy] = stress as number[]
+  return [
+    [xx, xy, xz],
+    [xy, yy, yz],
+    [xz, yz, zz],
+  ]
+}
+
+// Calculator results written by TrajectoryWriter (energy, forces, stress, magmoms, ...)
+// as frame metadata, with force_max/force_norm and pressure/stress_max derived like the
+// pymatgen parser. The calculator name is kept as `calculator`, its parameters dropped.
+function read_calculator_results(
+  view: DataView,
+  calculator: unknown,
+  step: number,
+): Record<string, unknown> {
+  if (!is_plain_object(calculator)) return {}
+  const results: Record<string, unknown> = {}
+  for (const [key, value] of Object.entries(calculator)) {
+    const name = key.endsWith(`.`) ? key.slice(0, -1) : key
+    if (name === `parameters`) continue
+    results[name === `name` ? `calculator` : name] = resolve_ulm_value(view, value)
+  }
+  if (Array.isArray(results.forces)) {
+    Object.assign(results, calc_force_stats(results.forces as number[][]))
+  }
+  if (Array.isArray(results.stress)) {
+    const stress = to_stress_matrix(results.stress as number[] | number[][])
+    if (math.is_square_matrix(stress, 3)) {
+      const normal = [stress[0][0], stress[1][1], stress[2][2]]
+      results.stress = stress
+      results.stress_max = Math.max(...normal.map(Math.abs))
+      results.pressure = -(normal[0] + normal[1] + normal[2]) / 3
+    } else console.warn(`Invalid stress in ASE frame ${step}`)
+  }
+  return results
+}
+
 // Decode a single ASE/ULM frame (JSON header + optional ndarray payloads) into a
 // TrajectoryFrame. Returns the atomic numbers actually used so callers can cache them
 // as fallback for later frames that omit `numbers` (ASE stores them only once).
@@ -50,9 +100,10 @@ export function decode_ase_frame(
   }
 
   const cell = frame_data.cell ? validate_3x3_matrix(frame_data.cell) : undefined
+  const calculator = frame_data[`calculator.`] ?? frame_data.calculator
   const metadata: Record<string, unknown> = {
     step,
-    ...frame_data.calculator,
+    ...read_calculator_results(view, calculator, step),
     ...frame_data.info,
   }
   if (cell) {
@@ -70,6 +121,7 @@ export function decode_ase_frame(
     frame_data.pbc ?? [true, true, true],
     step,
     metadata,
+    Array.isArray(metadata.forces) ? (metadata.forces as number[][]) : undefined,
   )
   return { frame, numbers }
 }
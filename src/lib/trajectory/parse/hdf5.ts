@@ -10,8 +10,14 @@ import {
   create_trajectory_frame,
   validate_3x3_matrix,
 } from '$lib/trajectory/helpers'
-import type { TrajectoryType } from '$lib/trajectory/index'
-import { is_hdf5_dataset, is_hdf5_group, read_dataset, with_h5_file } from './h5-utils'
+import type { TrajectoryFrame, TrajectoryType } from '$lib/trajectory/index'
+import {
+  is_hdf5_dataset,
+  is_hdf5_group,
+  read_dataset,
+  to_number_array,
+  with_h5_file,
+} from './h5-utils'
 import { is_vaspout_h5_file, parse_vaspout_h5_file } from './vaspout-h5'
 
 // Routes an opened HDF5 file to the right parser: vaspout.h5 has the VASP 6.x
@@ -33,6 +39,21 @@ const POSITION_ALIASES = [`positions`, `coords`, `coordinates`]
 const ATOMIC_NUMBER_ALIASES = [`atomic_numbers`, `numbers`, `Z`, `species`]
 const CELL_ALIASES = [`cell`, `cells`, `lattice`]
 const ENERGY_ALIASES = [`potential_energy`, `energy`]
+// optional datasets written by trajectory_to_hdf5: MD steps and per-frame scalars
+const STEPS_DATASET = `steps`
+const FRAME_PROPERTIES_GROUP = `/frame_properties`
+
+// Numeric datasets of the frame_properties group by name (one value per frame)
+function read_frame_properties(h5_file: h5wasm.File): Record<string, number[]> {
+  const group = h5_file.get(FRAME_PROPERTIES_GROUP)
+  if (!is_hdf5_group(group)) return {}
+  const properties: Record<string, number[]> = {}
+  for (const name of group.keys()) {
+    const values = to_number_array(read_dataset(h5_file, `${FRAME_PROPERTIES_GROUP}/${name}`))
+    if (values) properties[name] = values
+  }
+  return properties
+}
 
 function parse_torch_sim_h5_file(h5_file: h5wasm.File): TrajectoryType {
   const alias_groups = [POSITION_ALIASES, ATOMIC_NUMBER_ALIASES, CELL_ALIASES, ENERGY_ALIASES]
@@ -113,6 +134,8 @@ function parse_torch_sim_h5_file(h5_file: h5wasm.File): TrajectoryType {
       ? cells_data
       : [cells_data as unknown as number[][]]
     : null
+  const steps = to_number_array(read_dataset(h5_file, `/${STEPS_DATASET}`))
+  const frame_properties = read_frame_properties(h5_file)
   const frames: TrajectoryType[`frames`] = []
   let dropped_steps = 0
   for (const [idx, frame_pos] of positions.entries()) {
@@ -126,13 +149,23 @@ function parse_torch_sim_h5_file(h5_file: h5wasm.File): TrajectoryType {
       const energy = Array.isArray(energy_entry) ? energy_entry[0] : energy_entry
       const metadata: Record<string, unknown> = {}
       if (energy !== undefined) metadata.energy = energy
+      for (const [name, values] of Object.entries(frame_properties)) {
+        if (values[idx] !== undefined) metadata[name] = values[idx]
+      }
       if (lattice_mat) {
         metadata.volume = calc_lattice_params(lattice_mat).volume
       }
       const pbc: Pbc = lattice_mat ? [true, true, true] : [false, false, false]
 
       frames.push(
-        create_trajectory_frame(frame_pos, frame_elements, lattice_mat, pbc, idx, metadata),
+        create_trajectory_frame(
+          frame_pos,
+          frame_elements,
+          lattice_mat,
+          pbc,
+          steps?.[idx] ?? idx,
+          metadata,
+        ),
       )
     } catch (err) {
       // Same torn-tail resiliency as the vaspout parser: interrupted writers
@@ -168,3 +201,69 @@ function parse_torch_sim_h5_file(h5_file: h5wasm.File): TrajectoryType {
     },
   }
 }
+
+// Random access to one frame of an HDF5 trajectory whose positions (and cell, if
+// per-frame) are frame-major root datasets, like those trajectory_to_hdf5 writes. Only
+// the requested slice is read, so chunked files decompress a single frame.
+export async function read_hdf5_frame(
+  buffer: ArrayBuffer,
+  frame_idx: number,
+  filename?: string,
+): Promise<TrajectoryFrame> {
+  return with_h5_file(buffer, filename, (h5_file) => {
+    const root_dataset = (names: string[]) =>
+      names.map((name) => h5_file.get(`/${name}`)).find(is_hdf5_dataset)
+    const positions = root_dataset(POSITION_ALIASES)
+    const [n_frames, n_atoms, n_dims] = positions?.shape ?? []
+    if (!positions || positions.shape?.length !== 3 || n_dims !== 3) {
+      throw new Error(`Random frame access needs a frames × atoms × 3 positions dataset`)
+    }
+    if (!Number.isInteger(frame_idx) || frame_idx < 0 || frame_idx >= n_frames) {
+      throw new Error(`Frame ${frame_idx} out of range for ${n_frames} frames`)
+    }
+    // flat values of one frame: datasets with a leading frame axis on top of the per-frame
+    // rank are sliced along it, constant ones (e.g. a single cell) are read whole
+    const frame_values = (dataset: h5wasm.Dataset | undefined, frame_rank: number) => {
+      if (!dataset) return null
+      const per_frame = (dataset.shape?.length ?? 0) > frame_rank
+      return to_number_array(
+        per_frame ? dataset.slice([[frame_idx, frame_idx + 1]]) : dataset.value,
+      )
+    }
+    const flat_pos = frame_values(positions, 2)
+    const numbers = frame_values(root_dataset(ATOMIC_NUMBER_ALIASES), 1)
+    if (!flat_pos || !numbers || numbers.length !== n_atoms) {
+      throw new Error(`Missing or malformed positions/atomic numbers for frame ${frame_idx}`)
+    }
+    const frame_pos = Array.from({ length: n_atoms }, (_, idx) =>
+      flat_pos.slice(idx * 3, idx * 3 + 3),
+    )
+    const flat_cell = frame_values(root_dataset(CELL_ALIASES), 2)
+    const lattice_mat = flat_cell
+      ? transpose_3x3_matrix(
+          validate_3x3_matrix([0, 3, 6].map((start) => flat_cell.slice(start, start + 3))),
+        )
+      : undefined
+
+    const metadata: Record<string, unknown> = {}
+    const energy = frame_values(root_dataset(ENERGY_ALIASES), 0)?.[0]
+    if (energy !== undefined) metadata.energy = energy
+    const group = h5_file.get(FRAME_PROPERTIES_GROUP)
+    for (const name of is_hdf5_group(group) ? group.keys() : []) {
+      const dataset = group.get(name)
+      const value = is_hdf5_dataset(dataset) ? frame_values(dataset, 0)?.[0] : undefined
+      if (value !== undefined) metadata[name] = value
+    }
+    if (lattice_mat) metadata.volume = calc_lattice_params(lattice_mat).volume
+    const step = frame_values(root_dataset([STEPS_DATASET]), 0)?.[0] ?? frame_idx
+    const pbc: Pbc = lattice_mat ? [true, true, true] : [false, false, false]
+    return create_trajectory_frame(
+      frame_pos,
+      convert_atomic_numbers(numbers),
+      lattice_mat,
+      pbc,
+      step,
+      metadata,
+    )
+  })
+}
@@ -19,12 +19,13 @@ import type {
   TrajectoryType,
 } from '$lib/trajectory/index'
 import type { AtomTypeMapping, LoadingOptions } from '$lib/trajectory/types'
+import type { UnitSystem } from '$lib/units'
 import { parse_ase_trajectory } from './ase'
 import { get_traj_parse_warnings, reset_traj_parse_warnings, traj_warn } from './diagnostics'
 import { parse_hdf5_trajectory } from './hdf5'
 import { parse_lammps_trajectory } from './lammps'
 import { parse_pymatgen_trajectory } from './pymatgen'
-import { parse_vasp_xdatcar } from './vasp'
+import { parse_vasp_outcar, parse_vasp_xdatcar, parse_vasprun_xml } from './vasp'
 import { parse_xyz_trajectory } from './xyz'
 
 // Throw on a trajectory frame whose structure isn't a valid parsed structure (non-empty sites with species + coords)
@@ -61,11 +62,17 @@ export {
   validate_3x3_matrix,
 } from '$lib/trajectory/helpers'
 export { TrajFrameReader } from '$lib/trajectory/frame-reader'
+export { trajectory_to_hdf5 } from '$lib/trajectory/hdf5-export'
+export type { Hdf5ExportOptions } from '$lib/trajectory/hdf5-export'
+export { read_hdf5_frame } from './hdf5'
+export { iter_lammps_frames, iter_lammps_frames_async } from './lammps'
+export type { LammpsDumpOptions } from './lammps'
 
 export async function parse_trajectory_data(
   data: unknown,
   filename?: string,
   atom_type_mapping?: AtomTypeMapping,
+  lammps_units?: UnitSystem,
 ): Promise<TrajectoryType> {
   reset_traj_parse_warnings()
   if (data instanceof ArrayBuffer) {
@@ -76,12 +83,14 @@ export async function parse_trajectory_data(
 
   if (typeof data === `string`) {
     const content = data.trim()
+    if (FORMAT_PATTERNS.outcar(content, filename)) return parse_vasp_outcar(content, filename)
+    if (FORMAT_PATTERNS.vasprun(content, filename)) return parse_vasprun_xml(content, filename)
     if (FORMAT_PATTERNS.xyz_multi(content, filename)) return parse_xyz_trajectory(content)
     if (FORMAT_PATTERNS.vasp(content, filename)) {
       return parse_vasp_xdatcar(content, filename)
     }
     if (FORMAT_PATTERNS.lammpstrj(content, filename)) {
-      return parse_lammps_trajectory(content, filename, atom_type_mapping)
+      return parse_lammps_trajectory(content, filename, atom_type_mapping, lammps_units)
     }
 
     // Single XYZ fallback (content-sniffed when the filename gives no format hint,
@@ -204,6 +213,7 @@ export async function parse_trajectory_async(
     index_sample_rate = INDEX_SAMPLE_RATE,
     extract_plot_metadata = true,
     atom_type_mapping,
+    lammps_units,
   } = options
 
   const update_progress = (current: number, stage: string) =>
@@ -243,7 +253,7 @@ export async function parse_trajectory_async(
 
     // Fallback to direct parsing
     update_progress(10, `Parsing trajectory...`)
-    const result = await parse_trajectory_data(data, filename, atom_type_mapping)
+    const result = await parse_trajectory_data(data, filename, atom_type_mapping, lammps_units)
 
     update_progress(100, `Complete`)
     return attach_parse_warnings(result)
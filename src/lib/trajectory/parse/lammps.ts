@@ -8,6 +8,8 @@ import type { TrajectoryFrame, TrajectoryType } from '$lib/trajectory/index'
 import { coerce_elem_symbol } from '$lib/element/helpers'
 import { count_elements, create_trajectory_frame } from '$lib/trajectory/helpers'
 import type { AtomTypeMapping } from '$lib/trajectory/types'
+import type { UnitSystem } from '$lib/units'
+import { is_unit_system, unit_factor } from '$lib/units'
 import { traj_warn } from './diagnostics'
 
 const is_periodic = (token: string): boolean => token.toLowerCase().startsWith(`p`)
@@ -44,19 +46,24 @@ function parse_lammps_box(box_lines: string[], is_triclinic: boolean): math.Matr
   ]
 }
 
-// Parse LAMMPS trajectory (.lammpstrj). Atom types mapped to elements via atom_type_mapping
-// or by default: 1→H, 2→He, etc. Supports orthogonal and triclinic simulation boxes.
-export function parse_lammps_trajectory(
-  content: string,
-  filename?: string,
-  atom_type_mapping?: AtomTypeMapping,
-): TrajectoryType {
-  const lines = content.trim().split(/\r?\n/)
-  const frames: TrajectoryFrame[] = []
-  const atom_types_found = new Set<number>()
-  let id_fallback_warning_emitted = false
-  let idx = 0
+// State carried across the frames of one dump: ITEM: UNITS only precedes the first frame
+type LammpsDumpState = {
+  units: UnitSystem
+  atom_type_mapping?: AtomTypeMapping
+  atom_types_found: Set<number>
+  id_fallback_warned: boolean
+}
 
+// Map atom type to element symbol with a custom mapping or by default 1→H, 2→He, etc.
+const get_element = (atom_type: number, mapping?: AtomTypeMapping): ElementSymbol =>
+  mapping?.[atom_type] ?? ELEM_SYMBOLS[Math.max(0, atom_type - 1) % ELEM_SYMBOLS.length]
+
+// Frames in dump lines (any number of whole frames), skipping malformed ones
+function* parse_lammps_lines(
+  lines: string[],
+  state: LammpsDumpState,
+): Generator<TrajectoryFrame> {
+  let idx = 0
   const read_line = (): string => lines[idx++]?.trim() ?? ``
   const peek_line = (): string => lines[idx]?.trim() ?? ``
   const skip_to = (prefix: string): boolean => {
@@ -64,16 +71,26 @@ export function parse_lammps_trajectory(
     return idx < lines.length
   }
 
-  // Helper to map atom type to element symbol
-  const get_element = (atom_type: number): ElementSymbol => {
-    if (atom_type_mapping?.[atom_type]) return atom_type_mapping[atom_type]
-    return ELEM_SYMBOLS[Math.max(0, atom_type - 1) % ELEM_SYMBOLS.length]
-  }
-
   while (idx < lines.length) {
-    if (!skip_to(`ITEM: TIMESTEP`)) break
+    // dump_modify units/time write ITEM: UNITS (first frame only) and ITEM: TIME before
+    // each ITEM: TIMESTEP
+    let time: number | undefined
+    while (idx < lines.length && !peek_line().startsWith(`ITEM: TIMESTEP`)) {
+      const line = read_line()
+      if (line === `ITEM: UNITS`) {
+        const declared = read_line().toLowerCase()
+        if (is_unit_system(declared) && declared !== `ase`) state.units = declared
+        else traj_warn(`Unsupported LAMMPS units "${declared}", assuming ${state.units}`)
+      } else if (line === `ITEM: TIME`) {
+        const value = Number(read_line())
+        time = Number.isFinite(value) ? value : undefined
+      }
+    }
+    if (idx >= lines.length) break
     idx++
     const timestep = Math.trunc(Number(read_line())) || 0
+    const { units } = state
+    const length_scale = unit_factor(`length`, units, `ase`)
 
     if (!skip_to(`ITEM: NUMBER OF ATOMS`)) break
     idx++
@@ -90,32 +107,45 @@ export function parse_lammps_trajectory(
         ? [is_periodic(tokens[0]), is_periodic(tokens[1]), is_periodic(tokens[2])]
         : [true, true, true]
 
-    const lattice_matrix = parse_lammps_box(
-      [read_line(), read_line(), read_line()],
-      is_triclinic,
-    )
-    if (!lattice_matrix) continue
+    const box_matrix = parse_lammps_box([read_line(), read_line(), read_line()], is_triclinic)
+    if (!box_matrix) continue
+    const lattice_matrix = box_matrix.map((row) =>
+      row.map((val) => val * length_scale),
+    ) as math.Matrix3x3
 
     // Find ITEM: ATOMS and parse column headers
     if (!skip_to(`ITEM: ATOMS`)) break
     const cols = read_line().replace(`ITEM: ATOMS`, ``).trim().toLowerCase().split(/\s+/)
     const col = Object.fromEntries(cols.map((name, col_idx) => [name, col_idx]))
 
-    // Determine position columns: prefer unwrapped (xu/yu/zu) > scaled (xs/ys/zs) > regular (x/y/z)
-    const pos_keys = [`xu`, `yu`, `zu`].every((key) => key in col)
-      ? [`xu`, `yu`, `zu`]
-      : [`xs`, `ys`, `zs`].every((key) => key in col)
-        ? [`xs`, `ys`, `zs`]
-        : [`x`, `y`, `z`]
-    const pos_cols = pos_keys.map((key) => col[key])
+    // Position columns: prefer unwrapped (xu/yu/zu > xsu/ysu/zsu) > scaled (xs/ys/zs) > x/y/z
+    const has_pos_cols = (suffix: string) =>
+      [`x`, `y`, `z`].every((axis) => `${axis}${suffix}` in col)
+    const pos_suffix = [`u`, `su`, `s`].find(has_pos_cols) ?? ``
+    const pos_cols = [`x`, `y`, `z`].map((axis) => col[`${axis}${pos_suffix}`])
     // Atom identity: prefer numeric type, else explicit element symbol.
     // Fallback to ID-based mapping only for legacy dumps; this can be inaccurate
     // for large or non-element-like IDs, so prefer TYPE column when available.
     const type_col = col.type
     const element_col = col.element
     const id_col = col.id
-    const use_scaled = pos_keys[0] === `xs`
-    const max_col_idx = Math.max(...pos_cols, type_col ?? -1, element_col ?? -1, id_col ?? -1)
+    const use_scaled = pos_suffix.includes(`s`)
+    const force_cols = [`fx`, `fy`, `fz`].every((key) => key in col)
+      ? [col.fx, col.fy, col.fz]
+      : null
+    const force_scale = unit_factor(`force`, units, `ase`)
+    const vel_cols = [`vx`, `vy`, `vz`].every((key) => key in col)
+      ? [col.vx, col.vy, col.vz]
+      : null
+    const velocity_scale = unit_factor(`velocity`, units, `ase`)
+    const max_col_idx = Math.max(
+      ...pos_cols,
+      ...(force_cols ?? []),
+      ...(vel_cols ?? []),
+      type_col ?? -1,
+      element_col ?? -1,
+      id_col ?? -1,
+    )
 
     if (pos_cols.some((col_idx) => col_idx === undefined)) continue
     if (type_col === undefined && element_col === undefined && id_col === undefined) {
@@ -127,6 +157,8 @@ export function parse_lammps_trajectory(
 
     // Parse atom data
     const positions: number[][] = []
+    const forces: number[][] = []
+    const velocities: Vec3[] = []
     const elements: ElementSymbol[] = []
     const frac_to_cart = use_scaled ? math.create_frac_to_cart(lattice_matrix) : null
 
@@ -136,14 +168,16 @@ export function parse_lammps_trajectory(
       if (coords.some(isNaN) || parts.length <= max_col_idx) continue
 
       // Convert scaled coordinates to Cartesian if needed
-      const xyz = frac_to_cart ? frac_to_cart(coords as Vec3) : coords
+      const xyz = frac_to_cart
+        ? frac_to_cart(coords as Vec3)
+        : coords.map((coord) => coord * length_scale)
       let element_symbol: ElementSymbol | undefined
 
       if (type_col !== undefined) {
         // Map atom type to element using custom mapping or default (type 1 -> H, etc.)
         const atom_type = Math.trunc(Number(parts[type_col])) || 1
-        atom_types_found.add(atom_type)
-        element_symbol = get_element(atom_type)
+        state.atom_types_found.add(atom_type)
+        element_symbol = get_element(atom_type, state.atom_type_mapping)
       } else if (element_col !== undefined) {
         const raw_symbol = parts[element_col]
         if (!raw_symbol) continue
@@ -156,31 +190,138 @@ export function parse_lammps_trajectory(
         }
       } else if (id_col !== undefined) {
         const atom_id = Math.trunc(Number(parts[id_col])) || 1
-        atom_types_found.add(atom_id)
-        if (!id_fallback_warning_emitted) {
+        state.atom_types_found.add(atom_id)
+        if (!state.id_fallback_warned) {
           traj_warn(
             `LAMMPS parser fallback: mapping atom IDs to elements from ID column; this may be incorrect for large or sequential IDs. Prefer a TYPE column when available.`,
           )
-          id_fallback_warning_emitted = true
+          state.id_fallback_warned = true
         }
-        element_symbol = get_element(atom_id)
+        element_symbol = get_element(atom_id, state.atom_type_mapping)
       }
 
       if (!element_symbol) continue
       positions.push(xyz)
       elements.push(element_symbol)
+      if (force_cols) {
+        forces.push(force_cols.map((col_idx) => Number(parts[col_idx]) * force_scale))
+      }
+      if (vel_cols) {
+        const velocity = vel_cols.map((col_idx) => Number(parts[col_idx]) * velocity_scale)
+        velocities.push(velocity as Vec3)
+      }
     }
 
     if (positions.length === elements.length && positions.length === num_atoms) {
       const { volume } = math.calc_lattice_params(lattice_matrix)
-      frames.push(
-        create_trajectory_frame(positions, elements, lattice_matrix, pbc, timestep, {
-          volume,
-          timestep,
-        }),
+      const frame_metadata: Record<string, unknown> = { volume, timestep }
+      if (time !== undefined) frame_metadata.time = time * unit_factor(`time`, units, `ase`)
+      const frame = create_trajectory_frame(
+        positions,
+        elements,
+        lattice_matrix,
+        pbc,
+        timestep,
+        frame_metadata,
+        force_cols ? forces : undefined,
       )
+      if (vel_cols) {
+        frame.structure.sites.forEach((site, site_idx) => {
+          site.properties = { ...site.properties, velocity: velocities[site_idx] }
+        })
+      }
+      yield frame
     }
   }
+}
+
+// Splits streamed dump text into whole frames: a frame ends where the ITEM: UNITS, TIME
+// or TIMESTEP header of the next one starts after its ITEM: ATOMS section
+class LammpsFrameSplitter {
+  private partial = ``
+  private frame: string[] = []
+  private in_atoms = false
+
+  push(chunk: string): string[][] {
+    const lines = (this.partial + chunk).split(/\r?\n/)
+    this.partial = lines.pop() ?? ``
+    return lines.flatMap((line) => this.add_line(line))
+  }
+
+  flush(): string[][] {
+    const done = this.partial ? this.add_line(this.partial) : []
+    this.partial = ``
+    if (this.frame.length) done.push(this.frame)
+    this.frame = []
+    return done
+  }
+
+  private add_line(line: string): string[][] {
+    const item = line.trim()
+    if (this.in_atoms && /^ITEM: (?:UNITS|TIME|TIMESTEP)$/.test(item)) {
+      const done = this.frame
+      this.frame = [line]
+      this.in_atoms = false
+      return [done]
+    }
+    if (item.startsWith(`ITEM: ATOMS`)) this.in_atoms = true
+    this.frame.push(line)
+    return []
+  }
+}
+
+export type LammpsDumpOptions = {
+  atom_type_mapping?: AtomTypeMapping // type → element (default 1→H, 2→He, ...)
+  units?: UnitSystem // default metal, overridden by an ITEM: UNITS header
+}
+
+const new_dump_state = (options: LammpsDumpOptions): LammpsDumpState => ({
+  units: options.units ?? `metal`,
+  atom_type_mapping: options.atom_type_mapping,
+  atom_types_found: new Set(),
+  id_fallback_warned: false,
+})
+
+// Lazily read LAMMPS text dumps one frame at a time from the whole text or any iterable
+// of text chunks, so only the current frame is held in memory (e.g. for 10+ GB dumps fed
+// into streaming analysis). Frames are the same as from parse_lammps_trajectory.
+export function* iter_lammps_frames(
+  chunks: string | Iterable<string>,
+  options: LammpsDumpOptions = {},
+): Generator<TrajectoryFrame> {
+  const [splitter, state] = [new LammpsFrameSplitter(), new_dump_state(options)]
+  for (const chunk of typeof chunks === `string` ? [chunks] : chunks) {
+    for (const lines of splitter.push(chunk)) yield* parse_lammps_lines(lines, state)
+  }
+  for (const lines of splitter.flush()) yield* parse_lammps_lines(lines, state)
+}
+
+// iter_lammps_frames for async text streams, e.g.
+// file.stream().pipeThrough(new TextDecoderStream()) of a browser File
+export async function* iter_lammps_frames_async(
+  chunks: AsyncIterable<string>,
+  options: LammpsDumpOptions = {},
+): AsyncGenerator<TrajectoryFrame> {
+  const [splitter, state] = [new LammpsFrameSplitter(), new_dump_state(options)]
+  for await (const chunk of chunks) {
+    for (const lines of splitter.push(chunk)) yield* parse_lammps_lines(lines, state)
+  }
+  for (const lines of splitter.flush()) yield* parse_lammps_lines(lines, state)
+}
+
+// Parse LAMMPS trajectory (.lammpstrj). Atom types mapped to elements via atom_type_mapping
+// or by default: 1→H, 2→He, etc. Supports orthogonal and triclinic simulation boxes.
+// Lengths, forces (fx/fy/fz), velocities (vx/vy/vz) and frame times (ITEM: TIME) are
+// converted from `units` (or the dump's own ITEM: UNITS header when present) to Å, eV/Å,
+// Å/fs and fs.
+export function parse_lammps_trajectory(
+  content: string,
+  filename?: string,
+  atom_type_mapping?: AtomTypeMapping,
+  units: UnitSystem = `metal`,
+): TrajectoryType {
+  const state = new_dump_state({ atom_type_mapping, units })
+  const frames = [...parse_lammps_lines(content.trim().split(/\r?\n/), state)]
 
   if (frames.length === 0) {
     throw new Error(`No valid frames found in LAMMPS trajectory`)
@@ -202,8 +343,9 @@ export function parse_lammps_trajectory(
         `lattice` in first_frame.structure
           ? first_frame.structure.lattice.pbc
           : [true, true, true],
-      atom_types: Array.from(atom_types_found).toSorted((a, b) => a - b),
+      atom_types: Array.from(state.atom_types_found).toSorted((a, b) => a - b),
       element_counts,
+      units: state.units,
     },
   }
 }
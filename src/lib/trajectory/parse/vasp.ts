@@ -1,11 +1,16 @@
-// VASP XDATCAR trajectory parsing
+// VASP trajectory parsing: XDATCAR positions, and OUTCAR/vasprun.xml ionic steps with
+// energies, forces and stresses (relaxations and MD)
 import type { ElementSymbol } from '$lib/element/types'
 import type { Vec3 } from '$lib/math'
 import * as math from '$lib/math'
 import type { Pbc } from '$lib/structure/pbc'
 import type { TrajectoryFrame, TrajectoryType } from '$lib/trajectory/index'
 import { is_elem_symbol } from '$lib/element/helpers'
-import { create_trajectory_frame, validate_3x3_matrix } from '$lib/trajectory/helpers'
+import {
+  calc_force_stats,
+  create_trajectory_frame,
+  validate_3x3_matrix,
+} from '$lib/trajectory/helpers'
 import { parse_leading_num } from '$lib/utils'
 
 // Parse the 7-line XDATCAR header at lines[start]: title, scale factor, 3 lattice rows
@@ -120,3 +125,203 @@ export function parse_vasp_xdatcar(content: string, filename?: string): Trajecto
     },
   }
 }
+
+// Stress tensor in kB with VASP's sign convention (positive = compressive, as in OUTCAR's
+// "external pressure"), so pressure is +trace/3
+const vasp_stress_metadata = (stress: number[][]) => {
+  const normal = [stress[0][0], stress[1][1], stress[2][2]]
+  return {
+    stress,
+    stress_max: Math.max(...normal.map(Math.abs)),
+    pressure: (normal[0] + normal[1] + normal[2]) / 3,
+  }
+}
+
+// Fixed-width OUTCAR columns can run together (e.g. 1.23456-0.12345), so match numbers
+// instead of splitting on whitespace
+const outcar_floats = (line: string): number[] =>
+  (line.match(/-?\d*\.\d+(?:[Ee][-+]?\d+)?/g) ?? []).map(Number)
+
+// Element of a POTCAR title like "PAW_PBE Fe_pv 06Sep2000" or "PAW_PBE H1.25 07Sep2000"
+const potcar_element = (title: string): string | undefined =>
+  /^[A-Z][a-z]?/.exec(title.trim().split(/\s+/)[1] ?? ``)?.[0]
+
+const check_elements = (names: string[], counts: number[], source: string) => {
+  if (names.length === 0 || names.length !== counts.length) {
+    throw new Error(
+      `${source} element names/counts mismatch: names=${names.length}, counts=${counts.length}`,
+    )
+  }
+  const bad_element = names.find((name) => !is_elem_symbol(name))
+  if (bad_element) throw new Error(`Invalid element symbol in ${source}: ${bad_element}`)
+}
+
+// OUTCAR of a relaxation or MD run: one frame per "POSITION ... TOTAL-FORCE" block with the
+// latest lattice, the preceding stress ("in kB" line) and the following energies. Energies
+// are energy (sigma->0) and free_energy (TOTEN), MD steps add kinetic_energy, temperature
+// and total_energy. Frames are numbered by ionic step starting at 1.
+export function parse_vasp_outcar(content: string, filename?: string): TrajectoryType {
+  const lines = content.split(/\r?\n/)
+  const names = lines
+    .filter((line) => line.includes(`TITEL  =`))
+    .map((line) => potcar_element(line.split(`=`)[1]) ?? ``)
+  const counts_line = lines.find((line) => line.includes(`ions per type =`))
+  const counts = counts_line?.split(`=`)[1].trim().split(/\s+/).map(Number) ?? []
+  check_elements(names, counts, `OUTCAR`)
+  const elements = names.flatMap((name, idx) => Array(counts[idx]).fill(name))
+
+  const frames: TrajectoryFrame[] = []
+  let lattice: math.Matrix3x3 | null = null
+  let stress: number[][] | null = null
+  // electronic steps print energy(sigma->0) too, only read it from the ionic step summary
+  let in_summary = false
+  for (let line_idx = 0; line_idx < lines.length; line_idx++) {
+    const line = lines[line_idx]
+    const metadata = frames.at(-1)?.metadata
+    if (line.includes(`direct lattice vectors`)) {
+      const rows = lines.slice(line_idx + 1, line_idx + 4).map((row) => outcar_floats(row))
+      lattice = validate_3x3_matrix(rows.map((row) => row.slice(0, 3)))
+      line_idx += 3
+    } else if (/^\s*in kB/.test(line)) {
+      const [xx, yy, zz, xy, yz, zx] = outcar_floats(line)
+      stress = [
+        [xx, xy, zx],
+        [xy, yy, yz],
+        [zx, yz, zz],
+      ]
+    } else if (line.includes(`POSITION`) && line.includes(`TOTAL-FORCE`)) {
+      if (!lattice) throw new Error(`OUTCAR has atom positions before any lattice vectors`)
+      const rows = lines
+        .slice(line_idx + 2, line_idx + 2 + elements.length)
+        .map((row) => outcar_floats(row))
+      line_idx += 2 + elements.length
+      // stop at a block truncated by a still-running calculation
+      if (rows.length < elements.length || rows.some((row) => row.length < 6)) break
+      const forces = rows.map((row) => row.slice(3, 6))
+      const { volume } = math.calc_lattice_params(lattice)
+      const step_metadata = {
+        volume,
+        ...calc_force_stats(forces),
+        ...(stress && vasp_stress_metadata(stress)),
+      }
+      const positions = rows.map((row) => row.slice(0, 3))
+      const pbc: Pbc = [true, true, true]
+      frames.push(
+        create_trajectory_frame(
+          positions,
+          elements,
+          lattice,
+          pbc,
+          frames.length + 1,
+          step_metadata,
+          forces,
+        ),
+      )
+      stress = null
+    } else if (line.includes(`FREE ENERGIE OF THE ION-ELECTRON SYSTEM`)) {
+      in_summary = true
+    } else if (metadata && in_summary && line.includes(`TOTEN`)) {
+      metadata.free_energy = outcar_floats(line)[0]
+    } else if (metadata && in_summary && line.includes(`energy(sigma->0)`)) {
+      metadata.energy = outcar_floats(line.split(`energy(sigma->0)`)[1])[0]
+      in_summary = false
+    } else if (metadata && line.includes(`kinetic energy EKIN`)) {
+      const [kinetic_energy, temperature] = outcar_floats(line)
+      Object.assign(metadata, { kinetic_energy, temperature })
+    } else if (metadata && line.includes(`total energy   ETOTAL`)) {
+      metadata.total_energy = outcar_floats(line)[0]
+    }
+  }
+  if (frames.length === 0) throw new Error(`No ionic steps found in OUTCAR`)
+
+  return {
+    frames,
+    metadata: {
+      filename,
+      source_format: `vasp_outcar`,
+      frame_count: frames.length,
+      total_atoms: elements.length,
+      periodic_boundary_conditions: [true, true, true],
+      elements: names,
+      element_counts: counts,
+    },
+  }
+}
+
+// vasprun.xml <i name="...">/<v> elements are flat, so tag-level regexes suffice and avoid
+// building a DOM for multi-100 MB MD runs
+const xml_varray = (xml: string, name: string): number[][] | null => {
+  const block = new RegExp(`<varray name="${name}"\\s*>([\\s\\S]*?)</varray>`).exec(xml)
+  if (!block) return null
+  return [...block[1].matchAll(/<v[^>]*>([^<]*)<\/v>/g)].map((match) =>
+    match[1].trim().split(/\s+/).map(Number),
+  )
+}
+
+// vasprun.xml energy tags copied to frame metadata under repo-wide names
+const VASPRUN_ENERGY_KEYS: Record<string, string> = {
+  e_0_energy: `energy`,
+  e_fr_energy: `free_energy`,
+  kinetic: `kinetic_energy`,
+  total: `total_energy`,
+}
+
+// vasprun.xml of a relaxation or MD run: one frame per completed <calculation> with its
+// structure (fractional positions), forces, stress (kB, VASP sign) and final energies, so
+// a still-running calculation's unfinished last step is skipped
+export function parse_vasprun_xml(content: string, filename?: string): TrajectoryType {
+  const atoms = /<array name="atoms"\s*>([\s\S]*?)<\/array>/.exec(content)
+  if (!atoms) throw new Error(`vasprun.xml has no atominfo`)
+  const elements = [...atoms[1].matchAll(/<rc>\s*<c>\s*([^<]*?)\s*<\/c>/g)].map(
+    (match) => match[1],
+  ) as ElementSymbol[]
+  const names = [...new Set(elements)]
+  const counts = names.map((name) => elements.filter((element) => element === name).length)
+  check_elements(names, counts, `vasprun.xml`)
+
+  const frames: TrajectoryFrame[] = []
+  for (const [, block] of content.matchAll(/<calculation>([\s\S]*?)<\/calculation>/g)) {
+    // electronic steps repeat the <energy> tags, keep only the ionic step's own
+    const calc = block.replace(/<scstep>[\s\S]*?<\/scstep>/g, ``)
+    const structure = /<structure[^>]*>([\s\S]*?)<\/structure>/.exec(calc)?.[1]
+    const basis = structure && xml_varray(structure, `basis`)
+    const frac = structure && xml_varray(structure, `positions`)
+    if (!basis || !frac || frac.length !== elements.length) {
+      throw new Error(`vasprun.xml calculation ${frames.length + 1} has no valid structure`)
+    }
+    const lattice = validate_3x3_matrix(basis)
+    const frac_to_cart = math.create_frac_to_cart(lattice)
+    const positions = frac.map((abc) => frac_to_cart(abc as Vec3))
+    const forces = xml_varray(calc, `forces`) ?? undefined
+    const stress = xml_varray(calc, `stress`)
+
+    const metadata: Record<string, unknown> = {
+      volume: math.calc_lattice_params(lattice).volume,
+      ...(forces && calc_force_stats(forces)),
+      ...(stress && math.is_square_matrix(stress, 3) && vasp_stress_metadata(stress)),
+    }
+    const energy_block = /<energy>([\s\S]*?)<\/energy>/.exec(calc)?.[1] ?? ``
+    for (const [, tag, value] of energy_block.matchAll(/<i name="([^"]+)"\s*>([^<]*)<\/i>/g)) {
+      if (tag in VASPRUN_ENERGY_KEYS) metadata[VASPRUN_ENERGY_KEYS[tag]] = Number(value)
+    }
+    const pbc: Pbc = [true, true, true]
+    const step = frames.length + 1
+    frames.push(
+      create_trajectory_frame(positions, elements, lattice, pbc, step, metadata, forces),
+    )
+  }
+  if (frames.length === 0) throw new Error(`No ionic steps found in vasprun.xml`)
+
+  return {
+    frames,
+    metadata: {
+      filename,
+      source_format: `vasp_vasprun`,
+      frame_count: frames.length,
+      total_atoms: elements.length,
+      periodic_boundary_conditions: [true, true, true],
+      elements: names,
+      element_counts: counts,
+    },
+  }
+}
@@ -0,0 +1,108 @@
+// Profiles of per-atom quantities (number density, order parameters, composition,
+// temperature, ...) binned along a lattice direction and averaged over trajectory frames,
+// e.g. to characterize solid-liquid interfaces or segregation at surfaces
+import type { ElementSymbol } from '$lib/element'
+import * as math from '$lib/math'
+import type { Crystal, Site } from '$lib/structure/index'
+import { wrap_frac_coord } from '$lib/structure/pbc'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_mask } from '$lib/structure/select'
+import { site_field_values } from '$lib/structure/site-fields'
+import { is_crystal } from '$lib/structure/validation'
+import type { TrajectoryType } from './index'
+
+// site field name (see set_site_field) or per-atom function
+export type ProfileValue =
+  | string
+  | ((site: Site, site_idx: number, structure: Crystal) => number)
+
+export type ProfileOptions = {
+  axis?: 0 | 1 | 2 // lattice vector to bin along, bins are planes of the other two (default 2)
+  n_bins?: number // default 50
+  value?: ProfileValue // omit to get only the number density
+  elements?: ElementSymbol[] // only count atoms whose (first) species is one of these
+  selection?: AtomSelection // only count these atoms, re-evaluated for every frame
+}
+
+export type TrajectoryProfileOptions = ProfileOptions & {
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+}
+
+export type Profile = {
+  positions: number[] // Å, bin centers as heights along the plane normal
+  counts: number[] // mean number of selected atoms per bin and frame
+  density: number[] // selected atoms per Å³
+  values: number[] | null // mean value over the atoms in each bin (NaN for empty bins)
+  n_frames: number
+}
+
+// Bin the selected atoms of every structure by their fractional coordinate along axis
+function bin_structures(structures: Crystal[], options: ProfileOptions): Profile {
+  const { axis = 2, n_bins = 50, value, elements, selection } = options
+  if (!Number.isInteger(n_bins) || n_bins < 1) {
+    throw new Error(`n_bins must be a positive integer, got ${n_bins}`)
+  }
+  const [other_1, other_2] = [0, 1, 2].filter((dim) => dim !== axis)
+  const counts = Array<number>(n_bins).fill(0)
+  const density = Array<number>(n_bins).fill(0)
+  const sums = Array<number>(n_bins).fill(0)
+  let period_sum = 0
+  for (const structure of structures) {
+    const { matrix } = structure.lattice
+    const volume = Math.abs(math.det_3x3(matrix))
+    period_sum += volume / Math.hypot(...math.cross_3d(matrix[other_1], matrix[other_2]))
+    const bin_volume = volume / n_bins
+    const site_values =
+      typeof value === `string` ? site_field_values(structure, value) : undefined
+    const selected = selection_mask(structure, selection)
+    structure.sites.forEach((site, site_idx) => {
+      const element = site.species[0]?.element
+      if (!selected[site_idx] || (elements && !(element && elements.includes(element)))) {
+        return
+      }
+      const frac = wrap_frac_coord(site.abc[axis])
+      const bin = Math.min(Math.floor(frac * n_bins), n_bins - 1)
+      counts[bin]++
+      density[bin] += 1 / bin_volume
+      if (typeof value === `function`) sums[bin] += value(site, site_idx, structure)
+      else if (site_values) sums[bin] += site_values[site_idx]
+    })
+  }
+  const n_frames = structures.length
+  const spacing = period_sum / n_frames / n_bins
+  return {
+    positions: counts.map((_, bin) => (bin + 0.5) * spacing),
+    counts: counts.map((count) => count / n_frames),
+    density: density.map((dens) => dens / n_frames),
+    values: value === undefined ? null : sums.map((sum, bin) => sum / counts[bin]),
+    n_frames,
+  }
+}
+
+// Profile of a per-atom quantity across a single periodic structure
+export function calc_profile(structure: Crystal, options: ProfileOptions = {}): Profile {
+  return bin_structures([structure], options)
+}
+
+// Profile averaged over trajectory frames: densities are frame means, values are means
+// over all atoms that visited a bin. Positions use the mean cell height along the normal.
+export function calc_trajectory_profile(
+  trajectory: TrajectoryType,
+  options: TrajectoryProfileOptions = {},
+): Profile {
+  const { start_frame = 0, stride = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const structures = trajectory.frames
+    .slice(start_frame)
+    .filter((_, idx) => idx % stride === 0)
+    .map((frame) => frame.structure)
+  if (structures.length === 0) throw new Error(`No frames left after start_frame/stride`)
+  const crystals = structures.filter(is_crystal)
+  if (crystals.length !== structures.length) {
+    throw new Error(`Profiles require periodic structures with a lattice in every frame`)
+  }
+  return bin_structures(crystals, options)
+}
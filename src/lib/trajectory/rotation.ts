@@ -0,0 +1,224 @@
+// Rotational dynamics of molecules in MD trajectories: orientation autocorrelation
+// functions C_l(τ) = <P_l(u(t)·u(t + τ))> of molecular axes and rotational diffusion
+// coefficients per molecular species, e.g. for rotor phases and plastic crystals
+import { get_hill_formula } from '$lib/composition'
+import type { CompositionType } from '$lib/composition'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { linear_fit } from '$lib/stats'
+import type { AnyStructure } from '$lib/structure/index'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_mask } from '$lib/structure/select'
+import type { TopologyOptions } from '$lib/structure/topology'
+import { perceive_topology } from '$lib/structure/topology'
+import type { TrajectoryType } from './index'
+
+// Molecular axis from the whole-molecule Cartesian positions of its atoms (in fragment
+// order, i.e. ascending site index), or a pair of positions within the molecule
+// pointing from the first to the second atom (e.g. [0, 1] for the C→O axis of CO)
+export type MolecularAxis = `principal` | [number, number] | ((positions: Vec3[]) => Vec3)
+
+export type RotationOptions = {
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  time_step?: number // time per MD step, rates are per this unit (default 1)
+  max_lag?: number // longest lag in sampled frames (default half the sampled frames)
+  // default `principal`: long axis of the gyration tensor, sign kept continuous in time
+  axis?: MolecularAxis
+  molecules?: AtomSelection // only molecules containing a selected atom (first frame)
+  min_atoms?: number // smallest fragment counted as molecule (default 2)
+  topology?: TopologyOptions // bond perception on the first sampled frame
+  // fraction of the lag range for the fits of −ln C_l(τ) (default [0, 0.5])
+  fit_range?: [number, number]
+}
+
+export type RotationalSpecies = {
+  molecules: number[][] // site indices of each molecule
+  p1: number[] // C_1(τ) per lag
+  p2: number[] // C_2(τ) per lag
+  // ∫ C_l dτ up to max_lag (trapezoidal), an underestimate if C_l has not decayed
+  correlation_times: [number, number]
+  // D_r from −ln C_l = l (l + 1) D_r τ (+ const) for l = 1, 2, NaN if too few points
+  rotational_diffusion: { p1: number; p2: number }
+  rotational_diffusion_err: { p1: number; p2: number }
+}
+
+export type RotationalDynamics = {
+  lag_times: number[]
+  species: Record<string, RotationalSpecies> // keyed by Hill formula, e.g. CH4
+  n_frames: number
+}
+
+// Dominant eigenvector of the gyration tensor by power iteration, started from the
+// previous frame's axis so the sign stays continuous
+function principal_axis(positions: Vec3[], start: Vec3): Vec3 {
+  const center = math.scale(math.add(...positions), 1 / positions.length)
+  const rel = positions.map((pos) => math.subtract(pos, center))
+  const gyration = [0, 1, 2].map((row) =>
+    [0, 1, 2].map((col) => rel.reduce((sum, vec) => sum + vec[row] * vec[col], 0)),
+  )
+  let axis = start
+  for (let iter = 0; iter < 100; iter++) {
+    const next = math.normalize_vec(
+      gyration.map((row) => row[0] * axis[0] + row[1] * axis[1] + row[2] * axis[2]) as Vec3,
+      axis,
+    )
+    const converged = math.dot(next, axis) > 1 - 1e-14
+    axis = next
+    if (converged) break
+  }
+  return axis
+}
+
+// Initial guess for the principal axis: from the centroid to the farthest atom
+function farthest_atom_axis(positions: Vec3[]): Vec3 {
+  const center = math.scale(math.add(...positions), 1 / positions.length)
+  const farthest = positions.reduce((best, pos) =>
+    math.euclidean_dist(pos, center) > math.euclidean_dist(best, center) ? pos : best,
+  )
+  return math.normalize_vec(math.subtract(farthest, center))
+}
+
+// Fit −ln C over the lags in fit_lags where C is still clearly positive
+function fit_decay(lag_times: number[], corr: number[], fit_lags: number[], order: number) {
+  const lags = fit_lags.filter((lag) => corr[lag] > 0.05)
+  if (lags.length < 2) return { rate: NaN, rate_err: NaN }
+  const fit = linear_fit(
+    lags.map((lag) => lag_times[lag]),
+    lags.map((lag) => -Math.log(corr[lag])),
+  )
+  const norm = order * (order + 1)
+  return { rate: fit.slope / norm, rate_err: fit.slope_err / norm }
+}
+
+// Orientation autocorrelations of molecules found by bond perception on the first
+// sampled frame. Molecules are made whole by minimum-image vectors from their first
+// atom, so they must be smaller than half the cell. Lag times assume equally spaced
+// sampled frames.
+export function rotational_dynamics(
+  trajectory: TrajectoryType,
+  options: RotationOptions = {},
+): RotationalDynamics {
+  const {
+    start_frame = 0,
+    stride = 1,
+    time_step = 1,
+    axis = `principal`,
+    min_atoms = 2,
+    fit_range = [0, 0.5],
+  } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length < 2) {
+    throw new Error(`Need at least 2 frames for rotational dynamics, got ${frames.length}`)
+  }
+  const structures = frames.map(({ structure }) => structure)
+  const n_sites = structures[0].sites.length
+  if (structures.some(({ sites }) => sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+  const max_lag = options.max_lag ?? Math.floor(frames.length / 2)
+  if (!Number.isInteger(max_lag) || max_lag < 1 || max_lag >= frames.length) {
+    throw new Error(`max_lag must be an integer in [1, ${frames.length - 1}], got ${max_lag}`)
+  }
+  const [fit_lo, fit_hi] = fit_range
+  if (!(fit_lo >= 0 && fit_lo < fit_hi && fit_hi <= 1)) {
+    throw new Error(`fit_range must satisfy 0 <= start < end <= 1, got [${fit_lo}, ${fit_hi}]`)
+  }
+
+  const first = structures[0]
+  const selected = selection_mask(first, options.molecules)
+  const molecules = perceive_topology(first, { perceive_orders: false, ...options.topology })
+    .fragments.filter(
+      (fragment) =>
+        fragment.length >= min_atoms && fragment.some((site_idx) => selected[site_idx]),
+    )
+  if (Array.isArray(axis)) {
+    const bad = molecules.find((mol) => axis.some((idx) => !(idx >= 0 && idx < mol.length)))
+    if (bad) {
+      const atoms = axis.join(`, `)
+      throw new Error(`Axis atoms ${atoms} out of range for a ${bad.length}-atom molecule`)
+    }
+  }
+
+  // whole-molecule positions relative to the molecule's first atom
+  const whole = (structure: AnyStructure, molecule: number[]): Vec3[] => {
+    const anchor = structure.sites[molecule[0]].xyz
+    if (!(`lattice` in structure)) return molecule.map((idx) => structure.sites[idx].xyz)
+    const { matrix, pbc } = structure.lattice
+    return molecule.map((idx) =>
+      math.add(
+        anchor,
+        math.min_image_displacement(anchor, structure.sites[idx].xyz, matrix, undefined, pbc),
+      ),
+    )
+  }
+  // unit axis of every molecule in every frame
+  const orientations: Vec3[][] = molecules.map((molecule) => {
+    let prev: Vec3 | null = null
+    return structures.map((structure) => {
+      const positions = whole(structure, molecule)
+      let vec: Vec3
+      if (typeof axis === `function`) vec = axis(positions)
+      else if (Array.isArray(axis)) vec = math.subtract(positions[axis[1]], positions[axis[0]])
+      else vec = principal_axis(positions, prev ?? farthest_atom_axis(positions))
+      const unit = math.normalize_vec(vec)
+      if (Math.hypot(...unit) === 0) {
+        throw new Error(`Degenerate axis for molecule of sites ${molecule.join(`, `)}`)
+      }
+      prev = unit
+      return unit
+    })
+  })
+
+  const frame_time = (frames[1].step - frames[0].step) * time_step
+  const lag_times = Array.from({ length: max_lag + 1 }, (_, lag) => lag * frame_time)
+  const fit_lags = lag_times
+    .map((_, lag) => lag)
+    .filter((lag) => lag / max_lag >= fit_lo && lag / max_lag <= fit_hi)
+
+  const by_formula = new Map<string, number[]>()
+  molecules.forEach((molecule, mol_idx) => {
+    const composition: CompositionType = {}
+    for (const site_idx of molecule) {
+      const element = first.sites[site_idx].species[0]?.element
+      if (element) composition[element] = (composition[element] ?? 0) + 1
+    }
+    const formula = get_hill_formula(composition, true, ``)
+    by_formula.set(formula, [...(by_formula.get(formula) ?? []), mol_idx])
+  })
+
+  const species: Record<string, RotationalSpecies> = {}
+  for (const [formula, mol_indices] of by_formula) {
+    const [p1, p2] = [[1], [1]]
+    for (let lag = 1; lag <= max_lag; lag++) {
+      let [sum_1, sum_2, count] = [0, 0, 0]
+      for (const mol_idx of mol_indices) {
+        const axes = orientations[mol_idx]
+        for (let origin = 0; origin + lag < axes.length; origin++) {
+          const cos = math.dot(axes[origin], axes[origin + lag])
+          sum_1 += cos
+          sum_2 += (3 * cos * cos - 1) / 2
+          count++
+        }
+      }
+      p1.push(sum_1 / count)
+      p2.push(sum_2 / count)
+    }
+    const integral = (corr: number[]) =>
+      corr.slice(1).reduce((sum, val, idx) => sum + ((val + corr[idx]) / 2) * frame_time, 0)
+    const fit_1 = fit_decay(lag_times, p1, fit_lags, 1)
+    const fit_2 = fit_decay(lag_times, p2, fit_lags, 2)
+    species[formula] = {
+      molecules: mol_indices.map((mol_idx) => molecules[mol_idx]),
+      p1,
+      p2,
+      correlation_times: [integral(p1), integral(p2)],
+      rotational_diffusion: { p1: fit_1.rate, p2: fit_2.rate },
+      rotational_diffusion_err: { p1: fit_1.rate_err, p2: fit_2.rate_err },
+    }
+  }
+  return { lag_times, species, n_frames: frames.length }
+}
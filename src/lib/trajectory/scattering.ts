@@ -0,0 +1,183 @@
+// Intermediate scattering functions F(q, τ) and dynamic structure factors S(q, ω) from MD
+// trajectories, for comparison with quasi-elastic and inelastic neutron/X-ray scattering.
+// q vectors are reciprocal lattice vectors of the first sampled frame, so they satisfy
+// the periodic boundary conditions (exactly for fixed cells).
+import type { Vec3 } from '$lib/math'
+import { reciprocal_lattice_vectors, site_scattering_lengths } from '$lib/rdf/structure-factor'
+import { unwrap_positions } from './frames'
+import type { TrajectoryType } from './index'
+
+export type ScatteringOptions = {
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  time_step?: number // time per MD step, lag times in this unit (default 1)
+  max_lag?: number // longest lag in sampled frames (default half the sampled frames)
+  // half-width of the |q| shell around each requested q in Å⁻¹ (default 0.05)
+  q_tolerance?: number
+  // most q vectors averaged per shell, evenly picked from the shell (default 64)
+  max_vectors?: number
+  // per-element scattering lengths (e.g. coherent neutron lengths), default 1 per atom
+  scattering_lengths?: Record<string, number>
+  // unwrap positions across periodic boundaries, disable for already unwrapped
+  // coordinates (default true)
+  unwrap?: boolean
+}
+
+export type ScatteringFunction = {
+  q: number // mean |q| of the averaged q vectors in Å⁻¹
+  n_vectors: number
+  // F(q, τ) = <ρ_q(t + τ) ρ_−q(t)> / Σⱼ bⱼ² per lag, equal to S(q) at τ = 0
+  coherent: number[]
+  // self part F_s(q, τ) = <Σⱼ bⱼ² exp(i q·(rⱼ(t + τ) − rⱼ(t)))> / Σⱼ bⱼ², 1 at τ = 0
+  incoherent: number[]
+}
+
+export type ScatteringResult = {
+  lag_times: number[] // time of each lag, starting at 0
+  functions: ScatteringFunction[] // one per requested q
+  n_frames: number
+}
+
+export type DynamicStructureFactor = {
+  omega: number[] // angular frequency in rad per time unit
+  s_qw: number[]
+}
+
+// Coherent and incoherent intermediate scattering functions at each |q| in q_values
+// (Å⁻¹), averaged over time origins and the reciprocal lattice vectors within
+// q_tolerance. Throws if the cell supports no q vector in a shell.
+export function intermediate_scattering(
+  trajectory: TrajectoryType,
+  q_values: number[],
+  options: ScatteringOptions = {},
+): ScatteringResult {
+  const {
+    start_frame = 0,
+    stride = 1,
+    time_step = 1,
+    q_tolerance = 0.05,
+    max_vectors = 64,
+    scattering_lengths,
+    unwrap = true,
+  } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length < 2) {
+    throw new Error(`Need at least 2 frames for scattering functions, got ${frames.length}`)
+  }
+  const structures = frames.map(({ structure }) => structure)
+  const first = structures[0]
+  if (!(`lattice` in first)) throw new Error(`Scattering functions need a periodic cell`)
+  const n_sites = first.sites.length
+  if (structures.some(({ sites }) => sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+  const max_lag = options.max_lag ?? Math.floor(frames.length / 2)
+  if (!Number.isInteger(max_lag) || max_lag < 1 || max_lag >= frames.length) {
+    throw new Error(`max_lag must be an integer in [1, ${frames.length - 1}], got ${max_lag}`)
+  }
+
+  const positions = unwrap
+    ? unwrap_positions(structures)
+    : structures.map(({ sites }) => sites.map((site) => site.xyz))
+  const { matrix } = first.lattice
+  const weights = site_scattering_lengths(first.sites, scattering_lengths)
+  const norm = weights.reduce((sum, weight) => sum + weight ** 2, 0)
+  if (!(norm > 0)) throw new Error(`Trajectory has no scattering sites`)
+
+  const functions = q_values.map((q_target) => {
+    const [q_lo, q_hi] = [q_target - q_tolerance, q_target + q_tolerance]
+    const shell = reciprocal_lattice_vectors(matrix, q_hi, q_lo)
+    if (shell.length === 0) {
+      throw new Error(
+        `No reciprocal lattice vector with |q| within ${q_tolerance} of ${q_target} Å⁻¹`,
+      )
+    }
+    const picked =
+      shell.length <= max_vectors
+        ? shell
+        : Array.from(
+            { length: max_vectors },
+            (_, idx) => shell[Math.floor((idx * shell.length) / max_vectors)],
+          )
+    const coherent = Array<number>(max_lag + 1).fill(0)
+    const incoherent = Array<number>(max_lag + 1).fill(0)
+    for (const { q_vec } of picked) {
+      const phase = (pos: Vec3) => q_vec[0] * pos[0] + q_vec[1] * pos[1] + q_vec[2] * pos[2]
+      const cos = positions.map((frame_pos) => frame_pos.map((pos) => Math.cos(phase(pos))))
+      const sin = positions.map((frame_pos) => frame_pos.map((pos) => Math.sin(phase(pos))))
+      const rho = cos.map((frame_cos, frame) =>
+        frame_cos.reduce(
+          ([re, im], cos_j, site_idx) => [
+            re + weights[site_idx] * cos_j,
+            im + weights[site_idx] * sin[frame][site_idx],
+          ],
+          [0, 0],
+        ),
+      )
+      for (let lag = 0; lag <= max_lag; lag++) {
+        for (let origin = 0; origin + lag < frames.length; origin++) {
+          const [[re_0, im_0], [re_t, im_t]] = [rho[origin], rho[origin + lag]]
+          coherent[lag] += re_0 * re_t + im_0 * im_t
+          for (let site_idx = 0; site_idx < n_sites; site_idx++) {
+            incoherent[lag] +=
+              weights[site_idx] ** 2 *
+              (cos[origin][site_idx] * cos[origin + lag][site_idx] +
+                sin[origin][site_idx] * sin[origin + lag][site_idx])
+          }
+        }
+      }
+    }
+    const average = (sums: number[]) =>
+      sums.map((sum, lag) => sum / (norm * picked.length * (frames.length - lag)))
+    return {
+      q: picked.reduce((sum, { q_norm }) => sum + q_norm, 0) / picked.length,
+      n_vectors: picked.length,
+      coherent: average(coherent),
+      incoherent: average(incoherent),
+    }
+  })
+
+  const frame_time = (frames[1].step - frames[0].step) * time_step
+  const lag_times = Array.from({ length: max_lag + 1 }, (_, lag) => lag * frame_time)
+  return { lag_times, functions, n_frames: frames.length }
+}
+
+// S(q, ω) = (1 / 2π) ∫ F(q, t) exp(−iωt) dt of an intermediate scattering function
+// sampled at equally spaced lag_times from 0, using F(q, −t) = F(q, t). A Hann window
+// suppresses the ringing from truncating F(q, t) (default on). ω runs from 0 to
+// omega_max (default the Nyquist frequency π / Δt) in n_omega points (default as many as
+// lags).
+export function dynamic_structure_factor(
+  f_qt: number[],
+  lag_times: number[],
+  options: { omega_max?: number; n_omega?: number; window?: boolean } = {},
+): DynamicStructureFactor {
+  if (f_qt.length < 2 || f_qt.length !== lag_times.length) {
+    throw new Error(
+      `Need at least 2 lags and as many lag_times as values, got ${f_qt.length} values ` +
+        `and ${lag_times.length} lag_times`,
+    )
+  }
+  const dt = lag_times[1] - lag_times[0]
+  if (!(dt > 0)) throw new Error(`lag_times must increase, got Δt = ${dt}`)
+  const { omega_max = Math.PI / dt, n_omega = f_qt.length, window = true } = options
+  const t_max = lag_times[lag_times.length - 1]
+  const windowed = f_qt.map((val, idx) => {
+    const damping = window ? 0.5 * (1 + Math.cos((Math.PI * lag_times[idx]) / t_max)) : 1
+    // trapezoid weights, halved at both ends
+    const end_weight = idx === 0 || idx === f_qt.length - 1 ? 0.5 : 1
+    return val * damping * end_weight * dt
+  })
+  const omega = Array.from({ length: n_omega }, (_, idx) =>
+    n_omega === 1 ? 0 : (idx * omega_max) / (n_omega - 1),
+  )
+  const s_qw = omega.map(
+    (freq) =>
+      windowed.reduce((sum, val, idx) => sum + val * Math.cos(freq * lag_times[idx]), 0) /
+      Math.PI,
+  )
+  return { omega, s_qw }
+}
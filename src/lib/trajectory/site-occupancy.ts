@@ -0,0 +1,185 @@
+// Site-resolved ion dynamics for solid electrolytes: mobile ions are assigned to
+// crystallographic sites of a reference structure frame by frame, giving site
+// occupancies, residence times and hop rates between site types
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Crystal } from '$lib/structure/index'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_indices } from '$lib/structure/select'
+import { is_crystal } from '$lib/structure/validation'
+import type { DistinctSite } from '$lib/symmetry'
+import type { TrajectoryType } from './index'
+
+export type ReferenceSite = {
+  abc: Vec3 // fractional coordinates, mapped into each frame's cell
+  type: string // site type, e.g. `Li 24d`
+}
+
+export type SiteOccupancyOptions = {
+  mobile?: AtomSelection // ions to assign, evaluated on the first sampled frame (default all)
+  start_frame?: number // skip equilibration frames before this index (default 0)
+  stride?: number // use every stride-th frame (default 1)
+  time_step?: number // time per MD step, residence times and rates use this unit (default 1)
+  // Å, ions farther from every site are in transit (default half the shortest distance
+  // between reference sites)
+  cutoff?: number
+}
+
+export type SiteOccupancy = {
+  times: number[]
+  mobile_indices: number[] // site indices of the tracked ions
+  // [frame][ion] index into the reference sites, -1 while in transit
+  assignments: number[][]
+  occupancy: number[] // mean number of ions per reference site and frame
+  types: string[] // distinct site types in order of first appearance
+  type_occupancy: Record<string, number> // mean occupancy of the sites of each type
+  // completed stays per type, from arrival to the next hop (transit time included, so
+  // rattling across the cutoff doesn't count as hops)
+  residence_times: Record<string, number[]>
+  mean_residence_time: Record<string, number> // NaN without completed stays
+  hop_counts: number[][] // [from type][to type], in order of types
+  // hops per ion and time unit, hop counts over the total time ions spent on the from type
+  hop_rates: number[][]
+  n_frames: number
+}
+
+// Reference sites from a structure's symmetry-distinct sites (e.g. from
+// symmetry_distinct_sites(reference, { elements: [`Li`] })), typed by element and
+// Wyckoff position like `Li 24d`. Distinct orbits on the same Wyckoff letter get a
+// numbered suffix (`Li 4e`, `Li 4e (2)`).
+export function reference_sites_from_wyckoff(
+  reference: Crystal,
+  distinct_sites: DistinctSite[],
+): ReferenceSite[] {
+  const label_counts = new Map<string, number>()
+  return distinct_sites.flatMap(({ elem, wyckoff, site_indices }) => {
+    const label = `${elem} ${wyckoff}`
+    const count = (label_counts.get(label) ?? 0) + 1
+    label_counts.set(label, count)
+    const type = count > 1 ? `${label} (${count})` : label
+    return site_indices.map((site_idx) => {
+      const site = reference.sites[site_idx]
+      if (!site) throw new Error(`Site index ${site_idx} out of range for the reference`)
+      return { abc: [...site.abc] as Vec3, type }
+    })
+  })
+}
+
+// Shortest minimum-image distance between any two reference sites in a cell
+function shortest_site_distance(reference_sites: ReferenceSite[], crystal: Crystal): number {
+  const { matrix, pbc } = crystal.lattice
+  const converters = math.create_lattice_converters(matrix)
+  const site_xyz = reference_sites.map(({ abc }) => converters.frac_to_cart(abc))
+  let min_dist = Infinity
+  site_xyz.forEach((xyz_1, idx) => {
+    for (const xyz_2 of site_xyz.slice(idx + 1)) {
+      min_dist = Math.min(min_dist, math.pbc_dist(xyz_1, xyz_2, matrix, converters, pbc))
+    }
+  })
+  return min_dist
+}
+
+// Assign every mobile ion to its nearest reference site (minimum image in the frame's
+// cell) if within cutoff. Hops are counted core to core: an ion stays on its last site
+// while in transit until it arrives at a different one.
+export function site_occupancy(
+  trajectory: TrajectoryType,
+  reference_sites: ReferenceSite[],
+  options: SiteOccupancyOptions = {},
+): SiteOccupancy {
+  const { start_frame = 0, stride = 1, time_step = 1 } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  if (reference_sites.length === 0) throw new Error(`Need at least one reference site`)
+  const frames = trajectory.frames.slice(start_frame).filter((_, idx) => idx % stride === 0)
+  if (frames.length === 0) throw new Error(`No frames left after start_frame/stride`)
+  const crystals = frames.map(({ structure }) => structure)
+  if (!crystals.every(is_crystal)) {
+    throw new Error(`Site occupancy requires periodic structures in every frame`)
+  }
+  const mobile_indices = selection_indices(crystals[0], options.mobile)
+  const n_sites = crystals[0].sites.length
+  if (crystals.some(({ sites }) => sites.length !== n_sites)) {
+    throw new Error(`All frames must have the same number of sites`)
+  }
+
+  const cutoff = options.cutoff ?? shortest_site_distance(reference_sites, crystals[0]) / 2
+  if (!(cutoff > 0)) throw new Error(`cutoff must be positive, got ${cutoff}`)
+
+  const times = frames.map(({ step }) => step * time_step)
+  const assignments = crystals.map(({ lattice, sites }) => {
+    const converters = math.create_lattice_converters(lattice.matrix)
+    const site_xyz = reference_sites.map(({ abc }) => converters.frac_to_cart(abc))
+    return mobile_indices.map((ion_idx) => {
+      let [best, best_dist] = [-1, Infinity]
+      site_xyz.forEach((xyz, ref_idx) => {
+        const { xyz: ion_xyz } = sites[ion_idx]
+        const dist = math.pbc_dist(ion_xyz, xyz, lattice.matrix, converters, lattice.pbc)
+        if (dist < best_dist) [best, best_dist] = [ref_idx, dist]
+      })
+      return best_dist <= cutoff ? best : -1
+    })
+  })
+
+  const types = [...new Set(reference_sites.map(({ type }) => type))]
+  const type_idx = reference_sites.map(({ type }) => types.indexOf(type))
+  const occupancy = reference_sites.map(() => 0)
+  for (const frame_assignments of assignments) {
+    for (const ref_idx of frame_assignments) {
+      if (ref_idx >= 0) occupancy[ref_idx] += 1 / frames.length
+    }
+  }
+  const type_occupancy: Record<string, number> = {}
+  types.forEach((type, idx) => {
+    const of_type = occupancy.filter((_, ref_idx) => type_idx[ref_idx] === idx)
+    type_occupancy[type] = of_type.reduce((sum, occ) => sum + occ, 0) / of_type.length
+  })
+
+  const hop_counts = types.map(() => types.map(() => 0))
+  const time_on_type = types.map(() => 0)
+  const stays: number[][] = types.map(() => [])
+  const end_time = times[times.length - 1]
+  mobile_indices.forEach((_, ion) => {
+    let current = -1
+    let [arrival, completed] = [0, false] // completed: the current stay began with a hop
+    assignments.forEach((frame_assignments, frame_idx) => {
+      const ref_idx = frame_assignments[ion]
+      if (ref_idx < 0 || ref_idx === current) return
+      if (current >= 0) {
+        const duration = times[frame_idx] - arrival
+        time_on_type[type_idx[current]] += duration
+        hop_counts[type_idx[current]][type_idx[ref_idx]]++
+        if (completed) stays[type_idx[current]].push(duration)
+      }
+      completed = current >= 0
+      current = ref_idx
+      arrival = times[frame_idx]
+    })
+    if (current >= 0) time_on_type[type_idx[current]] += end_time - arrival
+  })
+
+  const residence_times: Record<string, number[]> = {}
+  const mean_residence_time: Record<string, number> = {}
+  types.forEach((type, idx) => {
+    residence_times[type] = stays[idx]
+    mean_residence_time[type] =
+      stays[idx].reduce((sum, val) => sum + val, 0) / stays[idx].length
+  })
+  const hop_rates = hop_counts.map((row, idx) =>
+    row.map((count) => (time_on_type[idx] > 0 ? count / time_on_type[idx] : NaN)),
+  )
+  return {
+    times,
+    mobile_indices,
+    assignments,
+    occupancy,
+    types,
+    type_occupancy,
+    residence_times,
+    mean_residence_time,
+    hop_counts,
+    hop_rates,
+    n_frames: frames.length,
+  }
+}
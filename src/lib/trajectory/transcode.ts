@@ -0,0 +1,150 @@
+// Streaming trajectory conversion between text formats with striding, frame ranges and
+// atom selection, e.g. to downsample multi-GB MD runs for visualization. Lazily loaded
+// trajectories are read one frame at a time through their frame_loader and every frame
+// is emitted as its own chunk, so memory stays bounded by a single frame.
+import type { AnyStructure, Crystal } from '$lib/structure'
+import { lammps_box_matrix, structure_to_xyz_str } from '$lib/structure/export'
+import type { AtomSelection } from '$lib/structure/select'
+import { selection_indices } from '$lib/structure/select'
+import { select_sites } from '$lib/structure/site-fields'
+import type { TrajectoryFrame, TrajectoryType } from './index'
+
+export const TRANSCODE_FORMATS = [`extxyz`, `lammps-dump`, `xdatcar`] as const
+export type TranscodeFormat = (typeof TRANSCODE_FORMATS)[number]
+
+export type TranscodeOptions = {
+  stride?: number // keep every stride-th frame of the range (default 1)
+  frame_range?: [number, number] // [start, end) frame indices (default all frames)
+  selection?: AtomSelection // sites to keep, evaluated per frame (index lists keep order)
+  data?: string | ArrayBuffer // raw file contents, needed for trajectories with a frame_loader
+}
+
+const fmt_num = (val: number) => (Number.isFinite(val) ? val : 0).toFixed(8)
+
+const require_lattice = (structure: AnyStructure, format: TranscodeFormat): Crystal => {
+  if (!(`lattice` in structure)) throw new Error(`${format} output needs periodic frames`)
+  return structure
+}
+
+// Extended XYZ frame with the step and scalar frame metadata (energy, ...) in the comment
+function extxyz_frame({ structure, step, metadata = {} }: TrajectoryFrame): string {
+  const lines = structure_to_xyz_str(structure).split(`\n`)
+  const scalars = Object.entries(metadata).filter(
+    (entry): entry is [string, number] =>
+      entry[0] !== `step` && typeof entry[1] === `number` && isFinite(entry[1]),
+  )
+  lines[1] += [[`step`, step], ...scalars].map(([key, val]) => ` ${key}=${val}`).join(``)
+  return `${lines.join(`\n`)}\n`
+}
+
+// LAMMPS dump frame (metal units) in the triclinic box convention with scaled coordinates
+// and an element column, readable by parse_lammps_trajectory and OVITO
+function lammps_dump_frame({ structure, step }: TrajectoryFrame): string {
+  const { lattice, sites } = require_lattice(structure, `lammps-dump`)
+  const [[lx], [xy, ly], [xz, yz, lz]] = lammps_box_matrix(lattice.matrix)
+  const [x_lo, x_hi] = [Math.min(0, xy, xz, xy + xz), lx + Math.max(0, xy, xz, xy + xz)]
+  const [y_lo, y_hi] = [Math.min(0, yz), ly + Math.max(0, yz)]
+  const pbc = lattice.pbc.map((periodic) => (periodic ? `pp` : `ff`)).join(` `)
+  const atom_lines = sites.map(
+    ({ species, abc }, idx) =>
+      `${idx + 1} ${species[0]?.element ?? `X`} ${abc.map(fmt_num).join(` `)}`,
+  )
+  return [
+    `ITEM: TIMESTEP`,
+    `${step}`,
+    `ITEM: NUMBER OF ATOMS`,
+    `${sites.length}`,
+    `ITEM: BOX BOUNDS xy xz yz ${pbc}`,
+    `${fmt_num(x_lo)} ${fmt_num(x_hi)} ${fmt_num(xy)}`,
+    `${fmt_num(y_lo)} ${fmt_num(y_hi)} ${fmt_num(xz)}`,
+    `0 ${fmt_num(lz)} ${fmt_num(yz)}`,
+    `ITEM: ATOMS id element xs ys zs`,
+    ...atom_lines,
+    ``,
+  ].join(`\n`)
+}
+
+// XDATCAR header: title, unit scale, lattice rows, then element names and counts of
+// consecutive runs of equal elements (repeated names are valid for VASP 5)
+function xdatcar_header(structure: Crystal): string {
+  const runs: [string, number][] = []
+  for (const { species } of structure.sites) {
+    const element = species[0]?.element ?? `X`
+    const last = runs[runs.length - 1]
+    if (last?.[0] === element) last[1]++
+    else runs.push([element, 1])
+  }
+  const title = structure.id ?? runs.map(([element]) => element).join(``)
+  return [
+    title,
+    `1.0`,
+    ...structure.lattice.matrix.map((row) => `  ${row.map(fmt_num).join(` `)}`),
+    runs.map(([element]) => element).join(` `),
+    runs.map(([, count]) => count).join(` `),
+    ``,
+  ].join(`\n`)
+}
+
+// Frames as text chunks in the target format, header included in the first chunk (and
+// repeated for XDATCAR whenever the cell or species change, as VASP does for NPT runs)
+export async function* transcode_trajectory(
+  trajectory: TrajectoryType,
+  format: TranscodeFormat,
+  options: TranscodeOptions = {},
+): AsyncGenerator<string> {
+  if (!TRANSCODE_FORMATS.includes(format)) {
+    throw new Error(
+      `Unsupported output format ${format}, expected one of ${TRANSCODE_FORMATS.join(`, `)}`,
+    )
+  }
+  const { stride = 1, selection, data } = options
+  if (!Number.isInteger(stride) || stride < 1) {
+    throw new Error(`stride must be a positive integer, got ${stride}`)
+  }
+  const loader = trajectory.frame_loader
+  if (loader && data === undefined) {
+    throw new Error(`Trajectories with a frame_loader need the raw file data`)
+  }
+  const n_frames =
+    loader && data !== undefined
+      ? (trajectory.total_frames ?? (await loader.get_total_frames(data)))
+      : trajectory.frames.length
+  const [start, end] = options.frame_range ?? [0, n_frames]
+  if (!(Number.isInteger(start) && Number.isInteger(end) && start >= 0 && start <= end)) {
+    throw new Error(`Invalid frame_range [${start}, ${end}]`)
+  }
+
+  let prev_header: string | null = null
+  for (let frame_idx = start; frame_idx < Math.min(end, n_frames); frame_idx += stride) {
+    const frame =
+      loader && data !== undefined
+        ? await loader.load_frame(data, frame_idx)
+        : trajectory.frames[frame_idx]
+    if (!frame) throw new Error(`Failed to load frame ${frame_idx}`)
+    let { structure } = frame
+    if (selection) structure = select_sites(structure, selection_indices(structure, selection))
+    const selected = { ...frame, structure }
+
+    if (format === `extxyz`) yield extxyz_frame(selected)
+    else if (format === `lammps-dump`) yield lammps_dump_frame(selected)
+    else {
+      const crystal = require_lattice(structure, format)
+      const header = xdatcar_header(crystal)
+      const coords = crystal.sites.map(({ abc }) => `  ${abc.map(fmt_num).join(` `)}`)
+      const body = [`Direct configuration= ${frame.step}`, ...coords, ``].join(`\n`)
+      yield header === prev_header ? body : `${header}${body}`
+      prev_header = header
+    }
+  }
+}
+
+// Concatenated output of transcode_trajectory, for trajectories that fit in memory
+export async function transcode_trajectory_to_string(
+  trajectory: TrajectoryType,
+  format: TranscodeFormat,
+  options: TranscodeOptions = {},
+): Promise<string> {
+  let text = ``
+  for await (const chunk of transcode_trajectory(trajectory, format, options)) text += chunk
+  return text
+}
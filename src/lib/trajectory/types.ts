@@ -1,4 +1,5 @@
 import type { ElementSymbol } from '$lib/element/types'
+import type { UnitSystem } from '$lib/units'
 
 export type AtomTypeMapping = Record<number, ElementSymbol>
 
@@ -10,4 +11,5 @@ export interface LoadingOptions {
   bin_file_threshold?: number // Threshold in bytes for ArrayBuffer files (default: MAX_BIN_FILE_SIZE)
   text_file_threshold?: number // Threshold in bytes for string files (default: MAX_TEXT_FILE_SIZE)
   atom_type_mapping?: AtomTypeMapping // Map LAMMPS atom types to element symbols (e.g. {1: 'Na', 2: 'Cl'})
+  lammps_units?: UnitSystem // units of LAMMPS dumps lacking ITEM: UNITS (default: metal)
 }
@@ -0,0 +1,92 @@
+// Unit systems for MD data and conversions between them. MatterViz stores structures and
+// trajectories in the `ase` convention (eV, Å, fs), so parsers convert incoming data from
+// the source's unit system and writers convert back out.
+
+export type UnitSystem = `ase` | `metal` | `real` | `si`
+export type PhysicalQuantity =
+  | `length`
+  | `energy`
+  | `time`
+  | `pressure`
+  | `force`
+  | `velocity`
+  | `mass`
+
+// Exact SI-defining constants
+const ELECTRON_VOLT = 1.602176634e-19 // J
+const AVOGADRO = 6.02214076e23 // 1/mol
+const KCAL_PER_MOL = 4184 / AVOGADRO // J per particle
+const DALTON = 1.6605390666e-27 // kg (CODATA 2018)
+
+type UnitDef = { label: string; si: number } // si: value of one unit in SI base units
+
+type UnitTable = Readonly<Record<UnitSystem, Record<PhysicalQuantity, UnitDef>>>
+
+export const UNIT_SYSTEMS: UnitTable = {
+  // ASE-style eV/Å/fs. Note ASE's own internal time unit is Å·√(amu/eV) ≈ 10.18 fs.
+  ase: {
+    length: { label: `Å`, si: 1e-10 },
+    energy: { label: `eV`, si: ELECTRON_VOLT },
+    time: { label: `fs`, si: 1e-15 },
+    pressure: { label: `eV/Å³`, si: ELECTRON_VOLT / 1e-30 },
+    force: { label: `eV/Å`, si: ELECTRON_VOLT / 1e-10 },
+    velocity: { label: `Å/fs`, si: 1e5 },
+    mass: { label: `amu`, si: DALTON },
+  },
+  // LAMMPS `units metal`: https://docs.lammps.org/units.html
+  metal: {
+    length: { label: `Å`, si: 1e-10 },
+    energy: { label: `eV`, si: ELECTRON_VOLT },
+    time: { label: `ps`, si: 1e-12 },
+    pressure: { label: `bar`, si: 1e5 },
+    force: { label: `eV/Å`, si: ELECTRON_VOLT / 1e-10 },
+    velocity: { label: `Å/ps`, si: 1e2 },
+    mass: { label: `g/mol`, si: DALTON },
+  },
+  // LAMMPS `units real`
+  real: {
+    length: { label: `Å`, si: 1e-10 },
+    energy: { label: `kcal/mol`, si: KCAL_PER_MOL },
+    time: { label: `fs`, si: 1e-15 },
+    pressure: { label: `atm`, si: 101325 },
+    force: { label: `kcal/(mol·Å)`, si: KCAL_PER_MOL / 1e-10 },
+    velocity: { label: `Å/fs`, si: 1e5 },
+    mass: { label: `g/mol`, si: DALTON },
+  },
+  // SI (also LAMMPS `units si`)
+  si: {
+    length: { label: `m`, si: 1 },
+    energy: { label: `J`, si: 1 },
+    time: { label: `s`, si: 1 },
+    pressure: { label: `Pa`, si: 1 },
+    force: { label: `N`, si: 1 },
+    velocity: { label: `m/s`, si: 1 },
+    mass: { label: `kg`, si: 1 },
+  },
+}
+
+export const is_unit_system = (value: unknown): value is UnitSystem =>
+  typeof value === `string` && Object.hasOwn(UNIT_SYSTEMS, value)
+
+// Multiply a quantity expressed in `from` units by this factor to express it in `to` units
+export function unit_factor(
+  quantity: PhysicalQuantity,
+  from: UnitSystem,
+  to: UnitSystem,
+): number {
+  if (!is_unit_system(from) || !is_unit_system(to)) {
+    throw new Error(`Unknown unit system: ${is_unit_system(from) ? to : from}`)
+  }
+  if (from === to) return 1
+  return UNIT_SYSTEMS[from][quantity].si / UNIT_SYSTEMS[to][quantity].si
+}
+
+export const convert_units = (
+  value: number,
+  quantity: PhysicalQuantity,
+  from: UnitSystem,
+  to: UnitSystem,
+): number => value * unit_factor(quantity, from, to)
+
+export const unit_label = (quantity: PhysicalQuantity, system: UnitSystem): string =>
+  UNIT_SYSTEMS[system][quantity].label
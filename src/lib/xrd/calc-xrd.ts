@@ -4,6 +4,7 @@ import { element_data } from '$lib/element'
 import * as math from '$lib/math'
 import type { Vec2 } from '$lib/math'
 import type { Crystal } from '$lib/structure/index'
+import { get_site_u_cart } from '$lib/structure/adp'
 import { parse_any_structure } from '$lib/structure/parse'
 import { is_crystal } from '$lib/structure/validation'
 // Single source of truth for atomic scattering params
@@ -170,6 +171,9 @@ export function compute_xrd_pattern(structure: Crystal, options: XrdOptions = {}
           (angle) => (2 * Math.sin((angle / 2) * (Math.PI / 180))) / wavelength,
         )
 
+  // Cartesian reciprocal vector g = h a* + k b* + l c* as a matrix-vector product
+  const recip_cols = math.transpose_3x3_matrix(recip_rows)
+
   const recip_points = enumerate_reciprocal_points(
     recip_rows,
     structure.lattice.matrix,
@@ -183,6 +187,9 @@ export function compute_xrd_pattern(structure: Crystal, options: XrdOptions = {}
   const frac_coords: math.Vec3[] = []
   const occus: number[] = []
   const dw_factors: number[] = []
+  // Per-site anisotropic U tensors (Å²) take precedence over per-element B factors
+  const u_tensors: (math.Matrix3x3 | null)[] = []
+  const use_site_adps = options.site_adps ?? false
 
   const debye_waller_factors = options.debye_waller_factors ?? {}
 
@@ -206,6 +213,7 @@ export function compute_xrd_pattern(structure: Crystal, options: XrdOptions = {}
       frac_coords.push(site.abc)
       occus.push(species.occu)
       dw_factors.push(debye_waller_factors[element_symbol] ?? 0)
+      u_tensors.push(use_site_adps ? get_site_u_cart(site) : null)
     }
   }
 
@@ -238,9 +246,14 @@ export function compute_xrd_pattern(structure: Crystal, options: XrdOptions = {}
       return atomic_number - 41.78214 * sin_theta_over_lambda_sq * sum_terms
     })
 
-    const dw_corr: number[] = dw_factors.map((dw_b) =>
-      Math.exp(-dw_b * sin_theta_over_lambda_sq),
-    )
+    // Anisotropic factor exp(-2π² gᵀUg) reduces to exp(-B s²) for isotropic U = B / 8π²
+    const g_cart = math.mat3x3_vec3_multiply(recip_cols, hkl)
+    const dw_corr: number[] = dw_factors.map((dw_b, idx) => {
+      const u_cart = u_tensors[idx]
+      if (!u_cart) return Math.exp(-dw_b * sin_theta_over_lambda_sq)
+      const g_u_g = math.dot(g_cart, math.mat3x3_vec3_multiply(u_cart, g_cart))
+      return Math.exp(-2 * Math.PI ** 2 * g_u_g)
+    })
 
     // Structure factor sum: sum(fs * occu * exp(2πi g·r) * DW)
     const { real: f_real, imag: f_imag } = f_scattering.reduce(
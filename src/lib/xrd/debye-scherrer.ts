@@ -0,0 +1,243 @@
+import * as math from '$lib/math'
+import type { Matrix3x3, Vec2, Vec3 } from '$lib/math'
+import type { Crystal } from '$lib/structure/index'
+import type { BroadeningParams } from './broadening'
+import { caglioti_fwhm, DEFAULT_BROADENING, pseudo_voigt } from './broadening'
+import { compute_xrd_pattern } from './calc-xrd'
+import type { Hkl, XrdOptions } from './index'
+
+// Fiber (uniaxial) texture: crystallites orient their `preferred_hkl` reciprocal-lattice
+// direction around the lab-frame `fiber_axis` following a March-Dollase distribution.
+export type FiberTexture = {
+  preferred_hkl: Hkl
+  // March-Dollase parameter r: 1 = random powder, < 1 = preferred_hkl concentrated along
+  // the fiber axis (platelets), > 1 = preferred_hkl perpendicular to it (needles)
+  march_dollase_r: number
+  // Lab-frame fiber axis with the incident beam along +z (default [0, 1, 0], vertical)
+  fiber_axis?: Vec3
+}
+
+// Flat area detector perpendicular to the incident beam.
+export type DetectorGeometry = {
+  distance: number // sample-to-detector distance (mm)
+  pixel_size: number // square pixel edge length (mm)
+  shape: Vec2 // detector size in pixels [n_cols, n_rows]
+  beam_center?: Vec2 // direct-beam position in pixels [col, row] (default: detector center)
+}
+
+export type DebyeScherrerOptions = XrdOptions & {
+  texture?: FiberTexture | null
+  n_azimuth?: number // azimuthal bins per ring (default 360)
+}
+
+export type DebyeScherrerRing = {
+  two_theta: number // ring position in degrees
+  intensity: number // powder intensity of the ring (same as the 1D pattern peak)
+  hkls: Hkl[] // every reflection contributing to the ring (not just family representatives)
+  // Relative intensity per azimuth bin. All ones for random powders.
+  azimuthal: number[]
+}
+
+export type DebyeScherrerPattern = {
+  azimuths: number[] // azimuth bin centers in degrees, counter-clockwise from +x
+  rings: DebyeScherrerRing[]
+}
+
+export type DetectorImage = {
+  width: number
+  height: number
+  data: Float32Array // row-major intensities, data[row * width + col]
+}
+
+// Number of points used to average the orientation density over the cone of crystallite
+// orientations that bring a reflection into diffraction condition at a given azimuth
+const N_CONE_SAMPLES = 72
+
+const march_dollase_cos = (cos_alpha: number, r_param: number): number =>
+  (r_param * r_param * cos_alpha * cos_alpha + (1 - cos_alpha * cos_alpha) / r_param) ** -1.5
+
+// March-Dollase orientation density P(α) = (r² cos²α + sin²α / r)^(-3/2), normalized
+// such that its average over the unit sphere is 1. alpha in radians.
+export function march_dollase(alpha: number, r_param: number): number {
+  if (!Number.isFinite(r_param) || r_param <= 0) {
+    throw new Error(`March-Dollase parameter must be finite and > 0, got ${r_param}`)
+  }
+  return march_dollase_cos(Math.cos(alpha), r_param)
+}
+
+// Unit scattering vector in the lab frame for a ring at two_theta and azimuth (both degrees).
+// The incident beam travels along +z, azimuth is measured counter-clockwise from +x.
+export function scattering_direction(two_theta: number, azimuth: number): Vec3 {
+  const theta = math.to_radians(two_theta) / 2
+  const eta = math.to_radians(azimuth)
+  return [Math.cos(theta) * Math.cos(eta), Math.cos(theta) * Math.sin(eta), -Math.sin(theta)]
+}
+
+// Every reflection on the reciprocal-lattice shells of the family representatives that
+// compute_xrd_pattern merged into a peak. Enumerating whole shells rather than permuting
+// indices also catches equivalents like (-110) of (100) in hexagonal cells.
+function expand_hkl_family(
+  representatives: Hkl[],
+  to_cart: (hkl: Hkl) => Vec3,
+  lattice: Matrix3x3,
+): Hkl[] {
+  const ref_norms = representatives.map((hkl) => Math.hypot(...to_cart(hkl)))
+  if (ref_norms.length === 0) return []
+  // |h_i| = |g·a_i| <= |g| |a_i| bounds the indices on the largest shell
+  const max_norm = Math.max(...ref_norms)
+  const [h_max, k_max, l_max] = lattice.map((row) => Math.ceil(max_norm * Math.hypot(...row)))
+  const members: Hkl[] = []
+  for (let h_idx = -h_max; h_idx <= h_max; h_idx++) {
+    for (let k_idx = -k_max; k_idx <= k_max; k_idx++) {
+      for (let l_idx = -l_max; l_idx <= l_max; l_idx++) {
+        const norm = Math.hypot(...to_cart([h_idx, k_idx, l_idx]))
+        const on_shell = ref_norms.some((ref) => Math.abs(norm - ref) <= 1e-6 * ref)
+        if (on_shell) members.push([h_idx, k_idx, l_idx])
+      }
+    }
+  }
+  return members
+}
+
+// Orientation density averaged over crystallites that have reflection g parallel to the
+// scattering vector q. Their preferred axis H then lies on a cone of half-angle
+// φ = ∠(g, H) around q, so cos∠(H, F) = cos ψ cos φ + sin ψ sin φ cos t with ψ = ∠(q, F).
+function cone_averaged_density(cos_psi: number, cos_phi: number, r_param: number): number {
+  const sin_psi = Math.sqrt(Math.max(0, 1 - cos_psi * cos_psi))
+  const sin_phi = Math.sqrt(Math.max(0, 1 - cos_phi * cos_phi))
+  let sum = 0
+  for (let idx = 0; idx < N_CONE_SAMPLES; idx++) {
+    const cos_t = Math.cos((2 * Math.PI * idx) / N_CONE_SAMPLES)
+    const cos_beta = Math.max(-1, Math.min(1, cos_psi * cos_phi + sin_psi * sin_phi * cos_t))
+    sum += march_dollase_cos(cos_beta, r_param)
+  }
+  return sum / N_CONE_SAMPLES
+}
+
+// Simulate Debye-Scherrer rings: the 1D powder pattern plus the azimuthal intensity
+// variation of each ring caused by an (optional) fiber texture.
+export function compute_debye_scherrer_rings(
+  structure: Crystal,
+  options: DebyeScherrerOptions = {},
+): DebyeScherrerPattern {
+  const { texture = null, n_azimuth = 360, ...xrd_options } = options
+  if (!Number.isInteger(n_azimuth) || n_azimuth < 1) {
+    throw new Error(`n_azimuth must be a positive integer, got ${n_azimuth}`)
+  }
+  if (texture) march_dollase(0, texture.march_dollase_r) // validate r early
+
+  const pattern = compute_xrd_pattern(structure, { ...xrd_options, scaled: false })
+  const recip_rows: Matrix3x3 = math.transpose_3x3_matrix(
+    math.matrix_inverse_3x3(structure.lattice.matrix),
+  )
+  const to_cart = ([h_idx, k_idx, l_idx]: Hkl): Vec3 =>
+    math.add(
+      math.scale(recip_rows[0], h_idx),
+      math.scale(recip_rows[1], k_idx),
+      math.scale(recip_rows[2], l_idx),
+    )
+
+  const azimuths = Array.from(
+    { length: n_azimuth },
+    (_, idx) => ((idx + 0.5) * 360) / n_azimuth,
+  )
+  const fiber_axis = texture ? math.normalize_vec<Vec3>(texture.fiber_axis ?? [0, 1, 0]) : null
+  const preferred = texture ? math.normalize_vec(to_cart(texture.preferred_hkl)) : null
+
+  // Rescale so the strongest ring is 100, consistent with the default 1D pattern scaling
+  const max_intensity = Math.max(0, ...pattern.y)
+  const scale = (options.scaled ?? true) && max_intensity > 0 ? 100 / max_intensity : 1
+
+  const rings = pattern.x.map((two_theta, peak_idx): DebyeScherrerRing => {
+    const hkls = expand_hkl_family(
+      (pattern.hkls?.[peak_idx] ?? []).map(({ hkl }) => hkl),
+      to_cart,
+      structure.lattice.matrix,
+    )
+    let azimuthal: number[] = Array(n_azimuth).fill(1)
+    if (texture && fiber_axis && preferred && hkls.length > 0) {
+      const { march_dollase_r } = texture
+      const cos_phis = hkls.map((hkl) => math.dot(math.normalize_vec(to_cart(hkl)), preferred))
+      azimuthal = azimuths.map((azimuth) => {
+        const cos_psi = math.dot(scattering_direction(two_theta, azimuth), fiber_axis)
+        const total = cos_phis.reduce(
+          (sum, cos_phi) => sum + cone_averaged_density(cos_psi, cos_phi, march_dollase_r),
+          0,
+        )
+        return total / cos_phis.length
+      })
+    }
+    return { two_theta, intensity: pattern.y[peak_idx] * scale, hkls, azimuthal }
+  })
+
+  return { azimuths, rings }
+}
+
+// Render Debye-Scherrer rings onto a flat area detector. Each ring is broadened radially
+// with the Caglioti pseudo-Voigt profile (combined in quadrature with the angular width of
+// the pixel so narrow rings aren't missed between pixel centers) and modulated by its
+// azimuthal texture profile. Intensities include the 1/sin(2θ) spreading of the ring around
+// the Debye cone and the cos³(2θ) solid angle of flat-detector pixels.
+export function simulate_detector_image(
+  structure: Crystal,
+  geometry: DetectorGeometry,
+  options: DebyeScherrerOptions & { broadening?: BroadeningParams } = {},
+): DetectorImage {
+  const { distance, pixel_size, shape } = geometry
+  if (!(distance > 0) || !(pixel_size > 0)) {
+    throw new Error(`Detector distance and pixel_size must be > 0`)
+  }
+  const [width, height] = shape
+  if (!Number.isInteger(width) || !Number.isInteger(height) || width < 1 || height < 1) {
+    throw new Error(`Detector shape must be positive integers, got [${shape.join(`, `)}]`)
+  }
+  const [center_col, center_row] = geometry.beam_center ?? [(width - 1) / 2, (height - 1) / 2]
+
+  // Largest scattering angle reaching the detector (farthest corner from the beam center)
+  const max_radius = Math.max(
+    ...[0, width - 1].flatMap((col) =>
+      [0, height - 1].map((row) => Math.hypot(col - center_col, row - center_row)),
+    ),
+  )
+  const max_two_theta = math.to_degrees(Math.atan2(max_radius * pixel_size, distance))
+
+  const { broadening = DEFAULT_BROADENING, ...ring_options } = options
+  const { shape_factor } = broadening
+  const { azimuths, rings } = compute_debye_scherrer_rings(structure, {
+    ...ring_options,
+    two_theta_range: options.two_theta_range ?? [0, Math.min(180, max_two_theta + 1)],
+  })
+  const n_azimuth = azimuths.length
+  const ring_params = rings.map(({ two_theta, intensity, azimuthal }) => {
+    const fwhm = caglioti_fwhm(two_theta, broadening.U, broadening.V, broadening.W)
+    const sin_two_theta = Math.max(Math.sin(math.to_radians(two_theta)), 1e-6)
+    return { two_theta, fwhm, weight: intensity / sin_two_theta, azimuthal }
+  })
+
+  const data = new Float32Array(width * height)
+  for (let row = 0; row < height; row++) {
+    const y_mm = (center_row - row) * pixel_size
+    for (let col = 0; col < width; col++) {
+      const x_mm = (col - center_col) * pixel_size
+      const radius_sq = x_mm * x_mm + y_mm * y_mm
+      const two_theta_rad = Math.atan2(Math.sqrt(radius_sq), distance)
+      const two_theta = math.to_degrees(two_theta_rad)
+      // d(2θ)/dr = D / (D² + r²): radial angular extent of this pixel
+      const pixel_width = math.to_degrees(
+        (pixel_size * distance) / (distance ** 2 + radius_sq),
+      )
+      const azimuth = (math.to_degrees(Math.atan2(y_mm, x_mm)) + 360) % 360
+      const az_idx = Math.min(n_azimuth - 1, Math.floor((azimuth / 360) * n_azimuth))
+      let value = 0
+      for (const ring of ring_params) {
+        const fwhm = Math.hypot(ring.fwhm, pixel_width)
+        // 20 FWHM window as in compute_broadened_pattern
+        if (Math.abs(two_theta - ring.two_theta) > 20 * fwhm) continue
+        const profile = pseudo_voigt(two_theta, ring.two_theta, fwhm, shape_factor)
+        value += ring.weight * profile * ring.azimuthal[az_idx]
+      }
+      data[row * width + col] = value * Math.cos(two_theta_rad) ** 3
+    }
+  }
+  return { width, height, data }
+}
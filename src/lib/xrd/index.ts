@@ -4,7 +4,10 @@ import type { RadiationKey } from './calc-xrd'
 
 export * from './broadening'
 export * from './calc-xrd'
+export * from './debye-scherrer'
 export * from './parse'
+export * from './refine'
+export * from './similarity'
 export { default as XrdPlot } from './XrdPlot.svelte'
 
 export type Hkl = Vec3
@@ -23,6 +26,9 @@ export type XrdOptions = {
   wavelength?: number | RadiationKey
   symprec?: number
   debye_waller_factors?: CompositionType
+  // Use per-site anisotropic U tensors from site.properties.u_cart (e.g. from
+  // compute_adp_tensors) as Debye-Waller factors when present (default false)
+  site_adps?: boolean
   scaled?: boolean
   // When null, treat as unbounded up to 2/λ (Bragg maximum); when omitted, default [0, 180]
   two_theta_range?: Vec2 | null
@@ -0,0 +1,315 @@
+import * as math from '$lib/math'
+import type { Crystal, LatticeParams } from '$lib/structure/index'
+import type { LatticeSystem } from '$lib/symmetry/spacegroups'
+import type { BroadeningParams } from './broadening'
+import { caglioti_fwhm, DEFAULT_BROADENING, pseudo_voigt } from './broadening'
+import type { RadiationKey } from './calc-xrd'
+import { compute_xrd_pattern } from './calc-xrd'
+import type { XrdPattern } from './index'
+
+type LatticeKey = keyof LatticeParams
+
+export type LatticeRefinementOptions = {
+  wavelength?: number | RadiationKey
+  broadening?: BroadeningParams // fixed peak-shape parameters (default DEFAULT_BROADENING)
+  // Constrains which lattice parameters are free. Inferred from the starting cell metric
+  // when omitted (e.g. a = b = c with 90° angles is treated as cubic).
+  lattice_system?: LatticeSystem
+  refine_zero_shift?: boolean // default true
+  zero_shift?: number // starting 2θ zero offset in degrees (default 0)
+  max_iter?: number // Levenberg-Marquardt iterations (default 50)
+  tol?: number // relative chi² change treated as converged (default 1e-8)
+}
+
+export type LatticeRefinementResult = {
+  structure: Crystal // input structure with refined lattice (fractional coords unchanged)
+  params: LatticeParams & { zero_shift: number }
+  // One standard deviation for each free parameter (and those constrained to it)
+  uncertainties: Partial<Record<LatticeKey | `zero_shift`, number>>
+  scale: number // fitted intensity scale factor
+  background: number // fitted constant background
+  r_wp: number // weighted profile R-factor
+  chi_sq: number // reduced chi²
+  n_iter: number
+  converged: boolean
+  calculated: XrdPattern // fitted profile on the observed 2θ grid
+}
+
+const LENGTH_KEYS: readonly LatticeKey[] = [`a`, `b`, `c`]
+
+// Free parameters per lattice system. Dependent ones are expressed via free ones below.
+const FREE_PARAMS: Record<LatticeSystem, LatticeKey[]> = {
+  cubic: [`a`],
+  tetragonal: [`a`, `c`],
+  hexagonal: [`a`, `c`],
+  rhombohedral: [`a`, `alpha`],
+  orthorhombic: [`a`, `b`, `c`],
+  monoclinic: [`a`, `b`, `c`, `beta`],
+  triclinic: [`a`, `b`, `c`, `alpha`, `beta`, `gamma`],
+}
+
+// Guess the lattice system from cell lengths (relative tol) and angles (degrees)
+export function infer_lattice_system(
+  { a, b, c, alpha, beta, gamma }: LatticeParams,
+  length_tol = 1e-3,
+  angle_tol = 0.05,
+): LatticeSystem {
+  const same = (len_1: number, len_2: number) =>
+    Math.abs(len_1 - len_2) <= length_tol * Math.max(len_1, len_2)
+  const is_angle = (angle: number, target: number) => Math.abs(angle - target) <= angle_tol
+  const n_right = [alpha, beta, gamma].filter((angle) => is_angle(angle, 90)).length
+
+  if (n_right === 3) {
+    if (same(a, b) && same(b, c)) return `cubic`
+    return same(a, b) ? `tetragonal` : `orthorhombic`
+  }
+  if (is_angle(alpha, 90) && is_angle(beta, 90) && is_angle(gamma, 120) && same(a, b)) {
+    return `hexagonal`
+  }
+  if (same(a, b) && same(b, c) && is_angle(alpha, beta) && is_angle(beta, gamma)) {
+    return `rhombohedral`
+  }
+  return n_right === 2 ? `monoclinic` : `triclinic`
+}
+
+// Map the free-parameter vector back onto a full set of lattice parameters
+function expand_params(
+  system: LatticeSystem,
+  free_keys: LatticeKey[],
+  values: number[],
+  start: LatticeParams,
+): LatticeParams {
+  const params = { ...start }
+  for (const [idx, key] of free_keys.entries()) params[key] = values[idx]
+  if (system === `cubic` || system === `rhombohedral`) params.b = params.c = params.a
+  if (system === `tetragonal` || system === `hexagonal`) params.b = params.a
+  if (system === `rhombohedral`) params.beta = params.gamma = params.alpha
+  return params
+}
+
+// Which free parameter each lattice parameter is tied to (for uncertainty reporting)
+function tied_to(
+  system: LatticeSystem,
+  free_keys: LatticeKey[],
+  key: LatticeKey,
+): LatticeKey | null {
+  if (free_keys.includes(key)) return key
+  if ((key === `b` || key === `c`) && (system === `cubic` || system === `rhombohedral`)) {
+    return `a`
+  }
+  if (key === `b` && (system === `tetragonal` || system === `hexagonal`)) return `a`
+  if ((key === `beta` || key === `gamma`) && system === `rhombohedral`) return `alpha`
+  return null
+}
+
+const with_lattice = (structure: Crystal, params: LatticeParams): Crystal => {
+  const { a, b, c, alpha, beta, gamma } = params
+  const matrix = math.cell_to_lattice_matrix(a, b, c, alpha, beta, gamma)
+  const frac_to_cart = math.create_frac_to_cart(matrix)
+  return {
+    ...structure,
+    lattice: { ...structure.lattice, matrix, ...math.calc_lattice_params(matrix) },
+    sites: structure.sites.map((site) => ({ ...site, xyz: frac_to_cart(site.abc) })),
+  }
+}
+
+// Least-squares refinement of lattice parameters and 2θ zero shift against an observed
+// powder pattern. The model is the simulated stick pattern convolved with fixed
+// pseudo-Voigt profiles, plus a scale factor and constant background that are solved
+// linearly at each step. Non-linear parameters are optimized with Levenberg-Marquardt
+// on Poisson-weighted residuals; uncertainties come from the covariance matrix
+// (JᵀJ)⁻¹ scaled by the reduced chi². Not a full Rietveld refinement: atomic positions,
+// thermal parameters and peak shapes stay fixed, which suffices for tracking lattice
+// parameters across in-situ datasets. Note the refined cell is rebuilt from its
+// parameters in the standard crystallographic orientation.
+export function refine_lattice_parameters(
+  structure: Crystal,
+  observed: Pick<XrdPattern, `x` | `y`>,
+  options: LatticeRefinementOptions = {},
+): LatticeRefinementResult {
+  const {
+    wavelength,
+    broadening = DEFAULT_BROADENING,
+    refine_zero_shift = true,
+    max_iter = 50,
+    tol = 1e-8,
+  } = options
+  const { x: two_thetas, y: intensities } = observed
+  if (two_thetas.length !== intensities.length) {
+    throw new Error(`Observed pattern x and y must have equal length`)
+  }
+
+  const start: LatticeParams = {
+    a: structure.lattice.a,
+    b: structure.lattice.b,
+    c: structure.lattice.c,
+    alpha: structure.lattice.alpha,
+    beta: structure.lattice.beta,
+    gamma: structure.lattice.gamma,
+  }
+  const system = options.lattice_system ?? infer_lattice_system(start)
+  let free_keys = FREE_PARAMS[system]
+  if (system === `monoclinic`) {
+    // free angle is whichever one deviates from 90° (β in the standard setting)
+    const angle_keys = [`alpha`, `beta`, `gamma`] as const
+    const unique = angle_keys.find((key) => Math.abs(start[key] - 90) > 0.05) ?? `beta`
+    free_keys = [`a`, `b`, `c`, unique]
+  }
+  const n_free = free_keys.length + (refine_zero_shift ? 1 : 0)
+  const n_points = two_thetas.length
+  if (n_points <= n_free + 2) {
+    throw new Error(
+      `Need more than ${n_free + 2} observed points to refine ${n_free} parameters`,
+    )
+  }
+
+  let [min_tt, max_tt] = [Infinity, -Infinity]
+  for (const two_theta of two_thetas) {
+    min_tt = Math.min(min_tt, two_theta)
+    max_tt = Math.max(max_tt, two_theta)
+  }
+  const weights = intensities.map((val) => 1 / Math.max(Math.abs(val), 1))
+
+  // Unscaled model profile on the observed grid for a parameter vector
+  const model_profile = (values: number[]): number[] => {
+    const lattice = expand_params(system, free_keys, values, start)
+    const zero = refine_zero_shift ? values[free_keys.length] : (options.zero_shift ?? 0)
+    const margin = 5 + Math.abs(zero)
+    const sticks = compute_xrd_pattern(with_lattice(structure, lattice), {
+      wavelength,
+      two_theta_range: [Math.max(0, min_tt - margin), Math.min(180, max_tt + margin)],
+      scaled: false,
+    })
+    const profile = Array<number>(n_points).fill(0)
+    sticks.x.forEach((two_theta, peak_idx) => {
+      const center = two_theta + zero
+      const fwhm = caglioti_fwhm(two_theta, broadening.U, broadening.V, broadening.W)
+      for (let idx = 0; idx < n_points; idx++) {
+        if (Math.abs(two_thetas[idx] - center) > 20 * fwhm) continue
+        const shape = pseudo_voigt(two_thetas[idx], center, fwhm, broadening.shape_factor)
+        profile[idx] += sticks.y[peak_idx] * shape
+      }
+    })
+    return profile
+  }
+
+  // Solve weighted linear least squares for scale and background given a profile
+  const fit_linear = (profile: number[]) => {
+    let [s_ww, s_wp, s_wpp, s_wy, s_wpy] = [0, 0, 0, 0, 0]
+    for (let idx = 0; idx < n_points; idx++) {
+      const [wt, prof, obs] = [weights[idx], profile[idx], intensities[idx]]
+      s_ww += wt
+      s_wp += wt * prof
+      s_wpp += wt * prof * prof
+      s_wy += wt * obs
+      s_wpy += wt * prof * obs
+    }
+    const solution = math.solve_linear_system(
+      [
+        [s_wpp, s_wp],
+        [s_wp, s_ww],
+      ],
+      [s_wpy, s_wy],
+    )
+    const [scale, background] = solution ?? [0, s_wy / s_ww]
+    const residuals = profile.map(
+      (prof, idx) => Math.sqrt(weights[idx]) * (intensities[idx] - scale * prof - background),
+    )
+    const chi_sq = residuals.reduce((sum, res) => sum + res * res, 0)
+    return { scale, background, residuals, chi_sq }
+  }
+
+  const evaluate = (values: number[]) => fit_linear(model_profile(values))
+
+  let values = [
+    ...free_keys.map((key) => start[key]),
+    ...(refine_zero_shift ? [options.zero_shift ?? 0] : []),
+  ]
+  const steps = [
+    ...free_keys.map((key) => (LENGTH_KEYS.includes(key) ? 1e-5 * start[key] : 1e-4)),
+    ...(refine_zero_shift ? [1e-4] : []),
+  ]
+  const jacobian = (vals: number[], base: number[]): number[][] =>
+    vals.map((val, param_idx) => {
+      const shifted = [...vals]
+      shifted[param_idx] = val + steps[param_idx]
+      const { residuals } = evaluate(shifted)
+      return residuals.map((res, idx) => (res - base[idx]) / steps[param_idx])
+    }) // rows = parameters, i.e. Jᵀ
+
+  let current = evaluate(values)
+  let lambda = 1e-3
+  let converged = false
+  let n_iter = 0
+  let jac_t = jacobian(values, current.residuals)
+
+  for (; n_iter < max_iter && !converged; n_iter++) {
+    const jtj = jac_t.map((row_i) => jac_t.map((row_j) => math.dot(row_i, row_j)))
+    const jtr = jac_t.map((row) => math.dot(row, current.residuals))
+    let improved = false
+    while (lambda < 1e12) {
+      const damped = jtj.map((row, idx) =>
+        row.map((val, col) => (col === idx ? val * (1 + lambda) + 1e-30 : val)),
+      )
+      const delta = math.solve_linear_system(damped, jtr.map((val) => -val))
+      if (!delta) {
+        lambda *= 10
+        continue
+      }
+      const trial_values = values.map((val, idx) => val + delta[idx])
+      const trial = evaluate(trial_values)
+      if (trial.chi_sq < current.chi_sq) {
+        const rel_change = (current.chi_sq - trial.chi_sq) / Math.max(current.chi_sq, 1e-300)
+        values = trial_values
+        current = trial
+        lambda = Math.max(lambda / 10, 1e-12)
+        improved = true
+        converged = rel_change < tol
+        break
+      }
+      lambda *= 10
+    }
+    if (!improved) {
+      converged = true // no downhill step exists: at a (local) minimum
+      break
+    }
+    jac_t = jacobian(values, current.residuals)
+  }
+
+  // Covariance = (JᵀJ)⁻¹ · reduced chi²
+  const dof = n_points - n_free - 2
+  const chi_sq = current.chi_sq / dof
+  const jtj = jac_t.map((row_i) => jac_t.map((row_j) => math.dot(row_i, row_j)))
+  const variances = values.map((_, param_idx) => {
+    const unit = values.map((_val, idx) => (idx === param_idx ? 1 : 0))
+    const column = math.solve_linear_system(jtj, unit)
+    return column ? column[param_idx] * chi_sq : NaN
+  })
+
+  const lattice = expand_params(system, free_keys, values, start)
+  const zero_shift = refine_zero_shift ? values[free_keys.length] : (options.zero_shift ?? 0)
+  const uncertainties: LatticeRefinementResult[`uncertainties`] = {}
+  for (const key of Object.keys(lattice) as LatticeKey[]) {
+    const free_key = tied_to(system, free_keys, key)
+    if (free_key) uncertainties[key] = Math.sqrt(variances[free_keys.indexOf(free_key)])
+  }
+  if (refine_zero_shift) uncertainties.zero_shift = Math.sqrt(variances[free_keys.length])
+
+  const sum_w_obs_sq = intensities.reduce((sum, obs, idx) => sum + weights[idx] * obs ** 2, 0)
+  const profile = model_profile(values)
+  return {
+    structure: with_lattice(structure, lattice),
+    params: { ...lattice, zero_shift },
+    uncertainties,
+    scale: current.scale,
+    background: current.background,
+    r_wp: Math.sqrt(current.chi_sq / sum_w_obs_sq),
+    chi_sq,
+    n_iter,
+    converged,
+    calculated: {
+      x: [...two_thetas],
+      y: profile.map((prof) => current.scale * prof + current.background),
+    },
+  }
+}
@@ -0,0 +1,99 @@
+// XRD fingerprint similarity: weighted cross-correlation of broadened powder patterns
+// (de Gelder et al., J. Comput. Chem. 22, 273, 2001). Tolerates small peak shifts from
+// strain or DFT lattice errors and needs no site matching, so it works across
+// prototypes as a cheap screening metric.
+import type { Vec2 } from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import type { BroadeningParams } from './broadening'
+import { compute_broadened_pattern, DEFAULT_BROADENING } from './broadening'
+import { compute_xrd_pattern } from './calc-xrd'
+import type { XrdOptions, XrdPattern } from './index'
+
+export type XrdSimilarityOptions = XrdOptions & {
+  broadening?: BroadeningParams // peak profiles (default DEFAULT_BROADENING)
+  range?: Vec2 // 2θ grid range in degrees (default [10, 90])
+  step_size?: number // 2θ grid spacing in degrees (default 0.05)
+  // half-width in degrees of the triangular weight over relative shifts; peaks this far
+  // apart still correlate partially (default 1, 0 for the plain normalized overlap)
+  shift_width?: number
+}
+
+// Broadened profile on the common 2θ grid used for comparisons
+export function xrd_fingerprint(
+  pattern: XrdPattern,
+  options: XrdSimilarityOptions = {},
+): number[] {
+  const { broadening = DEFAULT_BROADENING, range = [10, 90], step_size = 0.05 } = options
+  return compute_broadened_pattern(pattern, broadening, range, step_size).y
+}
+
+// Σ_k w(k) Σ_i f_i g_{i+k} with triangular weights w(k) = 1 − |k| / (n_shift + 1)
+function weighted_correlation(prof_1: number[], prof_2: number[], n_shift: number): number {
+  let total = 0
+  for (let shift = -n_shift; shift <= n_shift; shift++) {
+    const weight = 1 - Math.abs(shift) / (n_shift + 1)
+    let corr = 0
+    const [start, end] = [Math.max(0, -shift), Math.min(prof_1.length, prof_2.length - shift)]
+    for (let idx = start; idx < end; idx++) corr += prof_1[idx] * prof_2[idx + shift]
+    total += weight * corr
+  }
+  return total
+}
+
+// Similarity in [0, 1] of two broadened profiles on the same grid (1 = identical shape,
+// 0 if either profile is empty)
+export function fingerprint_similarity(
+  prof_1: number[],
+  prof_2: number[],
+  options: Pick<XrdSimilarityOptions, `shift_width` | `step_size`> = {},
+): number {
+  const { shift_width = 1, step_size = 0.05 } = options
+  if (prof_1.length !== prof_2.length) {
+    throw new Error(`Fingerprints differ in length: ${prof_1.length} vs ${prof_2.length}`)
+  }
+  const n_shift = Math.max(0, Math.round(shift_width / step_size))
+  const norm_1 = weighted_correlation(prof_1, prof_1, n_shift)
+  const norm_2 = weighted_correlation(prof_2, prof_2, n_shift)
+  if (!(norm_1 > 0 && norm_2 > 0)) return 0
+  return weighted_correlation(prof_1, prof_2, n_shift) / Math.sqrt(norm_1 * norm_2)
+}
+
+// Similarity of two discrete (stick) patterns, e.g. from compute_xrd_pattern or measured
+// peak lists
+export const xrd_pattern_similarity = (
+  pattern_1: XrdPattern,
+  pattern_2: XrdPattern,
+  options: XrdSimilarityOptions = {},
+): number =>
+  fingerprint_similarity(
+    xrd_fingerprint(pattern_1, options),
+    xrd_fingerprint(pattern_2, options),
+    options,
+  )
+
+// Pairwise similarity matrix of structures from their simulated patterns (each pattern
+// is computed once). Use 1 − S as a distance for clustering.
+export function xrd_similarity_matrix(
+  structures: readonly Crystal[],
+  options: XrdSimilarityOptions = {},
+): number[][] {
+  const prints = structures.map((structure) =>
+    xrd_fingerprint(compute_xrd_pattern(structure, options), options),
+  )
+  const matrix = prints.map(() => prints.map(() => 1))
+  prints.forEach((print_1, idx) => {
+    for (let jdx = idx + 1; jdx < prints.length; jdx++) {
+      const similarity = fingerprint_similarity(print_1, prints[jdx], options)
+      matrix[idx][jdx] = similarity
+      matrix[jdx][idx] = similarity
+    }
+  })
+  return matrix
+}
+
+// Similarity of two structures from their simulated powder patterns
+export const xrd_structure_similarity = (
+  struct_1: Crystal,
+  struct_2: Crystal,
+  options: XrdSimilarityOptions = {},
+): number => xrd_similarity_matrix([struct_1, struct_2], options)[0][1]
@@ -0,0 +1,67 @@
+import {
+  enumerate_compositions,
+  guess_oxidation_states,
+  is_charge_balanced,
+} from '$lib/composition'
+import { describe, expect, test } from 'vitest'
+
+describe(`enumerate_compositions`, () => {
+  test(`reduced binary stoichiometries ordered by size`, () => {
+    expect(enumerate_compositions([`Li`, `O`], 4)).toEqual([
+      { Li: 1, O: 1 },
+      { Li: 1, O: 2 },
+      { Li: 2, O: 1 },
+      { Li: 1, O: 3 },
+      { Li: 3, O: 1 }, // Li2O2 is a multiple of LiO
+    ])
+    expect(enumerate_compositions([`Li`, `Fe`, `O`], 2)).toEqual([])
+    expect(enumerate_compositions([`Li`, `Fe`, `O`], 3)).toEqual([{ Li: 1, Fe: 1, O: 1 }])
+  })
+
+  test(`charge balancing with ICSD oxidation states allows mixed valence`, () => {
+    expect(enumerate_compositions([`Li`, `O`], 4, true)).toEqual([{ Li: 2, O: 1 }])
+    // FeO, Fe2O3 and the mixed-valence spinel Fe3O4
+    expect(enumerate_compositions([`Fe`, `O`], 7, true)).toEqual([
+      { Fe: 1, O: 1 },
+      { Fe: 2, O: 3 },
+      { Fe: 3, O: 4 },
+    ])
+    expect(enumerate_compositions([`Li`, `Fe`, `P`, `O`], 7, true)).toContainEqual({
+      Li: 1,
+      Fe: 1,
+      P: 1,
+      O: 4,
+    })
+    // noble gases have no ICSD oxidation states
+    expect(enumerate_compositions([`Xe`, `F`], 5, true)).toEqual([])
+    const oxidation_states = { Xe: [2, 4, 6] }
+    expect(enumerate_compositions([`Xe`, `F`], 5, true, { oxidation_states })).toEqual([
+      { Xe: 1, F: 2 },
+      { Xe: 1, F: 4 },
+    ])
+  })
+
+  test(`is_charge_balanced`, () => {
+    expect(is_charge_balanced({ Na: 1, Cl: 1 })).toBe(true)
+    expect(is_charge_balanced({ Na: 2, Cl: 1 })).toBe(false)
+    expect(() => is_charge_balanced({ Na: 0.5, Cl: 0.5 })).toThrow(`integer amounts`)
+  })
+
+  test(`guess_oxidation_states picks one neutral state per element`, () => {
+    expect(guess_oxidation_states({ Li: 1, Fe: 1, P: 1, O: 4 })).toEqual({
+      Li: 1,
+      Fe: 2,
+      P: 5,
+      O: -2,
+    })
+    expect(guess_oxidation_states({ Fe: 2, O: 3 })).toEqual({ Fe: 3, O: -2 })
+    expect(guess_oxidation_states({ Fe: 3, O: 4 })).toBeNull() // needs mixed valence
+    expect(guess_oxidation_states({ Xe: 1, F: 2 })).toBeNull()
+  })
+
+  test(`invalid input`, () => {
+    expect(() => enumerate_compositions([], 3)).toThrow(`at least one element`)
+    expect(() => enumerate_compositions([`Fe`, `Fe`], 3)).toThrow(`Duplicate elements`)
+    expect(() => enumerate_compositions([`Fe`, `O`], 0)).toThrow(`max_atoms`)
+  })
+})
@@ -1,5 +1,10 @@
 // Tests for isosurface volumetric file parsers (CHGCAR, .cube)
-import { parse_chgcar, parse_cube, parse_volumetric_file } from '$lib/isosurface/parse'
+import {
+  parse_chgcar,
+  parse_cube,
+  parse_volumetric_file,
+  vasp_volumetric_kind,
+} from '$lib/isosurface/parse'
 import type { Vec3 } from '$lib/math'
 import { describe, expect, test } from 'vitest'
 
@@ -189,6 +194,69 @@ describe(`parse_chgcar`, () => {
     const result = parse_chgcar(content)
     expect(result).not.toBeNull()
     expect(result?.volumes[0].grid_dims).toEqual([2, 2, 2])
+    expect(result?.augmentation).toEqual([[[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8]]])
+  })
+
+  test(`reads augmentation occupancies per atom and spin block`, () => {
+    const content = make_chgcar({
+      augmentation: [
+        `augmentation occupancies   1   3`,
+        `  0.1  0.2`,
+        `  0.3`,
+        `augmentation occupancies   2   2`,
+        `  0.4  0.5`,
+        `  0.0  0.0`, // per-atom values VASP writes before the magnetization block
+      ].join(`\n`),
+      second_volume: [
+        `   2   2   2`,
+        `  0.1  0.2  0.3  0.4  0.5  0.6  0.7  0.8`,
+        `augmentation occupancies   2   1`,
+        `  -0.4`,
+      ].join(`\n`),
+    })
+    const result = parse_chgcar(content)
+    expect(result?.volumes.map(({ label }) => label)).toEqual([
+      `charge density`,
+      `magnetization density`,
+    ])
+    expect(result?.augmentation).toEqual([
+      [
+        [0.1, 0.2, 0.3],
+        [0.4, 0.5],
+      ],
+      [[], [-0.4]],
+    ])
+    // files without augmentation sections don't get the field
+    expect(parse_chgcar(make_chgcar())?.augmentation).toBeUndefined()
+  })
+
+  test(`noncollinear CHGCAR parses total and three magnetization components`, () => {
+    const block = `   2   2   2\n  0.1  0.2  0.3  0.4  0.5  0.6  0.7  0.8`
+    const result = parse_chgcar(
+      make_chgcar({ second_volume: [block, ``, block, ``, block].join(`\n`) }),
+    )
+    expect(result?.volumes.map(({ label }) => label)).toEqual([
+      `charge density`,
+      `magnetization density x`,
+      `magnetization density y`,
+      `magnetization density z`,
+    ])
+  })
+
+  test(`LOCPOT and ELFCAR values are not divided by the cell volume`, () => {
+    for (const [filename, label] of [
+      [`LOCPOT`, `local potential`],
+      [`ELFCAR.gz`, `ELF`],
+    ]) {
+      const result = parse_volumetric_file(make_chgcar(), filename)
+      expect(result?.volumes[0].label).toBe(label)
+      expect(result?.volumes[0].grid[1][1][1]).toBe(8)
+    }
+    const two_blocks = make_chgcar({ second_volume: `   2 2 2\n 1 1 1 1 1 1 1 1` })
+    const locpot = parse_chgcar(two_blocks, `locpot`)
+    expect(locpot?.volumes[1].label).toBe(`local potential (block 2)`)
+    expect(vasp_volumetric_kind(`run_1/LOCPOT`)).toBe(`locpot`)
+    expect(vasp_volumetric_kind(`PARCHG.0001`)).toBe(`chgcar`)
   })
 
   test(`wraps fractional coords to [0, 1)`, () => {
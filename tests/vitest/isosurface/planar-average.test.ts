@@ -0,0 +1,130 @@
+import {
+  band_alignment,
+  find_vacuum_region,
+  macroscopic_average,
+  planar_average,
+  slab_alignment,
+} from '$lib/isosurface'
+import type { Matrix3x3 } from '$lib/math'
+import { describe, expect, test } from 'vitest'
+import { make_grid, make_volume } from '../setup'
+
+const lattice: Matrix3x3 = [
+  [4, 0, 0],
+  [0, 4, 0],
+  [0, 0, 30],
+]
+// slab between z = 5 and 20 Å with bulk-like oscillations of period 2.5 Å around base,
+// flat vacuum elsewhere, and an in-plane modulation that the planar average removes
+const slab_potential = (base: number, vacuum: number) =>
+  make_volume(
+    make_grid(2, 3, 300, (ix, _iy, iz) => {
+      const z_val = iz * 0.1
+      if (z_val < 5 || z_val >= 20) return vacuum
+      return base + 3 * Math.cos((2 * Math.PI * (z_val - 5)) / 2.5) + (ix === 0 ? 0.5 : -0.5)
+    }),
+    { lattice },
+  )
+
+describe(`planar and macroscopic averages`, () => {
+  test(`planar average removes in-plane variation`, () => {
+    const planar = planar_average(slab_potential(-10, 4))
+    expect(planar.period).toBeCloseTo(30, 12)
+    expect(planar.values).toHaveLength(300)
+    expect(planar.positions[10]).toBeCloseTo(1, 12)
+    expect(planar.values[0]).toBeCloseTo(4, 12)
+    expect(planar.values[50]).toBeCloseTo(-7, 12)
+    // along a the in-plane average is over b and c
+    const along_a = planar_average(slab_potential(-10, 4), 0)
+    expect(along_a.values).toHaveLength(2)
+    expect(along_a.period).toBeCloseTo(4, 12)
+  })
+
+  test(`planar positions are heights along the normal of sheared cells`, () => {
+    const sheared: Matrix3x3 = [
+      [4, 0, 0],
+      [2, 4, 0],
+      [1, 1, 30],
+    ]
+    const volume = make_volume(make_grid(2, 2, 10, () => 1), { lattice: sheared })
+    const planar = planar_average(volume)
+    expect(planar.period).toBeCloseTo(30, 12)
+    expect(planar.positions[1]).toBeCloseTo(3, 12)
+  })
+
+  test(`macroscopic average over the bulk period flattens oscillations`, () => {
+    const planar = planar_average(slab_potential(-10, 4))
+    const macroscopic = macroscopic_average(planar, 2.5)
+    for (const idx of [80, 125, 170]) expect(macroscopic[idx]).toBeCloseTo(-10, 10)
+    // two windows are applied in turn, periodic so the vacuum far from the slab stays flat
+    const double = macroscopic_average(planar, [2.5, 1.5])
+    expect(double[125]).toBeCloseTo(-10, 10)
+    expect(double[250]).toBeCloseTo(4, 10)
+    expect(() => macroscopic_average(planar, 0)).toThrow(
+      `Averaging window must be > 0 and ≤ the cell length, got 0 Å`,
+    )
+  })
+
+  test(`vacuum plateau wraps around the cell boundary`, () => {
+    const vacuum = find_vacuum_region(planar_average(slab_potential(-10, 4)))
+    expect(vacuum.level).toBeCloseTo(4, 12)
+    expect(vacuum).toMatchObject({ start: 200, length: 150 })
+    expect(vacuum.center).toBeCloseTo(27.45, 10)
+    const no_vacuum = make_volume(
+      make_grid(1, 1, 300, (_ix, _iy, iz) => Math.cos((2 * Math.PI * iz) / 25)),
+      { lattice },
+    )
+    expect(() => find_vacuum_region(planar_average(no_vacuum))).toThrow(
+      `No flat vacuum region`,
+    )
+  })
+})
+
+describe(`band alignment`, () => {
+  test(`ionization potential and electron affinity of a slab`, () => {
+    const result = slab_alignment({
+      potential: slab_potential(-10, 4),
+      bulk_vbm: 5,
+      bulk_potential: 1,
+      window: 2.5,
+      band_gap: 2,
+    })
+    // E_VBM = 5 − 1 + (−10) − 4
+    expect(result.vacuum_level).toBeCloseTo(4, 10)
+    expect(result.slab_potential).toBeCloseTo(-10, 10)
+    expect(result.ionization_potential).toBeCloseTo(10, 10)
+    expect(result.electron_affinity).toBeCloseTo(8, 10)
+    const no_gap = slab_alignment({
+      potential: slab_potential(-10, 4),
+      bulk_vbm: 5,
+      bulk_potential: 1,
+      window: 2.5,
+    })
+    expect(no_gap.cbm).toBeNull()
+    expect(no_gap.electron_affinity).toBeNull()
+  })
+
+  test(`band offsets between two materials`, () => {
+    // bulk potential given as a volume, averaged over the cell to 0.5
+    const bulk_2 = make_volume(make_grid(2, 2, 2, (ix) => ix))
+    const result = band_alignment(
+      {
+        potential: slab_potential(-10, 4),
+        bulk_vbm: 5,
+        bulk_potential: 1,
+        window: 2.5,
+        band_gap: 2,
+      },
+      {
+        potential: slab_potential(-8, 3),
+        bulk_vbm: 4,
+        bulk_potential: bulk_2,
+        window: 2.5,
+        band_gap: 1,
+      },
+    )
+    expect(result.slabs[1].vbm).toBeCloseTo(-7.5, 10)
+    expect(result.valence_band_offset).toBeCloseTo(2.5, 10)
+    expect(result.conduction_band_offset).toBeCloseTo(1.5, 10)
+  })
+})
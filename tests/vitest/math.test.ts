@@ -1227,6 +1227,35 @@ describe(`lerp_vec3`, () => {
   )
 })
 
+describe(`counter-based random numbers`, () => {
+  test(`random_at gives the mulberry32 stream at any counter`, () => {
+    for (const seed of [0, 42, -5, 2 ** 32 - 1]) {
+      const random = math.mulberry32(seed)
+      const stream = Array.from({ length: 1000 }, () => random())
+      stream.forEach((val, counter) => expect(math.random_at(seed, counter)).toBe(val))
+      expect(stream.every((val) => val >= 0 && val < 1)).toBe(true)
+    }
+    expect(math.random_at(0, 0)).toBe(math.random_at(2 ** 32, 0))
+  })
+
+  test(`derive_seed gives distinct reproducible substreams`, () => {
+    const seeds = Array.from({ length: 1000 }, (_, key) => math.derive_seed(7, key))
+    expect(new Set(seeds).size).toBe(1000)
+    expect(math.derive_seed(7, 3)).toBe(seeds[3])
+    expect(math.derive_seed(7, 1, 2)).not.toBe(math.derive_seed(7, 2, 1))
+    expect(math.derive_seed(7)).toBe(7)
+  })
+
+  test(`random_normal has zero mean and unit variance`, () => {
+    const random = math.mulberry32(1)
+    const samples = Array.from({ length: 20_000 }, () => math.random_normal(random))
+    const mean = samples.reduce((sum, val) => sum + val, 0) / samples.length
+    const variance = samples.reduce((sum, val) => sum + (val - mean) ** 2, 0) / samples.length
+    expect(Math.abs(mean)).toBeLessThan(0.03)
+    expect(Math.abs(variance - 1)).toBeLessThan(0.05)
+  })
+})
+
 describe(`normalize_vec`, () => {
   const inv_sqrt3 = 1 / Math.sqrt(3)
   // oxfmt-ignore
@@ -0,0 +1,81 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import {
+  classify_all_atoms,
+  classify_all_atoms_frames,
+  CNA_STRUCTURE_TYPES,
+} from '$lib/order-params'
+import type { Crystal } from '$lib/structure'
+import { make_supercell, rattle_structure } from '$lib/structure'
+import { make_site } from '$lib/structure/site'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const a_hcp = 2.5
+const hcp_matrix: Matrix3x3 = [
+  [a_hcp, 0, 0],
+  [-a_hcp / 2, (a_hcp * Math.sqrt(3)) / 2, 0],
+  [0, 0, a_hcp * Math.sqrt(8 / 3)],
+]
+const crystals: Record<string, Crystal> = {
+  fcc: make_crystal(3.6, [
+    [`Cu`, [0, 0, 0]],
+    [`Cu`, [0.5, 0.5, 0]],
+    [`Cu`, [0.5, 0, 0.5]],
+    [`Cu`, [0, 0.5, 0.5]],
+  ]),
+  bcc: make_crystal(2.87, [
+    [`Fe`, [0, 0, 0]],
+    [`Fe`, [0.5, 0.5, 0.5]],
+  ]),
+  hcp: make_crystal(hcp_matrix, [
+    [`Mg`, [1 / 3, 2 / 3, 0.25]],
+    [`Mg`, [2 / 3, 1 / 3, 0.75]],
+  ]),
+  other: make_crystal(3, [[`Po`, [0, 0, 0]]]),
+}
+
+const type_names = (types: Uint8Array) => [...types].map((type) => CNA_STRUCTURE_TYPES[type])
+
+describe(`adaptive common neighbor analysis`, () => {
+  test.each(Object.entries(crystals))(`labels %s`, (name, crystal) => {
+    expect(new Set(type_names(classify_all_atoms(crystal)))).toEqual(new Set([name]))
+    // the per-atom cutoff adapts to thermal noise
+    const rattled = rattle_structure(make_supercell(crystal, [4, 4, 4]), 0.05, { seed: 1 })
+    expect(new Set(type_names(classify_all_atoms(rattled)))).toEqual(new Set([name]))
+  })
+
+  test(`icosahedral cluster center and its surface`, () => {
+    const golden = (1 + Math.sqrt(5)) / 2
+    const vertices = [-1, 1].flatMap((sign_1) =>
+      [-golden, golden].flatMap((sign_2) => [
+        [0, sign_1, sign_2],
+        [sign_1, sign_2, 0],
+        [sign_2, 0, sign_1],
+      ]),
+    )
+    const sites = [[0, 0, 0], ...vertices].map((xyz, idx) =>
+      make_site(`Cu`, [0, 0, 0], xyz as Vec3, `Cu${idx + 1}`),
+    )
+    const types = type_names(classify_all_atoms({ sites }))
+    expect(types).toEqual([`ico`, ...Array(12).fill(`other`)])
+  })
+
+  test(`trajectory frames match per-frame calls`, () => {
+    const supercell = make_supercell(crystals.fcc, [3, 3, 3])
+    const trajectory: TrajectoryType = {
+      frames: [0, 1, 2].map((step) => ({
+        structure: rattle_structure(supercell, 0.05 * step, { seed: step }),
+        step,
+        metadata: {},
+      })),
+    }
+    const batched = classify_all_atoms_frames(trajectory, { start_frame: 1 })
+    expect(batched).toEqual(
+      trajectory.frames.slice(1).map(({ structure }) => classify_all_atoms(structure)),
+    )
+    expect(() => classify_all_atoms_frames(trajectory, { stride: 1.5 })).toThrow(
+      `stride must be a positive integer`,
+    )
+  })
+})
@@ -0,0 +1,208 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import {
+  compute_orientations,
+  lattice_rotations,
+  misorientation,
+  polar_rotation,
+  rotation_angle,
+  segment_grains,
+} from '$lib/order-params'
+import type { Molecule } from '$lib/structure'
+import { make_site } from '$lib/structure/site'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const a_fcc = 3.615
+const fcc_basis: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+const fcc_bonds = (
+  [
+    [1, 1, 0],
+    [1, -1, 0],
+    [-1, 1, 0],
+    [-1, -1, 0],
+    [1, 0, 1],
+    [1, 0, -1],
+    [-1, 0, 1],
+    [-1, 0, -1],
+    [0, 1, 1],
+    [0, 1, -1],
+    [0, -1, 1],
+    [0, -1, -1],
+  ] as Vec3[]
+).map((vec) => math.scale(vec, a_fcc / 2))
+
+const rot_z = (degrees: number): Matrix3x3 => {
+  const [cos, sin] = [Math.cos(math.to_radians(degrees)), Math.sin(math.to_radians(degrees))]
+  return [
+    [cos, -sin, 0],
+    [sin, cos, 0],
+    [0, 0, 1],
+  ]
+}
+const rot_x = (degrees: number): Matrix3x3 => {
+  const [cos, sin] = [Math.cos(math.to_radians(degrees)), Math.sin(math.to_radians(degrees))]
+  return [
+    [1, 0, 0],
+    [0, cos, -sin],
+    [0, sin, cos],
+  ]
+}
+const cubic_symmetry = lattice_rotations(fcc_bonds)
+
+// fcc lattice points rotated by rot that fall inside the region keep()
+function fcc_points(rot: Matrix3x3, keep: (pos: Vec3) => boolean): Vec3[] {
+  const points: Vec3[] = []
+  for (let ix = -5; ix <= 5; ix++) {
+    for (let iy = -5; iy <= 5; iy++) {
+      for (let iz = -5; iz <= 5; iz++) {
+        for (const basis of fcc_basis) {
+          const pos = math.mat3x3_vec3_multiply(
+            rot,
+            math.scale(math.add([ix, iy, iz], basis), a_fcc),
+          )
+          if (keep(pos)) points.push(pos)
+        }
+      }
+    }
+  }
+  return points
+}
+
+// Free-standing 16 Å cube: left half unrotated, right half rotated 30° about z
+function bicrystal() {
+  const half = 8
+  const in_box = ([, y_pos, z_pos]: Vec3) => Math.abs(y_pos) <= half && Math.abs(z_pos) <= half
+  const left = fcc_points(rot_z(0), (pos) => in_box(pos) && pos[0] >= -half && pos[0] < 0)
+  const right = fcc_points(
+    rot_z(30),
+    (pos) =>
+      in_box(pos) &&
+      pos[0] >= 0 &&
+      pos[0] <= half &&
+      left.every((other) => Math.hypot(...math.subtract(pos, other)) >= 1.8),
+  )
+  const sites = [...left, ...right].map((xyz, idx) => make_site(`Cu`, xyz, xyz, `Cu${idx}`))
+  return { structure: { sites } as Molecule, n_left: left.length }
+}
+
+describe(`rotation helpers`, () => {
+  test(`lattice_rotations finds the 24 proper rotations of the cubic point group`, () => {
+    expect(cubic_symmetry).toHaveLength(24)
+    for (const rot of cubic_symmetry) expect(math.det_3x3(rot)).toBeCloseTo(1, 10)
+  })
+
+  test(`polar_rotation strips the stretch from R U`, () => {
+    const rot = math.dot(rot_z(25), rot_x(-40))
+    const stretch: Matrix3x3 = [
+      [1.1, 0.05, 0],
+      [0.05, 0.95, 0.02],
+      [0, 0.02, 1.03],
+    ]
+    const polar = polar_rotation(math.dot(rot, stretch))
+    polar?.forEach((row, idx) => {
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(rot[idx][jdx], 10))
+    })
+    const improper = rot.map((row) => row.map((val) => -val)) as Matrix3x3
+    expect(polar_rotation(improper)).toBeNull()
+  })
+
+  test.each([
+    [0, 0],
+    [30, 30],
+    [60, 30],
+    [90, 0],
+    [135, 45],
+  ])(`cubic disorientation of a %s° rotation about z is %s°`, (angle, expected) => {
+    const identity = rot_z(0)
+    expect(rotation_angle(rot_z(angle))).toBeCloseTo(angle, 8)
+    expect(misorientation(identity, rot_z(angle), cubic_symmetry).angle).toBeCloseTo(
+      expected,
+      8,
+    )
+  })
+})
+
+describe(`compute_orientations`, () => {
+  test(`recovers the rotation of a rotated perfect crystal up to cubic symmetry`, () => {
+    const rot = math.dot(rot_z(17), rot_x(33))
+    // lattice vectors are rows: a' = R a  ->  M' = a Rᵀ
+    const lattice = math
+      .transpose_3x3_matrix(rot)
+      .map((row) => row.map((val) => val * a_fcc)) as Matrix3x3
+    const crystal = make_crystal(lattice, fcc_basis.map((abc): [string, Vec3] => [`Cu`, abc]))
+    const orientations = compute_orientations(crystal, { cutoff: 3, reference: fcc_bonds })
+    for (const orientation of orientations) {
+      expect(orientation).not.toBeNull()
+      if (orientation) {
+        expect(misorientation(rot, orientation, cubic_symmetry).angle).toBeLessThan(1e-5)
+      }
+    }
+  })
+
+  test(`rejects environments that don't match the reference`, () => {
+    // simple cubic bonds (90° apart) don't fit fcc's 60°/90°/120° bond angles
+    const simple_cubic = make_crystal(2.6, [[`Cu`, [0, 0, 0]]])
+    expect(compute_orientations(simple_cubic, { cutoff: 3, reference: fcc_bonds })).toEqual([
+      null,
+    ])
+  })
+})
+
+describe(`segment_grains`, () => {
+  test(`splits a 30° [001] tilt bicrystal into two grains`, () => {
+    const { structure, n_left } = bicrystal()
+    const result = segment_grains(structure, {
+      cutoff: 3,
+      reference: fcc_bonds,
+      min_grain_size: 20,
+    })
+    expect(result.grains).toHaveLength(2)
+    for (const grain of result.grains) expect(grain.size).toBeGreaterThan(120)
+    expect(result.grains[0].size).toBeGreaterThanOrEqual(result.grains[1].size)
+
+    // each half maps onto exactly one grain (plus unassigned boundary/surface atoms)
+    const left_ids = new Set(result.grain_ids.slice(0, n_left).filter((id) => id >= 0))
+    const right_ids = new Set(result.grain_ids.slice(n_left).filter((id) => id >= 0))
+    expect(left_ids.size).toBe(1)
+    expect(right_ids.size).toBe(1)
+    expect([...left_ids][0]).not.toBe([...right_ids][0])
+    const n_unassigned = result.grain_ids.filter((id) => id < 0).length
+    expect(n_unassigned).toBeLessThan(0.15 * result.grain_ids.length)
+
+    // mean grain orientations match the construction
+    const left_grain = result.grains[[...left_ids][0]]
+    const right_grain = result.grains[[...right_ids][0]]
+    for (const [grain, angle] of [
+      [left_grain, 0],
+      [right_grain, 30],
+    ] as const) {
+      const expected = rot_z(angle)
+      expect(misorientation(expected, grain.orientation, cubic_symmetry).angle).toBeLessThan(1)
+    }
+
+    expect(result.boundaries).toHaveLength(1)
+    const [boundary] = result.boundaries
+    expect(boundary.grains).toEqual([0, 1])
+    expect(boundary.misorientation).toBeCloseTo(30, 0)
+    expect(boundary.n_atoms).toBeGreaterThan(0)
+  })
+
+  test(`a large threshold merges both halves into one grain`, () => {
+    const { structure } = bicrystal()
+    const result = segment_grains(structure, {
+      cutoff: 3,
+      reference: fcc_bonds,
+      threshold: 35,
+      min_grain_size: 20,
+    })
+    expect(result.grains).toHaveLength(1)
+    expect(result.grains[0].size).toBeGreaterThan(250)
+    expect(result.boundaries).toEqual([])
+  })
+})
@@ -0,0 +1,152 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import {
+  compute_nye_tensor,
+  dislocation_density,
+  reference_neighbor_vectors,
+} from '$lib/order-params'
+import { describe, expect, test } from 'vitest'
+import { make_crystal, make_fcc } from '../setup'
+
+const fcc = make_fcc(3.615, `Cu`)
+const simple_cubic_bonds: Vec3[] = [
+  [1, 0, 0],
+  [-1, 0, 0],
+  [0, 1, 0],
+  [0, -1, 0],
+  [0, 0, 1],
+  [0, 0, -1],
+]
+
+// Simple cubic (a = 1) column periodic along z containing a Volterra edge dislocation with
+// Burgers vector [1, 0, 0] along the z axis (isotropic elasticity, ν = 0.3)
+function edge_dislocation(radius: number) {
+  const [burgers, nu] = [1, 0.3]
+  const sites: { element: string; xyz: Vec3 }[] = []
+  for (let ix = -radius - 1; ix <= radius; ix++) {
+    for (let iy = -radius - 1; iy <= radius; iy++) {
+      const [x_pos, y_pos] = [ix + 0.5, iy + 0.5]
+      const r_sq = x_pos ** 2 + y_pos ** 2
+      if (r_sq > radius ** 2) continue
+      const theta = Math.atan2(y_pos, x_pos)
+      const u_x =
+        (burgers / (2 * Math.PI)) * (theta + (x_pos * y_pos) / (2 * (1 - nu) * r_sq))
+      const u_y =
+        (-burgers / (2 * Math.PI)) *
+        (((1 - 2 * nu) / (4 * (1 - nu))) * Math.log(r_sq) +
+          (x_pos ** 2 - y_pos ** 2) / (4 * (1 - nu) * r_sq))
+      sites.push({ element: `Fe`, xyz: [x_pos + u_x, y_pos + u_y, 0] })
+    }
+  }
+  const lattice: Matrix3x3 = [
+    [40, 0, 0],
+    [0, 40, 0],
+    [0, 0, 1],
+  ]
+  return make_crystal(lattice, sites, { pbc: [false, false, true] })
+}
+
+function expect_all_close(tensors: (Matrix3x3 | null)[], expected: Matrix3x3) {
+  for (const tensor of tensors) {
+    expect(tensor).not.toBeNull()
+    tensor?.forEach((row, idx) => {
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(expected[idx][jdx], 10))
+    })
+  }
+}
+
+describe(`compute_nye_tensor`, () => {
+  test(`reference_neighbor_vectors returns the 12 fcc nearest-neighbor bonds`, () => {
+    const bonds = reference_neighbor_vectors(fcc, 3)
+    expect(bonds).toHaveLength(12)
+    for (const bond of bonds) expect(Math.hypot(...bond)).toBeCloseTo(3.615 / Math.SQRT2, 10)
+  })
+
+  test(`perfect crystal has identity correspondence and vanishing Nye tensor`, () => {
+    const { correspondence, nye, nye_norm } = compute_nye_tensor(fcc, {
+      cutoff: 3,
+      reference: fcc,
+    })
+    expect_all_close(correspondence, [
+      [1, 0, 0],
+      [0, 1, 0],
+      [0, 0, 1],
+    ])
+    expect(nye.every((alpha) => alpha !== null)).toBe(true)
+    for (const norm of nye_norm) expect(norm).toBeCloseTo(0, 10)
+  })
+
+  test(`homogeneous strain gives G = F⁻ᵀ and no dislocation content`, () => {
+    const stretch: Matrix3x3 = [
+      [1.02, 0.01, 0],
+      [0.01, 0.99, 0],
+      [0, 0, 1.01],
+    ]
+    // rows of the lattice matrix transform as a' = a Fᵀ, here with symmetric F = stretch
+    const strained = make_fcc(math.dot(fcc.lattice.matrix, stretch), `Cu`)
+    const { correspondence, nye_norm } = compute_nye_tensor(strained, {
+      cutoff: 3,
+      reference: fcc,
+    })
+    expect_all_close(correspondence, math.matrix_inverse_3x3(stretch))
+    for (const norm of nye_norm) expect(norm).toBeCloseTo(0, 10)
+  })
+
+  test(`localizes an edge dislocation and recovers its Burgers vector`, () => {
+    const radius = 7
+    const structure = edge_dislocation(radius)
+    const { nye, nye_norm } = compute_nye_tensor(structure, {
+      cutoff: 1.2,
+      reference: simple_cubic_bonds,
+    })
+    // strongest Nye signal sits at the dislocation core on the z axis
+    const core_idx = nye_norm.indexOf(Math.max(...nye_norm))
+    const [core_x, core_y] = structure.sites[core_idx].xyz
+    expect(Math.hypot(core_x, core_y)).toBeLessThan(1)
+
+    // Burgers vector b_k = ∫ α_zk dA over the column cross-section (area 1 per atom),
+    // excluding the under-coordinated surface
+    const burgers: Vec3 = [0, 0, 0]
+    structure.sites.forEach((site, site_idx) => {
+      const alpha = nye[site_idx]
+      if (!alpha || Math.hypot(site.xyz[0], site.xyz[1]) > radius - 2) return
+      for (let k_idx = 0; k_idx < 3; k_idx++) burgers[k_idx] += alpha[2][k_idx]
+    })
+    expect(burgers[0]).toBeGreaterThan(0.9)
+    expect(burgers[0]).toBeLessThan(1.5)
+    expect(Math.abs(burgers[1])).toBeLessThan(0.1)
+    expect(Math.abs(burgers[2])).toBeLessThan(1e-10)
+
+    // elastic far field carries no dislocation content
+    structure.sites.forEach((site, site_idx) => {
+      const dist = Math.hypot(site.xyz[0], site.xyz[1])
+      if (dist > 3.5 && dist < radius - 2) expect(nye_norm[site_idx]).toBeLessThan(1e-3)
+    })
+  })
+
+  test(`atoms with too few matched bonds get null tensors`, () => {
+    const isolated = make_crystal(10, [[`Fe`, [0, 0, 0]]], { pbc: [false, false, false] })
+    const result = compute_nye_tensor(isolated, { cutoff: 1.2, reference: simple_cubic_bonds })
+    expect(result.correspondence).toEqual([null])
+    expect(result.nye).toEqual([null])
+    expect(result.nye_norm).toEqual([0])
+  })
+
+  test(`rejects references with fewer than 3 bonds`, () => {
+    expect(() => compute_nye_tensor(fcc, { cutoff: 3, reference: [[1, 0, 0]] })).toThrow(
+      `Need at least 3 ideal neighbor vectors`,
+    )
+  })
+})
+
+describe(`dislocation_density`, () => {
+  test(`integrates Nye norms into line length per volume`, () => {
+    // 10 atoms each carrying |α| = 0.1 Å⁻¹ in a 1000 Å³ box with |b| = 2.5 Å
+    expect(dislocation_density(Array(10).fill(0.1), 2.5, 1000, 12)).toBeCloseTo(
+      (10 * 0.1 * 12) / (2.5 * 1000),
+      12,
+    )
+    expect(dislocation_density([0.2, 0], 1, 50)).toBeCloseTo(0.2 / 2, 12)
+    expect(() => dislocation_density([0.1], 0, 10)).toThrow(`must be > 0`)
+  })
+})
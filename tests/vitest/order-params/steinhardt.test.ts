@@ -0,0 +1,110 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import { compute_steinhardt_q, compute_steinhardt_q_frames } from '$lib/order-params'
+import type { Molecule } from '$lib/structure'
+import { make_supercell, rattle_structure } from '$lib/structure'
+import { make_site } from '$lib/structure/site'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const fcc = make_crystal(3.6, [
+  [`Cu`, [0, 0, 0]],
+  [`Cu`, [0.5, 0.5, 0]],
+  [`Cu`, [0.5, 0, 0.5]],
+  [`Cu`, [0, 0.5, 0.5]],
+])
+const bcc = make_crystal(2.87, [
+  [`Fe`, [0, 0, 0]],
+  [`Fe`, [0.5, 0.5, 0.5]],
+])
+const a_hcp = 2.5
+const hcp_matrix: Matrix3x3 = [
+  [a_hcp, 0, 0],
+  [-a_hcp / 2, (a_hcp * Math.sqrt(3)) / 2, 0],
+  [0, 0, a_hcp * Math.sqrt(8 / 3)],
+]
+const hcp = make_crystal(hcp_matrix, [
+  [`Mg`, [1 / 3, 2 / 3, 0.25]],
+  [`Mg`, [2 / 3, 1 / 3, 0.75]],
+])
+
+describe(`Steinhardt order parameters`, () => {
+  // ideal-lattice values, e.g. from Mickel et al., J. Chem. Phys. 138, 044501 (2013)
+  test.each([
+    [`fcc`, fcc, 12, 0.19094, 0.57452],
+    [`bcc`, bcc, 8, 0.50918, 0.62854],
+    [`hcp`, hcp, 12, 0.09722, 0.48476],
+    [`sc`, make_crystal(3, [[`Po`, [0, 0, 0]]]), 6, 0.76376, 0.35355],
+  ])(`%s first shell q4 and q6`, (_, crystal, n_neighbors, q4, q6) => {
+    const [q4s, q6s] = compute_steinhardt_q(crystal, { n_neighbors })
+    expect(q4s).toHaveLength(crystal.sites.length)
+    q4s.forEach((val) => expect(val).toBeCloseTo(q4, 5))
+    q6s.forEach((val) => expect(val).toBeCloseTo(q6, 5))
+    // every atom's neighborhood is the same, so averaging changes nothing
+    const averaged = { n_neighbors, degrees: [6], averaged: true }
+    const [q6_avg] = compute_steinhardt_q(crystal, averaged)
+    q6_avg.forEach((val) => expect(val).toBeCloseTo(q6, 5))
+  })
+
+  test(`icosahedron has q4 = 0 and cutoff neighbors match kNN`, () => {
+    const golden = (1 + Math.sqrt(5)) / 2
+    const vertices = [-1, 1].flatMap((sign_1) =>
+      [-golden, golden].flatMap((sign_2) => [
+        [0, sign_1, sign_2],
+        [sign_1, sign_2, 0],
+        [sign_2, 0, sign_1],
+      ]),
+    )
+    const sites = [[0, 0, 0], ...vertices].map((xyz, idx) =>
+      make_site(`Cu`, [0, 0, 0], xyz as Vec3, `Cu${idx + 1}`),
+    )
+    const icosahedron: Molecule = { sites }
+    const [[q4], [q6]] = compute_steinhardt_q(icosahedron, { n_neighbors: 12 })
+    expect(q4).toBeCloseTo(0, 10)
+    expect(q6).toBeCloseTo(0.66332, 5)
+    const by_cutoff = compute_steinhardt_q(fcc, { cutoff: 3, degrees: [6, 8] })
+    const by_knn = compute_steinhardt_q(fcc, { n_neighbors: 12, degrees: [6, 8] })
+    expect(by_cutoff).toEqual(by_knn)
+  })
+
+  test(`trajectory frames match per-frame calls`, () => {
+    const supercell = make_supercell(fcc, [3, 3, 3])
+    const trajectory: TrajectoryType = {
+      frames: [0, 1, 2, 3, 4].map((step) => ({
+        structure: rattle_structure(supercell, 0.1, { seed: step }),
+        step,
+        metadata: {},
+      })),
+    }
+    const options = { cutoff: 3, averaged: true }
+    const batched = compute_steinhardt_q_frames(trajectory, {
+      ...options,
+      start_frame: 1,
+      stride: 2,
+    })
+    expect(batched).toHaveLength(2)
+    batched.forEach((frame_q, idx) => {
+      const expected = compute_steinhardt_q(trajectory.frames[1 + 2 * idx].structure, options)
+      frame_q.forEach((q_l, deg_idx) =>
+        q_l.forEach((val, atom_idx) =>
+          expect(val).toBeCloseTo(expected[deg_idx][atom_idx], 12),
+        ),
+      )
+    })
+  })
+
+  test(`rejects ambiguous neighbor definitions and bad degrees`, () => {
+    for (const options of [{}, { cutoff: 3, n_neighbors: 12 }]) {
+      expect(() => compute_steinhardt_q(fcc, options)).toThrow(
+        `exactly one of cutoff and n_neighbors`,
+      )
+    }
+    expect(() => compute_steinhardt_q(fcc, { cutoff: 3, degrees: [6.5] })).toThrow(
+      `non-negative integers`,
+    )
+    const no_frames: TrajectoryType = { frames: [] }
+    expect(() => compute_steinhardt_q_frames(no_frames, { cutoff: 3, stride: 0 })).toThrow(
+      `stride must be a positive integer`,
+    )
+  })
+})
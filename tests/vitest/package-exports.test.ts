@@ -90,6 +90,7 @@ describe(`package.json exports`, () => {
         `STRUCT_TEXT_FORMATS`,
         `structure_to_cif_str`,
         `structure_to_json_str`,
+        `structure_to_lammps_data_str`,
         `structure_to_poscar_str`,
         `structure_to_xyz_str`,
       ].toSorted(),
@@ -0,0 +1,101 @@
+import { COULOMB_EV_A } from '$lib/constants'
+import type { BuckinghamPair } from '$lib/potentials'
+import { create_buckingham_coulomb_potential } from '$lib/potentials'
+import type { AnyStructure } from '$lib/structure'
+import { ewald_energy, rattle_structure } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal, make_rocksalt } from '../setup'
+import { expect_energy_derivatives } from './fixtures/finite-differences'
+
+// rigid-ion MgO of Lewis & Catlow (J. Phys. C 18, 1149 (1985))
+const mgo_pairs: BuckinghamPair[] = [
+  { elements: [`Mg`, `O`], a: 1428.5, rho: 0.2945, c: 0 },
+  { elements: [`O`, `O`], a: 22764, rho: 0.149, c: 27.88 },
+]
+
+// charged triclinic cell with no special positions
+const triclinic = make_crystal(
+  [
+    [7, 0, 0],
+    [1.2, 6.5, 0],
+    [0.5, 0.8, 8],
+  ],
+  [
+    [`Mg`, [0.1, 0.2, 0.3], 2],
+    [`O`, [0.6, 0.1, 0.7], -2],
+    [`Li`, [0.3, 0.8, 0.2], 1],
+    [`F`, [0.85, 0.55, 0.45], -1],
+    [`Mg`, [0.45, 0.4, 0.9], 2],
+    [`O`, [0.2, 0.6, 0.6], -2],
+    [`O`, [0.7, 0.7, 0.1], -2],
+  ],
+)
+
+describe(`Buckingham + Ewald Coulomb potential`, () => {
+  test(`Coulomb part matches ewald_energy`, () => {
+    const nacl = make_rocksalt(5.64, `Na`, `Cl`, 1)
+    const { energy, energies } = create_buckingham_coulomb_potential([]).compute(nacl)
+    expect(energy).toBeCloseTo(ewald_energy(nacl, { real_cutoff: 10 }).energy, 8)
+    expect(energy).toBeCloseTo((-4 * 1.747565 * COULOMB_EV_A) / 2.82, 3)
+    expect(energies.reduce((sum, val) => sum + val, 0)).toBeCloseTo(energy, 10)
+    // net charge adds the neutralizing background term
+    const coulomb = create_buckingham_coulomb_potential([], { accuracy: `high` })
+    const expected = ewald_energy(triclinic, { real_cutoff: 10, accuracy: `high` }).energy
+    expect(coulomb.compute(triclinic).energy).toBeCloseTo(expected, 8)
+  })
+
+  test(`per-element charges replace oxidation states`, () => {
+    const nacl = make_rocksalt(5.64, `Na`, `Cl`, 1)
+    const neutral = make_rocksalt(5.64, `Na`, `Cl`)
+    const charges = { Na: 1, Cl: -1 }
+    const from_options = create_buckingham_coulomb_potential([], { charges }).compute(neutral)
+    const from_sites = create_buckingham_coulomb_potential([]).compute(nacl)
+    expect(from_options.energy).toBeCloseTo(from_sites.energy, 10)
+    expect(() =>
+      create_buckingham_coulomb_potential([], { charges: { Na: 1 } }).compute(nacl),
+    ).toThrow(`No charge given for site 4 (Cl)`)
+  })
+
+  test(`MgO is in equilibrium near its experimental lattice constant`, () => {
+    const mgo = create_buckingham_coulomb_potential(mgo_pairs)
+    const compressed = mgo.compute(make_rocksalt(4.1, `Mg`, `O`, 2))
+    const expanded = mgo.compute(make_rocksalt(4.3, `Mg`, `O`, 2))
+    expect(compressed.stress?.[0][0]).toBeLessThan(0)
+    expect(expanded.stress?.[0][0]).toBeGreaterThan(0)
+    expect(compressed.stress?.[0][1]).toBeCloseTo(0, 8)
+    const { energy, forces } = mgo.compute(make_rocksalt(4.212, `Mg`, `O`, 2))
+    expect(energy / 4).toBeCloseTo(-41.31, 2)
+    forces.flat().forEach((val) => expect(val).toBeCloseTo(0, 8))
+  })
+
+  test.each([
+    [`rattled MgO`, rattle_structure(make_rocksalt(4.21, `Mg`, `O`, 2), 0.1, { seed: 5 })],
+    [`charged triclinic cell`, triclinic],
+  ])(`%s forces and stress are derivatives of the energy`, (_, crystal) => {
+    // 4 digits for forces as math.erfc is accurate to ~1e-7
+    expect_energy_derivatives(
+      create_buckingham_coulomb_potential(mgo_pairs),
+      crystal,
+      [
+        [0, 0],
+        [3, 1],
+        [6, 2],
+      ],
+      { forces: 4, stress: 6 },
+    )
+  })
+
+  test(`rejects invalid parameters and non-periodic structures`, () => {
+    const bad_pair: BuckinghamPair = { elements: [`O`, `O`], a: 1, rho: 0, c: 0 }
+    expect(() => create_buckingham_coulomb_potential([bad_pair])).toThrow(
+      `Buckingham rho of O-O must be > 0`,
+    )
+    expect(() => create_buckingham_coulomb_potential([], { cutoff: -1 })).toThrow(
+      `cutoff must be positive, got -1`,
+    )
+    const molecule = { sites: triclinic.sites } as AnyStructure
+    expect(() => create_buckingham_coulomb_potential([]).compute(molecule)).toThrow(
+      `Ewald sums need periodic boundaries along all 3 axes`,
+    )
+  })
+})
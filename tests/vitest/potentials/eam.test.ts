@@ -0,0 +1,124 @@
+import type { Vec3 } from '$lib/math'
+import { create_eam_potential, parse_eam_alloy } from '$lib/potentials'
+import type { AnyStructure } from '$lib/structure'
+import { make_supercell, rattle_structure } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import type { SimpleSite } from '../setup'
+import { FCC_SITES, make_crystal } from '../setup'
+import { expect_energy_derivatives, move_site } from './fixtures/finite-differences'
+
+// analytic Cu/Ni model tabulated into a setfl file
+const cutoff = 5.5
+const taper = (r: number) => (r < cutoff ? (1 - r / cutoff) ** 4 : 0)
+const density = { Cu: 3, Ni: 2.5 }
+const rho_fn = (elem: `Cu` | `Ni`, r: number) => density[elem] * Math.exp(2.5 - r) * taper(r)
+const embed_fn = (elem: `Cu` | `Ni`, rho: number) =>
+  (elem === `Cu` ? -2 : -2.2) * Math.sqrt(rho)
+const phi_fn = (depth: number, r: number) =>
+  depth * (Math.exp(-3 * (r - 2.5)) - 2 * Math.exp(-1.5 * (r - 2.5))) * taper(r)
+const [n_rho, d_rho, n_r, d_r] = [1000, 0.01, 2000, 0.003]
+
+const grid = (count: number, step: number, fn: (x: number) => number) =>
+  Array.from({ length: count }, (_, idx) => fn(idx * step).toExponential(12))
+    // 5 values per line like LAMMPS potential files
+    .reduce<string[]>((lines, val, idx) => {
+      if (idx % 5 === 0) lines.push(val)
+      else lines[lines.length - 1] += ` ${val}`
+      return lines
+    }, [])
+    .join(`\n`)
+
+const setfl = [
+  `Synthetic Cu-Ni EAM`,
+  `for tests`,
+  ``,
+  `2 Cu Ni`,
+  `${n_rho} ${d_rho} ${n_r} ${d_r} ${cutoff}`,
+  `29 63.546 3.615 fcc`,
+  grid(n_rho, d_rho, (rho) => embed_fn(`Cu`, rho)),
+  grid(n_r, d_r, (r) => rho_fn(`Cu`, r)),
+  `28 58.6934 3.52 fcc`,
+  grid(n_rho, d_rho, (rho) => embed_fn(`Ni`, rho)),
+  grid(n_r, d_r, (r) => rho_fn(`Ni`, r)),
+  ...[0.5, 0.6, 0.7].map((depth) => grid(n_r, d_r, (r) => r * phi_fn(depth, r))),
+].join(`\n`)
+
+// L1₂ ordering: Ni on one of the four fcc sites
+const cu3ni = make_crystal(
+  3.6,
+  FCC_SITES.map((abc, idx): SimpleSite => [idx === 3 ? `Ni` : `Cu`, abc]),
+)
+
+describe(`EAM/alloy potential`, () => {
+  const data = parse_eam_alloy(setfl)
+
+  test(`parses the setfl header and tables`, () => {
+    expect(data).toMatchObject({ n_rho, d_rho, n_r, d_r, cutoff })
+    expect(data.comments).toEqual([`Synthetic Cu-Ni EAM`, `for tests`, ``])
+    const headers = data.elements.map(({ element, atomic_number, lattice_type }) => [
+      element,
+      atomic_number,
+      lattice_type,
+    ])
+    expect(headers).toEqual([
+      [`Cu`, 29, `fcc`],
+      [`Ni`, 28, `fcc`],
+    ])
+    expect(data.elements[1].mass).toBe(58.6934)
+    expect(data.elements[0].density).toHaveLength(n_r)
+    expect(data.pair).toHaveLength(3)
+    expect(data.pair[1][1000]).toBeCloseTo(3 * phi_fn(0.6, 3), 10)
+    const truncated = setfl.split(`\n`).slice(0, -10).join(`\n`)
+    expect(() => parse_eam_alloy(truncated)).toThrow(`ended while reading Ni-Ni r*phi(r)`)
+    expect(() => parse_eam_alloy(setfl.replace(`2 Cu Ni`, `3 Cu Ni`))).toThrow(
+      `Invalid setfl element line`,
+    )
+  })
+
+  test(`dimer energy and forces match the analytic model`, () => {
+    const eam = create_eam_potential(data)
+    const r = 2.4
+    const dimer = {
+      sites: [`Cu`, `Ni`].map((element, idx) => ({
+        species: [{ element, occu: 1, oxidation_state: 0 }],
+        xyz: [idx * r, 0, 0] as Vec3,
+        abc: [0, 0, 0] as Vec3,
+        label: element,
+        properties: {},
+      })),
+    } as AnyStructure
+    const { energy, energies, forces, stress } = eam.compute(dimer)
+    const expected =
+      embed_fn(`Cu`, rho_fn(`Ni`, r)) + embed_fn(`Ni`, rho_fn(`Cu`, r)) + phi_fn(0.6, r)
+    expect(energy).toBeCloseTo(expected, 5)
+    expect(energies[0] + energies[1]).toBeCloseTo(energy, 12)
+    expect(forces[0][0]).toBeCloseTo(-forces[1][0], 10)
+    const step = 1e-5
+    const numeric =
+      (eam.compute(move_site(dimer, 1, [-step, 0, 0])).energy -
+        eam.compute(move_site(dimer, 1, [step, 0, 0])).energy) /
+      (2 * step)
+    expect(forces[1][0]).toBeCloseTo(numeric, 6)
+    expect(stress).toBeNull()
+    expect(() => eam.compute(make_crystal(3, [[`Fe`, [0, 0, 0]]]))).toThrow(
+      `Site 0 (Fe) is not covered by the EAM potential for Cu, Ni`,
+    )
+  })
+
+  test(`forces and stress are derivatives of the energy`, () => {
+    const crystal = rattle_structure(make_supercell(cu3ni, [2, 2, 2]), 0.1, { seed: 3 })
+    const eam = create_eam_potential(data)
+    expect_energy_derivatives(eam, crystal, [
+      [0, 0],
+      [5, 1],
+      [31, 2],
+    ])
+  })
+
+  test(`perfect crystal has no forces or shear stress`, () => {
+    const { forces, stress } = create_eam_potential(data).compute(cu3ni)
+    forces.flat().forEach((val) => expect(val).toBeCloseTo(0, 10))
+    expect(stress?.[1][1]).toBeCloseTo(stress?.[2][2] ?? NaN, 10)
+    expect(stress?.[0][1]).toBeCloseTo(0, 10)
+  })
+})
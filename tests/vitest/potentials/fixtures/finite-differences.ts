@@ -0,0 +1,63 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { Potential } from '$lib/potentials'
+import type { AnyStructure, Crystal } from '$lib/structure'
+import { expect } from 'vitest'
+
+export const move_site = <T extends AnyStructure>(
+  structure: T,
+  site_idx: number,
+  shift: Vec3,
+): T => ({
+  ...structure,
+  sites: structure.sites.map((site, idx) =>
+    idx === site_idx ? { ...site, xyz: math.add(site.xyz, shift) } : site,
+  ),
+})
+
+// homogeneous deformation x → (1 + h e_row e_colᵀ) x of cell and sites
+export const deform = (crystal: Crystal, row: number, col: number, step: number): Crystal => {
+  const apply = (vec: Vec3): Vec3 =>
+    vec.map((val, axis) => val + (axis === row ? step * vec[col] : 0)) as Vec3
+  const matrix = crystal.lattice.matrix.map(apply) as Matrix3x3
+  return {
+    ...crystal,
+    lattice: { ...crystal.lattice, matrix },
+    sites: crystal.sites.map((site) => ({ ...site, xyz: apply(site.xyz) })),
+  }
+}
+
+// Checks that the forces sum to zero and that forces on the given [site, axis] pairs and
+// the stress components [row, col] match central differences of the energy
+export function expect_energy_derivatives(
+  potential: Potential,
+  crystal: Crystal,
+  site_axes: [number, number][],
+  digits = { forces: 5, stress: 6 },
+): void {
+  const { forces, stress } = potential.compute(crystal)
+  const net = forces.reduce((sum, force) => math.add(sum, force), [0, 0, 0])
+  net.forEach((val) => expect(val).toBeCloseTo(0, 10))
+
+  const step = 1e-5
+  for (const [site_idx, axis] of site_axes) {
+    const shift: Vec3 = [0, 0, 0]
+    shift[axis] = step
+    const e_plus = potential.compute(move_site(crystal, site_idx, shift)).energy
+    const e_minus = potential.compute(move_site(crystal, site_idx, math.scale(shift, -1)))
+      .energy
+    expect(forces[site_idx][axis]).toBeCloseTo(-(e_plus - e_minus) / (2 * step), digits.forces)
+  }
+  const volume = Math.abs(math.det_3x3(crystal.lattice.matrix))
+  const stress_components = [
+    [0, 0],
+    [1, 2],
+    [2, 0],
+  ]
+  for (const [row, col] of stress_components) {
+    const e_plus = potential.compute(deform(crystal, row, col, step)).energy
+    const e_minus = potential.compute(deform(crystal, row, col, -step)).energy
+    const numeric = (e_plus - e_minus) / (2 * step * volume)
+    expect(stress?.[row][col]).toBeCloseTo(numeric, digits.stress)
+  }
+}
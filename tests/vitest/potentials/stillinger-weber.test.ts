@@ -0,0 +1,82 @@
+import type { Vec3 } from '$lib/math'
+import type { SwEntry } from '$lib/potentials'
+import {
+  create_stillinger_weber_potential,
+  parse_lammps_sw,
+  SW_GE,
+  SW_SI,
+} from '$lib/potentials'
+import type { AnyStructure } from '$lib/structure'
+import { rattle_structure } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_diamond } from '../setup'
+import { expect_energy_derivatives } from './fixtures/finite-differences'
+
+describe(`Stillinger-Weber potential`, () => {
+  test(`diamond Si and Ge have cohesive energy -2ε at equilibrium`, () => {
+    const si = create_stillinger_weber_potential(SW_SI).compute(make_diamond(5.431, `Si`))
+    expect(si.energy / 8).toBeCloseTo(-4.3366, 4)
+    si.forces.flat().forEach((val) => expect(val).toBeCloseTo(0, 10))
+    expect(si.stress?.[0][1]).toBeCloseTo(0, 10)
+    // SW lattices scale with σ
+    const ge_lattice = (5.431 * SW_GE[0].sigma) / SW_SI[0].sigma
+    const ge = create_stillinger_weber_potential(SW_GE).compute(make_diamond(ge_lattice, `Ge`))
+    expect(ge.energy / 8).toBeCloseTo(-2 * SW_GE[0].epsilon, 4)
+  })
+
+  test(`dimer energy is the pair term`, () => {
+    const r = 2.35
+    const dimer = {
+      sites: [0, 1].map((idx) => ({
+        species: [{ element: `Si`, occu: 1, oxidation_state: 0 }],
+        xyz: [idx * r, 0, 0] as Vec3,
+        abc: [0, 0, 0] as Vec3,
+        label: `Si`,
+        properties: {},
+      })),
+    } as AnyStructure
+    const { energy, forces, stress } = create_stillinger_weber_potential(SW_SI).compute(dimer)
+    const { epsilon, sigma, cut_ratio, pair_a, pair_b } = SW_SI[0]
+    const cutoff_exp = Math.exp(sigma / (r - cut_ratio * sigma))
+    const expected = pair_a * epsilon * (pair_b * (sigma / r) ** 4 - 1) * cutoff_exp
+    expect(energy).toBeCloseTo(expected, 10)
+    expect(forces[0][0]).toBeCloseTo(-forces[1][0], 10)
+    expect(stress).toBeNull()
+  })
+
+  test(`forces and stress are derivatives of the energy`, () => {
+    const crystal = rattle_structure(make_diamond(5.431, `Si`), 0.1, { seed: 7 })
+    expect_energy_derivatives(create_stillinger_weber_potential(SW_SI), crystal, [
+      [0, 0],
+      [3, 1],
+      [6, 2],
+    ])
+  })
+
+  test(`parses LAMMPS .sw files`, () => {
+    const text = `# Stillinger-Weber Si
+Si Si Si 2.1683 2.0951 1.80 21.0 1.20 -0.333333333333
+         7.049556277 0.6022245584 4.0 0.0 0.0`
+    const [entry] = parse_lammps_sw(text)
+    expect(entry).toMatchObject({ ...SW_SI[0], cos_theta0: expect.any(Number) })
+    expect(entry.cos_theta0).toBeCloseTo(-1 / 3, 10)
+    expect(() => parse_lammps_sw(text.replace(` 0.0 0.0`, ``))).toThrow(
+      `SW file must have 14 fields per entry, got 12 in total`,
+    )
+    expect(() => parse_lammps_sw(text.replace(`21.0`, `x`))).toThrow(
+      `Invalid number in SW entry Si Si Si`,
+    )
+  })
+
+  test(`rejects uncovered elements and incomplete triplets`, () => {
+    const sw = create_stillinger_weber_potential(SW_SI)
+    expect(() => sw.compute(make_diamond(5.431, `Ge`))).toThrow(
+      `Site 0 (Ge) is not covered by the Stillinger-Weber potential for Si`,
+    )
+    const ge_si_si: SwEntry = { ...SW_GE[0], elements: [`Ge`, `Si`, `Si`] }
+    const si_ge = [...SW_SI, ge_si_si]
+    expect(() => create_stillinger_weber_potential(si_ge)).toThrow(
+      `Missing SW parameters for Si Si Ge`,
+    )
+  })
+})
@@ -0,0 +1,98 @@
+import type { Vec3 } from '$lib/math'
+import {
+  create_tersoff_potential,
+  parse_lammps_tersoff,
+  TERSOFF_C,
+  TERSOFF_SI,
+  TERSOFF_SIC,
+} from '$lib/potentials'
+import type { AnyStructure } from '$lib/structure'
+import { rattle_structure } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_diamond } from '../setup'
+import { expect_energy_derivatives } from './fixtures/finite-differences'
+
+// Tersoff's Si(C) entry as distributed with LAMMPS (Si.tersoff)
+const si_tersoff = `# Tersoff_2
+Si Si Si 3.0 1.0 0.0 1.0039e5 16.217 -0.59825 0.78734 1.1000e-6 1.7322 471.18 2.85 0.15
+         2.4799 1830.8`
+
+describe(`Tersoff potential`, () => {
+  test.each([
+    [`Si`, TERSOFF_SI, make_diamond(5.432, `Si`), -4.6296],
+    [`C`, TERSOFF_C, make_diamond(3.566, `C`), -7.3705],
+    [`SiC`, TERSOFF_SIC, make_diamond(4.36, `Si`, `C`), -6.1597],
+  ])(`%s cohesive energy per atom`, (_, entries, crystal, expected) => {
+    const { energy, forces, stress } = create_tersoff_potential(entries).compute(crystal)
+    expect(energy / crystal.sites.length).toBeCloseTo(expected, 3)
+    forces.flat().forEach((val) => expect(val).toBeCloseTo(0, 10))
+    expect(stress?.[0][0]).toBeCloseTo(stress?.[1][1] ?? NaN, 10)
+    expect(stress?.[1][2]).toBeCloseTo(0, 10)
+  })
+
+  test(`dimer has bond order 1`, () => {
+    const r = 2.35
+    const dimer = {
+      sites: [0, 1].map((idx) => ({
+        species: [{ element: `Si`, occu: 1, oxidation_state: 0 }],
+        xyz: [idx * r, 0, 0] as Vec3,
+        abc: [0, 0, 0] as Vec3,
+        label: `Si`,
+        properties: {},
+      })),
+    } as AnyStructure
+    const { energy, stress } = create_tersoff_potential(TERSOFF_SI).compute(dimer)
+    const { repulsive_a, attractive_b, lambda1, lambda2 } = TERSOFF_SI[0]
+    const expected =
+      repulsive_a * Math.exp(-lambda1 * r) - attractive_b * Math.exp(-lambda2 * r)
+    expect(energy).toBeCloseTo(expected, 10)
+    expect(stress).toBeNull()
+  })
+
+  test.each([
+    [`Si`, TERSOFF_SI, make_diamond(5.432, `Si`)],
+    [`SiC`, TERSOFF_SIC, make_diamond(4.36, `Si`, `C`)],
+  ])(`%s forces and stress are derivatives of the energy`, (_, entries, crystal) => {
+    expect_energy_derivatives(
+      create_tersoff_potential(entries),
+      rattle_structure(crystal, 0.1, { seed: 11 }),
+      [
+        [0, 0],
+        [3, 1],
+        [6, 2],
+      ],
+    )
+  })
+
+  test(`parses LAMMPS .tersoff files`, () => {
+    const [entry] = parse_lammps_tersoff(si_tersoff)
+    expect(entry).toMatchObject({
+      elements: [`Si`, `Si`, `Si`],
+      m: 3,
+      c: 1.0039e5,
+      n: 0.78734,
+      beta: 1.1e-6,
+      attractive_b: 471.18,
+      repulsive_a: 1830.8,
+    })
+    for (const key of [`cutoff_r`, `cutoff_d`] as const) {
+      expect(entry[key]).toBeCloseTo(TERSOFF_SI[0][key], 12)
+    }
+    expect(() => parse_lammps_tersoff(si_tersoff.replace(`2.4799 `, ``))).toThrow(
+      `Tersoff file must have 17 fields per entry, got 16 in total`,
+    )
+    expect(() => parse_lammps_tersoff(si_tersoff.replace(`16.217`, `x`))).toThrow(
+      `Invalid number in Tersoff entry Si Si Si`,
+    )
+  })
+
+  test(`rejects uncovered elements and incomplete triplets`, () => {
+    const silicon = create_tersoff_potential(TERSOFF_SI)
+    expect(() => silicon.compute(make_diamond(4.36, `Si`, `C`))).toThrow(
+      `Site 1 (C) is not covered by the Tersoff potential for Si`,
+    )
+    expect(() => create_tersoff_potential(TERSOFF_SIC.slice(1))).toThrow(
+      `Missing Tersoff parameters for Si Si Si`,
+    )
+  })
+})
@@ -3,6 +3,7 @@ import * as math from '$lib/math'
 import type { Matrix3x3 } from '$lib/math'
 import { calculate_all_pair_rdfs, calculate_rdf } from '$lib/rdf'
 import type { Pbc } from '$lib/structure'
+import { Select } from '$lib/structure'
 import { structure_map } from '$site/structures'
 import { describe, expect, test } from 'vitest'
 import { create_test_structure, make_crystal } from '../setup'
@@ -350,6 +351,35 @@ describe(`calculate_rdf`, () => {
       expect(result.g_r.every(isFinite)).toBe(true)
     },
   )
+
+  test(`atom selections match the equivalent species filters`, () => {
+    const by_species = calculate_rdf(bi2zr2o8_structure, {
+      center_species: `O`,
+      neighbor_species: `O`,
+      cutoff: 6,
+    })
+    // fully occupied O sites, so unit weights equal the O occupancies
+    const by_selection = calculate_rdf(bi2zr2o8_structure, {
+      center_selection: Select.element(`O`),
+      neighbor_selection: Select.element(`Bi`, `Zr`).not(),
+      cutoff: 6,
+    })
+    expect(by_selection.g_r).toEqual(by_species.g_r)
+    // index lists work too and combine with species filters (Na has index 0)
+    const nacl = create_test_structure(
+      [
+        [5, 0, 0],
+        [0, 5, 0],
+        [0, 0, 5],
+      ],
+      [make_site(`Na`, [0, 0, 0]), make_site(`Cl`, [2.5, 2.5, 2.5])],
+    )
+    const na_cl = calculate_rdf(nacl, { center_species: `Na`, neighbor_species: `Cl` })
+    const by_index = calculate_rdf(nacl, { center_selection: [0], neighbor_selection: [1] })
+    expect(by_index.g_r).toEqual(na_cl.g_r)
+    const empty = calculate_rdf(nacl, { center_species: `Na`, center_selection: [1] })
+    expect(empty.g_r.every((val) => val === 0)).toBe(true)
+  })
 })
 
 describe(`calculate_all_pair_rdfs`, () => {
@@ -0,0 +1,77 @@
+import {
+  calculate_structure_factor,
+  reciprocal_lattice_vectors,
+  structure_factor_from_rdf,
+} from '$lib/rdf'
+import { describe, expect, test } from 'vitest'
+import { IDENTITY_MATRIX3, make_crystal } from '../setup'
+
+// conventional bcc cell: h + k + l odd reflections cancel, even ones scatter with S = 2
+const a_fe = 2.87
+const bcc_fe = make_crystal(a_fe, [
+  [`Fe`, [0, 0, 0]],
+  [`Fe`, [0.5, 0.5, 0.5]],
+])
+
+describe(`reciprocal_lattice_vectors`, () => {
+  test(`lists the shells of a cubic cell within (q_min, q_max]`, () => {
+    const q_100 = 2 * Math.PI
+    const vectors = reciprocal_lattice_vectors(IDENTITY_MATRIX3, 1.5 * q_100)
+    expect(vectors).toHaveLength(6 + 12)
+    expect(vectors[0].q_norm).toBeCloseTo(q_100, 12)
+    expect(vectors[17].q_norm).toBeCloseTo(Math.SQRT2 * q_100, 12)
+    const shell = reciprocal_lattice_vectors(IDENTITY_MATRIX3, 1.5 * q_100, 1.1 * q_100)
+    const hkl_sums = shell.map(({ hkl }) => hkl.reduce((sum, idx) => sum + Math.abs(idx), 0))
+    expect(hkl_sums).toEqual(Array(12).fill(2))
+    expect(() => reciprocal_lattice_vectors(IDENTITY_MATRIX3, 0)).toThrow(`q_max`)
+  })
+})
+
+describe(`calculate_structure_factor`, () => {
+  test(`bcc extinctions and scattering length weighting`, () => {
+    const { q, s_q } = calculate_structure_factor(bcc_fe, { q_max: 4, n_bins: 40 })
+    const q_unit = (2 * Math.PI) / a_fe
+    expect(q.map((q_val) => q_val / q_unit)).toEqual([
+      expect.closeTo(1, 12),
+      expect.closeTo(Math.SQRT2, 12),
+      expect.closeTo(Math.sqrt(3), 12),
+    ])
+    expect(s_q).toEqual([expect.closeTo(0, 12), expect.closeTo(2, 12), expect.closeTo(0, 12)])
+
+    // CsCl ordering lifts the odd extinctions by (b_Cs − b_Cl)² / (b_Cs² + b_Cl²)
+    const scattering_lengths = { Fe: 1, Co: 3 }
+    const ordered = make_crystal(a_fe, [
+      [`Fe`, [0, 0, 0]],
+      [`Co`, [0.5, 0.5, 0.5]],
+    ])
+    const weighted = calculate_structure_factor(ordered, { q_max: 4, scattering_lengths })
+    expect(weighted.s_q[0]).toBeCloseTo(4 / 10, 12)
+    expect(weighted.s_q[1]).toBeCloseTo(16 / 10, 12)
+  })
+})
+
+describe(`structure_factor_from_rdf`, () => {
+  test(`ideal gas and hard-core step g(r)`, () => {
+    const bin_size = 0.001
+    const r = Array.from({ length: 10_000 }, (_, idx) => (idx + 0.5) * bin_size)
+    const ideal = structure_factor_from_rdf({ r, g_r: r.map(() => 1) }, 0.05)
+    expect(ideal.s_q.every((val) => val === 1)).toBe(true)
+
+    // g = 0 inside sigma: S(q) = 1 − 4πρ (sin(qσ) − qσ cos(qσ)) / q³
+    const [sigma, density] = [2.5, 0.03]
+    const g_r = r.map((rad) => (rad < sigma ? 0 : 1))
+    const { q, s_q } = structure_factor_from_rdf({ r, g_r }, density, {
+      q_max: 8,
+      n_q: 40,
+      lorch: false,
+    })
+    expect(q).toHaveLength(40)
+    q.forEach((q_val, idx) => {
+      const q_sigma = q_val * sigma
+      const core = Math.sin(q_sigma) - q_sigma * Math.cos(q_sigma)
+      const exact = 1 - (4 * Math.PI * density * core) / q_val ** 3
+      expect(s_q[idx]).toBeCloseTo(exact, 4)
+    })
+    expect(() => structure_factor_from_rdf({ r, g_r }, 0)).toThrow(`density must be > 0`)
+  })
+})
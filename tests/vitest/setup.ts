@@ -498,6 +498,36 @@ export const cubic_matrix = (a: number): math.Matrix3x3 => [
   [0, 0, a],
 ]
 
+// Fractional coordinates of the conventional fcc cell
+export const FCC_SITES: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+
+// Conventional fcc cell of one element (cubic edge length or lattice matrix)
+export const make_fcc = (lattice: number | math.Matrix3x3, element: string): Crystal =>
+  make_crystal(lattice, FCC_SITES.map((abc): SimpleSite => [element, abc]))
+
+// Conventional diamond cell, or zincblende when `other` differs from `element`
+export const make_diamond = (a: number, element: string, other = element): Crystal =>
+  make_crystal(
+    a,
+    FCC_SITES.flatMap((abc): SimpleSite[] => [
+      [element, abc],
+      [other, math.add(abc, [0.25, 0.25, 0.25])],
+    ]),
+  )
+
+// Conventional rocksalt cell: fcc cations with anions shifted by (½, 0, 0), carrying
+// oxidation states ±charge
+export const make_rocksalt = (a: number, cation: string, anion: string, charge = 0): Crystal =>
+  make_crystal(a, [
+    ...FCC_SITES.map((abc): SimpleSite => [cation, abc, charge]),
+    ...FCC_SITES.map(([x, y, z]): SimpleSite => [anion, [(x + 0.5) % 1, y, z], -charge]),
+  ])
+
 // Encode a 3x3 matrix as a flat 9-array in COLUMN-major order — how moyo/nalgebra serialize
 // rotation matrices on the wire (inverse of mat3_from_flat_col_major in symmetry-elements).
 export const col_major = (mat: math.Matrix3x3): number[] => {
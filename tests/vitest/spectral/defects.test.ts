@@ -0,0 +1,119 @@
+import type { Defect, DefectEquilibrium, ElectronicDos } from '$lib/spectral'
+import { defect_equilibrium_vs_temperature, solve_defect_equilibrium } from '$lib/spectral'
+import { describe, expect, test } from 'vitest'
+
+const k_b = 8.617333262e-5 // eV/K
+const effective_masses = { electron: 1, hole: 1 }
+// 2·(2π·m_e·k_B·300 K / h²)^{3/2} in cm⁻³
+const n_c_300 = 2.5094122252280967e19
+
+const net_charge = ({ electrons, holes, defects }: DefectEquilibrium) =>
+  defects.reduce((sum, { charge, concentration }) => sum + charge * concentration, 0) +
+  holes -
+  electrons
+
+describe(`solve_defect_equilibrium`, () => {
+  test(`intrinsic semiconductor with equal masses sits at midgap`, () => {
+    const band_gap = 1.2
+    const options = { band_gap, volume: 40, effective_masses }
+    const result = solve_defect_equilibrium([], 300, options)
+    expect(result.fermi_level).toBeCloseTo(band_gap / 2, 8)
+    const n_i = n_c_300 * Math.exp(-band_gap / (2 * k_b * 300))
+    expect(result.electrons / n_i).toBeCloseTo(1, 6)
+    expect(result.holes / n_i).toBeCloseTo(1, 6)
+    expect(result.defects).toEqual([])
+  })
+
+  test(`donor compensated by electrons matches the analytic Fermi level`, () => {
+    // c_D = N·exp(−(E_0 + E_F)/kT) = n = N_c·exp(−(E_g − E_F)/kT)
+    // → E_F = (E_g − E_0)/2 + kT/2·ln(N/N_c), holes negligible
+    const donor: Defect = { name: `V_O`, charge_states: [{ charge: 1, formation_energy: -1 }] }
+    const [temperature, volume] = [1000, 40]
+    const result = solve_defect_equilibrium([donor], temperature, {
+      band_gap: 3,
+      volume,
+      effective_masses,
+    })
+    const n_sites = 1e24 / volume
+    const n_c = n_c_300 * (temperature / 300) ** 1.5
+    const expected = 2 + ((k_b * temperature) / 2) * Math.log(n_sites / n_c)
+    expect(result.fermi_level).toBeCloseTo(expected, 6)
+    expect(result.defects[0].concentration / result.electrons).toBeCloseTo(1, 6)
+    expect(result.holes / result.electrons).toBeLessThan(1e-6)
+  })
+
+  test(`charge states and degeneracies of amphoteric defects`, () => {
+    const defects: Defect[] = [
+      {
+        name: `Ga_i`,
+        sites_per_cell: 2,
+        charge_states: [
+          { charge: 0, formation_energy: 2.5, degeneracy: 2 },
+          { charge: 1, formation_energy: 1.8 },
+          { charge: -1, formation_energy: 3.4 },
+        ],
+      },
+    ]
+    const options = { band_gap: 2, volume: 60, effective_masses: { electron: 0.3, hole: 2 } }
+    const results = defect_equilibrium_vs_temperature(defects, [400, 800, 1200], options)
+    expect(results.map(({ temperature }) => temperature)).toEqual([400, 800, 1200])
+    for (const result of results) {
+      expect(result.defects.map(({ charge }) => charge)).toEqual([0, 1, -1])
+      const scale = result.defects.reduce(
+        (sum, { charge, concentration }) => sum + Math.abs(charge) * concentration,
+        result.holes + result.electrons,
+      )
+      expect(Math.abs(net_charge(result))).toBeLessThan(1e-6 * scale)
+      const [neutral, positive] = result.defects
+      const k_t = k_b * result.temperature
+      const ratio = Math.exp(-(1.8 + result.fermi_level - 2.5) / k_t) / 2
+      expect(positive.concentration / neutral.concentration / ratio).toBeCloseTo(1, 8)
+    }
+  })
+
+  test(`carriers from a DOS`, () => {
+    // flat 1 state/eV/cell bands below 0 and above the 1.5 eV gap
+    const band_gap = 1.5
+    const energies = Array.from({ length: 2301 }, (_, idx) => 0.005 * idx - 5)
+    const densities = energies.map((energy) =>
+      energy <= 1e-9 || energy >= band_gap - 1e-9 ? 1 : 0,
+    )
+    const dos: ElectronicDos = { type: `electronic`, energies, densities }
+    const [temperature, volume] = [1000, 50]
+    const result = solve_defect_equilibrium([], temperature, { band_gap, volume, dos })
+    expect(result.fermi_level).toBeCloseTo(band_gap / 2, 6)
+    // nondegenerate limit: n = ∫ exp(−(E − E_F)/kT) dE over the flat band = kT·exp(−E_g/2kT)
+    const k_t = k_b * temperature
+    const expected = (1e24 / volume) * k_t * Math.exp(-band_gap / (2 * k_t))
+    expect(result.electrons / expected).toBeCloseTo(1, 2)
+    expect(result.holes / result.electrons).toBeCloseTo(1, 6)
+
+    // spin channels are summed and the VBM can sit anywhere on the energy axis
+    const shifted: ElectronicDos = {
+      ...dos,
+      energies: energies.map((energy) => energy + 3),
+      spin_down_densities: densities,
+    }
+    const spin = solve_defect_equilibrium([], temperature, {
+      band_gap,
+      volume,
+      dos: shifted,
+      vbm: 3,
+    })
+    expect(spin.fermi_level).toBeCloseTo(band_gap / 2, 6)
+    expect(spin.electrons / result.electrons).toBeCloseTo(2, 6)
+  })
+
+  test(`invalid inputs throw`, () => {
+    expect(() => solve_defect_equilibrium([], 300, { band_gap: 1, volume: 40 })).toThrow(
+      `Either dos or effective_masses is needed for carrier densities`,
+    )
+    const options = { band_gap: 1, volume: 40, effective_masses }
+    expect(() => solve_defect_equilibrium([], 0, options)).toThrow(
+      `Temperature must be > 0 K, got 0`,
+    )
+    expect(() => solve_defect_equilibrium([], 300, { ...options, volume: 0 })).toThrow(
+      `Cell volume must be > 0 Å³, got 0`,
+    )
+  })
+})
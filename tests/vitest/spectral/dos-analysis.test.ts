@@ -0,0 +1,168 @@
+import {
+  band_moments,
+  dos_band_gap,
+  LM_ORBITALS,
+  parse_doscar,
+  parse_qe_dos,
+  projected_band_moments,
+  smear_dos,
+} from '$lib/spectral'
+import type { ElectronicDos } from '$lib/spectral'
+import { describe, expect, test } from 'vitest'
+
+const energies = [-4, -2, 0, 2, 4]
+const header = (n_ions: number, efermi: number) => [
+  `   ${n_ions}   ${n_ions}   1   0`,
+  `  0.1E+02  0.4E-09  0.4E-09  0.4E-09  0.5E-15`,
+  `  1.0E-04`,
+  `  CAR`,
+  ` unknown system`,
+  `      4.0     -4.0    ${energies.length}      ${efermi}      1.0`,
+]
+const rows = (columns: (energy: number, row: number) => number[]) =>
+  energies.map((energy, row) => [energy, ...columns(energy, row)].join(`  `))
+
+// 2 ions, no spin, l-decomposed (s, p, d) projections; site 1 has d states at −4 and −2 eV
+const doscar = [
+  ...header(2, 0.5),
+  ...rows((_, row) => [row + 1, 2 * row]),
+  header(2, 0.5)[5],
+  ...rows(() => [0.1, 0.2, 0.3]),
+  header(2, 0.5)[5],
+  ...rows((energy) => [0, 0, energy < 0 ? 2 : 0]),
+].join(`\n`)
+
+describe(`DOS parsing`, () => {
+  test(`non-spin-polarized DOSCAR with l-projections`, () => {
+    const data = parse_doscar(doscar)
+    expect(data.efermi).toBe(0.5)
+    expect(data.total).toMatchObject({ energies, densities: [1, 2, 3, 4, 5], efermi: 0.5 })
+    expect(data.total.spin_polarized).toBe(false)
+    expect(data.integrated).toEqual([0, 2, 4, 6, 8])
+    expect(data.integrated_down).toBeUndefined()
+    expect(data.pdos).toHaveLength(2)
+    expect(Object.keys(data.pdos[0])).toEqual([`s`, `p`, `d`])
+    expect(data.pdos[1].d.densities).toEqual([2, 2, 0, 0, 0])
+  })
+
+  test(`spin-polarized DOSCAR with lm-projections`, () => {
+    const text = [
+      ...header(1, 0),
+      ...rows((_, row) => [row, -row, 0, 0]),
+      header(1, 0)[5],
+      // up/down interleaved per orbital: orbital k has up = k, down = −k
+      ...rows(() => Array.from({ length: 9 }, (_, orb) => [orb, -orb]).flat()),
+    ].join(`\n`)
+    const data = parse_doscar(text)
+    expect(data.total.spin_polarized).toBe(true)
+    expect(data.total.spin_down_densities).toEqual([0, -1, -2, -3, -4])
+    expect(data.integrated_down).toEqual([0, 0, 0, 0, 0])
+    expect(Object.keys(data.pdos[0])).toEqual(LM_ORBITALS.slice(0, 9))
+    expect(data.pdos[0].dz2.densities[0]).toBe(6)
+    expect(data.pdos[0].dz2.spin_down_densities?.[0]).toBe(-6)
+  })
+
+  test(`DOSCAR without projections and invalid files`, () => {
+    const total_only = doscar.split(`\n`).slice(0, 11).join(`\n`)
+    expect(parse_doscar(total_only).pdos).toEqual([])
+    expect(() => parse_doscar(`garbage`)).toThrow(`Invalid DOSCAR header`)
+    const truncated = doscar.split(`\n`).slice(0, 9).join(`\n`)
+    expect(() => parse_doscar(truncated)).toThrow(`Truncated DOSCAR: expected 5 rows`)
+  })
+
+  test(`Quantum ESPRESSO dos.x output`, () => {
+    const text = [
+      `#  E (eV)   dos(E)     Int dos(E) EFermi =    6.123 eV`,
+      `  -1.000  0.1000E+00  0.1000E+00`,
+      `   0.000  0.2000E+00  0.3000E+00`,
+    ].join(`\n`)
+    expect(parse_qe_dos(text)).toMatchObject({
+      energies: [-1, 0],
+      densities: [0.1, 0.2],
+      efermi: 6.123,
+      spin_polarized: false,
+    })
+    const spin = [`#  E (eV)  dosup(E)  dosdw(E)  Int dos(E) EFermi = -1.5 eV`, `0 1 2 3`]
+    const spin_dos = parse_qe_dos(spin.join(`\n`))
+    expect(spin_dos).toMatchObject({ spin_down_densities: [2], efermi: -1.5 })
+    expect(() => parse_qe_dos(`# no data`)).toThrow(`Invalid dos.x file`)
+  })
+})
+
+describe(`smearing`, () => {
+  // unit-area spike at 0 eV on a 0.01 eV grid
+  const grid = Array.from({ length: 1001 }, (_, idx) => -5 + 0.01 * idx)
+  const spike = grid.map((_, idx) => (idx === 500 ? 100 : 0))
+  const area = (dens: number[]) => dens.reduce((sum, val) => sum + val, 0) * 0.01
+
+  test(`Methfessel-Paxton order 0 is a Gaussian of standard deviation σ/√2`, () => {
+    const sigma = 0.2
+    const mp_0 = smear_dos(grid, spike, sigma, `methfessel_paxton`, 0)
+    const gaussian = smear_dos(grid, spike, sigma / Math.SQRT2)
+    expect(mp_0[500]).toBeCloseTo(1 / (sigma * Math.sqrt(Math.PI)), 10)
+    for (const idx of [450, 500, 530]) expect(mp_0[idx]).toBeCloseTo(gaussian[idx], 3)
+  })
+
+  test(`higher Methfessel-Paxton orders conserve the area but go negative`, () => {
+    const mp_1 = smear_dos(grid, spike, 0.2, `methfessel_paxton`)
+    expect(area(mp_1)).toBeCloseTo(1, 10)
+    // δ_1(0) = 3 / (2σ√π)
+    expect(mp_1[500]).toBeCloseTo(1.5 / (0.2 * Math.sqrt(Math.PI)), 10)
+    expect(Math.min(...mp_1)).toBeCloseTo(-0.231, 3)
+    expect(area(smear_dos(grid, spike, 0.2))).toBeCloseTo(1, 10)
+    expect(smear_dos(grid, spike, 0, `methfessel_paxton`)).toBe(spike)
+  })
+})
+
+describe(`band gap and band moments`, () => {
+  const grid = Array.from({ length: 101 }, (_, idx) => -5 + 0.1 * idx)
+  // valence band up to 0 eV, conduction band from 1.2 eV
+  const semiconductor: ElectronicDos = {
+    type: `electronic`,
+    energies: grid,
+    densities: grid.map((energy) => (energy <= 1e-9 ? 0.5 : 0)),
+    spin_down_densities: grid.map((energy) => (energy >= 1.2 - 1e-9 ? 0.5 : 0)),
+    efermi: 0.3,
+  }
+
+  test(`gap between the band edges around E_F`, () => {
+    const gap = dos_band_gap(semiconductor)
+    expect(gap.vbm).toBeCloseTo(0, 10)
+    expect(gap.cbm).toBeCloseTo(1.2, 10)
+    expect(gap.band_gap).toBeCloseTo(1.2, 10)
+    expect(gap.is_metal).toBe(false)
+    // E_F inside the valence band makes it metallic
+    const metal = dos_band_gap(semiconductor, { efermi: -1 })
+    expect(metal).toMatchObject({ band_gap: 0, is_metal: true })
+    expect(() => dos_band_gap(semiconductor, { efermi: -10 })).toThrow(
+      `DOS has no states above 0.001 on both sides of E_F = -10`,
+    )
+  })
+
+  test(`center, width and filling of a flat band`, () => {
+    const window = { e_min: -4 - 1e-9, e_max: -2 + 1e-9 }
+    const densities = grid.map((energy) =>
+      energy >= window.e_min && energy <= window.e_max ? 1 : 0,
+    )
+    const moments = band_moments(grid, densities, { ...window, efermi: -3 + 1e-9 })
+    expect(moments.center).toBeCloseTo(-3, 10)
+    // uniform band of width 2 eV: standard deviation 2 / √12 up to trapezoid error
+    expect(moments.width).toBeCloseTo(2 / Math.sqrt(12), 2)
+    expect(moments.filling).toBeCloseTo(0.5, 10)
+    expect(band_moments(grid, densities).filling).toBeNull()
+    expect(() => band_moments(grid, densities, { e_min: 1 })).toThrow(
+      `No states in the energy window`,
+    )
+  })
+
+  test(`d-band center of selected sites relative to E_F`, () => {
+    const data = parse_doscar(doscar)
+    // site 1 d states at −4 and −2 eV (−4.5 and −2.5 relative to E_F = 0.5)
+    const d_band = projected_band_moments(data, [1])
+    expect(d_band.center).toBeCloseTo(-3.5 + 1 / 3, 10)
+    expect(d_band.filling).toBeCloseTo(1, 10)
+    // s states on site 0 are uniform over the whole grid
+    expect(projected_band_moments(data, [0], `s`).center).toBeCloseTo(-0.5, 10)
+    expect(() => projected_band_moments(data, [5])).toThrow(`No projected DOS for site 5`)
+  })
+})
@@ -0,0 +1,131 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import {
+  calc_effective_masses,
+  find_band_edges,
+  HBAR2_OVER_2ME,
+  parse_eigenval,
+} from '$lib/spectral'
+import { describe, expect, test } from 'vitest'
+import { cubic_matrix } from '../setup'
+
+const eigenval_text = (
+  ispin: 1 | 2,
+  n_electrons: number,
+  blocks: { kpt: Vec3; bands: number[][] }[],
+) =>
+  [
+    `    2    2    1    ${ispin}`,
+    `  0.1250000E+03  0.5000000E-09  0.5000000E-09  0.5000000E-09  0.5000000E-15`,
+    `  1.000000000000000E-004`,
+    `  CAR`,
+    ` unknown system`,
+    `     ${n_electrons}     ${blocks.length}     ${blocks[0].bands.length}`,
+    ...blocks.flatMap(({ kpt, bands }) => [
+      ``,
+      `  ${[...kpt, 1 / blocks.length].map((val) => val.toExponential(7)).join(`  `)}`,
+      ...bands.map((cols, idx) =>
+        [`   `, idx + 1, ...cols.map((val) => val.toFixed(10))].join(`  `),
+      ),
+    ]),
+  ].join(`\n`)
+
+// cubic a = 5 Å, k-points along Γ-X and Γ-Y with parabolic bands: hole mass −1 and an
+// anisotropic conduction band (m_x = 0.5, m_y = 2) 1.5 eV above the VBM at Γ
+const lattice: Matrix3x3 = cubic_matrix(5)
+const k_cart = (frac: number) => (2 * Math.PI * frac) / 5
+const line_kpts: Vec3[] = [
+  ...Array.from({ length: 11 }, (_, idx): Vec3 => [0.02 * idx, 0, 0]),
+  ...Array.from({ length: 10 }, (_, idx): Vec3 => [0, 0.02 * (idx + 1), 0]),
+]
+const parabolic = parse_eigenval(
+  eigenval_text(
+    1,
+    2,
+    line_kpts.map((kpt) => {
+      const [k_x, k_y] = [k_cart(kpt[0]), k_cart(kpt[1])]
+      const valence = -HBAR2_OVER_2ME * (k_x ** 2 + k_y ** 2)
+      const conduction = 1.5 + HBAR2_OVER_2ME * (k_x ** 2 / 0.5 + k_y ** 2 / 2)
+      return { kpt, bands: [[valence, 1], [conduction, 0]] }
+    }),
+  ),
+)
+
+describe(`parse_eigenval`, () => {
+  test(`reads header, k-points, eigenvalues and occupations`, () => {
+    expect(parabolic).toMatchObject({ n_electrons: 2, n_kpoints: 21, n_bands: 2, n_spins: 1 })
+    expect(parabolic.kpoints[3]).toEqual([0.06, 0, 0])
+    expect(parabolic.weights[0]).toBeCloseTo(1 / 21, 7)
+    expect(parabolic.eigenvalues[0][0]).toEqual([0, 1.5])
+    expect(parabolic.occupations?.[0][0]).toEqual([1, 0])
+  })
+
+  test(`truncated files throw`, () => {
+    const text = eigenval_text(1, 2, [{ kpt: [0, 0, 0], bands: [[0, 1], [1, 0]] }])
+    expect(() => parse_eigenval(text.split(`\n`).slice(0, -1).join(`\n`))).toThrow(
+      `Truncated EIGENVAL: expected 3 data lines, got 2`,
+    )
+    expect(() => parse_eigenval(`not an EIGENVAL`)).toThrow(`Invalid EIGENVAL header`)
+  })
+})
+
+describe(`band edges and effective masses`, () => {
+  test(`direct gap at Γ`, () => {
+    const edges = find_band_edges(parabolic)
+    expect(edges.vbm).toEqual({ energy: 0, spin: 0, kpoint_idx: 0, band_idx: 0 })
+    expect(edges.cbm).toEqual({ energy: 1.5, spin: 0, kpoint_idx: 0, band_idx: 1 })
+    expect(edges).toMatchObject({ band_gap: 1.5, is_direct: true, is_metal: false })
+  })
+
+  test(`parabolic masses along the path directions`, () => {
+    const { vbm, cbm } = find_band_edges(parabolic)
+    const electrons = calc_effective_masses(parabolic, cbm, lattice)
+    expect(electrons).toHaveLength(2)
+    expect(electrons[0].direction[0]).toBeCloseTo(1, 12)
+    expect(electrons[1].direction[1]).toBeCloseTo(1, 12)
+    expect(electrons[0].mass).toBeCloseTo(0.5, 6)
+    expect(electrons[1].mass).toBeCloseTo(2, 6)
+    // 3 k-points per direction within 0.1 1/Å of Γ plus Γ itself
+    expect(electrons.map(({ n_points }) => n_points)).toEqual([4, 4])
+    for (const { mass } of calc_effective_masses(parabolic, vbm, lattice)) {
+      expect(mass).toBeCloseTo(-1, 6)
+    }
+  })
+
+  test(`requested directions`, () => {
+    const { cbm } = find_band_edges(parabolic)
+    const minus_y = { directions: [[0, -1, 0]] as Vec3[] }
+    const along_y = calc_effective_masses(parabolic, cbm, lattice, minus_y)
+    expect(along_y).toHaveLength(1)
+    expect(along_y[0].mass).toBeCloseTo(2, 6)
+    // no k-points along the diagonal
+    const diagonal = { directions: [[1, 1, 0]] as Vec3[] }
+    expect(calc_effective_masses(parabolic, cbm, lattice, diagonal)).toEqual([])
+  })
+
+  test(`spin-polarized file without occupations has an indirect gap`, () => {
+    const data = parse_eigenval(
+      eigenval_text(2, 2, [
+        { kpt: [0, 0, 0], bands: [[-1, -1.2], [1, 0.9]] },
+        { kpt: [0.5, 0, 0], bands: [[-1.5, -1.1], [0.8, 1.1]] },
+      ]),
+    )
+    expect(data.n_spins).toBe(2)
+    expect(data.occupations).toBeNull()
+    expect(data.eigenvalues[1][1]).toEqual([-1.1, 1.1])
+    const edges = find_band_edges(data)
+    expect(edges.vbm).toMatchObject({ energy: -1, spin: 0, kpoint_idx: 0 })
+    expect(edges.cbm).toMatchObject({ energy: 0.8, spin: 0, kpoint_idx: 1 })
+    expect(edges.band_gap).toBeCloseTo(1.8, 10)
+    expect(edges).toMatchObject({ is_direct: false, is_metal: false })
+  })
+
+  test(`partially occupied band is metallic`, () => {
+    const data = parse_eigenval(
+      eigenval_text(1, 2, [
+        { kpt: [0, 0, 0], bands: [[-1, 1], [1, 0]] },
+        { kpt: [0.5, 0, 0], bands: [[0.5, 0], [-0.2, 1]] },
+      ]),
+    )
+    expect(find_band_edges(data)).toMatchObject({ band_gap: 0, is_metal: true })
+  })
+})
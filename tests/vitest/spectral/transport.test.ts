@@ -0,0 +1,161 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { EigenvalData } from '$lib/spectral'
+import {
+  calc_transport,
+  evaluate_bands,
+  interpolate_bands,
+  onsager_coefficients,
+  transport_distribution,
+  transport_fermi_level,
+} from '$lib/spectral'
+import { describe, expect, test } from 'vitest'
+import { cubic_matrix } from '../setup'
+
+const HBAR_EV_S = 6.582119569e-16
+
+// nearest-neighbor tight-binding s band of a simple cubic crystal (a = 3 Å, t = 1 eV):
+// E(k) = −2t Σ cos(2π k_i), spanning [−6, 6] eV and particle-hole symmetric around 0
+const lattice = cubic_matrix(3)
+const tight_binding = (kpt: Vec3) =>
+  -2 * kpt.reduce((sum, val) => sum + Math.cos(2 * Math.PI * val), 0)
+const grid = (size: number): Vec3[] =>
+  Array.from({ length: size ** 3 }, (_, idx): Vec3 => [
+    Math.floor(idx / size ** 2) / size,
+    (Math.floor(idx / size) % size) / size,
+    (idx % size) / size,
+  ])
+
+const make_data = (kpoints: Vec3[], shifts: number[], n_electrons: number): EigenvalData => ({
+  n_electrons,
+  n_kpoints: kpoints.length,
+  n_bands: shifts.length,
+  n_spins: 1,
+  kpoints,
+  weights: kpoints.map(() => 1 / kpoints.length),
+  eigenvalues: [kpoints.map((kpt) => shifts.map((shift) => tight_binding(kpt) + shift))],
+  occupations: null,
+})
+
+// 48 signed permutation matrices of the cubic point group
+const cubic_rotations: Matrix3x3[] = [
+  [0, 1, 2],
+  [0, 2, 1],
+  [1, 0, 2],
+  [1, 2, 0],
+  [2, 0, 1],
+  [2, 1, 0],
+].flatMap((perm) =>
+  Array.from(
+    { length: 8 },
+    (_, signs) =>
+      perm.map((col, row) =>
+        [0, 1, 2].map((idx) => (idx === col ? (signs & (1 << row) ? -1 : 1) : 0)),
+      ) as Matrix3x3,
+  ),
+)
+
+const half_filled = make_data(grid(6), [0], 1) // one electron in a spin-degenerate band
+const interp = interpolate_bands(half_filled, lattice)
+const off_grid: Vec3[] = [
+  [0.1, 0.23, 0.37],
+  [0.05, 0, 0],
+  [0.31, -0.12, 0.44],
+]
+
+describe(`interpolate_bands`, () => {
+  test(`passes through the data and reproduces the band between grid points`, () => {
+    const at_data = evaluate_bands(interp, half_filled.kpoints)
+    at_data.energies[0].forEach(([energy], idx) =>
+      expect(energy).toBeCloseTo(half_filled.eigenvalues[0][idx][0], 8),
+    )
+    const { energies, velocities } = evaluate_bands(interp, off_grid)
+    off_grid.forEach((kpt, idx) => {
+      expect(energies[0][idx][0]).toBeCloseTo(tight_binding(kpt), 2)
+      // ħv = ∂E/∂k = 2ta sin(2πk) in eV·Å
+      velocities[0][idx][0].forEach((vel, axis) => {
+        const expected = 2 * 3 * Math.sin(2 * Math.PI * kpt[axis])
+        expect((vel * HBAR_EV_S) / 1e-10).toBeCloseTo(expected, 1)
+      })
+    })
+  })
+
+  test(`point-group rotations reduce the k-points without changing the fit`, () => {
+    const reduced = interpolate_bands(half_filled, lattice, { rotations: cubic_rotations })
+    expect(reduced.stars).toHaveLength(100) // 20 irreducible points of the 6×6×6 grid
+    expect(reduced.stars[1]).toHaveLength(6) // (±1, 0, 0) and permutations
+    const { energies } = evaluate_bands(reduced, off_grid)
+    off_grid.forEach((kpt, idx) =>
+      expect(energies[0][idx][0]).toBeCloseTo(tight_binding(kpt), 2),
+    )
+  })
+
+  test(`energy_range drops bands and counts the filled ones`, () => {
+    const three_bands = make_data(grid(4), [-20, 0, 20], 3)
+    const windowed = interpolate_bands(three_bands, lattice, { energy_range: [-10, 10] })
+    expect(windowed.band_indices).toEqual([[1]])
+    expect(windowed.coefficients[0]).toHaveLength(1)
+    expect(windowed.n_electrons).toBe(1)
+  })
+
+  test(`rejects rotations that do not fit the lattice and too few k-points`, () => {
+    const tetragonal: Matrix3x3 = [
+      [3, 0, 0],
+      [0, 3, 0],
+      [0, 0, 5],
+    ]
+    const swap_xz: Matrix3x3 = [
+      [0, 0, 1],
+      [0, 1, 0],
+      [1, 0, 0],
+    ]
+    expect(() => interpolate_bands(half_filled, tetragonal, { rotations: [swap_xz] })).toThrow(
+      `Rotations do not preserve the lattice metric`,
+    )
+    const gamma_only = make_data([[0, 0, 0]], [0], 1)
+    expect(() => interpolate_bands(gamma_only, lattice)).toThrow(`at least 2`)
+  })
+})
+
+describe(`transport`, () => {
+  const dist = transport_distribution(interp, { n_grid: [24, 24, 24] })
+
+  test(`density of states integrates to the band capacity`, () => {
+    const n_states = dist.dos.reduce((sum, val) => sum + val * dist.energy_step, 0)
+    expect(n_states).toBeCloseTo(2, 10)
+    expect(dist.volume).toBeCloseTo(27, 10)
+    expect(transport_fermi_level(dist)).toBeCloseTo(0, 2)
+  })
+
+  test(`cubic conductivity is isotropic and Seebeck changes sign with carrier type`, () => {
+    const [lower, upper] = [-1, 1].map((mu) => onsager_coefficients(dist, mu, 600))
+    for (const point of [lower, upper]) {
+      const { conductivity_over_tau: sigma } = point
+      expect(sigma[0][0]).toBeGreaterThan(1.02e21)
+      expect(sigma[0][0]).toBeLessThan(1.14e21)
+      expect(sigma[1][1] / sigma[0][0]).toBeCloseTo(1, 2)
+      expect(sigma[2][2] / sigma[0][0]).toBeCloseTo(1, 2)
+      expect(Math.abs(sigma[0][1] / sigma[0][0])).toBeLessThan(1e-3)
+    }
+    // fewer electrons than at half filling below the band center, more above
+    expect(lower.n_carriers).toBeCloseTo(-0.2875, 2)
+    expect(upper.n_carriers).toBeCloseTo(0.2875, 2)
+    expect(upper.carrier_density).toBe((upper.n_carriers / 27) * 1e24)
+    // but the states are electron-like below the band center and hole-like above
+    expect(lower.seebeck[0][0]).toBeLessThan(-12)
+    expect(upper.seebeck[0][0]).toBeGreaterThan(12)
+    expect(Math.abs(lower.seebeck[0][0] + upper.seebeck[0][0])).toBeLessThan(4)
+  })
+
+  test(`calc_transport scans temperatures and chemical potentials`, () => {
+    const points = calc_transport(dist, { temperatures: [300, 600] })
+    expect(points).toHaveLength(162)
+    expect(points.map(({ temperature }) => temperature)).toEqual([
+      ...Array(81).fill(300),
+      ...Array(81).fill(600),
+    ])
+    expect(points[1].mu - points[0].mu).toBeCloseTo(0.025, 10)
+    expect(points[40].mu).toBeCloseTo(0, 2)
+    expect(() => onsager_coefficients(dist, 0, 0)).toThrow(`temperature must be > 0`)
+    expect(() => transport_distribution(interp, { n_grid: [0, 4, 4] })).toThrow(`n_grid`)
+  })
+})
@@ -0,0 +1,78 @@
+import {
+  incomplete_beta,
+  linear_fit,
+  log_gamma,
+  student_t_cdf,
+  student_t_quantile,
+  theil_sen,
+} from '$lib/stats'
+import { describe, expect, test } from 'vitest'
+
+const xs = [0, 1, 2, 3, 4]
+const outlier = [1, 3, 5, 40, 9] // y = 2x + 1 except at x = 3
+
+describe(`linear_fit`, () => {
+  test(`slope, intercept and standard errors of a noisy line`, () => {
+    const fit = linear_fit(xs, [1.1, 2.9, 5, 7.1, 8.9])
+    expect(fit.slope).toBeCloseTo(1.98, 12)
+    expect(fit.intercept).toBeCloseTo(1.04, 12)
+    // s² = SSR / (n − 2) = 0.012, σ_slope = √(s² / Sxx)
+    // and σ_intercept = √(s² (1/n + x̄²/Sxx)) with Sxx = 10, x̄ = 2
+    expect(fit.slope_err).toBeCloseTo(Math.sqrt(0.012 / 10), 12)
+    expect(fit.intercept_err).toBeCloseTo(Math.sqrt(0.012 * (1 / 5 + 4 / 10)), 12)
+    expect(fit.r_squared).toBeGreaterThan(0.999)
+    expect(fit.n_points).toBe(5)
+  })
+
+  test(`zero weights drop points and degenerate inputs`, () => {
+    const fit = linear_fit(xs, outlier, [1, 1, 1, 0, 1])
+    expect(fit.slope).toBeCloseTo(2, 12)
+    expect(fit.intercept).toBeCloseTo(1, 12)
+    expect(fit.slope_err).toBeCloseTo(0, 12)
+    // two points fit exactly but leave no degrees of freedom for errors
+    const two = linear_fit([0, 1], [0, 2])
+    expect([two.slope, two.slope_err]).toEqual([2, NaN])
+    expect(linear_fit([1, 1, 1], [0, 1, 2]).slope).toBeNaN()
+    expect(() => linear_fit([0], [0])).toThrow(`at least 2 points`)
+    expect(() => linear_fit(xs, outlier, [1, 1])).toThrow(`weights must match`)
+    expect(() => linear_fit(xs, outlier, [1, 1, 1, -1, 1])).toThrow(`non-negative`)
+  })
+})
+
+describe(`theil_sen`, () => {
+  test(`median pairwise slope ignores an outlier`, () => {
+    expect(linear_fit(xs, outlier).slope).toBeCloseTo(5.3, 12)
+    const fit = theil_sen(xs, outlier)
+    expect(fit.slope).toBe(2)
+    expect(fit.intercept).toBe(1)
+    expect(fit.slope_range).toEqual([2, 13])
+    expect(() => theil_sen([1, 1], [0, 1])).toThrow(`distinct x values`)
+  })
+})
+
+describe(`Student-t distribution`, () => {
+  test(`special functions match closed forms`, () => {
+    expect(log_gamma(5)).toBeCloseTo(Math.log(24), 12)
+    expect(log_gamma(0.5)).toBeCloseTo(0.5 * Math.log(Math.PI), 12)
+    // I_x(2, 3) = 1 − (1 − x)⁴ − 4x (1 − x)³
+    expect(incomplete_beta(0.3, 2, 3)).toBeCloseTo(1 - 0.7 ** 4 - 4 * 0.3 * 0.7 ** 3, 12)
+    // Cauchy distribution for 1 degree of freedom
+    expect(student_t_cdf(1, 1)).toBeCloseTo(0.75, 12)
+    expect(student_t_cdf(0, 4)).toBe(0.5)
+  })
+
+  test.each([
+    [0.975, 1, 12.706204736],
+    [0.975, 3, 3.182446305],
+    [0.975, 10, 2.228138852],
+    [0.95, 30, 1.697260887],
+    [0.025, 3, -3.182446305],
+  ])(`quantile at p=%s with %s dof`, (prob, dof, expected) => {
+    expect(student_t_quantile(prob, dof)).toBeCloseTo(expected, 8)
+  })
+
+  test(`rejects invalid arguments`, () => {
+    expect(() => student_t_quantile(1, 3)).toThrow(`prob must be in (0, 1)`)
+    expect(() => student_t_cdf(1, 0)).toThrow(`dof must be > 0`)
+  })
+})
@@ -0,0 +1,62 @@
+import type { Matrix3x3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AdpCif } from '$lib/structure'
+import { get_site_u_cart, u_cart_to_cif, u_cif_to_cart, u_equiv } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const monoclinic = math.cell_to_lattice_matrix(4.2, 5.1, 6.3, 90, 103.5, 90)
+const triclinic = math.cell_to_lattice_matrix(3.9, 4.7, 5.6, 78, 95, 112)
+
+describe(`ADP conversions`, () => {
+  test(`isotropic U is unchanged by the CIF convention in a cubic cell`, () => {
+    const u_cart: Matrix3x3 = [
+      [0.02, 0, 0],
+      [0, 0.02, 0],
+      [0, 0, 0.02],
+    ]
+    const u_cif = u_cart_to_cif(u_cart, math.cell_to_lattice_matrix(4, 4, 4, 90, 90, 90))
+    u_cif.forEach((val, idx) => expect(val).toBeCloseTo(idx < 3 ? 0.02 : 0, 12))
+  })
+
+  test.each([
+    [`monoclinic`, monoclinic],
+    [`triclinic`, triclinic],
+  ])(`CIF <-> Cartesian round trip in a %s cell`, (_name, lattice) => {
+    const u_cif: AdpCif = [0.011, 0.017, 0.023, 0.002, -0.003, 0.004]
+    const u_cart = u_cif_to_cart(u_cif, lattice)
+    expect(u_cart[0][1]).toBeCloseTo(u_cart[1][0], 12) // stays symmetric
+    const round_trip = u_cart_to_cif(u_cart, lattice)
+    round_trip.forEach((val, idx) => expect(val).toBeCloseTo(u_cif[idx], 12))
+  })
+
+  test(`U_eq is the trace average and invariant for isotropic U in oblique cells`, () => {
+    const u_iso = 0.015
+    // Isotropic U in CIF convention is not diagonal for oblique cells: U_ij = U cos(angle)
+    const u_cif = u_cart_to_cif(
+      [
+        [u_iso, 0, 0],
+        [0, u_iso, 0],
+        [0, 0, u_iso],
+      ],
+      monoclinic,
+    )
+    expect(u_cif[0]).toBeCloseTo(u_iso, 12)
+    expect(u_cif[4]).toBeCloseTo(u_iso * Math.cos(math.to_radians(180 - 103.5)), 12)
+    expect(u_equiv(u_cif_to_cart(u_cif, monoclinic))).toBeCloseTo(u_iso, 12)
+  })
+
+  test(`get_site_u_cart reads valid tensors and ignores malformed ones`, () => {
+    const u_cart: Matrix3x3 = [
+      [0.01, 0, 0],
+      [0, 0.02, 0],
+      [0, 0, 0.03],
+    ]
+    const structure = make_crystal(4, [
+      { element: `Cu`, abc: [0, 0, 0], properties: { u_cart } },
+      { element: `Cu`, abc: [0.5, 0.5, 0], properties: { u_cart: [0.01, 0.02] } },
+      [`Cu`, [0.5, 0, 0.5]],
+    ])
+    expect(structure.sites.map(get_site_u_cart)).toEqual([u_cart, null, null])
+  })
+})
@@ -0,0 +1,117 @@
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import { identify_adsorbates } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// 3x3 Pt(111) slab with 3 ABC-stacked layers (nearest-neighbor distance 2.77 Å)
+const [a_nn, layer_gap] = [2.77, 2.27]
+const top_z = 2 * layer_gap
+const lattice: Matrix3x3 = [
+  [3 * a_nn, 0, 0],
+  [-1.5 * a_nn, 1.5 * Math.sqrt(3) * a_nn, 0],
+  [0, 0, 20],
+]
+// Cartesian position from primitive surface coordinates [u, v] and height z
+const at = (uv: [number, number], z: number): Vec3 => [
+  a_nn * (uv[0] - uv[1] / 2),
+  (a_nn * Math.sqrt(3) * uv[1]) / 2,
+  z,
+]
+// stacking offsets of the bottom (C), middle (B) and top (A) layer
+const layer_offsets: [number, number][] = [[1 / 3, 2 / 3], [2 / 3, 1 / 3], [0, 0]]
+const slab_sites = layer_offsets.flatMap(([du, dv], layer) =>
+  [0, 1, 2].flatMap((ii) =>
+    [0, 1, 2].map((jj) => ({
+      element: `Pt`,
+      xyz: at([ii + du, jj + dv], layer * layer_gap),
+    })),
+  ),
+)
+const slab_with = (adsorbates: [ElementSymbol, [number, number], number][]) =>
+  make_crystal(lattice, [
+    ...slab_sites,
+    ...adsorbates.map(([element, uv, dz]) => ({ element, xyz: at(uv, top_z + dz) })),
+  ])
+
+describe(`identify_adsorbates`, () => {
+  test(`CO on top and H in fcc and hcp hollows`, () => {
+    const structure = slab_with([
+      [`C`, [0, 0], 1.85],
+      [`O`, [0, 0], 3],
+      [`H`, [1 / 3, 2 / 3], 1],
+      [`H`, [5 / 3, 4 / 3], 1],
+    ])
+    const analysis = identify_adsorbates(structure)
+    expect(analysis.normal[2]).toBeCloseTo(1, 10)
+    expect(analysis.substrate_indices).toEqual([...Array(27).keys()])
+    expect(analysis.surface_indices).toEqual([...Array(9).keys()].map((idx) => idx + 18))
+    expect(analysis.surface_height).toBeCloseTo(top_z, 10)
+
+    const [co, h_fcc, h_hcp] = analysis.adsorbates
+    expect(analysis.adsorbates).toHaveLength(3)
+    expect(co).toMatchObject({
+      site_indices: [27, 28],
+      composition: { C: 1, O: 1 },
+      formula: `CO`,
+      binding_indices: [27],
+      surface_indices: [18],
+      site_type: `top`,
+    })
+    expect(co.height).toBeCloseTo(1.85, 10)
+    expect(h_fcc).toMatchObject({ site_indices: [29], formula: `H`, site_type: `fcc` })
+    expect(h_fcc.surface_indices).toHaveLength(3)
+    expect(h_fcc.height).toBeCloseTo(1, 10)
+    expect(h_hcp).toMatchObject({ site_indices: [30], site_type: `hcp` })
+  })
+
+  test(`bridge site and physisorbed molecule`, () => {
+    const structure = slab_with([
+      [`H`, [0.5, 0], 1.2],
+      [`O`, [2, 2], 4],
+      [`H`, [2.27, 2], 4.6],
+      [`H`, [1.73, 2], 4.6],
+    ])
+    const [h_bridge, water] = identify_adsorbates(structure).adsorbates
+    expect(h_bridge).toMatchObject({ site_type: `bridge`, surface_indices: [18, 21] })
+    expect(water).toMatchObject({
+      site_indices: [28, 29, 30],
+      formula: `H2O`,
+      binding_indices: [],
+      surface_indices: [],
+      site_type: null,
+    })
+    expect(water.height).toBeCloseTo(4, 10)
+  })
+
+  test(`sparse top layer of adatoms counts as adsorbate`, () => {
+    const structure = slab_with([
+      [`Pt`, [1 / 3, 2 / 3], layer_gap],
+      [`Pt`, [4 / 3, 2 / 3], layer_gap],
+    ])
+    const analysis = identify_adsorbates(structure)
+    expect(analysis.substrate_indices).toHaveLength(27)
+    expect(analysis.adsorbates).toHaveLength(1)
+    expect(analysis.adsorbates[0]).toMatchObject({
+      site_indices: [27, 28],
+      formula: `Pt2`,
+      binding_indices: [27, 28],
+      site_type: `fcc`,
+    })
+    expect(analysis.adsorbates[0].height).toBeCloseTo(layer_gap, 10)
+    // disabling adatom detection keeps the dimer in the substrate
+    const kept = identify_adsorbates(structure, { adatom_fraction: 0 })
+    expect(kept.adsorbates).toEqual([])
+    expect(kept.surface_indices).toEqual([27, 28])
+  })
+
+  test(`empty structure`, () => {
+    const analysis = identify_adsorbates(make_crystal(lattice, []))
+    expect(analysis).toMatchObject({
+      substrate_indices: [],
+      surface_indices: [],
+      surface_height: 0,
+      adsorbates: [],
+    })
+  })
+})
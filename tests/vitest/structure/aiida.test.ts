@@ -0,0 +1,87 @@
+import type { AiidaStructureData } from '$lib/structure'
+import { from_aiida_structure, to_aiida_structure } from '$lib/structure'
+import { parse_structure_file } from '$lib/structure/parse'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const alloy: AiidaStructureData = {
+  cell: [
+    [3.6, 0, 0],
+    [0, 3.6, 0],
+    [0, 0, 3.6],
+  ],
+  pbc1: true,
+  pbc2: true,
+  pbc3: true,
+  kinds: [
+    { name: `Cu`, symbols: [`Cu`], weights: [1], mass: 63.546 },
+    { name: `AuCu`, symbols: [`Au`, `Cu`], weights: [0.5, 0.5], mass: 130.2564 },
+    { name: `D`, symbols: [`H`], weights: [1], mass: 2.014 },
+  ],
+  sites: [
+    { kind_name: `Cu`, position: [0, 0, 0] },
+    { kind_name: `AuCu`, position: [1.8, 1.8, 0] },
+    { kind_name: `D`, position: [1.8, 0, 1.8] },
+  ],
+}
+
+describe(`AiiDA StructureData interop`, () => {
+  test(`reads kinds as species and labels`, () => {
+    const structure = from_aiida_structure(alloy)
+    if (!(`lattice` in structure)) throw new Error(`expected a crystal`)
+    expect(structure.lattice.pbc).toEqual([true, true, true])
+    expect(structure.sites.map(({ label }) => label)).toEqual([`Cu`, `AuCu`, `D`])
+    expect(structure.sites[1].species.map(({ element, occu }) => [element, occu])).toEqual([
+      [`Au`, 0.5],
+      [`Cu`, 0.5],
+    ])
+    expect(structure.sites[1].abc).toEqual([0.5, 0.5, 0])
+    // standard masses are dropped, isotopes kept
+    expect(structure.sites[0].properties.mass).toBeUndefined()
+    expect(structure.sites[2].properties.mass).toBe(2.014)
+  })
+
+  test(`round-trips through to_aiida_structure`, () => {
+    const attributes = to_aiida_structure(from_aiida_structure(alloy))
+    expect(attributes.cell).toEqual(alloy.cell)
+    expect(attributes.kinds).toEqual([
+      { name: `Cu`, symbols: [`Cu`], weights: [1] },
+      { name: `AuCu`, symbols: [`Au`, `Cu`], weights: [0.5, 0.5] },
+      { name: `H`, symbols: [`H`], weights: [1], mass: 2.014 },
+    ])
+    expect(attributes.sites.map(({ kind_name }) => kind_name)).toEqual([`Cu`, `AuCu`, `H`])
+    expect(attributes.sites[1].position).toEqual([1.8, 1.8, 0])
+  })
+
+  test(`same elements with other weights get numbered kinds`, () => {
+    const crystal = make_crystal(4, [
+      { element: `Li`, abc: [0, 0, 0] },
+      { element: `Li`, abc: [0.5, 0.5, 0.5], occu: 0.5 },
+    ])
+    const { kinds, pbc1 } = to_aiida_structure(crystal)
+    expect(kinds.map(({ name, weights }) => [name, weights])).toEqual([
+      [`Li`, [1]],
+      [`Li2`, [0.5]],
+    ])
+    expect(pbc1).toBe(true)
+  })
+
+  test(`molecules get a padded box without periodicity`, () => {
+    const molecule = { sites: make_crystal(4, [[`O`, [0, 0, 0]], [`H`, [0.25, 0, 0]]]).sites }
+    const attributes = to_aiida_structure(molecule)
+    expect(attributes.cell).toEqual([
+      [11, 0, 0],
+      [0, 10, 0],
+      [0, 0, 10],
+    ])
+    expect([attributes.pbc1, attributes.pbc2, attributes.pbc3]).toEqual([false, false, false])
+    expect(`lattice` in from_aiida_structure(attributes)).toBe(false)
+  })
+
+  test(`parses AiiDA JSON exports and rejects unknown kinds`, () => {
+    const structure = parse_structure_file(JSON.stringify({ attributes: alloy }), `node.json`)
+    expect(structure.sites).toHaveLength(3)
+    const broken = { ...alloy, sites: [{ kind_name: `Zr`, position: [0, 0, 0] }] }
+    expect(() => from_aiida_structure(broken)).toThrow(`unknown kind Zr`)
+  })
+})
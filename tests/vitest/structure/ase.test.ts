@@ -0,0 +1,94 @@
+import type { AseAtomsDict } from '$lib/structure'
+import { from_ase_atoms, to_ase_atoms } from '$lib/structure'
+import { describe, expect, test, vi } from 'vitest'
+import { make_crystal } from '../setup'
+
+const crystal = make_crystal(4, [
+  {
+    element: `Fe`,
+    abc: [0, 0, 0],
+    properties: {
+      force: [0.1, -0.2, 0.3],
+      charge: 0.5,
+      tag: 1,
+      momentum: [1, 2, 3],
+      selective_dynamics: [false, false, false],
+    },
+  },
+  {
+    element: `O`,
+    abc: [0.5, 0.5, 0.5],
+    properties: { force: [0, 0, -0.4], charge: -0.5, selective_dynamics: [true, true, false] },
+  },
+  { element: `O`, abc: [0.25, 0.5, 0] },
+])
+
+describe(`ASE Atoms interop`, () => {
+  test(`round-trips arrays and constraints through site properties`, () => {
+    const atoms = to_ase_atoms(crystal)
+    expect(atoms.numbers).toEqual([26, 8, 8])
+    expect(atoms.pbc).toEqual([true, true, true])
+    expect(atoms.forces).toEqual([[0.1, -0.2, 0.3], [0, 0, -0.4], [0, 0, 0]])
+    expect(atoms.initial_charges).toEqual([0.5, -0.5, 0])
+    expect(atoms.tags).toEqual([1, 0, 0])
+    expect(atoms.momenta).toEqual([[1, 2, 3], [0, 0, 0], [0, 0, 0]])
+    expect(atoms.initial_magmoms).toBeUndefined()
+    expect(atoms.constraints).toEqual([
+      { name: `FixAtoms`, kwargs: { indices: [0] } },
+      { name: `FixCartesian`, kwargs: { a: [1], mask: [false, false, true] } },
+    ])
+
+    const back = from_ase_atoms(atoms)
+    if (!(`lattice` in back)) throw new Error(`expected a crystal`)
+    expect(back.lattice.matrix).toEqual(crystal.lattice.matrix)
+    back.sites.forEach((site, idx) => {
+      site.abc.forEach((val, dim) => expect(val).toBeCloseTo(crystal.sites[idx].abc[dim], 12))
+      expect(site.species[0].element).toBe(crystal.sites[idx].species[0].element)
+    })
+    expect(back.sites[0].properties).toMatchObject(crystal.sites[0].properties ?? {})
+    expect(back.sites[1].properties?.selective_dynamics).toEqual([true, true, false])
+    expect(back.sites[2].properties?.selective_dynamics).toBeUndefined()
+  })
+
+  test(`zero cell gives a molecule and info becomes structure properties`, () => {
+    const atoms: AseAtomsDict = {
+      numbers: [8, 1, 1],
+      positions: [[0, 0, 0], [0.96, 0, 0], [-0.24, 0.93, 0]],
+      cell: [[0, 0, 0], [0, 0, 0], [0, 0, 0]],
+      pbc: [false, false, false],
+      initial_magmoms: [0, 0, 0],
+      info: { energy: -14.2 },
+    }
+    const molecule = from_ase_atoms(atoms)
+    expect(`lattice` in molecule).toBe(false)
+    expect(molecule.sites.map((site) => site.label)).toEqual([`O1`, `H2`, `H3`])
+    expect(molecule.sites[1].xyz).toEqual([0.96, 0, 0])
+    expect(molecule.properties).toEqual({ energy: -14.2 })
+    expect(to_ase_atoms(molecule)).toEqual(atoms)
+  })
+
+  test(`skips constraints without a site-level equivalent`, () => {
+    const warn = vi.spyOn(console, `warn`).mockImplementation(() => {})
+    const structure = from_ase_atoms({
+      numbers: [6, 6],
+      positions: [[0, 0, 0], [1.4, 0, 0]],
+      constraints: [
+        { name: `FixBondLength`, kwargs: { a1: 0, a2: 1 } },
+        { name: `FixCartesian`, kwargs: { a: 1, mask: [true, false, false] } },
+      ],
+    })
+    expect(warn).toHaveBeenCalledWith(`Skipping unsupported ASE constraint FixBondLength`)
+    expect(structure.sites[0].properties?.selective_dynamics).toBeUndefined()
+    expect(structure.sites[1].properties?.selective_dynamics).toEqual([false, true, true])
+    warn.mockRestore()
+  })
+
+  test(`rejects invalid input`, () => {
+    expect(() => from_ase_atoms({ numbers: [1, 1], positions: [[0, 0, 0]] })).toThrow(
+      `differ in length`,
+    )
+    expect(() => from_ase_atoms({ numbers: [0], positions: [[0, 0, 0]] })).toThrow(
+      `Unknown atomic number 0`,
+    )
+  })
+})
@@ -0,0 +1,87 @@
+import {
+  atom_arrays_to_extxyz,
+  atom_arrays_to_lammps_data,
+  for_each_neighbor_pair,
+  make_supercell,
+  replicate_atom_arrays,
+  structure_to_atom_arrays,
+} from '$lib/structure'
+import { structure_to_lammps_data_str } from '$lib/structure/export'
+import { parse_xyz } from '$lib/structure/parse'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const a_cu = 3.6
+const copper = {
+  ...make_crystal(a_cu, [
+    [`Cu`, [0, 0, 0]],
+    [`Cu`, [0.5, 0.5, 0]],
+    [`Cu`, [0.5, 0, 0.5]],
+    [`Ni`, [0, 0.5, 0.5]],
+  ]),
+  id: `cu3ni`,
+}
+
+describe(`atom arrays`, () => {
+  test(`replicate in the site order of make_supercell`, () => {
+    const atoms = structure_to_atom_arrays(copper)
+    expect([...atoms.numbers]).toEqual([29, 29, 29, 28])
+    expect([...atoms.positions.subarray(3, 6)]).toEqual([1.8, 1.8, 0])
+    const replicated = replicate_atom_arrays(copper, [2, 3, 1])
+    const supercell = make_supercell(copper, [2, 3, 1])
+    expect(replicated.numbers).toHaveLength(24)
+    expect(replicated.lattice?.matrix).toEqual(supercell.lattice.matrix)
+    supercell.sites.forEach(({ xyz }, idx) =>
+      xyz.forEach((coord, axis) =>
+        expect(replicated.positions[3 * idx + axis]).toBeCloseTo(coord, 12),
+      ),
+    )
+    expect(() => replicate_atom_arrays({ sites: copper.sites }, [2, 2, 2])).toThrow(
+      `without a lattice`,
+    )
+    expect(() => replicate_atom_arrays(copper, [2, 0, 1])).toThrow(`positive integers`)
+  })
+
+  test(`for_each_neighbor_pair streams the fcc first shell of a supercell`, () => {
+    const atoms = replicate_atom_arrays(copper, [6, 6, 6])
+    const counts = new Uint8Array(atoms.numbers.length)
+    let n_pairs = 0
+    for_each_neighbor_pair(atoms, 2.6, ({ center_idx, distance }) => {
+      counts[center_idx]++
+      n_pairs++
+      expect(distance).toBeCloseTo(a_cu / Math.SQRT2, 10)
+    })
+    expect(n_pairs).toBe(864 * 12)
+    expect(counts.every((count) => count === 12)).toBe(true)
+    const unit_cell = structure_to_atom_arrays(copper)
+    expect(() => for_each_neighbor_pair(unit_cell, 2.6, () => {})).toThrow(
+      `at least 3 cutoffs`,
+    )
+  })
+
+  test(`extXYZ chunks parse back to the structure`, () => {
+    const atoms = structure_to_atom_arrays(copper)
+    const chunks = [...atom_arrays_to_extxyz(atoms, { chunk_size: 3, title: `cu3ni` })]
+    // header plus atom lines in chunks of 3
+    expect(chunks).toHaveLength(3)
+    const parsed = parse_xyz(chunks.join(``))
+    expect(parsed?.sites.map(({ species }) => species[0].element)).toEqual([
+      `Cu`,
+      `Cu`,
+      `Cu`,
+      `Ni`,
+    ])
+    expect(parsed?.lattice?.matrix).toEqual(copper.lattice.matrix)
+  })
+
+  test(`LAMMPS data chunks match structure_to_lammps_data_str`, () => {
+    const atoms = structure_to_atom_arrays(copper)
+    const chunks = [...atom_arrays_to_lammps_data(atoms, { chunk_size: 2, title: `cu3ni` })]
+    expect(chunks).toHaveLength(3)
+    expect(chunks.join(``)).toBe(structure_to_lammps_data_str(copper))
+    expect(() => [...atom_arrays_to_lammps_data({ ...atoms, lattice: null })]).toThrow(
+      `No lattice information`,
+    )
+  })
+})
+
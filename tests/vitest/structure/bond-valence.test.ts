@@ -0,0 +1,33 @@
+import { compute_bv_sums } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// Fe on a simple cubic lattice with O on the edge centers, 6 Fe–O bonds of a / 2
+const fe_o3 = (a: number) =>
+  make_crystal(a, [
+    [`Fe`, [0, 0, 0]],
+    [`O`, [0.5, 0, 0]],
+    [`O`, [0, 0.5, 0]],
+    [`O`, [0, 0, 0.5]],
+  ])
+
+describe(`compute_bv_sums`, () => {
+  test(`sums exp((R0 − r) / b) over listed pairs`, () => {
+    // bond valence 1/2 at 2 Å
+    const r0 = 2 + 0.37 * Math.log(0.5)
+    const sums = compute_bv_sums(fe_o3(4), { r0: { 'O-Fe': r0 } })
+    expect(sums[0]).toBeCloseTo(3, 12)
+    for (const o_sum of sums.slice(1)) expect(o_sum).toBeCloseTo(1, 12)
+    // softer bonds and a cutoff below the bond length
+    const soft = compute_bv_sums(fe_o3(4), { r0: { 'Fe-O': 2 }, b: 0.5 })
+    expect(soft[0]).toBeCloseTo(6, 12)
+    expect(compute_bv_sums(fe_o3(4), { r0: { 'Fe-O': 2 }, cutoff: 1.9 })).toEqual([0, 0, 0, 0])
+    // unlisted pairs don't contribute
+    expect(compute_bv_sums(fe_o3(4), { r0: { 'Ti-O': 2 } })).toEqual([0, 0, 0, 0])
+  })
+
+  test(`rejects invalid parameters`, () => {
+    expect(() => compute_bv_sums(fe_o3(4), { r0: { FeO: 2 } })).toThrow(`must look like`)
+    expect(() => compute_bv_sums(fe_o3(4), { r0: {}, b: 0 })).toThrow(`b must be positive`)
+  })
+})
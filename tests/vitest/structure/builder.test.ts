@@ -0,0 +1,95 @@
+import type { Crystal } from '$lib/structure'
+import { StructureBuilder } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+
+describe(`StructureBuilder`, () => {
+  test(`builds a crystal from fractional and Cartesian sites`, () => {
+    const structure = new StructureBuilder()
+      .with_lattice([
+        [4, 0, 0],
+        [0, 4, 0],
+        [0, 0, 5],
+      ])
+      .add_site(`Na`, [0, 0, 0], { oxidation_state: 1 })
+      .add_site({ Cl: 0.5, Br: 0.5 }, [2, 2, 2.5], { cartesian: true, label: `X` })
+      .with_charge(0)
+      .with_property(`source`, `test`)
+      .build() as Crystal
+    expect(structure.lattice).toMatchObject({ a: 4, c: 5, volume: 80, alpha: 90 })
+    expect(structure.lattice.pbc).toEqual([true, true, true])
+    const [na, mixed] = structure.sites
+    expect(na).toMatchObject({ label: `Na1`, xyz: [0, 0, 0], abc: [0, 0, 0] })
+    expect(na.species).toEqual([{ element: `Na`, occu: 1, oxidation_state: 1 }])
+    expect(mixed.label).toBe(`X`)
+    mixed.abc.forEach((val) => expect(val).toBeCloseTo(0.5, 12))
+    expect(mixed.species.map(({ element }) => element)).toEqual([`Cl`, `Br`])
+    expect(structure.charge).toBe(0)
+    expect(structure.properties).toEqual({ source: `test` })
+  })
+
+  test(`cell parameters and molecules`, () => {
+    const hexagonal = new StructureBuilder()
+      .with_lattice({ a: 3, b: 3, c: 5, alpha: 90, beta: 90, gamma: 120 }, [true, true, false])
+      .add_site(`C`, [1 / 3, 2 / 3, 0.5])
+      .build() as Crystal
+    expect(hexagonal.lattice.gamma).toBeCloseTo(120, 10)
+    expect(hexagonal.lattice.pbc).toEqual([true, true, false])
+
+    const molecule = new StructureBuilder()
+      .add_site(`O`, [0, 0, 0], { cartesian: true })
+      .add_site(`H`, [0.96, 0, 0], { cartesian: true })
+      .build()
+    expect(`lattice` in molecule).toBe(false)
+    expect(molecule.sites.map(({ label }) => label)).toEqual([`O1`, `H2`])
+    expect(`properties` in molecule).toBe(false)
+  })
+
+  test(`build reports every invalid input at once`, () => {
+    const builder = new StructureBuilder()
+      .with_lattice([
+        [1, 0, 0],
+        [2, 0, 0],
+        [0, 0, 1],
+      ])
+      .add_site(`Xx`, [0, 0, 0])
+      .add_site({ Fe: 0.7, Ni: 0.6 }, [0, NaN, 0])
+      .with_charge(Infinity)
+    expect(builder.validate()).toEqual([
+      `Lattice vectors are linearly dependent (zero cell volume)`,
+      `Site 0 has unknown element Xx`,
+      `Site 1 occupancies sum to 1.2999999999999998 > 1`,
+      `Site 1 needs 3 finite coordinates, got [0,null,0]`,
+      `charge must be finite, got Infinity`,
+    ])
+    expect(() => builder.build()).toThrow(`Invalid structure: Lattice vectors`)
+    expect(new StructureBuilder().validate()).toEqual([`Structure needs at least one site`])
+    expect(new StructureBuilder().add_site(`H`, [0, 0, 0]).validate()).toEqual([
+      `Site 0 has fractional coordinates but no lattice was set`,
+    ])
+  })
+
+  test(`min_distance rejects overlapping sites and periodic images`, () => {
+    const builder = new StructureBuilder()
+      .with_lattice([
+        [3, 0, 0],
+        [0, 3, 0],
+        [0, 0, 3],
+      ])
+      .add_site(`Cu`, [0, 0, 0])
+      .add_site(`Cu`, [0.98, 0, 0])
+    expect(builder.validate({ min_distance: 1 })).toEqual([
+      `Sites 0 and 1 are 0.0600 Å apart, closer than min_distance=1`,
+    ])
+    expect(builder.build().sites).toHaveLength(2)
+    const small_cell = new StructureBuilder()
+      .with_lattice([
+        [1, 0, 0],
+        [0, 3, 0],
+        [0, 0, 3],
+      ])
+      .add_site(`Cu`, [0, 0, 0])
+    expect(small_cell.validate({ min_distance: 1.5 })).toEqual([
+      `Sites 0 and 0 are 1.0000 Å apart, closer than min_distance=1.5`,
+    ])
+  })
+})
@@ -0,0 +1,123 @@
+import type { Matrix3x3 } from '$lib/math'
+import type { BvseOptions } from '$lib/structure'
+import { bvse_map, migration_path, percolation_thresholds } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal, make_grid, make_volume } from '../setup'
+
+const li_o: BvseOptions = {
+  mobile_ion: `Li`,
+  bonds: { O: { b: 0.37, r_min: 2, d0: 1 } },
+  resolution: 0.4,
+}
+const cubic_o = make_crystal(4, [[`O`, [0, 0, 0]]])
+// O sheets 10 Å apart: easy in-plane, hard across the vacuum gap
+const layered_matrix: Matrix3x3 = [
+  [4, 0, 0],
+  [0, 4, 0],
+  [0, 0, 10],
+]
+const layered_o = make_crystal(layered_matrix, [[`O`, [0, 0, 0]]])
+
+describe(`bvse_map`, () => {
+  test(`grid spans the cell with energies relative to the minimum`, () => {
+    const { volume, minimum } = bvse_map(cubic_o, li_o)
+    expect(volume.grid_dims).toEqual([10, 10, 10])
+    expect(volume.periodic).toBe(true)
+    expect(volume.label).toBe(`BVSE Li`)
+    expect(volume.data_range.min).toBe(0)
+    expect(volume.data_range.max).toBeLessThanOrEqual(5)
+    // edge centers sit at r_min from two anions
+    expect([...minimum].sort()).toEqual([0, 0, 0.5])
+  })
+
+  test(`cubic symmetry gives equal energies at equivalent points`, () => {
+    const { grid } = bvse_map(cubic_o, li_o).volume
+    expect(grid[5][0][0]).toBeCloseTo(grid[0][5][0], 10)
+    expect(grid[5][5][0]).toBeCloseTo(grid[0][5][5], 10)
+    expect(grid[2][3][4]).toBeCloseTo(grid[4][2][3], 10)
+  })
+
+  test(`mobile ion sites are removed from the framework`, () => {
+    const with_li = make_crystal(4, [
+      [`O`, [0, 0, 0]],
+      [`Li`, [0.5, 0, 0]],
+    ])
+    expect(bvse_map(with_li, li_o).volume.grid).toEqual(bvse_map(cubic_o, li_o).volume.grid)
+  })
+
+  test(`cations repel the probe up to the energy cap`, () => {
+    const crystal = make_crystal(4, [
+      [`O`, [0, 0, 0]],
+      [`Ti`, [0.5, 0.5, 0.5]],
+    ])
+    const { grid } = bvse_map(crystal, { ...li_o, charges: { Ti: 4 }, max_energy: 3 }).volume
+    expect(grid[5][5][5]).toBe(3)
+    // without a charge Ti is ignored
+    expect(bvse_map(crystal, li_o).volume.grid).toEqual(bvse_map(cubic_o, li_o).volume.grid)
+  })
+
+  test(`cubic framework percolates in 1, 2 and 3 dimensions at once`, () => {
+    const [th_1d, th_2d, th_3d] = bvse_map(cubic_o, li_o).thresholds
+    expect(th_1d).toBeGreaterThan(0)
+    expect(th_2d).toBeCloseTo(th_1d ?? NaN, 10)
+    expect(th_3d).toBeCloseTo(th_1d ?? NaN, 10)
+  })
+
+  test(`layered framework conducts in-plane below the cross-plane barrier`, () => {
+    const [th_1d, th_2d, th_3d] = bvse_map(layered_o, li_o).thresholds
+    expect(th_2d).toBeCloseTo(th_1d ?? NaN, 10)
+    expect(th_3d).toBeGreaterThan((th_2d ?? Infinity) + 0.5)
+    // capping below the gap energy leaves only 2D transport
+    expect(bvse_map(layered_o, { ...li_o, max_energy: 1.5 }).thresholds[2]).toBeNull()
+  })
+
+  test.each([
+    [{ ...li_o, bonds: {} }, /Need bond parameters/],
+    [{ ...li_o, bonds: { S: { b: 0.37, r_min: 2.4, d0: 1 } } }, /No framework sites/],
+    [{ ...li_o, resolution: 0 }, /resolution must be > 0/],
+  ])(`rejects invalid options %#`, (options, message) => {
+    expect(() => bvse_map(cubic_o, options)).toThrow(message)
+  })
+})
+
+describe(`percolation_thresholds`, () => {
+  test(`channel along c percolates in 1D only`, () => {
+    const grid = make_grid(4, 4, 4, (ix, iy, iz) => (ix === 0 && iy === 0 ? iz % 2 : 9))
+    const volume = make_volume(grid, { periodic: true })
+    expect(percolation_thresholds(volume, 5)).toEqual([1, null, null])
+    expect(percolation_thresholds(volume)).toEqual([1, 9, 9])
+  })
+})
+
+describe(`migration_path`, () => {
+  test(`minimax path crosses the cell at the percolation barrier`, () => {
+    const { volume, minimum, thresholds } = bvse_map(cubic_o, li_o)
+    const path = migration_path(volume, [1, 0, 0])
+    expect(path).not.toBeNull()
+    if (!path) return
+    expect(path.barrier).toBeCloseTo(thresholds[0] ?? NaN, 10)
+    expect(Math.max(...path.energies)).toBeCloseTo(path.barrier, 10)
+    expect(path.energies[0]).toBe(0)
+    expect(path.abc[0]).toEqual(minimum)
+    expect(path.abc.at(-1)).toEqual([minimum[0] + 1, minimum[1], minimum[2]])
+    // consecutive points are face neighbors on the grid
+    for (let idx = 1; idx < path.abc.length; idx++) {
+      const steps = path.abc[idx].map((val, axis) => (val - path.abc[idx - 1][axis]) * 10)
+      expect(steps.reduce((sum, val) => sum + Math.abs(val), 0)).toBeCloseTo(1, 10)
+    }
+  })
+
+  test(`cross-plane path in a layered framework climbs the gap`, () => {
+    const { volume, thresholds } = bvse_map(layered_o, li_o)
+    const in_plane = migration_path(volume, [0, 1, 0])
+    const across = migration_path(volume, [0, 0, 1])
+    expect(in_plane?.barrier).toBeCloseTo(thresholds[1] ?? NaN, 10)
+    expect(across?.barrier).toBeCloseTo(thresholds[2] ?? NaN, 10)
+  })
+
+  test(`rejects non-lattice directions`, () => {
+    const { volume } = bvse_map(cubic_o, li_o)
+    expect(() => migration_path(volume, [0.5, 0, 0])).toThrow(/nonzero integer/)
+    expect(() => migration_path(volume, [0, 0, 0])).toThrow(/nonzero integer/)
+  })
+})
@@ -0,0 +1,225 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { CutoffFunction } from '$lib/structure'
+import {
+  coordination_sequence,
+  neighbor_shells,
+  smooth_coordination_numbers,
+  smooth_cutoff,
+  smooth_cutoff_derivative,
+  voronoi_coordination,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const fcc_abc: Vec3[] = [[0, 0, 0], [0.5, 0.5, 0], [0.5, 0, 0.5], [0, 0.5, 0.5]]
+
+describe(`coordination_sequence`, () => {
+  test(`simple cubic net`, () => {
+    const simple_cubic = make_crystal(1.5, [[`C`, [0, 0, 0]]])
+    expect(coordination_sequence(simple_cubic, 0, 4)).toEqual([6, 18, 38, 66])
+    expect(coordination_sequence(simple_cubic, 0, 0)).toEqual([])
+  })
+
+  test(`diamond`, () => {
+    const diamond = make_crystal(3.567, [
+      ...fcc_abc.map((abc): [string, Vec3] => [`C`, abc]),
+      ...fcc_abc.map((abc): [string, Vec3] => [`C`, abc.map((x) => x + 0.25) as Vec3]),
+    ])
+    for (const site_idx of [0, 5]) {
+      expect(coordination_sequence(diamond, site_idx, 5)).toEqual([4, 12, 24, 42, 64])
+    }
+  })
+
+  test(`bridging atoms are contracted into node-node edges`, () => {
+    // ReO3-type corner-sharing framework: Si on cube corners, O on edge centers
+    const framework = make_crystal(3.2, [
+      [`Si`, [0, 0, 0]],
+      [`O`, [0.5, 0, 0]],
+      [`O`, [0, 0.5, 0]],
+      [`O`, [0, 0, 0.5]],
+    ])
+    const options = { bridging_elements: [`O`] }
+    expect(coordination_sequence(framework, 0, 3, options)).toEqual([6, 18, 38])
+    // without contraction the first two shells are the O and then the Si neighbors
+    expect(coordination_sequence(framework, 0, 2)).toEqual([6, 6])
+    expect(() => coordination_sequence(framework, 1, 3, options)).toThrow(
+      `Site 1 is a bridging element`,
+    )
+    expect(() => coordination_sequence(framework, 4, 3)).toThrow(
+      `Site index 4 out of range for 4 sites`,
+    )
+  })
+
+  test(`molecules only count finite shells`, () => {
+    const lattice: Matrix3x3 = [
+      [10, 0, 0],
+      [0, 10, 0],
+      [0, 0, 10],
+    ]
+    // isolated C-C-C chain: the end atom sees 1, then 1, then nothing
+    const chain = make_crystal(lattice, [
+      { element: `C`, xyz: [1, 1, 1] },
+      { element: `C`, xyz: [2.5, 1, 1] },
+      { element: `C`, xyz: [4, 1, 1] },
+    ])
+    expect(coordination_sequence(chain, 0, 3)).toEqual([1, 1, 0])
+  })
+})
+
+describe(`neighbor_shells`, () => {
+  const a_cu = 3.61
+  const copper = make_crystal(a_cu, fcc_abc.map((abc): [string, Vec3] => [`Cu`, abc]))
+
+  test(`fcc shells`, () => {
+    const shells = neighbor_shells(copper, 0, 4)
+    expect(shells.map(({ neighbors }) => neighbors.length)).toEqual([12, 6, 24, 12])
+    const expected = [Math.SQRT1_2, 1, Math.sqrt(1.5), Math.SQRT2].map((x) => x * a_cu)
+    shells.forEach(({ distance }, idx) => expect(distance).toBeCloseTo(expected[idx], 10))
+  })
+
+  test(`max_cutoff limits the search to complete shells`, () => {
+    const shells = neighbor_shells(copper, 0, 10, 1e-3, 5)
+    expect(shells.map(({ neighbors }) => neighbors.length)).toEqual([12, 6, 24])
+  })
+})
+
+describe(`smooth cutoffs`, () => {
+  test.each([`cosine`, `polynomial`] as CutoffFunction[])(`%s shape`, (cutoff_fn) => {
+    expect(smooth_cutoff(0, 3, cutoff_fn)).toBe(1)
+    expect(smooth_cutoff(1.5, 3, cutoff_fn)).toBeCloseTo(0.5, 12)
+    expect(smooth_cutoff(3, 3, cutoff_fn)).toBe(0)
+    expect(smooth_cutoff(4, 3, cutoff_fn)).toBe(0)
+    // flat up to r_on, then switching over [r_on, r_cut]
+    expect(smooth_cutoff(1, 3, cutoff_fn, 2)).toBe(1)
+    expect(smooth_cutoff(2.5, 3, cutoff_fn, 2)).toBeCloseTo(0.5, 12)
+    // derivative matches finite differences and vanishes at both ends
+    for (const distance of [0.3, 1.2, 2.9]) {
+      const step = 1e-6
+      const numeric =
+        (smooth_cutoff(distance + step, 3, cutoff_fn) -
+          smooth_cutoff(distance - step, 3, cutoff_fn)) /
+        (2 * step)
+      expect(smooth_cutoff_derivative(distance, 3, cutoff_fn)).toBeCloseTo(numeric, 6)
+    }
+    expect(smooth_cutoff_derivative(3 - 1e-9, 3, cutoff_fn)).toBeCloseTo(0, 6)
+  })
+
+  test(`both functions have zero slope where switching starts`, () => {
+    expect(smooth_cutoff_derivative(2 + 1e-9, 3, `polynomial`, 2)).toBeCloseTo(0, 6)
+    expect(smooth_cutoff_derivative(1e-9, 3, `cosine`)).toBeCloseTo(0, 6)
+  })
+})
+
+describe(`smooth_coordination_numbers`, () => {
+  const a_cu = 3.61
+  const copper = make_crystal(a_cu, fcc_abc.map((abc): [string, Vec3] => [`Cu`, abc]))
+  const nn_dist = a_cu * Math.SQRT1_2
+
+  test(`fcc with a global cutoff`, () => {
+    const expected = 12 * smooth_cutoff(nn_dist, 3)
+    const cns = smooth_coordination_numbers(copper, { r_cut: 3 })
+    expect(cns).toHaveLength(4)
+    for (const cn of cns) expect(cn).toBeCloseTo(expected, 10)
+    // full count when the whole first shell lies before r_on
+    const flat = smooth_coordination_numbers(copper, {
+      r_cut: 3,
+      r_on_fraction: 0.9,
+      cutoff_fn: `polynomial`,
+    })
+    for (const cn of flat) expect(cn).toBeCloseTo(12, 10)
+  })
+
+  test(`changes continuously with the lattice constant`, () => {
+    const scaled = (factor: number) =>
+      make_crystal(a_cu * factor, fcc_abc.map((abc): [string, Vec3] => [`Cu`, abc]))
+    const [cn_1] = smooth_coordination_numbers(scaled(1), { r_cut: 3 })
+    const [cn_2] = smooth_coordination_numbers(scaled(1.001), { r_cut: 3 })
+    expect(cn_2).toBeLessThan(cn_1)
+    expect(cn_1 - cn_2).toBeLessThan(0.05)
+  })
+
+  test(`pair cutoffs and neighbor element filter`, () => {
+    const rocksalt = make_crystal(5.64, [
+      ...fcc_abc.map((abc): [string, Vec3] => [`Na`, abc]),
+      ...fcc_abc.map((abc): [string, Vec3] => [`Cl`, abc.map((x) => x + 0.5) as Vec3]),
+    ])
+    // Na-Cl 2.82 Å counted, like pairs at 3.99 Å fall outside the 0.5 Å default
+    const options = { pair_cutoffs: { 'Cl-Na': 3.5 }, r_cut: 0.5, r_on_fraction: 0.9 }
+    expect(smooth_coordination_numbers(rocksalt, options)).toEqual(Array(8).fill(6))
+    const na_only = smooth_coordination_numbers(rocksalt, {
+      ...options,
+      neighbor_elements: [`Na`],
+    })
+    expect(na_only).toEqual([0, 0, 0, 0, 6, 6, 6, 6])
+    // site 7 (Cl at a/2 along x) sits on both sides of every Na along one axis
+    const one_cl = smooth_coordination_numbers(rocksalt, {
+      ...options,
+      neighbor_selection: [7],
+    })
+    expect(one_cl).toEqual([2, 2, 2, 2, 0, 0, 0, 0])
+    expect(smooth_coordination_numbers({ sites: [] })).toEqual([])
+  })
+})
+
+describe(`voronoi_coordination`, () => {
+  test(`bcc cells are truncated octahedra with 8 large and 6 small faces`, () => {
+    const a_fe = 2.87
+    const iron = make_crystal(a_fe, [
+      [`Fe`, [0, 0, 0]],
+      [`Fe`, [0.5, 0.5, 0.5]],
+    ])
+    for (const cell of voronoi_coordination(iron)) {
+      expect(cell.cn).toBe(14)
+      expect(cell.volume).toBeCloseTo(a_fe ** 3 / 2, 10)
+      const [nearest, farthest] = [cell.faces[0], cell.faces[13]]
+      expect(nearest.distance).toBeCloseTo((a_fe * Math.sqrt(3)) / 2, 10)
+      expect(nearest.weight).toBeCloseTo(1, 10)
+      expect(farthest.distance).toBeCloseTo(a_fe, 10)
+      expect(farthest.weight).toBeCloseTo(0.3601, 4)
+      expect(cell.weighted_cn).toBeCloseTo(8 + 6 * farthest.weight, 10)
+    }
+    const [strict] = voronoi_coordination(iron, { min_weight: 0.5 })
+    expect(strict.cn).toBe(8)
+    expect(strict.weighted_cn).toBeCloseTo(8, 10)
+  })
+
+  test(`fcc and simple cubic cells`, () => {
+    const copper = make_crystal(3.6, fcc_abc.map((abc): [string, Vec3] => [`Cu`, abc]))
+    for (const cell of voronoi_coordination(copper)) {
+      expect(cell.cn).toBe(12)
+      expect(cell.volume).toBeCloseTo(3.6 ** 3 / 4, 10)
+    }
+    const [cube] = voronoi_coordination(make_crystal(3, [[`Po`, [0, 0, 0]]]))
+    const images = cube.faces.map(({ image }) => image.join(`,`)).toSorted()
+    expect(images).toEqual([`-1,0,0`, `0,-1,0`, `0,0,-1`, `0,0,1`, `0,1,0`, `1,0,0`])
+    for (const { area, solid_angle } of cube.faces) {
+      expect(area).toBeCloseTo(9, 10)
+      expect(solid_angle).toBeCloseTo((4 * Math.PI) / 6, 10)
+    }
+  })
+
+  test(`cells of a triclinic structure tile the unit cell`, () => {
+    const lattice: Matrix3x3 = [
+      [5, 0, 0],
+      [1, 6, 0],
+      [-1, 1.5, 7],
+    ]
+    const structure = make_crystal(lattice, [
+      [`Na`, [0.1, 0.2, 0.3]],
+      [`Cl`, [0.6, 0.55, 0.8]],
+      [`O`, [0.9, 0.1, 0.45]],
+      [`H`, [0.35, 0.75, 0.05]],
+    ])
+    const cells = voronoi_coordination(structure)
+    const total = cells.reduce((sum, { volume }) => sum + volume, 0)
+    expect(total).toBeCloseTo(210, 8)
+    for (const { faces } of cells) {
+      expect(Math.max(...faces.map(({ weight }) => weight))).toBe(1)
+    }
+  })
+
+  test(`needs 3D periodic structures`, () => {
+    const slab = make_crystal(3, [[`Fe`, [0, 0, 0]]], { pbc: [true, true, false] })
+    expect(() => voronoi_coordination(slab)).toThrow(`periodic along all 3 axes`)
+  })
+})
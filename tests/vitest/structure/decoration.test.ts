@@ -0,0 +1,101 @@
+import type { Vec3 } from '$lib/math'
+import { decorate_prototype, decorate_prototypes, ionic_radius } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const fcc_abc: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+const rocksalt = make_crystal(5.64, [
+  ...fcc_abc.map((abc) => ({ element: `Na` as const, abc })),
+  ...fcc_abc.map((abc) => ({
+    element: `Cl` as const,
+    abc: abc.map((coord) => (coord + 0.5) % 1) as Vec3,
+  })),
+])
+const perovskite = make_crystal(3.905, [
+  { element: `Sr`, abc: [0, 0, 0] },
+  { element: `Ti`, abc: [0.5, 0.5, 0.5] },
+  { element: `O`, abc: [0.5, 0.5, 0] },
+  { element: `O`, abc: [0.5, 0, 0.5] },
+  { element: `O`, abc: [0, 0.5, 0.5] },
+])
+
+describe(`ionic_radius`, () => {
+  test(`tabulated radius with fallbacks`, () => {
+    expect(ionic_radius(`Fe`, 3)).toBe(0.785)
+    expect(ionic_radius(`Fe`)).toBeCloseTo((0.92 + 0.785) / 2, 12)
+    expect(ionic_radius(`Zr`, 2)).toBe(0.86) // only Zr⁴⁺ is tabulated
+  })
+})
+
+describe(`decorate_prototype`, () => {
+  test(`every stoichiometry-compatible mapping, rescaled by ionic radii`, () => {
+    const decorated = decorate_prototype(rocksalt, { Mg: 1, O: 1 })
+    expect(decorated.map(({ substitution }) => substitution)).toEqual([
+      { Na: `Mg`, Cl: `O` },
+      { Na: `O`, Cl: `Mg` },
+    ])
+    const [{ structure }] = decorated
+    // (r_Mg²⁺ + r_O²⁻) / (r_Na⁺ + r_Cl⁻) = 2.12 / 2.83
+    expect(structure.lattice.a).toBeCloseTo((5.64 * 2.12) / 2.83, 10)
+    expect(structure.lattice.volume).toBeCloseTo(((5.64 * 2.12) / 2.83) ** 3, 8)
+    expect(structure.sites[0].species).toEqual([
+      { element: `Mg`, occu: 1, oxidation_state: 2 },
+    ])
+    expect(structure.sites[4].xyz[0]).toBeCloseTo(structure.lattice.a / 2, 10)
+    expect(structure.sites[4].label).toBe(`O`)
+    expect(rocksalt.sites[0].species[0].element).toBe(`Na`) // prototype untouched
+
+    const unscaled = decorate_prototype(rocksalt, { Mg: 1, O: 1 }, { rescale: false })
+    expect(unscaled[0].structure.lattice.a).toBe(5.64)
+  })
+
+  test(`perovskite nearest-neighbor bonds set the scale`, () => {
+    const [a_site, swapped] = decorate_prototype(perovskite, { Ba: 1, Zr: 1, O: 3 })
+    expect(a_site.substitution).toEqual({ Sr: `Ba`, Ti: `Zr`, O: `O` })
+    expect(swapped.substitution).toEqual({ Sr: `Zr`, Ti: `Ba`, O: `O` })
+    // Sr–O (Ba–O) for the A site, Ti–O (Zr–O) for the B site and all three O sites
+    const scale = ((1.49 + 1.26) / (1.32 + 1.26) + (4 * (0.86 + 1.26)) / (0.745 + 1.26)) / 5
+    expect(a_site.structure.lattice.a).toBeCloseTo(3.905 * scale, 10)
+  })
+
+  test(`mismatched stoichiometry gives no candidates, disordered prototypes throw`, () => {
+    expect(decorate_prototype(rocksalt, { Fe: 2, O: 3 })).toEqual([])
+    expect(decorate_prototype(rocksalt, { Li: 1, Fe: 1, O: 2 })).toEqual([])
+    const disordered = {
+      ...rocksalt,
+      sites: rocksalt.sites.map((site) => ({
+        ...site,
+        species: [
+          { element: `Na` as const, occu: 0.5, oxidation_state: 0 },
+          { element: `K` as const, occu: 0.5, oxidation_state: 0 },
+        ],
+      })),
+    }
+    expect(() => decorate_prototype(disordered, { Mg: 1, O: 1 })).toThrow(`must be ordered`)
+  })
+})
+
+describe(`decorate_prototypes`, () => {
+  const library = { rocksalt, perovskite }
+  const compositions = [{ Mg: 1, O: 1 }, { Ba: 1, Zr: 1, O: 3 }, { K: 1, Br: 1 }]
+
+  test(`drops equivalent decorations by fingerprint`, () => {
+    const candidates = decorate_prototypes(library, compositions)
+    expect(
+      candidates.map(({ prototype, substitution }) => [prototype, substitution]),
+    ).toEqual([
+      [`rocksalt`, { Na: `Mg`, Cl: `O` }], // Na→O, Cl→Mg is the same rock salt
+      [`perovskite`, { Sr: `Ba`, Ti: `Zr`, O: `O` }],
+      [`perovskite`, { Sr: `Zr`, Ti: `Ba`, O: `O` }], // distinct A/B site assignment
+      [`rocksalt`, { Na: `K`, Cl: `Br` }],
+    ])
+    expect(candidates[3].composition).toEqual({ K: 1, Br: 1 })
+    expect(candidates[3].structure.lattice.a).toBeCloseTo((5.64 * 3.34) / 2.83, 10)
+    expect(decorate_prototypes(library, compositions, { deduplicate: false })).toHaveLength(6)
+  })
+})
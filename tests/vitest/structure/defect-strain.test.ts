@@ -0,0 +1,103 @@
+import type { Vec3 } from '$lib/math'
+import {
+  defect_strain_field,
+  defect_strain_xyz,
+  get_site_fields,
+  make_supercell,
+  map_defect_sites,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// simple cubic 4x4x4 supercell (10 Å) with a vacancy at the origin whose 6 nearest
+// neighbors relax 0.1 Å inwards across the periodic boundaries
+const lattice_const = 2.5
+const pristine = make_supercell(make_crystal(lattice_const, [[`Fe`, [0, 0, 0]]]), 4)
+const min_image = (xyz: Vec3): Vec3 =>
+  xyz.map((coord) => coord - 10 * Math.round(coord / 10)) as Vec3
+const vacancy_idx = pristine.sites.findIndex(({ xyz }) => Math.hypot(...xyz) < 1e-6)
+const shift = 0.1
+const defect = {
+  ...pristine,
+  sites: pristine.sites
+    .filter((_, idx) => idx !== vacancy_idx)
+    .map((site) => {
+      const rel = min_image(site.xyz)
+      const dist = Math.hypot(...rel)
+      if (Math.abs(dist - lattice_const) > 1e-6) return site
+      const xyz = site.xyz.map((coord, dim) => coord - (shift * rel[dim]) / dist) as Vec3
+      return { ...site, xyz }
+    }),
+}
+const is_nn = (xyz: Vec3) => Math.abs(Math.hypot(...min_image(xyz)) - lattice_const) < 0.2
+
+describe(`map_defect_sites`, () => {
+  test(`finds the vacancy and maps relaxed sites onto their pristine sites`, () => {
+    const { mapping, vacancies, substitutions } = map_defect_sites(pristine, defect)
+    expect(vacancies).toEqual([vacancy_idx])
+    expect(substitutions).toEqual([])
+    expect(mapping).not.toContain(-1)
+    expect(new Set(mapping).size).toBe(defect.sites.length)
+  })
+
+  test(`substitutions and interstitials`, () => {
+    const [first, ...rest] = pristine.sites
+    const doped = {
+      ...pristine,
+      sites: [
+        { ...first, species: [{ element: `Ni` as const, occu: 1, oxidation_state: 0 }] },
+        ...rest,
+        { ...first, xyz: [1.25, 1.25, 1.25] as Vec3, abc: [0.125, 0.125, 0.125] as Vec3 },
+      ],
+    }
+    const { mapping, vacancies, substitutions } = map_defect_sites(pristine, doped)
+    expect(substitutions).toEqual([0])
+    expect(vacancies).toEqual([])
+    expect(mapping[mapping.length - 1]).toBe(-1)
+  })
+})
+
+describe(`defect_strain_field`, () => {
+  const field = defect_strain_field(pristine, defect, { cutoff: 2.6 })
+
+  test(`displacements point towards the vacancy`, () => {
+    defect.sites.forEach(({ xyz }, idx) => {
+      const norm = Math.hypot(...field.displacements[idx])
+      expect(norm).toBeCloseTo(is_nn(xyz) ? shift : 0, 10)
+    })
+    expect(Math.min(...(field.distances ?? []))).toBeCloseTo(lattice_const - shift, 10)
+  })
+
+  test(`strain localizes around the defect`, () => {
+    defect.sites.forEach(({ xyz }, idx) => {
+      const dist = Math.hypot(...min_image(xyz))
+      // atoms whose neighbor shells contain no relaxed atom stay unstrained
+      if (dist > 2 * lattice_const + 0.5) {
+        expect(field.volumetric_strain[idx]).toBeCloseTo(0, 12)
+        expect(field.shear_strain[idx]).toBeCloseTo(0, 12)
+      }
+    })
+    expect(Math.max(...field.shear_strain)).toBeGreaterThan(0.01)
+    // a relaxed atom only loses its bond to the vacancy: its remaining 5 neighbors
+    // stretch along the bond axis, so it expands
+    const nn_idx = defect.sites.findIndex(({ xyz }) => is_nn(xyz))
+    expect(field.volumetric_strain[nn_idx]).toBeGreaterThan(0)
+  })
+
+  test(`fields export as extXYZ columns`, () => {
+    const fields = get_site_fields(field.structure)
+    expect(Object.keys(fields)).toEqual([
+      `displacement`,
+      `volumetric_strain`,
+      `shear_strain`,
+      `defect_distance`,
+    ])
+    const xyz = defect_strain_xyz(pristine, defect, { cutoff: 2.6 })
+    expect(xyz.split(`\n`)[1]).toContain(
+      `displacement:R:3:volumetric_strain:R:1:shear_strain:R:1:defect_distance:R:1`,
+    )
+    const perfect = defect_strain_field(pristine, pristine, { cutoff: 2.6 })
+    expect(perfect.distances).toBeNull()
+    expect(Object.keys(get_site_fields(perfect.structure))).not.toContain(`defect_distance`)
+  })
+})
@@ -0,0 +1,122 @@
+import { ATOMIC_WEIGHTS } from '$lib/composition/parse'
+import type { ElementSymbol } from '$lib/element'
+import { element_by_symbol } from '$lib/element'
+import {
+  coulomb_matrix,
+  DESCRIPTOR_NAMES,
+  featurize,
+  featurize_named,
+  make_site,
+  make_supercell,
+  sine_matrix,
+  sorted_eigenvalues,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// CsCl: 8 nearest neighbors at a √3 / 2, the next 6 at a are 15.5% further out
+const a_cscl = 4.12
+const cscl = make_crystal(a_cscl, [
+  [`Cs`, [0, 0, 0]],
+  [`Cl`, [0.5, 0.5, 0.5]],
+])
+const copper = make_crystal(3.61, [
+  [`Cu`, [0, 0, 0]],
+  [`Cu`, [0.5, 0.5, 0]],
+  [`Cu`, [0.5, 0, 0.5]],
+  [`Cu`, [0, 0.5, 0.5]],
+])
+
+const [cs, cl] = ([`Cs`, `Cl`] as ElementSymbol[]).map((el) => element_by_symbol.get(el))
+
+describe(`featurize`, () => {
+  test(`CsCl descriptors`, () => {
+    const features = featurize_named(cscl)
+    expect(Object.keys(features)).toEqual([...DESCRIPTOR_NAMES])
+    const mass = (ATOMIC_WEIGHTS.get(`Cs`) ?? 0) + (ATOMIC_WEIGHTS.get(`Cl`) ?? 0)
+    expect(features.density).toBeCloseTo((1.66053907 * mass) / a_cscl ** 3, 10)
+    expect(features.volume_per_atom).toBeCloseTo(a_cscl ** 3 / 2, 10)
+    const sphere_volume = [cs, cl].reduce(
+      (sum, elem) => sum + (4 * Math.PI * (elem?.atomic_radius ?? 1) ** 3) / 3,
+      0,
+    )
+    expect(features.packing_fraction).toBeCloseTo(sphere_volume / a_cscl ** 3, 10)
+    expect(features.mean_coordination).toBe(8)
+    expect(features.std_coordination).toBe(0)
+    const en_diff = Math.abs((cs?.electronegativity ?? 0) - (cl?.electronegativity ?? 0))
+    expect(features.mean_en_diff).toBeCloseTo(en_diff, 10)
+    expect(features.max_en_diff).toBeCloseTo(en_diff, 10)
+    expect(features.wyckoff_entropy).toBeCloseTo(Math.log(2), 12)
+    expect(features.rdf_mean).toBeGreaterThan(3)
+    expect(features.rdf_mean).toBeLessThan(8)
+    expect(features.rdf_std).toBeGreaterThan(0)
+  })
+
+  test(`descriptors are intensive, so supercells featurize like their unit cell`, () => {
+    const unit = featurize(copper)
+    expect(unit).toHaveLength(DESCRIPTOR_NAMES.length)
+    featurize(make_supercell(copper, [2, 1, 1])).forEach((val, idx) =>
+      expect(val).toBeCloseTo(unit[idx], 8),
+    )
+    const { mean_coordination, mean_en_diff, wyckoff_entropy } = featurize_named(copper)
+    expect([mean_coordination, mean_en_diff, wyckoff_entropy]).toEqual([12, 0, 0])
+  })
+
+  test(`orbits option overrides site grouping`, () => {
+    expect(featurize_named(cscl, { orbits: [0, 0] }).wyckoff_entropy).toBe(0)
+    expect(() => featurize(cscl, { orbits: [0] })).toThrow(`orbits has 1 entries for 2 sites`)
+    expect(() => featurize({ ...cscl, sites: [] })).toThrow(`without sites`)
+  })
+})
+
+describe(`coulomb_matrix and sine_matrix`, () => {
+  // H-Cl at 1.27 Å with H listed first
+  const hcl = {
+    sites: [
+      make_site(`H`, [0, 0, 0], [0, 0, 0], `H1`),
+      make_site(`Cl`, [0, 0, 0], [0, 0, 1.27], `Cl1`),
+    ],
+  }
+
+  test(`Coulomb matrix sorts by row norm and zero-pads`, () => {
+    const [diag_h, diag_cl, off] = [0.5, 0.5 * 17 ** 2.4, 17 / 1.27]
+    const unsorted = coulomb_matrix(hcl, { sort: `none` })
+    expect(unsorted[0][0]).toBeCloseTo(diag_h, 12)
+    expect(unsorted[1][1]).toBeCloseTo(diag_cl, 10)
+    expect(unsorted[0][1]).toBeCloseTo(off, 12)
+    const padded = coulomb_matrix(hcl, { n_max: 3 })
+    expect(padded.map((row) => row.length)).toEqual([3, 3, 3])
+    expect(padded[0][0]).toBeCloseTo(diag_cl, 10)
+    expect(padded[1][0]).toBeCloseTo(off, 12)
+    expect([padded[2], padded[0][2]]).toEqual([[0, 0, 0], 0])
+    expect(() => coulomb_matrix(hcl, { n_max: 1 })).toThrow(`n_max=1 is smaller`)
+  })
+
+  test(`eigenvalues are sorted by magnitude and padded`, () => {
+    const eigenvalues = sorted_eigenvalues(coulomb_matrix(hcl), 4)
+    const [mean, half_gap] = [(0.5 + 0.5 * 17 ** 2.4) / 2, (0.5 * 17 ** 2.4 - 0.5) / 2]
+    const split = Math.hypot(half_gap, 17 / 1.27)
+    expect(eigenvalues[0]).toBeCloseTo(mean + split, 8)
+    expect(eigenvalues[1]).toBeCloseTo(mean - split, 8)
+    expect(eigenvalues.slice(2)).toEqual([0, 0])
+    const diagonal = [
+      [2, 0],
+      [0, -3],
+    ]
+    expect(sorted_eigenvalues(diagonal)).toEqual([-3, 2])
+  })
+
+  test(`sine matrix is periodic in the lattice`, () => {
+    const matrix = sine_matrix(cscl, { sort: `none` })
+    // Δf = (½, ½, ½) puts the Cs-Cl pair at the body diagonal |a₁ + a₂ + a₃|
+    expect(matrix[0][1]).toBeCloseTo((55 * 17) / (a_cscl * Math.sqrt(3)), 10)
+    expect(matrix[1][0]).toBe(matrix[0][1])
+    const shifted = make_crystal(a_cscl, [
+      [`Cs`, [1, -2, 0]],
+      [`Cl`, [0.5, 0.5, 1.5]],
+    ])
+    sine_matrix(shifted, { sort: `none` }).forEach((row, idx) =>
+      row.forEach((val, col) => expect(val).toBeCloseTo(matrix[idx][col], 10)),
+    )
+  })
+})
@@ -0,0 +1,99 @@
+import type { Vec3 } from '$lib/math'
+import {
+  decorate_prototype,
+  dls_refine,
+  get_neighbor_list,
+  ionic_radius,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const fcc_abc: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+// NaCl rock salt with optional fractional displacements of sites by index
+const rocksalt = (a: number, shifts: Record<number, Vec3> = {}) => {
+  const sites = [
+    ...fcc_abc.map((abc) => ({ element: `Na` as const, abc })),
+    ...fcc_abc.map((abc) => ({
+      element: `Cl` as const,
+      abc: abc.map((coord) => (coord + 0.5) % 1) as Vec3,
+    })),
+  ]
+  return make_crystal(
+    a,
+    sites.map(({ element, abc }, idx) => ({
+      element,
+      abc: abc.map((val, axis) => val + (shifts[idx]?.[axis] ?? 0)) as Vec3,
+    })),
+  )
+}
+const [mgo] = decorate_prototype(rocksalt(5.64), { Mg: 1, O: 1 })
+
+describe(`dls_refine`, () => {
+  test(`rock salt edge becomes twice the cation–anion radius sum`, () => {
+    // radius-ratio scaling of NaCl gives 5.64 × 2.12 / 2.83 = 4.225 Å, DLS fits the bonds
+    const result = dls_refine(mgo.structure)
+    expect(result.n_bonds).toBe(24) // 4 Mg with 6 O each
+    expect(result.rms_before).toBeCloseTo(2.12 - (5.64 * 1.06) / 2.83, 10)
+    expect(result.rms_after).toBeLessThan(1e-10)
+    expect(result.converged).toBe(true)
+    const { lattice } = result.structure
+    for (const key of [`a`, `b`, `c`] as const) expect(lattice[key]).toBeCloseTo(4.24, 8)
+    for (const key of [`alpha`, `beta`, `gamma`] as const) {
+      expect(lattice[key]).toBeCloseTo(90, 8)
+    }
+    // oxidation states are guessed when the sites have none: Na⁺ + Cl⁻ = 2.83 Å
+    const nacl = dls_refine(rocksalt(6)).structure
+    expect(nacl.lattice.a).toBeCloseTo(2 * 2.83, 8)
+  })
+
+  test(`perovskite balances A–O and B–O bonds in the least-squares sense`, () => {
+    const perovskite = make_crystal(4.2, [
+      { element: `Ba`, abc: [0, 0, 0], oxidation_state: 2 },
+      { element: `Zr`, abc: [0.5, 0.5, 0.5], oxidation_state: 4 },
+      { element: `O`, abc: [0.5, 0.5, 0], oxidation_state: -2 },
+      { element: `O`, abc: [0.5, 0, 0.5], oxidation_state: -2 },
+      { element: `O`, abc: [0, 0.5, 0.5], oxidation_state: -2 },
+    ])
+    const result = dls_refine(perovskite)
+    expect(result.n_bonds).toBe(18) // 12 Ba–O at a/√2, 6 Zr–O at a/2
+    // minimize 12 (a/√2 − d_BaO)² + 6 (a/2 − d_ZrO)²
+    const d_ba_o = ionic_radius(`Ba`, 2) + ionic_radius(`O`, -2)
+    const d_zr_o = ionic_radius(`Zr`, 4) + ionic_radius(`O`, -2)
+    const expected = ((12 * d_ba_o) / Math.SQRT2 + 3 * d_zr_o) / 7.5
+    expect(result.structure.lattice.a).toBeCloseTo(expected, 8)
+    expect(result.structure.lattice.c).toBeCloseTo(expected, 8)
+    expect(result.rms_after).toBeLessThan(result.rms_before)
+    // custom radii for a perfect fit: a/2 = 0.6 + 1.4, a/√2 = 2√2 − 1.4
+    const fitted = dls_refine(perovskite, { radii: { Ba: 2 * Math.SQRT2 - 1.4, Zr: 0.6 } })
+    expect(fitted.structure.lattice.a).toBeCloseTo(4, 8)
+    expect(fitted.rms_after).toBeLessThan(1e-10)
+  })
+
+  test(`relax_positions also moves atoms onto the target bond lengths`, () => {
+    const [displaced] = decorate_prototype(
+      rocksalt(5.64, { 0: [0.02, 0, 0], 5: [0, 0, -0.03] }),
+      { Mg: 1, O: 1 },
+    )
+    expect(dls_refine(displaced.structure).rms_after).toBeGreaterThan(1e-3)
+    const result = dls_refine(displaced.structure, { relax_positions: true })
+    expect(result.n_bonds).toBe(24)
+    expect(result.rms_after).toBeLessThan(1e-8)
+    const neighbors = get_neighbor_list(result.structure, 2.5)
+    for (const nbs of neighbors.slice(0, 4)) {
+      expect(nbs).toHaveLength(6)
+      for (const { distance } of nbs) expect(distance).toBeCloseTo(2.12, 6)
+    }
+  })
+
+  test(`throws without cations and anions`, () => {
+    const oxygen = make_crystal(3, [{ element: `O`, abc: [0, 0, 0] }])
+    expect(() => dls_refine(oxygen)).toThrow(`oxidation states`)
+    const metal = make_crystal(3, [{ element: `Cu`, abc: [0, 0, 0], oxidation_state: 1 }])
+    expect(() => dls_refine(metal)).toThrow(`No cation–anion bonds`)
+  })
+})
@@ -0,0 +1,132 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import {
+  dipole_strain_energy,
+  elastic_dipole_from_forces,
+  elastic_dipole_from_stress,
+  EV_PER_A3_TO_GPA,
+  make_supercell,
+  relaxation_volume,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// isotropic cubic stiffness (GPa): C44 = (C11 - C12) / 2
+const [c11, c12, c44] = [250, 100, 75]
+const elastic_tensor = [
+  [c11, c12, c12, 0, 0, 0],
+  [c12, c11, c12, 0, 0, 0],
+  [c12, c12, c11, 0, 0, 0],
+  [0, 0, 0, c44, 0, 0],
+  [0, 0, 0, 0, c44, 0],
+  [0, 0, 0, 0, 0, c44],
+]
+const bulk_modulus = (c11 + 2 * c12) / 3
+const diag = (val: number): Matrix3x3 => [
+  [val, 0, 0],
+  [0, val, 0],
+  [0, 0, val],
+]
+
+describe(`relaxation_volume`, () => {
+  test(`hydrostatic dipole gives ΔV = tr P / (3B)`, () => {
+    const { tensor, volume } = relaxation_volume(diag(3), elastic_tensor)
+    expect(volume).toBeCloseTo((9 * EV_PER_A3_TO_GPA) / (3 * bulk_modulus), 10)
+    expect(tensor[0][0]).toBeCloseTo(volume / 3, 10)
+    expect(tensor[0][1]).toBeCloseTo(0, 12)
+  })
+
+  test(`shear dipole gives traceless tensor with halved Voigt strain`, () => {
+    const dipole: Matrix3x3 = [
+      [0, 2, 0],
+      [2, 0, 0],
+      [0, 0, 0],
+    ]
+    const { tensor, volume } = relaxation_volume(dipole, elastic_tensor)
+    expect(volume).toBeCloseTo(0, 12)
+    expect(tensor[0][1]).toBeCloseTo((2 * EV_PER_A3_TO_GPA) / c44 / 2, 10)
+    expect(tensor[1][0]).toBe(tensor[0][1])
+  })
+
+  test(`invalid elastic tensors throw`, () => {
+    expect(() => relaxation_volume(diag(1), [[1]])).toThrow(`6x6 Voigt matrix`)
+    const singular = elastic_tensor.map((row) => row.map(() => 1))
+    expect(() => relaxation_volume(diag(1), singular)).toThrow(`singular`)
+  })
+})
+
+describe(`elastic_dipole_from_stress`, () => {
+  test(`compressive residual stress gives positive dipole`, () => {
+    const result = elastic_dipole_from_stress(diag(-1.5), 1000, {
+      reference_stress: diag(-0.5),
+      elastic_tensor,
+    })
+    expect(result.dipole[2][2]).toBeCloseTo(1000 / EV_PER_A3_TO_GPA, 10)
+    expect(result.dipole[0][2]).toBe(0)
+    // with P = V Δσ and ε = C⁻¹ P the relaxation volume is V tr(-Δσ) / (3B)
+    expect(result.relaxation?.volume).toBeCloseTo(1000 / bulk_modulus, 8)
+    expect(elastic_dipole_from_stress(diag(1), 10).relaxation).toBeNull()
+    expect(() => elastic_dipole_from_stress(diag(1), 0)).toThrow(`volume must be > 0`)
+  })
+
+  test(`dipole is symmetrized`, () => {
+    const stress: Matrix3x3 = [
+      [0, 1, 0],
+      [0, 0, 0],
+      [0, 0, 0],
+    ]
+    const { dipole } = elastic_dipole_from_stress(stress, EV_PER_A3_TO_GPA)
+    expect(dipole[0][1]).toBeCloseTo(-0.5, 12)
+    expect(dipole[1][0]).toBeCloseTo(-0.5, 12)
+  })
+})
+
+describe(`elastic_dipole_from_forces`, () => {
+  // simple cubic 4x4x4 supercell with the defect on the atom at the origin
+  const lattice_const = 2.5
+  const supercell = make_supercell(make_crystal(lattice_const, [[`Fe`, [0, 0, 0]]]), 4)
+  const defect: Vec3 = [0, 0, 0]
+  const force_mag = 0.5
+  // Kanzaki forces pushing the 6 nearest neighbors away from the defect, plus a spurious
+  // force on a distant atom that the cutoff removes
+  const forces = supercell.sites.map(({ xyz }): Vec3 => {
+    const rel = xyz.map((coord) => {
+      const wrapped = coord - 10 * Math.round(coord / 10)
+      return Math.abs(wrapped) < 1e-6 ? 0 : wrapped
+    })
+    const dist = Math.hypot(...rel)
+    if (Math.abs(dist - lattice_const) < 1e-6) {
+      return rel.map((val) => (force_mag * val) / dist) as Vec3
+    }
+    return dist > 4 ? [0.3, 0, 0] : [0, 0, 0]
+  })
+
+  test(`outward forces on nearest neighbors across periodic boundaries`, () => {
+    const { dipole, relaxation } = elastic_dipole_from_forces(supercell, defect, {
+      forces,
+      cutoff: 3,
+      elastic_tensor,
+    })
+    for (let idx = 0; idx < 3; idx++) {
+      expect(dipole[idx][idx]).toBeCloseTo(2 * force_mag * lattice_const, 10)
+      expect(dipole[idx][(idx + 1) % 3]).toBeCloseTo(0, 10)
+    }
+    expect(relaxation?.volume).toBeGreaterThan(0)
+  })
+
+  test(`forces default to site properties and must exist`, () => {
+    const with_forces = {
+      ...supercell,
+      sites: supercell.sites.map((site, idx) => ({
+        ...site,
+        properties: { ...site.properties, force: forces[idx] },
+      })),
+    }
+    const from_props = elastic_dipole_from_forces(with_forces, defect, { cutoff: 3 })
+    expect(from_props.dipole[1][1]).toBeCloseTo(2 * force_mag * lattice_const, 10)
+    expect(() => elastic_dipole_from_forces(supercell, defect)).toThrow(`No force for site 0`)
+  })
+})
+
+test(`dipole_strain_energy contracts dipole with strain`, () => {
+  expect(dipole_strain_energy(diag(2), diag(0.01))).toBeCloseTo(-0.06, 12)
+})
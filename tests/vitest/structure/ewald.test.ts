@@ -0,0 +1,137 @@
+import { COULOMB_EV_A } from '$lib/constants'
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import {
+  direct_reciprocal,
+  ewald_energy,
+  EWALD_ACCURACY,
+  oxidation_state_charges,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const nacl_a = 5.64
+const nacl = make_crystal(nacl_a, [
+  ...([[0, 0, 0], [0.5, 0.5, 0], [0.5, 0, 0.5], [0, 0.5, 0.5]] as Vec3[]).map(
+    (abc): [string, Vec3, number] => [`Na`, abc, 1],
+  ),
+  ...([[0.5, 0, 0], [0, 0.5, 0], [0, 0, 0.5], [0.5, 0.5, 0.5]] as Vec3[]).map(
+    (abc): [string, Vec3, number] => [`Cl`, abc, -1],
+  ),
+])
+// 4 formula units with Madelung constant 1.747565 at nearest-neighbor distance a / 2
+const nacl_energy = (-4 * 1.747565 * COULOMB_EV_A) / (nacl_a / 2)
+
+// neutral triclinic cell with no special positions
+const triclinic = make_crystal(
+  [
+    [7, 0, 0],
+    [1.2, 6.5, 0],
+    [0.5, 0.8, 8],
+  ],
+  [
+    [`Mg`, [0.1, 0.2, 0.3], 2],
+    [`O`, [0.6, 0.1, 0.7], -2],
+    [`Li`, [0.3, 0.8, 0.2], 1],
+    [`F`, [0.85, 0.55, 0.45], -1],
+    [`Mg`, [0.45, 0.4, 0.9], 2],
+    [`O`, [0.2, 0.6, 0.6], -2],
+  ],
+)
+
+describe(`ewald_energy`, () => {
+  test(`reproduces the rock-salt Madelung energy`, () => {
+    // small systems use the direct sum below PME_MIN_SITES charged sites
+    const result = ewald_energy(nacl)
+    expect(result.method).toBe(`direct`)
+    expect(result.mesh).toBeNull()
+    expect(result.energy).toBeCloseTo(nacl_energy, 3)
+    const terms = result.real + result.reciprocal + result.self + result.charged
+    expect(result.energy).toBeCloseTo(terms, 12)
+    expect(result.charged).toBe(0)
+    // truncation errors match the target accuracy
+    expect(result.real_cutoff * result.alpha).toBeCloseTo(
+      Math.sqrt(-Math.log(EWALD_ACCURACY.medium)),
+      12,
+    )
+  })
+
+  test(`total energy does not depend on the splitting parameter`, () => {
+    const reference = ewald_energy(triclinic, { accuracy: `high` }).energy
+    for (const alpha of [0.25, 0.7]) {
+      const result = ewald_energy(triclinic, { alpha, accuracy: `high` })
+      expect(Math.abs(result.energy / reference - 1)).toBeLessThan(1e-5)
+    }
+  })
+
+  test(`particle-mesh Ewald matches the direct sum`, () => {
+    const reference = ewald_energy(triclinic, { accuracy: `high` }).energy
+    const pme = ewald_energy(triclinic, { method: `pme` })
+    expect(pme.method).toBe(`pme`)
+    expect(pme.order).toBe(4)
+    expect(pme.mesh?.every((size) => Number.isInteger(Math.log2(size)))).toBe(true)
+    expect(Math.abs(pme.energy / reference - 1)).toBeLessThan(1e-4)
+    // higher order splines need a coarser mesh for the same accuracy
+    const fine = ewald_energy(triclinic, { method: `pme`, order: 6, mesh: [16, 16, 16] })
+    expect(fine.mesh).toEqual([16, 16, 16])
+    expect(Math.abs(fine.energy / reference - 1)).toBeLessThan(1e-6)
+    // the same for rock salt with the default 9 Å real-space cutoff
+    const rock_salt = ewald_energy(nacl, { method: `pme` })
+    expect(rock_salt.real_cutoff).toBeCloseTo(9, 12)
+    expect(rock_salt.energy).toBeCloseTo(nacl_energy, 3)
+  })
+
+  test(`charged cells get a neutralizing background`, () => {
+    const charges = [2, -2, 1, -1, 2, -1]
+    const [low, high] = [0.3, 0.6].map((alpha) =>
+      ewald_energy(triclinic, { charges, alpha, accuracy: `high` }),
+    )
+    expect(low.charged).toBeLessThan(high.charged)
+    expect(low.energy).toBeCloseTo(high.energy, 5)
+    const pme = ewald_energy(triclinic, { charges, method: `pme`, accuracy: `high`, order: 6 })
+    expect(pme.energy).toBeCloseTo(low.energy, 4)
+  })
+
+  test(`rejects invalid input`, () => {
+    expect(() => ewald_energy(nacl, { charges: [1, -1] })).toThrow(`charges must match`)
+    expect(() => ewald_energy(nacl, { accuracy: 2 })).toThrow(`accuracy must be in (0, 1)`)
+    expect(() => ewald_energy(nacl, { alpha: -1 })).toThrow(`alpha must be positive`)
+    expect(() => ewald_energy(nacl, { method: `pme`, order: 2 })).toThrow(`order must be`)
+    expect(() => ewald_energy(nacl, { method: `pme`, mesh: [12, 16, 16] })).toThrow(
+      `powers of 2`,
+    )
+    const slab = make_crystal(nacl_a, [[`Na`, [0, 0, 0], 1]], { pbc: [true, true, false] })
+    expect(() => ewald_energy(slab)).toThrow(`periodic boundaries along all 3 axes`)
+  })
+})
+
+describe(`direct_reciprocal`, () => {
+  test(`site energies add up and forces match finite differences`, () => {
+    const { lattice, sites } = triclinic
+    const charges = oxidation_state_charges(sites)
+    const recip = math.create_cart_to_frac_matrix(lattice.matrix)
+    const norms = lattice.matrix.map((vec) => Math.hypot(...vec)) as Vec3
+    const volume = Math.abs(math.det_3x3(lattice.matrix))
+    const to_frac = math.create_cart_to_frac(lattice.matrix)
+    const run = (xyzs: Vec3[]) =>
+      direct_reciprocal(xyzs.map(to_frac), charges, recip, norms, 0.35, 2.5, volume)
+    const xyzs = sites.map(({ xyz }) => xyz)
+    const result = run(xyzs)
+    const site_sum = result.site_energies.reduce((sum, val) => sum + val, 0)
+    expect(site_sum).toBeCloseTo(result.energy, 12)
+
+    const delta = 1e-5
+    for (const axis of [0, 1, 2]) {
+      const shifted = (sign: number) =>
+        xyzs.map((xyz, idx) =>
+          idx === 1
+            ? (xyz.map((val, dim) => val + (dim === axis ? sign * delta : 0)) as Vec3)
+            : xyz,
+        )
+      const numeric = -(run(shifted(1)).energy - run(shifted(-1)).energy) / (2 * delta)
+      expect(result.forces[1][axis]).toBeCloseTo(numeric, 6)
+    }
+    const total_force = math.add(...result.forces)
+    total_force.forEach((component) => expect(component).toBeCloseTo(0, 10))
+  })
+})
@@ -1,5 +1,8 @@
 import type { ElementSymbol } from '$lib'
 import { download } from '$lib/io/fetch'
+import { parse_cube } from '$lib/isosurface/parse'
+import type { VolumetricData } from '$lib/isosurface/types'
+import { grid_data_range } from '$lib/isosurface/types'
 import type { Matrix3x3, Vec3 } from '$lib/math'
 import * as math from '$lib/math'
 import type { AnyStructure, LatticeType, Site } from '$lib/structure'
@@ -12,11 +15,23 @@ import {
   generate_mtl_content,
   has_color_property,
   structure_to_cif_str,
+  structure_to_cube_str,
   structure_to_json_str,
+  structure_to_lammps_data_str,
+  structure_to_optimade,
+  structure_to_optimade_str,
   structure_to_poscar_str,
   structure_to_xyz_str,
 } from '$lib/structure/export'
-import { parse_cif, parse_poscar, parse_structure_file, parse_xyz } from '$lib/structure/parse'
+import {
+  optimade_to_crystal,
+  parse_cif,
+  parse_lammps_data,
+  parse_optimade_json,
+  parse_poscar,
+  parse_structure_file,
+  parse_xyz,
+} from '$lib/structure/parse'
 import ba_ti_o3_tetragonal from '$site/structures/BaTiO3-tetragonal.poscar?raw'
 import extended_xyz_quartz from '$site/structures/quartz.extxyz?raw'
 import tio2_cif from '$site/structures/TiO2.cif?raw'
@@ -34,7 +49,7 @@ import {
   SphereGeometry,
 } from 'three'
 import { assert, beforeEach, describe, expect, it, test, vi } from 'vitest'
-import { complex_structure, simple_structure } from '../setup'
+import { complex_structure, make_crystal, simple_structure } from '../setup'
 
 vi.mock(`$lib/io/fetch`, () => ({ download: vi.fn() }))
 const mock_download = vi.mocked(download)
@@ -1021,6 +1036,277 @@ describe(`Round-trip CIF and POSCAR exports`, () => {
   })
 })
 
+describe(`structure_to_lammps_data_str`, () => {
+  // Right-handed triclinic cell with a not along x, so export has to rotate into LAMMPS form
+  const lattice: Matrix3x3 = [
+    [0, 4, 0],
+    [-4, 0.5, 0.3],
+    [0.2, 0.1, 5],
+  ]
+  const crystal = make_crystal(lattice, [
+    { element: `Na`, abc: [0, 0, 0], properties: { velocity: [0.01, 0, -0.02] } },
+    { element: `Cl`, abc: [0.5, 0.5, 0.5], properties: { velocity: [0, 0, 0] } },
+    { element: `Na`, abc: [0.25, 0.1, 0.7], properties: { velocity: [0.003, 0.001, 0] } },
+  ])
+
+  test.each([`metal`, `real`, `si`] as const)(`%s round trip`, (units) => {
+    const content = structure_to_lammps_data_str(crystal, { units })
+    expect(content).toContain(`3 atoms\n2 atom types`)
+    expect(content).toMatch(/^1 \S+ # Na\n2 \S+ # Cl$/m)
+    expect(content).toContain(`Atoms # atomic`)
+    expect(content).toMatch(/xy xz yz$/m)
+
+    const parsed = parse_lammps_data(content, { units })
+    if (!parsed?.lattice) throw new Error(`expected periodic structure`)
+    for (const key of [`a`, `b`, `c`, `alpha`, `beta`, `gamma`, `volume`] as const) {
+      expect(parsed.lattice[key]).toBeCloseTo(crystal.lattice[key], 8)
+    }
+    // LAMMPS form: a along x, b in the xy plane
+    expect(parsed.lattice.matrix[0][1]).toBe(0)
+    expect(parsed.lattice.matrix[1][2]).toBe(0)
+    expect(parsed.sites.map((site) => site.species[0].element)).toEqual([`Na`, `Cl`, `Na`])
+    // velocities rotate with the box, so compare them in fractional (lattice) components
+    const parsed_to_frac = math.create_cart_to_frac(parsed.lattice.matrix)
+    const orig_to_frac = math.create_cart_to_frac(lattice)
+    parsed.sites.forEach((site, idx) => {
+      site.abc.forEach((coord, axis) => {
+        expect(coord).toBeCloseTo(crystal.sites[idx].abc[axis], 8)
+      })
+      const velocity = parsed_to_frac(site.properties?.velocity as Vec3)
+      const expected = orig_to_frac(crystal.sites[idx].properties?.velocity as Vec3)
+      velocity.forEach((val, axis) => expect(val).toBeCloseTo(expected[axis], 10))
+    })
+  })
+
+  test(`writes box, masses and velocities in the target units`, () => {
+    const cubic = make_crystal(4, [
+      { element: `Na`, abc: [0, 0, 0], properties: { velocity: [0.01, 0, -0.02] } },
+    ])
+    const metal = structure_to_lammps_data_str(cubic)
+    expect(metal).toMatch(/^0 4 xlo xhi$/m)
+    expect(metal).not.toContain(`xy xz yz`)
+    expect(metal).toMatch(/^Velocities\n\n1 10 0 -20$/m) // Å/fs -> Å/ps
+    const si = structure_to_lammps_data_str(cubic, { units: `si` })
+    expect(si).toMatch(/^0 4e-10 xlo xhi$/m)
+    expect(si).toMatch(/^1 3\.8\d+e-26 # Na$/m) // 22.99 amu in kg
+    expect(si).toMatch(/^Velocities\n\n1 1000 0 -2000$/m) // Å/fs -> m/s
+  })
+
+  test(`charge style writes site charges, falling back to oxidation states`, () => {
+    const nacl = make_crystal(5, [
+      { element: `Na`, abc: [0, 0, 0], properties: { charge: 0.8 } },
+      { element: `Cl`, abc: [0.5, 0.5, 0.5] },
+    ])
+    nacl.sites[1].species[0].oxidation_state = -1
+    const content = structure_to_lammps_data_str(nacl, { atom_style: `charge` })
+    expect(content).toContain(`Atoms # charge\n\n1 1 0.8 0 0 0\n2 2 -1 2.5 2.5 2.5\n`)
+    expect(content).not.toContain(`Velocities`)
+    const parsed = parse_lammps_data(content)
+    expect(parsed?.sites.map((site) => site.properties?.charge)).toEqual([0.8, -1])
+  })
+
+  test(`full style writes molecule IDs and typed topology sections`, () => {
+    // ethylene in a 10 Å box: C=C 1.33 Å, C-H 1.09 Å
+    const ethylene = make_crystal(10, [
+      { element: `C`, xyz: [0.665, 0, 0] },
+      { element: `C`, xyz: [-0.665, 0, 0] },
+      { element: `H`, xyz: [1.185, 0.958, 0] },
+      { element: `H`, xyz: [1.185, -0.958, 0] },
+      { element: `H`, xyz: [-1.185, 0.958, 0] },
+      { element: `H`, xyz: [-1.185, -0.958, 0] },
+    ])
+    const content = structure_to_lammps_data_str(ethylene, { atom_style: `full` })
+    expect(content).toContain(
+      `6 atoms\n5 bonds\n6 angles\n4 dihedrals\n2 impropers\n2 atom types\n`,
+    )
+    expect(content).toContain(`2 bond types # C-H C=C\n2 angle types # H-C-H C-C-H\n`)
+    expect(content).toContain(`1 dihedral types # H-C-C-H\n1 improper types # C-C-H-H\n`)
+    expect(content).toMatch(/^Atoms # full\n\n1 1 1 0 0\.665 0 0$/m)
+    expect(content).toContain(`\nBonds\n\n1 1 1 3\n2 1 1 4\n3 2 1 2\n`)
+    expect(content).toMatch(/\nAngles\n\n1 1 3 1 4\n/)
+    expect(content).toMatch(/\nDihedrals\n\n1 1 3 1 2 5\n/)
+    expect(content).toMatch(/\nImpropers\n\n1 1 1 3 4 2\n2 1 2 1 5 6\n$/)
+
+    const parsed = parse_lammps_data(content)
+    expect(parsed?.sites.map((site) => site.properties?.molecule_id)).toEqual(
+      Array(6).fill(1),
+    )
+
+    // an explicit topology is written as given; empty sections are omitted
+    const topology = {
+      bonds: [],
+      angles: [],
+      dihedrals: [],
+      impropers: [],
+      fragments: [[0], [1], [2], [3], [4], [5]],
+      molecule_ids: [0, 1, 2, 3, 4, 5],
+    }
+    const no_bonds = structure_to_lammps_data_str(ethylene, { atom_style: `full`, topology })
+    expect(no_bonds).toContain(`0 bonds\n0 angles\n0 dihedrals\n0 impropers\n`)
+    expect(no_bonds).toContain(`0 bond types\n`)
+    expect(no_bonds).not.toMatch(/^(Bonds|Angles|Dihedrals|Impropers)$/m)
+    expect(no_bonds).toMatch(/^6 6 2 0 -1\.185 -0\.958 0$/m)
+  })
+
+  test.each([
+    [`molecules`, { sites: crystal.sites } as AnyStructure, `No lattice information`],
+    [
+      `left-handed lattices`,
+      make_crystal(
+        [
+          [4, 0, 0],
+          [0, 4, 0],
+          [0, 0, -4],
+        ],
+        [[`Na`, [0, 0, 0]]],
+      ),
+      `requires a right-handed lattice`,
+    ],
+  ])(`throws for %s`, (_desc, structure, message) => {
+    expect(() => structure_to_lammps_data_str(structure)).toThrow(message)
+  })
+})
+
+describe(`structure_to_optimade`, () => {
+  const disordered = make_crystal(
+    [
+      [4, 0, 0],
+      [0, 4.2, 0],
+      [0.3, 0, 5],
+    ],
+    [
+      { element: `Fe`, abc: [0, 0, 0] },
+      { element: `Fe`, abc: [0.5, 0.5, 0], occu: 0.5 },
+      { element: `O`, abc: [0.5, 0, 0.5] },
+    ],
+  )
+  // Fe0.3 Ni0.7 mixed site
+  disordered.sites.push({
+    ...disordered.sites[0],
+    species: [
+      { element: `Fe`, occu: 0.3, oxidation_state: 0 },
+      { element: `Ni`, occu: 0.7, oxidation_state: 0 },
+    ],
+    abc: [0, 0.5, 0.5],
+    xyz: math.create_frac_to_cart(disordered.lattice.matrix)([0, 0.5, 0.5]),
+  })
+
+  test(`writes species with concentrations and vacancies for partial occupancies`, () => {
+    const { id, type, attributes } = structure_to_optimade(disordered, `test-1`)
+    expect([id, type]).toEqual([`test-1`, `structures`])
+    expect(attributes.species_at_sites).toEqual([`Fe`, `Fe2`, `O`, `FeNi`])
+    expect(attributes.species).toEqual([
+      { name: `Fe`, chemical_symbols: [`Fe`], concentration: [1] },
+      { name: `Fe2`, chemical_symbols: [`Fe`, `vacancy`], concentration: [0.5, 0.5] },
+      { name: `O`, chemical_symbols: [`O`], concentration: [1] },
+      { name: `FeNi`, chemical_symbols: [`Fe`, `Ni`], concentration: [0.3, 0.7] },
+    ])
+    expect(attributes.lattice_vectors).toEqual(disordered.lattice.matrix)
+    expect(attributes.dimension_types).toEqual([1, 1, 1])
+    expect(attributes).toMatchObject({
+      nsites: 4,
+      elements: [`Fe`, `Ni`, `O`],
+      nelements: 3,
+      structure_features: [`disorder`],
+    })
+  })
+
+  test(`round-trips through the OPTIMADE parsers`, () => {
+    const crystal = optimade_to_crystal(structure_to_optimade(disordered))
+    assert(crystal, `Failed to convert OPTIMADE structure`)
+    expect(crystal.lattice.matrix).toEqual(disordered.lattice.matrix)
+    expect(crystal.sites.map(({ species }) => species)).toEqual([
+      [{ element: `Fe`, occu: 1, oxidation_state: 0 }],
+      [{ element: `Fe`, occu: 0.5, oxidation_state: 0 }],
+      [{ element: `O`, occu: 1, oxidation_state: 0 }],
+      [
+        { element: `Ni`, occu: 0.7, oxidation_state: 0 },
+        { element: `Fe`, occu: 0.3, oxidation_state: 0 },
+      ],
+    ])
+    crystal.sites.forEach((site, idx) =>
+      site.xyz.forEach((val, dim) =>
+        expect(val).toBeCloseTo(disordered.sites[idx].xyz[dim], 12),
+      ),
+    )
+
+    const molecule: AnyStructure = { sites: disordered.sites.slice(0, 1) }
+    const json = structure_to_optimade_str(molecule)
+    expect(JSON.parse(json).data.attributes).toMatchObject({
+      dimension_types: [0, 0, 0],
+      nperiodic_dimensions: 0,
+      structure_features: [],
+    })
+    expect(parse_optimade_json(json)?.sites[0].species[0].element).toBe(`Fe`)
+    expect(() => structure_to_optimade(undefined)).toThrow(`No structure or sites`)
+  })
+})
+
+describe(`structure_to_cube_str`, () => {
+  const lattice: Matrix3x3 = [
+    [4, 0, 0],
+    [0.5, 5, 0],
+    [0, 0.3, 6],
+  ]
+  const crystal = make_crystal(lattice, [
+    [`O`, [0.1, 0.2, 0.3]],
+    [`Ti`, [0.5, 0.5, 0.5]],
+  ])
+  // 7 z points so each row spans two data lines
+  const make_volume = (origin: Vec3): VolumetricData => {
+    const grid = Array.from({ length: 3 }, (_, ix) =>
+      Array.from({ length: 4 }, (_, iy) =>
+        Array.from({ length: 7 }, (_, iz) => Math.sin(ix + 2 * iy) * 10 ** (iz - 3)),
+      ),
+    )
+    const data_range = grid_data_range(grid)
+    return { grid, grid_dims: [3, 4, 7], lattice, origin, data_range, periodic: true }
+  }
+
+  test(`round-trips through parse_cube`, () => {
+    const content = structure_to_cube_str(crystal, make_volume([0, 0, 0]), `test cube`)
+    const lines = content.split(`\n`)
+    expect(lines[0]).toBe(`test cube`)
+    expect(lines[2]).toMatch(/^ {4}2 +0\.000000 +0\.000000 +0\.000000$/)
+    expect(lines[6]).toMatch(/^ {4}8 +0\.000000/)
+    expect(lines[8]).toMatch(/^( +-?\d\.\d{5}E[+-]\d{2}){6}$/)
+    expect(lines[9]).toMatch(/^ +-?\d\.\d{5}E[+-]\d{2}$/)
+
+    const parsed = parse_cube(content)
+    if (!parsed) throw new Error(`expected parsed cube`)
+    const [volume] = parsed.volumes
+    expect(volume.grid_dims).toEqual([3, 4, 7])
+    expect(volume.periodic).toBe(true)
+    const [flat_lattice, flat_grid] = [lattice.flat(), make_volume([0, 0, 0]).grid.flat(2)]
+    volume.lattice.flat().forEach((val, idx) => expect(val).toBeCloseTo(flat_lattice[idx], 5))
+    volume.grid.flat(2).forEach((val, idx) => {
+      const expected = flat_grid[idx]
+      expect(Math.abs(val - expected)).toBeLessThanOrEqual(Math.abs(expected) * 1e-5)
+    })
+    expect(parsed.structure.sites.map((site) => site.species[0].element)).toEqual([`O`, `Ti`])
+    parsed.structure.sites.forEach((site, idx) => {
+      site.abc.forEach((val, axis) => expect(val).toBeCloseTo(crystal.sites[idx].abc[axis], 5))
+    })
+  })
+
+  test(`keeps atoms in place relative to a shifted grid origin`, () => {
+    const origin: Vec3 = [-2, 1, 0.5]
+    const parsed = parse_cube(structure_to_cube_str(crystal, make_volume(origin)))
+    if (!parsed) throw new Error(`expected parsed cube`)
+    parsed.volumes[0].origin.forEach((val, axis) => expect(val).toBeCloseTo(origin[axis], 5))
+    expect(parsed.volumes[0].periodic).toBe(false)
+    parsed.structure.sites.forEach((site, idx) => {
+      site.xyz.forEach((val, axis) => expect(val).toBeCloseTo(crystal.sites[idx].xyz[axis], 5))
+    })
+  })
+
+  test(`throws for grids that don't match grid_dims`, () => {
+    const volume = { ...make_volume([0, 0, 0]), grid_dims: [3, 4, 8] as Vec3 }
+    expect(() => structure_to_cube_str(crystal, volume)).toThrow(`does not match grid_dims`)
+    expect(() => structure_to_cube_str(undefined, volume)).toThrow(`No structure or sites`)
+  })
+})
+
 // Tests for 3D export color preservation (Issue #203)
 describe(`3D Export Color Preservation`, () => {
   describe(`extract_bond_color_for_instance`, () => {
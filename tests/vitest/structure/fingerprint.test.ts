@@ -0,0 +1,72 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import {
+  group_by_fingerprint,
+  make_supercell,
+  structure_fingerprint,
+  structure_fingerprint_key,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const fcc_abc: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+const rocksalt = (anion: `Cl` | `Br`, cation: `Na` | `K`, a_len: number) =>
+  make_crystal(a_len, [
+    ...fcc_abc.map((abc) => ({ element: cation, abc })),
+    ...fcc_abc.map((abc) => ({
+      element: anion,
+      abc: abc.map((coord) => (coord + 0.5) % 1) as Vec3,
+    })),
+  ])
+const nacl = rocksalt(`Cl`, `Na`, 5.64)
+
+describe(`structure_fingerprint`, () => {
+  test(`invariant to site order, supercells and cell setting`, () => {
+    const fingerprint = structure_fingerprint(nacl)
+    expect(fingerprint).toMatch(/^[0-9a-f]{16}$/)
+    const shuffled = { ...nacl, sites: nacl.sites.toReversed() }
+    expect(structure_fingerprint(shuffled)).toBe(fingerprint)
+    expect(structure_fingerprint(make_supercell(nacl, [2, 1, 3]))).toBe(fingerprint)
+
+    // primitive FCC cell of the same crystal
+    const half = 5.64 / 2
+    const primitive: Matrix3x3 = [
+      [0, half, half],
+      [half, 0, half],
+      [half, half, 0],
+    ]
+    const nacl_prim = make_crystal(primitive, [
+      { element: `Na`, abc: [0, 0, 0] },
+      { element: `Cl`, abc: [0.5, 0.5, 0.5] },
+    ])
+    expect(structure_fingerprint_key(nacl_prim)).toBe(structure_fingerprint_key(nacl))
+  })
+
+  test(`scale and anonymous options`, () => {
+    const kcl = rocksalt(`Cl`, `K`, 6.29)
+    const kbr = rocksalt(`Br`, `K`, 6.6)
+    expect(structure_fingerprint(kcl)).not.toBe(structure_fingerprint(nacl))
+    expect(structure_fingerprint(kcl, { anonymous: true })).toBe(
+      structure_fingerprint(nacl, { anonymous: true }),
+    )
+    expect(structure_fingerprint(kbr, { anonymous: true, scale: false })).not.toBe(
+      structure_fingerprint(nacl, { anonymous: true, scale: false }),
+    )
+    // same elements on a different lattice (CsCl type) don't collide
+    const cscl_type = make_crystal(3.2, [
+      [`Na`, [0, 0, 0]],
+      [`Cl`, [0.5, 0.5, 0.5]],
+    ])
+    expect(structure_fingerprint(cscl_type)).not.toBe(structure_fingerprint(nacl))
+  })
+
+  test(`group_by_fingerprint buckets duplicates`, () => {
+    const candidates = [nacl, rocksalt(`Br`, `K`, 6.6), make_supercell(nacl, 2)]
+    expect([...group_by_fingerprint(candidates).values()]).toEqual([[0, 2], [1]])
+    expect(() => structure_fingerprint(nacl, { precision: 0 })).toThrow(`precision`)
+  })
+})
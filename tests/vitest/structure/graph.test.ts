@@ -0,0 +1,82 @@
+import { batch_graphs, structure_to_graph, structures_to_graph_batch } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// CsCl: 8 nearest neighbors at a √3 / 2 ≈ 3.57 Å, the next 6 at a
+const a_cscl = 4.12
+const base = make_crystal(a_cscl, [
+  [`Cs`, [0, 0, 0], 1],
+  [`Cl`, [0.5, 0.5, 0.5], -1],
+])
+const cscl = {
+  ...base,
+  sites: [{ ...base.sites[0], properties: { magmom: [0, 3, 4] } }, base.sites[1]],
+}
+const water = {
+  sites: make_crystal(10, [
+    [`O`, [0, 0, 0]],
+    [`H`, [0.096, 0, 0]],
+    [`H`, [0, 0.096, 0]],
+  ]).sites,
+}
+
+describe(`structure_to_graph`, () => {
+  test(`cutoff graph of CsCl in flat arrays`, () => {
+    const graph = structure_to_graph(cscl, { cutoff: 3.7 })
+    expect([graph.n_nodes, graph.n_edges]).toEqual([2, 16])
+    expect([...graph.atomic_numbers]).toEqual([55, 17])
+    expect([...graph.charges]).toEqual([1, -1])
+    expect([...graph.magmoms]).toEqual([5, 0])
+    const [cs_x8, cl_x8] = [Array(8).fill(0), Array(8).fill(1)]
+    expect([...graph.edge_index.subarray(0, 16)]).toEqual([...cs_x8, ...cl_x8])
+    expect([...graph.edge_index.subarray(16)]).toEqual([...cl_x8, ...cs_x8])
+    for (let edge = 0; edge < graph.n_edges; edge++) {
+      expect(graph.edge_distances[edge]).toBeCloseTo((a_cscl * Math.sqrt(3)) / 2, 10)
+      const unit_vec = graph.edge_vectors.subarray(3 * edge, 3 * edge + 3)
+      expect(Math.hypot(...unit_vec)).toBeCloseTo(1, 12)
+      // Cl sits at the body center, so every edge points along a ⟨111⟩ direction
+      unit_vec.forEach((val) => expect(Math.abs(val)).toBeCloseTo(1 / Math.sqrt(3), 12))
+    }
+    // the image of each Cs → Cl edge matches the sign pattern of its unit vector
+    const images = Array.from({ length: 8 }, (_, edge) => [
+      ...graph.edge_images.subarray(3 * edge, 3 * edge + 3),
+    ])
+    expect(new Set(images.map((image) => image.join(`,`))).size).toBe(8)
+    images.forEach((image, edge) =>
+      image.forEach((shift, dim) =>
+        expect(Math.sign(graph.edge_vectors[3 * edge + dim])).toBe(shift === 0 ? 1 : -1),
+      ),
+    )
+  })
+
+  test(`k nearest neighbors reach beyond the first shell if needed`, () => {
+    const graph = structure_to_graph(cscl, { knn: 10 })
+    expect(graph.n_edges).toBe(20)
+    expect(graph.edge_distances[7]).toBeCloseTo((a_cscl * Math.sqrt(3)) / 2, 10)
+    expect(graph.edge_distances[9]).toBeCloseTo(a_cscl, 10)
+    // molecules connect to all other atoms when they have fewer than k
+    expect(structure_to_graph(water, { knn: 5 }).n_edges).toBe(6)
+    expect(() => structure_to_graph(cscl, { knn: 0 })).toThrow(`k must be a positive integer`)
+  })
+})
+
+describe(`batch_graphs`, () => {
+  test(`offsets edge indices by the nodes of preceding graphs`, () => {
+    const batch = structures_to_graph_batch([water, cscl], { cutoff: 3.7 })
+    const [mol_graph, crystal_graph] = [water, cscl].map((structure) =>
+      structure_to_graph(structure, { cutoff: 3.7 }),
+    )
+    expect([...batch.batch]).toEqual([0, 0, 0, 1, 1])
+    expect([...batch.node_ptr]).toEqual([0, 3, 5])
+    expect([...batch.edge_ptr]).toEqual([0, 6, 22])
+    expect([...batch.atomic_numbers]).toEqual([8, 1, 1, 55, 17])
+    const centers = batch.edge_index.subarray(0, batch.n_edges)
+    const neighbors = batch.edge_index.subarray(batch.n_edges)
+    expect([...centers.subarray(0, 6)]).toEqual([...mol_graph.edge_index.subarray(0, 6)])
+    expect([...neighbors.subarray(6)]).toEqual(
+      [...crystal_graph.edge_index.subarray(16)].map((idx) => idx + 3),
+    )
+    expect([...batch.edge_distances.subarray(6)]).toEqual([...crystal_graph.edge_distances])
+    expect(batch_graphs([]).n_nodes).toBe(0)
+  })
+})
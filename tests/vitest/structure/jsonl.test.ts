@@ -0,0 +1,102 @@
+import type { AnyStructure, Crystal } from '$lib/structure'
+import {
+  iter_jsonl_structures,
+  make_site,
+  read_jsonl_structures,
+  structure_to_pymatgen_dict,
+  structures_to_jsonl,
+  write_jsonl_structures,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const crystal: Crystal = {
+  ...make_crystal(4.2, [
+    [`Mg`, [0, 0, 0], 2],
+    [`O`, [0.5, 0.5, 0.5], -2],
+  ]),
+  id: `mgo-1`,
+  properties: { energy: -12.5 },
+}
+const molecule: AnyStructure = {
+  sites: [
+    make_site(`O`, [0, 0, 0], [0, 0, 0.12], `O1`),
+    make_site(`H`, [0, 0, 0], [0, 0.76, -0.47], `H1`),
+    make_site(`H`, [0, 0, 0], [0, -0.76, -0.47], `H2`),
+  ],
+  charge: 0,
+}
+
+const collect = async (structures: AsyncIterable<AnyStructure>) => {
+  const out: AnyStructure[] = []
+  for await (const structure of structures) out.push(structure)
+  return out
+}
+
+const expect_same = (actual: AnyStructure, expected: AnyStructure) => {
+  for (const key of [`label`, `xyz`, `species`] as const) {
+    const values = (structure: AnyStructure) => structure.sites.map((site) => site[key])
+    expect(values(actual)).toEqual(values(expected))
+  }
+  const lattice = (structure: AnyStructure) =>
+    `lattice` in structure ? structure.lattice : null
+  expect(lattice(actual)).toEqual(lattice(expected))
+  expect(actual.id).toBe(expected.id)
+}
+
+describe(`JSON Lines structures`, () => {
+  test(`pymatgen dicts distinguish Structure and Molecule`, () => {
+    const dict = structure_to_pymatgen_dict(crystal)
+    expect(dict[`@class`]).toBe(`Structure`)
+    expect(dict.lattice).toEqual({ matrix: crystal.lattice.matrix, pbc: [true, true, true] })
+    expect(dict.properties).toEqual({ energy: -12.5 })
+    const mol_dict = structure_to_pymatgen_dict(molecule)
+    expect(mol_dict[`@class`]).toBe(`Molecule`)
+    expect(mol_dict.lattice).toBeUndefined()
+    expect((mol_dict.sites as object[])[0]).toEqual({
+      species: [{ element: `O`, occu: 1 }],
+      xyz: [0, 0, 0.12],
+      label: `O1`,
+      properties: {},
+    })
+  })
+
+  test(`round-trips through text split into arbitrary chunks`, () => {
+    const text = [...structures_to_jsonl([crystal, molecule])].join(`\n`)
+    expect(text.split(`\n`).filter(Boolean)).toHaveLength(2)
+    const chunks = text.match(/[\s\S]{1,7}/g) ?? []
+    const [crystal_out, molecule_out, ...rest] = iter_jsonl_structures(chunks)
+    expect(rest).toEqual([])
+    expect_same(crystal_out, crystal)
+    expect(crystal_out.properties).toEqual({ energy: -12.5 })
+    expect_same(molecule_out, molecule)
+  })
+
+  test.each([undefined, `gzip`, `deflate`] as const)(
+    `streams bytes with compression %s`,
+    async (compression) => {
+      async function* produce() {
+        yield crystal
+        yield molecule
+      }
+      const stream = write_jsonl_structures(produce(), compression)
+      const structures = await collect(read_jsonl_structures(stream, compression))
+      expect(structures).toHaveLength(2)
+      expect_same(structures[0], crystal)
+      expect_same(structures[1], molecule)
+    },
+  )
+
+  test(`reads MP documents and reports bad lines by number`, () => {
+    const mp_doc = { material_id: `mp-1`, structure: structure_to_pymatgen_dict(crystal) }
+    const [from_doc] = iter_jsonl_structures(`${JSON.stringify(mp_doc)}\n\n`)
+    expect(from_doc.id).toBe(`mp-1`)
+    const valid = [...structures_to_jsonl([molecule])].join(``)
+    expect(() => [...iter_jsonl_structures(`${valid}\n{"sites": [`)]).toThrow(
+      `Invalid JSON on line 3`,
+    )
+    expect(() => [...iter_jsonl_structures(`${valid}{"energy": 1}`)]).toThrow(
+      `Line 2 is not a pymatgen structure`,
+    )
+  })
+})
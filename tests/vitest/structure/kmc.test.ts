@@ -0,0 +1,135 @@
+import { K_B_EV } from '$lib/constants'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { Hop, HopNetwork, HopSite } from '$lib/structure'
+import { kinetic_monte_carlo } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+
+const k_t = K_B_EV * 300
+
+// simple cubic lattice of 4³ sites 2 Å apart with nearest-neighbor hops along +a, +b, +c
+// (reverse hops are added by the kMC)
+function simple_cubic(hop_props: Partial<Hop>): HopNetwork {
+  const n_cell = 4
+  const lattice: Matrix3x3 = [
+    [8, 0, 0],
+    [0, 8, 0],
+    [0, 0, 8],
+  ]
+  const idx = ([x, y, z]: number[]) => (x * n_cell + y) * n_cell + z
+  const sites: HopSite[] = []
+  const hops: Hop[] = []
+  for (let x = 0; x < n_cell; x++) {
+    for (let y = 0; y < n_cell; y++) {
+      for (let z = 0; z < n_cell; z++) {
+        sites.push({ abc: [x / n_cell, y / n_cell, z / n_cell] })
+        for (let axis = 0; axis < 3; axis++) {
+          const target = [x, y, z]
+          const image: Vec3 = [0, 0, 0]
+          target[axis]++
+          if (target[axis] === n_cell) [target[axis], image[axis]] = [0, 1]
+          hops.push({ from: idx([x, y, z]), to: idx(target), image, ...hop_props })
+        }
+      }
+    }
+  }
+  return { lattice, sites, hops }
+}
+
+describe(`kinetic_monte_carlo`, () => {
+  test(`uncorrelated walkers without exclusion diffuse with D = Γa²`, () => {
+    const result = kinetic_monte_carlo(simple_cubic({ rate: 1 }), {
+      n_carriers: 50,
+      exclusion: false,
+      n_steps: 50_000,
+    })
+    expect(result.n_hops).toBe(50_000)
+    // 50 carriers hop at total rate 300, so the run covers ~167 time units
+    expect(result.time).toBeGreaterThan(160)
+    expect(result.time).toBeLessThan(175)
+    expect(result.tracer_diffusion).toBeGreaterThan(3.6)
+    expect(result.tracer_diffusion).toBeLessThan(4.4)
+    expect(result.correlation_factor).toBeGreaterThan(0.75)
+    expect(result.correlation_factor).toBeLessThan(1.3)
+    const total = result.site_occupancy.reduce((sum, occ) => sum + occ, 0)
+    expect(total).toBeCloseTo(50, 8)
+    expect(result.lag_times).toHaveLength(50)
+    expect(result.tracer_msd).toHaveLength(50)
+  })
+
+  test(`a single vacancy gives correlated tracer hops`, () => {
+    const result = kinetic_monte_carlo(simple_cubic({ rate: 1 }), {
+      n_carriers: 63,
+      n_steps: 50_000,
+    })
+    // f = 0.653 for vacancy diffusion on the simple cubic lattice
+    expect(result.correlation_factor).toBeGreaterThan(0.5)
+    expect(result.correlation_factor).toBeLessThan(0.85)
+    expect(Math.max(...result.site_occupancy)).toBeLessThanOrEqual(1)
+  })
+
+  test(`barriers set the clock, same seed reproduces the run`, () => {
+    const network = simple_cubic({ barrier: 0.3 })
+    const result = kinetic_monte_carlo(network, { n_steps: 20_000 })
+    const rate = 1e13 * Math.exp(-0.3 / k_t)
+    // one carrier with 6 hops of rate Γ waits 1 / 6Γ per hop on average
+    expect((result.time * 6 * rate) / 20_000).toBeCloseTo(1, 1)
+    const again = kinetic_monte_carlo(network, { n_steps: 20_000 })
+    expect(again.tracer_msd).toEqual(result.tracer_msd)
+    const other = kinetic_monte_carlo(network, { n_steps: 20_000, seed: 1 })
+    expect(other.time).not.toBe(result.time)
+  })
+
+  test(`reverse hops obey detailed balance for site energies`, () => {
+    // ring of an A site and a B site 0.05 eV higher, hops listed only from A
+    const network: HopNetwork = {
+      lattice: [
+        [4, 0, 0],
+        [0, 4, 0],
+        [0, 0, 4],
+      ],
+      sites: [{ abc: [0, 0, 0] }, { abc: [0.5, 0, 0], energy: 0.05 }],
+      hops: [
+        { from: 0, to: 1, rate: 1 },
+        { from: 0, to: 1, image: [-1, 0, 0], rate: 1 },
+      ],
+    }
+    const result = kinetic_monte_carlo(network, { n_steps: 20_000, n_equilibration: 100 })
+    const [occ_a, occ_b] = result.site_occupancy
+    expect(occ_a + occ_b).toBeCloseTo(1, 10)
+    expect(occ_b / occ_a).toBeCloseTo(Math.exp(-0.05 / k_t), 1)
+    // explicitly listed reverse hops are kept as is, here trapping the carrier on B
+    const one_way: HopNetwork = {
+      ...network,
+      hops: [
+        ...network.hops,
+        { from: 1, to: 0, rate: 0 },
+        { from: 1, to: 0, image: [1, 0, 0], rate: 0 },
+      ],
+    }
+    expect(() => kinetic_monte_carlo(one_way)).toThrow(`No allowed hops left`)
+  })
+
+  test(`rejects invalid input`, () => {
+    const network = simple_cubic({ rate: 1 })
+    expect(() => kinetic_monte_carlo(network, { n_carriers: 0 })).toThrow(
+      `n_carriers must be a positive integer`,
+    )
+    expect(() => kinetic_monte_carlo(network, { n_carriers: 65 })).toThrow(
+      `65 carriers don't fit on 64 sites`,
+    )
+    expect(() => kinetic_monte_carlo(network, { n_samples: 1 })).toThrow(`n_samples must be`)
+    expect(() => kinetic_monte_carlo(network, { initial_sites: [0, 0] })).toThrow(
+      `initial_sites must be distinct`,
+    )
+    expect(() => kinetic_monte_carlo(network, { initial_sites: [64] })).toThrow(
+      `valid site indices`,
+    )
+    expect(() => kinetic_monte_carlo(network, { n_carriers: 64 })).toThrow(
+      `No allowed hops left`,
+    )
+    const bad_hop = { ...network, hops: [{ from: 0, to: 99, rate: 1 }] }
+    expect(() => kinetic_monte_carlo(bad_hop)).toThrow(`Hop 0 → 99 references a missing site`)
+    const no_rate = { ...network, hops: [{ from: 0, to: 1 }] }
+    expect(() => kinetic_monte_carlo(no_rate)).toThrow(`needs a non-negative rate`)
+  })
+})
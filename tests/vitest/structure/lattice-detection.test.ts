@@ -0,0 +1,81 @@
+import type { ElementSymbol } from '$lib/element'
+import type { Vec3 } from '$lib/math'
+import type { Molecule } from '$lib/structure'
+import { detect_lattice } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+
+const a_len = 5.64
+const fcc: Vec3[] = [
+  [0, 0, 0],
+  [0.5, 0.5, 0],
+  [0.5, 0, 0.5],
+  [0, 0.5, 0.5],
+]
+const make_cloud = (points: [ElementSymbol, Vec3][]): Molecule => ({
+  sites: points.map(([element, xyz], idx) => ({
+    species: [{ element, occu: 1, oxidation_state: 0 }],
+    abc: [0, 0, 0],
+    xyz,
+    label: `${element}${idx}`,
+    properties: {},
+  })),
+})
+
+// 4x4x4 conventional cells of rocksalt with deterministic ±0.03 Å noise
+const rocksalt_points: [ElementSymbol, Vec3][] = []
+for (let cell = 0; cell < 64; cell++) {
+  const shift = [cell % 4, Math.floor(cell / 4) % 4, Math.floor(cell / 16)]
+  for (const frac of fcc) {
+    for (const [element, offset] of [
+      [`Na`, 0],
+      [`Cl`, 0.5],
+    ] as const) {
+      const idx = rocksalt_points.length
+      const xyz = frac.map((coord, dim) => {
+        const noise = 0.03 * Math.sin(idx * 1.3 + dim)
+        return (coord + shift[dim] + (dim === 0 ? offset : 0)) * a_len + noise
+      }) as Vec3
+      rocksalt_points.push([element, xyz])
+    }
+  }
+}
+
+describe(`detect_lattice`, () => {
+  test(`recovers the primitive rocksalt cell and its two-atom basis`, () => {
+    const { structure, rms_residual, outliers, n_cells } = detect_lattice(
+      make_cloud(rocksalt_points),
+    )
+    const { a, b, c, alpha, beta, gamma, volume } = structure.lattice
+    for (const length of [a, b, c]) expect(length).toBeCloseTo(a_len / Math.SQRT2, 1)
+    for (const angle of [alpha, beta, gamma]) {
+      expect([60, 90, 120]).toContain(Math.round(angle))
+    }
+    expect(volume / (a_len ** 3 / 4)).toBeCloseTo(1, 1)
+    expect(structure.sites.map(({ species }) => species[0].element).toSorted()).toEqual([
+      `Cl`,
+      `Na`,
+    ])
+    expect(rms_residual).toBeLessThan(0.06)
+    expect(outliers).toEqual([])
+    expect(n_cells).toBe(256)
+  })
+
+  test(`isolated interstitials are reported as outliers`, () => {
+    const with_defect = [...rocksalt_points, [`Na`, [6.1, 6.3, 6.2]] as [ElementSymbol, Vec3]]
+    const { structure, outliers, residuals } = detect_lattice(make_cloud(with_defect))
+    expect(structure.sites).toHaveLength(2)
+    expect(outliers).toEqual([rocksalt_points.length])
+    expect(residuals[rocksalt_points.length]).toBeNaN()
+  })
+
+  test(`2D sheets and tiny clouds throw`, () => {
+    const sheet: [ElementSymbol, Vec3][] = Array.from({ length: 36 }, (_, idx) => [
+      `C`,
+      [(idx % 6) * 2.5, Math.floor(idx / 6) * 2.5, 0],
+    ])
+    expect(() => detect_lattice(make_cloud(sheet))).toThrow(
+      `Found only 2 independent lattice translations`,
+    )
+    expect(() => detect_lattice(make_cloud(sheet.slice(0, 3)))).toThrow(`at least 4 points`)
+  })
+})
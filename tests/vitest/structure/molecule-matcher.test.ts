@@ -0,0 +1,168 @@
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import {
+  group_medoid,
+  group_molecules,
+  make_site,
+  molecule_rmsd,
+  molecules_match,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+
+const molecule = (atoms: [ElementSymbol, Vec3][]): AnyStructure => ({
+  sites: atoms.map(([element, xyz], idx) =>
+    make_site(element, [0, 0, 0], xyz, `${element}${idx + 1}`),
+  ),
+})
+
+// rotation by angle (radians) about z, then about x
+const rotation = (angle: number): Matrix3x3 => {
+  const [cos, sin] = [Math.cos(angle), Math.sin(angle)]
+  const rot_z: Matrix3x3 = [
+    [cos, -sin, 0],
+    [sin, cos, 0],
+    [0, 0, 1],
+  ]
+  const rot_x: Matrix3x3 = [
+    [1, 0, 0],
+    [0, cos, -sin],
+    [0, sin, cos],
+  ]
+  return math.dot(rot_x, rot_z)
+}
+const transform = (mol: AnyStructure, rot: Matrix3x3, shift: Vec3): AnyStructure => ({
+  sites: mol.sites.map((site) => ({
+    ...site,
+    xyz: math.add(math.mat3x3_vec3_multiply(rot, site.xyz), shift),
+  })),
+})
+
+// tetrahedral CHFClBr, chiral with bond lengths to tell the substituents apart
+const tetra: Vec3[] = [
+  [1, 1, 1],
+  [1, -1, -1],
+  [-1, 1, -1],
+  [-1, -1, 1],
+]
+const bonds: [ElementSymbol, number][] = [
+  [`H`, 1.09],
+  [`F`, 1.35],
+  [`Cl`, 1.77],
+  [`Br`, 1.94],
+]
+const chfclbr = molecule([
+  [`C`, [0, 0, 0]],
+  ...bonds.map(([element, length], idx): [ElementSymbol, Vec3] => [
+    element,
+    math.scale(math.normalize_vec(tetra[idx]), length),
+  ]),
+])
+const mirror_image: AnyStructure = {
+  sites: chfclbr.sites.map((site) => ({
+    ...site,
+    xyz: [-site.xyz[0], site.xyz[1], site.xyz[2]] as Vec3,
+  })),
+}
+const methane = molecule([
+  [`C`, [0, 0, 0]],
+  ...tetra.map((vec): [ElementSymbol, Vec3] => [
+    `H`,
+    math.scale(math.normalize_vec(vec), 1.09),
+  ]),
+])
+
+describe(`molecule_rmsd`, () => {
+  test(`finds zero RMSD and the atom mapping for shuffled, rotated, shifted copies`, () => {
+    const moved = transform(methane, rotation(0.7), [3, -2, 5])
+    const shuffled = { sites: [3, 0, 4, 2, 1].map((idx) => moved.sites[idx]) }
+    const fit = molecule_rmsd(methane, shuffled)
+    expect(fit?.rmsd).toBeCloseTo(0, 8)
+    expect(fit?.mapping[0]).toBe(1) // C moved to index 1
+    expect(fit?.inverted).toBe(false)
+    expect(math.det_3x3(fit?.rotation ?? rotation(0))).toBeCloseTo(1, 10)
+    expect(molecules_match(methane, shuffled)).toBe(true)
+  })
+
+  test(`RMSD of a perturbed copy is at most the displacement RMS`, () => {
+    const perturbed = {
+      sites: methane.sites.map((site, idx) => ({
+        ...site,
+        xyz: math.add(site.xyz, idx === 1 ? [0, 0, 0.1] : ([0, 0, -0.025] as Vec3)),
+      })),
+    }
+    // displacements are centroid-free, so the best fit leaves them (nearly) in place
+    const expected = Math.sqrt((0.1 ** 2 + 4 * 0.025 ** 2) / 5)
+    const fit = molecule_rmsd(methane, transform(perturbed, rotation(2.1), [1, 1, 1]))
+    expect(fit?.rmsd).toBeLessThanOrEqual(expected + 1e-9)
+    expect(fit?.rmsd).toBeGreaterThan(0.02)
+  })
+
+  test(`enantiomers only match with allow_inversion`, () => {
+    const other_hand = transform(mirror_image, rotation(1.3), [0, 4, 0])
+    const proper = molecule_rmsd(chfclbr, other_hand)
+    expect(proper?.rmsd).toBeGreaterThan(0.3)
+    expect(molecules_match(chfclbr, other_hand)).toBe(false)
+    const improper = molecule_rmsd(chfclbr, other_hand, { allow_inversion: true })
+    expect(improper?.rmsd).toBeCloseTo(0, 8)
+    expect(improper?.inverted).toBe(true)
+    expect(improper?.mapping).toEqual([0, 1, 2, 3, 4])
+  })
+
+  test(`permute: false pairs atoms by index and requires equal element order`, () => {
+    const moved = transform(methane, rotation(0.4), [0, 0, 0])
+    expect(molecule_rmsd(methane, moved, { permute: false })?.rmsd).toBeCloseTo(0, 8)
+    const swapped = { sites: [moved.sites[1], moved.sites[0], ...moved.sites.slice(2)] }
+    expect(molecule_rmsd(methane, swapped, { permute: false })).toBeNull()
+    // swapping two H is a reflection, which no rotation undoes without reordering
+    const h_swapped = { sites: [0, 2, 1, 3, 4].map((idx) => moved.sites[idx]) }
+    expect(molecule_rmsd(methane, h_swapped, { permute: false })?.rmsd).toBeGreaterThan(0.1)
+    expect(molecule_rmsd(methane, h_swapped)?.rmsd).toBeCloseTo(0, 8)
+  })
+
+  test(`returns null for different compositions and throws without sites`, () => {
+    expect(molecule_rmsd(methane, chfclbr)).toBeNull()
+    expect(molecules_match(methane, { sites: methane.sites.slice(1) })).toBe(false)
+    expect(() => molecule_rmsd({ sites: [] }, { sites: [] })).toThrow(`without sites`)
+  })
+})
+
+describe(`group_molecules`, () => {
+  test(`groups matches and keeps enantiomers apart unless inversion is allowed`, () => {
+    const structures = [
+      chfclbr,
+      methane,
+      transform(chfclbr, rotation(0.5), [1, 0, 0]),
+      mirror_image,
+      transform(methane, rotation(2.5), [0, 0, 2]),
+    ]
+    expect(group_molecules(structures)).toEqual([[0, 2], [1, 4], [3]])
+    expect(group_molecules(structures, { allow_inversion: true })).toEqual([
+      [0, 2, 3],
+      [1, 4],
+    ])
+  })
+})
+
+describe(`group_medoid`, () => {
+  // methane with one C-H bond stretched (or compressed), as from a noisy relaxation
+  const distorted = (stretch: number) => ({
+    sites: methane.sites.map((site, idx) =>
+      idx === 1 ? { ...site, xyz: math.scale(site.xyz, 1 + stretch / 1.09) } : site,
+    ),
+  })
+
+  test(`picks the member closest to all others as representative`, () => {
+    const structures = [
+      distorted(0.08),
+      chfclbr,
+      transform(methane, rotation(0.9), [2, 0, 0]),
+      distorted(-0.08),
+    ]
+    expect(group_medoid(structures, [0, 2, 3])).toBe(2)
+    expect(group_molecules(structures)).toEqual([[0, 2, 3], [1]])
+    expect(group_molecules(structures, { representative: `medoid` })).toEqual([[2, 0, 3], [1]])
+    expect(() => group_medoid(structures, [])).toThrow(`empty group`)
+  })
+})
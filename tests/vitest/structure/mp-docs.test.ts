@@ -0,0 +1,104 @@
+import { from_mp_document, is_mp_document } from '$lib/structure'
+import { parse_structure_file } from '$lib/structure/parse'
+import { describe, expect, test } from 'vitest'
+
+// pymatgen Structure.as_dict() of rock salt LiF (2 sites of the primitive cell)
+const pmg_structure = (a: number) => ({
+  '@module': `pymatgen.core.structure`,
+  '@class': `Structure`,
+  charge: 0,
+  lattice: {
+    matrix: [
+      [0, a / 2, a / 2],
+      [a / 2, 0, a / 2],
+      [a / 2, a / 2, 0],
+    ],
+    pbc: [true, true, true],
+  },
+  sites: [
+    {
+      species: [{ element: `Li`, occu: 1, oxidation_state: 1 }],
+      abc: [0, 0, 0],
+      xyz: [0, 0, 0],
+      label: `Li`,
+      properties: { magmom: 0 },
+    },
+    {
+      species: [{ element: `F`, occu: 1 }],
+      abc: [0.5, 0.5, 0.5],
+      xyz: [a / 2, a / 2, a / 2],
+      label: `F`,
+      properties: {},
+    },
+  ],
+})
+
+const summary_doc = {
+  material_id: `mp-1138`,
+  formula_pretty: `LiF`,
+  energy_above_hull: 0,
+  band_gap: 8.7,
+  is_stable: true,
+  symmetry: { symbol: `Fm-3m` },
+  structure: pmg_structure(4.03),
+}
+
+const task_doc = {
+  task_id: `mp-1234567`,
+  task_type: `Structure Optimization`,
+  input: { structure: pmg_structure(4.2) },
+  output: { structure: pmg_structure(4.03), energy_per_atom: -4.9, bandgap: 8.6 },
+}
+
+describe(`Materials Project documents`, () => {
+  test(`summary docs keep id, structure and headline properties`, () => {
+    const structure = from_mp_document(summary_doc)
+    expect(structure.id).toBe(`mp-1138`)
+    expect(structure.properties).toEqual({
+      formula_pretty: `LiF`,
+      energy_above_hull: 0,
+      band_gap: 8.7,
+      is_stable: true,
+    })
+    if (!(`lattice` in structure)) throw new Error(`expected a crystal`)
+    expect(structure.lattice.volume).toBeCloseTo(4.03 ** 3 / 4, 10)
+    const [li_species] = structure.sites[0].species
+    expect(li_species).toEqual({ element: `Li`, occu: 1, oxidation_state: 1 })
+    expect(structure.sites[0].properties).toEqual({ magmom: 0 })
+  })
+
+  test(`structures go through the pymatgen JSON parser`, () => {
+    const structure = pmg_structure(4)
+    structure.sites[1].abc = [1.5, -0.5, 0.5]
+    const doc = { material_id: `mp-1`, structure }
+    const parsed = from_mp_document(doc)
+    // fractional coordinates are wrapped into the cell like for pymatgen JSON files
+    expect(parsed.sites[1].abc).toEqual([0.5, 0.5, 0.5])
+    expect(parsed.sites[1].xyz).toEqual([2, 2, 2])
+    expect(parse_structure_file(JSON.stringify(structure), `LiF.json`).sites).toEqual(
+      parsed.sites,
+    )
+  })
+
+  test(`task docs use the relaxed output structure`, () => {
+    const structure = from_mp_document(task_doc)
+    expect(structure.id).toBe(`mp-1234567`)
+    expect(structure.properties).toEqual({
+      task_type: `Structure Optimization`,
+      energy_per_atom: -4.9,
+      bandgap: 8.6,
+    })
+    expect(structure.sites[1].xyz).toEqual([2.015, 2.015, 2.015])
+  })
+
+  test(`API responses and JSON files are detected`, () => {
+    const response = { data: [summary_doc], meta: { total_doc: 1 } }
+    expect(is_mp_document(response)).toBe(true)
+    expect(from_mp_document(response).id).toBe(`mp-1138`)
+    const parsed = parse_structure_file(JSON.stringify(task_doc), `task.json`)
+    expect(parsed.sites[1].xyz).toEqual([2.015, 2.015, 2.015])
+
+    expect(is_mp_document({ structure: pmg_structure(4) })).toBe(false) // no id
+    expect(() => from_mp_document({ material_id: `mp-1` })).toThrow(`Not a Materials Project`)
+  })
+})
@@ -0,0 +1,238 @@
+import type { Vec3 } from '$lib/math'
+import {
+  get_k_nearest,
+  get_knn_neighbor_list,
+  get_neighbor_list,
+  NeighborList,
+  perturb_structure,
+  rattle_structure,
+} from '$lib/structure'
+import type { Crystal, Neighbor, Pbc } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// site index, image and rounded distance of each neighbor to compare lists computed
+// along different paths
+const summarize = (neighbor_list: Neighbor[][]) =>
+  neighbor_list.map((neighbors) =>
+    neighbors.map(({ site_idx, image, distance }) => [
+      site_idx,
+      ...image,
+      distance.toFixed(8),
+    ]),
+  )
+
+describe(`get_neighbor_list`, () => {
+  test(`fcc shells include periodic images and are sorted by distance`, () => {
+    const fcc = make_crystal(3.6, [
+      [`Cu`, [0, 0, 0]],
+      [`Cu`, [0.5, 0.5, 0]],
+      [`Cu`, [0.5, 0, 0.5]],
+      [`Cu`, [0, 0.5, 0.5]],
+    ])
+    const neighbors = get_neighbor_list(fcc, 3.7)
+    for (const site_neighbors of neighbors) {
+      // 12 nearest neighbors at a/√2 plus 6 second neighbors at a
+      expect(site_neighbors).toHaveLength(18)
+      expect(site_neighbors[0].distance).toBeCloseTo(3.6 / Math.SQRT2, 10)
+      expect(site_neighbors[17].distance).toBeCloseTo(3.6, 10)
+    }
+    // second shell of a site is made of its own periodic images
+    const self_images = neighbors[0].filter((nb) => nb.site_idx === 0)
+    expect(self_images).toHaveLength(6)
+    expect(self_images.map((nb) => nb.image)).toContainEqual([0, 0, -1])
+  })
+
+  test(`images stay consistent with displacements for sites outside the unit cell`, () => {
+    const crystal = make_crystal(4, [
+      [`Na`, [0.1, 0.1, 0.1]],
+      [`Cl`, [1.4, 0.1, 0.1]], // unwrapped frac coords: x = 1.4
+    ])
+    const [na_neighbors] = get_neighbor_list(crystal, 2.5)
+    const cl = na_neighbors.find((nb) => nb.site_idx === 1)
+    expect(cl?.distance).toBeCloseTo(1.2, 10)
+    expect(cl?.image).toEqual([-1, 0, 0])
+    expect(cl?.displacement).toEqual([1.2, 0, 0].map((val) => expect.closeTo(val, 10)))
+  })
+
+  test(`respects non-periodic axes`, () => {
+    const slab = make_crystal(3, [[`Fe`, [0, 0, 0]]], { pbc: [true, true, false] })
+    const [fe_neighbors] = get_neighbor_list(slab, 3.1)
+    expect(fe_neighbors).toHaveLength(4)
+    expect(fe_neighbors.every((nb) => nb.image[2] === 0)).toBe(true)
+  })
+
+  test(`molecules use plain Cartesian distances`, () => {
+    const molecule = {
+      sites: make_crystal(10, [
+        [`O`, [0, 0, 0]],
+        [`H`, [0.096, 0, 0]],
+        [`H`, [0, 0.096, 0]],
+      ]).sites,
+    }
+    const neighbors = get_neighbor_list(molecule, 1)
+    expect(neighbors[0].map((nb) => nb.site_idx).toSorted()).toEqual([1, 2])
+    expect(neighbors[1].map((nb) => nb.site_idx)).toEqual([0])
+    expect(() => get_neighbor_list(molecule, 0)).toThrow(`cutoff must be > 0`)
+  })
+
+  test.each<[string, Pbc | null]>([
+    [`3d periodic`, [true, true, true]],
+    [`slab`, [true, true, false]],
+    [`molecule`, null],
+  ])(`linked cells reproduce the all-pairs list of a %s`, (_, pbc) => {
+    // rattled 4×4×4 supercell of fcc Cu (256 sites), some pushed just outside the cell
+    const fcc_abc: Vec3[] = [
+      [0, 0, 0],
+      [0.5, 0.5, 0],
+      [0.5, 0, 0.5],
+      [0, 0.5, 0.5],
+    ]
+    const site_inputs = [0, 1, 2, 3].flatMap((ia) =>
+      [0, 1, 2, 3].flatMap((ib) =>
+        [0, 1, 2, 3].flatMap((ic) =>
+          fcc_abc.map(([fa, fb, fc]): [string, Vec3] => [
+            `Cu`,
+            [(ia + fa) / 4, (ib + fb) / 4, (ic + fc) / 4],
+          ]),
+        ),
+      ),
+    )
+    const crystal = make_crystal(14.4, site_inputs, { pbc: pbc ?? [true, true, true] })
+    const rattled = rattle_structure(pbc ? crystal : { sites: crystal.sites }, 0.1, {
+      seed: 5,
+    })
+    const brute_force = get_neighbor_list(rattled, 3, { method: `brute_force` })
+    // default switches to linked cells for 100+ sites
+    expect(summarize(get_neighbor_list(rattled, 3))).toEqual(summarize(brute_force))
+    expect(brute_force.flat().length).toBeGreaterThan(2000)
+  })
+})
+
+describe(`get_knn_neighbor_list`, () => {
+  const fcc = make_crystal(3.6, [
+    [`Cu`, [0, 0, 0]],
+    [`Cu`, [0.5, 0.5, 0]],
+    [`Cu`, [0.5, 0, 0.5]],
+    [`Cu`, [0, 0.5, 0.5]],
+  ])
+
+  test(`cuts through degenerate shells by site index and image`, () => {
+    const neighbors = get_knn_neighbor_list(fcc, 14)
+    expect(neighbors.map((nbs) => nbs.length)).toEqual([14, 14, 14, 14])
+    // the 6-fold second shell of site 0 are its own images, lowest images come first
+    const second_shell = neighbors[0].slice(12)
+    expect(second_shell.map(({ site_idx }) => site_idx)).toEqual([0, 0])
+    expect(second_shell.map(({ image }) => image)).toEqual([
+      [-1, 0, 0],
+      [0, -1, 0],
+    ])
+    expect(second_shell[0].distance).toBeCloseTo(3.6, 10)
+    // the first shell is ordered by site index too
+    const first_shell = neighbors[0].slice(0, 12).map(({ site_idx }) => site_idx)
+    expect(first_shell).toEqual([1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3])
+  })
+
+  test(`tie tolerance completes the shell of the k-th neighbor`, () => {
+    const lengths = (k: number, tie_tolerance: number) =>
+      get_knn_neighbor_list(fcc, k, { tie_tolerance }).map((nbs) => nbs.length)
+    expect(lengths(12, 0.01)).toEqual([12, 12, 12, 12])
+    expect(lengths(13, 0.01)).toEqual([18, 18, 18, 18])
+    expect(lengths(10, 0.01)).toEqual([12, 12, 12, 12])
+  })
+
+  test(`molecules and invalid input`, () => {
+    const molecule = {
+      sites: make_crystal(10, [
+        [`O`, [0, 0, 0]],
+        [`H`, [0.096, 0, 0]],
+        [`H`, [0, 0.096, 0]],
+      ]).sites,
+    }
+    const neighbors = get_knn_neighbor_list(molecule, 5)
+    expect(neighbors.map((nbs) => nbs.map(({ site_idx }) => site_idx))).toEqual([
+      [1, 2],
+      [0, 2],
+      [0, 1],
+    ])
+    expect(() => get_knn_neighbor_list(fcc, 0)).toThrow(`k must be a positive integer`)
+    expect(() => get_knn_neighbor_list(fcc, 1, { tie_tolerance: -1 })).toThrow(
+      `tie_tolerance must be >= 0`,
+    )
+  })
+
+  test(`get_k_nearest queries one site like the full kNN list`, () => {
+    for (const [k, tie_tolerance] of [
+      [12, 0],
+      [14, 0],
+      [13, 0.01],
+      [50, 0],
+    ]) {
+      const nearest = get_k_nearest(fcc, 2, k, { tie_tolerance })
+      expect(summarize([nearest])).toEqual(
+        summarize(get_knn_neighbor_list(fcc, k, { tie_tolerance }).slice(2, 3)),
+      )
+    }
+    expect(get_k_nearest(fcc, 2, 13, { tie_tolerance: 0.01 })).toHaveLength(18)
+    const [first] = get_k_nearest(fcc, 0, 1)
+    expect(first).toMatchObject({ site_idx: 1, image: [-1, -1, 0] })
+    expect(first.distance).toBeCloseTo(3.6 / Math.SQRT2, 10)
+    expect(() => get_k_nearest(fcc, 4, 1)).toThrow(`Site index 4 out of range`)
+    expect(() => get_k_nearest(fcc, 0, 1.5)).toThrow(`k must be a positive integer`)
+  })
+})
+
+describe(`NeighborList`, () => {
+  const fcc = make_crystal(3.6, [
+    [`Cu`, [0, 0, 0]],
+    [`Cu`, [0.5, 0.5, 0]],
+    [`Cu`, [0.5, 0, 0.5]],
+    [`Cu`, [0, 0.5, 0.5]],
+  ])
+  const wrap = (structure: Crystal): Crystal => ({
+    ...structure,
+    sites: structure.sites.map((site) => {
+      const abc = site.abc.map((val) => val - Math.floor(val)) as Vec3
+      return { ...site, abc, xyz: abc.map((val) => val * 3.6) as Vec3 }
+    }),
+  })
+
+  test(`reuses the padded list while atoms move less than half the skin`, () => {
+    const neighbor_list = new NeighborList(2.7, 0.5)
+    const expected = summarize(get_neighbor_list(fcc, 2.7))
+    expect(summarize(neighbor_list.update(fcc))).toEqual(expected)
+    for (const seed of [1, 2, 3]) {
+      // moves of 0.2 Å push some nearest neighbors (2.55 Å) beyond the cutoff
+      const moved = perturb_structure(fcc, 0.2, { seed })
+      expect(summarize(neighbor_list.update(moved))).toEqual(
+        summarize(get_neighbor_list(moved, 2.7)),
+      )
+      // wrapping atoms back into the cell shifts images instead of forcing a rebuild
+      const wrapped = wrap(moved)
+      expect(summarize(neighbor_list.update(wrapped))).toEqual(
+        summarize(get_neighbor_list(wrapped, 2.7)),
+      )
+    }
+    expect(neighbor_list.n_builds).toBe(1)
+  })
+
+  test(`rebuilds after large moves or cell changes`, () => {
+    const neighbor_list = new NeighborList(2.7, 0.5)
+    neighbor_list.update(fcc)
+    const moved = perturb_structure(fcc, 0.3, { seed: 4 })
+    expect(summarize(neighbor_list.update(moved))).toEqual(
+      summarize(get_neighbor_list(moved, 2.7)),
+    )
+    expect(neighbor_list.n_builds).toBe(2)
+    const strained = make_crystal(
+      3.5,
+      fcc.sites.map(({ abc }): [string, Vec3] => [`Cu`, abc]),
+    )
+    expect(summarize(neighbor_list.update(strained))).toEqual(
+      summarize(get_neighbor_list(strained, 2.7)),
+    )
+    expect(neighbor_list.n_builds).toBe(3)
+    expect(() => new NeighborList(0)).toThrow(`cutoff must be > 0`)
+    expect(() => new NeighborList(3, -1)).toThrow(`skin must be >= 0`)
+  })
+})
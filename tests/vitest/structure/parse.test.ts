@@ -1,14 +1,19 @@
 import type { OptimadeStructure } from '$lib/api/optimade'
 import type { Matrix3x3, Vec3 } from '$lib/math'
 import { mat3x3_vec3_multiply, transpose_3x3_matrix } from '$lib/math'
-import type { ParsedStructure } from '$lib/structure/parse'
+import type { CifBlock, ParsedStructure } from '$lib/structure/parse'
 import {
   detect_structure_type,
+  get_parse_warnings,
   is_optimade_json,
   is_structure_file,
+  iter_cif_blocks,
+  iter_cif_blocks_async,
   optimade_to_crystal,
   parse_any_structure,
   parse_cif,
+  parse_cif_with_report,
+  parse_lammps_data,
   parse_optimade_json,
   parse_phonopy_yaml,
   parse_poscar,
@@ -1576,6 +1581,103 @@ Na Na 0.000 0.000 0.000`
   })
 })
 
+describe(`LAMMPS data Parser`, () => {
+  // Triclinic NaCl-like cell shifted off the origin, atoms listed out of ID order
+  const full_data = `LAMMPS data file via write_data, timestep = 0, units = metal
+
+3 atoms
+2 atom types
+
+-1.0 4.0 xlo xhi
+0.5 5.5 ylo yhi
+0.0 6.0 zlo zhi
+1.0 0.0 0.5 xy xz yz
+
+Masses
+
+1 22.98977 # Na
+2 35.453
+
+Atoms # full
+
+2 1 2 -1.0 3.5 0.5 3.0 0 0 1
+1 1 1 1.0 -1.0 0.5 0.0 0 0 0
+3 2 1 1.0 1.5 3.0 1.5
+
+Velocities
+
+1 10.0 0.0 -20.0
+2 0.0 0.0 0.0
+3 1.0 2.0 3.0
+`
+
+  test(`parses full style: elements from masses, box, charges, molecules, velocities`, () => {
+    const result = parse_lammps_data(full_data)
+    if (!result?.lattice) throw new Error(`expected a parsed periodic structure`)
+    expect(result.lattice.matrix).toEqual([
+      [5, 0, 0],
+      [1, 5, 0],
+      [0, 0.5, 6],
+    ])
+    expect(result.sites.map((site) => site.species[0].element)).toEqual([`Na`, `Cl`, `Na`])
+    // positions are shifted by the box origin and sorted by atom ID
+    expect(result.sites.map((site) => site.xyz)).toEqual([
+      [0, 0, 0],
+      [4.5, 0, 3],
+      [2.5, 2.5, 1.5],
+    ])
+    expect(result.sites[2].abc).toEqual([
+      expect.closeTo(0.405, 12),
+      expect.closeTo(0.475, 12),
+      expect.closeTo(0.25, 12),
+    ])
+    expect(result.sites.map((site) => site.properties?.charge)).toEqual([1, -1, 1])
+    expect(result.sites.map((site) => site.properties?.molecule_id)).toEqual([1, 1, 2])
+    // Å/ps -> Å/fs
+    expect(result.sites[0].properties?.velocity).toEqual([
+      expect.closeTo(0.01, 12),
+      expect.closeTo(0, 12),
+      expect.closeTo(-0.02, 12),
+    ])
+  })
+
+  test(`infers atomic style from column count and honors atom_type_mapping`, () => {
+    const content = `title\n2 atoms\n0 3 xlo xhi\n0 3 ylo yhi\n0 3 zlo zhi\n\nAtoms\n
+1 1 0 0 0\n2 2 1.5 1.5 1.5\n`
+    const defaults = parse_lammps_data(content)
+    expect(defaults?.sites.map((site) => site.species[0].element)).toEqual([`H`, `He`])
+    expect(defaults?.sites[0].properties).toEqual({})
+    const mapped = parse_lammps_data(content, { atom_type_mapping: { 1: `Cs`, 2: `Cl` } })
+    expect(mapped?.sites.map((site) => site.species[0].element)).toEqual([`Cs`, `Cl`])
+  })
+
+  test(`converts SI lengths and masses`, () => {
+    const content = `title\n1 atoms\n0 3e-10 xlo xhi\n0 3e-10 ylo yhi\n0 3e-10 zlo zhi
+\nMasses\n\n1 9.2732e-26\n\nAtoms # atomic\n\n1 1 1.5e-10 0 0\n`
+    const result = parse_lammps_data(content, { units: `si` })
+    expect(result?.lattice?.a).toBeCloseTo(3, 10)
+    expect(result?.sites[0].xyz[0]).toBeCloseTo(1.5, 10)
+    // 9.2732e-26 kg ≈ 55.845 amu
+    expect(result?.sites[0].species[0].element).toBe(`Fe`)
+  })
+
+  test.each([
+    [`no Atoms section`, `title\n1 atoms\n0 3 xlo xhi\n0 3 ylo yhi\n0 3 zlo zhi\n`],
+    [
+      `unsupported style`,
+      `title\n0 3 xlo xhi\n0 3 ylo yhi\n0 3 zlo zhi\nAtoms # sphere\n1 1 0 0 0\n`,
+    ],
+    [`missing box`, `title\n1 atoms\nAtoms # atomic\n1 1 0 0 0\n`],
+  ])(`returns null for %s`, (_desc, content) => {
+    expect(parse_lammps_data(content)).toBeNull()
+  })
+
+  test.each([`system.lmp`, `system.data`])(`parse_structure_file dispatches %s`, (filename) => {
+    const result = parse_structure_file(full_data, filename)
+    expect(result.sites).toHaveLength(3)
+  })
+})
+
 describe(`Phonopy YAML Parser`, () => {
   const simple_phonopy_yaml = `
 phono3py:
@@ -2618,6 +2720,43 @@ describe(`OPTIMADE to Pymatgen Conversion`, () => {
     expect(result.sites[0].species[0].element).toBe(`Ni`) // highest concentration wins
     expect(result.sites[0].properties.mass).toBe(58.693) // mass[1], not mass[0]
     expect(result.sites[0].properties.concentration).toBe(0.7) // concentration[1], not [0]
+    // all elements of the disordered site are kept as partial occupancies
+    expect(result.sites[0].species.map(({ element, occu }) => [element, occu])).toEqual([
+      [`Ni`, 0.7],
+      [`Fe`, 0.3],
+    ])
+  })
+
+  it(`turns assemblies into partial occupancies of their groups`, () => {
+    // site 0 is fixed, sites 1 and 2 are alternative positions of one O atom
+    const result = parse_optimade_json(
+      JSON.stringify({
+        data: {
+          id: `assembly`,
+          type: `structures`,
+          attributes: {
+            lattice_vectors: [
+              [4, 0, 0],
+              [0, 4, 0],
+              [0, 0, 4],
+            ],
+            cartesian_site_positions: [
+              [0, 0, 0],
+              [1, 0, 0],
+              [0, 1, 0],
+            ],
+            species_at_sites: [`Ti`, `O`, `O`],
+            species: [
+              { name: `Ti`, chemical_symbols: [`Ti`], concentration: [1] },
+              { name: `O`, chemical_symbols: [`O`], concentration: [1] },
+            ],
+            assemblies: [{ sites_in_groups: [[1], [2]], group_probabilities: [0.25, 0.75] }],
+          },
+        },
+      }),
+    )
+    assert(result, `Failed to parse OPTIMADE JSON`)
+    expect(result.sites.map(({ species }) => species[0].occu)).toEqual([1, 0.25, 0.75])
   })
 })
 
@@ -2765,6 +2904,251 @@ C1 C 0 0 0`
   })
 })
 
+describe(`CIF lenient and strict modes`, () => {
+  let console_warn_spy: ReturnType<typeof vi.spyOn>
+  beforeEach(() => {
+    console_warn_spy = vi.spyOn(console, `warn`).mockImplementation(() => {})
+  })
+  afterEach(() => console_warn_spy.mockRestore())
+
+  // ICSD/COD-style quirks: mixed-case tags, uncertainties, a `?` angle, a forgotten loop_
+  // keyword and a legacy space-group symbol without symmetry operations
+  const quirky_cif = `DATA_quirky
+_Cell_Length_A  4.0(1)
+_cell_length_b  4.0
+_cell_length_c  4.0
+_cell_angle_alpha  ?
+_cell_angle_beta   90
+_Symmetry_Space_Group_Name_H-M  'F m -3 m'
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+Na1 Na 0 0 0 1.0(0)
+Cl1 Cl 0.5 0.5 0.5 1
+`
+
+  test(`default mode rejects quirky files`, () => {
+    expect(parse_cif(quirky_cif)).toBeNull()
+  })
+
+  test(`lenient mode recovers and collects warnings`, () => {
+    const report = parse_cif_with_report(quirky_cif, { mode: `lenient` })
+    expect(report.errors).toEqual([])
+    expect(report.spacegroup_number).toBe(225)
+    const { structure } = report
+    assert(structure?.lattice)
+    expect(structure.lattice.a).toBeCloseTo(4, 8)
+    expect(structure.lattice.alpha).toBeCloseTo(90, 8)
+    expect(structure.lattice.gamma).toBeCloseTo(90, 8)
+    // without symmetry ops (or _atom_type_number_in_cell) sites are kept as listed
+    expect(structure.sites).toHaveLength(2)
+    for (const expected of [
+      `Invalid CIF cell parameter in line: _cell_angle_alpha  ?`,
+      `Missing _cell_angle_alpha, assuming 90°`,
+      `Missing _cell_angle_gamma, assuming 90°`,
+      `Atom site loop is missing its loop_ keyword`,
+      `No symmetry operations for space group 225, using atom sites as listed`,
+    ]) {
+      expect(report.warnings).toContain(expected)
+      expect(console_warn_spy).toHaveBeenCalledWith(expected)
+    }
+    // the legacy boolean flag maps false to lenient
+    expect(parse_cif(quirky_cif, true, false)?.sites).toHaveLength(2)
+  })
+
+  test(`strict mode turns every recovery into an error`, () => {
+    const report = parse_cif_with_report(quirky_cif, { mode: `strict` })
+    expect(report.structure).toBeNull()
+    expect(report.warnings).toEqual([])
+    expect(report.errors).toContain(`Missing _cell_angle_gamma, assuming 90°`)
+    expect(report.errors.length).toBeGreaterThanOrEqual(5)
+    const message = `Atom site loop is missing its loop_ keyword`
+    expect(console_error_spy).toHaveBeenCalledWith(message)
+  })
+
+  test(`strict mode accepts a clean file without issues`, () => {
+    const report = parse_cif_with_report(tio2_cif, { mode: `strict` })
+    expect(report.errors).toEqual([])
+    expect(report.warnings).toEqual([])
+    expect(report.structure?.sites.length).toBe(parse_cif(tio2_cif)?.sites.length)
+  })
+
+  test(`validation issues: labels, occupancies, elements, short rows, space group`, () => {
+    const cif = `data_bad
+_cell_length_a 5
+_cell_length_b 5
+_cell_length_c 5
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+_space_group_IT_number 221
+_symmetry_space_group_name_H-M 'P 21/c'
+loop_
+_symmetry_equiv_pos_as_xyz
+'x, y, z'
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+_atom_site_occupancy
+Fe1 Fe 0 0 0 1.2
+Fe1 Fe 0.5 0.5 0.5 1
+Xx1 Xx 0.25 0.25 0.25 1
+O1 O 0.1 0.2
+`
+    const { warnings, spacegroup_number, structure } = parse_cif_with_report(cif, {
+      mode: `lenient`,
+    })
+    expect(spacegroup_number).toBe(221)
+    expect(warnings).toEqual([
+      `Skipping atom row with too few values: O1 O 0.1 0.2`,
+      `Occupancy 1.2 of atom Fe1 is outside (0, 1]`,
+      `Duplicate atom site label: Fe1`,
+      `Unknown element symbol 'Xx' for atom Xx1`,
+      `Space group symbol 'P 21/c' does not match number 221`,
+    ])
+    expect(structure?.sites).toHaveLength(3)
+    expect(parse_cif_with_report(cif, { mode: `strict` }).structure).toBeNull()
+  })
+
+  test(`parses unlooped single atom sites in all modes`, () => {
+    const cif = `data_single
+_cell_length_a 3
+_cell_length_b 3
+_cell_length_c 3
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+_atom_site_label Cu1
+_atom_site_type_symbol Cu
+_atom_site_fract_x 0.5
+_atom_site_fract_y 0.5
+_atom_site_fract_z 0.5
+`
+    for (const mode of [`default`, `lenient`, `strict`] as const) {
+      const structure = parse_cif_with_report(cif, { mode }).structure
+      expect(structure?.sites.map((site) => site.abc)).toEqual([[0.5, 0.5, 0.5]])
+    }
+  })
+
+  test(`parse_structure_file falls back to lenient parsing and keeps its warnings`, () => {
+    const structure = parse_structure_file(quirky_cif, `quirky.cif`)
+    expect(structure?.sites).toHaveLength(2)
+    expect(get_parse_warnings()).toContain(`Atom site loop is missing its loop_ keyword`)
+    // the next parse starts with a clean slate
+    parse_structure_file(tio2_cif, `TiO2.cif`)
+    expect(get_parse_warnings()).toEqual([])
+  })
+
+  test(`parse_structure_file parses well-formed CIFs in default mode`, () => {
+    // no H-M symbol: only lenient mode infers I centering from the space group number
+    const cif = `data_fe
+_cell_length_a 2.87
+_cell_length_b 2.87
+_cell_length_c 2.87
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+_space_group_IT_number 229
+loop_
+_space_group_symop_operation_xyz
+'x, y, z'
+loop_
+_atom_type_symbol
+_atom_type_number_in_cell
+Fe 2
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+Fe1 Fe 0 0 0
+`
+    expect(parse_cif(cif, true, `lenient`)?.sites).toHaveLength(2)
+    const default_parse = parse_cif(cif)
+    expect(default_parse?.sites).toHaveLength(1)
+    expect(parse_structure_file(cif, `fe.cif`)).toEqual(default_parse)
+    expect(parse_structure_file(cif)).toEqual(default_parse)
+    expect(get_parse_warnings()).toEqual([])
+  })
+})
+
+describe(`multi-block CIF reading`, () => {
+  const cubic_block = (name: string, element: string, cell = `4.0`) => `data_${name}
+_cell_length_a ${cell}
+_cell_length_b 4.0
+_cell_length_c 4.0
+_cell_angle_alpha 90
+_cell_angle_beta 90
+_cell_angle_gamma 90
+loop_
+_atom_site_label
+_atom_site_type_symbol
+_atom_site_fract_x
+_atom_site_fract_y
+_atom_site_fract_z
+${element}1 ${element} 0 0 0
+`
+  // the second block has a broken cell, the third a text field with a data_ line
+  const dump = `# COD dump
+${cubic_block(`cu`, `Cu`)}${cubic_block(`bad`, `Fe`, `invalid`)}data_ni
+_publ_section_comment
+;
+data_not_a_block
+;
+${cubic_block(`ni`, `Ni`).split(`\n`).slice(1).join(`\n`)}`
+  const summary = (blocks: CifBlock[]) =>
+    blocks.map(({ name, index, structure }) => [name, index, structure?.sites.length ?? 0])
+
+  test(`yields one structure per data block and skips failed blocks`, () => {
+    const blocks = [...iter_cif_blocks(dump)]
+    expect(summary(blocks)).toEqual([
+      [`cu`, 0, 1],
+      [`ni`, 2, 1],
+    ])
+    expect(blocks[1].structure?.sites[0].species[0].element).toBe(`Ni`)
+    expect(blocks[0].errors).toEqual([])
+  })
+
+  test(`chunk boundaries don't matter`, () => {
+    const chunks = Array.from({ length: Math.ceil(dump.length / 7) }, (_, idx) =>
+      dump.slice(7 * idx, 7 * idx + 7),
+    )
+    expect(summary([...iter_cif_blocks(chunks)])).toEqual(summary([...iter_cif_blocks(dump)]))
+  })
+
+  test(`on_error yields or throws failed blocks`, async () => {
+    const with_failed = [...iter_cif_blocks(dump, { on_error: `yield` })]
+    expect(summary(with_failed)).toEqual([
+      [`cu`, 0, 1],
+      [`bad`, 1, 0],
+      [`ni`, 2, 1],
+    ])
+    expect(with_failed[1].errors[0]).toContain(`Invalid CIF cell parameter`)
+    const blocks = iter_cif_blocks(dump, { on_error: `throw` })
+    const first = blocks.next()
+    assert(!first.done)
+    expect(first.value.name).toBe(`cu`)
+    expect(() => blocks.next()).toThrow(`CIF block data_bad (#1): Error parsing CIF file`)
+
+    async function* stream() {
+      yield dump.slice(0, 100)
+      yield dump.slice(100)
+    }
+    const streamed: CifBlock[] = []
+    for await (const block of iter_cif_blocks_async(stream(), { on_error: `yield` })) {
+      streamed.push(block)
+    }
+    expect(summary(streamed)).toEqual(summary(with_failed))
+  })
+})
+
 describe(`detect_structure_type`, () => {
   test.each([
     [`structure.json`, `{"lattice": {"a": 5.0}}`, `crystal`],
@@ -0,0 +1,52 @@
+import * as math from '$lib/math'
+import { perturb_structure, rattle_structure, Select } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const crystal = make_crystal(4, [
+  [`Na`, [0, 0, 0]],
+  [`Cl`, [0.5, 0.5, 0.5]],
+  [`Na`, [0.5, 0.5, 0]],
+  [`Cl`, [0, 0, 0.5]],
+])
+const displacements = (moved: typeof crystal) =>
+  moved.sites.map((site, idx) => math.subtract(site.xyz, crystal.sites[idx].xyz))
+
+describe(`perturb_structure`, () => {
+  test(`moves every site by the given distance and keeps abc in sync`, () => {
+    const moved = perturb_structure(crystal, 0.1, { seed: 3 })
+    for (const disp of displacements(moved)) expect(Math.hypot(...disp)).toBeCloseTo(0.1, 12)
+    moved.sites.forEach(({ abc, xyz }) =>
+      abc.forEach((frac, dim) => expect(4 * frac).toBeCloseTo(xyz[dim], 12)),
+    )
+    expect(crystal.sites[0].xyz).toEqual([0, 0, 0])
+    const ranged = perturb_structure(crystal, 0.2, { min_distance: 0.1 })
+    for (const disp of displacements(ranged)) {
+      expect(Math.hypot(...disp)).toBeGreaterThanOrEqual(0.1)
+      expect(Math.hypot(...disp)).toBeLessThanOrEqual(0.2)
+    }
+    expect(() => perturb_structure(crystal, 0.1, { min_distance: 0.2 })).toThrow(
+      `min_distance <= distance`,
+    )
+  })
+})
+
+describe(`rattle_structure`, () => {
+  test(`same seed, same displacements for a site however sites are selected`, () => {
+    const rattled = displacements(rattle_structure(crystal, 0.05, { seed: 11 }))
+    expect(displacements(rattle_structure(crystal, 0.05, { seed: 11 }))).toEqual(rattled)
+    expect(displacements(rattle_structure(crystal, 0.05, { seed: 12 }))).not.toEqual(rattled)
+    // site streams are independent of the other sites
+    const selection = Select.element(`Na`)
+    const na_disps = displacements(rattle_structure(crystal, 0.05, { seed: 11, selection }))
+    expect([na_disps[0], na_disps[2]]).toEqual([rattled[0], rattled[2]])
+    expect([na_disps[1], na_disps[3]]).toEqual([
+      [0, 0, 0],
+      [0, 0, 0],
+    ])
+    expect(rattle_structure(crystal, 0).sites.map(({ xyz }) => xyz)).toEqual(
+      crystal.sites.map(({ xyz }) => xyz),
+    )
+    expect(() => rattle_structure(crystal, -1)).toThrow(`stdev must be >= 0`)
+  })
+})
@@ -0,0 +1,71 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import { calc_polarization, E_PER_A2_TO_UC_PER_CM2 } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// cubic BaTiO3 (a = 4 Å) with Ti shifted by ti_dz (Å) along c
+const batio3 = (ti_dz = 0, o_abc: Vec3 = [0.5, 0.5, 0]) =>
+  make_crystal(4, [
+    [`Ba`, [0, 0, 0], 2],
+    [`Ti`, [0.5, 0.5, 0.5 + ti_dz / 4], 4],
+    [`O`, o_abc, -2],
+    [`O`, [0.5, 0, 0.5], -2],
+    [`O`, [0, 0.5, 0.5], -2],
+  ])
+
+describe(`calc_polarization`, () => {
+  const reference = batio3()
+
+  test(`point charges from oxidation states`, () => {
+    const result = calc_polarization(batio3(0.1), reference)
+    // 4 e · 0.1 Å / 64 Å³
+    const expected = (0.4 / 64) * E_PER_A2_TO_UC_PER_CM2
+    expect(result.polarization[0]).toBeCloseTo(0, 10)
+    expect(result.polarization[2]).toBeCloseTo(expected, 10)
+    expect(result.magnitude).toBeCloseTo(expected, 10)
+    expect(result.dipole[2]).toBeCloseTo(0.4, 10)
+    expect(result.quantum[2]).toBeCloseTo((4 / 64) * E_PER_A2_TO_UC_PER_CM2, 10)
+    expect(result.quanta[2]).toBeCloseTo(0.1, 10)
+    expect(result.displacements[1][2]).toBeCloseTo(0.1, 10)
+    expect(calc_polarization(reference, reference).magnitude).toBeCloseTo(0, 10)
+  })
+
+  test(`sites crossing the cell boundary take the shortest displacement`, () => {
+    const shifted = batio3(0, [0.5, 0.5, 0.99])
+    const result = calc_polarization(shifted, reference)
+    expect(result.displacements[2][2]).toBeCloseTo(-0.04, 10)
+    expect(result.dipole[2]).toBeCloseTo(0.08, 10)
+  })
+
+  test.each([
+    [`scalar`, 7.25],
+    [`tensor`, [[7.25, 0, 0], [0, 7.25, 0], [0, 0, 7.25]] as Matrix3x3],
+  ])(`%s Born effective charges`, (_label, ti_charge) => {
+    const born_charges = [2.75, ti_charge, -2, -2, -2]
+    const result = calc_polarization(batio3(0.1), reference, { born_charges })
+    expect(result.polarization[2]).toBeCloseTo((0.725 / 64) * E_PER_A2_TO_UC_PER_CM2, 10)
+  })
+
+  test(`anisotropic Born tensor rotates the dipole`, () => {
+    const ti_charge: Matrix3x3 = [[0, 0, 1], [0, 0, 0], [0, 0, 0]]
+    const born_charges = [0, ti_charge, 0, 0, 0]
+    const result = calc_polarization(batio3(0.1), reference, { born_charges })
+    expect(result.dipole[0]).toBeCloseTo(0.1, 10)
+    expect(result.dipole[2]).toBeCloseTo(0, 10)
+  })
+
+  test(`invalid inputs throw`, () => {
+    expect(() => calc_polarization(batio3(), make_crystal(4, [[`Ba`, [0, 0, 0]]]))).toThrow(
+      `Site count mismatch: structure has 5, reference has 1`,
+    )
+    expect(() => calc_polarization(batio3(), reference, { born_charges: [1] })).toThrow(
+      `Expected 5 Born charges`,
+    )
+    const no_oxi = make_crystal(4, [[`Na`, [0, 0, 0]], [`Cl`, [0.5, 0.5, 0.5]]])
+    expect(() => calc_polarization(no_oxi, no_oxi)).toThrow(`no oxidation states`)
+    const charged = make_crystal(4, [[`Na`, [0, 0, 0], 1]])
+    expect(() => calc_polarization(charged, charged)).toThrow(`not charge neutral`)
+    const molecule = { sites: reference.sites }
+    expect(() => calc_polarization(molecule, reference)).toThrow(`need a lattice`)
+  })
+})
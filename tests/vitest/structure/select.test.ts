@@ -0,0 +1,53 @@
+import { Select, selection_indices, selection_mask } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// Li atoms stacked along c of a 4 × 4 × 20 Å cell on top of an O layer
+const slab = make_crystal(
+  [
+    [4, 0, 0],
+    [0, 4, 0],
+    [0, 0, 20],
+  ],
+  [
+    { element: `O`, abc: [0, 0, 0.1] },
+    { element: `Li`, abc: [0.5, 0.5, 0.3] },
+    { element: `Li`, abc: [0, 0, 0.6] },
+    { element: `O`, abc: [0.5, 0.5, 0.9] },
+    { element: `Li`, abc: [0.95, 0, 0.6] },
+  ],
+)
+
+describe(`Select`, () => {
+  test(`combines element, height and index filters`, () => {
+    expect(Select.element(`Li`).indices(slab)).toEqual([1, 2, 4])
+    expect(Select.element(`Li`).and(Select.z_above(10)).indices(slab)).toEqual([2, 4])
+    expect(Select.z_below(3).or(Select.index(3)).indices(slab)).toEqual([0, 3])
+    expect(Select.element(`Li`, `O`).not().indices(slab)).toEqual([])
+    expect(Select.all().mask(slab)).toEqual(Array(5).fill(true))
+    expect(Select.none().indices(slab)).toEqual([])
+    const custom = Select.where((site, idx) => idx % 2 === 0 && site.abc[2] > 0.5)
+    expect(custom.indices(slab)).toEqual([2, 4])
+    expect(Select.frac_between(0, 0.4, 1).indices(slab)).toEqual([1, 3, 4])
+  })
+
+  test(`within uses minimum-image distances in periodic cells`, () => {
+    // site 4 at x = 3.8 Å is 0.2 Å from site 2 at x = 0 across the a boundary
+    expect(Select.within([0, 0, 12], 0.5).indices(slab)).toEqual([2, 4])
+    const molecule = { sites: slab.sites }
+    expect(Select.within([0, 0, 12], 0.5).indices(molecule)).toEqual([2])
+    expect(Select.frac_between(2, 0, 1).indices(molecule)).toEqual([])
+  })
+})
+
+describe(`selection helpers`, () => {
+  test(`accept index lists, Select and undefined`, () => {
+    expect(selection_indices(slab)).toEqual([0, 1, 2, 3, 4])
+    expect(selection_indices(slab, [4, 1])).toEqual([4, 1])
+    expect(selection_indices(slab, Select.element(`O`))).toEqual([0, 3])
+    expect(selection_mask(slab, [4, 1])).toEqual([false, true, false, false, true])
+    expect(selection_mask(slab)).toEqual(Array(5).fill(true))
+    expect(() => selection_indices(slab, [5])).toThrow(`Atom index 5 out of range`)
+    expect(() => selection_mask(slab, [-1])).toThrow(`Atom index -1 out of range`)
+  })
+})
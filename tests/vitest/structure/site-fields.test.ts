@@ -0,0 +1,136 @@
+import type { Vec3 } from '$lib/math'
+import {
+  get_atom_colors,
+  get_site_fields,
+  get_site_properties,
+  make_supercell,
+  remove_site_field,
+  set_site_field,
+  set_site_properties,
+  site_field_color_fn,
+  site_field_values,
+  site_fields_from_properties,
+  site_fields_to_typed_arrays,
+} from '$lib/structure'
+import {
+  structure_to_cif_str,
+  structure_to_json_str,
+  structure_to_xyz_str,
+} from '$lib/structure/export'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const nacl = make_crystal(4, [
+  { element: `Na`, abc: [0, 0, 0], properties: { bader: 0.8, force: [0, 0, 0.1] } },
+  { element: `Cl`, abc: [0.5, 0.5, 0.5], properties: { bader: -0.8, force: [0, 0, -0.1] } },
+])
+const forces: Vec3[] = [
+  [0.1, 0, 0],
+  [0, -0.3, 0.4],
+]
+const with_charges = set_site_field(nacl, `charge`, [0.8, -0.8], `e`)
+const with_fields = set_site_field(with_charges, `forces`, forces)
+
+describe(`site field container`, () => {
+  test(`set, read and remove fields without mutating the input`, () => {
+    const fields = get_site_fields(with_fields)
+    expect(fields.charge).toEqual({ kind: `scalar`, values: [0.8, -0.8], unit: `e` })
+    expect(fields.forces).toEqual({ kind: `vector`, values: forces })
+    expect(nacl.properties?.site_fields).toBeUndefined()
+    expect(Object.keys(get_site_fields(remove_site_field(with_fields, `charge`)))).toEqual([
+      `forces`,
+    ])
+    expect(site_field_values(with_fields, `forces`)).toEqual([0.1, 0.5])
+  })
+
+  test(`invalid fields throw`, () => {
+    expect(() => set_site_field(nacl, `q6`, [1])).toThrow(
+      `Site field q6 has 1 values for 2 sites`,
+    )
+    expect(() => set_site_field(nacl, `bad name`, [1, 2])).toThrow(`Invalid site field name`)
+    expect(() => set_site_field(nacl, `mixed`, [1, [0, 0, 1]] as number[])).toThrow(
+      `must hold only numbers or only 3-vectors`,
+    )
+    expect(() => site_field_values(nacl, `charge`)).toThrow(`No site field 'charge'`)
+    // fields that no longer match the sites are reported instead of misaligned
+    const stale = { ...with_fields, sites: with_fields.sites.slice(0, 1) }
+    expect(() => get_site_fields(stale)).toThrow(`Site field charge has 2 values for 1 sites`)
+  })
+
+  test(`lift site properties into fields`, () => {
+    const lifted = site_fields_from_properties(nacl, [`bader`, `force`, `missing`])
+    const fields = get_site_fields(lifted)
+    expect(Object.keys(fields)).toEqual([`bader`, `force`])
+    expect(fields.force.kind).toBe(`vector`)
+  })
+
+  test(`pymatgen site_properties dict-of-lists round trip`, () => {
+    const partial = make_crystal(4, [
+      { element: `Na`, abc: [0, 0, 0], properties: { magmom: 0.5 } },
+      { element: `Cl`, abc: [0.5, 0.5, 0.5] },
+    ])
+    expect(get_site_properties(partial)).toEqual({ magmom: [0.5, null] })
+    expect(get_site_properties(nacl)).toEqual({
+      bader: [0.8, -0.8],
+      force: [
+        [0, 0, 0.1],
+        [0, 0, -0.1],
+      ],
+    })
+
+    const updated = set_site_properties(nacl, { bader: [null, -0.7], tag: [1, 2] })
+    expect(updated.sites[0].properties).toEqual({ force: [0, 0, 0.1], tag: 1 })
+    expect(updated.sites[1].properties).toEqual({ bader: -0.7, force: [0, 0, -0.1], tag: 2 })
+    expect(nacl.sites[0].properties.bader).toBe(0.8)
+    expect(set_site_properties(nacl, get_site_properties(nacl))).toEqual(nacl)
+
+    expect(() => set_site_properties(nacl, { magmom: [1] })).toThrow(
+      `Site property magmom needs one value per site (2), got 1 values`,
+    )
+  })
+
+  test(`supercells tile the fields with the sites`, () => {
+    const supercell = make_supercell(with_fields, [2, 1, 1])
+    expect(site_field_values(supercell, `charge`)).toEqual([0.8, -0.8, 0.8, -0.8])
+    expect(get_site_fields(supercell).forces.values[3]).toEqual(forces[1])
+  })
+
+  test(`color atoms by a field and export typed arrays`, () => {
+    const color_fn = site_field_color_fn(with_fields, `charge`)
+    const { values } = get_atom_colors(with_fields, { mode: `custom`, color_fn })
+    expect(values).toEqual([0.8, -0.8])
+    const arrays = site_fields_to_typed_arrays(with_fields)
+    expect(arrays.charge).toMatchObject({ n_components: 1, unit: `e` })
+    expect([...arrays.forces.data]).toEqual([0.1, 0, 0, 0, -0.3, 0.4])
+    expect(arrays.forces.n_components).toBe(3)
+  })
+})
+
+describe(`site field serialization`, () => {
+  test(`extended XYZ Properties columns`, () => {
+    const lines = structure_to_xyz_str(with_fields).split(`\n`)
+    expect(lines[1]).toContain(`Properties=species:S:1:pos:R:3:charge:R:1:forces:R:3`)
+    expect(lines[3].split(/\s+/).slice(4).map(Number)).toEqual([-0.8, 0, -0.3, 0.4])
+    // without fields the plain XYZ format is unchanged
+    expect(structure_to_xyz_str(nacl)).not.toContain(`Properties=`)
+  })
+
+  test(`CIF loop keyed by atom label`, () => {
+    const cif = structure_to_cif_str(with_fields)
+    const loop = cif.slice(cif.indexOf(`_atom_site_field_label`)).split(`\n`)
+    expect(loop.slice(0, 5)).toEqual([
+      `_atom_site_field_label`,
+      `_atom_site_field_charge`,
+      `_atom_site_field_forces_x`,
+      `_atom_site_field_forces_y`,
+      `_atom_site_field_forces_z`,
+    ])
+    expect(loop[5].split(` `).slice(1).map(Number)).toEqual([0.8, 0.1, 0, 0])
+    expect(structure_to_cif_str(nacl)).not.toContain(`_atom_site_field_`)
+  })
+
+  test(`JSON round trip`, () => {
+    const parsed = JSON.parse(structure_to_json_str(with_fields))
+    expect(get_site_fields(parsed)).toEqual(get_site_fields(with_fields))
+  })
+})
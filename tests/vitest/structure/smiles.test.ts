@@ -0,0 +1,120 @@
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { fragment_smiles, structure_to_smiles } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const make_molecule = (atoms: [ElementSymbol, Vec3][]): AnyStructure => ({
+  sites: atoms.map(([element, xyz], idx) => ({
+    species: [{ element, occu: 1, oxidation_state: 0 }],
+    xyz,
+    abc: [0, 0, 0],
+    label: `${element}${idx + 1}`,
+    properties: {},
+  })),
+  charge: 0,
+})
+
+// staggered ethane with one methyl H replaced by an OH group
+const ethanol_atoms: [ElementSymbol, Vec3][] = [
+  [`C`, [0, 0, 0.765]],
+  [`C`, [0, 0, -0.765]],
+  [`H`, [1.027, 0, 1.129]],
+  [`H`, [-0.514, 0.89, 1.129]],
+  [`H`, [-0.514, -0.89, 1.129]],
+  [`H`, [0.514, 0.89, -1.129]],
+  [`O`, [-1.347, 0, -1.243]],
+  [`H`, [0.514, -0.89, -1.129]],
+  [`H`, [-1.347, 0, -2.203]],
+]
+
+// planar hexagon, C-C 1.39 Å, C-H 1.09 Å
+const ring_atoms = (heteroatom?: ElementSymbol): [ElementSymbol, Vec3][] =>
+  [0, 1, 2, 3, 4, 5].flatMap((idx) => {
+    const angle = (idx * Math.PI) / 3
+    const [cos, sin] = [Math.cos(angle), Math.sin(angle)]
+    if (idx === 0 && heteroatom) return [[heteroatom, [1.39 * cos, 1.39 * sin, 0]]]
+    return [
+      [`C`, [1.39 * cos, 1.39 * sin, 0]],
+      [`H`, [2.48 * cos, 2.48 * sin, 0]],
+    ]
+  })
+
+describe(`structure_to_smiles`, () => {
+  test.each([
+    [`methane`, [
+      [`C`, [0, 0, 0]],
+      [`H`, [0.629, 0.629, 0.629]],
+      [`H`, [-0.629, -0.629, 0.629]],
+      [`H`, [-0.629, 0.629, -0.629]],
+      [`H`, [0.629, -0.629, -0.629]],
+    ], `C`],
+    [`water`, [
+      [`O`, [0, 0, 0]],
+      [`H`, [0.757, 0.586, 0]],
+      [`H`, [-0.757, 0.586, 0]],
+    ], `O`],
+    [`ethylene`, [
+      [`C`, [0.665, 0, 0]],
+      [`C`, [-0.665, 0, 0]],
+      [`H`, [1.185, 0.958, 0]],
+      [`H`, [1.185, -0.958, 0]],
+      [`H`, [-1.185, 0.958, 0]],
+      [`H`, [-1.185, -0.958, 0]],
+    ], `C=C`],
+    [`CO2`, [
+      [`C`, [0, 0, 0]],
+      [`O`, [1.16, 0, 0]],
+      [`O`, [-1.16, 0, 0]],
+    ], `O=C=O`],
+    [`H2`, [
+      [`H`, [0, 0, 0]],
+      [`H`, [0.74, 0, 0]],
+    ], `[H][H]`],
+    [`ethanol`, ethanol_atoms, `CCO`],
+    [`benzene`, ring_atoms(), `c1ccccc1`],
+    [`pyridine`, ring_atoms(`N`), `c1ccncc1`],
+  ] as [string, [ElementSymbol, Vec3][], string][])(`%s`, (_name, atoms, expected) => {
+    expect(structure_to_smiles(make_molecule(atoms))).toBe(expected)
+  })
+
+  test(`independent of site order`, () => {
+    const shuffled = [...ethanol_atoms].reverse()
+    expect(structure_to_smiles(make_molecule(shuffled))).toBe(`CCO`)
+    const pyridine = ring_atoms(`N`)
+    const rotated = [...pyridine.slice(5), ...pyridine.slice(0, 5)]
+    expect(structure_to_smiles(make_molecule(rotated))).toBe(`c1ccncc1`)
+  })
+
+  test(`separate molecules are joined with dots`, () => {
+    const waters = make_molecule([
+      [`O`, [0, 0, 0]],
+      [`H`, [0.757, 0.586, 0]],
+      [`H`, [-0.757, 0.586, 0]],
+      [`O`, [5, 0, 0]],
+      [`H`, [5.757, 0.586, 0]],
+      [`H`, [4.243, 0.586, 0]],
+      [`C`, [10, 0, 0]],
+      [`O`, [11.16, 0, 0]],
+      [`O`, [8.84, 0, 0]],
+    ])
+    expect(structure_to_smiles(waters)).toBe(`O.O.O=C=O`)
+    expect(fragment_smiles(waters).map(({ site_indices }) => site_indices)).toEqual([
+      [0, 1, 2],
+      [3, 4, 5],
+      [6, 7, 8],
+    ])
+  })
+
+  test(`fragments extended through periodic images have no SMILES`, () => {
+    const lattice: Matrix3x3 = [
+      [1.5, 0, 0],
+      [0, 10, 0],
+      [0, 0, 10],
+    ]
+    const chain = make_crystal(lattice, [[`C`, [0, 0, 0]]])
+    expect(fragment_smiles(chain)).toEqual([{ site_indices: [0], smiles: null }])
+    expect(structure_to_smiles(chain)).toBe(``)
+  })
+})
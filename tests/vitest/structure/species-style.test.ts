@@ -0,0 +1,77 @@
+import {
+  get_species_styles,
+  StructureBuilder,
+  structure_to_styled_json_str,
+} from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+
+const lattice = 5
+const magnetite_like = new StructureBuilder()
+  .with_lattice([
+    [lattice, 0, 0],
+    [0, lattice, 0],
+    [0, 0, lattice],
+  ])
+  .add_site(`Fe`, [0, 0, 0], { oxidation_state: 2 })
+  .add_site(`Fe`, [0.5, 0.5, 0], { oxidation_state: 3 })
+  .add_site(`Fe`, [0.5, 0, 0.5], { oxidation_state: 3 })
+  .add_site(`O`, [0.25, 0.25, 0.25], { oxidation_state: -2 })
+  .add_site(`O`, [0.75, 0.75, 0.25], { oxidation_state: -2 })
+  .build()
+
+describe(`species styles`, () => {
+  test(`defaults match the structure viewer (Vesta colors, atomic radii)`, () => {
+    const { color_scheme, radius_type, species } = get_species_styles(magnetite_like)
+    expect([color_scheme, radius_type]).toEqual([`Vesta`, `atomic`])
+    expect(species).toEqual([
+      { element: `Fe`, oxidation_state: 2, label: `Fe+2`, color: `#b57100`, radius: 1.4 },
+      { element: `Fe`, oxidation_state: 3, label: `Fe+3`, color: `#b57100`, radius: 1.4 },
+      { element: `O`, oxidation_state: -2, label: `O-2`, color: `#fe0300`, radius: 0.6 },
+    ])
+  })
+
+  test(`ionic radii depend on the oxidation state`, () => {
+    const { species } = get_species_styles(magnetite_like, {
+      color_scheme: `Jmol`,
+      radius_type: `ionic`,
+    })
+    expect(species.map(({ label, radius }) => [label, radius])).toEqual([
+      [`Fe+2`, 0.92],
+      [`Fe+3`, 0.785],
+      [`O-2`, 1.26],
+    ])
+    expect(species[0].color).toBe(`#e06633`)
+    // no oxidation state: mean over the tabulated ones
+    const neutral = new StructureBuilder()
+      .add_site(`Fe`, [0, 0, 0], { cartesian: true })
+      .build()
+    const [fe] = get_species_styles(neutral, { radius_type: `ionic` }).species
+    expect(fe.radius).toBeCloseTo((0.92 + 0.785) / 2, 12)
+  })
+
+  test(`CPK colors, covalent radii and overrides`, () => {
+    const { species } = get_species_styles(magnetite_like, {
+      color_scheme: `CPK`,
+      radius_type: `covalent`,
+      colors: { O: `#ff0000` },
+      radii: { Fe: 1.5 },
+    })
+    expect(species.map(({ color, radius }) => [color, radius])).toEqual([
+      [`#e06633`, 1.5],
+      [`#e06633`, 1.5],
+      [`#ff0000`, 0.66],
+    ])
+    // @ts-expect-error unknown scheme name
+    expect(() => get_species_styles(magnetite_like, { color_scheme: `Rainbow` })).toThrow(
+      `Unknown color scheme Rainbow`,
+    )
+  })
+
+  test(`styled JSON round-trips the structure`, () => {
+    const json_str = structure_to_styled_json_str(magnetite_like, { radius_type: `ionic` })
+    const parsed = JSON.parse(json_str)
+    expect(parsed.structure).toEqual(magnetite_like)
+    expect(parsed.species_styles.radius_type).toBe(`ionic`)
+    expect(parsed.species_styles.species).toHaveLength(3)
+  })
+})
@@ -0,0 +1,106 @@
+import type { Vec3 } from '$lib/math'
+import { make_supercell, spin_monte_carlo } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const a_0 = 2.5
+// 4x4x4 simple cubic supercell of moment-2 μB Fe
+const ferro = make_supercell(
+  make_crystal(a_0, [{ element: `Fe`, abc: [0, 0, 0], properties: { magmom: 2 } }]),
+  4,
+)
+// same lattice with checkerboard (G-type) antiferromagnetic moments
+const corners: Vec3[] = [0, 0.5].flatMap((x) =>
+  [0, 0.5].flatMap((y) => [0, 0.5].map((z): Vec3 => [x, y, z])),
+)
+const antiferro = make_supercell(
+  make_crystal(
+    2 * a_0,
+    corners.map((abc) => ({
+      element: `Mn`,
+      abc,
+      properties: { magmom: 2 * (-1) ** (2 * (abc[0] + abc[1] + abc[2])) },
+    })),
+  ),
+  2,
+)
+// 20 to 100 K in 5 K steps
+const temperatures = Array.from({ length: 17 }, (_, idx) => 20 + 5 * idx)
+const sweeps = { n_equilibration: 200, n_sweeps: 1000 }
+
+describe(`spin_monte_carlo`, () => {
+  test(`simple cubic Ising ferromagnet orders near 4.51 J/k_B`, () => {
+    const result = spin_monte_carlo(ferro, {
+      couplings: [1],
+      temperatures,
+      model: `ising`,
+      ...sweeps,
+    })
+    expect(result.ordering).toBe(`ferromagnetic`)
+    expect(result.magnetic_sites).toHaveLength(64)
+    const [cold, hot] = [result.points[0], result.points.at(-1)]
+    expect(cold.order_parameter).toBeGreaterThan(0.95)
+    expect(cold.magnetization).toBeCloseTo(2 * cold.order_parameter, 10)
+    expect(cold.energy).toBeCloseTo(-3, 1) // 3 bonds per site
+    expect(hot?.order_parameter).toBeLessThan(0.3)
+    // exact T_c = 4.5115 J/k_B ≈ 52 K, finite-size peak sits slightly above
+    expect(result.critical_temperature).toBeGreaterThanOrEqual(45)
+    expect(result.critical_temperature).toBeLessThanOrEqual(60)
+  })
+
+  test(`antiferromagnet orders staggered with vanishing net moment`, () => {
+    const result = spin_monte_carlo(antiferro, {
+      couplings: [-1],
+      temperatures,
+      model: `ising`,
+      ...sweeps,
+    })
+    expect(result.ordering).toBe(`antiferromagnetic`)
+    expect(result.points[0].order_parameter).toBeGreaterThan(0.95)
+    expect(result.points[0].magnetization).toBeLessThan(0.1)
+    // bipartite lattice: same Néel temperature as the ferromagnet's Curie temperature
+    expect(result.critical_temperature).toBeGreaterThanOrEqual(45)
+    expect(result.critical_temperature).toBeLessThanOrEqual(60)
+  })
+
+  test(`Heisenberg model is reproducible for a given seed`, () => {
+    const options = { couplings: [1], temperatures: [60, 2], n_sweeps: 300 }
+    const result = spin_monte_carlo(ferro, options)
+    expect(result.points.map(({ temperature }) => temperature)).toEqual([2, 60])
+    expect(result.points[0].order_parameter).toBeGreaterThan(0.9)
+    expect(result.points[1].order_parameter).toBeLessThan(0.35)
+    expect(result.critical_temperature).toBeNull()
+    expect(spin_monte_carlo(ferro, options)).toEqual(result)
+    expect(spin_monte_carlo(ferro, { ...options, seed: 1 })).not.toEqual(result)
+  })
+
+  test(`non-magnetic sites are skipped`, () => {
+    const oxide = make_crystal(4, [
+      { element: `Ni`, abc: [0, 0, 0], properties: { magmom: [0, 0, 1.7] } },
+      { element: `O`, abc: [0.5, 0.5, 0.5], properties: { magmom: 0.01 } },
+    ])
+    const result = spin_monte_carlo(make_supercell(oxide, 3), {
+      couplings: [1],
+      temperatures: [10],
+      n_equilibration: 10,
+      n_sweeps: 10,
+    })
+    expect(result.magnetic_sites).toEqual(Array.from({ length: 27 }, (_, idx) => 2 * idx))
+  })
+
+  test(`invalid inputs throw`, () => {
+    const options = { couplings: [1], temperatures: [10] }
+    const unit_cell = make_crystal(a_0, [
+      { element: `Fe`, abc: [0, 0, 0], properties: { magmom: 2 } },
+    ])
+    expect(() => spin_monte_carlo(unit_cell, options)).toThrow(
+      `Site 0 couples to its own periodic image in shell 1, use a larger supercell`,
+    )
+    expect(() => spin_monte_carlo(make_crystal(a_0, [[`Fe`, [0, 0, 0]]]), options)).toThrow(
+      `No sites with |magmom| ≥ 0.1 μB`,
+    )
+    expect(() => spin_monte_carlo(ferro, { ...options, temperatures: [0, 10] })).toThrow(
+      `Temperatures must be a non-empty list of positive values`,
+    )
+  })
+})
@@ -1,8 +1,11 @@
 import type { Matrix3x3, Vec3 } from '$lib/math'
 import * as math from '$lib/math'
 import type { Crystal } from '$lib/structure'
+import { set_site_field, site_field_values } from '$lib/structure'
 import { find_image_atoms, get_pbc_image_sites } from '$lib/structure/pbc'
 import {
+  discretize_occupancies,
+  fit_supercell,
   generate_lattice_points,
   is_valid_supercell_input,
   make_supercell,
@@ -10,7 +13,14 @@ import {
   scale_lattice_matrix,
 } from '$lib/structure/supercell'
 import { describe, expect, test } from 'vitest'
-import { make_crystal } from '../setup'
+import { IDENTITY_MATRIX3, make_crystal } from '../setup'
+
+// Rock salt-like cell with one partially occupied site
+const make_partial = (occu: number, lattice: number | Matrix3x3 = 4): Crystal =>
+  make_crystal(lattice, [
+    { element: `Li`, abc: [0, 0, 0], occu },
+    { element: `O`, abc: [0.5, 0.5, 0.5] },
+  ])
 
 // Sample structure for testing
 const sample_structure = make_crystal(
@@ -483,3 +493,171 @@ describe(`performance tests`, () => {
     10000,
   )
 })
+
+describe(`discretize_occupancies`, () => {
+  test.each<[number, number, Vec3, number]>([
+    [0.437, 1e-3, [4, 2, 2], 7],
+    [0.437, 0.01, [3, 3, 1], 4],
+    [0.5, 0.01, [2, 1, 1], 1],
+    [0.25, 0.01, [2, 2, 1], 1],
+  ])(`occupancy %s with tolerance %s -> %s cell, %s atoms`, (occu, tol, scaling, count) => {
+    const result = discretize_occupancies(make_partial(occu), { tolerance: tol })
+    expect(result.scaling).toEqual(scaling)
+    expect(result.within_tolerance).toBe(true)
+    const [site] = result.sites
+    expect(site.site_idx).toBe(0)
+    expect(site.species[0].count).toBe(count)
+    expect(site.species[0].achieved).toBeCloseTo(count / result.n_cells, 12)
+    expect(site.error).toBeCloseTo(Math.abs(count / result.n_cells - occu), 12)
+    expect(result.max_error).toBe(site.error)
+    // rounded occupancies are applied to every image of the site
+    const li_occus = result.structure.sites
+      .filter((supercell_site) => supercell_site.species[0].element === `Li`)
+      .map((supercell_site) => supercell_site.species[0].occu)
+    expect(li_occus).toEqual(Array(result.n_cells).fill(count / result.n_cells))
+  })
+
+  test(`prefers stretching the short axis of anisotropic cells`, () => {
+    const lattice: Matrix3x3 = [
+      [3, 0, 0],
+      [0, 6, 0],
+      [0, 0, 6],
+    ]
+    expect(discretize_occupancies(make_partial(0.5, lattice)).scaling).toEqual([2, 1, 1])
+    expect(discretize_occupancies(make_partial(0.25, lattice)).scaling).toEqual([4, 1, 1])
+  })
+
+  test(`respects the atom budget and reports the best achievable error`, () => {
+    // 2 sites x 10 cells max: 9 cells (4/9) is the closest to 0.437 within budget
+    const result = discretize_occupancies(make_partial(0.437), {
+      tolerance: 1e-3,
+      max_atoms: 20,
+    })
+    expect(result.n_cells).toBe(9)
+    expect(result.within_tolerance).toBe(false)
+    expect(result.max_error).toBeCloseTo(4 / 9 - 0.437, 12)
+  })
+
+  test(`keeps mixed-site totals within one atom per cell`, () => {
+    const structure = make_partial(1)
+    structure.sites[0].species = [
+      { element: `Fe`, occu: 0.5, oxidation_state: 0 },
+      { element: `Ni`, occu: 0.5, oxidation_state: 0 },
+    ]
+    // a single cell can't hold 2 x round(0.5) = 2 atoms on one site
+    const single = discretize_occupancies(structure, { max_scaling: 1 })
+    expect(single.sites[0].species.map(({ count }) => count)).toEqual([0, 1])
+    expect(single.structure.sites[0].species).toEqual([
+      { element: `Ni`, occu: 1, oxidation_state: 0 },
+    ])
+    expect(single.within_tolerance).toBe(false)
+
+    const result = discretize_occupancies(structure)
+    expect(result.n_cells).toBe(2)
+    expect(result.sites[0].species.map(({ achieved }) => achieved)).toEqual([0.5, 0.5])
+  })
+
+  test(`ordered structures need no supercell`, () => {
+    const result = discretize_occupancies(make_partial(1))
+    expect(result).toMatchObject({ scaling: [1, 1, 1], n_cells: 1, max_error: 0, sites: [] })
+    expect(result.within_tolerance).toBe(true)
+  })
+
+  test(`drops sites whose occupancy rounds to zero`, () => {
+    const result = discretize_occupancies(make_partial(0.01), { max_scaling: 1 })
+    expect(result.structure.sites.map((site) => site.species[0].element)).toEqual([`O`])
+  })
+
+  test(`cuts site fields down to the remaining sites`, () => {
+    const structure = set_site_field(make_partial(0.01), `charge`, [1, -2])
+    const { structure: discretized } = discretize_occupancies(structure, { max_scaling: 1 })
+    expect(discretized.properties?.site_fields?.charge.values).toEqual([-2])
+    expect(site_field_values(discretized, `charge`)).toEqual([-2])
+  })
+
+  test.each([
+    [{ tolerance: -1 }, `tolerance must be non-negative`],
+    [{ max_scaling: 0 }, `max_scaling must be a positive integer`],
+    [{ max_scaling: 1.5 }, `max_scaling must be a positive integer`],
+  ])(`rejects invalid options %o`, (options, message) => {
+    expect(() => discretize_occupancies(make_partial(0.5), options)).toThrow(message)
+  })
+})
+
+describe(`fit_supercell`, () => {
+  // sites of sample_structure in the supercell M · lattice, shifted, then deformed by F
+  const build_supercell = (scaling: Matrix3x3, deformation: Matrix3x3, shift: Vec3) => {
+    const ideal = math.dot(scaling, sample_structure.lattice.matrix)
+    const to_frac = math.create_cart_to_frac(ideal)
+    const unit_to_cart = math.create_frac_to_cart(sample_structure.lattice.matrix)
+    const sites: { element: string; xyz: Vec3 }[] = []
+    for (const cell of generate_lattice_points([7, 7, 7])) {
+      const offset = unit_to_cart(math.add(cell, [-3, -3, -3]))
+      for (const site of sample_structure.sites) {
+        const xyz = math.add(site.xyz, offset)
+        if (to_frac(xyz).every((frac) => frac > -1e-9 && frac < 1 - 1e-9)) {
+          const strained = math.mat3x3_vec3_multiply(deformation, math.add(xyz, shift))
+          sites.push({ element: site.species[0].element, xyz: strained })
+        }
+      }
+    }
+    const matrix = math.dot(ideal, math.transpose_3x3_matrix(deformation))
+    return make_crystal(matrix, sites)
+  }
+  const scaling: Matrix3x3 = [
+    [1, 1, 0],
+    [-1, 1, 0],
+    [0, 0, 2],
+  ]
+  // anisotropic thermal expansion with a little shear
+  const expansion: Matrix3x3 = [
+    [1.02, 0.01, 0],
+    [0, 1.01, 0],
+    [0, 0, 1.03],
+  ]
+
+  test(`infers a non-diagonal supercell matrix`, () => {
+    const supercell = build_supercell(scaling, IDENTITY_MATRIX3, [0.3, -0.7, 1.1])
+    expect(supercell.sites).toHaveLength(8)
+    const fit = fit_supercell(sample_structure, supercell)
+    expect(fit?.supercell_matrix).toEqual(scaling)
+    expect(fit?.n_cells).toBe(4)
+    expect(fit?.volume_ratio).toBeCloseTo(4, 12)
+    expect(fit?.rms_displacement).toBeCloseTo(0, 10)
+    fit?.axis_scaling.forEach((stretch) => expect(stretch).toBeCloseTo(1, 12))
+  })
+
+  test(`fits anisotropically expanded supercells only with scale`, () => {
+    const supercell = build_supercell(scaling, expansion, [0.1, 0.2, 0.3])
+    expect(fit_supercell(sample_structure, supercell)).toBeNull()
+    const fit = fit_supercell(sample_structure, supercell, { scale: true })
+    expect(fit?.supercell_matrix).toEqual(scaling)
+    expect(fit?.volume_ratio).toBeCloseTo(4 * math.det_3x3(expansion), 10)
+    expect(fit?.max_displacement).toBeCloseTo(0, 10)
+    fit?.deformation
+      .flat()
+      .forEach((val, idx) => expect(val).toBeCloseTo(expansion.flat()[idx], 10))
+    const expected_scaling = [1.02, Math.hypot(0.01, 1.01), 1.03]
+    fit?.axis_scaling.forEach((stretch, idx) =>
+      expect(stretch).toBeCloseTo(expected_scaling[idx], 10),
+    )
+    const strict = { scale: true, max_strain: 0.01 }
+    expect(fit_supercell(sample_structure, supercell, strict)).toBeNull()
+  })
+
+  test(`rejects displaced sites beyond stol and mismatched compositions`, () => {
+    const supercell = build_supercell(scaling, IDENTITY_MATRIX3, [0, 0, 0])
+    const displaced = make_crystal(
+      supercell.lattice.matrix,
+      supercell.sites.map((site, idx) => ({
+        element: site.species[0].element,
+        xyz: idx === 5 ? math.add(site.xyz, [0.5, 0, 0]) : site.xyz,
+      })),
+    )
+    expect(fit_supercell(sample_structure, displaced)).toBeNull()
+    const fit = fit_supercell(sample_structure, displaced, { stol: 0.6 })
+    expect(fit?.max_displacement).toBeCloseTo(0.5, 10)
+    const half = make_crystal(supercell.lattice.matrix, [[`Ba`, [0, 0, 0]]])
+    expect(fit_supercell(sample_structure, half)).toBeNull()
+  })
+})
@@ -0,0 +1,140 @@
+import type { ElementSymbol } from '$lib/element'
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { AnyStructure } from '$lib/structure'
+import { perceive_topology } from '$lib/structure'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const make_molecule = (atoms: [ElementSymbol, Vec3][]): AnyStructure => ({
+  sites: atoms.map(([element, xyz], idx) => ({
+    species: [{ element, occu: 1, oxidation_state: 0 }],
+    xyz,
+    abc: [0, 0, 0],
+    label: `${element}${idx + 1}`,
+    properties: {},
+  })),
+  charge: 0,
+})
+
+// staggered ethane, C-C 1.53 Å, C-H 1.09 Å
+const ethane = make_molecule([
+  [`C`, [0, 0, 0.765]],
+  [`C`, [0, 0, -0.765]],
+  [`H`, [1.027, 0, 1.129]],
+  [`H`, [-0.514, 0.89, 1.129]],
+  [`H`, [-0.514, -0.89, 1.129]],
+  [`H`, [0.514, 0.89, -1.129]],
+  [`H`, [-1.027, 0, -1.129]],
+  [`H`, [0.514, -0.89, -1.129]],
+])
+
+const ethylene = make_molecule([
+  [`C`, [0.665, 0, 0]],
+  [`C`, [-0.665, 0, 0]],
+  [`H`, [1.185, 0.958, 0]],
+  [`H`, [1.185, -0.958, 0]],
+  [`H`, [-1.185, 0.958, 0]],
+  [`H`, [-1.185, -0.958, 0]],
+])
+
+describe(`perceive_topology`, () => {
+  test(`ethane: bonds, angles and dihedrals`, () => {
+    const topology = perceive_topology(ethane)
+    expect(topology.bonds).toHaveLength(7)
+    expect(topology.bonds.every(({ order }) => order === 1)).toBe(true)
+    const cc_bond = topology.bonds.find(
+      ({ site_idx_1, site_idx_2 }) => site_idx_1 === 0 && site_idx_2 === 1,
+    )
+    expect(cc_bond?.length).toBeCloseTo(1.53, 10)
+    expect(topology.bonds.some(({ cell_shift }) => cell_shift)).toBe(false)
+    // 6 angles around each sp3 carbon, none around the terminal hydrogens
+    expect(topology.angles).toHaveLength(12)
+    expect(topology.angles.every(([, center]) => center < 2)).toBe(true)
+    // 3 x 3 H-C-C-H torsions around the single C-C bond
+    expect(topology.dihedrals).toHaveLength(9)
+    for (const [idx_i, idx_j, idx_k, idx_l] of topology.dihedrals) {
+      expect([idx_j, idx_k]).toEqual([0, 1])
+      expect(idx_i).toBeGreaterThanOrEqual(2)
+      expect(idx_l).toBeGreaterThanOrEqual(2)
+    }
+    expect(topology.impropers).toEqual([])
+    expect(topology.fragments).toEqual([[0, 1, 2, 3, 4, 5, 6, 7]])
+    expect(topology.molecule_ids).toEqual(Array(8).fill(0))
+  })
+
+  test(`ethylene: double bond and impropers on sp2 carbons`, () => {
+    const topology = perceive_topology(ethylene)
+    const orders = new Map(
+      topology.bonds.map(({ site_idx_1, site_idx_2, order }) => [
+        `${site_idx_1}-${site_idx_2}`,
+        order,
+      ]),
+    )
+    expect(orders.get(`0-1`)).toBe(2)
+    expect(orders.get(`0-2`)).toBe(1)
+    expect(topology.angles).toHaveLength(6)
+    expect(topology.dihedrals).toHaveLength(4)
+    expect(topology.impropers.map(([center]) => center)).toEqual([0, 1])
+    const outer_atoms = topology.impropers.map(([, ...others]) => others.toSorted())
+    expect(outer_atoms).toEqual([
+      [1, 2, 3],
+      [0, 4, 5],
+    ])
+    // without perception every bond stays single
+    const unperceived = perceive_topology(ethylene, { perceive_orders: false })
+    expect(unperceived.bonds.map(({ order }) => order)).toEqual(Array(5).fill(1))
+  })
+
+  test(`separate molecules become separate fragments`, () => {
+    const waters = make_molecule([
+      [`O`, [0, 0, 0]],
+      [`H`, [0.757, 0.586, 0]],
+      [`H`, [-0.757, 0.586, 0]],
+      [`O`, [5, 0, 0]],
+      [`H`, [5.757, 0.586, 0]],
+      [`H`, [4.243, 0.586, 0]],
+    ])
+    const topology = perceive_topology(waters)
+    expect(topology.fragments).toEqual([
+      [0, 1, 2],
+      [3, 4, 5],
+    ])
+    expect(topology.molecule_ids).toEqual([0, 0, 0, 1, 1, 1])
+    expect(topology.bonds).toHaveLength(4)
+    expect(topology.angles).toEqual([
+      [1, 0, 2],
+      [4, 3, 5],
+    ])
+    expect(topology.dihedrals).toEqual([])
+  })
+
+  test(`bonds to periodic images in an infinite chain`, () => {
+    const lattice: Matrix3x3 = [
+      [1.5, 0, 0],
+      [0, 10, 0],
+      [0, 0, 10],
+    ]
+    const chain = make_crystal(lattice, [[`C`, [0, 0, 0]]])
+    const topology = perceive_topology(chain, { perceive_orders: false })
+    expect(topology.bonds).toEqual([
+      { site_idx_1: 0, site_idx_2: 0, order: 1, length: 1.5, cell_shift: [1, 0, 0] },
+    ])
+    // the atom's two images span one angle and one torsion
+    expect(topology.angles).toEqual([[0, 0, 0]])
+    expect(topology.dihedrals).toEqual([[0, 0, 0, 0]])
+    expect(topology.fragments).toEqual([[0]])
+  })
+
+  test(`tolerance and min_distance control bond detection`, () => {
+    // C-H stretched to 1.6 Å: beyond 0.31 + 0.76 + 0.45 = 1.52 Å
+    const stretched = make_molecule([
+      [`C`, [0, 0, 0]],
+      [`H`, [1.6, 0, 0]],
+    ])
+    expect(perceive_topology(stretched).bonds).toEqual([])
+    expect(perceive_topology(stretched, { tolerance: 0.6 }).bonds).toHaveLength(1)
+    const options = { tolerance: 0.6, min_distance: 2 }
+    expect(perceive_topology(stretched, options).bonds).toEqual([])
+    expect(perceive_topology(make_molecule([])).fragments).toEqual([])
+  })
+})
@@ -1,4 +1,6 @@
 import type { Vec3 } from '$lib/math'
+import { set_site_field } from '$lib/structure'
+import { structure_to_xyz_str } from '$lib/structure/export'
 import type { CellType } from '$lib/symmetry'
 import { moyo_cell_to_structure, transform_cell } from '$lib/symmetry'
 import type { MoyoCell, MoyoDataset } from '@spglib/moyo-wasm'
@@ -185,4 +187,14 @@ describe(`transform_cell`, () => {
       expect(result.properties).toEqual({ custom_metadata: `kept` })
     },
   )
+
+  test(`drops site fields so the conventional cell of a primitive cell still exports`, () => {
+    const primitive = set_site_field(make_crystal(3, [[`Na`, [0, 0, 0]]]), `charge`, [0.9])
+    const conventional = transform_cell(primitive, `conventional`, sym_data)
+    expect(conventional.sites).toHaveLength(2)
+    expect(conventional.properties?.site_fields).toBeUndefined()
+    const lines = structure_to_xyz_str(conventional).split(`\n`)
+    expect(lines[0]).toBe(`2`)
+    expect(lines[1]).not.toContain(`charge`)
+  })
 })
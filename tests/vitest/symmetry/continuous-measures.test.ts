@@ -0,0 +1,146 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import type { SymmetryOperation } from '$lib/symmetry'
+import {
+  chirality_measure,
+  IDEAL_POLYHEDRA,
+  is_chiral_environment,
+  is_chiral_space_group,
+  local_environment,
+  local_shape_measures,
+  shape_measure,
+  space_group_deviation,
+} from '$lib/symmetry'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// rotation by 0.7 rad about z followed by 1.1 rad about y
+const rotation: Matrix3x3 = math.dot(
+  [
+    [Math.cos(1.1), 0, Math.sin(1.1)],
+    [0, 1, 0],
+    [-Math.sin(1.1), 0, Math.cos(1.1)],
+  ],
+  [
+    [Math.cos(0.7), -Math.sin(0.7), 0],
+    [Math.sin(0.7), Math.cos(0.7), 0],
+    [0, 0, 1],
+  ],
+)
+
+describe(`shape_measure`, () => {
+  test.each(Object.entries(IDEAL_POLYHEDRA))(
+    `%s is zero after rotation, scaling, translation and reordering`,
+    (_shape, vertices) => {
+      const moved = vertices
+        .map((vertex) => math.scale(math.mat3x3_vec3_multiply(rotation, vertex), 2.3))
+        .map((vertex) => math.add(vertex, [1, -2, 3]))
+        .reverse()
+      expect(shape_measure(moved, vertices)).toBeCloseTo(0, 8)
+    },
+  )
+
+  test(`distances between ideal shapes`, () => {
+    const { tetrahedron, square_planar, octahedron, trigonal_prism } = IDEAL_POLYHEDRA
+    expect(shape_measure(tetrahedron, square_planar)).toBeCloseTo(100 / 3, 8)
+    expect(shape_measure(square_planar, tetrahedron)).toBeCloseTo(100 / 3, 8)
+    expect(shape_measure(octahedron, trigonal_prism)).toBeCloseTo(21.3288, 3)
+  })
+
+  test(`grows with distortion`, () => {
+    const { octahedron } = IDEAL_POLYHEDRA
+    const stretch = (factor: number): Vec3[] =>
+      octahedron.map(([x, y, z]) => [x, y, z * factor])
+    const small = shape_measure(stretch(1.05), octahedron)
+    expect(small).toBeGreaterThan(0)
+    expect(shape_measure(stretch(1.2), octahedron)).toBeGreaterThan(small)
+  })
+
+  test(`mismatched point counts throw`, () => {
+    expect(() => shape_measure(IDEAL_POLYHEDRA.cube, IDEAL_POLYHEDRA.octahedron)).toThrow(
+      `Shape measure needs equal point counts, got 8 vs 6`,
+    )
+  })
+})
+
+describe(`chirality`, () => {
+  test(`environments`, () => {
+    expect(chirality_measure(IDEAL_POLYHEDRA.tetrahedron)).toBeCloseTo(0, 8)
+    expect(is_chiral_environment(IDEAL_POLYHEDRA.octahedron)).toBe(false)
+    // four unequal arms without mirror plane
+    const twisted: Vec3[] = [[1, 0, 0], [0, 1.3, 0], [0, 0, 1.7], [0.2, 0.3, 0.4]]
+    expect(chirality_measure(twisted)).toBeGreaterThan(1)
+    expect(is_chiral_environment(twisted)).toBe(true)
+  })
+
+  test(`space groups`, () => {
+    const op = (rotation: number[]): SymmetryOperation => ({
+      rotation,
+      translation: [0, 0, 0],
+    })
+    const identity = op([1, 0, 0, 0, 1, 0, 0, 0, 1])
+    const two_fold = op([-1, 0, 0, 0, -1, 0, 0, 0, 1])
+    const inversion = op([-1, 0, 0, 0, -1, 0, 0, 0, -1])
+    expect(is_chiral_space_group([identity, two_fold])).toBe(true)
+    expect(is_chiral_space_group([identity, inversion])).toBe(false)
+  })
+})
+
+describe(`local environments`, () => {
+  const a_nacl = 5.64
+  const fcc: Vec3[] = [[0, 0, 0], [0.5, 0.5, 0], [0.5, 0, 0.5], [0, 0.5, 0.5]]
+  const rocksalt = make_crystal(a_nacl, [
+    ...fcc.map((abc): [string, Vec3] => [`Na`, abc]),
+    ...fcc.map((abc): [string, Vec3] => [`Cl`, math.add(abc, [0.5, 0, 0])]),
+  ])
+
+  test(`rock-salt Na sits in an ideal octahedron`, () => {
+    const { neighbor_indices, vectors } = local_environment(rocksalt, 0, 6)
+    expect(neighbor_indices.every((idx) => idx >= 4)).toBe(true)
+    for (const vec of vectors) expect(Math.hypot(...vec)).toBeCloseTo(a_nacl / 2, 8)
+    const measures = local_shape_measures(rocksalt, 0, 6)
+    expect(measures.map(({ shape }) => shape)).toEqual([`octahedron`, `trigonal_prism`])
+    expect(measures[0].measure).toBeCloseTo(0, 8)
+  })
+
+  test(`too few neighbors within cutoff throws`, () => {
+    expect(() => local_environment(rocksalt, 0, 6, 2)).toThrow(
+      `Site 0 has 0 neighbors within 2 Å, need 6`,
+    )
+  })
+})
+
+describe(`space_group_deviation`, () => {
+  // point group mmm: all sign flips of the axes
+  const mmm_ops: SymmetryOperation[] = [1, -1].flatMap((sx) =>
+    [1, -1].flatMap((sy) =>
+      [1, -1].map((sz) => ({
+        rotation: [sx, 0, 0, 0, sy, 0, 0, 0, sz],
+        translation: [0, 0, 0],
+      })),
+    ),
+  )
+  const perovskite = (ti_dz: number) =>
+    make_crystal(4, [
+      [`Ba`, [0, 0, 0]],
+      [`Ti`, [0.5, 0.5, 0.5 + ti_dz / 4]],
+      [`O`, [0.5, 0.5, 0]],
+      [`O`, [0.5, 0, 0.5]],
+      [`O`, [0, 0.5, 0.5]],
+    ])
+
+  test(`off-center Ti deviates from the centrosymmetric group`, () => {
+    const result = space_group_deviation(perovskite(0.1), mmm_ops)
+    expect(result.deviations[1][2]).toBeCloseTo(-0.1, 10)
+    expect(result.symmetrized_abc[1][2]).toBeCloseTo(0.5, 10)
+    expect(result.max).toBeCloseTo(0.1, 10)
+    expect(result.rms).toBeCloseTo(0.1 / Math.sqrt(5), 10)
+    expect(space_group_deviation(perovskite(0), mmm_ops).max).toBeCloseTo(0, 10)
+  })
+
+  test(`operations that don't nearly map the structure onto itself throw`, () => {
+    expect(() => space_group_deviation(perovskite(0.8), mmm_ops, 1)).toThrow(
+      `Operation 1 maps site 1 more than 1 Å from any site`,
+    )
+  })
+})
@@ -0,0 +1,137 @@
+import type { Matrix3x3 } from '$lib/math'
+import type { SymmetryOperation } from '$lib/symmetry'
+import { decompose_distortion } from '$lib/symmetry'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// point group mmm: all sign flips of the axes (diagonal, so row- and column-major agree)
+const mmm_ops: SymmetryOperation[] = [1, -1].flatMap((sx) =>
+  [1, -1].flatMap((sy) =>
+    [1, -1].map((sz) => ({
+      rotation: [sx, 0, 0, 0, sy, 0, 0, 0, sz],
+      translation: [0, 0, 0],
+    })),
+  ),
+)
+
+// cubic perovskite (a = 4 Å) with Ti shifted by ti_dz (Å) along c
+const perovskite = (ti_dz = 0) =>
+  make_crystal(4, [
+    [`Ba`, [0, 0, 0]],
+    [`Ti`, [0.5, 0.5, 0.5 + ti_dz / 4]],
+    [`O`, [0.5, 0.5, 0]],
+    [`O`, [0.5, 0, 0.5]],
+    [`O`, [0, 0.5, 0.5]],
+  ])
+
+describe(`decompose_distortion`, () => {
+  test(`polar Ti shift is purely symmetry breaking`, () => {
+    const options = { remove_translation: false }
+    const result = decompose_distortion(perovskite(), perovskite(0.1), mmm_ops, options)
+    expect(result.site_mapping).toEqual([0, 1, 2, 3, 4])
+    expect(result.orbits).toEqual([[0], [1], [2], [3], [4]])
+    expect(result.total.amplitude).toBeCloseTo(0.1, 10)
+    expect(result.symmetric.amplitude).toBeCloseTo(0, 10)
+    expect(result.breaking.amplitude).toBeCloseTo(0.1, 10)
+    expect(result.breaking.orbit_amplitudes[1]).toBeCloseTo(0.1, 10)
+    expect(result.breaking.displacements[1][2]).toBeCloseTo(0.1, 10)
+    // the distortion keeps the operations that don't flip z (polar point group mm2)
+    expect(result.isotropy_ops).toEqual([0, 2, 4, 6])
+  })
+
+  test(`rigid translation is removed by default`, () => {
+    const result = decompose_distortion(perovskite(), perovskite(0.1), mmm_ops)
+    // Ti moves 0.08 Å, the other 4 sites -0.02 Å
+    expect(result.total.amplitude).toBeCloseTo(Math.sqrt(0.08 ** 2 + 4 * 0.02 ** 2), 10)
+    expect(result.total.displacements[0][2]).toBeCloseTo(-0.02, 10)
+  })
+
+  test(`symmetric breathing mode with reordered child sites`, () => {
+    const lattice: Matrix3x3 = [
+      [4, 0, 0],
+      [0, 4, 0],
+      [0, 0, 8],
+    ]
+    const parent = make_crystal(lattice, [
+      [`Na`, [0, 0, 0]],
+      [`Cl`, [0, 0, 0.25]],
+      [`Cl`, [0, 0, 0.75]],
+    ])
+    const child = make_crystal(lattice, [
+      [`Cl`, [0, 0, 0.74]],
+      [`Na`, [0, 0, 0]],
+      [`Cl`, [0, 0, 0.26]],
+    ])
+    const result = decompose_distortion(parent, child, mmm_ops)
+    expect(result.site_mapping).toEqual([1, 2, 0])
+    expect(result.orbits).toEqual([[0], [1, 2]])
+    expect(result.symmetric.amplitude).toBeCloseTo(Math.sqrt(2) * 0.08, 10)
+    expect(result.symmetric.orbit_amplitudes[1]).toBeCloseTo(Math.sqrt(2) * 0.08, 10)
+    expect(result.breaking.amplitude).toBeCloseTo(0, 10)
+    expect(result.isotropy_ops).toEqual([...mmm_ops.keys()])
+  })
+
+  test(`projects displacements onto irreps with characters and isotropy subgroups`, () => {
+    const parent = perovskite()
+    // Ti shifted along c, the two equatorial O along a and b by different amounts
+    const child = make_crystal(4, [
+      [`Ba`, [0, 0, 0]],
+      [`Ti`, [0.5, 0.5, 0.5 + 0.1 / 4]],
+      [`O`, [0.5, 0.5, 0]],
+      [`O`, [0.5 + 0.05 / 4, 0, 0.5]],
+      [`O`, [0, 0.5 + 0.03 / 4, 0.5]],
+    ])
+    const result = decompose_distortion(parent, child, mmm_ops, { remove_translation: false })
+    const [polar_z, polar_x, polar_y] = result.irreps
+    expect(result.irreps).toHaveLength(3)
+    expect(polar_z.amplitude).toBeCloseTo(0.1, 10)
+    expect(polar_z.characters).toEqual([1, -1, 1, -1, 1, -1, 1, -1])
+    expect(polar_z.isotropy_ops).toEqual([0, 2, 4, 6])
+    expect(polar_x.amplitude).toBeCloseTo(0.05, 10)
+    expect(polar_x.displacements[3][0]).toBeCloseTo(0.05, 10)
+    expect(polar_x.characters).toEqual([1, 1, 1, 1, -1, -1, -1, -1])
+    expect(polar_y.amplitude).toBeCloseTo(0.03, 10)
+    expect(polar_y.characters).toEqual([1, 1, -1, -1, 1, 1, -1, -1])
+    // irrep amplitudes add up in quadrature to the total
+    const sum_sq = result.irreps.reduce((sum, { amplitude }) => sum + amplitude ** 2, 0)
+    expect(Math.sqrt(sum_sq)).toBeCloseTo(result.total.amplitude, 10)
+    expect(result.breaking.amplitude).toBeCloseTo(result.total.amplitude, 10)
+  })
+
+  test(`two-dimensional irrep and totally symmetric part of a 4-fold axis`, () => {
+    // C4 about c as column-major fractional rotations
+    const c4_ops: SymmetryOperation[] = [
+      [1, 0, 0, 0, 1, 0, 0, 0, 1],
+      [0, 1, 0, -1, 0, 0, 0, 0, 1],
+      [-1, 0, 0, 0, -1, 0, 0, 0, 1],
+      [0, -1, 0, 1, 0, 0, 0, 0, 1],
+    ].map((rotation) => ({ rotation, translation: [0, 0, 0] }))
+    const parent = make_crystal(4, [[`Fe`, [0, 0, 0]]])
+    const child = make_crystal(4, [[`Fe`, [0.1 / 4, 0, 0.05 / 4]]])
+    const result = decompose_distortion(parent, child, c4_ops, { remove_translation: false })
+    const [symmetric, doublet] = result.irreps
+    expect(symmetric.characters).toEqual([1, 1, 1, 1])
+    expect(symmetric.displacements[0][2]).toBeCloseTo(0.05, 10)
+    expect(symmetric.amplitude).toBeCloseTo(result.symmetric.amplitude, 10)
+    // E = complex-conjugate pair of 1D irreps, combined into one real 2D representation
+    expect(doublet.dim).toBe(2)
+    expect(doublet.characters).toEqual([2, 0, -2, 0])
+    expect(doublet.amplitude).toBeCloseTo(0.1, 10)
+    expect(doublet.isotropy_ops).toEqual([0])
+  })
+
+  test(`invalid inputs throw`, () => {
+    const parent = perovskite()
+    const shifted_op = [{ rotation: [1, 0, 0, 0, 1, 0, 0, 0, 1], translation: [0, 0, 0.1] }]
+    expect(() => decompose_distortion(parent, parent, shifted_op)).toThrow(
+      `Operation 0 does not map parent site 0 onto the parent`,
+    )
+    const fewer = make_crystal(4, [[`Ba`, [0, 0, 0]]])
+    expect(() => decompose_distortion(parent, fewer, mmm_ops)).toThrow(
+      `Parent and child site counts differ: 5 vs 1`,
+    )
+    expect(() =>
+      decompose_distortion(parent, perovskite(0.5), mmm_ops, { max_displacement: 0.2 }),
+    ).toThrow(`No child site within 0.2 Å matches parent site 1 (Ti)`)
+  })
+})
@@ -4,6 +4,7 @@ import type { Crystal } from '$lib/structure'
 import type { WyckoffPos } from '$lib/symmetry'
 import {
   apply_symmetry_operations,
+  distinct_sites_from_moyo,
   map_std_to_orig_site_indices,
   map_wyckoff_to_all_atoms,
   simplicity_score,
@@ -186,6 +187,30 @@ describe(`wyckoff_positions_from_moyo`, () => {
   })
 })
 
+describe(`distinct_sites_from_moyo`, () => {
+  test(`one representative per orbit with multiplicities, filtered by element`, () => {
+    const dataset = make_wyckoff_dataset(
+      [
+        [0, 0, 0],
+        [0.5, 0.5, 0],
+        [0.5, 0, 0.5],
+        [0, 0.5, 0.5],
+        [0.5, 0.5, 0.5],
+      ],
+      [8, 1, 1, 1, 8],
+      [`1a`, `3c`, `3c`, `3c`, `1b`],
+    )
+    expect(distinct_sites_from_moyo(dataset)).toEqual([
+      { site_idx: 0, site_indices: [0], multiplicity: 1, wyckoff: `1a`, elem: `O` },
+      { site_idx: 1, site_indices: [1, 2, 3], multiplicity: 3, wyckoff: `3c`, elem: `H` },
+      { site_idx: 4, site_indices: [4], multiplicity: 1, wyckoff: `1b`, elem: `O` },
+    ])
+    const oxygens = distinct_sites_from_moyo(dataset, [`O`])
+    expect(oxygens.map(({ site_idx }) => site_idx)).toEqual([0, 4])
+    expect(distinct_sites_from_moyo(null)).toEqual([])
+  })
+})
+
 describe(`simplicity_score`, () => {
   test.each([
     [[0, 0, 0], 0.75],
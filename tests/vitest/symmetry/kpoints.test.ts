@@ -0,0 +1,109 @@
+import type { Vec3 } from '$lib/math'
+import type { SymmetryOperation } from '$lib/symmetry'
+import {
+  irreducible_kpoints_from_operations,
+  irreducible_kpoints_to_vasp,
+} from '$lib/symmetry'
+import { describe, expect, test } from 'vitest'
+
+// the 48 operations of m-3m as signed permutation matrices (flattened column-major)
+const permutations = [
+  [0, 1, 2],
+  [0, 2, 1],
+  [1, 0, 2],
+  [1, 2, 0],
+  [2, 0, 1],
+  [2, 1, 0],
+]
+const cubic_ops: SymmetryOperation[] = permutations.flatMap((perm) =>
+  [0, 1, 2, 3, 4, 5, 6, 7].map((signs) => {
+    const rotation = Array<number>(9).fill(0)
+    perm.forEach((col, row) => {
+      rotation[row + 3 * col] = signs & (1 << row) ? -1 : 1
+    })
+    return { rotation, translation: [0, 0, 0] }
+  }),
+)
+
+describe(`irreducible_kpoints_from_operations`, () => {
+  test.each([
+    [[4, 4, 4], [0, 0, 0], [1, 6, 3, 12, 12, 3, 8, 12, 6, 1]],
+    [[4, 4, 4], [0.5, 0.5, 0.5], [8, 24, 24, 8]],
+    [[3, 3, 3], [0.5, 0.5, 0.5], [8, 12, 6, 1]],
+  ])(`cubic %j mesh with shift %j`, (mesh, shift, weights) => {
+    const result = irreducible_kpoints_from_operations(mesh as Vec3, cubic_ops, {
+      shift: shift as Vec3,
+    })
+    expect(result.weights).toEqual(weights)
+    expect(result.n_operations).toBe(48)
+  })
+
+  test(`mapping sends every grid point to a symmetry-equivalent irreducible point`, () => {
+    const { grid, kpoints, weights, mapping } = irreducible_kpoints_from_operations(
+      [8, 8, 8],
+      cubic_ops,
+    )
+    expect(kpoints).toHaveLength(35)
+    expect(grid).toHaveLength(512)
+    // cubic images of k are its coordinates permuted with signs flipped
+    const invariant = (kpt: Vec3) =>
+      kpt
+        .map((val) => Math.abs(val))
+        .toSorted()
+        .join()
+    grid.forEach((kpt, idx) => expect(invariant(kpt)).toBe(invariant(kpoints[mapping[idx]])))
+    const counts = kpoints.map((_, ir_idx) => mapping.filter((val) => val === ir_idx).length)
+    expect(counts).toEqual(weights)
+    // grid index i + n_a (j + n_b k), coordinates in [-0.5, 0.5)
+    expect(grid[1]).toEqual([0.125, 0, 0])
+    expect(grid[8 + 4]).toEqual([-0.5, 0.125, 0])
+  })
+
+  test(`time reversal alone pairs k with -k`, () => {
+    const with_tr = irreducible_kpoints_from_operations([4, 4, 4], [])
+    // the 8 points with k ≡ -k are their own partners
+    expect(with_tr.kpoints).toHaveLength((64 + 8) / 2)
+    const without = irreducible_kpoints_from_operations([4, 4, 4], [], {
+      time_reversal: false,
+    })
+    expect(without.weights).toEqual(Array(64).fill(1))
+    expect(without.n_operations).toBe(1)
+  })
+
+  test(`skips rotations that don't keep the grid`, () => {
+    // only the 16 operations of 4/mmm along c map a 4x4x2 grid onto itself
+    const result = irreducible_kpoints_from_operations([4, 4, 2], cubic_ops)
+    expect(result.n_operations).toBe(16)
+    expect(result.weights.reduce((sum, val) => sum + val, 0)).toBe(32)
+    expect(result.kpoints).toHaveLength(12)
+  })
+
+  test(`rejects invalid meshes and shifts`, () => {
+    expect(() => irreducible_kpoints_from_operations([4, 0, 4], cubic_ops)).toThrow(
+      `mesh must be 3 positive integers, got [4,0,4]`,
+    )
+    expect(() =>
+      irreducible_kpoints_from_operations([4, 4, 4], cubic_ops, { shift: [0.25, 0, 0] }),
+    ).toThrow(`shift must be 0 or 0.5 along each axis, got [0.25,0,0]`)
+  })
+})
+
+describe(`irreducible_kpoints_to_vasp`, () => {
+  test(`writes an explicit KPOINTS list with weights`, () => {
+    const result = irreducible_kpoints_from_operations([4, 4, 4], cubic_ops, {
+      shift: [0.5, 0.5, 0.5],
+    })
+    const lines = irreducible_kpoints_to_vasp(result).trimEnd().split(`\n`)
+    expect(lines.slice(0, 3)).toEqual([
+      `Irreducible k-points of a 4x4x4 grid`,
+      `4`,
+      `Reciprocal`,
+    ])
+    expect(lines.slice(3).map((line) => line.trim().split(/\s+/).map(Number))).toEqual([
+      [0.125, 0.125, 0.125, 8],
+      [0.375, 0.125, 0.125, 24],
+      [0.375, 0.375, 0.125, 24],
+      [0.375, 0.375, 0.375, 8],
+    ])
+  })
+})
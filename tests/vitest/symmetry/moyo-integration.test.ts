@@ -7,12 +7,17 @@ import type { Crystal } from '$lib'
 import {
   analyze_structure_symmetry,
   apply_symmetry_operations,
+  canonicalize_structure,
+  find_primitive_cell,
   get_conventional_cell,
   get_primitive_cell,
+  irreducible_kpoints,
   map_wyckoff_to_all_atoms,
   SPACEGROUP_SYMBOL_TO_NUM,
   spacegroup_num_to_crystal_sys,
+  snap_to_symmetry,
   spacegroup_num_to_lattice_system,
+  symmetry_distinct_sites,
   wyckoff_multiplicity,
   wyckoff_positions_from_moyo,
 } from '$lib/symmetry'
@@ -352,6 +357,159 @@ describe(`map_wyckoff_to_all_atoms across display frames`, () => {
   })
 })
 
+describe(`symmetry_distinct_sites`, () => {
+  beforeAll(init_moyo_for_tests)
+
+  test(`rocksalt conventional cell has one Na and one Cl orbit`, async () => {
+    const na_abc: Vec3[] = [
+      [0, 0, 0],
+      [0.5, 0.5, 0],
+      [0.5, 0, 0.5],
+      [0, 0.5, 0.5],
+    ]
+    const cl_abc = na_abc.map((abc) => abc.map((coord) => (coord + 0.5) % 1) as Vec3)
+    const nacl = make_crystal(5.64, [
+      ...na_abc.map((abc) => ({ element: `Na` as const, abc })),
+      ...cl_abc.map((abc) => ({ element: `Cl` as const, abc })),
+    ])
+    const sites = await symmetry_distinct_sites(nacl, { symprec: 1e-4 })
+    const summary = sites.map(({ elem, site_idx, multiplicity }) => [
+      elem,
+      site_idx,
+      multiplicity,
+    ])
+    expect(summary).toEqual([
+      [`Na`, 0, 4],
+      [`Cl`, 4, 4],
+    ])
+    // 4a/4b depending on which sublattice moyo puts at the origin
+    expect(sites.map(({ wyckoff }) => wyckoff).toSorted()).toEqual([`4a`, `4b`])
+    const chlorine = await symmetry_distinct_sites(nacl, { elements: [`Cl`] })
+    expect(chlorine.map(({ site_indices }) => site_indices)).toEqual([[4, 5, 6, 7]])
+  })
+})
+
+describe(`snap_to_symmetry`, () => {
+  beforeAll(init_moyo_for_tests)
+
+  test(`noisy relaxed rocksalt snaps back to exact Fm-3m`, async () => {
+    const noisy = make_crystal(
+      [
+        [5.641, 0.003, 0],
+        [-0.002, 5.638, 0],
+        [0, 0.001, 5.64],
+      ],
+      [
+        { element: `Na`, abc: [0.001, 0, 0] },
+        { element: `Na`, abc: [0.5, 0.499, 0] },
+        { element: `Na`, abc: [0.5, 0, 0.501] },
+        { element: `Na`, abc: [0, 0.5, 0.5] },
+        { element: `Cl`, abc: [0.5, 0, 0] },
+        { element: `Cl`, abc: [0, 0.5, 0.001] },
+        { element: `Cl`, abc: [0, 0, 0.5] },
+        { element: `Cl`, abc: [0.499, 0.5, 0.5] },
+      ],
+    )
+    expect((await analyze_crystal(noisy, 1e-4)).number).not.toBe(225)
+    const snapped = await snap_to_symmetry(noisy, { symprec: 0.05 })
+    expect((await analyze_crystal(snapped, 1e-6)).number).toBe(225)
+    const { a, b, c, gamma } = snapped.lattice
+    expect([b, c]).toEqual([expect.closeTo(a, 8), expect.closeTo(a, 8)])
+    expect(gamma).toBeCloseTo(90, 8)
+  })
+})
+
+describe(`irreducible_kpoints`, () => {
+  beforeAll(init_moyo_for_tests)
+
+  test(`rocksalt conventional cell reduces like m-3m`, async () => {
+    const fcc_sites: Vec3[] = [
+      [0, 0, 0],
+      [0.5, 0.5, 0],
+      [0.5, 0, 0.5],
+      [0, 0.5, 0.5],
+    ]
+    const nacl = make_crystal(5.64, [
+      ...fcc_sites.map((abc): [string, Vec3] => [`Na`, abc]),
+      ...fcc_sites.map((abc): [string, Vec3] => [`Cl`, [(abc[0] + 0.5) % 1, abc[1], abc[2]]]),
+    ])
+    const gamma = await irreducible_kpoints(nacl, [4, 4, 4])
+    expect(gamma.weights).toEqual([1, 6, 3, 12, 12, 3, 8, 12, 6, 1])
+    expect(gamma.n_operations).toBe(48)
+    const shifted = await irreducible_kpoints(nacl, [4, 4, 4], { shift: [0.5, 0.5, 0.5] })
+    expect(shifted.weights).toEqual([8, 24, 24, 8])
+  })
+})
+
+describe(`find_primitive_cell`, () => {
+  beforeAll(init_moyo_for_tests)
+
+  test(`noisy supercell only reduces at a looser tolerance`, async () => {
+    const noisy = supercell_po()
+    noisy.sites[1] = { ...noisy.sites[1], abc: [0.503, 0, 0] }
+    noisy.sites[1].xyz = [0.503 * 2 * PO_A, 0, 0]
+    const { structure, symprec, spacegroup, scan } = await find_primitive_cell(noisy)
+    expect(structure.sites).toHaveLength(1)
+    expect(spacegroup).toBe(221)
+    expect(symprec).toBeGreaterThan(0.01)
+    // tight tolerances keep both atoms
+    expect(scan[0]).toMatchObject({ symprec: 1e-5, n_sites: 2 })
+    expect(scan.map((entry) => entry.symprec)).toEqual([1e-5, 1e-4, 1e-3, 0.01, 0.03, 0.1])
+  })
+})
+
+describe(`canonicalize_structure`, () => {
+  beforeAll(init_moyo_for_tests)
+
+  test(`reordered sites in a rotated frame serialize byte-identically`, async () => {
+    const reference = await canonicalize_structure(prim_diamond_si())
+    // rotate the Cartesian frame by 30° about z and reverse the site order
+    const [cos, sin] = [Math.cos(Math.PI / 6), Math.sin(Math.PI / 6)]
+    const { lattice, sites } = prim_diamond_si()
+    const rotated = make_crystal(
+      lattice.matrix.map(([x, y, z]): Vec3 => [cos * x - sin * y, sin * x + cos * y, z]),
+      sites.toReversed().map(({ species, abc }) => ({ element: species[0].element, abc })),
+    )
+    const canonical = await canonicalize_structure(rotated)
+    expect(JSON.stringify(canonical)).toBe(JSON.stringify(reference))
+    expect(canonical.sites).toHaveLength(8) // conventional Fd-3m cell
+    expect(canonical.lattice.matrix).toEqual([
+      [SI_A, 0, 0],
+      [0, SI_A, 0],
+      [0, 0, SI_A],
+    ])
+    const primitive = await canonicalize_structure(rotated, { cell: `primitive` })
+    expect(primitive.sites).toHaveLength(2)
+  })
+
+  test(`sites sort by element, then Wyckoff orbit, then coordinates`, async () => {
+    const na_abc: Vec3[] = [
+      [0, 0, 0],
+      [0.5, 0.5, 0],
+      [0.5, 0, 0.5],
+      [0, 0.5, 0.5],
+    ]
+    const cl_abc = na_abc.map((abc) => abc.map((coord) => (coord + 0.5) % 1) as Vec3)
+    const nacl = make_crystal(5.64, [
+      ...cl_abc.map((abc) => ({ element: `Cl` as const, abc })),
+      ...na_abc.toReversed().map((abc) => ({ element: `Na` as const, abc })),
+    ])
+    const { sites } = await canonicalize_structure(nacl)
+    expect(sites.map(({ species }) => species[0].element)).toEqual([
+      ...Array(4).fill(`Na`),
+      ...Array(4).fill(`Cl`),
+    ])
+    for (const group of [sites.slice(0, 4), sites.slice(4)]) {
+      const keys = group.map(({ abc }) => abc.join(`,`))
+      expect(keys).toEqual(keys.toSorted())
+    }
+    for (const { abc } of sites) {
+      for (const coord of abc) expect([0, 0.5]).toContain(coord)
+    }
+    await expect(canonicalize_structure(nacl, { decimals: 1.5 })).rejects.toThrow(`decimals`)
+  })
+})
+
 // Cross-validate the hand-rolled space group tables against moyo's authoritative data
 describe(`space group tables vs moyo`, () => {
   beforeAll(init_moyo_for_tests)
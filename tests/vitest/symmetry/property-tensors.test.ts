@@ -0,0 +1,246 @@
+import type { Matrix3x3 } from '$lib/math'
+import type { PiezoTensor } from '$lib/symmetry'
+import {
+  cartesian_rotations,
+  check_dielectric_symmetry,
+  check_piezo_symmetry,
+  ieee_rotation,
+  max_dielectric_response,
+  max_piezo_response,
+  piezo_from_full,
+  piezo_to_full,
+  rotate_dielectric,
+  rotate_piezo,
+  symmetrize_dielectric,
+  symmetrize_piezo,
+} from '$lib/symmetry'
+import { describe, expect, test } from 'vitest'
+
+const mat_mul = (mat_1: Matrix3x3, mat_2: Matrix3x3): Matrix3x3 =>
+  mat_1.map((row) =>
+    [0, 1, 2].map((jdx) => row.reduce((sum, val, kdx) => sum + val * mat_2[kdx][jdx], 0)),
+  ) as Matrix3x3
+
+// closure of a set of generators under multiplication
+function generate_group(generators: Matrix3x3[]): Matrix3x3[] {
+  const key = (mat: Matrix3x3) => mat.flat().map((val) => Math.round(val * 1e6)).join()
+  const group = new Map<string, Matrix3x3>()
+  const queue: Matrix3x3[] = [
+    [
+      [1, 0, 0],
+      [0, 1, 0],
+      [0, 0, 1],
+    ],
+  ]
+  while (queue.length > 0) {
+    const mat = queue.pop() as Matrix3x3
+    if (group.has(key(mat))) continue
+    group.set(key(mat), mat)
+    for (const gen of generators) queue.push(mat_mul(gen, mat))
+  }
+  return [...group.values()]
+}
+
+const rot_z_90: Matrix3x3 = [
+  [0, -1, 0],
+  [1, 0, 0],
+  [0, 0, 1],
+]
+const mirror_x: Matrix3x3 = [
+  [-1, 0, 0],
+  [0, 1, 0],
+  [0, 0, 1],
+]
+const inversion: Matrix3x3 = [
+  [-1, 0, 0],
+  [0, -1, 0],
+  [0, 0, -1],
+]
+const rot_xyz: Matrix3x3 = [
+  [0, 0, 1],
+  [1, 0, 0],
+  [0, 1, 0],
+]
+const group_4mm = generate_group([rot_z_90, mirror_x]) // e.g. tetragonal BaTiO3
+const group_m3m = generate_group([rot_z_90, rot_xyz, inversion])
+
+// generic tensor without any symmetry
+const generic: PiezoTensor = [
+  [0.3, -0.2, 0.1, 0.5, 1.1, -0.4],
+  [-0.1, 0.25, 0.2, 0.9, 0.05, 0.15],
+  [-0.6, -0.4, 5.2, 0.1, -0.3, 0.2],
+]
+
+describe(`piezoelectric tensors`, () => {
+  test(`Voigt round trip and shear factors`, () => {
+    for (const kind of [`e`, `d`] as const) {
+      expect(piezo_from_full(piezo_to_full(generic, kind), kind)).toEqual(generic)
+    }
+    // d_x,xz = d15 / 2 while e_x,xz = e15
+    expect(piezo_to_full(generic, `d`)[0][0][2]).toBeCloseTo(0.55, 12)
+    expect(piezo_to_full(generic, `e`)[0][2][0]).toBe(1.1)
+    expect(() => piezo_to_full([[1, 2, 3]])).toThrow(/3x6/)
+  })
+
+  test(`2-fold rotation about z flips only components odd in x and y`, () => {
+    const rot_z_180 = mat_mul(rot_z_90, rot_z_90)
+    const rotated = rotate_piezo(generic, rot_z_180)
+    // z-row normal components and x/y-row shear components with one z index are even
+    expect(rotated[2][0]).toBeCloseTo(generic[2][0], 12)
+    expect(rotated[2][2]).toBeCloseTo(generic[2][2], 12)
+    expect(rotated[0][4]).toBeCloseTo(generic[0][4], 12)
+    expect(rotated[0][0]).toBeCloseTo(-generic[0][0], 12)
+    expect(rotated[2][5]).toBeCloseTo(generic[2][5], 12)
+  })
+
+  test(`4mm projection keeps e31 = e32, e33 and e15 = e24`, () => {
+    expect(group_4mm).toHaveLength(8)
+    const sym = symmetrize_piezo(generic, group_4mm)
+    const e31 = (generic[2][0] + generic[2][1]) / 2
+    const e15 = (generic[0][4] + generic[1][3]) / 2
+    const expected = [
+      [0, 0, 0, 0, e15, 0],
+      [0, 0, 0, e15, 0, 0],
+      [e31, e31, generic[2][2], 0, 0, 0],
+    ]
+    sym.forEach((row, idx) =>
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(expected[idx][jdx], 12)),
+    )
+    expect(check_piezo_symmetry(sym, group_4mm).valid).toBe(true)
+    expect(check_piezo_symmetry(generic, group_4mm).valid).toBe(false)
+  })
+
+  test(`centrosymmetric groups forbid piezoelectricity`, () => {
+    expect(group_m3m).toHaveLength(48)
+    const check = check_piezo_symmetry(generic, group_m3m)
+    expect(check.valid).toBe(false)
+    expect(check.max_violation).toBeCloseTo(1, 12)
+    for (const val of check.symmetrized.flat()) expect(val).toBeCloseTo(0, 12)
+  })
+
+  test(`maximum longitudinal response follows the polar axis`, () => {
+    const sym = symmetrize_piezo(generic, group_4mm)
+    const { direction, value } = max_piezo_response(sym)
+    expect(value).toBeCloseTo(5.2, 6)
+    expect(direction[2]).toBeCloseTo(1, 6)
+    // reversed polarization flips the optimal direction
+    const flipped = max_piezo_response(sym.map((row) => row.map((val) => -val)))
+    expect(flipped.direction[2]).toBeCloseTo(-1, 6)
+  })
+})
+
+describe(`dielectric tensors`, () => {
+  const uniaxial: Matrix3x3 = [
+    [2, 0, 0],
+    [0, 2, 0],
+    [0, 0, 3],
+  ]
+
+  test(`rotation swaps principal components`, () => {
+    const rotated = rotate_dielectric(
+      [
+        [2, 0, 0],
+        [0, 3, 0],
+        [0, 0, 5],
+      ],
+      rot_z_90,
+    )
+    expect(rotated[0][0]).toBeCloseTo(3, 12)
+    expect(rotated[1][1]).toBeCloseTo(2, 12)
+  })
+
+  test(`uniaxial tensor is valid for 4mm but not cubic symmetry`, () => {
+    expect(check_dielectric_symmetry(uniaxial, group_4mm).valid).toBe(true)
+    const cubic = check_dielectric_symmetry(uniaxial, group_m3m)
+    expect(cubic.valid).toBe(false)
+    cubic.symmetrized.forEach((row, idx) =>
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(idx === jdx ? 7 / 3 : 0, 12)),
+    )
+  })
+
+  test(`asymmetric tensors are symmetrized`, () => {
+    const tensor: Matrix3x3 = [
+      [2, 0.4, 0],
+      [0, 2, 0],
+      [0, 0, 2],
+    ]
+    expect(symmetrize_dielectric(tensor, [])[0][1]).toBeCloseTo(0.2, 12)
+    expect(check_dielectric_symmetry(tensor, []).valid).toBe(false)
+  })
+
+  test(`maximum response is the largest principal value`, () => {
+    const tilted = rotate_dielectric(
+      [
+        [2, 0, 0],
+        [0, 3, 0],
+        [0, 0, 5],
+      ],
+      rot_xyz,
+    )
+    const { direction, value } = max_dielectric_response(tilted)
+    expect(value).toBeCloseTo(5, 12)
+    expect(Math.abs(direction[0])).toBeCloseTo(1, 12) // rot_xyz maps z onto x
+  })
+})
+
+describe(`axes and operations`, () => {
+  // arbitrary proper rotation applied to standard cells
+  const tilt = mat_mul(
+    [
+      [Math.cos(0.3), -Math.sin(0.3), 0],
+      [Math.sin(0.3), Math.cos(0.3), 0],
+      [0, 0, 1],
+    ],
+    [
+      [1, 0, 0],
+      [0, Math.cos(0.7), -Math.sin(0.7)],
+      [0, Math.sin(0.7), Math.cos(0.7)],
+    ],
+  )
+  const apply = (rot: Matrix3x3, lattice: Matrix3x3): Matrix3x3 =>
+    lattice.map((vec) =>
+      rot.map((row) => row.reduce((sum, val, idx) => sum + val * vec[idx], 0)),
+    ) as Matrix3x3
+  const rotate_rows = (lattice: Matrix3x3) => apply(tilt, lattice)
+
+  test(`hexagonal cells get x along a and z along c`, () => {
+    const hexagonal: Matrix3x3 = [
+      [3, 0, 0],
+      [-1.5, 1.5 * Math.sqrt(3), 0],
+      [0, 0, 5],
+    ]
+    const tilted = rotate_rows(hexagonal)
+    const aligned = apply(ieee_rotation(tilted, `hexagonal`), tilted)
+    aligned.flat().forEach((val, idx) => expect(val).toBeCloseTo(hexagonal.flat()[idx], 10))
+  })
+
+  test(`monoclinic cells get y along the unique axis b`, () => {
+    const beta = (100 * Math.PI) / 180
+    const monoclinic: Matrix3x3 = [
+      [4 * Math.sin(beta), 0, 4 * Math.cos(beta)],
+      [0, 5, 0],
+      [0, 0, 6],
+    ]
+    const rot = ieee_rotation(rotate_rows(monoclinic), `monoclinic`)
+    const aligned = apply(rot, rotate_rows(monoclinic))
+    aligned.flat().forEach((val, idx) => expect(val).toBeCloseTo(monoclinic.flat()[idx], 10))
+    const det =
+      rot[0][0] * (rot[1][1] * rot[2][2] - rot[1][2] * rot[2][1]) -
+      rot[0][1] * (rot[1][0] * rot[2][2] - rot[1][2] * rot[2][0]) +
+      rot[0][2] * (rot[1][0] * rot[2][1] - rot[1][1] * rot[2][0])
+    expect(det).toBeCloseTo(1, 12)
+  })
+
+  test(`cartesian_rotations turns fractional 6-fold operations into rotations`, () => {
+    const hexagonal: Matrix3x3 = [
+      [1, 0, 0],
+      [-0.5, Math.sqrt(3) / 2, 0],
+      [0, 0, 1.6],
+    ]
+    // x' = x − y, y' = x, z' = z, flattened column-major
+    const [rot] = cartesian_rotations(hexagonal, [{ rotation: [1, 1, 0, -1, 0, 0, 0, 0, 1] }])
+    expect(rot[0][0]).toBeCloseTo(0.5, 12)
+    expect(rot[1][0]).toBeCloseTo(Math.sqrt(3) / 2, 12)
+    expect(rot[2][2]).toBeCloseTo(1, 12)
+  })
+})
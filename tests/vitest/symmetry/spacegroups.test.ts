@@ -159,6 +159,37 @@ describe(`normalize_spacegroup`, () => {
   })
 })
 
+describe(`hm_symbol_to_spacegroup_num`, () => {
+  test.each([
+    [`P 21/c`, 14],
+    [`'P 1 21/c 1'`, 14],
+    [`"P 21 21 21"`, 19],
+    [`p n m a`, 62],
+    [`Pnma S`, 62],
+    [`Fd-3m:2`, 227],
+    [`F d -3 m :1`, 227],
+    [`R -3 m H`, 166],
+    [`R3:R`, 146],
+    [`Cmca`, 64],
+    [`C m c e`, 64],
+    [`Aba2`, 41],
+    [`P6_3/mmc`, 194],
+    [`P 63/m m c`, 194],
+    [`garbage`, null],
+    [`P 99`, null],
+    [``, null],
+    [`  `, null],
+  ])(`%s -> %s`, (symbol, expected) => {
+    expect(spg.hm_symbol_to_spacegroup_num(symbol)).toBe(expected)
+  })
+
+  test(`normalize_spacegroup falls back to it for non-standard symbols`, () => {
+    expect(spg.normalize_spacegroup(`P 21/c`)).toBe(14)
+    expect(spg.normalize_spacegroup(`Cmca`)).toBe(64)
+    expect(spg.normalize_spacegroup(`Fd-3m:2`)).toBe(227)
+  })
+})
+
 describe(`SPACEGROUP_SYMBOL_TO_NUM`, () => {
   test.each([
     [`P1`, 1],
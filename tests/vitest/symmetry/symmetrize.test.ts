@@ -0,0 +1,111 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { SymmetryOperation } from '$lib/symmetry'
+import { create_symmetry_filter, symmetrize_structure } from '$lib/symmetry'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// the 48 operations of m-3m as signed permutation matrices (flattened column-major)
+const permutations = [
+  [0, 1, 2],
+  [0, 2, 1],
+  [1, 0, 2],
+  [1, 2, 0],
+  [2, 0, 1],
+  [2, 1, 0],
+]
+const cubic_ops: SymmetryOperation[] = permutations.flatMap((perm) =>
+  [0, 1, 2, 3, 4, 5, 6, 7].map((signs) => {
+    const rotation = Array<number>(9).fill(0)
+    perm.forEach((col, row) => {
+      rotation[row + 3 * col] = signs & (1 << row) ? -1 : 1
+    })
+    return { rotation, translation: [0, 0, 0] }
+  }),
+)
+
+const noisy_lattice: Matrix3x3 = [
+  [3.01, 0.004, 0],
+  [0, 2.99, -0.003],
+  [0.002, 0, 3.005],
+]
+
+describe(`symmetrize_structure`, () => {
+  test(`idealizes a noisy cubic cell and pins the site to the origin`, () => {
+    const noisy = make_crystal(noisy_lattice, [{ element: `Po`, abc: [0.001, 0, 0.002] }])
+    const snapped = symmetrize_structure(noisy, cubic_ops, { symprec: 0.05 })
+    const { a, b, c, alpha, beta, gamma } = snapped.lattice
+    expect(b).toBeCloseTo(a, 10)
+    expect(c).toBeCloseTo(a, 10)
+    for (const angle of [alpha, beta, gamma]) expect(angle).toBeCloseTo(90, 8)
+    expect(a).toBeCloseTo(3, 1)
+    // symmetric stretch keeps the cell orientation
+    snapped.lattice.matrix.forEach((row, idx) =>
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(noisy_lattice[idx][jdx], 1)),
+    )
+    const [site] = snapped.sites
+    for (const coord of site.abc) expect(Math.min(coord, 1 - coord)).toBeCloseTo(0, 10)
+  })
+
+  test(`averages positions over orbits`, () => {
+    // CsCl with the body-center atom displaced: inversion about the origin maps it onto
+    // itself only after averaging to (1/2, 1/2, 1/2)
+    const inversion: SymmetryOperation = {
+      rotation: [-1, 0, 0, 0, -1, 0, 0, 0, -1],
+      translation: [0, 0, 0],
+    }
+    const identity: SymmetryOperation = {
+      rotation: [1, 0, 0, 0, 1, 0, 0, 0, 1],
+      translation: [0, 0, 0],
+    }
+    const shift: Vec3 = [0.002, -0.001, 0.001]
+    const cscl = make_crystal(4.1, [
+      [`Cs`, [0, 0, 0]],
+      [`Cl`, [0.5 + shift[0], 0.5 + shift[1], 0.5 + shift[2]]],
+    ])
+    const snapped = symmetrize_structure(cscl, [identity, inversion], { symprec: 0.05 })
+    for (const coord of snapped.sites[1].abc) expect(coord).toBeCloseTo(0.5, 10)
+    expect(symmetrize_structure(cscl, [])).toBe(cscl)
+    expect(() => symmetrize_structure(cscl, [identity, inversion], { symprec: 1e-4 })).toThrow(
+      `Operation 1 maps site 1 (Cl)`,
+    )
+  })
+})
+
+describe(`create_symmetry_filter`, () => {
+  test(`cubic site symmetry removes forces and makes stress isotropic`, () => {
+    const po = make_crystal(3, [[`Po`, [0, 0, 0]]])
+    const filter = create_symmetry_filter(po, cubic_ops)
+    for (const val of filter.forces([[0.1, 0.2, -0.3]])[0]) expect(val).toBeCloseTo(0, 12)
+    const stress = filter.stress([
+      [1, 0.5, 0],
+      [0.5, 2, 0.1],
+      [0, 0.1, 3],
+    ])
+    stress.forEach((row, idx) =>
+      row.forEach((val, jdx) => expect(val).toBeCloseTo(idx === jdx ? 2 : 0, 12)),
+    )
+  })
+
+  test(`inversion-related pair keeps only antisymmetric forces`, () => {
+    const dimer = make_crystal(5, [
+      [`H`, [0.4, 0.5, 0.5]],
+      [`H`, [0.6, 0.5, 0.5]],
+    ])
+    const inversion: SymmetryOperation = {
+      rotation: [-1, 0, 0, 0, -1, 0, 0, 0, -1],
+      translation: [1, 1, 1],
+    }
+    const identity: SymmetryOperation = {
+      rotation: [1, 0, 0, 0, 1, 0, 0, 0, 1],
+      translation: [0, 0, 0],
+    }
+    const filter = create_symmetry_filter(dimer, [identity, inversion])
+    const [force_1, force_2] = filter.forces([
+      [0.1, 0, 0],
+      [-0.3, 0.1, 0],
+    ])
+    force_1.forEach((val, dim) => expect(val).toBeCloseTo([0.2, -0.05, 0][dim], 12))
+    force_2.forEach((val, dim) => expect(val).toBeCloseTo(-force_1[dim], 12))
+    expect(() => filter.forces([[0, 0, 0]])).toThrow(`Got 1 forces for 2 sites`)
+  })
+})
@@ -0,0 +1,92 @@
+import type { Vec3 } from '$lib/math'
+import { structure_to_cif_str } from '$lib/structure/export'
+import { compute_adp_tensors } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { compute_xrd_pattern } from '$lib/xrd'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const wrap = (abc: Vec3): Vec3 => abc.map((val) => val - Math.floor(val)) as Vec3
+
+// Two-atom cubic cell: Na rattles ±0.1 Å along x across the cell boundary, Cl ±0.2 Å
+// along y
+function rattling_trajectory(n_frames = 20, offset: Vec3 = [0, 0, 0]): TrajectoryType {
+  const frames = Array.from({ length: n_frames }, (_, step) => {
+    const sign = step % 2 === 0 ? 1 : -1
+    const shift = offset.map((val) => (val * step) / 5) as Vec3
+    const na: Vec3 = wrap([(sign * 0.1) / 5 + shift[0], shift[1], shift[2]])
+    const cl: Vec3 = wrap([0.5 + shift[0], 0.5 + (sign * 0.2) / 5 + shift[1], 0.5 + shift[2]])
+    const structure = make_crystal(5, [
+      [`Na`, na],
+      [`Cl`, cl],
+    ])
+    return { structure, step, metadata: {} }
+  })
+  return { frames }
+}
+
+describe(`compute_adp_tensors`, () => {
+  test(`recovers anisotropic U tensors for atoms vibrating across periodic boundaries`, () => {
+    const result = compute_adp_tensors(rattling_trajectory(), { remove_drift: false })
+    expect(result.n_frames).toBe(20)
+    const [u_na, u_cl] = result.u_cart
+    expect(u_na[0][0]).toBeCloseTo(0.01, 10)
+    expect(u_na[1][1]).toBeCloseTo(0, 10)
+    expect(u_cl[1][1]).toBeCloseTo(0.04, 10)
+    expect(u_cl[0][1]).toBeCloseTo(0, 10)
+    expect(result.u_equiv[0]).toBeCloseTo(0.01 / 3, 10)
+
+    // mean positions sit on the lattice sites and carry the tensor as a site property
+    const [na_site, cl_site] = result.structure.sites
+    for (const coord of na_site.abc) expect(Math.min(coord, 1 - coord)).toBeCloseTo(0, 10)
+    expect(cl_site.xyz[1]).toBeCloseTo(2.5, 10)
+    expect(na_site.properties.u_cart).toEqual(u_na)
+    expect(result.structure.lattice.a).toBeCloseTo(5, 10)
+  })
+
+  test(`drift removal cancels a rigid translation of the whole cell contents`, () => {
+    const trajectory = rattling_trajectory(20, [0.3, -0.2, 0.1])
+    const drifting = compute_adp_tensors(trajectory, { remove_drift: false })
+    const corrected = compute_adp_tensors(trajectory)
+    expect(drifting.u_equiv[0]).toBeGreaterThan(0.1)
+    // equal-and-opposite rattles per frame only shift the centroid by their mean
+    const mean_shift = [0.1 / 2, 0.2 / 2]
+    expect(corrected.u_cart[0][0][0]).toBeCloseTo((0.1 - mean_shift[0]) ** 2, 10)
+    expect(corrected.u_cart[1][1][1]).toBeCloseTo((0.2 - mean_shift[1]) ** 2, 10)
+  })
+
+  test(`start_frame and stride select frames`, () => {
+    // stride 2 only samples even steps, i.e. atoms frozen at +displacement
+    const result = compute_adp_tensors(rattling_trajectory(), { start_frame: 4, stride: 2 })
+    expect(result.n_frames).toBe(8)
+    for (const u_cart of result.u_cart) {
+      for (const row of u_cart) for (const val of row) expect(val).toBeCloseTo(0, 12)
+    }
+  })
+
+  test(`ADPs flow into CIF export and damp XRD intensities at high angle`, () => {
+    const { structure } = compute_adp_tensors(rattling_trajectory(), { remove_drift: false })
+    const cif = structure_to_cif_str(structure)
+    expect(cif).toContain(`_atom_site_aniso_U_11`)
+    expect(cif).toMatch(/^Na0 0\.010000 0\.000000 0\.000000 0\.000000 0\.000000 0\.000000$/m)
+
+    const with_adps = compute_xrd_pattern(structure, { scaled: false, site_adps: true })
+    const static_pattern = compute_xrd_pattern(structure, { scaled: false })
+    // u_cart is ignored unless site_adps opts in
+    const sites = structure.sites.map((site) => ({ ...site, properties: {} }))
+    const without_u_cart = compute_xrd_pattern({ ...structure, sites }, { scaled: false })
+    expect(static_pattern).toEqual(without_u_cart)
+    expect(with_adps.x).toEqual(static_pattern.x)
+    const ratios = with_adps.y.map((val, idx) => val / static_pattern.y[idx])
+    for (const ratio of ratios) expect(ratio).toBeLessThanOrEqual(1 + 1e-12)
+    expect(ratios.at(-1)).toBeLessThan(ratios[0])
+  })
+
+  test.each([
+    [{ frames: [] }, {}, `Need at least 2 frames`],
+    [rattling_trajectory(), { stride: 0 }, `stride must be a positive integer`],
+    [rattling_trajectory(3), { start_frame: 2 }, `Need at least 2 frames`],
+  ])(`rejects invalid input %#`, (trajectory, options, msg) => {
+    expect(() => compute_adp_tensors(trajectory, options)).toThrow(msg)
+  })
+})
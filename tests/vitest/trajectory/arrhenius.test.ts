@@ -0,0 +1,106 @@
+import { K_B_EV } from '$lib/constants'
+import { linear_fit } from '$lib/stats'
+import { arrhenius_fit, arrhenius_predict } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+
+const temps = [400, 500, 600, 800, 1000]
+const arrhenius = (temp: number) => 1e-3 * Math.exp(-0.5 / (K_B_EV * temp))
+// ±5% scatter around the exact line
+const noisy = temps.map((temp, idx) => arrhenius(temp) * [1.05, 0.95, 1.02, 0.97, 1.04][idx])
+
+describe(`arrhenius_fit`, () => {
+  test(`recovers activation energy and prefactor of exact data`, () => {
+    const fit = arrhenius_fit(temps, temps.map(arrhenius))
+    expect(fit.activation_energy).toBeCloseTo(0.5, 10)
+    expect(fit.prefactor / 1e-3).toBeCloseTo(1, 10)
+    expect(fit.activation_energy_err).toBeCloseTo(0, 10)
+    expect(fit.r_squared).toBeCloseTo(1, 12)
+    expect(fit.n_points).toBe(5)
+    const [room] = arrhenius_predict(fit, [300])
+    expect(room.value / arrhenius(300)).toBeCloseTo(1, 8)
+    expect(room.upper / room.lower).toBeCloseTo(1, 6)
+  })
+
+  test(`conductivities as σT`, () => {
+    const sigma = temps.map((temp) => (50 / temp) * Math.exp(-0.3 / (K_B_EV * temp)))
+    const fit = arrhenius_fit(temps, sigma, { times_temperature: true })
+    expect(fit.activation_energy).toBeCloseTo(0.3, 10)
+    expect(fit.prefactor).toBeCloseTo(50, 8)
+    const [pred] = arrhenius_predict(fit, [300])
+    expect(pred.value).toBeCloseTo((50 / 300) * Math.exp(-0.3 / (K_B_EV * 300)), 12)
+  })
+
+  test(`extrapolation bands follow the line-fit covariance`, () => {
+    const fit = arrhenius_fit(temps, noisy)
+    const line = linear_fit(
+      temps.map((temp) => 1 / temp),
+      noisy.map(Math.log),
+    )
+    expect(fit.activation_energy).toBeCloseTo(-line.slope * K_B_EV, 12)
+    expect(fit.activation_energy_err).toBeCloseTo(line.slope_err * K_B_EV, 12)
+    const mean_inv_temp = temps.reduce((sum, temp) => sum + 1 / temp, 0) / temps.length
+    for (const pred of arrhenius_predict(fit, [300, 700, 1500], 3)) {
+      const inv_t = 1 / pred.temperature
+      const ln_var =
+        line.intercept_err ** 2 +
+        line.slope_err ** 2 * (inv_t * inv_t - 2 * inv_t * mean_inv_temp)
+      expect(Math.log(pred.upper / pred.value)).toBeCloseTo(3 * Math.sqrt(ln_var), 10)
+      expect(Math.log(pred.value / pred.lower)).toBeCloseTo(3 * Math.sqrt(ln_var), 10)
+    }
+    // bands are narrowest near the data and widen on extrapolation
+    const [cold, mid] = arrhenius_predict(fit, [300, 600])
+    expect(cold.upper / cold.lower).toBeGreaterThan(mid.upper / mid.lower)
+  })
+
+  test(`Student-t confidence intervals`, () => {
+    const fit = arrhenius_fit(temps, noisy)
+    // t quantile for 95% with 5 − 2 dof
+    expect(fit.t_value).toBeCloseTo(3.182446305, 8)
+    const half_width = fit.t_value * fit.activation_energy_err
+    expect(fit.activation_energy_ci[0]).toBeCloseTo(fit.activation_energy - half_width, 12)
+    expect(fit.activation_energy_ci[1]).toBeCloseTo(fit.activation_energy + half_width, 12)
+    const [a_lo, a_hi] = fit.prefactor_ci
+    expect(Math.log(a_hi / fit.prefactor)).toBeCloseTo(fit.t_value * fit.ln_prefactor_err, 12)
+    expect(Math.log(fit.prefactor / a_lo)).toBeCloseTo(fit.t_value * fit.ln_prefactor_err, 12)
+    const narrow = arrhenius_fit(temps, noisy, { confidence: 0.68 })
+    expect(narrow.t_value).toBeLessThan(fit.t_value)
+    expect(narrow.t_value).toBeGreaterThan(1)
+    const [room] = arrhenius_predict(fit, [300], fit.t_value)
+    expect(room.upper).toBeGreaterThan(arrhenius_predict(fit, [300])[0].upper)
+    const two = arrhenius_fit([400, 800], [arrhenius(400), arrhenius(800)])
+    expect([two.t_value, ...two.activation_energy_ci]).toEqual([NaN, NaN, NaN])
+    expect(() => arrhenius_fit(temps, noisy, { confidence: 1 })).toThrow(
+      `confidence must be in (0, 1)`,
+    )
+  })
+
+  test(`relative errors weight the points`, () => {
+    // equal relative errors reproduce the unweighted fit
+    const same = arrhenius_fit(temps, noisy, { errors: noisy.map((val) => 0.1 * val) })
+    const unweighted = arrhenius_fit(temps, noisy)
+    expect(same.activation_energy).toBeCloseTo(unweighted.activation_energy, 12)
+    // an outlier with a large error barely pulls the fit
+    const outlier = [...temps.map(arrhenius).slice(0, 4), 10 * arrhenius(1000)]
+    const errors = [1e-6, 1e-6, 1e-6, 1e-6, 1e3].map((rel, idx) => rel * outlier[idx])
+    expect(arrhenius_fit(temps, outlier, { errors }).activation_energy).toBeCloseTo(0.5, 6)
+    // two points fit exactly with undefined errors
+    const two = arrhenius_fit([400, 800], [arrhenius(400), arrhenius(800)])
+    expect(two.activation_energy).toBeCloseTo(0.5, 10)
+    expect(arrhenius_predict(two, [300])[0].lower).toBeNaN()
+  })
+
+  test(`rejects invalid input`, () => {
+    expect(() => arrhenius_fit([300], [1, 2])).toThrow(`Need one value per temperature`)
+    expect(() => arrhenius_fit([300, 400], [1, 0])).toThrow(`need positive values`)
+    expect(() => arrhenius_fit([0, 400], [1, 1])).toThrow(`Temperatures must be positive`)
+    expect(() => arrhenius_fit([300, 400], [1, 1], { errors: [1] })).toThrow(
+      `errors must match`,
+    )
+    expect(() => arrhenius_fit([300, 400], [1, 1], { errors: [1, 0] })).toThrow(
+      `errors must be positive`,
+    )
+    expect(() => arrhenius_fit([300], [1])).toThrow(`at least 2 points`)
+    const fit = arrhenius_fit(temps, noisy)
+    expect(() => arrhenius_predict(fit, [-10])).toThrow(`Temperatures must be positive`)
+  })
+})
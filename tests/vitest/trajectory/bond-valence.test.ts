@@ -0,0 +1,65 @@
+import { Select } from '$lib/structure'
+import type { TrajectoryType } from '$lib/trajectory'
+import { bv_sum_trajectory } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// Fe–O bond lengths that give a BVS of exactly 2 or 3 with 6 bonds, R0 = 1.75 Å
+const [r0, b] = [1.75, 0.37]
+const bond_for = (bvs: number) => r0 - b * Math.log(bvs / 6)
+const target_bvs = [2, 2, 3, 3, 2]
+const trajectory: TrajectoryType = {
+  frames: target_bvs.map((bvs, frame) => ({
+    structure: make_crystal(2 * bond_for(bvs), [
+      [`Fe`, [0, 0, 0]],
+      [`O`, [0.5, 0, 0]],
+      [`O`, [0, 0.5, 0]],
+      [`O`, [0, 0, 0.5]],
+    ]),
+    step: 10 * frame,
+  })),
+}
+
+describe(`bv_sum_trajectory`, () => {
+  test(`per-atom BVS series and redox events`, () => {
+    const result = bv_sum_trajectory(trajectory, {
+      r0: { 'Fe-O': r0 },
+      selection: Select.element(`Fe`),
+      oxidation_states: { Fe: [2, 3] },
+      time_step: 0.5,
+    })
+    expect(result.site_indices).toEqual([0])
+    expect(result.n_frames).toBe(5)
+    expect(result.times).toEqual([0, 5, 10, 15, 20])
+    result.bv_sums[0].forEach((bvs, frame) => expect(bvs).toBeCloseTo(target_bvs[frame], 10))
+    expect(result.mean[0]).toBeCloseTo(2.4, 10)
+    expect(result.std[0]).toBeCloseTo(Math.sqrt(0.24), 10)
+    expect(result.states).toEqual([[2, 2, 3, 3, 2]])
+    expect(result.redox_events).toEqual([
+      { site_idx: 0, frame: 2, time: 10, from: 2, to: 3 },
+      { site_idx: 0, frame: 4, time: 20, from: 3, to: 2 },
+    ])
+  })
+
+  test(`smoothing and untyped elements`, () => {
+    const result = bv_sum_trajectory(trajectory, { r0: { 'Fe-O': r0 }, smoothing: 3 })
+    expect(result.site_indices).toEqual([0, 1, 2, 3])
+    const expected = [2, 7 / 3, 8 / 3, 8 / 3, 2.5]
+    result.smoothed[0].forEach((bvs, frame) => expect(bvs).toBeCloseTo(expected[frame], 10))
+    expect(result.states[1]).toEqual([null, null, null, null, null])
+    expect(result.redox_events).toEqual([])
+  })
+
+  test(`rejects invalid options`, () => {
+    const r0_fe = { 'Fe-O': r0 }
+    expect(() => bv_sum_trajectory(trajectory, { r0: r0_fe, stride: 0 })).toThrow(
+      `stride must be`,
+    )
+    expect(() => bv_sum_trajectory(trajectory, { r0: r0_fe, smoothing: 2 })).toThrow(
+      `smoothing must be an odd positive integer`,
+    )
+    expect(() => bv_sum_trajectory(trajectory, { r0: r0_fe, start_frame: 5 })).toThrow(
+      `No frames left`,
+    )
+  })
+})
@@ -0,0 +1,116 @@
+import type { Vec3 } from '$lib/math'
+import { find_cavities, track_cavities, with_cavity_sites } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// 3x3x3 simple cubic supercell (a = 3 Å): with 1.5 Å atoms and a 0.7 Å probe only the
+// cube centers are open, the face-center windows are too narrow
+const options = { radii: { Ar: 1.5 }, probe_radius: 0.7 }
+const lattice_sites: Vec3[] = Array.from({ length: 27 }, (_, idx) => [
+  (idx % 3) / 3,
+  (Math.floor(idx / 3) % 3) / 3,
+  Math.floor(idx / 9) / 3,
+])
+const make_supercell = (vacancy?: Vec3) =>
+  make_crystal(
+    9,
+    lattice_sites
+      .filter((abc) => !vacancy || abc.some((val, axis) => val !== vacancy[axis]))
+      .map((abc): [string, Vec3] => [`Ar`, abc]),
+  )
+
+describe(`find_cavities`, () => {
+  test(`isolated voids at every cube center`, () => {
+    const cavities = find_cavities(make_supercell(), options)
+    expect(cavities).toHaveLength(27)
+    for (const { volume, percolating, center } of cavities) {
+      expect(volume).toBeCloseTo(cavities[0].volume, 10)
+      expect(percolating).toBe(false)
+      for (const coord of center) expect(coord % 3).toBeCloseTo(1.5, 6)
+    }
+  })
+
+  test(`vacancy merges the surrounding cube centers into one void`, () => {
+    const cavities = find_cavities(make_supercell([1 / 3, 1 / 3, 1 / 3]), options)
+    expect(cavities).toHaveLength(20)
+    const [largest, ...rest] = cavities
+    expect(largest.volume).toBeGreaterThan(8 * rest[0].volume)
+    largest.center.forEach((coord) => expect(coord).toBeCloseTo(3, 6))
+    const filtered = find_cavities(make_supercell([1 / 3, 1 / 3, 1 / 3]), {
+      ...options,
+      min_volume: 1,
+    })
+    expect(filtered).toEqual([largest])
+  })
+
+  test(`open channels are flagged as percolating`, () => {
+    const crystal = make_crystal(3, [[`Ar`, [0, 0, 0]]])
+    const cavities = find_cavities(crystal, { radii: { Ar: 1 }, probe_radius: 0.5 })
+    expect(cavities).toHaveLength(1)
+    expect(cavities[0].percolating).toBe(true)
+  })
+
+  test(`fully blocked cell has no cavities`, () => {
+    const crystal = make_crystal(3, [[`Ar`, [0, 0, 0]]])
+    expect(find_cavities(crystal, { radii: { Ar: 2.2 }, probe_radius: 0.5 })).toEqual([])
+  })
+
+  test(`rejects non-positive resolution`, () => {
+    expect(() => find_cavities(make_supercell(), { resolution: 0 })).toThrow(/resolution/)
+  })
+})
+
+test(`with_cavity_sites appends placeholder sites at cavity centers`, () => {
+  const structure = make_supercell([1 / 3, 1 / 3, 1 / 3])
+  const cavities = find_cavities(structure, { ...options, min_volume: 1 })
+  const decorated = with_cavity_sites(structure, cavities, `Xe`)
+  expect(decorated.sites).toHaveLength(27)
+  const site = decorated.sites[26]
+  expect(site.species[0].element).toBe(`Xe`)
+  expect(site.label).toBe(`cavity1`)
+  expect(site.properties.cavity_volume).toBe(cavities[0].volume)
+  site.abc.forEach((coord) => expect(coord).toBeCloseTo(1 / 3, 6))
+})
+
+describe(`track_cavities`, () => {
+  // vacancy sits still for two frames, then hops one lattice spacing along a
+  const vacancies: Vec3[] = [
+    [1 / 3, 1 / 3, 1 / 3],
+    [1 / 3, 1 / 3, 1 / 3],
+    [2 / 3, 1 / 3, 1 / 3],
+  ]
+  const trajectory: TrajectoryType = {
+    frames: vacancies.map((vacancy, step) => ({
+      structure: make_supercell(vacancy),
+      step,
+      metadata: {},
+    })),
+  }
+
+  test(`vacancy void closes after the hop and reopens at the new site`, () => {
+    const tracking = track_cavities(trajectory, { ...options, time_step: 2 })
+    const { times, cavities, tracks } = tracking
+    expect(times).toEqual([0, 2, 4])
+    expect(cavities.map((found) => found.length)).toEqual([20, 20, 20])
+    expect(tracks).toHaveLength(25)
+    const [vacancy_track] = tracks
+    expect(vacancy_track.frames).toEqual([0, 1])
+    expect(vacancy_track.lifetime).toBe(2)
+    expect(vacancy_track.closed).toBe(true)
+    const hopped = tracks.find(({ frames, volumes }) => frames[0] === 2 && volumes[0] > 5)
+    expect(hopped?.centers[0][0]).toBeCloseTo(6, 6)
+    // cube centers away from both vacancies persist through all frames
+    const persistent = tracks.filter(({ frames }) => frames.length === 3)
+    expect(persistent).toHaveLength(15)
+    for (const track of persistent) {
+      expect(track.lifetime).toBe(4)
+      expect(track.closed).toBe(false)
+    }
+    expect(tracks.filter(({ closed }) => closed)).toHaveLength(5)
+  })
+
+  test(`rejects invalid stride`, () => {
+    expect(() => track_cavities(trajectory, { stride: 0 })).toThrow(/stride/)
+  })
+})
@@ -0,0 +1,107 @@
+import { K_B_EV } from '$lib/constants'
+import type { Molecule } from '$lib/structure'
+import { set_site_field } from '$lib/structure'
+import {
+  coordination_cv,
+  distance_cv,
+  free_energy_profile,
+  project_trajectory,
+  site_field_cv,
+} from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// two atoms x Å apart along a in a 10 Å cubic cell
+const dimer = (x_pos: number) =>
+  make_crystal(10, [
+    [`Ar`, [0.1, 0, 0]],
+    [`Ar`, [0.1 + x_pos / 10, 0, 0]],
+  ])
+
+describe(`collective variables`, () => {
+  test(`distance_cv uses the minimum image in periodic cells`, () => {
+    expect(distance_cv(0, 1)(dimer(3))).toBeCloseTo(3, 12)
+    expect(distance_cv(0, 1)(dimer(8))).toBeCloseTo(2, 12)
+    const molecule: Molecule = { sites: dimer(8).sites }
+    expect(distance_cv(0, 1)(molecule)).toBeCloseTo(8, 12)
+  })
+
+  test(`coordination_cv averages smooth coordination numbers`, () => {
+    // cosine switch at half the cutoff: f = 0.5 for each atom
+    const cv = coordination_cv(undefined, { r_cut: 2 })
+    expect(cv(dimer(1))).toBeCloseTo(0.5, 12)
+    expect(coordination_cv([0], { r_cut: 2 })(dimer(3))).toBe(0)
+  })
+
+  test(`site_field_cv averages a per-atom field over selected sites`, () => {
+    const structure = set_site_field(dimer(3), `q6`, [0.2, 0.6])
+    expect(site_field_cv(`q6`)(structure)).toBeCloseTo(0.4, 12)
+    expect(site_field_cv(`q6`, [1])(structure)).toBeCloseTo(0.6, 12)
+  })
+
+  test(`out-of-range site indices throw`, () => {
+    expect(() => distance_cv(0, 2)(dimer(3))).toThrow(/Site index 2 out of range/)
+    expect(() => site_field_cv(`q6`, [-1])(dimer(3))).toThrow(/out of range/)
+  })
+})
+
+describe(`project_trajectory`, () => {
+  const trajectory: TrajectoryType = {
+    frames: [1, 2, 3, 4, 5].map((x_pos, step) => ({
+      structure: dimer(x_pos),
+      step: 10 * step,
+      metadata: {},
+    })),
+  }
+
+  test(`evaluates every CV on the sampled frames`, () => {
+    const cvs = { dist: distance_cv(0, 1), cn: coordination_cv(undefined, { r_cut: 3 }) }
+    const { times, values } = project_trajectory(trajectory, cvs, {
+      start_frame: 1,
+      stride: 2,
+      time_step: 0.5,
+    })
+    expect(times).toEqual([5, 15])
+    expect(values.dist.map((val) => Number(val.toFixed(10)))).toEqual([2, 4])
+    expect(values.cn[0]).toBeCloseTo(0.25, 12)
+    expect(values.cn[1]).toBe(0)
+  })
+
+  test(`rejects invalid stride`, () => {
+    expect(() => project_trajectory(trajectory, {}, { stride: 0 })).toThrow(/stride/)
+  })
+})
+
+describe(`free_energy_profile`, () => {
+  test(`Boltzmann inversion of the sample histogram`, () => {
+    const samples = [0.1, 0.2, 0.3, 1.7, 2.5]
+    const { centers, counts, free_energy, probability } = free_energy_profile(samples, {
+      n_bins: 3,
+      range: [0, 3],
+      temperature: 500,
+    })
+    expect(centers).toEqual([0.5, 1.5, 2.5])
+    expect(counts).toEqual([3, 1, 1])
+    expect(probability.reduce((sum, val) => sum + val, 0)).toBeCloseTo(1, 12)
+    expect(free_energy[0]).toBe(0)
+    expect(free_energy[1]).toBeCloseTo(K_B_EV * 500 * Math.log(3), 12)
+  })
+
+  test(`empty bins get infinite free energy and weights reweight samples`, () => {
+    const samples = [0.1, 0.2, 2.9]
+    const profile = free_energy_profile(samples, { n_bins: 3, weights: [1, 1, 2] })
+    expect(profile.free_energy[1]).toBe(Infinity)
+    expect(profile.free_energy[2]).toBeCloseTo(0, 12)
+    expect(profile.counts).toEqual([2, 0, 1])
+  })
+
+  test.each([
+    [[], {}, /finite sample/],
+    [[1, 2], { weights: [1] }, /Got 1 weights for 2 samples/],
+    [[1, 2], { n_bins: 0 }, /n_bins/],
+    [[1, 2], { range: [5, 6] as [number, number] }, /No weighted samples/],
+  ])(`rejects invalid input %#`, (samples, options, message) => {
+    expect(() => free_energy_profile(samples, options)).toThrow(message)
+  })
+})
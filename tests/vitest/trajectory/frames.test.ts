@@ -0,0 +1,135 @@
+import type { Vec3 } from '$lib/math'
+import { set_site_field, site_field_values } from '$lib/structure'
+import { structure_to_xyz_str } from '$lib/structure/export'
+import type { TrajectoryType } from '$lib/trajectory'
+import {
+  compute_msd,
+  concat_trajectories,
+  filter_trajectory_species,
+  frame_property,
+  frame_property_keys,
+  frame_stresses,
+  slice_trajectory,
+  unwrap_positions,
+  unwrap_trajectory,
+} from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const stress = [
+  [1, 0, 0],
+  [0, 2, 0],
+  [0, 0, 3],
+]
+// Li hops +1 Å along x per frame in a 4 Å cell, wrapping back to 0 after frame 3. O stays.
+const trajectory: TrajectoryType = {
+  frames: Array.from({ length: 6 }, (_, frame) => ({
+    structure: make_crystal(4, [
+      { element: `Li`, xyz: [(0.5 + frame) % 4, 0, 0] as Vec3 },
+      { element: `O`, abc: [0, 0.5, 0.5] },
+    ]),
+    step: 10 * frame,
+    metadata: {
+      energy: -10 - frame,
+      forces: [
+        [frame, 0, 0],
+        [0, 0, 0],
+      ],
+      ...(frame % 2 === 0 && { temperature: 300 + frame, stress }),
+    },
+  })),
+  metadata: { source_format: `test` },
+}
+
+describe(`slice_trajectory and concat_trajectories`, () => {
+  test(`slices with stride and negative indices and concatenates in order`, () => {
+    const sliced = slice_trajectory(trajectory, 1, -1, 2)
+    expect(sliced.frames.map(({ step }) => step)).toEqual([10, 30])
+    expect(sliced.metadata).toEqual({ source_format: `test` })
+    expect(slice_trajectory(trajectory).frames).toHaveLength(6)
+    expect(() => slice_trajectory(trajectory, 0, undefined, 0)).toThrow(`stride`)
+
+    const joined = concat_trajectories([sliced, slice_trajectory(trajectory, 4)])
+    expect(joined.frames.map(({ step }) => step)).toEqual([10, 30, 40, 50])
+    const swapped = make_crystal(4, [
+      [`O`, [0, 0, 0]],
+      [`Li`, [0, 0, 0]],
+    ])
+    const other = { frames: [{ ...trajectory.frames[0], structure: swapped }] }
+    expect(() => concat_trajectories([trajectory, other])).toThrow(`different sites`)
+    expect(() => concat_trajectories([])).toThrow(`without frames`)
+  })
+})
+
+describe(`filter_trajectory_species`, () => {
+  test(`keeps sites of the given elements and their per-site metadata`, () => {
+    const li_only = filter_trajectory_species(trajectory, [`Li`])
+    expect(li_only.frames).toHaveLength(6)
+    expect(li_only.frames[2].structure.sites.map(({ label }) => label)).toEqual([`Li0`])
+    expect(li_only.frames[2].metadata?.forces).toEqual([[2, 0, 0]])
+    expect(li_only.frames[2].metadata?.energy).toBe(-12)
+    expect(li_only.frames[2].metadata?.stress).toEqual(stress)
+    expect(filter_trajectory_species(trajectory, [`Na`]).frames[0].structure.sites).toEqual([])
+  })
+
+  test(`filters site fields so filtered frames still export`, () => {
+    const with_charges: TrajectoryType = {
+      frames: trajectory.frames.map((frame) => ({
+        ...frame,
+        structure: set_site_field(frame.structure, `charge`, [0.9, -1.8]),
+      })),
+    }
+    const [frame] = filter_trajectory_species(with_charges, [`O`]).frames
+    expect(site_field_values(frame.structure, `charge`)).toEqual([-1.8])
+    expect(structure_to_xyz_str(frame.structure).split(`\n`)[2]).toMatch(/^O .* -1\.80000000$/)
+  })
+})
+
+describe(`frame properties`, () => {
+  test(`per-frame scalar series and stress tensors with null for missing values`, () => {
+    expect(frame_property_keys(trajectory)).toEqual([`energy`, `temperature`])
+    expect(frame_property(trajectory, `energy`)).toEqual([-10, -11, -12, -13, -14, -15])
+    const temperatures = frame_property(trajectory, `temperature`)
+    expect(temperatures).toEqual([300, null, 302, null, 304, null])
+    expect(frame_stresses(trajectory)).toEqual([stress, null, stress, null, stress, null])
+  })
+})
+
+describe(`unwrap_trajectory`, () => {
+  test(`removes jumps across the cell boundary without changing the input`, () => {
+    const unwrapped = unwrap_trajectory(trajectory)
+    const li_x = unwrapped.frames.map(({ structure }) => structure.sites[0].xyz[0])
+    li_x.forEach((x_pos, frame) => expect(x_pos).toBeCloseTo(0.5 + frame, 12))
+    expect(unwrapped.frames[5].structure.sites[0].abc[0]).toBeCloseTo(5.5 / 4, 12)
+    expect(unwrapped.frames[5].structure.sites[1].xyz).toEqual([0, 2, 2])
+    expect(unwrapped.frames[5].metadata?.energy).toBe(-15)
+    expect(trajectory.frames[5].structure.sites[0].xyz[0]).toBeCloseTo(1.5, 12)
+    // MSD unwraps internally, so it agrees on wrapped and unwrapped input
+    const options = { remove_drift: false, max_lag: 5 }
+    const wrapped_msd = compute_msd(trajectory, options).groups.Li.msd.x
+    compute_msd(unwrapped, options).groups.Li.msd.x.forEach((msd, lag) =>
+      expect(msd).toBeCloseTo(wrapped_msd[lag], 10),
+    )
+  })
+})
+
+describe(`unwrap_positions`, () => {
+  test(`follows atoms through a growing cell without rescaling their displacements`, () => {
+    // NPT-like expansion from 4 to 5 Å: one atom rests at (1, 1, 1), the other moves
+    // +0.5 Å per frame along x and wraps to the origin of the 4.5 Å cell in frame 2
+    const structures = [3.5, 4, 0, 0.5, 1].map((x_pos, frame) =>
+      make_crystal(4 + 0.25 * frame, [
+        { element: `Li`, xyz: [1, 1, 1] as Vec3 },
+        { element: `Li`, xyz: [x_pos, 2, 2] as Vec3 },
+      ]),
+    )
+    const positions = unwrap_positions(structures)
+    positions.forEach(([resting, moving], frame) => {
+      resting.forEach((coord) => expect(coord).toBeCloseTo(1, 12))
+      expect(moving[0]).toBeCloseTo(3.5 + 0.5 * frame, 12)
+    })
+    const molecule = { sites: structures[0].sites }
+    expect(unwrap_positions([molecule, molecule])[1]).toEqual(positions[0])
+    expect(unwrap_positions([])).toEqual([])
+  })
+})
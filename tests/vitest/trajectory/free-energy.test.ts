@@ -0,0 +1,94 @@
+import { K_B_EV } from '$lib/constants'
+import type { BiasedWindow } from '$lib/trajectory'
+import { free_energy_profile, harmonic_bias, mbar, wham } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+
+const temperature = 300
+const k_t = K_B_EV * temperature
+// underlying free energy on s ∈ [0, 1] with a 0.2 eV peak-to-peak modulation
+const true_free_energy = (cv: number) => 0.1 * Math.sin(2 * Math.PI * cv)
+
+// deterministic "samples" of the biased density: its quantiles at (j + 0.5) / n_samples
+function biased_quantiles(bias: (cv: number) => number, n_samples = 500): number[] {
+  const n_grid = 4000
+  const grid = Array.from({ length: n_grid }, (_, idx) => (idx + 0.5) / n_grid)
+  const density = grid.map((cv) => Math.exp(-(true_free_energy(cv) + bias(cv)) / k_t))
+  const total = density.reduce((sum, val) => sum + val, 0)
+  const samples: number[] = []
+  let [cdf, grid_idx] = [density[0] / total, 0]
+  for (let idx = 0; idx < n_samples; idx++) {
+    const quantile = (idx + 0.5) / n_samples
+    while (cdf < quantile) cdf += density[++grid_idx] / total
+    samples.push(grid[grid_idx])
+  }
+  return samples
+}
+
+const windows: BiasedWindow[] = Array.from({ length: 9 }, (_, idx) => {
+  const bias = harmonic_bias(idx / 8, 2)
+  return { samples: biased_quantiles(bias), bias }
+})
+const options = { n_bins: 20, range: [0, 1] as [number, number], temperature }
+
+const max_error = (centers: number[], free_energy: number[]) => {
+  const reference = centers.map(true_free_energy)
+  const ref_min = Math.min(...reference)
+  return Math.max(
+    ...free_energy.map((val, bin) => Math.abs(val - (reference[bin] - ref_min))),
+  )
+}
+
+test(`harmonic_bias is a quadratic restraint`, () => {
+  expect(harmonic_bias(1, 4)(1.5)).toBeCloseTo(0.5, 12)
+  expect(harmonic_bias(1, 4)(1)).toBe(0)
+})
+
+describe.each([
+  [`wham`, wham],
+  [`mbar`, mbar],
+])(`%s`, (_name, estimator) => {
+  test(`recovers the unbiased profile from umbrella windows`, () => {
+    const result = estimator(windows, options)
+    expect(result.converged).toBe(true)
+    expect(result.centers).toHaveLength(20)
+    expect(Math.min(...result.free_energy)).toBe(0)
+    expect(max_error(result.centers, result.free_energy)).toBeLessThan(0.01)
+    expect(result.window_free_energies[0]).toBe(0)
+    expect(result.uncertainty).toBeNull()
+  })
+
+  test(`single unbiased window reduces to Boltzmann inversion`, () => {
+    const samples = biased_quantiles(() => 0, 300)
+    const result = estimator([{ samples, bias: () => 0 }], options)
+    const reference = free_energy_profile(samples, options)
+    result.free_energy.forEach((val, bin) => {
+      expect(val).toBeCloseTo(reference.free_energy[bin], 10)
+    })
+    expect(result.counts).toEqual(reference.counts)
+  })
+
+  test(`bootstrap uncertainties are reproducible for a seed`, () => {
+    const boot = { ...options, n_bootstrap: 5, seed: 7 }
+    const { uncertainty } = estimator(windows.slice(3, 6), boot)
+    expect(uncertainty).toHaveLength(20)
+    expect(estimator(windows.slice(3, 6), boot).uncertainty).toEqual(uncertainty)
+    const finite = (uncertainty ?? []).filter(Number.isFinite)
+    expect(finite.length).toBeGreaterThan(0)
+    for (const val of finite) expect(val).toBeGreaterThanOrEqual(0)
+  })
+
+  test.each([
+    [[], {}, /at least one biased window/],
+    [[{ samples: [], bias: () => 0 }], {}, /Window 0 has no samples/],
+    [[{ samples: [NaN], bias: () => 0 }], {}, /finite sample/],
+    [windows, { n_bins: 0 }, /n_bins/],
+  ])(`rejects invalid input %#`, (wins, opts, message) => {
+    expect(() => estimator(wins, opts)).toThrow(message)
+  })
+})
+
+test(`wham and mbar agree on window free energies`, () => {
+  const from_wham = wham(windows, options).window_free_energies
+  const from_mbar = mbar(windows, options).window_free_energies
+  from_wham.forEach((val, idx) => expect(val).toBeCloseTo(from_mbar[idx], 2))
+})
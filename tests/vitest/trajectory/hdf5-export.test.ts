@@ -0,0 +1,93 @@
+import type { Vec3 } from '$lib/math'
+import type { TrajectoryType } from '$lib/trajectory'
+import {
+  parse_trajectory_data,
+  read_hdf5_frame,
+  trajectory_to_hdf5,
+} from '$lib/trajectory/parse'
+import { describe, expect, test } from 'vitest'
+import { make_crystal, make_trajectory_frame } from '../setup'
+
+// sheared cell that grows by 1% per frame, with Fe moving along x
+const frames = [0, 1, 2].map((frame_idx) => {
+  const scale = 1 + frame_idx / 100
+  const structure = make_crystal(
+    [
+      [4 * scale, 0, 0],
+      [1, 4 * scale, 0],
+      [0.5, 0.3, 5 * scale],
+    ],
+    [
+      [`Fe`, [0.1 * frame_idx, 0, 0]],
+      [`O`, [0.5, 0.5, 0.5]],
+    ],
+  )
+  const metadata = { energy: -10 - frame_idx, temperature: 300 + frame_idx, label: `md` }
+  return { structure, step: 10 * frame_idx, metadata }
+})
+const trajectory: TrajectoryType = { frames }
+
+describe(`trajectory_to_hdf5`, () => {
+  test(`round-trips positions, cells, species, steps and frame scalars`, async () => {
+    const buffer = await trajectory_to_hdf5(trajectory)
+    const parsed = await parse_trajectory_data(buffer, `md.h5`)
+    expect(parsed.metadata?.source_format).toBe(`hdf5_trajectory`)
+    expect(parsed.frames.map(({ step }) => step)).toEqual([0, 10, 20])
+    parsed.frames.forEach(({ structure, metadata }, frame_idx) => {
+      const original = frames[frame_idx]
+      if (!(`lattice` in structure)) throw new Error(`expected a periodic frame`)
+      expect(structure.lattice.matrix).toEqual(original.structure.lattice.matrix)
+      expect(structure.sites.map(({ species }) => species[0].element)).toEqual([`Fe`, `O`])
+      structure.sites.forEach(({ xyz }, idx) =>
+        xyz.forEach((val, dim) =>
+          expect(val).toBeCloseTo(original.structure.sites[idx].xyz[dim], 12),
+        ),
+      )
+      expect(metadata?.energy).toBe(original.metadata.energy)
+      expect(metadata?.temperature).toBe(original.metadata.temperature)
+      expect(metadata?.label).toBeUndefined() // only numeric metadata is written
+    })
+  })
+
+  test(`reads single frames by slicing chunked datasets`, async () => {
+    for (const compression_level of [0, 9]) {
+      const buffer = await trajectory_to_hdf5(trajectory, { compression_level })
+      const frame = await read_hdf5_frame(buffer, 2, `md.h5`)
+      expect(frame.step).toBe(20)
+      expect(frame.metadata).toMatchObject({ energy: -12, temperature: 302 })
+      if (!(`lattice` in frame.structure)) throw new Error(`expected a periodic frame`)
+      expect(frame.structure.lattice.matrix).toEqual(frames[2].structure.lattice.matrix)
+      const fe_x = frames[2].structure.sites[0].xyz[0]
+      expect(frame.structure.sites[0].xyz[0]).toBeCloseTo(fe_x, 12)
+      await expect(read_hdf5_frame(buffer, 3)).rejects.toThrow(`out of range for 3 frames`)
+    }
+  })
+
+  test(`writes molecules without a cell and per-frame species when they change`, async () => {
+    const molecules = [0, 1].map((step) => make_trajectory_frame(step, 2, { energy: -step }))
+    molecules[1].structure.sites[1].species = [{ element: `He`, occu: 1, oxidation_state: 0 }]
+    const buffer = await trajectory_to_hdf5({ frames: molecules })
+    const parsed = await parse_trajectory_data(buffer, `mc.h5`)
+    expect(parsed.metadata?.has_cell_info).toBe(false)
+    expect(`lattice` in parsed.frames[0].structure).toBe(false)
+    const elements = parsed.frames.map(({ structure }) =>
+      structure.sites.map(({ species }) => species[0].element),
+    )
+    expect(elements).toEqual([
+      [`H`, `H`],
+      [`H`, `He`],
+    ])
+    const frame = await read_hdf5_frame(buffer, 1)
+    expect(frame.structure.sites[1].species[0].element).toBe(`He`)
+    expect(frame.structure.sites[1].xyz).toEqual([1, 0, 0] as Vec3)
+  })
+
+  test(`rejects empty trajectories, changing atom counts and bad compression`, async () => {
+    await expect(trajectory_to_hdf5({ frames: [] })).rejects.toThrow(`empty trajectory`)
+    const uneven = { frames: [make_trajectory_frame(0, 2), make_trajectory_frame(1, 3)] }
+    await expect(trajectory_to_hdf5(uneven)).rejects.toThrow(`frame 1 has 3`)
+    await expect(trajectory_to_hdf5(trajectory, { compression_level: 10 })).rejects.toThrow(
+      `compression_level must be an integer in 0-9`,
+    )
+  })
+})
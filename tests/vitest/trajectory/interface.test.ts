@@ -0,0 +1,112 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import { find_interfaces, track_interfaces } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const lattice: Matrix3x3 = [
+  [4, 0, 0],
+  [0, 4, 0],
+  [0, 0, 20],
+]
+// 40 layers of 4 atoms, 0.5 Å apart starting at z = 0.25 Å
+const layered = make_crystal(
+  lattice,
+  Array.from({ length: 160 }, (_, idx): [string, Vec3] => [
+    `Ar`,
+    [0.5 * (idx % 2), 0.5 * (Math.floor(idx / 2) % 2), (Math.floor(idx / 4) + 0.5) / 40],
+  ]),
+)
+// atoms between lower and upper (Å) count as crystalline
+const slab_classifier = (lower: number, upper: number) => (structure: Crystal) =>
+  structure.sites.map(({ xyz }) => xyz[2] > lower && xyz[2] < upper)
+const bins = { n_bins: 20, smoothing: 1 }
+
+describe(`find_interfaces`, () => {
+  test(`two interfaces bounding a crystalline slab`, () => {
+    const result = find_interfaces(layered, { classify: slab_classifier(5, 15), ...bins })
+    expect(result.period).toBeCloseTo(20, 12)
+    expect(result.n_solid).toBe(80)
+    expect(result.solid_fraction.slice(3, 7)).toEqual([0, 0, 1, 1])
+    expect(result.interfaces).toHaveLength(2)
+    expect(result.interfaces[0].position).toBeCloseTo(5, 12)
+    expect(result.interfaces[0].direction).toBe(-1)
+    expect(result.interfaces[1].position).toBeCloseTo(15, 12)
+    expect(result.interfaces[1].direction).toBe(1)
+  })
+
+  test(`slab crossing the cell boundary and partially solid bins`, () => {
+    const wrapped = (structure: Crystal) =>
+      structure.sites.map(({ xyz }) => xyz[2] < 4.5 || xyz[2] > 16)
+    const { interfaces } = find_interfaces(layered, { classify: wrapped, ...bins })
+    expect(interfaces.map(({ position }) => position)).toEqual([4.5, 16])
+    expect(interfaces.map(({ direction }) => direction)).toEqual([1, -1])
+  })
+
+  test(`template matching classifies a perfect crystal as solid`, () => {
+    const simple_cubic = make_crystal(
+      3,
+      Array.from({ length: 27 }, (_, idx): [string, Vec3] => [
+        `Po`,
+        [idx % 3, Math.floor(idx / 3) % 3, Math.floor(idx / 9)].map((val) => val / 3) as Vec3,
+      ]),
+    )
+    const reference: Vec3[] = [
+      [1, 0, 0],
+      [-1, 0, 0],
+      [0, 1, 0],
+      [0, -1, 0],
+      [0, 0, 1],
+      [0, 0, -1],
+    ]
+    const result = find_interfaces(simple_cubic, {
+      classify: { cutoff: 1.2, reference },
+      n_bins: 3,
+    })
+    expect(result.n_solid).toBe(27)
+    expect(result.interfaces).toEqual([])
+  })
+
+  test(`classifier label count must match the sites`, () => {
+    expect(() => find_interfaces(layered, { classify: () => [true] })).toThrow(
+      `Classifier returned 1 labels for 160 sites`,
+    )
+  })
+})
+
+describe(`track_interfaces`, () => {
+  // crystal growing by 0.5 Å per MD step into the liquid on both sides
+  const trajectory: TrajectoryType = {
+    frames: [0, 1, 2, 3, 4].map((step) => ({
+      structure: {
+        ...layered,
+        properties: { solid_bounds: [5 - 0.5 * step, 15 + 0.5 * step] },
+      },
+      step,
+      metadata: {},
+    })),
+  }
+  const classify = (structure: Crystal) => {
+    const [lower, upper] = structure.properties?.solid_bounds as [number, number]
+    return slab_classifier(lower, upper)(structure)
+  }
+
+  test(`growth velocities from both interfaces`, () => {
+    const result = track_interfaces(trajectory, { classify, ...bins, time_step: 2 })
+    expect(result.times).toEqual([0, 2, 4, 6, 8])
+    expect(result.tracks).toHaveLength(2)
+    const [lower, upper] = result.tracks
+    expect(lower.positions.map((pos) => Number(pos.toFixed(10)))).toEqual([5, 4.5, 4, 3.5, 3])
+    expect(lower.velocity).toBeCloseTo(-0.25, 10)
+    expect(lower.velocity_err).toBeCloseTo(0, 8)
+    expect(upper.velocity).toBeCloseTo(0.25, 10)
+    expect(lower.growth_velocity).toBeCloseTo(0.25, 10)
+    expect(result.growth_velocity).toBeCloseTo(0.25, 10)
+    const robust = track_interfaces(trajectory, { classify, ...bins, robust: true })
+    expect(robust.tracks[1].velocity).toBeCloseTo(0.5, 10) // Å per step
+    expect(() => track_interfaces(trajectory, { classify, stride: 0 })).toThrow(
+      `stride must be a positive integer`,
+    )
+  })
+})
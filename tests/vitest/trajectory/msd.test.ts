@@ -0,0 +1,103 @@
+import type { Vec3 } from '$lib/math'
+import { Select } from '$lib/structure'
+import { compute_msd, unwrap_trajectory } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const wrap = (abc: Vec3): Vec3 => abc.map((val) => val - Math.floor(val)) as Vec3
+
+// 16 Li walk along x and 16 Na along z with all 2⁴ sequences of ±0.5 Å steps, so the
+// MSD over atoms is exactly 0.25 Å² per frame of lag from every time origin. Two O stay.
+const n_steps = 4
+const walk = (walker: number, frame: number) =>
+  Array.from({ length: frame }, (_, step) => ((walker >> step) & 1 ? 0.5 : -0.5)).reduce(
+    (sum, val) => sum + val,
+    0,
+  )
+const walkers: TrajectoryType = {
+  frames: Array.from({ length: n_steps + 1 }, (_, frame) => ({
+    structure: make_crystal(20, [
+      ...Array.from({ length: 16 }, (_, idx) => ({
+        element: `Li` as const,
+        abc: wrap([0.95 + walk(idx, frame) / 20, 0.5, 0.5]),
+      })),
+      ...Array.from({ length: 16 }, (_, idx) => ({
+        element: `Na` as const,
+        abc: wrap([0.5, 0.5, 0.02 + walk(idx, frame) / 20]),
+      })),
+      { element: `O`, abc: [0, 0, 0.5] },
+      { element: `O`, abc: [0.5, 0, 0] },
+    ]),
+    step: 10 * frame,
+  })),
+}
+
+describe(`compute_msd`, () => {
+  test(`per-element, per-selection and directional MSDs in one pass`, () => {
+    const result = compute_msd(walkers, {
+      max_lag: 4,
+      time_step: 0.5,
+      selections: { mobile: Select.element(`O`).not(), first_li: [0] },
+    })
+    expect(result.lag_times).toEqual([0, 5, 10, 15, 20])
+    expect(result.n_frames).toBe(5)
+    expect(Object.keys(result.groups)).toEqual([`all`, `Li`, `Na`, `O`, `mobile`, `first_li`])
+    const { Li, Na, O, all, mobile } = result.groups
+    expect([Li.n_atoms, Na.n_atoms, O.n_atoms, all.n_atoms, mobile.n_atoms]).toEqual([
+      16, 16, 2, 34, 32,
+    ])
+    for (let lag = 0; lag <= 4; lag++) {
+      // walks cross the periodic boundaries but get unwrapped
+      expect(Li.msd.x[lag]).toBeCloseTo(0.25 * lag, 10)
+      expect(Li.msd.ab[lag]).toBeCloseTo(0.25 * lag, 10)
+      expect(Li.msd.c[lag]).toBeCloseTo(0, 10)
+      expect(Na.msd.z[lag]).toBeCloseTo(0.25 * lag, 10)
+      expect(Na.msd.c[lag]).toBeCloseTo(0.25 * lag, 10)
+      expect(Na.msd.ab[lag]).toBeCloseTo(0, 10)
+      expect(O.msd.total[lag]).toBeCloseTo(0, 10)
+      expect(mobile.msd.total[lag]).toBeCloseTo(0.25 * lag, 10)
+      expect(all.msd.total[lag]).toBeCloseTo((0.25 * lag * 32) / 34, 10)
+    }
+    // MSD = 2 d D t with 0.25 Å² per 5 time units
+    expect(Li.diffusion.x).toBeCloseTo(0.025, 10)
+    expect(Li.diffusion.ab).toBeCloseTo(0.0125, 10)
+    expect(Li.diffusion.total).toBeCloseTo(0.05 / 6, 10)
+    expect(Li.diffusion.y).toBeCloseTo(0, 10)
+    expect(Na.diffusion.c).toBeCloseTo(0.025, 10)
+    expect(Li.diffusion_err.x).toBeLessThan(1e-6)
+  })
+
+  test(`drift removal cancels a uniform translation`, () => {
+    const drifting: TrajectoryType = {
+      frames: [0, 1, 2, 3, 4].map((frame) => ({
+        structure: make_crystal(4, [
+          { element: `Cu`, abc: wrap([0, 0.1 + 0.25 * frame, 0]) },
+          { element: `Cu`, abc: wrap([0.5, 0.6 + 0.25 * frame, 0.5]) },
+        ]),
+        step: frame,
+      })),
+    }
+    const raw = compute_msd(drifting, { remove_drift: false, max_lag: 4 }).groups.Cu
+    expect(raw.msd.y.map((val) => Math.round(val * 1e8) / 1e8)).toEqual([0, 1, 4, 9, 16])
+    const fixed = compute_msd(drifting, { max_lag: 4 }).groups.Cu
+    for (const val of fixed.msd.total) expect(val).toBeCloseTo(0, 10)
+    // unwrap: false trusts the coordinates, so only pre-unwrapped ones give the same MSD
+    const options = { remove_drift: false, max_lag: 4, unwrap: false }
+    const pre_unwrapped = compute_msd(unwrap_trajectory(drifting), options).groups.Cu
+    pre_unwrapped.msd.y.forEach((val, lag) => expect(val).toBeCloseTo(lag ** 2, 10))
+    expect(compute_msd(drifting, options).groups.Cu.msd.y[4]).toBeCloseTo(0, 10)
+  })
+
+  test(`rejects invalid options`, () => {
+    expect(() => compute_msd(walkers, { stride: 0 })).toThrow(`stride must be`)
+    expect(() => compute_msd(walkers, { start_frame: 4 })).toThrow(`at least 2 frames`)
+    expect(() => compute_msd(walkers, { max_lag: 5 })).toThrow(`max_lag must be`)
+    expect(() => compute_msd(walkers, { fit_range: [0.5, 0.5] })).toThrow(`fit_range`)
+    expect(() => compute_msd(walkers, { selections: { Li: [0] } })).toThrow(`clashes`)
+    const shrunk = { ...walkers.frames[1], structure: { sites: [] } }
+    expect(() => compute_msd({ frames: [walkers.frames[0], shrunk] })).toThrow(
+      `same number of sites`,
+    )
+  })
+})
@@ -0,0 +1,107 @@
+import type { Vec3 } from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import {
+  largest_cluster_series,
+  largest_solid_cluster,
+  nucleation_mfpt,
+} from '$lib/trajectory'
+import type { ClusterSeries, TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// 4×4×4 simple cubic grid with 1 Å spacing
+const grid_points: Vec3[] = Array.from({ length: 64 }, (_, idx) => [
+  idx % 4,
+  Math.floor(idx / 4) % 4,
+  Math.floor(idx / 16),
+])
+const grid = make_crystal(
+  4,
+  grid_points.map((point) => [`Ar`, point.map((val) => val / 4) as Vec3]),
+)
+const in_set = (points: Vec3[]) => (structure: Crystal) =>
+  structure.sites.map(({ xyz }) =>
+    points.some((point) => point.every((val, dim) => Math.abs(val - xyz[dim]) < 1e-6)),
+  )
+const cube: Vec3[] = grid_points.filter((point) => point.every((val) => val <= 1))
+
+describe(`largest_solid_cluster`, () => {
+  test(`connected solid atoms including periodic images`, () => {
+    const lone: Vec3 = [3, 3, 2]
+    const with_lone = in_set([...cube, lone])
+    const result = largest_solid_cluster(grid, { classify: with_lone, cutoff: 1.1 })
+    expect(result.size).toBe(8)
+    expect(result.site_indices).toEqual([0, 1, 4, 5, 16, 17, 20, 21])
+    // x = 3 touches x = 0 across the cell boundary
+    const wrapped = in_set([...cube, [3, 0, 0]])
+    expect(largest_solid_cluster(grid, { classify: wrapped, cutoff: 1.1 }).size).toBe(9)
+    expect(largest_solid_cluster(grid, { classify: in_set([]), cutoff: 1.1 })).toEqual({
+      size: 0,
+      site_indices: [],
+    })
+  })
+
+  test(`cluster size series of a growing nucleus`, () => {
+    const trajectory: TrajectoryType = {
+      frames: [1, 4, 8].map((n_solid, step) => ({
+        structure: { ...grid, properties: { n_solid } },
+        step: 10 * step,
+        metadata: {},
+      })),
+    }
+    // the first n_solid atoms of the cube are crystalline
+    const classify = (structure: Crystal) =>
+      in_set(cube.slice(0, Number(structure.properties?.n_solid)))(structure)
+    const options = { classify, cutoff: 1.1, time_step: 0.5 }
+    const series = largest_cluster_series(trajectory, options)
+    expect(series).toEqual({ times: [0, 5, 10], sizes: [1, 4, 8] })
+  })
+})
+
+describe(`nucleation_mfpt`, () => {
+  test(`mean first passage times over trajectories`, () => {
+    const series: ClusterSeries[] = [
+      { times: [0, 1, 2, 3], sizes: [0, 2, 3, 5] },
+      { times: [10, 11, 12, 13], sizes: [1, 1, 4, 6] },
+    ]
+    const result = nucleation_mfpt(series)
+    expect(result.sizes).toEqual([1, 2, 3, 4, 5])
+    expect(result.mfpt).toEqual([0.5, 1.5, 2, 2.5, 3])
+    expect(result.rate).toBeNull()
+    expect(result.n_trajectories).toBe(2)
+    expect(() => nucleation_mfpt(series, { sizes: [1, 2, 7] })).toThrow(
+      `Not every trajectory reaches cluster size 7`,
+    )
+    expect(() => nucleation_mfpt([])).toThrow(`Need at least one cluster size series`)
+  })
+
+  test(`recovers rate, critical size and Zeldovich factor of the Wedekind form`, () => {
+    // erf by Simpson integration of 2/√π exp(−t²)
+    const erf = (x_val: number) => {
+      const n_steps = 400
+      const width = x_val / n_steps
+      let sum = 0
+      for (let idx = 0; idx <= n_steps; idx++) {
+        const weight = idx === 0 || idx === n_steps ? 1 : idx % 2 ? 4 : 2
+        sum += weight * Math.exp(-((idx * width) ** 2))
+      }
+      return ((width / 3) * sum * 2) / Math.sqrt(Math.PI)
+    }
+    const [tau_j, critical, c_val] = [100, 50, 0.1]
+    const tau = (size: number) => (tau_j / 2) * (1 + erf(c_val * (size - critical)))
+    // largest cluster grows such that it first reaches size n at time τ(n)
+    const times = Array.from({ length: 12_001 }, (_, idx) => idx / 100)
+    let size = 0
+    const sizes = times.map((time) => {
+      while (size < 120 && tau(size + 1) <= time) size++
+      return size
+    })
+    const result = nucleation_mfpt([{ times, sizes }], { volume: 1000 })
+    expect(result.sizes).toHaveLength(120)
+    expect(result.critical_size).toBeCloseTo(critical, 1)
+    expect(result.tau_j / tau_j).toBeCloseTo(1, 3)
+    expect(result.zeldovich).toBeCloseTo(c_val / Math.sqrt(Math.PI), 3)
+    expect((result.rate ?? 0) * tau_j * 1000).toBeCloseTo(1, 3)
+    expect(result.fitted[119]).toBeCloseTo(result.mfpt[119], 1)
+  })
+})
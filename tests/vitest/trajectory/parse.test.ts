@@ -3,13 +3,16 @@ import type { TrajectoryFrame } from '$lib/trajectory'
 import {
   get_unsupported_format_message,
   is_trajectory_file,
+  iter_lammps_frames,
+  iter_lammps_frames_async,
   parse_trajectory_data,
 } from '$lib/trajectory/parse'
 import { get_traj_parse_warnings } from '$lib/trajectory/parse/diagnostics'
+import { parse_lammps_trajectory } from '$lib/trajectory/parse/lammps'
 import { existsSync, readdirSync, statSync } from 'node:fs'
 import { join } from 'node:path'
 import process from 'node:process'
-import { describe, expect, it, test, vi } from 'vitest'
+import { assert, describe, expect, it, test, vi } from 'vitest'
 import { get_dummy_structure, read_binary_test_file, read_maybe_gz } from '../setup'
 
 const TRAJECTORY_DIR = `src/site/trajectories`
@@ -42,6 +45,10 @@ describe(`Trajectory File Detection`, () => {
     [`Xdatcar`, true],
     [`XDATCAR.out`, true],
     [`xdatcar.out`, true],
+    [`OUTCAR`, true],
+    [`relax/OUTCAR.gz`, true],
+    [`vasprun.xml`, true],
+    [`vasprun.relax2.xml.gz`, true],
 
     // xyz/extxyz files with trajectory keywords are detected by filename for auto-render
     [`relax-simulation.xyz`, true], // Has trajectory keyword "relax"
@@ -348,6 +355,167 @@ describe(`VASP XDATCAR Parser`, () => {
   })
 })
 
+describe(`VASP OUTCAR and vasprun.xml Parsers`, () => {
+  // two ionic steps of Si2 whose cell grows from 5.4 to 5.5 Å. Electronic steps print
+  // their own (unconverged) energies which must not leak into the previous frame.
+  const outcar_step = (a_len: number, stress: string, toten: number, sigma: number) => `
+ --------------------------------------- Iteration    1(   1)  ---------------------------
+  free energy    TOTEN  =       -99.00000000 eV
+  energy without entropy =      -99.00000000  energy(sigma->0) =      -99.00000000
+  FORCE on cell =-STRESS in cart. coord.  units (eV):
+  Direction    XX          YY          ZZ          XY          YZ          ZX
+  in kB ${stress}
+  external pressure =        1.00 kB  Pullay stress =        0.00 kB
+ VOLUME and BASIS-vectors are now :
+      direct lattice vectors                 reciprocal lattice vectors
+     ${a_len.toFixed(9)}  0.000000000  0.000000000     0.18  0.00  0.00
+     0.000000000  ${a_len.toFixed(9)}  0.000000000     0.00  0.18  0.00
+     0.000000000  0.000000000  ${a_len.toFixed(9)}     0.00  0.00  0.18
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.300000      0.000000     -0.400000
+      1.35000      1.35000      1.35000        -0.300000      0.000000      0.400000
+ -----------------------------------------------------------------------------------
+  FREE ENERGIE OF THE ION-ELECTRON SYSTEM (eV)
+  ---------------------------------------------------
+  free  energy   TOTEN  =       ${toten} eV
+
+  energy  without entropy=      ${sigma}  energy(sigma->0) =      ${sigma}
+  kinetic energy EKIN   =         0.069034  (temperature  267.00 K)
+  total energy   ETOTAL =       -10.77000000 eV
+`
+  const stresses = [
+    `     3.00000     3.00000     3.00000     0.00000     0.00000     1.50000`,
+    `    -1.00000    -2.00000    -3.00000     0.00000     0.00000     0.00000`,
+  ]
+  const outcar = ` vasp.6.3.2 27Jun22 (build Jan 01 2023) complex
+   TITEL  = PAW_PBE Si 05Jan2001
+   ions per type =               2
+${outcar_step(5.4, stresses[0], -10.8, -10.75)}
+${outcar_step(5.5, stresses[1], -10.9, -10.85)}
+ POSITION                                       TOTAL-FORCE (eV/Angst)
+ -----------------------------------------------------------------------------------
+      0.00000      0.00000      0.00000         0.100000`
+
+  it(`reads positions, forces, stresses and energies per OUTCAR ionic step`, async () => {
+    const trajectory = await parse_trajectory_data(outcar, `OUTCAR`)
+    expect(trajectory.metadata?.source_format).toBe(`vasp_outcar`)
+    expect(trajectory.metadata?.elements).toEqual([`Si`])
+    expect(trajectory.frames).toHaveLength(2) // truncated third step is dropped
+    const [first, second] = trajectory.frames
+    expect(first.step).toBe(1)
+    expect(first.metadata).toMatchObject({
+      free_energy: -10.8,
+      energy: -10.75,
+      pressure: 3,
+      stress_max: 3,
+      temperature: 267,
+      kinetic_energy: 0.069034,
+      total_energy: -10.77,
+    })
+    expect(first.metadata?.stress).toEqual([
+      [3, 0, 1.5],
+      [0, 3, 0],
+      [1.5, 0, 3],
+    ])
+    expect(first.metadata?.volume).toBeCloseTo(5.4 ** 3, 6)
+    expect(first.metadata?.force_max).toBeCloseTo(0.5, 12)
+    expect(first.structure.sites[1].properties.force).toEqual([-0.3, 0, 0.4])
+    expect(second.metadata).toMatchObject({ energy: -10.85, pressure: -2, stress_max: 3 })
+    if (!(`lattice` in second.structure)) throw new Error(`expected a periodic frame`)
+    expect(second.structure.lattice.a).toBeCloseTo(5.5, 9)
+    expect(second.structure.sites[1].abc[0]).toBeCloseTo(1.35 / 5.5, 9)
+  })
+
+  const vasprun_calc = (a_len: number, energy: number, scsteps = ``) => `
+ <calculation>
+  ${scsteps}
+  <structure>
+   <crystal>
+    <varray name="basis" >
+     <v>       ${a_len}       0.00000000       0.00000000 </v>
+     <v>       0.00000000       ${a_len}       0.00000000 </v>
+     <v>       0.00000000       0.00000000       ${a_len} </v>
+    </varray>
+    <i name="volume">    ${a_len ** 3} </i>
+   </crystal>
+   <varray name="positions" >
+    <v>       0.00000000       0.00000000       0.00000000 </v>
+    <v>       0.25000000       0.25000000       0.25000000 </v>
+   </varray>
+  </structure>
+  <varray name="forces" >
+   <v>       0.00000000       0.60000000       0.80000000 </v>
+   <v>       0.00000000      -0.60000000      -0.80000000 </v>
+  </varray>
+  <varray name="stress" >
+   <v>      -6.00000000       0.00000000       0.00000000 </v>
+   <v>       0.00000000      -6.00000000       0.00000000 </v>
+   <v>       0.00000000       0.00000000      -6.00000000 </v>
+  </varray>
+  <energy>
+   <i name="e_fr_energy">    ${energy - 0.01} </i>
+   <i name="e_wo_entrp">    ${energy} </i>
+   <i name="e_0_energy">    ${energy} </i>
+  </energy>
+ </calculation>`
+  const scstep = `<scstep><energy><i name="e_0_energy">  -99.0 </i></energy></scstep>`
+  const vasprun = `<?xml version="1.0" encoding="ISO-8859-1"?>
+<modeling>
+ <atominfo>
+  <atoms>       2 </atoms>
+  <array name="atoms" >
+   <dimension dim="1">ion</dimension>
+   <field type="string">element</field>
+   <field type="int">atomtype</field>
+   <set>
+    <rc><c>Ga</c><c>   1</c></rc>
+    <rc><c>As</c><c>   2</c></rc>
+   </set>
+  </array>
+ </atominfo>
+ ${vasprun_calc(5.6, -8.5, scstep)}
+ ${vasprun_calc(5.7, -8.6, scstep)}
+ <calculation>
+  <structure>`
+
+  it(`reads completed calculations of a vasprun.xml`, async () => {
+    const trajectory = await parse_trajectory_data(vasprun, `vasprun.xml`)
+    expect(trajectory.metadata?.source_format).toBe(`vasp_vasprun`)
+    expect(trajectory.metadata?.elements).toEqual([`Ga`, `As`])
+    expect(trajectory.metadata?.element_counts).toEqual([1, 1])
+    expect(trajectory.frames.map(({ step }) => step)).toEqual([1, 2])
+    const [first, second] = trajectory.frames
+    expect(first.metadata).toMatchObject({
+      energy: -8.5,
+      free_energy: -8.5 - 0.01,
+      pressure: -6,
+      stress_max: 6,
+    })
+    expect(first.metadata?.force_max).toBeCloseTo(1, 12)
+    expect(second.metadata?.energy).toBe(-8.6)
+    expect(second.metadata?.volume).toBeCloseTo(5.7 ** 3, 6)
+    expect(second.structure.sites[1].xyz).toEqual([1.425, 1.425, 1.425])
+    expect(second.structure.sites[0].properties.force).toEqual([0, 0.6, 0.8])
+  })
+
+  it.each([
+    [`OUTCAR`, `No ionic steps found in OUTCAR`, outcar.split(` POSITION`)[0]],
+    [`OUTCAR`, `Invalid element symbol in OUTCAR`, outcar.replace(`PAW_PBE Si`, `PAW_PBE Qq`)],
+    [`vasprun.xml`, `No ionic steps found in vasprun`, vasprun.split(` </calculation>`)[0]],
+  ])(`rejects %s without usable ionic steps: %s`, async (filename, message, content) => {
+    await expect(parse_trajectory_data(content, filename)).rejects.toThrow(message)
+  })
+
+  it.each([
+    [`OUTCAR`, outcar, `vasp_outcar`],
+    [`vasprun.xml`, vasprun, `vasp_vasprun`],
+  ])(`detects %s content without filename`, async (_label, content, expected) => {
+    const trajectory = await parse_trajectory_data(content, undefined)
+    expect(trajectory.metadata?.source_format).toBe(expected)
+  })
+})
+
 describe(`LAMMPS Trajectory Format`, () => {
   // Orthogonal 10x10x10 box frame(s) with one atom per entry of `types`
   const lammps_frames = (
@@ -562,6 +730,147 @@ ITEM: ATOMS id type x y z\n1 1 5.0 5.0 5.0`
       }
     })
   })
+
+  describe(`units`, () => {
+    // One-atom orthogonal frame with forces, optionally preceded by UNITS/TIME items
+    const forces_frame = (opts: { units?: string; time?: number; box?: number } = {}) => {
+      const { units, time, box = 10 } = opts
+      return [
+        ...(units ? [`ITEM: UNITS`, units] : []),
+        ...(time === undefined ? [] : [`ITEM: TIME`, `${time}`]),
+        `ITEM: TIMESTEP`,
+        `100`,
+        `ITEM: NUMBER OF ATOMS`,
+        `1`,
+        `ITEM: BOX BOUNDS pp pp pp`,
+        `0.0 ${box}`,
+        `0.0 ${box}`,
+        `0.0 ${box}`,
+        `ITEM: ATOMS id type x y z fx fy fz`,
+        `1 1 ${box / 2} 0 0 1.0 -2.0 0.5`,
+      ].join(`\n`)
+    }
+    const kcal_mol_in_ev = 4184 / 6.02214076e23 / 1.602176634e-19
+
+    test(`metal default: lengths and forces pass through, ps times become fs`, async () => {
+      const traj = await parse_trajectory_data(forces_frame({ time: 0.25 }), `test.lammpstrj`)
+      const [frame] = traj.frames
+      expect(traj.metadata?.units).toBe(`metal`)
+      expect(frame.structure.sites[0].xyz).toEqual([5, 0, 0])
+      expect(frame.structure.sites[0].properties?.force).toEqual([1, -2, 0.5])
+      expect(frame.metadata?.time).toBeCloseTo(250, 10)
+      expect(frame.metadata?.timestep).toBe(100)
+    })
+
+    test(`real units convert kcal/(mol·Å) forces to eV/Å and keep fs times`, async () => {
+      const traj = await parse_trajectory_data(
+        forces_frame({ time: 50 }),
+        `test.lammpstrj`,
+        undefined,
+        `real`,
+      )
+      const [frame] = traj.frames
+      const force = frame.structure.sites[0].properties?.force as number[]
+      ;[1, -2, 0.5].forEach((val, idx) => {
+        expect(force[idx]).toBeCloseTo(val * kcal_mol_in_ev, 10)
+      })
+      expect(frame.metadata?.time).toBeCloseTo(50, 10)
+      expect(traj.metadata?.units).toBe(`real`)
+    })
+
+    test(`ITEM: UNITS header overrides the default and converts SI lengths`, async () => {
+      const content = forces_frame({ units: `si`, box: 1e-9 })
+      const traj = await parse_trajectory_data(content, `test.lammpstrj`, undefined, `real`)
+      const { structure } = traj.frames[0]
+      expect(traj.metadata?.units).toBe(`si`)
+      if (!(`lattice` in structure)) throw new Error(`expected periodic frame`)
+      expect(structure.lattice.a).toBeCloseTo(10, 8)
+      expect(structure.sites[0].xyz[0]).toBeCloseTo(5, 8)
+      // 1 eV/Å = 1.602176634e-9 N
+      const force = structure.sites[0].properties?.force as number[]
+      ;[1, -2, 0.5].forEach((val, idx) => {
+        expect(force[idx] * 1.602176634e-9).toBeCloseTo(val, 10)
+      })
+    })
+
+    test(`unsupported ITEM: UNITS warns and keeps the fallback units`, async () => {
+      const traj = await parse_trajectory_data(forces_frame({ units: `lj` }), `test.lammpstrj`)
+      expect(traj.metadata?.units).toBe(`metal`)
+      expect(get_traj_parse_warnings()).toEqual([
+        expect.stringContaining(`Unsupported LAMMPS units "lj"`),
+      ])
+    })
+  })
+
+  describe(`lazy frame reading`, () => {
+    // Triclinic frames with scaled unwrapped positions, velocities and forces
+    const custom_frame = (step: number, units?: string) =>
+      [
+        ...(units ? [`ITEM: UNITS`, units] : []),
+        `ITEM: TIME`,
+        `${step / 1000}`,
+        `ITEM: TIMESTEP`,
+        `${step}`,
+        `ITEM: NUMBER OF ATOMS`,
+        `2`,
+        `ITEM: BOX BOUNDS xy xz yz pp pp pp`,
+        `0.0 12.0 2.0`,
+        `0.0 10.0 0.0`,
+        `0.0 10.0 0.0`,
+        `ITEM: ATOMS id type xsu ysu zsu vx vy vz fx fy fz`,
+        `1 1 ${1 + step / 1000} 0.5 0.5 1.0 0.0 -2.0 0.1 0.2 0.3`,
+        `2 2 0.25 0.0 0.0 0.0 3.0 0.0 0.0 0.0 0.0`,
+      ].join(`\n`)
+    const content = [custom_frame(0, `metal`), custom_frame(100), custom_frame(200)].join(`\n`)
+
+    test(`matches the eager parser for any chunking`, async () => {
+      const eager = parse_lammps_trajectory(content, `test.lammpstrj`)
+      const lazy = [...iter_lammps_frames(content)]
+      expect(lazy).toEqual(eager.frames)
+      // chunks split mid-line and mid-number
+      const chunks = content.match(/[\s\S]{1,7}/g) ?? []
+      expect([...iter_lammps_frames(chunks)]).toEqual(eager.frames)
+      const stream = (async function* () {
+        yield* chunks
+      })()
+      const streamed: TrajectoryFrame[] = []
+      for await (const frame of iter_lammps_frames_async(stream)) streamed.push(frame)
+      expect(streamed).toEqual(eager.frames)
+    })
+
+    test(`reads scaled unwrapped positions, velocities and forces`, () => {
+      const frames = iter_lammps_frames(content, { atom_type_mapping: { 1: `Li`, 2: `O` } })
+      const first = frames.next()
+      assert(!first.done)
+      const [li, oxygen] = first.value.structure.sites
+      expect(li.species[0].element).toBe(`Li`)
+      expect(oxygen.species[0].element).toBe(`O`)
+      // xsu 1 lies outside the box: a + b / 2 + c / 2 with b = (2, 10, 0)
+      ;[11, 5, 5].forEach((val, idx) => expect(li.xyz[idx]).toBeCloseTo(val, 10))
+      // metal velocities are Å/ps, stored in Å/fs
+      expect(li.properties?.velocity).toEqual([1e-3, 0, -2e-3])
+      expect(oxygen.properties?.velocity).toEqual([0, 3e-3, 0])
+      expect(li.properties?.force).toEqual([0.1, 0.2, 0.3])
+      expect(first.value.metadata?.time).toBe(0)
+      // frames are produced on demand
+      const second = frames.next()
+      assert(!second.done)
+      expect(second.value.step).toBe(100)
+      expect(second.value.metadata?.time).toBeCloseTo(100, 10)
+      expect(second.value.structure.sites[0].xyz[0]).toBeCloseTo(12, 10)
+    })
+
+    test(`ITEM: UNITS of the first frame applies to all later frames`, () => {
+      const real = [custom_frame(0, `real`), custom_frame(100)].join(`\n`)
+      const frames = [...iter_lammps_frames(real)]
+      expect(frames).toHaveLength(2)
+      for (const frame of frames) {
+        // real velocities are already Å/fs
+        expect(frame.structure.sites[1].properties?.velocity).toEqual([0, 3, 0])
+      }
+      expect([...iter_lammps_frames(``)]).toEqual([])
+    })
+  })
 })
 
 describe(`XYZ Trajectory Format`, () => {
@@ -844,6 +1153,74 @@ describe(`ASE Trajectory Format`, () => {
       `Unsupported binary format`,
     )
   })
+
+  it(`exposes calculator energy, forces and stress as frame properties`, async () => {
+    const trajectory = await parse_trajectory_data(
+      read_binary_test_file(`ase-LiMnO2-chgnet-relax.traj`),
+      `ase-LiMnO2-chgnet-relax.traj`,
+    )
+    const [first, second] = trajectory.frames
+    expect(first.metadata.calculator).toBe(`chgnetcalculator`)
+    expect(first.metadata.parameters).toBeUndefined()
+    expect(first.metadata.energy).toBeCloseTo(-58.97273254394531, 10)
+    expect(second.metadata.energy).toBeCloseTo(-58.59364700317383, 10)
+    expect(first.metadata.forces).toHaveLength(8)
+    expect(first.metadata.force_max).toBeCloseTo(0.025403, 6)
+    expect(first.structure.sites[0].properties.force).toEqual(
+      (first.metadata.forces as number[][])[0],
+    )
+    expect(first.metadata.stress).toHaveLength(3)
+    expect(first.metadata.pressure).toBeCloseTo(-0.0012979226, 9)
+    expect(first.metadata.magmoms).toHaveLength(8)
+    expect((first.metadata.magmoms as number[])[2]).toBeCloseTo(3.85729, 5)
+  })
+
+  it(`expands Voigt stresses from JSON-only calculator results`, async () => {
+    const frame_json = new TextEncoder().encode(
+      JSON.stringify({
+        numbers: [1, 1],
+        positions: [
+          [0, 0, 0],
+          [0.74, 0, 0],
+        ],
+        cell: [
+          [5, 0, 0],
+          [0, 5, 0],
+          [0, 0, 5],
+        ],
+        calculator: {
+          name: `emt`,
+          energy: -1.5,
+          forces: [
+            [0.3, 0, 0],
+            [-0.3, 0, 0],
+          ],
+          stress: [0.1, 0.2, 0.3, 0.04, 0.05, 0.06],
+        },
+      }),
+    )
+    const buffer = new ArrayBuffer(64 + frame_json.length)
+    const view = new DataView(buffer)
+    new Uint8Array(buffer).set(new TextEncoder().encode(`- of UlmASE-Trajectory  `))
+    view.setBigInt64(32, BigInt(1), true) // n_items
+    view.setBigInt64(40, BigInt(48), true) // offsets table
+    view.setBigInt64(48, BigInt(56), true) // frame 0
+    view.setBigInt64(56, BigInt(frame_json.length), true)
+    new Uint8Array(buffer, 64).set(frame_json)
+
+    const { metadata, structure } = (await parse_trajectory_data(buffer, `h2.traj`)).frames[0]
+    expect(metadata.calculator).toBe(`emt`)
+    expect(metadata.energy).toBe(-1.5)
+    expect(metadata.force_max).toBeCloseTo(0.3, 12)
+    expect(structure.sites[1].properties.force).toEqual([-0.3, 0, 0])
+    expect(metadata.stress).toEqual([
+      [0.1, 0.06, 0.05],
+      [0.06, 0.2, 0.04],
+      [0.05, 0.04, 0.3],
+    ])
+    expect(metadata.pressure).toBeCloseTo(-0.2, 12)
+    expect(metadata.stress_max).toBeCloseTo(0.3, 12)
+  })
 })
 
 describe(`JSON Formats`, () => {
@@ -0,0 +1,91 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import { Select, set_site_field } from '$lib/structure'
+import type { Site } from '$lib/structure'
+import { calc_profile, calc_trajectory_profile } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const lattice: Matrix3x3 = [
+  [4, 0, 0],
+  [0, 4, 0],
+  [0, 0, 10],
+]
+// Cu-rich lower half, Ni-rich upper half, one atom wrapped from z = 1.05
+const slab = make_crystal(lattice, [
+  [`Cu`, [0, 0, 0.05]],
+  [`Cu`, [0.5, 0.5, 0.15]],
+  [`Ni`, [0, 0.5, 0.2]],
+  [`Ni`, [0.5, 0, 0.7]],
+  [`Ni`, [0, 0, 0.8]],
+  [`Cu`, [0.5, 0.5, 1.05]],
+])
+
+describe(`calc_profile`, () => {
+  test(`number density and composition along c`, () => {
+    const is_ni = (site: Site) => (site.species[0].element === `Ni` ? 1 : 0)
+    const profile = calc_profile(slab, { n_bins: 2, value: is_ni })
+    expect(profile.positions).toEqual([2.5, 7.5])
+    expect(profile.counts).toEqual([4, 2])
+    // bins of 4 × 4 × 5 Å³
+    expect(profile.density[0]).toBeCloseTo(4 / 80, 12)
+    expect(profile.density[1]).toBeCloseTo(2 / 80, 12)
+    expect(profile.values).toEqual([0.25, 1])
+    expect(profile.n_frames).toBe(1)
+  })
+
+  test(`element selection, site fields and other axes`, () => {
+    const ni_only = calc_profile(slab, { n_bins: 5, elements: [`Ni`] })
+    expect(ni_only.counts).toEqual([0, 1, 0, 1, 1])
+    expect(ni_only.values).toBeNull()
+    // atom selections combine with the element filter: Cu at a = 0.5 (sites 1 and 5)
+    const centered = Select.frac_between(0, 0.25, 0.75)
+    expect(calc_profile(slab, { n_bins: 2, selection: centered }).counts).toEqual([2, 1])
+    const centered_cu = calc_profile(slab, {
+      n_bins: 2,
+      elements: [`Cu`],
+      selection: centered,
+    })
+    expect(centered_cu.counts).toEqual([2, 0])
+    expect(calc_profile(slab, { n_bins: 2, selection: [3, 4] }).counts).toEqual([0, 2])
+    const q6 = set_site_field(slab, `q6`, [0.5, 0.5, 0.3, 0.1, 0.1, 0.5])
+    const by_field = calc_profile(q6, { n_bins: 2, value: `q6` })
+    expect(by_field.values?.[0]).toBeCloseTo(0.45, 12)
+    expect(by_field.values?.[1]).toBeCloseTo(0.1, 12)
+    const along_a = calc_profile(slab, { axis: 0, n_bins: 4 })
+    expect(along_a.positions).toEqual([0.5, 1.5, 2.5, 3.5])
+    expect(along_a.counts).toEqual([3, 0, 3, 0])
+    // empty bins have no mean value
+    expect(calc_profile(slab, { n_bins: 4, value: () => 1 }).values?.[1]).toBeNaN()
+    expect(() => calc_profile(slab, { n_bins: 0 })).toThrow(
+      `n_bins must be a positive integer`,
+    )
+  })
+})
+
+describe(`calc_trajectory_profile`, () => {
+  // one atom hopping between the two halves of the cell
+  const frames = [0.2, 0.7, 0.2, 0.7].map((z_frac, step) => ({
+    structure: make_crystal(lattice, [[`Ar`, [0, 0, z_frac] as Vec3]]),
+    step,
+    metadata: { energy: step },
+  }))
+  const trajectory: TrajectoryType = { frames }
+
+  test(`frame-averaged counts and values`, () => {
+    const profile = calc_trajectory_profile(trajectory, {
+      n_bins: 2,
+      value: (_site, _idx, structure) => structure.sites[0].abc[2],
+    })
+    expect(profile.n_frames).toBe(4)
+    expect(profile.counts).toEqual([0.5, 0.5])
+    expect(profile.values?.[0]).toBeCloseTo(0.2, 12)
+    expect(profile.values?.[1]).toBeCloseTo(0.7, 12)
+    const every_other = { n_bins: 2, start_frame: 1, stride: 2 }
+    expect(calc_trajectory_profile(trajectory, every_other).counts).toEqual([0, 1])
+    expect(() => calc_trajectory_profile(trajectory, { stride: 0 })).toThrow(
+      `stride must be a positive integer`,
+    )
+    expect(() => calc_trajectory_profile({ frames: [] })).toThrow(`No frames left`)
+  })
+})
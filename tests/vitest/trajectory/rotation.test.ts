@@ -0,0 +1,98 @@
+import type { Vec3 } from '$lib/math'
+import * as math from '$lib/math'
+import { Select } from '$lib/structure'
+import { rotational_dynamics } from '$lib/trajectory'
+import type { TrajectoryType } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const [cell, delta] = [30, 0.3]
+const wrap = (xyz: Vec3): Vec3 =>
+  xyz.map((val, dim) => {
+    const frac = val / (dim === 2 ? 10 : cell)
+    return frac - Math.floor(frac)
+  }) as Vec3
+// 16 H2 rotors in the xy plane turn by ±0.3 rad per frame through all 2⁴ sequences, so
+// <cos Δθ> = cos(δ)ⁿ after n frames. The rotor at x = 0 straddles the cell boundary.
+const angle = (rotor: number, frame: number) =>
+  Array.from({ length: frame }, (_, step) => ((rotor >> step) & 1 ? delta : -delta)).reduce(
+    (sum, val) => sum + val,
+    0,
+  )
+const rotors: TrajectoryType = {
+  frames: [0, 1, 2, 3, 4].map((frame) => ({
+    structure: make_crystal(
+      [
+        [cell, 0, 0],
+        [0, cell, 0],
+        [0, 0, 10],
+      ],
+      [
+        ...Array.from({ length: 16 }, (_, rotor) => {
+          const center: Vec3 = [6 * (rotor % 4), 6 * Math.floor(rotor / 4) + 3, 5]
+          const theta = angle(rotor, frame)
+          const half: Vec3 = [0.37 * Math.cos(theta), 0.37 * Math.sin(theta), 0]
+          return [1, -1].map((sign) => ({
+            element: `H`,
+            abc: wrap(math.add(center, math.scale(half, sign))),
+          }))
+        }).flat(),
+        { element: `C`, abc: wrap([25, 27, 5]) },
+        { element: `O`, abc: wrap([25, 27, 6.13]) },
+      ],
+    ),
+    step: frame,
+  })),
+}
+
+describe(`rotational_dynamics`, () => {
+  test(`orientation autocorrelations and rotational diffusion per species`, () => {
+    const result = rotational_dynamics(rotors, { max_lag: 4, time_step: 2 })
+    expect(result.lag_times).toEqual([0, 2, 4, 6, 8])
+    expect(result.n_frames).toBe(5)
+    expect(Object.keys(result.species).toSorted()).toEqual([`CO`, `H2`])
+    const { H2, CO } = result.species
+    expect(H2.molecules).toHaveLength(16)
+    expect(H2.molecules[0]).toEqual([0, 1])
+    for (let lag = 0; lag <= 4; lag++) {
+      expect(H2.p1[lag]).toBeCloseTo(Math.cos(delta) ** lag, 10)
+      expect(H2.p2[lag]).toBeCloseTo(0.25 + 0.75 * Math.cos(2 * delta) ** lag, 10)
+      expect(CO.p1[lag]).toBeCloseTo(1, 10)
+    }
+    // −ln C_1 = 2 D_r τ exactly for this walk, 2 time units per frame
+    expect(H2.rotational_diffusion.p1).toBeCloseTo(-Math.log(Math.cos(delta)) / 4, 10)
+    expect(H2.rotational_diffusion_err.p1).toBeLessThan(1e-6)
+    expect(CO.rotational_diffusion.p2).toBeCloseTo(0, 10)
+    // trapezoidal integral of C_1 over the 8 time units
+    const p1_integral = H2.p1.slice(1).reduce((sum, val, idx) => sum + val + H2.p1[idx], 0)
+    expect(H2.correlation_times[0]).toBeCloseTo(p1_integral, 10)
+  })
+
+  test(`molecule selection and custom axes`, () => {
+    const only_co = rotational_dynamics(rotors, {
+      molecules: Select.element(`C`),
+      axis: [0, 1],
+      max_lag: 2,
+    })
+    expect(Object.keys(only_co.species)).toEqual([`CO`])
+    expect(only_co.species.CO.molecules).toEqual([[32, 33]])
+    // a fixed lab-frame axis never decorrelates
+    const fixed = rotational_dynamics(rotors, { axis: () => [0, 0, 1], max_lag: 2 })
+    expect(fixed.species.H2.p2).toEqual([1, 1, 1])
+    // the H–H bond vector follows the same rotation as the principal axis
+    const bond_axis = rotational_dynamics(rotors, { axis: [0, 1], max_lag: 4 })
+    expect(bond_axis.species.H2.p1[3]).toBeCloseTo(Math.cos(delta) ** 3, 10)
+  })
+
+  test(`rejects invalid options`, () => {
+    expect(() => rotational_dynamics(rotors, { stride: 0 })).toThrow(`stride must be`)
+    expect(() => rotational_dynamics(rotors, { start_frame: 4 })).toThrow(`at least 2 frames`)
+    expect(() => rotational_dynamics(rotors, { max_lag: 5 })).toThrow(`max_lag must be`)
+    expect(() => rotational_dynamics(rotors, { axis: [0, 2] })).toThrow(
+      `Axis atoms 0, 2 out of range for a 2-atom molecule`,
+    )
+    expect(() => rotational_dynamics(rotors, { axis: () => [0, 0, 0] })).toThrow(
+      `Degenerate axis`,
+    )
+  })
+})
@@ -0,0 +1,67 @@
+import type { TrajectoryType } from '$lib/trajectory'
+import { dynamic_structure_factor, intermediate_scattering } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// one Ar atom drifts +0.3 Å per frame along x through a 5 Å cube, the other one rests
+const [cell, speed] = [5, 0.3]
+const trajectory: TrajectoryType = {
+  frames: Array.from({ length: 20 }, (_, frame) => ({
+    structure: make_crystal(cell, [
+      [`Ar`, [((1 + speed * frame) / cell) % 1, 0.2, 0.2]],
+      [`Ar`, [0.6, 0.6, 0.6]],
+    ]),
+    step: frame,
+  })),
+}
+
+describe(`intermediate_scattering`, () => {
+  test(`self part follows the phase of the moving atom across wraps`, () => {
+    const q_min = (2 * Math.PI) / cell
+    const { lag_times, functions, n_frames } = intermediate_scattering(trajectory, [q_min], {
+      max_lag: 10,
+      time_step: 2,
+    })
+    expect(n_frames).toBe(20)
+    expect(lag_times).toEqual(Array.from({ length: 11 }, (_, lag) => 2 * lag))
+    const [{ q, n_vectors, coherent, incoherent }] = functions
+    expect(q).toBeCloseTo(q_min, 12)
+    expect(n_vectors).toBe(6)
+    // only the 2 of 6 q vectors along ±x see the motion of 1 of the 2 atoms
+    incoherent.forEach((val, lag) => {
+      const moving = (4 + 2 * Math.cos(q_min * speed * lag)) / 6
+      expect(val).toBeCloseTo((1 + moving) / 2, 12)
+    })
+    expect(coherent[0]).toBeGreaterThan(0)
+    expect(coherent).toHaveLength(11)
+  })
+
+  test(`rejects shells without reciprocal lattice vectors and molecules`, () => {
+    expect(() => intermediate_scattering(trajectory, [0.5])).toThrow(
+      `No reciprocal lattice vector`,
+    )
+    const molecule = { sites: trajectory.frames[0].structure.sites }
+    const frames = [0, 1].map((step) => ({ structure: molecule, step }))
+    expect(() => intermediate_scattering({ frames }, [1])).toThrow(`periodic cell`)
+  })
+})
+
+describe(`dynamic_structure_factor`, () => {
+  test(`exponential decay transforms to a Lorentzian`, () => {
+    const [tau, dt] = [2, 0.01]
+    const lag_times = Array.from({ length: 4000 }, (_, idx) => idx * dt)
+    const f_qt = lag_times.map((time) => Math.exp(-time / tau))
+    const { omega, s_qw } = dynamic_structure_factor(f_qt, lag_times, {
+      omega_max: 2,
+      n_omega: 5,
+      window: false,
+    })
+    expect(omega).toEqual([0, 0.5, 1, 1.5, 2])
+    omega.forEach((freq, idx) =>
+      expect(s_qw[idx]).toBeCloseTo(tau / (Math.PI * (1 + (freq * tau) ** 2)), 4),
+    )
+    const windowed = dynamic_structure_factor(f_qt, lag_times)
+    expect(windowed.omega.at(-1)).toBeCloseTo(Math.PI / dt, 10)
+    expect(() => dynamic_structure_factor([1], [0])).toThrow(`at least 2 lags`)
+  })
+})
@@ -0,0 +1,101 @@
+import type { Vec3 } from '$lib/math'
+import { Select } from '$lib/structure'
+import type { DistinctSite } from '$lib/symmetry'
+import type { ReferenceSite, TrajectoryType } from '$lib/trajectory'
+import { reference_sites_from_wyckoff, site_occupancy } from '$lib/trajectory'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+// one A site at the origin, two B sites 2 Å away in a 4 Å cubic cell
+const reference_sites: ReferenceSite[] = [
+  { abc: [0, 0, 0], type: `A` },
+  { abc: [0.5, 0, 0], type: `B` },
+  { abc: [0.5, 0.5, 0], type: `B` },
+]
+// Li 0 rattles on A, passes 1 Å from both A and B in frame 3, sits on B, then moves on
+// along +x into the next A image. Li 1 stays on the second B site, O is not tracked.
+const li_0_x = [0, 0.1, 0, 1, 2, 2.1, 2, 4, 3.9, 0]
+const trajectory: TrajectoryType = {
+  frames: li_0_x.map((x_pos, frame) => ({
+    structure: make_crystal(4, [
+      { element: `Li`, xyz: [x_pos, 0, 0] as Vec3 },
+      { element: `Li`, abc: [0.5, 0.5, 0] },
+      { element: `O`, abc: [0, 0, 0.5] },
+    ]),
+    step: frame,
+  })),
+}
+
+describe(`site_occupancy`, () => {
+  test(`occupancies, residence times and hop rates between site types`, () => {
+    const result = site_occupancy(trajectory, reference_sites, {
+      mobile: Select.element(`Li`),
+      cutoff: 0.6,
+    })
+    expect(result.mobile_indices).toEqual([0, 1])
+    expect(result.n_frames).toBe(10)
+    expect(result.assignments.map(([ion_0]) => ion_0)).toEqual([0, 0, 0, -1, 1, 1, 1, 0, 0, 0])
+    expect(result.assignments.every(([, ion_1]) => ion_1 === 2)).toBe(true)
+    result.occupancy.forEach((occ, idx) => expect(occ).toBeCloseTo([0.6, 0.3, 1][idx], 12))
+    expect(result.types).toEqual([`A`, `B`])
+    expect(result.type_occupancy.A).toBeCloseTo(0.6, 12)
+    expect(result.type_occupancy.B).toBeCloseTo(0.65, 12)
+    // transit frame 3 stays with A: A → B at t = 4, B → A at t = 7
+    expect(result.hop_counts).toEqual([
+      [0, 1],
+      [1, 0],
+    ])
+    expect(result.residence_times).toEqual({ A: [], B: [3] })
+    expect(result.mean_residence_time.A).toBeNaN()
+    expect(result.mean_residence_time.B).toBe(3)
+    // time on A: 4 + 2, on B: 3 (Li 0) + 9 (Li 1)
+    expect(result.hop_rates).toEqual([
+      [0, 1 / 6],
+      [1 / 12, 0],
+    ])
+  })
+
+  test(`default cutoff assigns every frame and time_step scales times`, () => {
+    const result = site_occupancy(trajectory, reference_sites, { mobile: [0], time_step: 2 })
+    // 1 Å is exactly half the A–B distance, nearest site wins ties by order
+    expect(result.assignments[3]).toEqual([0])
+    expect(result.times[9]).toBe(18)
+    expect(result.residence_times.B).toEqual([6])
+  })
+
+  test(`rejects invalid input`, () => {
+    expect(() => site_occupancy(trajectory, [])).toThrow(`at least one reference site`)
+    expect(() => site_occupancy(trajectory, reference_sites, { stride: 0 })).toThrow(
+      `stride must be`,
+    )
+    expect(() => site_occupancy(trajectory, reference_sites, { cutoff: 0 })).toThrow(
+      `cutoff must be positive`,
+    )
+    expect(() => site_occupancy(trajectory, reference_sites, { mobile: [3] })).toThrow(
+      `Atom index 3 out of range`,
+    )
+    const molecule = { frames: [{ structure: { sites: [] }, step: 0 }] }
+    expect(() => site_occupancy(molecule, reference_sites)).toThrow(`periodic structures`)
+  })
+})
+
+describe(`reference_sites_from_wyckoff`, () => {
+  test(`types sites by element and Wyckoff position`, () => {
+    const reference = make_crystal(4, [
+      { element: `Li`, abc: [0, 0, 0] },
+      { element: `Li`, abc: [0.5, 0, 0] },
+      { element: `Li`, abc: [0, 0.5, 0] },
+      { element: `Li`, abc: [0.25, 0, 0] },
+    ])
+    const distinct: DistinctSite[] = [
+      { site_idx: 0, site_indices: [0], multiplicity: 1, wyckoff: `1a`, elem: `Li` },
+      { site_idx: 1, site_indices: [1, 2], multiplicity: 2, wyckoff: `3c`, elem: `Li` },
+      { site_idx: 3, site_indices: [3], multiplicity: 1, wyckoff: `3c`, elem: `Li` },
+    ]
+    const sites = reference_sites_from_wyckoff(reference, distinct)
+    expect(sites.map(({ type }) => type)).toEqual([`Li 1a`, `Li 3c`, `Li 3c`, `Li 3c (2)`])
+    expect(sites[2].abc).toEqual([0, 0.5, 0])
+    const bad = [{ ...distinct[0], site_indices: [7] }]
+    expect(() => reference_sites_from_wyckoff(reference, bad)).toThrow(`Site index 7`)
+  })
+})
@@ -0,0 +1,134 @@
+import type { Matrix3x3, Vec3 } from '$lib/math'
+import { set_site_field } from '$lib/structure'
+import type { TrajectoryType } from '$lib/trajectory'
+import { transcode_trajectory, transcode_trajectory_to_string } from '$lib/trajectory'
+import { TrajFrameReader } from '$lib/trajectory/parse'
+import { parse_lammps_trajectory } from '$lib/trajectory/parse/lammps'
+import { parse_vasp_xdatcar } from '$lib/trajectory/parse/vasp'
+import { parse_xyz_trajectory } from '$lib/trajectory/parse/xyz'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const lattice: Matrix3x3 = [
+  [4, 0, 0],
+  [1, 4, 0],
+  [0.5, 0.5, 5],
+]
+// Li atom hopping along a, frame i at step 10 i with energy −i
+const trajectory: TrajectoryType = {
+  frames: [0, 1, 2, 3, 4].map((idx) => ({
+    structure: make_crystal(lattice, [
+      { element: `Li`, abc: [0.1 * idx, 0.25, 0.25] },
+      { element: `O`, abc: [0.5, 0.5, 0.5] },
+      { element: `O`, abc: [0, 0, 0.5] },
+    ]),
+    step: 10 * idx,
+    metadata: { energy: -idx },
+  })),
+}
+const abcs = (traj: TrajectoryType) =>
+  traj.frames.map(({ structure }) => structure.sites.map(({ abc }) => abc))
+
+describe(`transcode_trajectory`, () => {
+  test(`extXYZ keeps steps, energies, cell and positions`, async () => {
+    const text = await transcode_trajectory_to_string(trajectory, `extxyz`)
+    const parsed = parse_xyz_trajectory(text)
+    expect(parsed.frames.map(({ step }) => step)).toEqual([0, 10, 20, 30, 40])
+    expect(parsed.frames[3].metadata?.energy).toBe(-3)
+    const frame_3 = parsed.frames[3].structure
+    if (!(`lattice` in frame_3)) throw new Error(`Expected a periodic frame`)
+    expect(frame_3.lattice.matrix).toEqual(lattice)
+    frame_3.sites[0].xyz.forEach((coord, axis) =>
+      expect(coord).toBeCloseTo(trajectory.frames[3].structure.sites[0].xyz[axis], 5),
+    )
+  })
+
+  test(`LAMMPS dump of a triclinic cell round-trips fractional coordinates`, async () => {
+    const text = await transcode_trajectory_to_string(trajectory, `lammps-dump`, {
+      frame_range: [1, 5],
+      stride: 2,
+    })
+    expect(text.split(`\n`).slice(4, 9)).toEqual([
+      `ITEM: BOX BOUNDS xy xz yz pp pp pp`,
+      `0.00000000 5.50000000 1.00000000`, // x bounds widened by the tilts xy + xz
+      `0.00000000 4.50000000 0.50000000`,
+      `0 5.00000000 0.50000000`,
+      `ITEM: ATOMS id element xs ys zs`,
+    ])
+    const parsed = parse_lammps_trajectory(text)
+    expect(parsed.frames.map(({ step }) => step)).toEqual([10, 30])
+    expect(parsed.frames[1].structure.sites.map(({ species }) => species[0].element)).toEqual([
+      `Li`,
+      `O`,
+      `O`,
+    ])
+    abcs(parsed)[1].forEach((abc, site_idx) =>
+      abc.forEach((coord, axis) =>
+        expect(coord).toBeCloseTo(abcs(trajectory)[3][site_idx][axis], 7),
+      ),
+    )
+  })
+
+  test(`XDATCAR with an atom selection writes the header once`, async () => {
+    const text = await transcode_trajectory_to_string(trajectory, `xdatcar`, {
+      selection: [1, 0],
+    })
+    expect(text.match(/^O Li$/gm)).toHaveLength(1)
+    expect(text.match(/Direct configuration=/g)).toHaveLength(5)
+    const parsed = parse_vasp_xdatcar(text)
+    expect(parsed.frames.map(({ step }) => step)).toEqual([0, 10, 20, 30, 40])
+    const expected: Vec3[] = [
+      [0.5, 0.5, 0.5],
+      [0.2, 0.25, 0.25],
+    ]
+    abcs(parsed)[2].forEach((abc, site_idx) =>
+      abc.forEach((coord, axis) => expect(coord).toBeCloseTo(expected[site_idx][axis], 7)),
+    )
+  })
+
+  test(`extXYZ with an atom selection subsets site fields`, async () => {
+    const with_charges: TrajectoryType = {
+      frames: trajectory.frames.map((frame) => ({
+        ...frame,
+        structure: set_site_field(frame.structure, `charge`, [1, -0.5, -0.5]),
+      })),
+    }
+    const text = await transcode_trajectory_to_string(with_charges, `extxyz`, {
+      selection: [0, 2],
+    })
+    const [n_atoms, comment, ...atom_lines] = text.split(`\n`)
+    expect(n_atoms).toBe(`2`)
+    expect(comment).toContain(`Properties=species:S:1:pos:R:3:charge:R:1`)
+    expect(atom_lines[0]).toMatch(/^Li .* 1\.00000000$/)
+    expect(atom_lines[1]).toMatch(/^O .* -0\.50000000$/)
+  })
+
+  test(`lazily loaded trajectories stream frame by frame`, async () => {
+    const data = await transcode_trajectory_to_string(trajectory, `extxyz`)
+    const lazy: TrajectoryType = { frames: [], frame_loader: new TrajFrameReader(`md.xyz`) }
+    const chunks: string[] = []
+    for await (const chunk of transcode_trajectory(lazy, `xdatcar`, { data, stride: 2 })) {
+      chunks.push(chunk)
+    }
+    expect(chunks).toHaveLength(3)
+    expect(chunks[1]).toMatch(/^Direct configuration= 20\n/)
+    const parsed = parse_vasp_xdatcar(chunks.join(``))
+    expect(parsed.frames.map(({ step }) => step)).toEqual([0, 20, 40])
+  })
+
+  test(`rejects invalid options`, async () => {
+    const run = (options: Parameters<typeof transcode_trajectory>[2]) =>
+      transcode_trajectory_to_string(trajectory, `extxyz`, options)
+    await expect(run({ stride: 0 })).rejects.toThrow(`stride must be a positive integer`)
+    await expect(run({ frame_range: [3, 1] })).rejects.toThrow(`Invalid frame_range`)
+    await expect(run({ selection: [5] })).rejects.toThrow(`Atom index 5 out of range`)
+    const lazy: TrajectoryType = { frames: [], frame_loader: new TrajFrameReader(`md.xyz`) }
+    await expect(transcode_trajectory_to_string(lazy, `xdatcar`)).rejects.toThrow(
+      `need the raw file data`,
+    )
+    const molecule = { frames: [{ ...trajectory.frames[0], structure: { sites: [] } }] }
+    await expect(transcode_trajectory_to_string(molecule, `xdatcar`)).rejects.toThrow(
+      `xdatcar output needs periodic frames`,
+    )
+  })
+})
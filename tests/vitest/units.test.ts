@@ -0,0 +1,100 @@
+import type { PhysicalQuantity, UnitSystem } from '$lib/units'
+import {
+  convert_units,
+  is_unit_system,
+  unit_factor,
+  unit_label,
+  UNIT_SYSTEMS,
+} from '$lib/units'
+import { describe, expect, test } from 'vitest'
+
+const systems = Object.keys(UNIT_SYSTEMS) as UnitSystem[]
+const quantities = Object.keys(UNIT_SYSTEMS.ase) as PhysicalQuantity[]
+
+describe(`unit_factor`, () => {
+  test.each<[PhysicalQuantity, UnitSystem, UnitSystem, number]>([
+    [`time`, `metal`, `ase`, 1000],
+    [`time`, `real`, `ase`, 1],
+    [`velocity`, `metal`, `ase`, 1e-3],
+    [`energy`, `real`, `metal`, 0.0433641043],
+    [`force`, `real`, `ase`, 0.0433641043],
+    [`pressure`, `ase`, `metal`, 1602176.634],
+    [`pressure`, `real`, `metal`, 1.01325],
+    [`pressure`, `metal`, `si`, 1e5],
+    [`length`, `si`, `ase`, 1e10],
+    [`energy`, `ase`, `si`, 1.602176634e-19],
+    [`mass`, `metal`, `ase`, 1],
+    [`mass`, `ase`, `si`, 1.6605390666e-27],
+  ])(`%s: %s -> %s = %s`, (quantity, from, to, expected) => {
+    expect(unit_factor(quantity, from, to) / expected).toBeCloseTo(1, 8)
+  })
+
+  test.each(quantities)(`%s factors are consistent across all system pairs`, (quantity) => {
+    for (const from of systems) {
+      expect(unit_factor(quantity, from, from)).toBe(1)
+      for (const to of systems) {
+        const round_trip = unit_factor(quantity, from, to) * unit_factor(quantity, to, from)
+        expect(round_trip).toBeCloseTo(1, 12)
+        // converting via SI gives the same factor
+        const via_si = unit_factor(quantity, from, `si`) * unit_factor(quantity, `si`, to)
+        expect(unit_factor(quantity, from, to) / via_si).toBeCloseTo(1, 12)
+      }
+    }
+  })
+
+  test(`derived units are consistent within each system`, () => {
+    for (const system of systems) {
+      const units = UNIT_SYSTEMS[system]
+      const { length, energy, time } = units
+      expect(units.force.si / (energy.si / length.si)).toBeCloseTo(1, 10)
+      expect(units.velocity.si / (length.si / time.si)).toBeCloseTo(1, 10)
+    }
+    // eV/Å³ is the only energy/length³ pressure unit, the LAMMPS ones are bar/atm
+    const { energy, length, pressure } = UNIT_SYSTEMS.ase
+    expect(pressure.si / (energy.si / length.si ** 3)).toBeCloseTo(1, 10)
+  })
+
+  test(`throws on unknown unit systems`, () => {
+    expect(() => unit_factor(`energy`, `lj` as UnitSystem, `metal`)).toThrow(
+      `Unknown unit system: lj`,
+    )
+    expect(() => unit_factor(`energy`, `metal`, `cgs` as UnitSystem)).toThrow(
+      `Unknown unit system: cgs`,
+    )
+  })
+})
+
+describe(`convert_units`, () => {
+  test(`converts values and round-trips`, () => {
+    expect(convert_units(2, `time`, `metal`, `ase`)).toBeCloseTo(2000, 10)
+    expect(convert_units(1, `energy`, `real`, `ase`)).toBeCloseTo(0.0433641, 6)
+    const pressure_bar = 1234.5
+    const in_ase = convert_units(pressure_bar, `pressure`, `metal`, `ase`)
+    expect(convert_units(in_ase, `pressure`, `ase`, `metal`)).toBeCloseTo(pressure_bar, 8)
+  })
+})
+
+describe(`helpers`, () => {
+  test.each([
+    [`metal`, true],
+    [`real`, true],
+    [`ase`, true],
+    [`si`, true],
+    [`lj`, false],
+    [`toString`, false],
+    [42, false],
+    [undefined, false],
+  ])(`is_unit_system(%s) = %s`, (value, expected) => {
+    expect(is_unit_system(value)).toBe(expected)
+  })
+
+  test.each<[PhysicalQuantity, UnitSystem, string]>([
+    [`time`, `metal`, `ps`],
+    [`pressure`, `metal`, `bar`],
+    [`energy`, `real`, `kcal/mol`],
+    [`pressure`, `ase`, `eV/Å³`],
+    [`force`, `si`, `N`],
+  ])(`unit_label(%s, %s) = %s`, (quantity, system, expected) => {
+    expect(unit_label(quantity, system)).toBe(expected)
+  })
+})
@@ -0,0 +1,178 @@
+import type { Matrix3x3 } from '$lib/math'
+import {
+  compute_debye_scherrer_rings,
+  compute_xrd_pattern,
+  march_dollase,
+  scattering_direction,
+  simulate_detector_image,
+} from '$lib/xrd'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const tetragonal: Matrix3x3 = [
+  [3, 0, 0],
+  [0, 3, 0],
+  [0, 0, 5],
+]
+const tetragonal_structure = make_crystal(tetragonal, [[`Fe`, [0, 0, 0]]])
+const cu_fcc = make_crystal(3.615, [
+  [`Cu`, [0, 0, 0]],
+  [`Cu`, [0.5, 0.5, 0]],
+  [`Cu`, [0.5, 0, 0.5]],
+  [`Cu`, [0, 0.5, 0.5]],
+])
+
+describe(`march_dollase`, () => {
+  test.each([0.3, 0.7, 1.5, 3])(`is normalized over the unit sphere for r=%s`, (r_param) => {
+    const n_steps = 4000
+    let integral = 0
+    for (let idx = 0; idx < n_steps; idx++) {
+      const alpha = ((idx + 0.5) * Math.PI) / n_steps
+      integral += march_dollase(alpha, r_param) * Math.sin(alpha) * (Math.PI / n_steps)
+    }
+    expect(integral / 2).toBeCloseTo(1, 4)
+  })
+
+  test(`reduces to 1 for random powders and r^-3 along the fiber axis`, () => {
+    expect(march_dollase(0.8, 1)).toBeCloseTo(1, 12)
+    expect(march_dollase(0, 0.5)).toBeCloseTo(8, 12)
+    expect(march_dollase(Math.PI / 2, 0.5)).toBeCloseTo(0.5 ** 1.5, 12)
+  })
+
+  test.each([0, -1, NaN, Infinity])(`rejects invalid r=%s`, (r_param) => {
+    expect(() => march_dollase(0, r_param)).toThrow(`March-Dollase parameter`)
+  })
+})
+
+test(`scattering_direction is a unit vector tilted back by theta`, () => {
+  const q_hat = scattering_direction(60, 90)
+  expect(Math.hypot(...q_hat)).toBeCloseTo(1, 12)
+  expect(q_hat[0]).toBeCloseTo(0, 12)
+  expect(q_hat[1]).toBeCloseTo(Math.cos(Math.PI / 6), 12)
+  expect(q_hat[2]).toBeCloseTo(-0.5, 12)
+})
+
+describe(`compute_debye_scherrer_rings`, () => {
+  test(`untextured rings match the 1D powder pattern and are azimuthally uniform`, () => {
+    const pattern = compute_xrd_pattern(cu_fcc)
+    const { azimuths, rings } = compute_debye_scherrer_rings(cu_fcc, { n_azimuth: 36 })
+    expect(azimuths).toHaveLength(36)
+    expect(azimuths[0]).toBeCloseTo(5)
+    expect(rings.map((ring) => ring.two_theta)).toEqual(pattern.x)
+    rings.forEach((ring, idx) => {
+      expect(ring.intensity).toBeCloseTo(pattern.y[idx], 8)
+      expect(ring.azimuthal).toEqual(Array(36).fill(1))
+    })
+  })
+
+  test(`expands family representatives into all contributing reflections`, () => {
+    const { rings } = compute_debye_scherrer_rings(cu_fcc)
+    // fcc: {111} has 8 members, {200} has 6
+    expect(rings[0].hkls).toHaveLength(8)
+    expect(rings[1].hkls).toHaveLength(6)
+    expect(rings[1].hkls).toContainEqual([0, 0, -2])
+  })
+
+  test(`expands hexagonal rings beyond index permutations`, () => {
+    const hexagonal = make_crystal(
+      [
+        [3, 0, 0],
+        [-1.5, 1.5 * Math.sqrt(3), 0],
+        [0, 0, 5],
+      ],
+      [[`Mg`, [0, 0, 0]]],
+    )
+    // (001) ring first, then the 6-fold {100} ring including (1-10) and (-110)
+    const { rings } = compute_debye_scherrer_rings(hexagonal)
+    expect(rings[1].hkls).toHaveLength(6)
+    expect(rings[1].hkls).toContainEqual([1, -1, 0])
+    expect(rings[1].hkls).toContainEqual([-1, 1, 0])
+  })
+
+  test(`r=1 texture is identical to a random powder`, () => {
+    const { rings } = compute_debye_scherrer_rings(tetragonal_structure, {
+      texture: { preferred_hkl: [0, 0, 1], march_dollase_r: 1 },
+      n_azimuth: 12,
+    })
+    for (const ring of rings) {
+      for (const val of ring.azimuthal) expect(val).toBeCloseTo(1, 10)
+    }
+  })
+
+  test(`platelet texture brightens (00l) rings where q is parallel to the fiber`, () => {
+    const { azimuths, rings } = compute_debye_scherrer_rings(tetragonal_structure, {
+      texture: { preferred_hkl: [0, 0, 1], march_dollase_r: 0.5, fiber_axis: [0, 1, 0] },
+    })
+    expect(azimuths[89]).toBe(89.5)
+    // largest d-spacing comes first: the (001) ring with members (0 0 ±1) only
+    expect(rings[0].hkls).toHaveLength(2)
+    expect(rings[0].hkls.every(([h_idx, k_idx]) => h_idx === 0 && k_idx === 0)).toBe(true)
+    const { azimuthal } = rings[0]
+    // vertical (q nearly ∥ fiber) much brighter than horizontal (q ⊥ fiber)
+    expect(azimuthal[89]).toBeGreaterThan(5 * azimuthal[0])
+    // mirror symmetric about the vertical fiber axis
+    expect(azimuthal[89]).toBeCloseTo(azimuthal[90], 10)
+    expect(azimuthal[10]).toBeCloseTo(azimuthal[169], 10)
+  })
+
+  test.each([0, 2.5, -3])(`rejects invalid n_azimuth=%s`, (n_azimuth) => {
+    expect(() => compute_debye_scherrer_rings(cu_fcc, { n_azimuth })).toThrow(
+      `n_azimuth must be a positive integer`,
+    )
+  })
+})
+
+describe(`simulate_detector_image`, () => {
+  const geometry = { distance: 30, pixel_size: 1, shape: [81, 81] as [number, number] }
+
+  test(`returns a non-negative row-major image with a ring at the Bragg radius`, () => {
+    const image = simulate_detector_image(cu_fcc, geometry)
+    expect(image.width).toBe(81)
+    expect(image.height).toBe(81)
+    expect(image.data).toHaveLength(81 * 81)
+    expect(image.data.every((val) => Number.isFinite(val) && val >= 0)).toBe(true)
+
+    // Scan right from the beam center along the center row: brightest pixel sits on
+    // the strongest ring, i.e. the (111) ring at r = D tan(2θ)
+    const row = 40
+    const profile = Array.from({ length: 41 }, (_, dx) => image.data[row * 81 + 40 + dx])
+    const brightest = profile.indexOf(Math.max(...profile))
+    const two_theta_111 = compute_xrd_pattern(cu_fcc).x[0]
+    const expected_radius = 30 * Math.tan((two_theta_111 * Math.PI) / 180)
+    expect(Math.abs(brightest - expected_radius)).toBeLessThanOrEqual(1)
+  })
+
+  test(`untextured image is mirror symmetric about the beam center`, () => {
+    const image = simulate_detector_image(cu_fcc, geometry)
+    for (const [col, row] of [
+      [10, 40],
+      [55, 12],
+      [70, 70],
+    ]) {
+      const val = image.data[row * 81 + col]
+      expect(image.data[row * 81 + (80 - col)]).toBeCloseTo(val, 3)
+      expect(image.data[(80 - row) * 81 + col]).toBeCloseTo(val, 3)
+    }
+  })
+
+  test(`fiber texture breaks the azimuthal symmetry of the (001) ring`, () => {
+    const wide = { distance: 20, pixel_size: 0.2, shape: [101, 101] as [number, number] }
+    const image = simulate_detector_image(tetragonal_structure, wide, {
+      texture: { preferred_hkl: [0, 0, 1], march_dollase_r: 0.4 },
+    })
+    const two_theta_001 = compute_debye_scherrer_rings(tetragonal_structure).rings[0].two_theta
+    const radius_px = Math.round((20 * Math.tan((two_theta_001 * Math.PI) / 180)) / 0.2)
+    const top = image.data[(50 - radius_px) * 101 + 50]
+    const side = image.data[50 * 101 + 50 + radius_px]
+    expect(top).toBeGreaterThan(3 * side)
+  })
+
+  test.each([
+    [{ distance: 0, pixel_size: 1, shape: [10, 10] }, `distance and pixel_size must be > 0`],
+    [{ distance: 10, pixel_size: -1, shape: [10, 10] }, `distance and pixel_size must be > 0`],
+    [{ distance: 10, pixel_size: 1, shape: [0, 10] }, `shape must be positive integers`],
+    [{ distance: 10, pixel_size: 1, shape: [10.5, 10] }, `shape must be positive integers`],
+  ])(`rejects invalid geometry %o`, (bad_geometry, msg) => {
+    expect(() => simulate_detector_image(cu_fcc, bad_geometry as typeof geometry)).toThrow(msg)
+  })
+})
@@ -0,0 +1,105 @@
+import type { Matrix3x3 } from '$lib/math'
+import type { Crystal } from '$lib/structure'
+import {
+  compute_broadened_pattern,
+  compute_xrd_pattern,
+  DEFAULT_BROADENING,
+  infer_lattice_system,
+  refine_lattice_parameters,
+} from '$lib/xrd'
+import { describe, expect, test } from 'vitest'
+import { make_crystal, make_fcc } from '../setup'
+
+const tetragonal = (a_len: number, c_len: number): Matrix3x3 => [
+  [a_len, 0, 0],
+  [0, a_len, 0],
+  [0, 0, c_len],
+]
+
+// Synthetic measurement: broadened pattern of the true structure shifted by zero_shift,
+// scaled to 1000 counts on top of a flat background
+function synthetic_pattern(truth: Crystal, zero_shift: number, background = 10) {
+  const sticks = compute_xrd_pattern(truth, { two_theta_range: [20, 100] })
+  const shifted = { ...sticks, x: sticks.x.map((angle) => angle + zero_shift) }
+  const profile = compute_broadened_pattern(shifted, DEFAULT_BROADENING, [25, 95], 0.02)
+  const max_y = Math.max(...profile.y)
+  return { x: profile.x, y: profile.y.map((val) => (1000 * val) / max_y + background) }
+}
+
+describe(`infer_lattice_system`, () => {
+  test.each([
+    [{ a: 4, b: 4, c: 4, alpha: 90, beta: 90, gamma: 90 }, `cubic`],
+    [{ a: 4, b: 4, c: 6, alpha: 90, beta: 90, gamma: 90 }, `tetragonal`],
+    [{ a: 3, b: 4, c: 5, alpha: 90, beta: 90, gamma: 90 }, `orthorhombic`],
+    [{ a: 3, b: 3, c: 5, alpha: 90, beta: 90, gamma: 120 }, `hexagonal`],
+    [{ a: 5, b: 5, c: 5, alpha: 60, beta: 60, gamma: 60 }, `rhombohedral`],
+    [{ a: 3, b: 4, c: 5, alpha: 90, beta: 105, gamma: 90 }, `monoclinic`],
+    [{ a: 3, b: 4, c: 5, alpha: 80, beta: 105, gamma: 95 }, `triclinic`],
+  ] as const)(`%o -> %s`, (params, expected) => {
+    expect(infer_lattice_system(params)).toBe(expected)
+  })
+})
+
+describe(`refine_lattice_parameters`, () => {
+  test(`recovers cubic lattice parameter and zero shift from a synthetic pattern`, () => {
+    const truth = make_fcc(3.62, `Cu`)
+    const start = make_fcc(3.615, `Cu`)
+    const observed = synthetic_pattern(truth, 0.03)
+
+    const result = refine_lattice_parameters(start, observed)
+    expect(result.converged).toBe(true)
+    expect(result.params.a).toBeCloseTo(3.62, 3)
+    expect(result.params.b).toBe(result.params.a)
+    expect(result.params.c).toBe(result.params.a)
+    expect(result.params.zero_shift).toBeCloseTo(0.03, 2)
+    expect(result.background).toBeCloseTo(10, 0)
+    expect(result.r_wp).toBeLessThan(0.02)
+    expect(result.structure.lattice.a).toBeCloseTo(result.params.a, 10)
+    // fractional coords preserved, Cartesian coords follow the refined cell
+    expect(result.structure.sites[1].abc).toEqual([0.5, 0.5, 0])
+    expect(result.structure.sites[1].xyz[0]).toBeCloseTo(result.params.a / 2, 10)
+
+    const { a: sigma_a, b: sigma_b, zero_shift: sigma_zero } = result.uncertainties
+    expect(sigma_a).toBeGreaterThan(0)
+    expect(sigma_a).toBeLessThan(1e-3)
+    expect(sigma_b).toBe(sigma_a) // b is constrained to a in a cubic cell
+    expect(sigma_zero).toBeGreaterThan(0)
+    expect(result.calculated.x).toEqual(observed.x)
+    expect(result.calculated.y).toHaveLength(observed.y.length)
+  })
+
+  test(`refines a and c independently for tetragonal cells`, () => {
+    const truth = make_crystal(tetragonal(3.01, 4.98), [[`Fe`, [0, 0, 0]]])
+    const start = make_crystal(tetragonal(3, 5), [[`Fe`, [0, 0, 0]]])
+    const result = refine_lattice_parameters(start, synthetic_pattern(truth, 0), {
+      refine_zero_shift: false,
+    })
+    expect(result.params.a).toBeCloseTo(3.01, 3)
+    expect(result.params.c).toBeCloseTo(4.98, 3)
+    expect(result.params.zero_shift).toBe(0)
+    expect(result.uncertainties.zero_shift).toBeUndefined()
+    expect(result.uncertainties.c).toBeGreaterThan(0)
+  })
+
+  test(`explicit lattice_system overrides the inferred one`, () => {
+    // treat a metrically cubic start as orthorhombic: a, b, c refine separately
+    const start = make_fcc(3.615, `Cu`)
+    const truth = make_fcc(3.62, `Cu`)
+    const result = refine_lattice_parameters(start, synthetic_pattern(truth, 0), {
+      lattice_system: `orthorhombic`,
+      refine_zero_shift: false,
+    })
+    expect(Object.keys(result.uncertainties).toSorted()).toEqual([`a`, `b`, `c`])
+    for (const key of [`a`, `b`, `c`] as const) {
+      expect(result.params[key]).toBeCloseTo(3.62, 3)
+    }
+  })
+
+  test.each([
+    [{ x: [1, 2, 3], y: [1, 2] }, `x and y must have equal length`],
+    [{ x: [20, 30, 40], y: [1, 2, 3] }, `Need more than 4 observed points`],
+  ])(`rejects invalid observed data %#`, (observed, msg) => {
+    const start = make_fcc(3.6, `Cu`)
+    expect(() => refine_lattice_parameters(start, observed)).toThrow(msg)
+  })
+})
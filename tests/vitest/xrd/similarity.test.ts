@@ -0,0 +1,52 @@
+import {
+  fingerprint_similarity,
+  xrd_fingerprint,
+  xrd_pattern_similarity,
+  xrd_similarity_matrix,
+  xrd_structure_similarity,
+} from '$lib/xrd'
+import { describe, expect, test } from 'vitest'
+import { make_crystal } from '../setup'
+
+const simple_cubic = (a_len: number) => make_crystal(a_len, [[`Cu`, [0, 0, 0]]])
+
+describe(`xrd_pattern_similarity`, () => {
+  const peak = { x: [30], y: [100] }
+  const shifted = { x: [30.8], y: [100] }
+
+  test(`identical patterns are fully similar regardless of scale`, () => {
+    expect(xrd_pattern_similarity(peak, { x: [30], y: [3] })).toBeCloseTo(1, 10)
+  })
+
+  test(`shift tolerance grows with shift_width`, () => {
+    const strict = xrd_pattern_similarity(peak, shifted, { shift_width: 0 })
+    const tolerant = xrd_pattern_similarity(peak, shifted, { shift_width: 2 })
+    expect(strict).toBeGreaterThan(0)
+    expect(tolerant).toBeGreaterThan(strict)
+    expect(tolerant).toBeLessThan(1)
+  })
+
+  test(`empty patterns and mismatched grids`, () => {
+    expect(xrd_pattern_similarity(peak, { x: [], y: [] })).toBe(0)
+    const print = xrd_fingerprint(peak)
+    expect(print).toHaveLength(1600)
+    expect(() => fingerprint_similarity(print, print.slice(1))).toThrow(`differ in length`)
+  })
+})
+
+describe(`structure similarity`, () => {
+  test(`strained copies stay similar, different lattices don't`, () => {
+    const base = simple_cubic(3)
+    const strained = simple_cubic(3.03)
+    const bcc = make_crystal(3.7, [
+      [`Cu`, [0, 0, 0]],
+      [`Cu`, [0.5, 0.5, 0.5]],
+    ])
+    const matrix = xrd_similarity_matrix([base, strained, bcc])
+    expect(matrix.map((row, idx) => row[idx])).toEqual([1, 1, 1])
+    expect(matrix[0][1]).toBe(matrix[1][0])
+    expect(matrix[0][1]).toBeGreaterThan(matrix[0][2])
+    const strict = xrd_structure_similarity(base, strained, { shift_width: 0 })
+    expect(matrix[0][1]).toBeGreaterThan(strict)
+  })
+})